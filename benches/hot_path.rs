@@ -0,0 +1,122 @@
+//! Latency of each step between a Create event arriving and a signed snipe buy, against
+//! the budgets in the README's Benchmarks section. Compare a change against the last
+//! baseline with:
+//!
+//! ```bash
+//! cargo bench --bench hot_path -- --save-baseline main   # on main
+//! cargo bench --bench hot_path -- --baseline main        # on the branch
+//! ```
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use solana_sdk::{
+    hash::Hash, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signature::Keypair, signer::Signer,
+};
+use std::time::Duration;
+use trading_bot_rust::events::{self, CreateEvent, PumpEvent, TradeEvent, EVENT_IX_TAG};
+use trading_bot_rust::{alt, cal, pump_buy, snipe::BuyTemplate};
+
+fn create_event() -> CreateEvent {
+    let curve = cal::new_bonding_curve(&cal::Global::default());
+    CreateEvent {
+        name: "Bench Token".to_string(),
+        symbol: "BENCH".to_string(),
+        uri: "https://example.com/metadata.json".to_string(),
+        mint: Pubkey::new_unique(),
+        bonding_curve: Pubkey::new_unique(),
+        user: Pubkey::new_unique(),
+        creator: Pubkey::new_unique(),
+        timestamp: 1_700_000_000,
+        virtual_token_reserves: curve.virtual_token_reserves,
+        virtual_sol_reserves: curve.virtual_sol_reserves,
+        real_token_reserves: curve.real_token_reserves,
+        token_total_supply: curve.token_total_supply,
+    }
+}
+
+fn quotes(c: &mut Criterion) {
+    let global = cal::Global::default();
+    let curve = cal::new_bonding_curve(&global);
+    let mut group = c.benchmark_group("quote");
+    group.bench_function("buy", |b| {
+        b.iter(|| cal::get_tokens_for_sol(&global, Some(&curve), black_box(LAMPORTS_PER_SOL)))
+    });
+    group.bench_function("sell", |b| {
+        b.iter(|| cal::get_sol_from_tokens(&global, Some(&curve), black_box(1_000_000_000_000)))
+    });
+    group.bench_function("price_impact", |b| {
+        b.iter(|| cal::price_impact_bps(&global, &curve, black_box(LAMPORTS_PER_SOL)))
+    });
+    group.finish();
+}
+
+fn instructions(c: &mut Criterion) {
+    let user = Pubkey::new_unique();
+    let event = create_event();
+    let template = BuyTemplate::new(&user, &spl_token::ID);
+    let mut group = c.benchmark_group("instructions");
+    group.bench_function("buy", |b| {
+        b.iter(|| {
+            pump_buy::buy_instruction(
+                &user,
+                black_box(&event.mint),
+                &event.creator,
+                &spl_token::ID,
+                1_000_000,
+                LAMPORTS_PER_SOL,
+            )
+        })
+    });
+    group.bench_function("patch_template", |b| {
+        b.iter(|| {
+            template.patch(
+                black_box(&event.mint),
+                &event.bonding_curve,
+                &event.creator,
+                1_000_000,
+                LAMPORTS_PER_SOL,
+            )
+        })
+    });
+    group.finish();
+}
+
+fn signing(c: &mut Criterion) {
+    let user = Keypair::new();
+    let event = create_event();
+    let instructions = BuyTemplate::new(&user.pubkey(), &spl_token::ID).patch(
+        &event.mint,
+        &event.bonding_curve,
+        &event.creator,
+        1_000_000,
+        LAMPORTS_PER_SOL,
+    );
+    c.bench_function("sign_buy", |b| {
+        b.iter(|| {
+            alt::build_versioned_transaction(black_box(&instructions), &user, &[], Hash::default())
+                .unwrap()
+        })
+    });
+}
+
+fn decoding(c: &mut Criterion) {
+    let trade = events::encode_event(&PumpEvent::Trade(TradeEvent::default())).unwrap();
+    let create = events::encode_event(&PumpEvent::Create(create_event())).unwrap();
+    let log = format!("Program data: {}", BASE64.encode(&trade));
+    let cpi = [&EVENT_IX_TAG[..], &create].concat();
+    let mut group = c.benchmark_group("decode");
+    group.bench_function("trade_log", |b| {
+        b.iter(|| events::decode_log(black_box(&log)))
+    });
+    group.bench_function("create_cpi", |b| {
+        b.iter(|| events::decode_cpi(black_box(&cpi)))
+    });
+    group.finish();
+}
+
+criterion_group! {
+    name = hot_path;
+    config = Criterion::default().measurement_time(Duration::from_secs(3));
+    targets = quotes, instructions, signing, decoding
+}
+criterion_main!(hot_path);
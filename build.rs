@@ -0,0 +1,306 @@
+//! Generates the pump.fun instruction builders in `src/pump_idl.rs` from the Anchor IDL
+//! committed as `idl.json`, so a program upgrade only needs the new IDL dropped in.
+
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::path::Path;
+
+const IDL_PATH: &str = "idl.json";
+/// Accounts the bot marks differently from the IDL, as `(instruction, account, writable,
+/// signer)`: the creator signs its own fee claim, and the extend payer's balance changes
+const ACCOUNT_OVERRIDES: &[(&str, &str, bool, bool)] = &[
+    ("collect_creator_fee", "creator", true, true),
+    ("extend_account", "user", true, true),
+];
+/// IDL types sent as another Rust type: `OptionBool` goes on the wire as a borsh
+/// `Option<bool>`, tag byte included
+const TYPE_OVERRIDES: &[(&str, &str)] = &[("OptionBool", "Option<bool>")];
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", IDL_PATH);
+    let idl: Value = serde_json::from_str(
+        &std::fs::read_to_string(IDL_PATH).expect("Failed to read the pump.fun IDL"),
+    )
+    .expect("Failed to parse the pump.fun IDL");
+
+    let out_dir = std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+    std::fs::write(Path::new(&out_dir).join("pump_idl.rs"), generate(&idl))
+        .expect("Failed to write the generated instruction builders");
+}
+
+/// `extend_account` -> `ExtendAccount`
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or_else(String::new, |first| {
+                first.to_uppercase().chain(chars).collect()
+            })
+        })
+        .collect()
+}
+
+/// `BondingCurve` -> `BONDING_CURVE`
+fn upper_snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.extend(c.to_uppercase());
+    }
+    snake
+}
+
+/// The first paragraph of an IDL `docs` array as doc comment lines
+fn docs(item: &Value, fallback: String) -> String {
+    let lines: Vec<&str> = item["docs"]
+        .as_array()
+        .map(|docs| {
+            docs.iter()
+                .filter_map(Value::as_str)
+                .take_while(|line| !line.trim().is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    if lines.is_empty() {
+        return format!("/// {}\n", fallback);
+    }
+    lines
+        .iter()
+        .map(|line| format!("/// {}\n", line.trim()))
+        .collect()
+}
+
+/// The Rust type of an IDL type, noting defined types that need generating
+fn rust_type(ty: &Value, defined: &mut BTreeSet<String>) -> String {
+    if let Some(name) = ty.as_str() {
+        return match name {
+            "bool" | "u8" | "u16" | "u32" | "u64" | "u128" | "i8" | "i16" | "i32" | "i64"
+            | "i128" => name.to_string(),
+            "string" => "String".to_string(),
+            "pubkey" => "Pubkey".to_string(),
+            "bytes" => "Vec<u8>".to_string(),
+            other => panic!("Unsupported IDL type {}", other),
+        };
+    }
+    if let Some(inner) = ty.get("option") {
+        return format!("Option<{}>", rust_type(inner, defined));
+    }
+    if let Some(inner) = ty.get("vec") {
+        return format!("Vec<{}>", rust_type(inner, defined));
+    }
+    if let Some(array) = ty.get("array") {
+        return format!("[{}; {}]", rust_type(&array[0], defined), array[1]);
+    }
+    if let Some(name) = ty["defined"]["name"].as_str() {
+        if let Some((_, rust)) = TYPE_OVERRIDES.iter().find(|(idl, _)| *idl == name) {
+            return rust.to_string();
+        }
+        defined.insert(name.to_string());
+        return name.to_string();
+    }
+    panic!("Unsupported IDL type {}", ty)
+}
+
+/// A struct from the IDL `types`, with its own defined fields queued in `defined`
+fn defined_type(idl: &Value, name: &str, defined: &mut BTreeSet<String>) -> String {
+    let ty = idl["types"]
+        .as_array()
+        .and_then(|types| types.iter().find(|t| t["name"] == name))
+        .unwrap_or_else(|| panic!("IDL type {} is not defined", name));
+    if ty["type"]["kind"] != "struct" {
+        panic!("Only struct IDL types are supported, {} is not one", name);
+    }
+    let fields = ty["type"]["fields"].as_array().cloned().unwrap_or_default();
+    let mut code = docs(ty, format!("`{}` from the IDL", name));
+    code += "#[derive(Debug, Clone, PartialEq, borsh::BorshSerialize)]\n";
+    if fields.iter().all(|field| field.get("name").is_none()) {
+        let types: Vec<String> = fields
+            .iter()
+            .map(|field| format!("pub {}", rust_type(field, defined)))
+            .collect();
+        let _ = writeln!(code, "pub struct {}({});\n", name, types.join(", "));
+    } else {
+        let _ = writeln!(code, "pub struct {} {{", name);
+        for field in &fields {
+            let _ = writeln!(
+                code,
+                "    pub {}: {},",
+                field["name"].as_str().expect("IDL field name"),
+                rust_type(&field["type"], defined)
+            );
+        }
+        code += "}\n\n";
+    }
+    code
+}
+
+/// Borsh size of an IDL type, None when it varies
+fn fixed_size(idl: &Value, ty: &Value) -> Option<usize> {
+    if let Some(name) = ty.as_str() {
+        return match name {
+            "bool" | "u8" | "i8" => Some(1),
+            "u16" | "i16" => Some(2),
+            "u32" | "i32" => Some(4),
+            "u64" | "i64" => Some(8),
+            "u128" | "i128" => Some(16),
+            "pubkey" => Some(32),
+            _ => None,
+        };
+    }
+    if let Some(array) = ty.get("array") {
+        return Some(fixed_size(idl, &array[0])? * array[1].as_u64()? as usize);
+    }
+    let name = ty["defined"]["name"].as_str()?;
+    let fields = idl["types"]
+        .as_array()?
+        .iter()
+        .find(|t| t["name"] == name)?["type"]["fields"]
+        .as_array()?;
+    fields
+        .iter()
+        .map(|field| fixed_size(idl, field.get("type").unwrap_or(field)))
+        .sum()
+}
+
+/// Data length of each fixed-size program account: discriminator and fields
+fn account_lengths(idl: &Value) -> String {
+    let mut code = String::new();
+    for account in idl["accounts"].as_array().cloned().unwrap_or_default() {
+        let name = account["name"].as_str().expect("IDL account name");
+        if let Some(size) = fixed_size(idl, &serde_json::json!({ "defined": { "name": name } })) {
+            let _ = writeln!(
+                code,
+                "/// Bytes of `{}` data the program reads: discriminator and fields\n\
+                 pub const {}_LEN: usize = {};\n",
+                name,
+                upper_snake_case(name),
+                8 + size
+            );
+        }
+    }
+    code
+}
+
+/// Discriminator, accounts struct, args struct and builder for one instruction
+fn instruction(ix: &Value, defined: &mut BTreeSet<String>) -> String {
+    let name = ix["name"].as_str().expect("IDL instruction name");
+    let pascal = pascal_case(name);
+    let discriminator: Vec<String> = ix["discriminator"]
+        .as_array()
+        .expect("IDL instruction discriminator")
+        .iter()
+        .map(Value::to_string)
+        .collect();
+    let accounts = ix["accounts"].as_array().cloned().unwrap_or_default();
+    let args = ix["args"].as_array().cloned().unwrap_or_default();
+
+    let mut code = format!(
+        "/// `{}` instruction discriminator\npub const {}_DISCRIMINATOR: [u8; 8] = [{}];\n\n",
+        name,
+        name.to_uppercase(),
+        discriminator.join(", ")
+    );
+
+    let _ = writeln!(
+        code,
+        "/// Accounts of the `{}` instruction\n\
+         #[derive(Debug, Clone, Copy, PartialEq, Eq)]\npub struct {}Accounts {{",
+        name, pascal
+    );
+    for account in &accounts {
+        let _ = writeln!(
+            code,
+            "    pub {}: Pubkey,",
+            account["name"].as_str().expect("IDL account name")
+        );
+    }
+    code += "}\n\n";
+    let _ = writeln!(code, "impl {}Accounts {{", pascal);
+    code += "    /// Account metas in the order the program expects\n";
+    code += "    pub fn to_account_metas(&self) -> Vec<AccountMeta> {\n        vec![\n";
+    for account in &accounts {
+        let account_name = account["name"].as_str().expect("IDL account name");
+        let (writable, signer) = ACCOUNT_OVERRIDES
+            .iter()
+            .find(|(ix, account, _, _)| *ix == name && *account == account_name)
+            .map_or(
+                (account["writable"] == true, account["signer"] == true),
+                |(_, _, writable, signer)| (*writable, *signer),
+            );
+        let constructor = if writable { "new" } else { "new_readonly" };
+        let _ = writeln!(
+            code,
+            "            AccountMeta::{}(self.{}, {}),",
+            constructor, account_name, signer
+        );
+    }
+    code += "        ]\n    }\n}\n\n";
+
+    if !args.is_empty() {
+        let _ = writeln!(
+            code,
+            "/// Arguments of the `{}` instruction\n\
+             #[derive(Debug, Clone, PartialEq, borsh::BorshSerialize)]\npub struct {}Args {{",
+            name, pascal
+        );
+        for arg in &args {
+            let _ = writeln!(
+                code,
+                "    pub {}: {},",
+                arg["name"].as_str().expect("IDL arg name"),
+                rust_type(&arg["type"], defined)
+            );
+        }
+        code += "}\n\n";
+    }
+
+    code += &docs(ix, format!("Build the `{}` instruction", name));
+    let upper = name.to_uppercase();
+    if args.is_empty() {
+        let _ = writeln!(
+            code,
+            "pub fn create_{}_instruction(accounts: {}Accounts) -> Instruction {{\n    \
+             let data = {}_DISCRIMINATOR.to_vec();",
+            name, pascal, upper
+        );
+    } else {
+        let _ = writeln!(
+            code,
+            "pub fn create_{}_instruction(accounts: {}Accounts, args: {}Args) -> Instruction {{\n    \
+             let mut data = {}_DISCRIMINATOR.to_vec();\n    \
+             data.extend(borsh::to_vec(&args).expect(\"borsh encoding into a Vec cannot fail\"));",
+            name, pascal, pascal, upper
+        );
+    }
+    code += "    Instruction {\n        program_id: PROGRAM_ID,\n        \
+             accounts: accounts.to_account_metas(),\n        data,\n    }\n}\n\n";
+    code
+}
+
+fn generate(idl: &Value) -> String {
+    let address = idl["address"].as_str().expect("IDL program address");
+    let mut code = format!(
+        "// Generated by build.rs from {}. Do not edit.\n\n\
+         /// The program the IDL describes\n\
+         pub const PROGRAM_ID: Pubkey = solana_sdk::pubkey!(\"{}\");\n\n",
+        IDL_PATH, address
+    );
+
+    code += &account_lengths(idl);
+
+    let mut defined = BTreeSet::new();
+    for ix in idl["instructions"].as_array().expect("IDL instructions") {
+        code += &instruction(ix, &mut defined);
+    }
+
+    // Types used by arguments, and the types their fields use in turn
+    let mut generated = BTreeSet::new();
+    while let Some(name) = defined.iter().find(|n| !generated.contains(*n)).cloned() {
+        code += &defined_type(idl, &name, &mut defined);
+        generated.insert(name);
+    }
+    code
+}
@@ -0,0 +1,40 @@
+//! Pump.fun account data: the bonding curve (and the creator read out of it), Global and
+//! FeeConfig. Inputs are also tried behind the right discriminator so the fuzzer reaches
+//! the borsh fields without having to guess it first.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use trading_bot_rust::accounts::{AnchorAccount, BondingCurve, FeeConfig, Global};
+use trading_bot_rust::{cal, fees, pump_idl};
+
+/// `data`, and `data` behind `T`'s discriminator
+fn parse<T: AnchorAccount>(data: &[u8]) -> [Option<T>; 2] {
+    let prefixed = [&T::DISCRIMINATOR[..], data].concat();
+    [
+        T::try_from_account_data(data).ok(),
+        T::try_from_account_data(&prefixed).ok(),
+    ]
+}
+
+fuzz_target!(|data: &[u8]| {
+    for curve in parse::<BondingCurve>(data).into_iter().flatten() {
+        // A parsed curve is quoted straight away, whatever reserves it claims
+        let global = cal::Global::default();
+        cal::get_tokens_for_sol(&global, Some(&curve), 1_000_000_000);
+        cal::get_sol_for_tokens(&global, Some(&curve), curve.real_token_reserves);
+        cal::get_sol_from_tokens(&global, Some(&curve), curve.token_total_supply);
+        cal::price_impact_bps(&global, &curve, 1_000_000_000);
+        cal::graduation_progress(&curve);
+        cal::market_cap_sol(&curve);
+        pump_idl::get_creator_vault_pda(&curve.creator);
+    }
+    pump_idl::extend_bonding_curve(&Default::default(), data, &Default::default());
+
+    for global in parse::<Global>(data).into_iter().flatten() {
+        fees::global_fee_recipients(&global);
+    }
+    for config in parse::<FeeConfig>(data).into_iter().flatten() {
+        fees::fees_for_market_cap(&config, u128::from(u64::MAX));
+    }
+});
@@ -0,0 +1,22 @@
+//! pump.fun events as they arrive in inner instruction data and program logs. Inputs are
+//! also tried behind each event's discriminator so the fuzzer reaches the borsh fields.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use trading_bot_rust::events::{
+    self, AnchorEvent, CompleteEvent, CreateEvent, TradeEvent, EVENT_IX_TAG,
+};
+
+fuzz_target!(|data: &[u8]| {
+    events::decode_event(data);
+    for discriminator in [
+        TradeEvent::DISCRIMINATOR,
+        CreateEvent::DISCRIMINATOR,
+        CompleteEvent::DISCRIMINATOR,
+    ] {
+        events::decode_cpi(&[&EVENT_IX_TAG[..], &discriminator, data].concat());
+    }
+    let log = format!("Program data: {}", String::from_utf8_lossy(data));
+    events::decode_transaction([data], &[log, String::from_utf8_lossy(data).into_owned()]);
+});
@@ -0,0 +1,18 @@
+//! Mint account data: Token-2022 extensions, authorities and on-chain metadata, which
+//! are parsed before every buy of a new mint
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use solana_sdk::pubkey::Pubkey;
+use trading_bot_rust::{cal, metadata, safety};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(extensions) = cal::parse_mint_extensions(data, 0) {
+        extensions.gross_amount(u64::MAX);
+    }
+    let _ = cal::parse_mint_extensions(data, u64::MAX);
+    let _ = safety::parse_mint_authorities(data);
+    let _ = metadata::parse_metaplex_metadata(&Pubkey::default(), data);
+    let _ = metadata::parse_token_2022_metadata(&Pubkey::default(), data);
+});
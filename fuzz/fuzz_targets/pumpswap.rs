@@ -0,0 +1,12 @@
+//! PumpSwap pool and global config data, which sells of graduated tokens read the coin
+//! creator and fee recipients from
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use trading_bot_rust::pumpswap;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = pumpswap::parse_pool(data);
+    let _ = pumpswap::parse_global_config(data);
+});
@@ -0,0 +1,136 @@
+use anyhow::{anyhow, Result};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::cal;
+use crate::error;
+
+/// An Anchor account: 8 byte discriminator followed by the borsh encoded struct
+pub trait AnchorAccount: BorshDeserialize {
+    /// First 8 bytes of `sha256("account:<Name>")`
+    const DISCRIMINATOR: [u8; 8];
+    const NAME: &'static str;
+
+    /// Check the discriminator and deserialize. Trailing bytes are ignored so
+    /// fields appended by program upgrades don't break parsing.
+    fn try_from_account_data(data: &[u8]) -> Result<Self> {
+        if data.len() < 8 || data[..8] != Self::DISCRIMINATOR {
+            return Err(anyhow!("Account is not a {}", Self::NAME));
+        }
+        Self::deserialize(&mut &data[8..])
+            .map_err(|e| anyhow!("Failed to deserialize {}: {}", Self::NAME, e))
+    }
+}
+
+/// Fetch and deserialize the account at `address`
+pub fn fetch<T: AnchorAccount>(rpc: &RpcClient, address: &Pubkey) -> error::Result<T> {
+    let account = cal::fetch_account(rpc, address)?;
+    Ok(T::try_from_account_data(&account.data)?)
+}
+
+/// Bonding curve state
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct BondingCurve {
+    pub virtual_token_reserves: u64,
+    pub virtual_sol_reserves: u64,
+    pub real_token_reserves: u64,
+    pub real_sol_reserves: u64,
+    pub token_total_supply: u64,
+    pub complete: bool,
+    pub creator: Pubkey,
+}
+
+impl AnchorAccount for BondingCurve {
+    const DISCRIMINATOR: [u8; 8] = [23, 183, 248, 55, 96, 216, 172, 96];
+    const NAME: &'static str = "BondingCurve";
+}
+
+/// pump.fun global state
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct Global {
+    pub initialized: bool,
+    pub authority: Pubkey,
+    pub fee_recipient: Pubkey,
+    pub initial_virtual_token_reserves: u64,
+    pub initial_virtual_sol_reserves: u64,
+    pub initial_real_token_reserves: u64,
+    pub token_total_supply: u64,
+    pub fee_basis_points: u64,
+    pub withdraw_authority: Pubkey,
+    pub enable_migrate: bool,
+    pub pool_migration_fee: u64,
+    pub creator_fee_basis_points: u64,
+    pub fee_recipients: [Pubkey; 7],
+    pub set_creator_authority: Pubkey,
+    pub admin_set_creator_authority: Pubkey,
+}
+
+impl AnchorAccount for Global {
+    const DISCRIMINATOR: [u8; 8] = [167, 232, 232, 177, 200, 108, 114, 127];
+    const NAME: &'static str = "Global";
+}
+
+impl Global {
+    /// The curve parameters used by the quote math
+    pub fn curve_params(&self) -> cal::Global {
+        cal::Global {
+            initial_virtual_token_reserves: self.initial_virtual_token_reserves,
+            initial_virtual_sol_reserves: self.initial_virtual_sol_reserves,
+            initial_real_token_reserves: self.initial_real_token_reserves,
+            token_total_supply: self.token_total_supply,
+            fee_basis_points: self.fee_basis_points,
+            creator_fee_basis_points: self.creator_fee_basis_points,
+        }
+    }
+}
+
+/// Fee split in basis points
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct Fees {
+    pub lp_fee_bps: u64,
+    pub protocol_fee_bps: u64,
+    pub creator_fee_bps: u64,
+}
+
+/// Fees that apply from `market_cap_lamports_threshold` upwards
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct FeeTier {
+    pub market_cap_lamports_threshold: u128,
+    pub fees: Fees,
+}
+
+/// Fee program config for pump.fun and PumpSwap
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct FeeConfig {
+    pub bump: u8,
+    pub admin: Pubkey,
+    pub flat_fees: Fees,
+    pub fee_tiers: Vec<FeeTier>,
+}
+
+impl AnchorAccount for FeeConfig {
+    const DISCRIMINATOR: [u8; 8] = [143, 52, 146, 187, 219, 123, 76, 155];
+    const NAME: &'static str = "FeeConfig";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discriminator_and_trailing_bytes() {
+        let curve = cal::new_bonding_curve(&cal::Global::default());
+        let mut data = BondingCurve::DISCRIMINATOR.to_vec();
+        data.extend(borsh::to_vec(&curve).unwrap());
+        // Newer program versions append fields
+        data.push(1);
+
+        let parsed = BondingCurve::try_from_account_data(&data).unwrap();
+        assert_eq!(parsed.virtual_sol_reserves, curve.virtual_sol_reserves);
+        assert_eq!(parsed.creator, curve.creator);
+
+        assert!(FeeConfig::try_from_account_data(&data).is_err());
+        assert!(BondingCurve::try_from_account_data(&data[..40]).is_err());
+    }
+}
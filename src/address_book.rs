@@ -0,0 +1,190 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::RwLock;
+use tracing::warn;
+
+use crate::config;
+
+/// Global address book used when printing addresses. The file has one entry
+/// per line: `<pubkey> <kind> <name>`, `#` starts a comment.
+static ADDRESS_BOOK: Lazy<RwLock<AddressBook>> = Lazy::new(|| {
+    let book = AddressBook::load(&config::get().storage.address_book_path).unwrap_or_else(|e| {
+        warn!(error = %e, "Failed to load address book");
+        AddressBook::default()
+    });
+    RwLock::new(book)
+});
+
+/// What kind of address a label describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelKind {
+    MyWallet,
+    CopyTarget,
+    Deployer,
+    CexHotWallet,
+    Other,
+}
+
+impl LabelKind {
+    /// Prefix shown in front of the label name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LabelKind::MyWallet => "me",
+            LabelKind::CopyTarget => "target",
+            LabelKind::Deployer => "deployer",
+            LabelKind::CexHotWallet => "cex",
+            LabelKind::Other => "label",
+        }
+    }
+
+    fn parse(s: &str) -> Result<Self> {
+        match s {
+            "me" => Ok(LabelKind::MyWallet),
+            "target" => Ok(LabelKind::CopyTarget),
+            "deployer" => Ok(LabelKind::Deployer),
+            "cex" => Ok(LabelKind::CexHotWallet),
+            "label" => Ok(LabelKind::Other),
+            other => Err(anyhow!("Unknown address label kind: {}", other)),
+        }
+    }
+}
+
+/// A human-readable name for an address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub kind: LabelKind,
+    pub name: String,
+}
+
+/// Local mapping of pubkeys to labels
+#[derive(Debug, Clone, Default)]
+pub struct AddressBook {
+    entries: HashMap<Pubkey, Label>,
+}
+
+impl AddressBook {
+    /// Load an address book file. A missing file is an empty book.
+    pub fn load(path: &str) -> Result<Self> {
+        if !Path::new(path).exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read address book {}: {}", path, e))?;
+        Self::parse(&contents)
+    }
+
+    /// Parse address book lines of the form `<pubkey> <kind> <name>`
+    pub fn parse(contents: &str) -> Result<Self> {
+        let mut book = Self::default();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut parts = line.splitn(3, char::is_whitespace);
+            let (Some(pubkey), Some(kind), Some(name)) = (parts.next(), parts.next(), parts.next())
+            else {
+                return Err(anyhow!(
+                    "Address book line {}: expected `<pubkey> <kind> <name>`",
+                    number + 1
+                ));
+            };
+            let pubkey = Pubkey::from_str(pubkey)
+                .map_err(|e| anyhow!("Address book line {}: {}", number + 1, e))?;
+            book.insert(pubkey, LabelKind::parse(kind)?, name.trim());
+        }
+        Ok(book)
+    }
+
+    /// Write the book back out, sorted by kind then name
+    pub fn save(&self, path: &str) -> Result<()> {
+        let mut entries: Vec<(&Pubkey, &Label)> = self.entries.iter().collect();
+        entries.sort_by(|a, b| (a.1.kind.as_str(), &a.1.name).cmp(&(b.1.kind.as_str(), &b.1.name)));
+
+        let contents: String = entries
+            .iter()
+            .map(|(pubkey, label)| format!("{} {} {}\n", pubkey, label.kind.as_str(), label.name))
+            .collect();
+        fs::write(path, contents)
+            .map_err(|e| anyhow!("Failed to write address book {}: {}", path, e))
+    }
+
+    pub fn insert(&mut self, pubkey: Pubkey, kind: LabelKind, name: &str) {
+        self.entries.insert(
+            pubkey,
+            Label {
+                kind,
+                name: name.to_string(),
+            },
+        );
+    }
+
+    pub fn remove(&mut self, pubkey: &Pubkey) -> Option<Label> {
+        self.entries.remove(pubkey)
+    }
+
+    pub fn get(&self, pubkey: &Pubkey) -> Option<&Label> {
+        self.entries.get(pubkey)
+    }
+
+    /// Every address with the given kind of label
+    pub fn addresses_of_kind(&self, kind: LabelKind) -> Vec<Pubkey> {
+        self.entries
+            .iter()
+            .filter(|(_, label)| label.kind == kind)
+            .map(|(pubkey, _)| *pubkey)
+            .collect()
+    }
+
+    /// `kind:name` for labelled addresses, base58 otherwise
+    pub fn display(&self, pubkey: &Pubkey) -> String {
+        match self.get(pubkey) {
+            Some(label) => format!("{}:{}", label.kind.as_str(), label.name),
+            None => pubkey.to_string(),
+        }
+    }
+}
+
+/// Format `pubkey` with its label from the global address book
+pub fn label(pubkey: &Pubkey) -> String {
+    match ADDRESS_BOOK.read() {
+        Ok(book) => book.display(pubkey),
+        Err(_) => pubkey.to_string(),
+    }
+}
+
+/// Add a label to the global address book and persist it
+pub fn add_label(pubkey: Pubkey, kind: LabelKind, name: &str) -> Result<()> {
+    let mut book = ADDRESS_BOOK
+        .write()
+        .map_err(|_| anyhow!("Address book lock poisoned"))?;
+    book.insert(pubkey, kind, name);
+    book.save(&config::get().storage.address_book_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        let deployer = Pubkey::new_unique();
+        let unknown = Pubkey::new_unique();
+        let book = AddressBook::parse(&format!(
+            "# known wallets\n{} deployer serial rugger  # seen 3 times\n\n",
+            deployer
+        ))
+        .unwrap();
+
+        assert_eq!(book.display(&deployer), "deployer:serial rugger");
+        assert_eq!(book.display(&unknown), unknown.to_string());
+        assert_eq!(book.addresses_of_kind(LabelKind::Deployer), vec![deployer]);
+        assert!(AddressBook::parse(&format!("{} whale big", deployer)).is_err());
+    }
+}
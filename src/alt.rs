@@ -0,0 +1,229 @@
+use anyhow::{anyhow, Result};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    address_lookup_table::{
+        instruction::{create_lookup_table, extend_lookup_table},
+        state::AddressLookupTable,
+        AddressLookupTableAccount,
+    },
+    commitment_config::CommitmentConfig,
+    compute_budget,
+    hash::Hash,
+    instruction::Instruction,
+    message::{v0, VersionedMessage},
+    pubkey::Pubkey,
+    signer::Signer,
+    system_program,
+    transaction::{Transaction, VersionedTransaction},
+};
+use std::str::FromStr;
+use tracing::info;
+
+use crate::sender::{self, SendConfig};
+
+// Constants
+/// Maximum addresses one extend instruction can carry and still fit in a transaction
+const MAX_ADDRESSES_PER_EXTEND: usize = 20;
+
+lazy_static::lazy_static! {
+    /// Accounts every pump.fun / PumpSwap trade references regardless of mint or user
+    pub static ref PUMP_STATIC_ACCOUNTS: Vec<Pubkey> = [
+        "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P", // pump program
+        "4wTV1YmiEkRvAtNtsSGPtUrqRYQMe5SKy2uB4Jjaxnjf", // pump global
+        "Ce6TQqeHC9p8KetsN6JsjHK7UTZk7nasjjnr7XxXp9F1", // pump event authority
+        "CebN5WGQ4jvEPvsVU4EoHEpgzq1VV7AbicfhtW4xC9iM", // fee recipient
+        "pfeeUxB6jkeY1Hxd7CsFCAjcbHA9rWtchMGdZ6VojVZ",  // fee program
+        "8Wf5TiAheLUqBrKXeYg2JtAFFMWtKdG2BSFgqUcPVwTt", // pump fee config
+        "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA",  // PumpSwap program
+    ]
+    .iter()
+    .map(|a| Pubkey::from_str(a).unwrap())
+    .chain([
+        spl_token::native_mint::ID,
+        spl_token::ID,
+        spl_token_2022::ID,
+        spl_associated_token_account::ID,
+        system_program::ID,
+        compute_budget::ID,
+        Pubkey::find_program_address(
+            &[b"global_volume_accumulator"],
+            &Pubkey::from_str("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P").unwrap(),
+        )
+        .0,
+    ])
+    .collect();
+}
+
+/// Fetch and decode an address lookup table
+pub fn fetch_lookup_table(rpc: &RpcClient, address: &Pubkey) -> Result<AddressLookupTableAccount> {
+    let account = rpc
+        .get_account(address)
+        .map_err(|e| anyhow!("Failed to fetch lookup table {}: {}", address, e))?;
+    let table = AddressLookupTable::deserialize(&account.data)
+        .map_err(|e| anyhow!("Failed to decode lookup table {}: {}", address, e))?;
+    Ok(AddressLookupTableAccount {
+        key: *address,
+        addresses: table.addresses.to_vec(),
+    })
+}
+
+/// Sign `instructions` as a v0 transaction resolving accounts through `lookup_tables`.
+/// Falls back to a legacy transaction when no tables are given.
+pub fn build_versioned_transaction(
+    instructions: &[Instruction],
+    payer: &dyn Signer,
+    lookup_tables: &[AddressLookupTableAccount],
+    blockhash: Hash,
+) -> Result<VersionedTransaction> {
+    if lookup_tables.is_empty() {
+        let mut transaction = Transaction::new_with_payer(instructions, Some(&payer.pubkey()));
+        transaction
+            .try_sign(&[payer], blockhash)
+            .map_err(|e| anyhow!("Failed to sign transaction: {}", e))?;
+        return Ok(VersionedTransaction::from(transaction));
+    }
+
+    let message = v0::Message::try_compile(&payer.pubkey(), instructions, lookup_tables, blockhash)
+        .map_err(|e| anyhow!("Failed to compile v0 message: {}", e))?;
+    VersionedTransaction::try_new(VersionedMessage::V0(message), &[payer])
+        .map_err(|e| anyhow!("Failed to sign v0 transaction: {}", e))
+}
+
+/// Creates and maintains a lookup table holding the static pump.fun accounts
+pub struct AltManager {
+    pub address: Pubkey,
+    table: AddressLookupTableAccount,
+}
+
+impl AltManager {
+    /// Load an existing lookup table
+    pub fn load(rpc: &RpcClient, address: &Pubkey) -> Result<Self> {
+        Ok(Self {
+            address: *address,
+            table: fetch_lookup_table(rpc, address)?,
+        })
+    }
+
+    /// Create a new lookup table owned by `authority` and fill it with the static pump.fun accounts.
+    /// New entries are only usable one slot after they are added.
+    pub fn create(rpc: &RpcClient, authority: &dyn Signer) -> Result<Self> {
+        let recent_slot = rpc.get_slot_with_commitment(CommitmentConfig::finalized())?;
+        let (create_ix, address) =
+            create_lookup_table(authority.pubkey(), authority.pubkey(), recent_slot);
+
+        let result = sender::send_and_confirm_with_retries(
+            rpc,
+            &[create_ix],
+            authority,
+            &SendConfig::default(),
+        )?;
+        if !result.is_confirmed() {
+            return Err(anyhow!(
+                "Failed to create lookup table: {:?}",
+                result.status
+            ));
+        }
+        info!(%address, "Created lookup table");
+
+        let mut manager = Self {
+            address,
+            table: AddressLookupTableAccount {
+                key: address,
+                addresses: Vec::new(),
+            },
+        };
+        manager.extend(rpc, authority, &PUMP_STATIC_ACCOUNTS)?;
+        Ok(manager)
+    }
+
+    /// Add any of `addresses` not already in the table
+    pub fn extend(
+        &mut self,
+        rpc: &RpcClient,
+        authority: &dyn Signer,
+        addresses: &[Pubkey],
+    ) -> Result<()> {
+        let mut missing: Vec<Pubkey> = Vec::new();
+        for address in addresses {
+            if !self.table.addresses.contains(address) && !missing.contains(address) {
+                missing.push(*address);
+            }
+        }
+
+        for chunk in missing.chunks(MAX_ADDRESSES_PER_EXTEND) {
+            let extend_ix = extend_lookup_table(
+                self.address,
+                authority.pubkey(),
+                Some(authority.pubkey()),
+                chunk.to_vec(),
+            );
+            let result = sender::send_and_confirm_with_retries(
+                rpc,
+                &[extend_ix],
+                authority,
+                &SendConfig::default(),
+            )?;
+            if !result.is_confirmed() {
+                return Err(anyhow!(
+                    "Failed to extend lookup table {}: {:?}",
+                    self.address,
+                    result.status
+                ));
+            }
+            self.table.addresses.extend_from_slice(chunk);
+            info!(
+                address = %self.address,
+                added = chunk.len(),
+                "Extended lookup table"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Re-read the table from chain (e.g. after another process extended it)
+    pub fn refresh(&mut self, rpc: &RpcClient) -> Result<()> {
+        self.table = fetch_lookup_table(rpc, &self.address)?;
+        Ok(())
+    }
+
+    /// The table in the form v0 message compilation expects
+    pub fn account(&self) -> AddressLookupTableAccount {
+        self.table.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{instruction::AccountMeta, signature::Keypair};
+
+    #[test]
+    fn test_lookup_table_shrinks_transaction() {
+        let payer = Keypair::new();
+        let instruction = Instruction {
+            program_id: PUMP_STATIC_ACCOUNTS[0],
+            accounts: PUMP_STATIC_ACCOUNTS[1..]
+                .iter()
+                .map(|a| AccountMeta::new_readonly(*a, false))
+                .collect(),
+            data: vec![0; 24],
+        };
+        let table = AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: PUMP_STATIC_ACCOUNTS.clone(),
+        };
+
+        let legacy = build_versioned_transaction(
+            std::slice::from_ref(&instruction),
+            &payer,
+            &[],
+            Hash::default(),
+        )
+        .unwrap();
+        let v0 =
+            build_versioned_transaction(&[instruction], &payer, &[table], Hash::default()).unwrap();
+
+        assert!(v0.message.serialize().len() < legacy.message.serialize().len());
+    }
+}
@@ -0,0 +1,183 @@
+use anyhow::{anyhow, Result};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use std::fmt;
+
+/// Decimals of every pump.fun mint
+pub const PUMP_TOKEN_DECIMALS: u8 = 6;
+/// Decimals of SOL: a lamport is 10^-9 SOL
+pub const SOL_DECIMALS: u8 = 9;
+
+/// A raw token amount with the decimals of its mint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TokenAmount {
+    raw: u64,
+    decimals: u8,
+}
+
+impl TokenAmount {
+    pub const fn new(raw: u64, decimals: u8) -> Self {
+        Self { raw, decimals }
+    }
+
+    /// A raw amount of a pump.fun mint
+    pub const fn pump(raw: u64) -> Self {
+        Self::new(raw, PUMP_TOKEN_DECIMALS)
+    }
+
+    /// `ui` whole tokens of a mint with `decimals`, e.g. `from_ui(12.5, 6)` is 12_500_000 raw
+    pub fn from_ui(ui: f64, decimals: u8) -> Result<Self> {
+        Ok(Self::new(to_raw(ui, decimals)?, decimals))
+    }
+
+    /// Parse a decimal string such as "12.5" exactly, without going through f64. More
+    /// fractional digits than the mint has is an error rather than silently truncated.
+    pub fn parse_ui(s: &str, decimals: u8) -> Result<Self> {
+        Ok(Self::new(parse_units(s, decimals)?, decimals))
+    }
+
+    pub fn raw(&self) -> u64 {
+        self.raw
+    }
+
+    pub fn decimals(&self) -> u8 {
+        self.decimals
+    }
+
+    /// Whole tokens, as a float for display and ratios
+    pub fn ui(&self) -> f64 {
+        self.raw as f64 / 10f64.powi(self.decimals as i32)
+    }
+}
+
+impl fmt::Display for TokenAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_units(self.raw, self.decimals))
+    }
+}
+
+impl From<TokenAmount> for u64 {
+    fn from(amount: TokenAmount) -> u64 {
+        amount.raw
+    }
+}
+
+/// An amount of SOL, held in lamports
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SolAmount(u64);
+
+impl SolAmount {
+    pub const fn from_lamports(lamports: u64) -> Self {
+        Self(lamports)
+    }
+
+    /// `sol` SOL, rounded to the nearest lamport
+    pub fn from_sol(sol: f64) -> Result<Self> {
+        Ok(Self(to_raw(sol, SOL_DECIMALS)?))
+    }
+
+    /// Parse a decimal string such as "0.25" exactly, like `TokenAmount::parse_ui`
+    pub fn parse_sol(s: &str) -> Result<Self> {
+        Ok(Self(parse_units(s, SOL_DECIMALS)?))
+    }
+
+    pub fn lamports(&self) -> u64 {
+        self.0
+    }
+
+    /// Whole SOL, as a float for display and ratios
+    pub fn sol(&self) -> f64 {
+        self.0 as f64 / LAMPORTS_PER_SOL as f64
+    }
+}
+
+impl fmt::Display for SolAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} SOL", format_units(self.0, SOL_DECIMALS))
+    }
+}
+
+impl From<SolAmount> for u64 {
+    fn from(amount: SolAmount) -> u64 {
+        amount.0
+    }
+}
+
+/// Scale `ui` up by `decimals`, rounding to the nearest raw unit
+fn to_raw(ui: f64, decimals: u8) -> Result<u64> {
+    let raw = (ui * 10f64.powi(decimals as i32)).round();
+    // u64::MAX as f64 rounds up to 2^64, which itself doesn't fit
+    if !raw.is_finite() || raw < 0.0 || raw >= u64::MAX as f64 {
+        return Err(anyhow!(
+            "Amount {} is out of range for {} decimals",
+            ui,
+            decimals
+        ));
+    }
+    Ok(raw as u64)
+}
+
+/// Parse a non-negative decimal string into raw units of `decimals`
+fn parse_units(s: &str, decimals: u8) -> Result<u64> {
+    let invalid = || anyhow!("Invalid amount {:?} for {} decimals", s, decimals);
+    let (whole, fraction) = s.trim().split_once('.').unwrap_or((s.trim(), ""));
+    let digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if (whole.is_empty() && fraction.is_empty())
+        || !digits(whole)
+        || !digits(fraction)
+        || fraction.len() > decimals as usize
+    {
+        return Err(invalid());
+    }
+    let scale = 10u64.checked_pow(decimals as u32).ok_or_else(invalid)?;
+    let whole: u64 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().map_err(|_| invalid())?
+    };
+    let fraction: u64 = format!("{:0<width$}", fraction, width = decimals as usize)
+        .parse()
+        .unwrap_or(0);
+    whole
+        .checked_mul(scale)
+        .and_then(|raw| raw.checked_add(fraction))
+        .ok_or_else(invalid)
+}
+
+/// `raw` units of `decimals` as an exact decimal string, trailing zeros trimmed
+fn format_units(raw: u64, decimals: u8) -> String {
+    let scale = 10u128.pow(decimals as u32);
+    let (whole, fraction) = (raw as u128 / scale, raw as u128 % scale);
+    if fraction == 0 {
+        return whole.to_string();
+    }
+    let fraction = format!("{:0>width$}", fraction, width = decimals as usize);
+    format!("{}.{}", whole, fraction.trim_end_matches('0'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_amounts_round_trip_through_ui_units() {
+        let tokens = TokenAmount::from_ui(12.5, PUMP_TOKEN_DECIMALS).unwrap();
+        assert_eq!(tokens.raw(), 12_500_000);
+        assert_eq!(tokens.to_string(), "12.5");
+        assert_eq!(TokenAmount::parse_ui("12.5", 6).unwrap(), tokens);
+        assert_eq!(TokenAmount::parse_ui(".000001", 6).unwrap().raw(), 1);
+        assert_eq!(TokenAmount::pump(1_000_000).to_string(), "1");
+        assert!(TokenAmount::parse_ui("1.0000001", 6).is_err());
+        assert!(TokenAmount::parse_ui("-1", 6).is_err());
+        assert!(TokenAmount::parse_ui(".", 6).is_err());
+        assert!(TokenAmount::from_ui(-1.0, 6).is_err());
+        assert!(TokenAmount::from_ui(f64::NAN, 6).is_err());
+
+        // Exact past 2^53 lamports, where f64 would round
+        let sol = SolAmount::parse_sol("9007199.254740993").unwrap();
+        assert_eq!(sol.lamports(), 9_007_199_254_740_993);
+        assert_eq!(sol.to_string(), "9007199.254740993 SOL");
+        assert_eq!(SolAmount::from_sol(0.25).unwrap().lamports(), 250_000_000);
+        assert_eq!(SolAmount::from_lamports(1).to_string(), "0.000000001 SOL");
+        assert!(SolAmount::parse_sol("18446744073.709551616").is_err());
+    }
+}
@@ -0,0 +1,670 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+use crate::blacklist::{self, EntryKind, TokenList};
+use crate::cal::ExitLiquidity;
+use crate::creators::ListStatus;
+use crate::error::{self, BotError};
+use crate::executor::{self, IdempotencyKey, TradeIntent};
+use crate::limit_orders::{EntryLadder, LimitOrderBook};
+use crate::orders::{self, OrderError};
+use crate::portfolio::Portfolio;
+use crate::pump_buy::{self, BuyAmount, BuyRequest};
+use crate::pump_sell::{SellAmount, SellRequest};
+use crate::router::{self, Venue};
+use crate::{address_book, cal, config, rpc_pool};
+
+#[cfg(feature = "api")]
+pub use server::run;
+
+/// Body of `POST /buy`: spend `lamports`, or buy exactly `token_amount` tokens
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BuyBody {
+    pub mint: String,
+    /// SOL to spend
+    pub lamports: Option<u64>,
+    /// Raw tokens to receive
+    pub token_amount: Option<u64>,
+    /// Defaults to `trading.buy_slippage_bps`
+    pub slippage_bps: Option<u64>,
+    /// Take-profit this many percent above the fill price, placed as soon as the buy lands
+    pub tp_pct: Option<f64>,
+    /// Stop-loss this many percent below the fill price, placed as soon as the buy lands
+    pub sl_pct: Option<f64>,
+    /// Limit buys staged below the fill price, placed as soon as the buy lands
+    pub entry_ladder: Option<EntryLadder>,
+    /// Buy even past `trading.max_price_impact_bps`
+    pub force: Option<bool>,
+    /// Repeating a request with the same key is refused instead of trading again
+    pub idempotency_key: Option<String>,
+}
+
+/// Body of `POST /sell`: `percent` of the position or exactly `token_amount` tokens,
+/// everything when neither is given
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SellBody {
+    pub mint: String,
+    pub percent: Option<f64>,
+    pub token_amount: Option<u64>,
+    /// Defaults to `trading.sell_slippage_bps`
+    pub slippage_bps: Option<u64>,
+    /// Sell even past `trading.max_price_impact_bps`
+    pub force: Option<bool>,
+    /// Repeating a request with the same key is refused instead of trading again
+    pub idempotency_key: Option<String>,
+}
+
+/// Query of `GET /quote/:mint`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuoteQuery {
+    /// Lamports to quote a buy for, defaults to `trading.buy_amount_lamports`
+    pub lamports: Option<u64>,
+    /// Tokens to quote a sell for, none by default
+    pub tokens: Option<u64>,
+}
+
+/// Query of `GET /orders`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OrdersQuery {
+    /// Only orders on this mint
+    pub mint: Option<String>,
+    /// Include filled, failed and cancelled orders
+    pub all: Option<bool>,
+}
+
+/// Body of `POST /orders/:id/replace`: the new amount (lamports for buys, tokens for
+/// sells) and/or limit price, the other kept as it was
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReplaceBody {
+    pub amount: Option<u64>,
+    pub price: Option<f64>,
+}
+
+/// Body of `POST /blacklist`: list a mint, creator or symbol pattern
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ListBody {
+    /// mint, creator or symbol
+    pub kind: String,
+    /// The pubkey, or a case-insensitive symbol regex
+    pub value: String,
+    /// Put it on the allowlist instead of the blacklist
+    pub allow: Option<bool>,
+    pub reason: Option<String>,
+}
+
+/// Body of `POST /blacklist/remove`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UnlistBody {
+    pub kind: String,
+    pub value: String,
+}
+
+/// A failed request: HTTP status and the message returned as `{"error": ...}`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            status: 400,
+            message: message.into(),
+        }
+    }
+
+    pub fn unauthorized() -> Self {
+        Self {
+            status: 401,
+            message: "Missing or invalid x-api-key".to_string(),
+        }
+    }
+}
+
+impl From<BotError> for ApiError {
+    fn from(e: BotError) -> Self {
+        let status = match &e {
+            BotError::AccountNotFound(_) => 404,
+            BotError::DuplicateTrade(_) => 409,
+            // The trade was refused or failed on chain
+            BotError::CurveMigrated { .. }
+            | BotError::InsufficientBalance { .. }
+            | BotError::InsufficientTokens { .. }
+            | BotError::SlippageExceeded(_)
+            | BotError::SimulationFailed(_)
+            | BotError::DangerousMint { .. }
+            | BotError::UnsafeMint { .. }
+            | BotError::Blacklisted { .. }
+            | BotError::ExposureLimit { .. }
+            | BotError::PositionLimit { .. }
+            | BotError::ExitLiquidity { .. }
+            | BotError::PriceImpactTooHigh { .. }
+            | BotError::TransactionFailed(_) => 422,
+            BotError::RpcError(_) => 502,
+            BotError::TransactionExpired => 504,
+            BotError::InvalidInstruction(_) | BotError::Other(_) => 500,
+        };
+        Self {
+            status,
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<OrderError> for ApiError {
+    fn from(e: OrderError) -> Self {
+        let status = match &e {
+            OrderError::NotFound(_) => 404,
+            OrderError::NotOpen { .. } => 409,
+            OrderError::Invalid(_) => 400,
+            OrderError::Other(_) => 500,
+        };
+        Self {
+            status,
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        BotError::Other(e).into()
+    }
+}
+
+/// Whether the `x-api-key` header matches `api.api_key`. Compares every byte so the
+/// time taken doesn't leak how much of the key matched.
+pub fn authorized(header: Option<&str>, api_key: &str) -> bool {
+    let Some(header) = header else {
+        return false;
+    };
+    !api_key.is_empty()
+        && header.len() == api_key.len()
+        && header
+            .bytes()
+            .zip(api_key.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn parse_mint(mint: &str) -> Result<Pubkey, ApiError> {
+    Pubkey::from_str(mint)
+        .map_err(|e| ApiError::bad_request(format!("Invalid mint {}: {}", mint, e)))
+}
+
+/// The executor key for a request's `idempotency_key`, fresh when it has none
+fn idempotency_key(key: Option<&str>) -> IdempotencyKey {
+    key.map_or_else(IdempotencyKey::random, |key| {
+        IdempotencyKey::new(format!("api-{}", key))
+    })
+}
+
+fn check_slippage(slippage_bps: Option<u64>, default: u64) -> Result<u64, ApiError> {
+    match slippage_bps.unwrap_or(default) {
+        bps if bps > 10_000 => Err(ApiError::bad_request("slippage_bps is more than 100%")),
+        bps => Ok(bps),
+    }
+}
+
+/// `POST /buy`: buy on the bonding curve, or the PumpSwap pool once it has migrated,
+/// through the executor and wait for the result. Blocking.
+pub fn buy(body: &BuyBody) -> Result<Value, ApiError> {
+    let mint = parse_mint(&body.mint)?;
+    let amount = match (body.lamports, body.token_amount) {
+        (Some(_), Some(_)) => {
+            return Err(ApiError::bad_request(
+                "Give lamports or token_amount, not both",
+            ))
+        }
+        (None, None) => return Err(ApiError::bad_request("Give lamports or token_amount")),
+        (Some(0), None) => return Err(ApiError::bad_request("lamports must be positive")),
+        (Some(lamports), None) => BuyAmount::Sol(lamports),
+        (None, Some(0)) => return Err(ApiError::bad_request("token_amount must be positive")),
+        (None, Some(tokens)) => BuyAmount::ExactTokens(tokens),
+    };
+    let slippage_bps = check_slippage(body.slippage_bps, config::get().trading.buy_slippage_bps)?;
+    let request = BuyRequest {
+        tp_pct: body.tp_pct,
+        sl_pct: body.sl_pct,
+        entry_ladder: body.entry_ladder,
+        force: body.force.unwrap_or(false),
+        ..BuyRequest::new(mint, amount)
+    };
+    request
+        .validate()
+        .map_err(|e| ApiError::bad_request(e.to_string()))?;
+
+    let (token_amount, max_sol_cost) =
+        pump_buy::resolve_buy_request(&rpc_pool::get_client(), &request, slippage_bps)?;
+    let protected = request.has_follow_up_orders();
+    let intent = if protected {
+        TradeIntent::ProtectedBuy {
+            request,
+            token_amount,
+            max_sol_cost,
+        }
+    } else {
+        TradeIntent::Buy {
+            mint,
+            token_amount,
+            max_sol_cost,
+        }
+    };
+    executor::global().execute_keyed(idempotency_key(body.idempotency_key.as_deref()), intent)?;
+    Ok(json!({
+        "mint": mint.to_string(),
+        "token_amount": token_amount,
+        "max_sol_cost": max_sol_cost,
+        "protected": protected,
+    }))
+}
+
+/// `POST /sell`: sell wherever the mint trades, through the executor. Blocking.
+pub fn sell(body: &SellBody) -> Result<Value, ApiError> {
+    let mint = parse_mint(&body.mint)?;
+    let amount = match (body.percent, body.token_amount) {
+        (Some(_), Some(_)) => {
+            return Err(ApiError::bad_request(
+                "Give percent or token_amount, not both",
+            ))
+        }
+        (Some(pct), None) if pct <= 0.0 || pct > 100.0 => {
+            return Err(ApiError::bad_request("percent must be in (0, 100]"))
+        }
+        (Some(pct), None) => SellAmount::Percent(pct),
+        (None, Some(0)) => return Err(ApiError::bad_request("token_amount must be positive")),
+        (None, Some(tokens)) => SellAmount::Tokens(tokens),
+        (None, None) => SellAmount::All,
+    };
+    let slippage_bps = check_slippage(body.slippage_bps, config::get().trading.sell_slippage_bps)?;
+
+    executor::global().execute_keyed(
+        idempotency_key(body.idempotency_key.as_deref()),
+        TradeIntent::SellRequest {
+            request: SellRequest {
+                mint,
+                amount,
+                force: body.force.unwrap_or(false),
+            },
+            slippage_bps,
+        },
+    )?;
+    Ok(json!({ "mint": mint.to_string(), "amount": format!("{:?}", amount) }))
+}
+
+/// What selling a position on its curve would return within `trading.sell_slippage_bps`
+/// of spot, null once the curve has completed or when it couldn't be fetched
+fn exit_json(exit: error::Result<Option<ExitLiquidity>>) -> Value {
+    match exit {
+        Ok(Some(exit)) => json!({
+            "max_impact_bps": config::get().trading.sell_slippage_bps,
+            "sellable_tokens": exit.sellable_tokens,
+            "sellable_lamports": exit.sellable_lamports,
+            "liquidation_lamports": exit.liquidation_lamports,
+            "liquidation_impact_bps": exit.liquidation_impact_bps,
+        }),
+        _ => Value::Null,
+    }
+}
+
+/// `GET /positions`: open positions in the portfolio for the current `trading.mode`, with
+/// what each could be sold for right now. Blocking.
+pub fn positions() -> Result<Value, ApiError> {
+    let open = Portfolio::open_default()?.get_open_positions()?;
+    let holdings: Vec<(Pubkey, u64)> = open.iter().map(|p| (p.mint, p.token_amount)).collect();
+    let exits = cal::fetch_exit_liquidity(
+        &rpc_pool::get_client(),
+        &holdings,
+        config::get().trading.sell_slippage_bps,
+    );
+    let positions: Vec<Value> = open
+        .iter()
+        .zip(exits)
+        .map(|(p, exit)| {
+            json!({
+                "mint": p.mint.to_string(),
+                "label": address_book::label(&p.mint),
+                "token_amount": p.token_amount,
+                "cost_basis_lamports": p.cost_basis_lamports,
+                "avg_entry_price": p.avg_entry_price(),
+                "realized_pnl_lamports": p.realized_pnl_lamports,
+                "fees_paid_lamports": p.fees_paid_lamports,
+                "opened_at": p.opened_at,
+                "last_fill_at": p.last_fill_at,
+                "exit": exit_json(exit),
+            })
+        })
+        .collect();
+    Ok(json!({ "positions": positions }))
+}
+
+/// `GET /orders`: open orders, every order with `all`, optionally only on `mint`
+pub fn list_orders(query: &OrdersQuery) -> Result<Value, ApiError> {
+    let mint = query.mint.as_deref().map(parse_mint).transpose()?;
+    let orders: Vec<Value> = orders::list(
+        &LimitOrderBook::open_default()?,
+        mint.as_ref(),
+        query.all.unwrap_or(false),
+    )?
+    .iter()
+    .map(orders::to_json)
+    .collect();
+    Ok(json!({ "orders": orders }))
+}
+
+/// `POST /orders/:id/cancel`: cancel an open order
+pub fn cancel_order(id: i64) -> Result<Value, ApiError> {
+    let order = orders::cancel(&LimitOrderBook::open_default()?, id)?;
+    Ok(orders::to_json(&order))
+}
+
+/// `POST /orders/:id/replace`: cancel an open order and place it again with the new
+/// amount and/or price, returning the replacement
+pub fn replace_order(id: i64, body: &ReplaceBody) -> Result<Value, ApiError> {
+    let book = LimitOrderBook::open_default()?;
+    let order = orders::replace(&book, id, body.amount, body.price)?;
+    Ok(orders::to_json(&order))
+}
+
+/// `GET /blacklist`: every blacklist and allowlist entry, creators included
+pub fn list_entries() -> Result<Value, ApiError> {
+    let entries: Vec<Value> = TokenList::open_default()?
+        .entries()?
+        .iter()
+        .map(blacklist::to_json)
+        .collect();
+    Ok(json!({ "entries": entries }))
+}
+
+/// `POST /blacklist`: blacklist (or allow-list) a mint, creator or symbol pattern
+pub fn set_entry(body: &ListBody) -> Result<Value, ApiError> {
+    let kind = EntryKind::parse(&body.kind).map_err(|e| ApiError::bad_request(e.to_string()))?;
+    let status = if body.allow.unwrap_or(false) {
+        ListStatus::Allow
+    } else {
+        ListStatus::Deny
+    };
+    TokenList::open_default()?
+        .set(kind, &body.value, status, body.reason.as_deref())
+        .map_err(|e| ApiError::bad_request(e.to_string()))?;
+    Ok(json!({ "kind": kind.as_str(), "value": body.value, "status": status.as_str() }))
+}
+
+/// `POST /blacklist/remove`: take a mint, creator or symbol pattern off either list
+pub fn remove_entry(body: &UnlistBody) -> Result<Value, ApiError> {
+    let kind = EntryKind::parse(&body.kind).map_err(|e| ApiError::bad_request(e.to_string()))?;
+    let removed = TokenList::open_default()?
+        .remove(kind, &body.value)
+        .map_err(|e| ApiError::bad_request(e.to_string()))?;
+    Ok(json!({ "removed": removed }))
+}
+
+/// `GET /quote/:mint`: where the mint trades, a buy quote while it is on its curve and
+/// a sell quote for `tokens` anywhere. Blocking.
+pub fn quote(mint: &str, query: &QuoteQuery) -> Result<Value, ApiError> {
+    let mint = parse_mint(mint)?;
+    let rpc = rpc_pool::get_client();
+    let venue = router::venue(&rpc, &mint)?;
+
+    let mut response = json!({
+        "mint": mint.to_string(),
+        "venue": format!("{:?}", venue),
+    });
+    if venue == Venue::BondingCurve {
+        let lamports = query
+            .lamports
+            .unwrap_or(config::get().trading.buy_amount_lamports);
+        let buy = cal::quote_buy(&rpc, &mint, lamports)?;
+        response["buy"] = json!({
+            "lamports": buy.input_amount,
+            "token_amount": buy.output_amount,
+            "fee": buy.fee,
+        });
+    }
+    if let Some(tokens) = query.tokens {
+        response["sell"] = json!({
+            "token_amount": tokens,
+            "lamports": router::quote_sell(&rpc, &mint, venue, tokens)?,
+        });
+    }
+    Ok(response)
+}
+
+/// `GET /health`: trading mode, RPC endpoints and the maintenance jobs. Healthy when
+/// every job's last run succeeded and at least one endpoint is up.
+pub fn health(jobs: Value) -> (bool, Value) {
+    let endpoints: Vec<Value> = rpc_pool::pool()
+        .status()
+        .iter()
+        .map(|e| json!({ "url": e.url, "healthy": e.healthy }))
+        .collect();
+    let healthy = jobs["healthy"].as_bool().unwrap_or(false)
+        && endpoints.iter().any(|e| e["healthy"] == true);
+    let body = json!({
+        "healthy": healthy,
+        "mode": config::get().trading.mode.as_str(),
+        "rpc": endpoints,
+        "jobs": jobs,
+    });
+    (healthy, body)
+}
+
+#[cfg(feature = "api")]
+mod server {
+    use anyhow::anyhow;
+    use axum::{
+        extract::{Path, Query, State},
+        http::{HeaderMap, StatusCode},
+        response::{IntoResponse, Json, Response},
+        routing::{get, post},
+        Router,
+    };
+    use serde_json::{json, Value};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use tracing::info;
+
+    use super::{
+        ApiError, BuyBody, ListBody, OrdersQuery, QuoteQuery, ReplaceBody, SellBody, UnlistBody,
+    };
+    use crate::config;
+    use crate::scheduler::{self, SchedulerStatus};
+
+    impl IntoResponse for ApiError {
+        fn into_response(self) -> Response {
+            let status =
+                StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            (status, Json(json!({ "error": self.message }))).into_response()
+        }
+    }
+
+    fn authorize(headers: &HeaderMap) -> Result<(), ApiError> {
+        let header = headers.get("x-api-key").and_then(|v| v.to_str().ok());
+        if super::authorized(header, &config::get().api.api_key) {
+            Ok(())
+        } else {
+            Err(ApiError::unauthorized())
+        }
+    }
+
+    /// Run a blocking handler off the async runtime
+    async fn blocking(
+        handler: impl FnOnce() -> Result<Value, ApiError> + Send + 'static,
+    ) -> Result<Json<Value>, ApiError> {
+        tokio::task::spawn_blocking(handler)
+            .await
+            .map_err(|e| ApiError::from(anyhow!("Handler panicked: {}", e)))?
+            .map(Json)
+    }
+
+    async fn buy(headers: HeaderMap, Json(body): Json<BuyBody>) -> Result<Json<Value>, ApiError> {
+        authorize(&headers)?;
+        blocking(move || super::buy(&body)).await
+    }
+
+    async fn sell(headers: HeaderMap, Json(body): Json<SellBody>) -> Result<Json<Value>, ApiError> {
+        authorize(&headers)?;
+        blocking(move || super::sell(&body)).await
+    }
+
+    async fn positions(headers: HeaderMap) -> Result<Json<Value>, ApiError> {
+        authorize(&headers)?;
+        blocking(super::positions).await
+    }
+
+    async fn quote(
+        headers: HeaderMap,
+        Path(mint): Path<String>,
+        Query(query): Query<QuoteQuery>,
+    ) -> Result<Json<Value>, ApiError> {
+        authorize(&headers)?;
+        blocking(move || super::quote(&mint, &query)).await
+    }
+
+    async fn orders(
+        headers: HeaderMap,
+        Query(query): Query<OrdersQuery>,
+    ) -> Result<Json<Value>, ApiError> {
+        authorize(&headers)?;
+        blocking(move || super::list_orders(&query)).await
+    }
+
+    async fn cancel_order(
+        headers: HeaderMap,
+        Path(id): Path<i64>,
+    ) -> Result<Json<Value>, ApiError> {
+        authorize(&headers)?;
+        blocking(move || super::cancel_order(id)).await
+    }
+
+    async fn replace_order(
+        headers: HeaderMap,
+        Path(id): Path<i64>,
+        Json(body): Json<ReplaceBody>,
+    ) -> Result<Json<Value>, ApiError> {
+        authorize(&headers)?;
+        blocking(move || super::replace_order(id, &body)).await
+    }
+
+    async fn blacklist(headers: HeaderMap) -> Result<Json<Value>, ApiError> {
+        authorize(&headers)?;
+        blocking(super::list_entries).await
+    }
+
+    async fn set_entry(
+        headers: HeaderMap,
+        Json(body): Json<ListBody>,
+    ) -> Result<Json<Value>, ApiError> {
+        authorize(&headers)?;
+        blocking(move || super::set_entry(&body)).await
+    }
+
+    async fn remove_entry(
+        headers: HeaderMap,
+        Json(body): Json<UnlistBody>,
+    ) -> Result<Json<Value>, ApiError> {
+        authorize(&headers)?;
+        blocking(move || super::remove_entry(&body)).await
+    }
+
+    /// Unauthenticated so load balancers and uptime checks can poll it
+    async fn health(State(jobs): State<SchedulerStatus>) -> Response {
+        let (healthy, body) = super::health(jobs.health_json());
+        let status = if healthy {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+        (status, Json(body)).into_response()
+    }
+
+    /// Serve the API on `api.bind` and run the maintenance jobs until Ctrl-C
+    pub fn run() -> anyhow::Result<()> {
+        let config = &config::get().api;
+        if config.api_key.is_empty() {
+            return Err(anyhow!("Config field `api.api_key` is not set"));
+        }
+
+        let mut jobs = scheduler::default_scheduler();
+        let status = jobs.status();
+        let stop = Arc::new(AtomicBool::new(false));
+        std::thread::spawn({
+            let stop = stop.clone();
+            move || jobs.run(stop)
+        });
+
+        let app = Router::new()
+            .route("/buy", post(buy))
+            .route("/sell", post(sell))
+            .route("/positions", get(positions))
+            .route("/quote/:mint", get(quote))
+            .route("/orders", get(orders))
+            .route("/orders/:id/cancel", post(cancel_order))
+            .route("/orders/:id/replace", post(replace_order))
+            .route("/blacklist", get(blacklist).post(set_entry))
+            .route("/blacklist/remove", post(remove_entry))
+            .route("/health", get(health))
+            .with_state(status);
+
+        let served = tokio::runtime::Runtime::new()?.block_on(async {
+            let listener = tokio::net::TcpListener::bind(config.bind.as_str())
+                .await
+                .map_err(|e| anyhow!("Failed to bind {}: {}", config.bind, e))?;
+            info!(bind = %config.bind, "API listening");
+            axum::serve(listener, app)
+                .with_graceful_shutdown(async {
+                    let _ = tokio::signal::ctrl_c().await;
+                })
+                .await?;
+            Ok::<(), anyhow::Error>(())
+        });
+        stop.store(true, Ordering::Relaxed);
+        served
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_api_key_and_error_statuses() {
+        assert!(authorized(Some("secret"), "secret"));
+        assert!(!authorized(Some("secreT"), "secret"));
+        assert!(!authorized(Some("secret2"), "secret"));
+        assert!(!authorized(None, "secret"));
+        // An unset key never authorizes anything
+        assert!(!authorized(Some(""), ""));
+
+        let status = |e: BotError| ApiError::from(e).status;
+        assert_eq!(status(BotError::AccountNotFound(Pubkey::new_unique())), 404);
+        assert_eq!(status(BotError::SlippageExceeded("moved".into())), 422);
+        assert_eq!(status(BotError::DuplicateTrade("retry".into())), 409);
+        assert_eq!(
+            status(BotError::Blacklisted {
+                mint: Pubkey::new_unique(),
+                reason: "not on the allowlist".into(),
+            }),
+            422
+        );
+        assert_eq!(status(BotError::TransactionExpired), 504);
+        assert_eq!(status(BotError::Other(anyhow!("boom"))), 500);
+        assert_eq!(parse_mint("not-a-mint").unwrap_err().status, 400);
+        assert_eq!(ApiError::from(OrderError::NotFound(7)).status, 404);
+        assert_eq!(
+            ApiError::from(OrderError::Invalid("bad".into())).status,
+            400
+        );
+    }
+}
@@ -0,0 +1,511 @@
+use anyhow::{anyhow, Result};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use solana_sdk::{hash, pubkey::Pubkey};
+use std::str::FromStr;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use crate::config;
+use crate::creators::{self, CreatorHistory, Launch};
+use crate::events::{self, PumpEvent, StreamedEvent};
+
+// Constants
+lazy_static::lazy_static! {
+    static ref PUMP_PROGRAM_ID: Pubkey = Pubkey::from_str("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P").unwrap();
+}
+/// Events buffered before `run` writes them out
+const BATCH_SIZE: usize = 500;
+/// Longest an event waits in the buffer
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+/// Pause before resubscribing after the stream closes or fails
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+/// Which pump.fun event a row holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Trade,
+    Create,
+    Complete,
+}
+
+impl EventKind {
+    pub fn of(event: &PumpEvent) -> Self {
+        match event {
+            PumpEvent::Trade(_) => EventKind::Trade,
+            PumpEvent::Create(_) => EventKind::Create,
+            PumpEvent::Complete(_) => EventKind::Complete,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            EventKind::Trade => "trade",
+            EventKind::Create => "create",
+            EventKind::Complete => "complete",
+        }
+    }
+}
+
+/// The wallet an event is indexed under: the trader, the token's creator, or whoever
+/// completed the curve
+fn wallet(event: &PumpEvent) -> Pubkey {
+    match event {
+        PumpEvent::Trade(e) => e.user,
+        PumpEvent::Create(e) => e.creator,
+        PumpEvent::Complete(e) => e.user,
+    }
+}
+
+/// Which archived events `EventArchive::events` returns. Unset fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    pub mint: Option<Pubkey>,
+    pub wallet: Option<Pubkey>,
+    pub kind: Option<EventKind>,
+    /// Unix seconds, inclusive
+    pub since: Option<i64>,
+    /// Unix seconds, inclusive
+    pub until: Option<i64>,
+    /// Return at most this many, from the oldest
+    pub limit: Option<usize>,
+}
+
+/// A wallet's bonding curve trading in the archive
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalletActivity {
+    pub wallet: Pubkey,
+    pub trades: u64,
+    /// Distinct mints traded
+    pub mints: u64,
+    pub sol_bought: u64,
+    pub sol_sold: u64,
+}
+
+impl WalletActivity {
+    /// Lamports taken out of curves less lamports put in. Tokens still held are not counted.
+    pub fn net_sol(&self) -> i64 {
+        self.sol_sold as i64 - self.sol_bought as i64
+    }
+}
+
+/// SQLite archive of pump.fun events, indexed by mint, wallet and time
+pub struct EventArchive {
+    conn: Connection,
+}
+
+impl EventArchive {
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)
+            .map_err(|e| anyhow!("Failed to open event archive {}: {}", path, e))?;
+        Self::init(conn)
+    }
+
+    /// Open the archive at `storage.archive_db_path`
+    pub fn open_default() -> Result<Self> {
+        Self::open(&config::get().storage.archive_db_path)
+    }
+
+    /// Open a throwaway in-memory archive
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                hash BLOB NOT NULL UNIQUE,
+                kind TEXT NOT NULL,
+                mint TEXT NOT NULL,
+                wallet TEXT NOT NULL,
+                timestamp INTEGER NOT NULL,
+                is_buy INTEGER,
+                sol_amount INTEGER,
+                token_amount INTEGER,
+                data BLOB NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS events_mint ON events (mint, timestamp);
+            CREATE INDEX IF NOT EXISTS events_wallet ON events (wallet, timestamp);
+            CREATE INDEX IF NOT EXISTS events_timestamp ON events (timestamp);",
+        )?;
+        Ok(Self { conn })
+    }
+
+    /// Store imported `events` in one transaction, skipping any already archived.
+    /// Imports carry no signatures, so an event counts as archived when one with the
+    /// same data is. Returns how many were new.
+    pub fn insert(&mut self, events: &[PumpEvent]) -> Result<usize> {
+        self.insert_keyed(events.iter().map(|event| (None, event)))
+    }
+
+    /// Store streamed `events` in one transaction, skipping any already archived (the
+    /// same event delivered again after a resubscribe). Events are keyed on their
+    /// transaction signature and position in it, so identical trades in separate
+    /// transactions are each kept. Returns how many were new.
+    pub fn insert_streamed(&mut self, events: &[StreamedEvent]) -> Result<usize> {
+        self.insert_keyed(events.iter().map(|streamed| {
+            let index = (streamed.index as u64).to_le_bytes();
+            let key = hash::hashv(&[streamed.signature.as_ref(), &index]);
+            (Some(key), &streamed.event)
+        }))
+    }
+
+    /// Store events under their dedupe keys, or the hash of their data when they have
+    /// none, ignoring keys already archived
+    fn insert_keyed<'a>(
+        &mut self,
+        events: impl Iterator<Item = (Option<hash::Hash>, &'a PumpEvent)>,
+    ) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut added = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO events
+                 (hash, kind, mint, wallet, timestamp, is_buy, sol_amount, token_amount, data)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            )?;
+            for (key, event) in events {
+                let data = events::encode_event(event)?;
+                let trade = match event {
+                    PumpEvent::Trade(e) => Some(e),
+                    _ => None,
+                };
+                added += stmt.execute(params![
+                    key.unwrap_or_else(|| hash::hash(&data)).to_bytes().to_vec(),
+                    EventKind::of(event).as_str(),
+                    event.mint().to_string(),
+                    wallet(event).to_string(),
+                    event.timestamp(),
+                    trade.map(|e| e.is_buy),
+                    trade.map(|e| e.sol_amount as i64),
+                    trade.map(|e| e.token_amount as i64),
+                    data,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(added)
+    }
+
+    pub fn count(&self) -> Result<u64> {
+        let count: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM events", [], |row| row.get(0))?;
+        Ok(count as u64)
+    }
+
+    /// Archived events matching `filter`, oldest first
+    pub fn events(&self, filter: &EventFilter) -> Result<Vec<PumpEvent>> {
+        let mut clauses = Vec::new();
+        let mut values = Vec::new();
+        let mut clause = |sql: &'static str, value: Value| {
+            clauses.push(sql);
+            values.push(value);
+        };
+        if let Some(mint) = filter.mint {
+            clause("mint = ?", Value::Text(mint.to_string()));
+        }
+        if let Some(wallet) = filter.wallet {
+            clause("wallet = ?", Value::Text(wallet.to_string()));
+        }
+        if let Some(kind) = filter.kind {
+            clause("kind = ?", Value::Text(kind.as_str().to_string()));
+        }
+        if let Some(since) = filter.since {
+            clause("timestamp >= ?", Value::Integer(since));
+        }
+        if let Some(until) = filter.until {
+            clause("timestamp <= ?", Value::Integer(until));
+        }
+
+        let mut sql = "SELECT data FROM events".to_string();
+        if !clauses.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&clauses.join(" AND "));
+        }
+        sql.push_str(" ORDER BY timestamp, id");
+        if let Some(limit) = filter.limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(params_from_iter(values), |row| row.get::<_, Vec<u8>>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        rows.iter()
+            .map(|data| events::decode_event(data).ok_or_else(|| anyhow!("Corrupt archived event")))
+            .collect()
+    }
+
+    /// Wallets with at least `min_trades` trades since `since`, most SOL taken out of
+    /// curves first. A starting point for finding copy-trade targets.
+    pub fn top_wallets(
+        &self,
+        since: i64,
+        min_trades: u64,
+        limit: usize,
+    ) -> Result<Vec<WalletActivity>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT wallet, COUNT(*), COUNT(DISTINCT mint),
+                    SUM(CASE WHEN is_buy THEN sol_amount ELSE 0 END) AS bought,
+                    SUM(CASE WHEN is_buy THEN 0 ELSE sol_amount END) AS sold
+             FROM events
+             WHERE kind = 'trade' AND timestamp >= ?1
+             GROUP BY wallet
+             HAVING COUNT(*) >= ?2
+             ORDER BY sold - bought DESC
+             LIMIT ?3",
+        )?;
+        let rows = stmt
+            .query_map(params![since, min_trades as i64, limit as i64], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, i64>(4)?,
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        rows.into_iter()
+            .map(|(wallet, trades, mints, sol_bought, sol_sold)| {
+                Ok(WalletActivity {
+                    wallet: Pubkey::from_str(&wallet)?,
+                    trades: trades as u64,
+                    mints: mints as u64,
+                    sol_bought: sol_bought as u64,
+                    sol_sold: sol_sold as u64,
+                })
+            })
+            .collect()
+    }
+
+    /// `creator`'s archived launches, newest first, and how each turned out. Unlike
+    /// `creators::fetch_history` this needs no RPC calls, but only knows what was
+    /// archived.
+    pub fn creator_history(&self, creator: &Pubkey) -> Result<CreatorHistory> {
+        let created = self.events(&EventFilter {
+            wallet: Some(*creator),
+            kind: Some(EventKind::Create),
+            ..Default::default()
+        })?;
+        let mut launches = Vec::new();
+        for event in created.iter().rev() {
+            let mint = event.mint().to_string();
+            let complete = self
+                .conn
+                .query_row(
+                    "SELECT 1 FROM events WHERE mint = ?1 AND kind = 'complete' LIMIT 1",
+                    params![mint],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some();
+            let sold = self
+                .conn
+                .query_row(
+                    "SELECT 1 FROM events
+                     WHERE mint = ?1 AND wallet = ?2 AND kind = 'trade' AND NOT is_buy LIMIT 1",
+                    params![mint, creator.to_string()],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some();
+            launches.push(Launch {
+                mint: event.mint(),
+                created_at: event.timestamp(),
+                outcome: creators::outcome(complete, sold),
+            });
+        }
+        Ok(CreatorHistory {
+            creator: *creator,
+            launches,
+        })
+    }
+
+    /// Archive `stream` until it ends, writing every `BATCH_SIZE` events or
+    /// `FLUSH_INTERVAL`, whichever comes first
+    fn record(
+        &mut self,
+        stream: impl Iterator<Item = StreamedEvent> + Send + 'static,
+    ) -> Result<()> {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for event in stream {
+                if tx.send(event).is_err() {
+                    return;
+                }
+            }
+        });
+
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        let mut flushed = Instant::now();
+        loop {
+            let closed = match rx.recv_timeout(FLUSH_INTERVAL) {
+                Ok(event) => {
+                    batch.push(event);
+                    false
+                }
+                Err(RecvTimeoutError::Timeout) => false,
+                Err(RecvTimeoutError::Disconnected) => true,
+            };
+            if !batch.is_empty()
+                && (closed || batch.len() >= BATCH_SIZE || flushed.elapsed() >= FLUSH_INTERVAL)
+            {
+                let added = self.insert_streamed(&batch)?;
+                debug!(received = batch.len(), added, "Archived events");
+                batch.clear();
+                flushed = Instant::now();
+            }
+            if closed {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Archive every pump.fun event to `storage.archive_db_path`, resubscribing whenever
+/// the stream closes. Runs until a write fails.
+pub fn run() -> Result<()> {
+    let config = config::get();
+    let mut archive = EventArchive::open_default()?;
+    info!(
+        path = %config.storage.archive_db_path,
+        archived = archive.count()?,
+        "Archiving pump.fun events"
+    );
+    loop {
+        match events::subscribe_events(&config.rpc.ws_url, &PUMP_PROGRAM_ID) {
+            Ok(stream) => {
+                archive.record(stream.streamed())?;
+                warn!("Event stream closed, resubscribing");
+            }
+            Err(e) => warn!(error = %e, "Failed to subscribe to pump.fun events"),
+        }
+        std::thread::sleep(RESUBSCRIBE_DELAY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creators::LaunchOutcome;
+    use crate::events::{CompleteEvent, CreateEvent, TradeEvent};
+    use solana_sdk::signature::Signature;
+
+    fn create(mint: Pubkey, creator: Pubkey, timestamp: i64) -> PumpEvent {
+        PumpEvent::Create(CreateEvent {
+            name: "Test".to_string(),
+            symbol: "TEST".to_string(),
+            uri: String::new(),
+            mint,
+            bonding_curve: Pubkey::new_unique(),
+            user: creator,
+            creator,
+            timestamp,
+            virtual_token_reserves: 1_073_000_000_000_000,
+            virtual_sol_reserves: 30_000_000_000,
+            real_token_reserves: 793_100_000_000_000,
+            token_total_supply: 1_000_000_000_000_000,
+        })
+    }
+
+    fn trade(mint: Pubkey, user: Pubkey, is_buy: bool, sol: u64, timestamp: i64) -> PumpEvent {
+        PumpEvent::Trade(TradeEvent {
+            mint,
+            sol_amount: sol,
+            token_amount: 1_000_000,
+            is_buy,
+            user,
+            timestamp,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_archive_indexes_and_deduplicates() {
+        let mut archive = EventArchive::open_in_memory().unwrap();
+        let creator = Pubkey::new_unique();
+        let trader = Pubkey::new_unique();
+        let (migrated, dumped) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let history = vec![
+            create(migrated, creator, 100),
+            trade(migrated, trader, true, 1_000, 110),
+            PumpEvent::Complete(CompleteEvent {
+                user: trader,
+                mint: migrated,
+                bonding_curve: Pubkey::new_unique(),
+                timestamp: 120,
+            }),
+            trade(migrated, trader, false, 3_000, 130),
+            create(dumped, creator, 200),
+            trade(dumped, creator, false, 500, 210),
+        ];
+        assert_eq!(archive.insert(&history).unwrap(), 6);
+        // Overlapping batches only add what's new
+        let later = trade(dumped, trader, true, 700, 220);
+        assert_eq!(archive.insert(&[history[5].clone(), later]).unwrap(), 1);
+        assert_eq!(archive.count().unwrap(), 7);
+
+        let by_mint = archive
+            .events(&EventFilter {
+                mint: Some(migrated),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(by_mint, history[..4].to_vec());
+
+        let trades = archive
+            .events(&EventFilter {
+                wallet: Some(trader),
+                kind: Some(EventKind::Trade),
+                since: Some(115),
+                limit: Some(1),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(trades, vec![history[3].clone()]);
+
+        let wallets = archive.top_wallets(0, 2, 10).unwrap();
+        assert_eq!(wallets.len(), 1);
+        assert_eq!(wallets[0].wallet, trader);
+        assert_eq!((wallets[0].trades, wallets[0].mints), (3, 2));
+        assert_eq!(wallets[0].net_sol(), 1_300);
+
+        let creator_history = archive.creator_history(&creator).unwrap();
+        let outcomes: Vec<_> = creator_history
+            .launches
+            .iter()
+            .map(|l| (l.mint, l.outcome))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                (dumped, LaunchOutcome::Dumped),
+                (migrated, LaunchOutcome::Migrated)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_streamed_events_deduplicate_on_their_source() {
+        let mut archive = EventArchive::open_in_memory().unwrap();
+        let event = trade(Pubkey::new_unique(), Pubkey::new_unique(), true, 1_000, 100);
+        let streamed = |signature, index| StreamedEvent {
+            signature,
+            index,
+            event: event.clone(),
+        };
+        let (first, second) = (Signature::new_unique(), Signature::new_unique());
+
+        // The same trade twice in one second, in separate transactions or one
+        let batch = [streamed(first, 0), streamed(second, 0), streamed(second, 1)];
+        assert_eq!(archive.insert_streamed(&batch).unwrap(), 3);
+        // Redelivered after a resubscribe
+        assert_eq!(archive.insert_streamed(&[streamed(first, 0)]).unwrap(), 0);
+        assert_eq!(archive.count().unwrap(), 3);
+    }
+}
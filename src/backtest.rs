@@ -0,0 +1,678 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signature::Signature};
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+use tracing::{debug, info, warn};
+
+use crate::accounts::{BondingCurve, FeeConfig};
+use crate::cal::{self, Global, RoundingPolicy};
+use crate::error::{self, BotError};
+use crate::events::{self, PumpEvent};
+use crate::executor::TradeIntent;
+use crate::indicators::Indicators;
+use crate::pnl::PnlReport;
+use crate::portfolio::{self, Fill, Position, Side};
+use crate::strategy::{Context, Market, Strategy};
+use crate::{fees, signatures, snipe};
+
+/// Backtest settings
+#[derive(Debug, Clone)]
+pub struct BacktestConfig {
+    /// SOL the simulated wallet starts with
+    pub initial_lamports: u64,
+    /// Fee tiers to charge; None charges the default fees
+    pub fee_config: Option<FeeConfig>,
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        Self {
+            initial_lamports: 10 * LAMPORTS_PER_SOL,
+            fee_config: None,
+        }
+    }
+}
+
+/// The simulated wallet's value at one point of the replay
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EquityPoint {
+    /// Unix seconds
+    pub timestamp: i64,
+    /// Cash plus what selling every open position on its curve would return
+    pub equity_lamports: u64,
+}
+
+/// Market state rebuilt from the replayed events and our simulated fills
+struct ReplayMarket {
+    fee_config: Option<FeeConfig>,
+    curves: HashMap<Pubkey, BondingCurve>,
+    /// Token balances by (owner, mint), ours included
+    balances: HashMap<(Pubkey, Pubkey), u64>,
+    fills: HashMap<Pubkey, Vec<Fill>>,
+    /// Unix seconds of the latest event
+    now: i64,
+}
+
+impl ReplayMarket {
+    /// Follow one event: Create opens a curve, Trade carries the reserves after the
+    /// trade and moves the trader's balance, Complete closes the curve
+    fn apply(&mut self, event: &PumpEvent) {
+        match event {
+            PumpEvent::Create(event) => {
+                self.curves.insert(event.mint, snipe::launch_curve(event));
+            }
+            PumpEvent::Trade(event) => {
+                let supply = self
+                    .curves
+                    .get(&event.mint)
+                    .map_or(Global::default().token_total_supply, |c| {
+                        c.token_total_supply
+                    });
+                self.curves.insert(
+                    event.mint,
+                    BondingCurve {
+                        virtual_token_reserves: event.virtual_token_reserves,
+                        virtual_sol_reserves: event.virtual_sol_reserves,
+                        real_token_reserves: event.real_token_reserves,
+                        real_sol_reserves: event.real_sol_reserves,
+                        token_total_supply: supply,
+                        complete: event.real_token_reserves == 0,
+                        creator: event.creator,
+                    },
+                );
+                let balance = self.balances.entry((event.user, event.mint)).or_default();
+                *balance = if event.is_buy {
+                    *balance + event.token_amount
+                } else {
+                    balance.saturating_sub(event.token_amount)
+                };
+            }
+            PumpEvent::Complete(event) => {
+                if let Some(curve) = self.curves.get_mut(&event.mint) {
+                    curve.complete = true;
+                }
+            }
+        }
+    }
+
+    /// What selling `token_amount` of `mint` on its last known curve would return
+    fn value(&self, mint: &Pubkey, token_amount: u64) -> u64 {
+        self.curves.get(mint).map_or(0, |curve| {
+            cal::get_sol_from_tokens(&self.global(curve), Some(curve), token_amount)
+        })
+    }
+}
+
+impl Market for ReplayMarket {
+    fn rpc(&self) -> Option<&RpcClient> {
+        None
+    }
+
+    fn now(&self) -> i64 {
+        self.now
+    }
+
+    fn bonding_curve(&self, mint: &Pubkey) -> error::Result<BondingCurve> {
+        self.curves
+            .get(mint)
+            .cloned()
+            .ok_or(BotError::AccountNotFound(*mint))
+    }
+
+    fn global(&self, curve: &BondingCurve) -> Global {
+        self.fee_config
+            .as_ref()
+            .map_or_else(Global::default, |config| {
+                fees::global_with_fees(&fees::fees_for_market_cap(
+                    config,
+                    fees::market_cap_lamports(curve),
+                ))
+            })
+    }
+
+    fn token_balance(&self, owner: &Pubkey, mint: &Pubkey) -> error::Result<u64> {
+        Ok(self.balances.get(&(*owner, *mint)).copied().unwrap_or(0))
+    }
+
+    fn position(&self, mint: &Pubkey) -> Result<Option<Position>> {
+        Ok(self
+            .fills
+            .get(mint)
+            .and_then(|fills| portfolio::build_position(mint, fills)))
+    }
+}
+
+/// Outcome of a backtest
+#[derive(Debug, Clone)]
+pub struct BacktestReport {
+    /// PnL per mint and round trip, open positions marked on their last curve
+    pub pnl: PnlReport,
+    pub fills: Vec<Fill>,
+    /// One point per second in which anything happened, after that second's events
+    pub equity: Vec<EquityPoint>,
+    pub initial_lamports: u64,
+    pub final_lamports: u64,
+    pub events: usize,
+    /// Trades refused for slippage, a missing or completed curve, or lack of funds
+    pub rejected: usize,
+}
+
+impl BacktestReport {
+    pub fn return_pct(&self) -> f64 {
+        if self.initial_lamports == 0 {
+            return 0.0;
+        }
+        (self.final_lamports as f64 / self.initial_lamports as f64 - 1.0) * 100.0
+    }
+
+    /// Largest fall of the equity curve from a previous peak, in percent
+    pub fn max_drawdown_pct(&self) -> f64 {
+        let mut peak = self.initial_lamports;
+        let mut drawdown = 0.0f64;
+        for point in &self.equity {
+            peak = peak.max(point.equity_lamports);
+            if peak > 0 {
+                let fall = (peak - point.equity_lamports) as f64 / peak as f64 * 100.0;
+                drawdown = drawdown.max(fall);
+            }
+        }
+        drawdown
+    }
+
+    /// The equity curve as `timestamp,equity_lamports` rows
+    pub fn equity_csv(&self) -> String {
+        let mut csv = "timestamp,equity_lamports\n".to_string();
+        for point in &self.equity {
+            csv.push_str(&format!("{},{}\n", point.timestamp, point.equity_lamports));
+        }
+        csv
+    }
+
+    pub fn print(&self) {
+        let sol = |lamports: u64| lamports as f64 / LAMPORTS_PER_SOL as f64;
+        let count = |side| self.fills.iter().filter(|f| f.side == side).count();
+        println!("\n=== Backtest ===");
+        println!("Events:       {}", self.events);
+        println!(
+            "Trades:       {} buys, {} sells, {} rejected",
+            count(Side::Buy),
+            count(Side::Sell),
+            self.rejected
+        );
+        println!(
+            "Equity:       {:.4} -> {:.4} SOL ({:+.2}%)",
+            sol(self.initial_lamports),
+            sol(self.final_lamports),
+            self.return_pct()
+        );
+        println!("Max drawdown: {:.2}%", self.max_drawdown_pct());
+        self.pnl.print();
+    }
+}
+
+/// Replays pump.fun history through strategies, filling their trades against the
+/// replayed curves with the bonding curve math in `cal`. Trades fill at the state the
+/// triggering event left, before any later trade, which is as fast as trading gets.
+/// Our own trades move a curve only until the next recorded trade resets its reserves.
+pub struct Backtest {
+    config: BacktestConfig,
+    strategies: Vec<Box<dyn Strategy>>,
+}
+
+impl Backtest {
+    pub fn new(config: BacktestConfig) -> Self {
+        Self {
+            config,
+            strategies: Vec::new(),
+        }
+    }
+
+    pub fn register(&mut self, strategy: impl Strategy + 'static) -> &mut Self {
+        self.strategies.push(Box::new(strategy));
+        self
+    }
+
+    /// Replay `events`, oldest first. Timers run on the events' clock, so a strategy's
+    /// timer only fires when an event arrives at or after it is due.
+    pub fn run(&mut self, events: &[PumpEvent]) -> BacktestReport {
+        let mut replay = Replay {
+            user: Pubkey::new_unique(),
+            cash: self.config.initial_lamports,
+            market: ReplayMarket {
+                fee_config: self.config.fee_config.clone(),
+                curves: HashMap::new(),
+                balances: HashMap::new(),
+                fills: HashMap::new(),
+                now: events.first().map_or(0, PumpEvent::timestamp),
+            },
+            fills: Vec::new(),
+            equity: Vec::new(),
+            rejected: 0,
+            indicators: Indicators::new(),
+        };
+        let mut timers: Vec<Option<i64>> = self
+            .strategies
+            .iter()
+            .map(|strategy| timer_secs(strategy.as_ref()).map(|secs| replay.market.now + secs))
+            .collect();
+
+        for event in events {
+            replay.market.now = replay.market.now.max(event.timestamp());
+            let mut intents = Vec::new();
+            {
+                let ctx = Context {
+                    market: &replay.market,
+                    user: &replay.user,
+                    indicators: &replay.indicators,
+                };
+                for (index, (strategy, timer)) in
+                    self.strategies.iter_mut().zip(&mut timers).enumerate()
+                {
+                    if timer.is_some_and(|due| due <= replay.market.now) {
+                        *timer = timer_secs(strategy.as_ref()).map(|secs| replay.market.now + secs);
+                        let wanted = strategy.on_timer(&ctx);
+                        intents.extend(wanted.into_iter().map(|intent| (index, intent)));
+                    }
+                }
+            }
+
+            replay.market.apply(event);
+            replay.indicators.on_event(event);
+            let ctx = Context {
+                market: &replay.market,
+                user: &replay.user,
+                indicators: &replay.indicators,
+            };
+            for (index, strategy) in self.strategies.iter_mut().enumerate() {
+                let wanted = match event {
+                    PumpEvent::Create(event) => strategy.on_new_token(&ctx, event),
+                    PumpEvent::Trade(event) => strategy.on_price_update(&ctx, event),
+                    PumpEvent::Complete(event) => strategy.on_complete(&ctx, event),
+                };
+                intents.extend(wanted.into_iter().map(|intent| (index, intent)));
+            }
+
+            // Each trade is tagged with the strategy that wants it, which hears if it fails
+            let mut queue = VecDeque::from(intents);
+            while let Some((owner, intent)) = queue.pop_front() {
+                let fill = replay.execute(&intent);
+                let ctx = Context {
+                    market: &replay.market,
+                    user: &replay.user,
+                    indicators: &replay.indicators,
+                };
+                let Some(fill) = fill else {
+                    let wanted = self.strategies[owner].on_trade_failed(&ctx, &intent);
+                    queue.extend(wanted.into_iter().map(|intent| (owner, intent)));
+                    continue;
+                };
+                for (index, strategy) in self.strategies.iter_mut().enumerate() {
+                    let wanted = strategy.on_fill(&ctx, &fill);
+                    queue.extend(wanted.into_iter().map(|intent| (index, intent)));
+                }
+            }
+            replay.mark();
+        }
+
+        let values: HashMap<Pubkey, u64> = replay
+            .market
+            .fills
+            .keys()
+            .filter_map(|mint| {
+                let position = replay.market.position(mint).ok()??;
+                Some((*mint, replay.market.value(mint, position.token_amount)))
+            })
+            .collect();
+        let mut pnl = PnlReport::build(&replay.fills, &values);
+        pnl.generated_at = replay.market.now;
+        BacktestReport {
+            pnl,
+            final_lamports: replay.equity_lamports(),
+            fills: replay.fills,
+            equity: replay.equity,
+            initial_lamports: self.config.initial_lamports,
+            events: events.len(),
+            rejected: replay.rejected,
+        }
+    }
+}
+
+/// A strategy's timer interval in whole seconds, at least 1
+fn timer_secs(strategy: &dyn Strategy) -> Option<i64> {
+    strategy
+        .timer_interval()
+        .map(|interval| interval.as_secs().max(1) as i64)
+}
+
+/// The simulated wallet during one backtest
+struct Replay {
+    /// Stands in for the trading wallet
+    user: Pubkey,
+    cash: u64,
+    market: ReplayMarket,
+    fills: Vec<Fill>,
+    equity: Vec<EquityPoint>,
+    rejected: usize,
+    indicators: Indicators,
+}
+
+impl Replay {
+    fn equity_lamports(&self) -> u64 {
+        let held: u64 = self
+            .market
+            .balances
+            .iter()
+            .filter(|((owner, _), amount)| *owner == self.user && **amount > 0)
+            .map(|((_, mint), amount)| self.market.value(mint, *amount))
+            .sum();
+        self.cash + held
+    }
+
+    /// Record the equity as of the latest event, one point per second
+    fn mark(&mut self) {
+        let point = EquityPoint {
+            timestamp: self.market.now,
+            equity_lamports: self.equity_lamports(),
+        };
+        match self.equity.last_mut() {
+            Some(last) if last.timestamp == point.timestamp => *last = point,
+            _ => self.equity.push(point),
+        }
+    }
+
+    fn reject(&mut self, intent: &TradeIntent, reason: &str) -> Option<Fill> {
+        debug!(?intent, reason, "Backtest trade rejected");
+        self.rejected += 1;
+        None
+    }
+
+    /// Fill `intent` against its curve, moving the curve, the cash and our balance
+    fn execute(&mut self, intent: &TradeIntent) -> Option<Fill> {
+        let mint = intent.mint();
+        let Some(curve) = self
+            .market
+            .curves
+            .get(&mint)
+            .filter(|c| !c.complete)
+            .cloned()
+        else {
+            return self.reject(intent, "no active bonding curve");
+        };
+        let global = self.market.global(&curve);
+        let held = self.market.token_balance(&self.user, &mint).unwrap_or(0);
+
+        let (fill, after) = match intent {
+            TradeIntent::Buy {
+                token_amount,
+                max_sol_cost,
+                ..
+            } => {
+                let cost = cal::get_sol_for_tokens(&global, Some(&curve), *token_amount);
+                if cost > *max_sol_cost {
+                    return self.reject(intent, "slippage");
+                }
+                if cost > self.cash {
+                    return self.reject(intent, "insufficient SOL");
+                }
+                let quote = cal::quote_buy_with_rounding(
+                    &global,
+                    Some(&curve),
+                    cost,
+                    RoundingPolicy::default(),
+                );
+                self.cash -= cost;
+                let fill = Fill::new(
+                    mint,
+                    Side::Buy,
+                    quote.output_amount,
+                    cost,
+                    quote.fee,
+                    String::new(),
+                );
+                (fill, cal::curve_after_buy(&global, &curve, cost))
+            }
+            TradeIntent::Sell {
+                token_amount,
+                min_sol_output,
+                ..
+            } => {
+                let tokens = (*token_amount).min(held);
+                if tokens == 0 {
+                    return self.reject(intent, "no tokens");
+                }
+                let quote = cal::quote_sell_with_rounding(
+                    &global,
+                    Some(&curve),
+                    tokens,
+                    RoundingPolicy::default(),
+                );
+                if quote.output_amount < *min_sol_output {
+                    return self.reject(intent, "slippage");
+                }
+                self.cash += quote.output_amount;
+                let fill = Fill::new(
+                    mint,
+                    Side::Sell,
+                    tokens,
+                    quote.output_amount,
+                    quote.fee,
+                    String::new(),
+                );
+                (fill, cal::curve_after_sell(&global, &curve, tokens))
+            }
+            // There is no order book to place the TP/SL legs or entry ladder in, so it fills
+            // as a plain buy
+            TradeIntent::ProtectedBuy {
+                token_amount,
+                max_sol_cost,
+                ..
+            } => {
+                return self.execute(&TradeIntent::Buy {
+                    mint,
+                    token_amount: *token_amount,
+                    max_sol_cost: *max_sol_cost,
+                });
+            }
+            TradeIntent::SellRequest { request, .. } => {
+                // Nothing moves between the quote and the fill, so slippage can't bite
+                let tokens = match request.amount.resolve(held, &global, Some(&curve)) {
+                    Ok(tokens) => tokens,
+                    Err(_) => return self.reject(intent, "unresolvable sell amount"),
+                };
+                return self.execute(&TradeIntent::Sell {
+                    mint,
+                    token_amount: tokens,
+                    min_sol_output: 0,
+                });
+            }
+        };
+
+        let fill = Fill {
+            signature: format!("backtest-{}", self.fills.len() + 1),
+            timestamp: self.market.now,
+            ..fill
+        };
+        let balance = self.market.balances.entry((self.user, mint)).or_default();
+        *balance = match fill.side {
+            Side::Buy => *balance + fill.token_amount,
+            Side::Sell => *balance - fill.token_amount,
+        };
+        self.market.curves.insert(mint, after);
+        self.market
+            .fills
+            .entry(mint)
+            .or_default()
+            .push(fill.clone());
+        self.fills.push(fill.clone());
+        Some(fill)
+    }
+}
+
+/// Read an archive of one base64 event per line, as in `Program data:` logs.
+/// Blank lines and lines starting with `#` are skipped.
+pub fn load_archive(path: &str) -> Result<Vec<PumpEvent>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read archive {}: {}", path, e))?;
+    let mut events = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let event = BASE64
+            .decode(line)
+            .ok()
+            .and_then(|data| events::decode_event(&data))
+            .ok_or_else(|| anyhow!("{}:{} is not a pump.fun event", path, number + 1))?;
+        events.push(event);
+    }
+    Ok(events)
+}
+
+/// Write `events` as an archive `load_archive` reads back
+pub fn save_archive(path: &str, events: &[PumpEvent]) -> Result<()> {
+    let mut contents = String::new();
+    for event in events {
+        contents.push_str(&BASE64.encode(events::encode_event(event)?));
+        contents.push('\n');
+    }
+    std::fs::write(path, contents).map_err(|e| anyhow!("Failed to write archive {}: {}", path, e))
+}
+
+/// Events of the successful transactions among the last `limit` mentioning `account`
+/// (e.g. a mint's bonding curve or a wallet), oldest first
+pub fn fetch_history(rpc: &RpcClient, account: &Pubkey, limit: usize) -> Result<Vec<PumpEvent>> {
+    let signatures: Vec<_> = signatures::recent(rpc, account, limit)?
+        .into_iter()
+        .filter(|s| s.err.is_none())
+        .collect();
+
+    let mut events = Vec::new();
+    for status in signatures.iter().rev() {
+        let signature = Signature::from_str(&status.signature)?;
+        match events::fetch_transaction_events(rpc, &signature) {
+            Ok(found) => events.extend(found),
+            Err(e) => warn!(%signature, error = %e, "Skipping transaction"),
+        }
+    }
+    info!(%account, transactions = signatures.len(), events = events.len(), "Fetched history");
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{CreateEvent, TradeEvent};
+    use std::time::Duration;
+
+    /// Buys every launch, sells everything on the next trade after a timer tick
+    struct FlipOnTimer {
+        held: Vec<Pubkey>,
+    }
+
+    impl Strategy for FlipOnTimer {
+        fn name(&self) -> &str {
+            "flip"
+        }
+
+        fn subscriptions(&self) -> Vec<Pubkey> {
+            Vec::new()
+        }
+
+        fn timer_interval(&self) -> Option<Duration> {
+            Some(Duration::from_secs(10))
+        }
+
+        fn on_new_token(&mut self, ctx: &Context, event: &CreateEvent) -> Vec<TradeIntent> {
+            let curve = ctx.market.bonding_curve(&event.mint).unwrap();
+            let global = ctx.market.global(&curve);
+            vec![TradeIntent::Buy {
+                mint: event.mint,
+                token_amount: cal::get_tokens_for_sol(&global, Some(&curve), LAMPORTS_PER_SOL),
+                max_sol_cost: LAMPORTS_PER_SOL + LAMPORTS_PER_SOL / 100,
+            }]
+        }
+
+        fn on_fill(&mut self, _ctx: &Context, fill: &Fill) -> Vec<TradeIntent> {
+            if fill.side == Side::Buy {
+                self.held.push(fill.mint);
+            }
+            Vec::new()
+        }
+
+        fn on_timer(&mut self, ctx: &Context) -> Vec<TradeIntent> {
+            self.held
+                .drain(..)
+                .map(|mint| TradeIntent::Sell {
+                    mint,
+                    token_amount: ctx.market.token_balance(ctx.user, &mint).unwrap(),
+                    min_sol_output: 0,
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_backtest_round_trip_and_archive() {
+        let global = Global::default();
+        let mint = Pubkey::new_unique();
+        let creator = Pubkey::new_unique();
+        let launch = cal::new_bonding_curve(&global);
+        let create = PumpEvent::Create(CreateEvent {
+            name: "Test".to_string(),
+            symbol: "TST".to_string(),
+            uri: String::new(),
+            mint,
+            bonding_curve: Pubkey::new_unique(),
+            user: creator,
+            creator,
+            timestamp: 1_000,
+            virtual_token_reserves: launch.virtual_token_reserves,
+            virtual_sol_reserves: launch.virtual_sol_reserves,
+            real_token_reserves: launch.real_token_reserves,
+            token_total_supply: launch.token_total_supply,
+        });
+        // Someone else buys 5 SOL worth after us, lifting the price
+        let pumped = cal::curve_after_buy(&global, &launch, 5 * LAMPORTS_PER_SOL);
+        let trade = |timestamp| {
+            PumpEvent::Trade(TradeEvent {
+                mint,
+                is_buy: true,
+                user: Pubkey::new_unique(),
+                timestamp,
+                virtual_sol_reserves: pumped.virtual_sol_reserves,
+                virtual_token_reserves: pumped.virtual_token_reserves,
+                real_sol_reserves: pumped.real_sol_reserves,
+                real_token_reserves: pumped.real_token_reserves,
+                creator,
+                ..Default::default()
+            })
+        };
+        let history = vec![create, trade(1_005), trade(1_020)];
+
+        let path = std::env::temp_dir().join(format!("backtest-{}.b64", mint));
+        let path = path.to_str().unwrap();
+        save_archive(path, &history).unwrap();
+        let events = load_archive(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(events, history);
+
+        let mut backtest = Backtest::new(BacktestConfig::default());
+        backtest.register(FlipOnTimer { held: Vec::new() });
+        let report = backtest.run(&events);
+
+        assert_eq!(report.events, 3);
+        assert_eq!(report.rejected, 0);
+        assert_eq!(report.fills.len(), 2);
+        assert_eq!(report.fills[0].timestamp, 1_000);
+        // The timer due at 1_010 fires with the event at 1_020
+        assert_eq!(report.fills[1].timestamp, 1_020);
+        assert!(report.final_lamports > report.initial_lamports);
+        assert_eq!(report.pnl.win_rate(), Some(1.0));
+        assert_eq!(report.equity.len(), 3);
+        assert!(report.max_drawdown_pct() > 0.0);
+        assert_eq!(report.equity_csv().lines().count(), 4);
+    }
+}
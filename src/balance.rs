@@ -0,0 +1,268 @@
+use anyhow::anyhow;
+use once_cell::sync::Lazy;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    instruction::Instruction, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signer::Signer,
+    system_instruction,
+};
+use spl_token::native_mint::ID as WSOL_MINT;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tracing::{info, warn};
+
+use crate::config::{self, ExecutionMode};
+use crate::error::{self, BotError};
+use crate::sender::{self, SendConfig};
+use crate::{rpc_pool, signer, telegram, token_accounts};
+
+/// Manager shared by the executor and the balance job, for `wallet.private_key`
+static MANAGER: Lazy<Arc<BalanceManager>> = Lazy::new(|| Arc::new(BalanceManager::from_config()));
+
+type FetchBalance = Arc<dyn Fn() -> error::Result<u64> + Send + Sync>;
+
+/// SOL balance of `wallet.private_key`
+fn trading_wallet_balance() -> error::Result<u64> {
+    let user = signer::load_default_wallet()?;
+    Ok(rpc_pool::get_client().get_balance(&user.pubkey())?)
+}
+
+/// Keeps `balance.reserve_lamports` of the trading wallet's SOL out of buys, so fees
+/// and rent can always be paid
+pub struct BalanceManager {
+    reserve_lamports: u64,
+    /// Refuse buys that would dip into the reserve
+    enforced: bool,
+    balance: FetchBalance,
+    /// Lamports of buys accepted but not finished
+    pending: Mutex<u64>,
+}
+
+impl BalanceManager {
+    pub fn new(reserve_lamports: u64, enforced: bool, balance: FetchBalance) -> Self {
+        Self {
+            reserve_lamports,
+            enforced,
+            balance,
+            pending: Mutex::new(0),
+        }
+    }
+
+    /// Reserve from `[balance]`, enforced in live mode only since paper and simulated
+    /// buys spend nothing
+    pub fn from_config() -> Self {
+        let config = config::get();
+        Self::new(
+            config.balance.reserve_lamports,
+            config.trading.mode == ExecutionMode::Live,
+            Arc::new(trading_wallet_balance),
+        )
+    }
+
+    pub fn reserve_lamports(&self) -> u64 {
+        self.reserve_lamports
+    }
+
+    pub fn pending_lamports(&self) -> u64 {
+        *self.lock_pending()
+    }
+
+    fn lock_pending(&self) -> MutexGuard<'_, u64> {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn spendable(&self, pending: u64) -> error::Result<u64> {
+        Ok((self.balance)()?.saturating_sub(self.reserve_lamports + pending))
+    }
+
+    /// Lamports new buys may spend: the wallet's balance less the reserve and the buys
+    /// already accepted
+    pub fn spendable_lamports(&self) -> error::Result<u64> {
+        self.spendable(self.pending_lamports())
+    }
+
+    /// Count a buy of `lamports` as pending, or refuse it if it would dip into the
+    /// reserve. Every successful claim must be followed by a `release`.
+    pub fn claim(&self, lamports: u64) -> error::Result<()> {
+        // Held across the check so concurrent buys can't both spend the last of the balance
+        let mut pending = self.lock_pending();
+        if self.enforced {
+            let available = self.spendable(*pending)?;
+            if lamports > available {
+                return Err(BotError::InsufficientBalance {
+                    needed: lamports,
+                    available,
+                });
+            }
+        }
+        *pending += lamports;
+        Ok(())
+    }
+
+    /// The buy claimed with `claim` has finished, landed or not
+    pub fn release(&self, lamports: u64) {
+        let mut pending = self.lock_pending();
+        *pending = pending.saturating_sub(lamports);
+    }
+}
+
+/// The balance manager shared by the executor
+pub fn manager() -> Arc<BalanceManager> {
+    MANAGER.clone()
+}
+
+/// Lamports the trading wallet can put into new buys
+pub fn spendable_lamports() -> error::Result<u64> {
+    MANAGER.spendable_lamports()
+}
+
+/// Send `instructions` paid by `payer` in live mode, simulate them otherwise.
+/// Returns whether they were sent.
+fn send(rpc: &RpcClient, instructions: &[Instruction], payer: &dyn Signer) -> error::Result<bool> {
+    if config::get().trading.mode != ExecutionMode::Live {
+        sender::simulate_and_print(rpc, instructions, payer)?;
+        return Ok(false);
+    }
+    let instructions = sender::simulate_for_send(rpc, instructions, payer)?;
+    let result =
+        sender::send_and_confirm_with_retries(rpc, &instructions, payer, &SendConfig::default())?;
+    if let Some(e) = BotError::from_trade_status(&payer.pubkey(), &result.status) {
+        return Err(e);
+    }
+    Ok(true)
+}
+
+/// Close `owner`'s wrapped SOL accounts, turning their balance back into SOL.
+/// Returns the lamports unwrapped.
+pub fn unwrap_wsol(rpc: &RpcClient, owner: &dyn Signer) -> error::Result<u64> {
+    let accounts: Vec<_> = token_accounts::fetch_closable_accounts(rpc, &owner.pubkey())?
+        .into_iter()
+        .filter(|account| account.mint == WSOL_MINT)
+        .collect();
+    if accounts.is_empty() {
+        return Ok(0);
+    }
+
+    let mut instructions = config::get().fees.compute_budget_instructions();
+    for account in &accounts {
+        instructions.push(token_accounts::close_account(
+            &account.address,
+            &owner.pubkey(),
+            &account.token_program,
+        )?);
+    }
+    let lamports = accounts.iter().map(|a| a.lamports).sum();
+    info!(
+        count = accounts.len(),
+        sol = lamports as f64 / LAMPORTS_PER_SOL as f64,
+        "Unwrapping WSOL"
+    );
+    Ok(if send(rpc, &instructions, owner)? {
+        lamports
+    } else {
+        0
+    })
+}
+
+/// Move `lamports` from `treasury` to `wallet`, keeping the treasury's own reserve
+pub fn top_up(
+    rpc: &RpcClient,
+    treasury: &dyn Signer,
+    wallet: &Pubkey,
+    lamports: u64,
+) -> error::Result<()> {
+    let available = rpc
+        .get_balance(&treasury.pubkey())?
+        .saturating_sub(config::get().balance.reserve_lamports);
+    if available < lamports {
+        return Err(BotError::InsufficientBalance {
+            needed: lamports,
+            available,
+        });
+    }
+
+    let mut instructions = config::get().fees.compute_budget_instructions();
+    instructions.push(system_instruction::transfer(
+        &treasury.pubkey(),
+        wallet,
+        lamports,
+    ));
+    info!(
+        treasury = %treasury.pubkey(),
+        %wallet,
+        sol = lamports as f64 / LAMPORTS_PER_SOL as f64,
+        "Topping up wallet"
+    );
+    send(rpc, &instructions, treasury)?;
+    Ok(())
+}
+
+/// Unwrap stray WSOL (when `balance.unwrap_wsol` is on), then top `wallet` up from the
+/// treasury if its SOL is below `balance.low_balance_lamports`, or warn when there is no
+/// treasury. Returns the wallet's balance before any top-up.
+pub fn maintain(rpc: &RpcClient, wallet: &dyn Signer) -> error::Result<u64> {
+    let config = &config::get().balance;
+    if config.unwrap_wsol {
+        unwrap_wsol(rpc, wallet)?;
+    }
+
+    let balance = rpc.get_balance(&wallet.pubkey())?;
+    if balance >= config.low_balance_lamports {
+        return Ok(balance);
+    }
+    let sol = balance as f64 / LAMPORTS_PER_SOL as f64;
+    if config.treasury_private_key.is_empty() {
+        warn!(wallet = %wallet.pubkey(), sol, "Trading wallet balance is low");
+        telegram::notify(format!(
+            "Trading wallet {} is down to {:.4} SOL",
+            wallet.pubkey(),
+            sol
+        ));
+        return Ok(balance);
+    }
+
+    let treasury = signer::load_wallet(&config.treasury_private_key)
+        .map_err(|e| anyhow!("Invalid balance.treasury_private_key: {}", e))?;
+    top_up(rpc, &treasury, &wallet.pubkey(), config.top_up_lamports)?;
+    telegram::notify(format!(
+        "Topped up {} from {:.4} SOL with {:.4} SOL from the treasury",
+        wallet.pubkey(),
+        sol,
+        config.top_up_lamports as f64 / LAMPORTS_PER_SOL as f64
+    ));
+    Ok(balance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[test]
+    fn test_claims_stop_at_the_reserve() {
+        let balance = Arc::new(AtomicU64::new(1_000_000));
+        let manager = BalanceManager::new(100_000, true, {
+            let balance = balance.clone();
+            Arc::new(move || Ok(balance.load(Ordering::SeqCst)))
+        });
+
+        assert_eq!(manager.spendable_lamports().unwrap(), 900_000);
+        manager.claim(600_000).unwrap();
+        assert_eq!(manager.spendable_lamports().unwrap(), 300_000);
+        assert!(matches!(
+            manager.claim(300_001),
+            Err(BotError::InsufficientBalance {
+                needed: 300_001,
+                available: 300_000
+            })
+        ));
+        manager.claim(300_000).unwrap();
+        manager.release(600_000);
+        assert_eq!(manager.pending_lamports(), 300_000);
+
+        // A balance below the reserve leaves nothing to spend
+        balance.store(50_000, Ordering::SeqCst);
+        assert_eq!(manager.spendable_lamports().unwrap(), 0);
+
+        let paper = BalanceManager::new(100_000, false, Arc::new(|| Ok(0)));
+        assert!(paper.claim(1_000_000).is_ok());
+    }
+}
@@ -0,0 +1,350 @@
+use anyhow::{anyhow, Result};
+use regex::{Regex, RegexBuilder};
+use rusqlite::{params, Connection};
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+use crate::creators::{CreatorList, ListStatus};
+use crate::error::{self, BotError};
+use crate::portfolio::now_unix;
+use crate::{cal, config, metadata, rpc_pool};
+
+/// What a blacklist or allowlist entry matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    Mint,
+    /// Every launch of a creator, kept in the creator allow / deny list
+    Creator,
+    /// A case-insensitive regex matched against the token symbol
+    Symbol,
+}
+
+impl EntryKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntryKind::Mint => "mint",
+            EntryKind::Creator => "creator",
+            EntryKind::Symbol => "symbol",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "mint" => Ok(EntryKind::Mint),
+            "creator" => Ok(EntryKind::Creator),
+            "symbol" => Ok(EntryKind::Symbol),
+            other => Err(anyhow!(
+                "Unknown list entry kind {:?}: use mint, creator or symbol",
+                other
+            )),
+        }
+    }
+}
+
+/// A black- or allow-listed mint, creator or symbol pattern
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListEntry {
+    pub kind: EntryKind,
+    /// The pubkey, or the symbol regex
+    pub value: String,
+    pub status: ListStatus,
+    pub reason: Option<String>,
+    pub added_at: i64,
+}
+
+impl ListEntry {
+    /// Whether this entry covers `token`
+    fn matches(&self, token: &Token) -> bool {
+        match self.kind {
+            EntryKind::Mint => self.value == token.mint.to_string(),
+            EntryKind::Creator => token
+                .creator
+                .is_some_and(|creator| self.value == creator.to_string()),
+            EntryKind::Symbol => token.symbol.is_some_and(|symbol| {
+                symbol_pattern(&self.value).is_ok_and(|pattern| pattern.is_match(symbol))
+            }),
+        }
+    }
+}
+
+/// What a buy is screened on. The creator and symbol are None when no entry needs them.
+#[derive(Debug, Clone, Copy)]
+pub struct Token<'a> {
+    pub mint: Pubkey,
+    pub creator: Option<Pubkey>,
+    pub symbol: Option<&'a str>,
+}
+
+fn symbol_pattern(pattern: &str) -> Result<Regex> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| anyhow!("Invalid symbol pattern {:?}: {}", pattern, e))
+}
+
+/// `value` checked for `kind`: pubkeys normalized, symbol patterns compiled
+fn normalize(kind: EntryKind, value: &str) -> Result<String> {
+    match kind {
+        EntryKind::Mint | EntryKind::Creator => Ok(Pubkey::from_str(value)
+            .map_err(|e| anyhow!("Invalid {} {}: {}", kind.as_str(), value, e))?
+            .to_string()),
+        EntryKind::Symbol => symbol_pattern(value).map(|_| value.to_string()),
+    }
+}
+
+/// Mint and symbol black / allow lists persisted in SQLite, alongside the creator list
+pub struct TokenList {
+    conn: Connection,
+    creators: CreatorList,
+}
+
+impl TokenList {
+    /// Open (or create) the list tables in the database at `path`
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)
+            .map_err(|e| anyhow!("Failed to open blacklist database {}: {}", path, e))?;
+        Self::init(conn, CreatorList::open(path)?)
+    }
+
+    /// Open the lists in the portfolio database
+    pub fn open_default() -> Result<Self> {
+        Self::open(&config::get().storage.db_path)
+    }
+
+    /// Open throwaway in-memory lists
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(
+            Connection::open_in_memory()?,
+            CreatorList::open_in_memory()?,
+        )
+    }
+
+    fn init(conn: Connection, creators: CreatorList) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS token_list (
+                kind TEXT NOT NULL,
+                value TEXT NOT NULL,
+                status TEXT NOT NULL,
+                reason TEXT,
+                added_at INTEGER NOT NULL,
+                PRIMARY KEY (kind, value)
+            );",
+        )?;
+        Ok(Self { conn, creators })
+    }
+
+    /// Put `value` on the blacklist (Deny) or allowlist (Allow), replacing any earlier entry
+    pub fn set(
+        &self,
+        kind: EntryKind,
+        value: &str,
+        status: ListStatus,
+        reason: Option<&str>,
+    ) -> Result<()> {
+        let value = normalize(kind, value)?;
+        if kind == EntryKind::Creator {
+            return self
+                .creators
+                .set(&Pubkey::from_str(&value)?, status, reason);
+        }
+        self.conn.execute(
+            "INSERT OR REPLACE INTO token_list (kind, value, status, reason, added_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![kind.as_str(), value, status.as_str(), reason, now_unix()],
+        )?;
+        Ok(())
+    }
+
+    /// Take `value` off either list. Returns false if it wasn't listed.
+    pub fn remove(&self, kind: EntryKind, value: &str) -> Result<bool> {
+        let value = normalize(kind, value)?;
+        if kind == EntryKind::Creator {
+            return self.creators.remove(&Pubkey::from_str(&value)?);
+        }
+        let changed = self.conn.execute(
+            "DELETE FROM token_list WHERE kind = ?1 AND value = ?2",
+            params![kind.as_str(), value],
+        )?;
+        Ok(changed == 1)
+    }
+
+    /// Every entry of both lists, creators included, oldest first
+    pub fn entries(&self) -> Result<Vec<ListEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT kind, value, status, reason, added_at FROM token_list ORDER BY added_at",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, i64>(4)?,
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut entries = rows
+            .into_iter()
+            .map(|(kind, value, status, reason, added_at)| {
+                Ok(ListEntry {
+                    kind: EntryKind::parse(&kind)?,
+                    value,
+                    status: ListStatus::parse(&status)?,
+                    reason,
+                    added_at,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        entries.extend(self.creators.entries()?.into_iter().map(|entry| ListEntry {
+            kind: EntryKind::Creator,
+            value: entry.creator.to_string(),
+            status: entry.status,
+            reason: entry.reason,
+            added_at: entry.added_at,
+        }));
+        entries.sort_by_key(|entry| entry.added_at);
+        Ok(entries)
+    }
+}
+
+/// Why `token` may not be bought, if it may not. A blacklist match always refuses, even
+/// when the token is also allow-listed; with `allowlist_only` the token must match an
+/// allowlist entry too.
+pub fn refusal(entries: &[ListEntry], token: &Token, allowlist_only: bool) -> Option<String> {
+    if let Some(entry) = entries
+        .iter()
+        .find(|e| e.status == ListStatus::Deny && e.matches(token))
+    {
+        let reason = entry
+            .reason
+            .as_ref()
+            .map_or(String::new(), |reason| format!(" ({})", reason));
+        return Some(format!(
+            "{} {} is blacklisted{}",
+            entry.kind.as_str(),
+            entry.value,
+            reason
+        ));
+    }
+    if allowlist_only
+        && !entries
+            .iter()
+            .any(|e| e.status == ListStatus::Allow && e.matches(token))
+    {
+        return Some("not on the allowlist".to_string());
+    }
+    None
+}
+
+/// Refuse a buy of `mint` if the lists (and `safety.allowlist_only`) don't allow it. The
+/// curve creator and symbol are only fetched when some entry needs them. Blocking.
+pub fn screen(mint: &Pubkey) -> error::Result<()> {
+    let allowlist_only = config::get().safety.allowlist_only;
+    let entries = TokenList::open_default()?.entries()?;
+    if entries.is_empty() && !allowlist_only {
+        return Ok(());
+    }
+    let wants = |kind| entries.iter().any(|e| e.kind == kind);
+    let rpc = rpc_pool::get_client();
+    let creator = if wants(EntryKind::Creator) {
+        match cal::fetch_bonding_curve(&rpc, mint) {
+            Ok(curve) => Some(curve.creator),
+            Err(BotError::AccountNotFound(_)) => None,
+            Err(e) => return Err(e),
+        }
+    } else {
+        None
+    };
+    let symbol = if wants(EntryKind::Symbol) {
+        Some(metadata::fetch_metadata(&rpc, mint, false)?.symbol)
+    } else {
+        None
+    };
+    let token = Token {
+        mint: *mint,
+        creator,
+        symbol: symbol.as_deref(),
+    };
+    match refusal(&entries, &token, allowlist_only) {
+        Some(reason) => Err(BotError::Blacklisted {
+            mint: *mint,
+            reason,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// `entry` as JSON for the API
+pub fn to_json(entry: &ListEntry) -> Value {
+    json!({
+        "kind": entry.kind.as_str(),
+        "value": entry.value,
+        "status": entry.status.as_str(),
+        "reason": entry.reason,
+        "added_at": entry.added_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blacklist_refuses_before_the_allowlist_admits() {
+        let list = TokenList::open_in_memory().unwrap();
+        let (mint, creator) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let token = Token {
+            mint,
+            creator: Some(creator),
+            symbol: Some("ELONRUG"),
+        };
+        assert_eq!(refusal(&list.entries().unwrap(), &token, false), None);
+        assert_eq!(
+            refusal(&list.entries().unwrap(), &token, true).as_deref(),
+            Some("not on the allowlist")
+        );
+
+        list.set(
+            EntryKind::Creator,
+            &creator.to_string(),
+            ListStatus::Allow,
+            None,
+        )
+        .unwrap();
+        assert_eq!(refusal(&list.entries().unwrap(), &token, true), None);
+
+        list.set(EntryKind::Symbol, "rug$", ListStatus::Deny, Some("scam"))
+            .unwrap();
+        let entries = list.entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            refusal(&entries, &token, true).as_deref(),
+            Some("symbol rug$ is blacklisted (scam)")
+        );
+        let other = Token {
+            symbol: Some("RUGGED"),
+            ..token
+        };
+        assert_eq!(refusal(&entries, &other, false), None);
+
+        assert!(list
+            .set(EntryKind::Symbol, "(", ListStatus::Deny, None)
+            .is_err());
+        assert!(list
+            .set(EntryKind::Mint, "not-a-mint", ListStatus::Deny, None)
+            .is_err());
+        assert!(list.remove(EntryKind::Symbol, "rug$").unwrap());
+        assert!(!list.remove(EntryKind::Symbol, "rug$").unwrap());
+
+        list.set(EntryKind::Mint, &mint.to_string(), ListStatus::Deny, None)
+            .unwrap();
+        assert_eq!(
+            refusal(&list.entries().unwrap(), &token, true),
+            Some(format!("mint {} is blacklisted", mint))
+        );
+        assert!(EntryKind::parse("wallet").is_err());
+    }
+}
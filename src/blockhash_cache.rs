@@ -0,0 +1,197 @@
+use once_cell::sync::Lazy;
+use solana_client::{pubsub_client::PubsubClient, rpc_client::RpcClient};
+use solana_sdk::{commitment_config::CommitmentConfig, hash::Hash};
+use std::sync::{Once, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::config::{self, StreamBackend};
+use crate::error;
+use crate::rpc_pool;
+
+// Constants
+/// How long the refresher polls after losing the slot stream before subscribing again
+const RESUBSCRIBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Cache shared by every sender, with `rpc.blockhash_max_age_ms` as its max age
+static CACHE: Lazy<BlockhashCache> = Lazy::new(BlockhashCache::from_config);
+static REFRESHER: Once = Once::new();
+
+/// A blockhash and the last block height transactions signed against it can land in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatestBlockhash {
+    pub blockhash: Hash,
+    pub last_valid_block_height: u64,
+    pub fetched_at: Instant,
+}
+
+impl LatestBlockhash {
+    pub fn age(&self) -> Duration {
+        self.fetched_at.elapsed()
+    }
+}
+
+/// The latest confirmed blockhash, served until it is `max_age` old. A background
+/// refresher replaces it on the first new slot after half that age, so senders sign
+/// without a round trip.
+pub struct BlockhashCache {
+    max_age: Duration,
+    latest: RwLock<Option<LatestBlockhash>>,
+}
+
+impl BlockhashCache {
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            latest: RwLock::new(None),
+        }
+    }
+
+    pub fn from_config() -> Self {
+        Self::new(Duration::from_millis(
+            config::get().rpc.blockhash_max_age_ms,
+        ))
+    }
+
+    /// The cached blockhash if it is still within the max age
+    pub fn cached(&self) -> Option<LatestBlockhash> {
+        let latest = (*self.latest.read().ok()?)?;
+        (latest.age() < self.max_age).then_some(latest)
+    }
+
+    /// Whether the refresher should fetch a new blockhash
+    fn needs_refresh(&self) -> bool {
+        self.cached()
+            .is_none_or(|latest| latest.age() >= self.max_age / 2)
+    }
+
+    fn store(&self, latest: LatestBlockhash) {
+        if let Ok(mut cached) = self.latest.write() {
+            *cached = Some(latest);
+        }
+    }
+
+    /// Fetch the latest confirmed blockhash and cache it
+    pub fn refresh(&self, rpc: &RpcClient) -> error::Result<LatestBlockhash> {
+        let (blockhash, last_valid_block_height) =
+            rpc.get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())?;
+        let latest = LatestBlockhash {
+            blockhash,
+            last_valid_block_height,
+            fetched_at: Instant::now(),
+        };
+        self.store(latest);
+        Ok(latest)
+    }
+
+    /// The blockhash to sign against and its last valid block height, fetched only if
+    /// the cached one is missing or stale
+    pub fn get(&self, rpc: &RpcClient) -> error::Result<(Hash, u64)> {
+        let latest = match self.cached() {
+            Some(latest) => latest,
+            None => self.refresh(rpc)?,
+        };
+        Ok((latest.blockhash, latest.last_valid_block_height))
+    }
+
+    /// `get` for a send confirmed at `commitment`. Only confirmed blockhashes are cached, so
+    /// any other level is fetched at that level each time.
+    pub fn get_with_commitment(
+        &self,
+        rpc: &RpcClient,
+        commitment: CommitmentConfig,
+    ) -> error::Result<(Hash, u64)> {
+        if commitment == CommitmentConfig::confirmed() {
+            return self.get(rpc);
+        }
+        Ok(rpc.get_latest_blockhash_with_commitment(commitment)?)
+    }
+
+    /// Refresh whenever the blockhash is half its max age, timed by `rpc.ws_url` slot
+    /// notifications. Falls back to polling while no slot stream is available.
+    fn run_refresher(&self) {
+        let rpc = rpc_pool::get_client();
+        let refresh = |rpc: &RpcClient| {
+            if self.needs_refresh() {
+                match self.refresh(rpc) {
+                    Ok(latest) => debug!(blockhash = %latest.blockhash, "Refreshed blockhash"),
+                    Err(e) => warn!(error = %e, "Failed to refresh blockhash"),
+                }
+            }
+        };
+        loop {
+            let config = config::get();
+            if config.stream.backend == StreamBackend::WebSocket {
+                match PubsubClient::slot_subscribe(&config.rpc.ws_url) {
+                    Ok((_subscription, slots)) => {
+                        for _ in slots.iter() {
+                            refresh(&rpc);
+                        }
+                        warn!("Slot stream ended, polling for blockhashes");
+                    }
+                    Err(e) => {
+                        warn!(error = %e, "Slot subscription failed, polling for blockhashes")
+                    }
+                }
+            }
+            let polling = Instant::now();
+            while polling.elapsed() < RESUBSCRIBE_INTERVAL {
+                refresh(&rpc);
+                std::thread::sleep(self.max_age / 4);
+            }
+        }
+    }
+}
+
+/// The cache shared by senders. The first call starts its background refresher.
+pub fn global() -> &'static BlockhashCache {
+    REFRESHER.call_once(|| {
+        std::thread::spawn(|| CACHE.run_refresher());
+    });
+    &CACHE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_serves_fresh_blockhashes_only() {
+        // Every fetch fails
+        let rpc = RpcClient::new_mock("fails".to_string());
+        let cache = BlockhashCache::new(Duration::from_secs(2));
+        assert!(cache.needs_refresh());
+        assert!(cache.get(&rpc).is_err());
+
+        let latest = LatestBlockhash {
+            blockhash: Hash::new_unique(),
+            last_valid_block_height: 150,
+            fetched_at: Instant::now(),
+        };
+        cache.store(latest);
+        assert!(!cache.needs_refresh());
+        assert_eq!(cache.get(&rpc).unwrap(), (latest.blockhash, 150));
+
+        // Due for a background refresh, still served
+        cache.store(LatestBlockhash {
+            fetched_at: Instant::now() - Duration::from_secs(1),
+            ..latest
+        });
+        assert!(cache.needs_refresh());
+        assert!(cache.get(&rpc).is_ok());
+        assert!(cache
+            .get_with_commitment(&rpc, CommitmentConfig::confirmed())
+            .is_ok());
+        assert!(cache
+            .get_with_commitment(&rpc, CommitmentConfig::finalized())
+            .is_err());
+
+        // Too old to sign with, so it has to be refetched
+        cache.store(LatestBlockhash {
+            fetched_at: Instant::now() - Duration::from_secs(2),
+            ..latest
+        });
+        assert!(cache.cached().is_none());
+        assert!(cache.get(&rpc).is_err());
+    }
+}
@@ -1,7 +1,34 @@
 use anyhow::{anyhow, Result};
-use solana_client::rpc_client::RpcClient;
-use solana_sdk::pubkey::Pubkey;
-use std::str::FromStr;
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::{
+    pubsub_client::{AccountSubscription, PubsubClient},
+    rpc_client::RpcClient,
+    rpc_config::RpcAccountInfoConfig,
+};
+use solana_sdk::{
+    account::Account, commitment_config::CommitmentConfig, native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+};
+use spl_token_2022::ID as TOKEN_2022_PROGRAM_ID;
+use tracing::warn;
+
+pub use crate::accounts::BondingCurve;
+pub use crate::pump_idl::get_bonding_curve_pda;
+use crate::accounts::AnchorAccount;
+use crate::amount::{SolAmount, TokenAmount};
+use crate::config::{self, StreamBackend};
+use crate::{fees, geyser};
+use crate::error::{self, BotError};
+
+// Constants
+/// Mint accounts are padded to the token account size before the account type byte
+pub(crate) const TOKEN_2022_EXTENSIONS_OFFSET: usize = 166;
+/// Token-2022 extension types
+const TRANSFER_FEE_CONFIG_EXTENSION: u16 = 1;
+const PERMANENT_DELEGATE_EXTENSION: u16 = 12;
+const TRANSFER_HOOK_EXTENSION: u16 = 14;
+/// Most accounts `getMultipleAccounts` returns at once
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
 
 /// Global state from pump.fun program
 #[derive(Debug, Clone)]
@@ -14,18 +41,6 @@ pub struct Global {
     pub creator_fee_basis_points: u64,
 }
 
-/// Bonding curve state
-#[derive(Debug, Clone)]
-pub struct BondingCurve {
-    pub virtual_token_reserves: u64,
-    pub virtual_sol_reserves: u64,
-    pub real_token_reserves: u64,
-    pub real_sol_reserves: u64,
-    pub token_total_supply: u64,
-    pub complete: bool,
-    pub creator: Pubkey,
-}
-
 impl Default for Global {
     fn default() -> Self {
         // Default pump.fun global values
@@ -53,30 +68,111 @@ pub fn new_bonding_curve(global: &Global) -> BondingCurve {
     }
 }
 
+/// Rounding direction for integer division in quotes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    Floor,
+    Ceil,
+}
+
+/// How each kind of quoted amount is rounded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoundingPolicy {
+    /// Amounts you receive (tokens on buy, SOL on sell)
+    pub outputs: Rounding,
+    /// Amounts you pay (SOL cost of an exact-token buy)
+    pub costs: Rounding,
+    /// Protocol and creator fees
+    pub fees: Rounding,
+}
+
+impl RoundingPolicy {
+    /// Never overstate what you receive, never understate what you pay
+    pub const CONSERVATIVE: RoundingPolicy = RoundingPolicy {
+        outputs: Rounding::Floor,
+        costs: Rounding::Ceil,
+        fees: Rounding::Ceil,
+    };
+}
+
+impl Default for RoundingPolicy {
+    fn default() -> Self {
+        Self::CONSERVATIVE
+    }
+}
+
+/// Result of a buy or sell quote
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quote {
+    /// Lamports in for a buy, tokens in for a sell
+    pub input_amount: u64,
+    /// Tokens out for a buy, lamports out (after fees) for a sell
+    pub output_amount: u64,
+    /// Total fee in lamports
+    pub fee: u64,
+    /// Tokens withheld by a Token-2022 transfer fee
+    pub transfer_fee: u64,
+    /// Rounding applied to produce this quote
+    pub rounding: RoundingPolicy,
+}
+
 /// Ceiling division: ceil(a / b)
 fn ceil_div(a: u128, b: u128) -> u128 {
-    (a + b - 1) / b
+    a.div_ceil(b)
+}
+
+/// Divide with the requested rounding
+fn div_round(a: u128, b: u128, rounding: Rounding) -> u128 {
+    match rounding {
+        Rounding::Floor => a / b,
+        Rounding::Ceil => ceil_div(a, b),
+    }
 }
 
 /// Compute fee based on basis points (1 basis point = 0.01%)
-fn compute_fee(amount: u64, fee_basis_points: u64) -> u64 {
-    ceil_div(amount as u128 * fee_basis_points as u128, 10_000) as u64
+fn compute_fee(amount: u64, fee_basis_points: u64, rounding: Rounding) -> u64 {
+    div_round(amount as u128 * fee_basis_points as u128, 10_000, rounding) as u64
 }
 
 /// Get total fee (platform fee + creator fee if applicable)
-fn get_fee(
+pub fn get_fee(
+    global: &Global,
+    bonding_curve: &BondingCurve,
+    amount: u64,
+    is_new_bonding_curve: bool,
+) -> u64 {
+    get_fee_with_rounding(
+        global,
+        bonding_curve,
+        amount,
+        is_new_bonding_curve,
+        RoundingPolicy::default().fees,
+    )
+}
+
+/// Get total fee with an explicit rounding direction
+pub fn get_fee_with_rounding(
     global: &Global,
     bonding_curve: &BondingCurve,
     amount: u64,
     is_new_bonding_curve: bool,
+    rounding: Rounding,
 ) -> u64 {
-    let platform_fee = compute_fee(amount, global.fee_basis_points);
+    let platform_fee = compute_fee(amount, global.fee_basis_points, rounding);
     let creator_fee = if is_new_bonding_curve || bonding_curve.creator != Pubkey::default() {
-        compute_fee(amount, global.creator_fee_basis_points)
+        compute_fee(amount, global.creator_fee_basis_points, rounding)
     } else {
         0
     };
-    platform_fee + creator_fee
+    platform_fee.saturating_add(creator_fee)
+}
+
+/// Resolve the curve to quote against (a fresh curve if none is given)
+fn resolve_curve(global: &Global, bonding_curve: Option<&BondingCurve>) -> (BondingCurve, bool) {
+    match bonding_curve {
+        Some(bc) => (bc.clone(), false),
+        None => (new_bonding_curve(global), true),
+    }
 }
 
 /// Calculate how many tokens you receive for a given SOL amount (BUY)
@@ -86,34 +182,53 @@ pub fn get_tokens_for_sol(
     bonding_curve: Option<&BondingCurve>,
     sol_amount: u64, // in lamports
 ) -> u64 {
+    quote_buy_with_rounding(global, bonding_curve, sol_amount, RoundingPolicy::default())
+        .output_amount
+}
+
+/// Buy quote (SOL -> tokens) with an explicit rounding policy
+pub fn quote_buy_with_rounding(
+    global: &Global,
+    bonding_curve: Option<&BondingCurve>,
+    sol_amount: u64, // in lamports
+    rounding: RoundingPolicy,
+) -> Quote {
+    let mut quote = Quote {
+        input_amount: sol_amount,
+        output_amount: 0,
+        fee: 0,
+        transfer_fee: 0,
+        rounding,
+    };
     if sol_amount == 0 {
-        return 0;
+        return quote;
     }
 
-    let (curve, is_new) = match bonding_curve {
-        Some(bc) => (bc.clone(), false),
-        None => (new_bonding_curve(global), true),
-    };
+    let (curve, is_new) = resolve_curve(global, bonding_curve);
 
     // Migrated bonding curve check
     if curve.virtual_token_reserves == 0 {
-        return 0;
+        return quote;
     }
 
     // Deduct fees from input SOL
-    let fee = get_fee(global, &curve, sol_amount, is_new);
-    let sol_after_fee = sol_amount.saturating_sub(fee);
+    quote.fee = get_fee_with_rounding(global, &curve, sol_amount, is_new, rounding.fees);
+    let sol_after_fee = sol_amount.saturating_sub(quote.fee);
 
     if sol_after_fee == 0 {
-        return 0;
+        return quote;
     }
 
     // Constant product formula: tokens_out = (virtual_token_reserves * sol_in) / (virtual_sol_reserves + sol_in)
-    let tokens_out = (curve.virtual_token_reserves as u128 * sol_after_fee as u128)
-        / (curve.virtual_sol_reserves as u128 + sol_after_fee as u128);
+    let tokens_out = div_round(
+        curve.virtual_token_reserves as u128 * sol_after_fee as u128,
+        curve.virtual_sol_reserves as u128 + sol_after_fee as u128,
+        rounding.outputs,
+    );
 
     // Cap at real token reserves
-    std::cmp::min(tokens_out as u64, curve.real_token_reserves)
+    quote.output_amount = std::cmp::min(tokens_out as u64, curve.real_token_reserves);
+    quote
 }
 
 /// Calculate SOL cost for buying a specific token amount (BUY - inverse)
@@ -123,37 +238,74 @@ pub fn get_sol_for_tokens(
     bonding_curve: Option<&BondingCurve>,
     token_amount: u64,
 ) -> u64 {
+    get_sol_for_tokens_with_rounding(
+        global,
+        bonding_curve,
+        token_amount,
+        RoundingPolicy::default(),
+    )
+}
+
+/// SOL cost for an exact token amount with an explicit rounding policy.
+/// `Rounding::Ceil` uses floor + 1 like the pump SDK, which never falls below the exact cost.
+pub fn get_sol_for_tokens_with_rounding(
+    global: &Global,
+    bonding_curve: Option<&BondingCurve>,
+    token_amount: u64,
+    rounding: RoundingPolicy,
+) -> u64 {
+    quote_sol_for_tokens_with_rounding(global, bonding_curve, token_amount, rounding).input_amount
+}
+
+/// Buy quote for an exact token amount (tokens -> SOL cost) with an explicit rounding
+/// policy. `input_amount` is the SOL needed including `fee`, charged on the cost before it.
+pub fn quote_sol_for_tokens_with_rounding(
+    global: &Global,
+    bonding_curve: Option<&BondingCurve>,
+    token_amount: u64,
+    rounding: RoundingPolicy,
+) -> Quote {
+    let mut quote = Quote {
+        input_amount: 0,
+        output_amount: 0,
+        fee: 0,
+        transfer_fee: 0,
+        rounding,
+    };
     if token_amount == 0 {
-        return 0;
+        return quote;
     }
 
-    let (curve, is_new) = match bonding_curve {
-        Some(bc) => (bc.clone(), false),
-        None => (new_bonding_curve(global), true),
-    };
+    let (curve, is_new) = resolve_curve(global, bonding_curve);
 
     // Migrated bonding curve check
     if curve.virtual_token_reserves == 0 {
-        return 0;
+        return quote;
     }
 
     // Cap token amount at available reserves
     let min_amount = std::cmp::min(token_amount, curve.real_token_reserves);
+    quote.output_amount = min_amount;
 
     // Constant product formula (inverse): sol_cost = (virtual_sol_reserves * tokens) / (virtual_token_reserves - tokens) + 1
     let denominator = curve.virtual_token_reserves.saturating_sub(min_amount);
     if denominator == 0 {
-        return u64::MAX; // Would require all tokens
+        quote.input_amount = u64::MAX; // Would require all tokens
+        return quote;
     }
 
-    let sol_cost = (curve.virtual_sol_reserves as u128 * min_amount as u128)
-        / denominator as u128
-        + 1;
+    let sol_cost = (curve.virtual_sol_reserves as u128 * min_amount as u128) / denominator as u128;
+    let sol_cost = match rounding.costs {
+        Rounding::Floor => sol_cost,
+        Rounding::Ceil => sol_cost + 1,
+    };
 
-    let sol_cost = sol_cost as u64;
+    let sol_cost = u64::try_from(sol_cost).unwrap_or(u64::MAX);
 
     // Add fees
-    sol_cost + get_fee(global, &curve, sol_cost, is_new)
+    quote.fee = get_fee_with_rounding(global, &curve, sol_cost, is_new, rounding.fees);
+    quote.input_amount = sol_cost.saturating_add(quote.fee);
+    quote
 }
 
 /// Calculate how much SOL you receive for selling tokens (SELL)
@@ -163,118 +315,701 @@ pub fn get_sol_from_tokens(
     bonding_curve: Option<&BondingCurve>,
     token_amount: u64,
 ) -> u64 {
+    quote_sell_with_rounding(
+        global,
+        bonding_curve,
+        token_amount,
+        RoundingPolicy::default(),
+    )
+    .output_amount
+}
+
+/// Sell quote (tokens -> SOL) with an explicit rounding policy
+pub fn quote_sell_with_rounding(
+    global: &Global,
+    bonding_curve: Option<&BondingCurve>,
+    token_amount: u64,
+    rounding: RoundingPolicy,
+) -> Quote {
+    let mut quote = Quote {
+        input_amount: token_amount,
+        output_amount: 0,
+        fee: 0,
+        transfer_fee: 0,
+        rounding,
+    };
     if token_amount == 0 {
-        return 0;
+        return quote;
     }
 
-    let (curve, is_new) = match bonding_curve {
-        Some(bc) => (bc.clone(), false),
-        None => (new_bonding_curve(global), true),
-    };
+    let (curve, is_new) = resolve_curve(global, bonding_curve);
 
     // Migrated bonding curve check
     if curve.virtual_token_reserves == 0 || curve.virtual_sol_reserves == 0 {
-        return 0;
+        return quote;
     }
 
     // Constant product formula: sol_out = (virtual_sol_reserves * tokens_in) / (virtual_token_reserves + tokens_in)
-    let sol_out = (curve.virtual_sol_reserves as u128 * token_amount as u128)
-        / (curve.virtual_token_reserves as u128 + token_amount as u128);
+    let sol_out = div_round(
+        curve.virtual_sol_reserves as u128 * token_amount as u128,
+        curve.virtual_token_reserves as u128 + token_amount as u128,
+        rounding.outputs,
+    );
 
     let sol_out = sol_out as u64;
 
     // Deduct fees
-    let fee = get_fee(global, &curve, sol_out, is_new);
-    sol_out.saturating_sub(fee)
+    quote.fee = get_fee_with_rounding(global, &curve, sol_out, is_new, rounding.fees);
+    quote.output_amount = sol_out.saturating_sub(quote.fee);
+    quote
+}
+
+/// `get_tokens_for_sol` in typed amounts: pump.fun tokens received for `sol`
+pub fn tokens_for_sol(
+    global: &Global,
+    bonding_curve: Option<&BondingCurve>,
+    sol: SolAmount,
+) -> TokenAmount {
+    TokenAmount::pump(get_tokens_for_sol(global, bonding_curve, sol.lamports()))
+}
+
+/// `get_sol_from_tokens` in typed amounts: SOL received for selling `tokens`
+pub fn sol_from_tokens(
+    global: &Global,
+    bonding_curve: Option<&BondingCurve>,
+    tokens: TokenAmount,
+) -> SolAmount {
+    SolAmount::from_lamports(get_sol_from_tokens(global, bonding_curve, tokens.raw()))
+}
+
+/// Inverse of `get_sol_from_tokens`: smallest token amount (at most `max_tokens`)
+/// whose sale nets at least `sol_amount`. None if `max_tokens` can't reach it.
+pub fn get_tokens_for_sol_output(
+    global: &Global,
+    bonding_curve: Option<&BondingCurve>,
+    sol_amount: u64,
+    max_tokens: u64,
+) -> Option<u64> {
+    if get_sol_from_tokens(global, bonding_curve, max_tokens) < sol_amount {
+        return None;
+    }
+
+    // Sell output is monotonic in tokens, so binary search for the boundary
+    let (mut low, mut high) = (0u64, max_tokens);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if get_sol_from_tokens(global, bonding_curve, mid) >= sol_amount {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    Some(low)
+}
+
+/// Marginal price of the curve in lamports per raw token unit
+pub fn spot_price_lamports_per_token(bonding_curve: &BondingCurve) -> f64 {
+    if bonding_curve.virtual_token_reserves == 0 {
+        return 0.0;
+    }
+    bonding_curve.virtual_sol_reserves as f64 / bonding_curve.virtual_token_reserves as f64
+}
+
+/// Percent of the initial real token reserves sold so far. The curve migrates at 100.
+pub fn graduation_progress(bonding_curve: &BondingCurve) -> f64 {
+    if bonding_curve.complete {
+        return 100.0;
+    }
+    let initial = Global::default().initial_real_token_reserves;
+    let sold = initial.saturating_sub(bonding_curve.real_token_reserves);
+    sold as f64 / initial as f64 * 100.0
+}
+
+/// Fully diluted market cap in SOL at the spot price
+pub fn market_cap_sol(bonding_curve: &BondingCurve) -> f64 {
+    fees::market_cap_lamports(bonding_curve) as f64 / LAMPORTS_PER_SOL as f64
+}
+
+/// Fully diluted market cap in USD given the SOL price
+pub fn market_cap_usd(bonding_curve: &BondingCurve, sol_price_usd: f64) -> f64 {
+    market_cap_sol(bonding_curve) * sol_price_usd
+}
+
+/// Raw tokens making up `pct` percent of the total supply
+pub fn tokens_for_supply_pct(bonding_curve: &BondingCurve, pct: f64) -> u64 {
+    (bonding_curve.token_total_supply as f64 * pct / 100.0) as u64
+}
+
+/// Tokens to buy for the market cap to reach `target_sol`, capped at the tokens left on
+/// the curve. 0 when the market cap is already there.
+pub fn tokens_to_market_cap(bonding_curve: &BondingCurve, target_sol: f64) -> u64 {
+    if bonding_curve.virtual_token_reserves == 0 || target_sol <= market_cap_sol(bonding_curve) {
+        return 0;
+    }
+    // The market cap is k * supply / virtual_token_reserves^2 with k the constant product,
+    // so solve for the virtual token reserves that price the supply at the target
+    let k = bonding_curve.virtual_sol_reserves as f64 * bonding_curve.virtual_token_reserves as f64;
+    let target_lamports = target_sol * LAMPORTS_PER_SOL as f64;
+    let reserves = (k * bonding_curve.token_total_supply as f64 / target_lamports).sqrt().floor();
+    let tokens = bonding_curve.virtual_token_reserves.saturating_sub(reserves as u64);
+    tokens.min(bonding_curve.real_token_reserves)
+}
+
+/// SOL (fees included) that buying `pct` percent of the supply costs
+pub fn sol_for_supply_pct(global: &Global, bonding_curve: &BondingCurve, pct: f64) -> u64 {
+    get_sol_for_tokens(global, Some(bonding_curve), tokens_for_supply_pct(bonding_curve, pct))
+}
+
+/// SOL (fees included) that lifts the market cap to `target_sol`
+pub fn sol_to_market_cap(global: &Global, bonding_curve: &BondingCurve, target_sol: f64) -> u64 {
+    get_sol_for_tokens(global, Some(bonding_curve), tokens_to_market_cap(bonding_curve, target_sol))
+}
+
+/// Curve state after a buy spending `sol_amount` lamports (fees included).
+/// Only the SOL left after fees enters the reserves.
+pub fn curve_after_buy(
+    global: &Global,
+    bonding_curve: &BondingCurve,
+    sol_amount: u64,
+) -> BondingCurve {
+    let quote = quote_buy_with_rounding(
+        global,
+        Some(bonding_curve),
+        sol_amount,
+        RoundingPolicy::default(),
+    );
+    let tokens = quote.output_amount;
+    let mut after = bonding_curve.clone();
+    if tokens == 0 {
+        return after;
+    }
+
+    // A buy capped at the real reserves only pays for the tokens it gets. Buying out the
+    // virtual reserves only happens on a curve without SOL, where it costs nothing.
+    let cost = ceil_div(
+        bonding_curve.virtual_sol_reserves as u128 * tokens as u128,
+        (bonding_curve.virtual_token_reserves - tokens).max(1) as u128,
+    );
+    let sol_in = sol_amount
+        .saturating_sub(quote.fee)
+        .min(u64::try_from(cost).unwrap_or(u64::MAX));
+
+    after.virtual_sol_reserves = after.virtual_sol_reserves.saturating_add(sol_in);
+    after.real_sol_reserves = after.real_sol_reserves.saturating_add(sol_in);
+    after.virtual_token_reserves -= tokens;
+    after.real_token_reserves -= tokens;
+    after.complete = after.real_token_reserves == 0;
+    after
 }
 
-/// Parse bonding curve data from on-chain account
-/// Layout: 8 (discriminator) + 8 (virtual_token_reserves) + 8 (virtual_sol_reserves) +
-///         8 (real_token_reserves) + 8 (real_sol_reserves) + 8 (token_total_supply) +
-///         1 (complete) + 32 (creator)
-pub fn parse_bonding_curve(data: &[u8]) -> Result<BondingCurve> {
-    if data.len() < 81 {
-        return Err(anyhow!("Bonding curve data too short: {} bytes", data.len()));
+/// Curve state after selling `token_amount` tokens. Fees are paid out of the
+/// SOL leaving the curve, so the reserves drop by the gross amount.
+pub fn curve_after_sell(
+    global: &Global,
+    bonding_curve: &BondingCurve,
+    token_amount: u64,
+) -> BondingCurve {
+    let quote = quote_sell_with_rounding(
+        global,
+        Some(bonding_curve),
+        token_amount,
+        RoundingPolicy::default(),
+    );
+    let sol_out = quote.output_amount + quote.fee;
+    let mut after = bonding_curve.clone();
+    if sol_out == 0 {
+        return after;
     }
 
-    let virtual_token_reserves = u64::from_le_bytes(data[8..16].try_into().unwrap());
-    let virtual_sol_reserves = u64::from_le_bytes(data[16..24].try_into().unwrap());
-    let real_token_reserves = u64::from_le_bytes(data[24..32].try_into().unwrap());
-    let real_sol_reserves = u64::from_le_bytes(data[32..40].try_into().unwrap());
-    let token_total_supply = u64::from_le_bytes(data[40..48].try_into().unwrap());
-    let complete = data[48] != 0;
-    let creator = Pubkey::new_from_array(data[49..81].try_into().unwrap());
+    after.virtual_sol_reserves -= sol_out;
+    after.real_sol_reserves = after.real_sol_reserves.saturating_sub(sol_out);
+    after.virtual_token_reserves = after.virtual_token_reserves.saturating_add(token_amount);
+    after.real_token_reserves = after.real_token_reserves.saturating_add(token_amount);
+    after
+}
+
+/// How far the average price of a `sol_amount` buy sits above the spot price, in
+/// basis points. Fees are excluded; compare spot prices before and after
+/// `curve_after_buy` for how far the order moves the market.
+pub fn price_impact_bps(global: &Global, bonding_curve: &BondingCurve, sol_amount: u64) -> u64 {
+    if sol_amount == 0
+        || bonding_curve.virtual_token_reserves == 0
+        || bonding_curve.virtual_sol_reserves == 0
+    {
+        return 0;
+    }
+    let after = curve_after_buy(global, bonding_curve, sol_amount);
+    let tokens = bonding_curve.virtual_token_reserves - after.virtual_token_reserves;
+    if tokens == 0 {
+        return 10_000;
+    }
+    let sol_in = after.virtual_sol_reserves - bonding_curve.virtual_sol_reserves;
 
-    Ok(BondingCurve {
-        virtual_token_reserves,
-        virtual_sol_reserves,
-        real_token_reserves,
-        real_sol_reserves,
-        token_total_supply,
-        complete,
-        creator,
-    })
+    // (sol_in / tokens) / (virtual_sol / virtual_token) - 1, in basis points
+    let average =
+        (sol_in as u128 * bonding_curve.virtual_token_reserves as u128).saturating_mul(10_000);
+    let spot = tokens as u128 * bonding_curve.virtual_sol_reserves as u128;
+    u64::try_from((average / spot).saturating_sub(10_000)).unwrap_or(u64::MAX)
 }
 
-lazy_static::lazy_static! {
-    static ref PUMP_PROGRAM_ID: Pubkey = Pubkey::from_str("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P").unwrap();
+/// How far the average price of selling `token_amount` tokens sits below the spot
+/// price, in basis points. Fees are excluded, as for `price_impact_bps`.
+pub fn sell_price_impact_bps(
+    global: &Global,
+    bonding_curve: &BondingCurve,
+    token_amount: u64,
+) -> u64 {
+    if token_amount == 0
+        || bonding_curve.virtual_token_reserves == 0
+        || bonding_curve.virtual_sol_reserves == 0
+    {
+        return 0;
+    }
+    let after = curve_after_sell(global, bonding_curve, token_amount);
+    let sol_out = bonding_curve.virtual_sol_reserves - after.virtual_sol_reserves;
+
+    // 1 - (sol_out / tokens) / (virtual_sol / virtual_token), in basis points
+    let average =
+        (sol_out as u128 * bonding_curve.virtual_token_reserves as u128).saturating_mul(10_000);
+    let spot = token_amount as u128 * bonding_curve.virtual_sol_reserves as u128;
+    10_000u64.saturating_sub(u64::try_from(average / spot).unwrap_or(u64::MAX))
 }
 
-/// Derive the bonding curve PDA for a mint
-pub fn get_bonding_curve_pda(mint: &Pubkey) -> (Pubkey, u8) {
-    Pubkey::find_program_address(&[b"bonding-curve", mint.as_ref()], &PUMP_PROGRAM_ID)
+/// What selling a position on its bonding curve right now would return
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExitLiquidity {
+    pub position_tokens: u64,
+    /// Most of the position one sell can take within the impact bound
+    pub sellable_tokens: u64,
+    /// Lamports that sell nets, fees deducted
+    pub sellable_lamports: u64,
+    /// Lamports selling the whole position at once nets, fees deducted
+    pub liquidation_lamports: u64,
+    /// How far the average price of selling the whole position sits below spot
+    pub liquidation_impact_bps: u64,
+}
+
+impl ExitLiquidity {
+    /// Whether the whole position sells within the impact bound
+    pub fn fully_sellable(&self) -> bool {
+        self.sellable_tokens == self.position_tokens
+    }
+}
+
+/// Estimate exiting `position_tokens` on `bonding_curve`: the largest sell whose average
+/// price stays within `max_impact_bps` of spot, and what liquidating everything at once
+/// returns. A completed curve has no liquidity left; its PumpSwap pool isn't estimated.
+pub fn exit_liquidity(
+    global: &Global,
+    bonding_curve: &BondingCurve,
+    position_tokens: u64,
+    max_impact_bps: u64,
+) -> ExitLiquidity {
+    let within = |tokens| sell_price_impact_bps(global, bonding_curve, tokens) <= max_impact_bps;
+    let liquidation_impact_bps = sell_price_impact_bps(global, bonding_curve, position_tokens);
+    let sellable_tokens = if liquidation_impact_bps <= max_impact_bps {
+        position_tokens
+    } else {
+        // Impact grows with the sell size, so binary search for the largest one within it
+        let (mut low, mut high) = (0u64, position_tokens);
+        while low < high {
+            let mid = low + (high - low).div_ceil(2);
+            if within(mid) {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+        low
+    };
+    ExitLiquidity {
+        position_tokens,
+        sellable_tokens,
+        sellable_lamports: get_sol_from_tokens(global, Some(bonding_curve), sellable_tokens),
+        liquidation_lamports: get_sol_from_tokens(global, Some(bonding_curve), position_tokens),
+        liquidation_impact_bps,
+    }
+}
+
+/// `exit_liquidity` for many `(mint, token_amount)` holdings, fetching every curve in one
+/// round trip. None for mints whose curve has completed. Results are in the order of
+/// `holdings`.
+pub fn fetch_exit_liquidity(
+    rpc: &RpcClient,
+    holdings: &[(Pubkey, u64)],
+    max_impact_bps: u64,
+) -> Vec<error::Result<Option<ExitLiquidity>>> {
+    let mints: Vec<Pubkey> = holdings.iter().map(|(mint, _)| *mint).collect();
+    holdings
+        .iter()
+        .zip(fetch_bonding_curves(rpc, &mints))
+        .map(|((_, token_amount), curve)| {
+            let curve = curve?;
+            if curve.complete {
+                return Ok(None);
+            }
+            let global = fees::current_global(rpc, &curve);
+            Ok(Some(exit_liquidity(
+                &global,
+                &curve,
+                *token_amount,
+                max_impact_bps,
+            )))
+        })
+        .collect()
+}
+
+/// Fetch an account, telling a missing account apart from an RPC failure
+pub fn fetch_account(rpc: &RpcClient, address: &Pubkey) -> error::Result<Account> {
+    rpc.get_account_with_commitment(address, rpc.commitment())?
+        .value
+        .ok_or(BotError::AccountNotFound(*address))
 }
 
 /// Fetch and parse bonding curve from RPC
-pub fn fetch_bonding_curve(rpc: &RpcClient, mint: &Pubkey) -> Result<BondingCurve> {
+pub fn fetch_bonding_curve(rpc: &RpcClient, mint: &Pubkey) -> error::Result<BondingCurve> {
     let (bonding_curve_pda, _) = get_bonding_curve_pda(mint);
-    let account = rpc
-        .get_account(&bonding_curve_pda)
-        .map_err(|e| anyhow!("Failed to fetch bonding curve: {}", e))?;
-    parse_bonding_curve(&account.data)
+    let account = fetch_account(rpc, &bonding_curve_pda)?;
+    Ok(BondingCurve::try_from_account_data(&account.data)?)
+}
+
+/// Fetch many accounts with one `getMultipleAccounts` call per 100 addresses.
+/// Results are in the order of `addresses`; a failed call fails every address it covered.
+pub fn fetch_accounts(rpc: &RpcClient, addresses: &[Pubkey]) -> Vec<error::Result<Account>> {
+    let mut accounts = Vec::with_capacity(addresses.len());
+    for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        match rpc.get_multiple_accounts_with_commitment(chunk, rpc.commitment()) {
+            Ok(response) => accounts.extend(
+                chunk
+                    .iter()
+                    .zip(response.value)
+                    .map(|(address, account)| account.ok_or(BotError::AccountNotFound(*address))),
+            ),
+            Err(e) => {
+                let error = e.to_string();
+                accounts.extend(chunk.iter().map(|address| {
+                    Err(anyhow!("Failed to fetch {}: {}", address, error).into())
+                }));
+            }
+        }
+    }
+    accounts
+}
+
+/// Fetch and parse the bonding curves of `mints` in one round trip (per 100 mints),
+/// in the order of `mints`
+pub fn fetch_bonding_curves(
+    rpc: &RpcClient,
+    mints: &[Pubkey],
+) -> Vec<error::Result<BondingCurve>> {
+    let curves: Vec<Pubkey> = mints.iter().map(|mint| get_bonding_curve_pda(mint).0).collect();
+    fetch_accounts(rpc, &curves)
+        .into_iter()
+        .map(|account| Ok(BondingCurve::try_from_account_data(&account?.data)?))
+        .collect()
+}
+
+enum CurveSource {
+    WebSocket(AccountSubscription),
+    Geyser(geyser::AccountUpdates),
+}
+
+/// A Token-2022 transfer fee for one epoch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferFeeRate {
+    pub basis_points: u16,
+    /// Cap on the fee of a single transfer, in raw tokens
+    pub maximum_fee: u64,
+}
+
+impl TransferFeeRate {
+    /// Tokens withheld from a transfer of `amount`, rounded up like the token program
+    pub fn fee(&self, amount: u64) -> u64 {
+        let fee = ceil_div(amount as u128 * self.basis_points as u128, 10_000);
+        fee.min(self.maximum_fee as u128) as u64
+    }
+
+    /// Smallest transfer that still delivers `net` tokens once the fee is withheld
+    pub fn gross_amount(&self, net: u64) -> u64 {
+        let uncapped = match 10_000u128.checked_sub(self.basis_points as u128) {
+            Some(kept) if kept > 0 => ceil_div(net as u128 * 10_000, kept),
+            _ => u128::MAX,
+        };
+        // Past the cap the fee stops growing
+        uncapped
+            .min(net as u128 + self.maximum_fee as u128)
+            .min(u64::MAX as u128) as u64
+    }
+}
+
+/// Token-2022 extensions that change what a trade delivers or who controls the tokens
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MintExtensions {
+    pub transfer_fee: Option<TransferFeeRate>,
+    /// Can move or burn tokens out of any holder's account
+    pub permanent_delegate: Option<Pubkey>,
+    /// Program invoked on every transfer, which can block sells
+    pub transfer_hook: Option<Pubkey>,
+}
+
+impl MintExtensions {
+    /// Tokens withheld when `amount` is transferred
+    pub fn transfer_fee(&self, amount: u64) -> u64 {
+        self.transfer_fee.map_or(0, |rate| rate.fee(amount))
+    }
+
+    /// Tokens to transfer so that `net` arrive after the transfer fee
+    pub fn gross_amount(&self, net: u64) -> u64 {
+        self.transfer_fee.map_or(net, |rate| rate.gross_amount(net))
+    }
+
+    /// Names of the extensions that make the mint unsafe to buy
+    pub fn dangerous(&self) -> Vec<&'static str> {
+        let mut dangerous = Vec::new();
+        if self.permanent_delegate.is_some() {
+            dangerous.push("permanent delegate");
+        }
+        if self.transfer_hook.is_some() {
+            dangerous.push("transfer hook");
+        }
+        dangerous
+    }
+
+    /// Refuse mints with dangerous extensions unless `trading.allow_dangerous_extensions`
+    pub fn check(&self, mint: &Pubkey) -> error::Result<()> {
+        let dangerous = self.dangerous();
+        if dangerous.is_empty() || config::get().trading.allow_dangerous_extensions {
+            return Ok(());
+        }
+        Err(BotError::DangerousMint {
+            mint: *mint,
+            extensions: dangerous.join(", "),
+        })
+    }
+}
+
+/// Pubkey at `offset`, or None for the all-zero "unset" key
+fn read_optional_pubkey(value: &[u8], offset: usize) -> Option<Pubkey> {
+    let bytes: [u8; 32] = value.get(offset..offset + 32)?.try_into().ok()?;
+    (bytes != [0; 32]).then(|| Pubkey::new_from_array(bytes))
+}
+
+/// Fee for `epoch` from a `TransferFeeConfig` value
+/// Layout: 32 (config authority) + 32 (withdraw authority) + 8 (withheld) + older + newer,
+/// each fee being 8 (epoch) + 8 (maximum_fee) + 2 (basis points)
+fn parse_transfer_fee(value: &[u8], epoch: u64) -> Result<TransferFeeRate> {
+    if value.len() < 108 {
+        return Err(anyhow!("Transfer fee config is truncated"));
+    }
+    let read_u64 = |at: usize| u64::from_le_bytes(value[at..at + 8].try_into().unwrap());
+    let rate = |at: usize| TransferFeeRate {
+        basis_points: u16::from_le_bytes(value[at + 16..at + 18].try_into().unwrap()),
+        maximum_fee: read_u64(at + 8),
+    };
+    // The newer fee takes over from its epoch on
+    Ok(if epoch >= read_u64(90) { rate(90) } else { rate(72) })
+}
+
+/// The `(type, value)` TLV entries of a Token-2022 mint account's extensions, in order
+pub fn mint_extension_entries(data: &[u8]) -> Result<Vec<(u16, &[u8])>> {
+    let mut entries = Vec::new();
+    let mut offset = TOKEN_2022_EXTENSIONS_OFFSET;
+    while offset + 4 <= data.len() {
+        let extension_type = u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap());
+        let len = u16::from_le_bytes(data[offset + 2..offset + 4].try_into().unwrap()) as usize;
+        offset += 4;
+        let value = data
+            .get(offset..offset + len)
+            .ok_or_else(|| anyhow!("Mint extension {} is truncated", extension_type))?;
+        entries.push((extension_type, value));
+        offset += len;
+    }
+    Ok(entries)
+}
+
+/// Parse the extensions of a Token-2022 mint account, with the transfer fee of `epoch`
+pub fn parse_mint_extensions(data: &[u8], epoch: u64) -> Result<MintExtensions> {
+    let mut extensions = MintExtensions::default();
+    for (extension_type, value) in mint_extension_entries(data)? {
+        match extension_type {
+            TRANSFER_FEE_CONFIG_EXTENSION => {
+                extensions.transfer_fee = Some(parse_transfer_fee(value, epoch)?)
+            }
+            PERMANENT_DELEGATE_EXTENSION => {
+                extensions.permanent_delegate = read_optional_pubkey(value, 0)
+            }
+            // 32 (authority) + 32 (program id)
+            TRANSFER_HOOK_EXTENSION => extensions.transfer_hook = read_optional_pubkey(value, 32),
+            _ => {}
+        }
+    }
+    Ok(extensions)
+}
+
+/// Extensions of an already fetched mint account. SPL Token mints have none.
+pub fn mint_extensions(rpc: &RpcClient, mint_account: &Account) -> error::Result<MintExtensions> {
+    if mint_account.owner != TOKEN_2022_PROGRAM_ID {
+        return Ok(MintExtensions::default());
+    }
+    let extensions = parse_mint_extensions(&mint_account.data, 0)?;
+    if extensions.transfer_fee.is_none() {
+        return Ok(extensions);
+    }
+    // The epoch only matters for picking between the older and newer transfer fee
+    let epoch = rpc.get_epoch_info()?.epoch;
+    Ok(parse_mint_extensions(&mint_account.data, epoch)?)
+}
+
+/// Fetch a mint and parse its Token-2022 extensions
+pub fn fetch_mint_extensions(rpc: &RpcClient, mint: &Pubkey) -> error::Result<MintExtensions> {
+    mint_extensions(rpc, &fetch_account(rpc, mint)?)
+}
+
+/// Live bonding curve updates from an `accountSubscribe` WebSocket or a Geyser
+/// account stream (`stream.backend`). Iterating blocks until the next update; it
+/// ends when the stream closes. Dropping the stream unsubscribes.
+pub struct BondingCurveStream {
+    source: CurveSource,
+}
+
+impl Iterator for BondingCurveStream {
+    type Item = BondingCurve;
+
+    fn next(&mut self) -> Option<BondingCurve> {
+        loop {
+            let curve = match &mut self.source {
+                CurveSource::WebSocket((_, receiver)) => {
+                    parse_ui_bonding_curve(&receiver.recv().ok()?.value)
+                }
+                CurveSource::Geyser(updates) => {
+                    BondingCurve::try_from_account_data(&updates.next()?)
+                }
+            };
+            match curve {
+                Ok(curve) => return Some(curve),
+                Err(e) => warn!(error = %e, "Skipping bonding curve update"),
+            }
+        }
+    }
+}
+
+fn parse_ui_bonding_curve(account: &UiAccount) -> Result<BondingCurve> {
+    let data = account
+        .data
+        .decode()
+        .ok_or_else(|| anyhow!("Bonding curve update is not base64 encoded"))?;
+    BondingCurve::try_from_account_data(&data)
+}
+
+/// Subscribe to the bonding curve PDA of `mint` over `rpc.ws_url` or `stream.geyser_url`
+pub fn subscribe_bonding_curve(mint: &Pubkey) -> error::Result<BondingCurveStream> {
+    let (bonding_curve_pda, _) = get_bonding_curve_pda(mint);
+    let config = config::get();
+    let source = match config.stream.backend {
+        StreamBackend::WebSocket => PubsubClient::account_subscribe(
+            &config.rpc.ws_url,
+            &bonding_curve_pda,
+            Some(RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(CommitmentConfig::confirmed()),
+                ..RpcAccountInfoConfig::default()
+            }),
+        )
+        .map(CurveSource::WebSocket)
+        .map_err(|e| anyhow!("{}", e)),
+        StreamBackend::Geyser => {
+            geyser::subscribe_account(&bonding_curve_pda).map(CurveSource::Geyser)
+        }
+    }
+    .map_err(|e| anyhow!("Failed to subscribe to bonding curve of {}: {}", mint, e))?;
+    Ok(BondingCurveStream { source })
 }
 
 /// Calculate buy quote: SOL -> Tokens
-/// Returns (tokens_received, sol_after_fees, fee_amount)
-pub fn quote_buy(
+/// Uses the on-chain fee tier and the conservative rounding policy (floor outputs, ceil fees).
+/// A Token-2022 transfer fee is taken out of the tokens received.
+pub fn quote_buy(rpc: &RpcClient, mint: &Pubkey, sol_amount: u64) -> error::Result<Quote> {
+    let bonding_curve = fetch_bonding_curve(rpc, mint)?;
+    let extensions = fetch_mint_extensions(rpc, mint)?;
+    quote_buy_on_curve(rpc, mint, &bonding_curve, &extensions, sol_amount)
+}
+
+/// `quote_buy` against an already fetched curve and mint
+pub fn quote_buy_on_curve(
     rpc: &RpcClient,
     mint: &Pubkey,
+    bonding_curve: &BondingCurve,
+    extensions: &MintExtensions,
     sol_amount: u64,
-) -> Result<(u64, u64, u64)> {
-    let bonding_curve = fetch_bonding_curve(rpc, mint)?;
-    let global = Global::default();
-
-    let tokens = get_tokens_for_sol(&global, Some(&bonding_curve), sol_amount);
-    let fee = get_fee(&global, &bonding_curve, sol_amount, false);
-    let sol_after_fee = sol_amount.saturating_sub(fee);
-
-    Ok((tokens, sol_after_fee, fee))
+) -> error::Result<Quote> {
+    if bonding_curve.complete {
+        return Err(BotError::CurveMigrated { mint: *mint });
+    }
+    let global = fees::current_global(rpc, bonding_curve);
+    let mut quote = quote_buy_with_rounding(
+        &global,
+        Some(bonding_curve),
+        sol_amount,
+        RoundingPolicy::default(),
+    );
+    quote.transfer_fee = extensions.transfer_fee(quote.output_amount);
+    quote.output_amount -= quote.transfer_fee;
+    Ok(quote)
 }
 
 /// Calculate sell quote: Tokens -> SOL
-/// Returns (sol_received, fee_amount)
-pub fn quote_sell(
+/// Uses the on-chain fee tier and the conservative rounding policy (floor outputs, ceil fees).
+/// With a Token-2022 transfer fee, only the tokens that reach the curve are priced.
+pub fn quote_sell(rpc: &RpcClient, mint: &Pubkey, token_amount: u64) -> error::Result<Quote> {
+    let bonding_curve = fetch_bonding_curve(rpc, mint)?;
+    let extensions = fetch_mint_extensions(rpc, mint)?;
+    quote_sell_on_curve(rpc, mint, &bonding_curve, &extensions, token_amount)
+}
+
+/// `quote_sell` against an already fetched curve and mint
+pub fn quote_sell_on_curve(
     rpc: &RpcClient,
     mint: &Pubkey,
+    bonding_curve: &BondingCurve,
+    extensions: &MintExtensions,
     token_amount: u64,
-) -> Result<(u64, u64)> {
-    let bonding_curve = fetch_bonding_curve(rpc, mint)?;
-    let global = Global::default();
-
-    // Calculate gross SOL (before fees)
-    let gross_sol = (bonding_curve.virtual_sol_reserves as u128 * token_amount as u128)
-        / (bonding_curve.virtual_token_reserves as u128 + token_amount as u128);
-    let gross_sol = gross_sol as u64;
-
-    let fee = get_fee(&global, &bonding_curve, gross_sol, false);
-    let net_sol = gross_sol.saturating_sub(fee);
+) -> error::Result<Quote> {
+    if bonding_curve.complete {
+        return Err(BotError::CurveMigrated { mint: *mint });
+    }
+    let global = fees::current_global(rpc, bonding_curve);
+    let transfer_fee = extensions.transfer_fee(token_amount);
+    let mut quote = quote_sell_with_rounding(
+        &global,
+        Some(bonding_curve),
+        token_amount - transfer_fee,
+        RoundingPolicy::default(),
+    );
+    quote.input_amount = token_amount;
+    quote.transfer_fee = transfer_fee;
+    Ok(quote)
+}
 
-    Ok((net_sol, fee))
+/// `quote_sell` for many `(mint, token_amount)` holdings, fetching every curve and mint
+/// in two round trips instead of two per mint. Results are in the order of `holdings`.
+pub fn quote_sells(rpc: &RpcClient, holdings: &[(Pubkey, u64)]) -> Vec<error::Result<Quote>> {
+    let mints: Vec<Pubkey> = holdings.iter().map(|(mint, _)| *mint).collect();
+    let curves = fetch_bonding_curves(rpc, &mints);
+    let mint_accounts = fetch_accounts(rpc, &mints);
+    holdings
+        .iter()
+        .zip(curves.into_iter().zip(mint_accounts))
+        .map(|((mint, token_amount), (curve, mint_account))| {
+            let extensions = mint_extensions(rpc, &mint_account?)?;
+            quote_sell_on_curve(rpc, mint, &curve?, &extensions, *token_amount)
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn test_buy_calculation() {
@@ -315,6 +1050,418 @@ mod tests {
 
         println!("To buy {} tokens, need {} lamports", desired_tokens, sol_needed);
         assert!(sol_needed > 0);
+
+        // The fee is charged on the cost before it, not on the fee-inclusive total
+        let quote = quote_sol_for_tokens_with_rounding(
+            &global,
+            Some(&bonding_curve),
+            desired_tokens,
+            RoundingPolicy::default(),
+        );
+        assert_eq!(quote.input_amount, sol_needed);
+        assert_eq!(
+            quote.fee,
+            get_fee(&global, &bonding_curve, sol_needed - quote.fee, false)
+        );
+        assert!(quote.fee < get_fee(&global, &bonding_curve, sol_needed, false));
     }
-}
 
+    #[test]
+    fn test_conservative_rounding_bounds() {
+        let global = Global::default();
+        let bonding_curve = new_bonding_curve(&global);
+        let optimistic = RoundingPolicy {
+            outputs: Rounding::Ceil,
+            costs: Rounding::Floor,
+            fees: Rounding::Floor,
+        };
+
+        for amount in [1, 999, 123_456_789, 1_000_000_000] {
+            let safe = quote_buy_with_rounding(
+                &global,
+                Some(&bonding_curve),
+                amount,
+                RoundingPolicy::CONSERVATIVE,
+            );
+            let loose = quote_buy_with_rounding(&global, Some(&bonding_curve), amount, optimistic);
+            assert!(safe.output_amount <= loose.output_amount);
+            assert!(safe.fee >= loose.fee);
+
+            let safe = quote_sell_with_rounding(
+                &global,
+                Some(&bonding_curve),
+                amount,
+                RoundingPolicy::CONSERVATIVE,
+            );
+            let loose = quote_sell_with_rounding(&global, Some(&bonding_curve), amount, optimistic);
+            assert!(safe.output_amount <= loose.output_amount);
+
+            let safe_cost = get_sol_for_tokens_with_rounding(
+                &global,
+                Some(&bonding_curve),
+                amount,
+                RoundingPolicy::CONSERVATIVE,
+            );
+            let loose_cost =
+                get_sol_for_tokens_with_rounding(&global, Some(&bonding_curve), amount, optimistic);
+            assert!(safe_cost > loose_cost);
+        }
+
+        // The plain helpers use the conservative policy
+        assert_eq!(
+            get_tokens_for_sol(&global, Some(&bonding_curve), 1_000_000_000),
+            quote_buy_with_rounding(
+                &global,
+                Some(&bonding_curve),
+                1_000_000_000,
+                RoundingPolicy::default()
+            )
+            .output_amount
+        );
+    }
+
+    #[test]
+    fn test_tokens_for_sol_output_inverts_sell() {
+        let global = Global::default();
+        let bonding_curve = new_bonding_curve(&global);
+        let balance = 10_000_000_000_000; // 10M tokens
+
+        let target = 100_000_000; // 0.1 SOL
+        let tokens =
+            get_tokens_for_sol_output(&global, Some(&bonding_curve), target, balance).unwrap();
+        assert!(get_sol_from_tokens(&global, Some(&bonding_curve), tokens) >= target);
+        assert!(get_sol_from_tokens(&global, Some(&bonding_curve), tokens - 1) < target);
+
+        // Unreachable with the given balance
+        assert_eq!(
+            get_tokens_for_sol_output(&global, Some(&bonding_curve), 100_000_000_000, balance),
+            None
+        );
+    }
+
+    #[test]
+    fn test_price_impact_and_post_trade_curve() {
+        let global = Global::default();
+        let bonding_curve = new_bonding_curve(&global);
+        let spot = spot_price_lamports_per_token(&bonding_curve);
+        assert!((spot - 30_000_000_000.0 / 1_073_000_000_000_000.0).abs() < 1e-12);
+
+        // 1 SOL (0.99 after the protocol fee, no creator set) into 30 SOL of virtual reserves
+        let impact = price_impact_bps(&global, &bonding_curve, 1_000_000_000);
+        assert_eq!(impact, 330);
+        assert!(price_impact_bps(&global, &bonding_curve, 10_000_000_000) > impact);
+
+        let after_buy = curve_after_buy(&global, &bonding_curve, 1_000_000_000);
+        let tokens = get_tokens_for_sol(&global, Some(&bonding_curve), 1_000_000_000);
+        assert_eq!(after_buy.virtual_sol_reserves, 30_990_000_000);
+        assert_eq!(after_buy.real_sol_reserves, 990_000_000);
+        assert_eq!(
+            after_buy.real_token_reserves,
+            bonding_curve.real_token_reserves - tokens
+        );
+        assert!(spot_price_lamports_per_token(&after_buy) > spot);
+        let sell_impact = sell_price_impact_bps(&global, &after_buy, tokens);
+        assert!(sell_impact > 0 && sell_impact < 10_000);
+        assert!(sell_price_impact_bps(&global, &after_buy, tokens / 10) < sell_impact);
+
+        let after_sell = curve_after_sell(&global, &after_buy, tokens);
+        assert_eq!(
+            after_sell.virtual_token_reserves,
+            bonding_curve.virtual_token_reserves
+        );
+        assert!(after_sell.virtual_sol_reserves >= bonding_curve.virtual_sol_reserves);
+        assert!(after_sell.virtual_sol_reserves - bonding_curve.virtual_sol_reserves <= 1);
+
+        // Buying out the curve stops at the real reserves and completes it
+        let drained = curve_after_buy(&global, &bonding_curve, 1_000_000_000_000);
+        assert_eq!(drained.real_token_reserves, 0);
+        assert!(drained.complete);
+    }
+
+    #[test]
+    fn test_exit_liquidity_within_impact_bound() {
+        let global = Global::default();
+        let fresh = new_bonding_curve(&global);
+        let position = get_tokens_for_sol(&global, Some(&fresh), 1_000_000_000);
+        let bonding_curve = curve_after_buy(&global, &fresh, 1_000_000_000);
+
+        // Selling a 1 SOL position back into 31 SOL of reserves moves the price ~3%
+        let exit = exit_liquidity(&global, &bonding_curve, position, 100);
+        assert!(!exit.fully_sellable());
+        assert!(exit.liquidation_impact_bps > 100);
+        assert!(sell_price_impact_bps(&global, &bonding_curve, exit.sellable_tokens) <= 100);
+        assert!(
+            sell_price_impact_bps(&global, &bonding_curve, exit.sellable_tokens * 11 / 10) > 100
+        );
+        assert!(exit.sellable_lamports < exit.liquidation_lamports);
+        assert_eq!(
+            exit.liquidation_lamports,
+            get_sol_from_tokens(&global, Some(&bonding_curve), position)
+        );
+
+        let bound = exit.liquidation_impact_bps;
+        let loose = exit_liquidity(&global, &bonding_curve, position, bound);
+        assert!(loose.fully_sellable());
+        assert_eq!(loose.sellable_lamports, loose.liquidation_lamports);
+    }
+
+    #[test]
+    fn test_graduation_progress_and_market_cap() {
+        let global = Global::default();
+        let bonding_curve = new_bonding_curve(&global);
+        assert_eq!(graduation_progress(&bonding_curve), 0.0);
+        assert!((market_cap_sol(&bonding_curve) - 27.958993476).abs() < 1e-9);
+        assert!((market_cap_usd(&bonding_curve, 150.0) - 4_193.849).abs() < 1e-3);
+
+        let half_sold = BondingCurve {
+            real_token_reserves: global.initial_real_token_reserves / 2,
+            ..bonding_curve.clone()
+        };
+        assert!((graduation_progress(&half_sold) - 50.0).abs() < 1e-9);
+
+        let drained = curve_after_buy(&global, &bonding_curve, 1_000_000_000_000);
+        assert_eq!(graduation_progress(&drained), 100.0);
+        assert!(market_cap_sol(&drained) > market_cap_sol(&bonding_curve));
+    }
+
+    #[test]
+    fn test_supply_and_market_cap_targets() {
+        let global = Global::default();
+        let bonding_curve = new_bonding_curve(&global);
+        assert_eq!(tokens_for_supply_pct(&bonding_curve, 1.0), 10_000_000_000_000);
+        assert_eq!(
+            sol_for_supply_pct(&global, &bonding_curve, 1.0),
+            get_sol_for_tokens(&global, Some(&bonding_curve), 10_000_000_000_000)
+        );
+
+        // Taking the tokens off the curve lands the market cap on the target
+        let tokens = tokens_to_market_cap(&bonding_curve, 50.0);
+        let k = bonding_curve.virtual_sol_reserves as u128
+            * bonding_curve.virtual_token_reserves as u128;
+        let virtual_token_reserves = bonding_curve.virtual_token_reserves - tokens;
+        let after = BondingCurve {
+            virtual_token_reserves,
+            virtual_sol_reserves: (k / virtual_token_reserves as u128) as u64,
+            ..bonding_curve.clone()
+        };
+        assert!((market_cap_sol(&after) - 50.0).abs() < 1e-3);
+        assert!(sol_to_market_cap(&global, &bonding_curve, 50.0) > 0);
+
+        assert_eq!(tokens_to_market_cap(&bonding_curve, 10.0), 0);
+        assert_eq!(
+            tokens_to_market_cap(&bonding_curve, 1_000_000.0),
+            bonding_curve.real_token_reserves
+        );
+    }
+
+    #[test]
+    fn test_parse_subscription_update() {
+        let mut curve = new_bonding_curve(&Global::default());
+        curve.complete = true;
+        let mut data = BondingCurve::DISCRIMINATOR.to_vec();
+        data.extend(borsh::to_vec(&curve).unwrap());
+        let account = Account {
+            data,
+            ..Account::default()
+        };
+        let ui = solana_account_decoder::encode_ui_account(
+            &Pubkey::new_unique(),
+            &account,
+            UiAccountEncoding::Base64,
+            None,
+            None,
+        );
+
+        let parsed = parse_ui_bonding_curve(&ui).unwrap();
+        assert_eq!(parsed.virtual_token_reserves, curve.virtual_token_reserves);
+        assert_eq!(parsed.virtual_sol_reserves, curve.virtual_sol_reserves);
+        assert!(parsed.complete);
+    }
+
+    #[test]
+    fn test_parse_mint_extensions() {
+        let delegate = Pubkey::new_unique();
+        let mut data = vec![0u8; TOKEN_2022_EXTENSIONS_OFFSET];
+
+        // Older fee 1% capped at 500, newer 2% from epoch 10 capped at 1_000
+        let mut transfer_fee = vec![0u8; 72];
+        for (epoch, maximum_fee, basis_points) in [(0u64, 500u64, 100u16), (10, 1_000, 200)] {
+            transfer_fee.extend(epoch.to_le_bytes());
+            transfer_fee.extend(maximum_fee.to_le_bytes());
+            transfer_fee.extend(basis_points.to_le_bytes());
+        }
+        for (extension_type, value) in [
+            (TRANSFER_FEE_CONFIG_EXTENSION, transfer_fee),
+            (PERMANENT_DELEGATE_EXTENSION, delegate.to_bytes().to_vec()),
+            (TRANSFER_HOOK_EXTENSION, vec![0u8; 64]),
+        ] {
+            data.extend(extension_type.to_le_bytes());
+            data.extend((value.len() as u16).to_le_bytes());
+            data.extend(value);
+        }
+
+        let extensions = parse_mint_extensions(&data, 5).unwrap();
+        assert_eq!(extensions.transfer_fee(10_000), 100);
+        assert_eq!(extensions.transfer_fee(1_000_000), 500);
+        assert_eq!(extensions.gross_amount(9_900), 10_000);
+        // Capped fee
+        assert_eq!(extensions.gross_amount(1_000_000), 1_000_500);
+        assert_eq!(MintExtensions::default().gross_amount(9_900), 9_900);
+        assert_eq!(extensions.permanent_delegate, Some(delegate));
+        // An unset hook program id means no hook
+        assert_eq!(extensions.transfer_hook, None);
+        assert_eq!(extensions.dangerous(), vec!["permanent delegate"]);
+
+        let newer = parse_mint_extensions(&data, 10).unwrap();
+        assert_eq!(newer.transfer_fee(10_001), 201);
+        assert!(parse_mint_extensions(&data[..data.len() - 1], 5).is_err());
+        assert_eq!(
+            parse_mint_extensions(&data[..TOKEN_2022_EXTENSIONS_OFFSET], 5).unwrap(),
+            MintExtensions::default()
+        );
+    }
+
+    /// Default curve parameters with protocol and creator fees of up to 10% each
+    fn any_global() -> impl Strategy<Value = Global> {
+        (0..=1_000u64, 0..=1_000u64).prop_map(|(fee_basis_points, creator_fee_basis_points)| {
+            Global {
+                fee_basis_points,
+                creator_fee_basis_points,
+                ..Global::default()
+            }
+        })
+    }
+
+    /// A curve some amount of the way to graduation, optionally with a creator
+    fn live_curve() -> impl Strategy<Value = BondingCurve> {
+        let global = Global::default();
+        (0..=global.initial_real_token_reserves, any::<bool>()).prop_map(move |(sold, creator)| {
+            let mut curve = new_bonding_curve(&global);
+            let k = curve.virtual_sol_reserves as u128 * curve.virtual_token_reserves as u128;
+            curve.virtual_token_reserves -= sold;
+            curve.real_token_reserves -= sold;
+            curve.virtual_sol_reserves = ceil_div(k, curve.virtual_token_reserves as u128) as u64;
+            curve.real_sol_reserves =
+                curve.virtual_sol_reserves - global.initial_virtual_sol_reserves;
+            if creator {
+                curve.creator = Pubkey::new_unique();
+            }
+            curve
+        })
+    }
+
+    /// Any reserves at all, including ones the program would never produce
+    fn any_curve() -> impl Strategy<Value = BondingCurve> {
+        (any::<[u64; 5]>(), any::<bool>()).prop_map(|(fields, complete)| BondingCurve {
+            virtual_token_reserves: fields[0],
+            virtual_sol_reserves: fields[1],
+            real_token_reserves: fields[2],
+            real_sol_reserves: fields[3],
+            token_total_supply: fields[4],
+            complete,
+            creator: Pubkey::new_unique(),
+        })
+    }
+
+    /// `amount` times `bps` basis points, rounded down and up
+    fn bps_bounds(amount: u64, bps: u64) -> (u64, u64) {
+        let exact = amount as u128 * bps as u128;
+        ((exact / 10_000) as u64, exact.div_ceil(10_000) as u64)
+    }
+
+    proptest! {
+        #[test]
+        fn prop_buy_then_sell_never_profits(
+            global in any_global(),
+            curve in live_curve(),
+            sol_amount in 0..1_000_000_000_000u64,
+        ) {
+            let tokens = get_tokens_for_sol(&global, Some(&curve), sol_amount);
+            prop_assert!(get_sol_from_tokens(&global, Some(&curve), tokens) <= sol_amount);
+            let after = curve_after_buy(&global, &curve, sol_amount);
+            prop_assert!(get_sol_from_tokens(&global, Some(&after), tokens) <= sol_amount);
+            // The constant product never shrinks
+            prop_assert!(
+                after.virtual_sol_reserves as u128 * after.virtual_token_reserves as u128
+                    >= curve.virtual_sol_reserves as u128 * curve.virtual_token_reserves as u128
+            );
+        }
+
+        /// Each fee component rounds up on its own, so one more unit in can mean two more
+        /// lamports of fees. Net amounts only grow over steps of two lamports.
+        #[test]
+        fn prop_quotes_are_monotonic(
+            global in any_global(),
+            curve in live_curve(),
+            low in any::<u64>(),
+            step in 2..=u64::MAX / 2,
+        ) {
+            let high = low.saturating_add(step);
+            let curve = Some(&curve);
+            prop_assert!(
+                get_tokens_for_sol(&global, curve, low) <= get_tokens_for_sol(&global, curve, high)
+            );
+            // Costs round up the whole way, so they never dip
+            let cost = |tokens| get_sol_for_tokens(&global, curve, tokens);
+            for high in [low, low.saturating_add(1), high] {
+                prop_assert!(cost(low) <= cost(high));
+            }
+            // A few more tokens can sell for a single lamport more, which the fees can take
+            let policy = RoundingPolicy::default();
+            let sell_low = quote_sell_with_rounding(&global, curve, low, policy);
+            let sell_high = quote_sell_with_rounding(&global, curve, high, policy);
+            prop_assert!(sell_low.output_amount <= sell_high.output_amount + 1);
+        }
+
+        #[test]
+        fn prop_fees_stay_within_the_basis_points(
+            global in any_global(),
+            curve in live_curve(),
+            amount in any::<u64>(),
+        ) {
+            let bps = global.fee_basis_points + global.creator_fee_basis_points;
+            let policy = RoundingPolicy::default();
+
+            let buy = quote_buy_with_rounding(&global, Some(&curve), amount, policy);
+            prop_assert!(buy.output_amount <= curve.real_token_reserves);
+            let (lower, upper) = bps_bounds(amount, bps);
+            prop_assert!(buy.fee <= upper + 1);
+            if curve.creator != Pubkey::default() {
+                prop_assert!(buy.fee >= lower);
+            }
+
+            let sell = quote_sell_with_rounding(&global, Some(&curve), amount, policy);
+            let gross = (curve.virtual_sol_reserves as u128 * amount as u128
+                / (curve.virtual_token_reserves as u128 + amount as u128)) as u64;
+            prop_assert!(gross < curve.virtual_sol_reserves);
+            let (lower, upper) = bps_bounds(gross, bps);
+            prop_assert!(sell.fee <= upper + 1);
+            if curve.creator != Pubkey::default() {
+                prop_assert!(sell.fee >= lower);
+            }
+            prop_assert_eq!(sell.output_amount, gross.saturating_sub(sell.fee));
+        }
+
+        #[test]
+        fn prop_no_panics_at_extremes(
+            global in any_global(),
+            curve in any_curve(),
+            amount in prop_oneof![Just(0u64), Just(1), Just(u64::MAX), any::<u64>()],
+        ) {
+            for bonding_curve in [Some(&curve), None] {
+                get_tokens_for_sol(&global, bonding_curve, amount);
+                get_sol_for_tokens(&global, bonding_curve, amount);
+                get_sol_from_tokens(&global, bonding_curve, amount);
+                get_tokens_for_sol_output(&global, bonding_curve, amount, u64::MAX);
+            }
+            curve_after_buy(&global, &curve, amount);
+            curve_after_sell(&global, &curve, amount);
+            price_impact_bps(&global, &curve, amount);
+            spot_price_lamports_per_token(&curve);
+            graduation_progress(&curve);
+            tokens_to_market_cap(&curve, amount as f64 / LAMPORTS_PER_SOL as f64);
+            sol_to_market_cap(&global, &curve, amount as f64 / LAMPORTS_PER_SOL as f64);
+            sol_for_supply_pct(&global, &curve, (amount % 101) as f64);
+        }
+    }
+}
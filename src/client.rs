@@ -0,0 +1,85 @@
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{pubkey::Pubkey, signer::Signer};
+
+use crate::cal::{self, BondingCurve, Quote};
+use crate::error::Result;
+use crate::pump_sell::{SellAmount, SellRequest};
+use crate::signer::{self, Wallet};
+use crate::{blacklist, fees, pump_buy, pump_sell, pumpswap, rpc_pool, sizing};
+
+/// Trading handle bound to one RPC connection and one wallet.
+/// Graduated mints are routed through PumpSwap automatically.
+pub struct PumpFunClient {
+    rpc: RpcClient,
+    wallet: Wallet,
+}
+
+impl PumpFunClient {
+    /// Client signing with `wallet`, a `Keypair` or a remote `Wallet`
+    pub fn new(rpc: RpcClient, wallet: impl Into<Wallet>) -> Self {
+        Self {
+            rpc,
+            wallet: wallet.into(),
+        }
+    }
+
+    /// Client using the RPC url and wallet from the loaded config
+    pub fn from_config() -> Result<Self> {
+        Ok(Self::new(
+            rpc_pool::get_client(),
+            signer::load_default_wallet()?,
+        ))
+    }
+
+    pub fn rpc(&self) -> &RpcClient {
+        &self.rpc
+    }
+
+    pub fn pubkey(&self) -> Pubkey {
+        self.wallet.pubkey()
+    }
+
+    /// Fetch the live bonding curve of `mint`
+    pub fn fetch_curve(&self, mint: &Pubkey) -> Result<BondingCurve> {
+        cal::fetch_bonding_curve(&self.rpc, mint)
+    }
+
+    /// Tokens received for `sol_amount` lamports on the bonding curve
+    pub fn quote_buy(&self, mint: &Pubkey, sol_amount: u64) -> Result<Quote> {
+        cal::quote_buy(&self.rpc, mint, sol_amount)
+    }
+
+    /// Lamports received for `token_amount` tokens on the bonding curve
+    pub fn quote_sell(&self, mint: &Pubkey, token_amount: u64) -> Result<Quote> {
+        cal::quote_sell(&self.rpc, mint, token_amount)
+    }
+
+    /// Spend `sol_amount` lamports on `mint`, allowing `slippage_bps` above the quoted cost.
+    /// Blacklisted mints and bonding curve buys past `trading.max_price_impact_bps` are
+    /// refused.
+    pub fn buy(&self, mint: &Pubkey, sol_amount: u64, slippage_bps: u64) -> Result<()> {
+        blacklist::screen(mint)?;
+        let curve = self.fetch_curve(mint)?;
+        let token_amount = if curve.complete {
+            pumpswap::quote_buy(&self.rpc, mint, sol_amount)?
+        } else {
+            let global = fees::current_global(&self.rpc, &curve);
+            let impact_bps = cal::price_impact_bps(&global, &curve, sol_amount);
+            sizing::check_price_impact(mint, true, impact_bps, false)?;
+            cal::get_tokens_for_sol(&global, Some(&curve), sol_amount)
+        };
+        let max_sol_cost = pump_buy::with_slippage(sol_amount, slippage_bps);
+        pump_buy::buy(&self.rpc, &self.wallet, token_amount, *mint, max_sol_cost)?;
+        Ok(())
+    }
+
+    /// Sell `amount` of `mint`, accepting `slippage_bps` below the quoted output
+    pub fn sell(&self, mint: &Pubkey, amount: SellAmount, slippage_bps: u64) -> Result<()> {
+        let request = SellRequest {
+            mint: *mint,
+            amount,
+            force: false,
+        };
+        pump_sell::sell_request(&self.rpc, &self.wallet, &request, slippage_bps)
+    }
+}
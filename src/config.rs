@@ -0,0 +1,1603 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction, instruction::Instruction, pubkey::Pubkey,
+};
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+// Constants
+pub const DEFAULT_CONFIG_PATH: &str = "config.toml";
+/// Env var pointing at an alternative config file
+pub const CONFIG_PATH_ENV: &str = "PUMPBOT_CONFIG";
+/// Prefix of per-field overrides, e.g. `PUMPBOT_RPC_URL` overrides `rpc.url`
+const ENV_PREFIX: &str = "PUMPBOT_";
+/// Smallest tip the block engine accepts on a bundle-only transaction
+pub const MIN_JITO_TIP_LAMPORTS: u64 = 1_000;
+/// Most variants of one snipe that are raced against each other
+pub const MAX_RACE_TIERS: usize = 5;
+
+static CONFIG: OnceCell<Config> = OnceCell::new();
+
+/// Solana cluster the bot trades on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    Mainnet,
+    Devnet,
+    /// A `solana-test-validator` on this machine, loaded from `network.fixture_dir`
+    Localnet,
+}
+
+impl Network {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "mainnet",
+            Network::Devnet => "devnet",
+            Network::Localnet => "localnet",
+        }
+    }
+
+    /// Public RPC endpoint of the cluster
+    pub fn rpc_url(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "https://api.mainnet-beta.solana.com",
+            Network::Devnet => "https://api.devnet.solana.com",
+            Network::Localnet => "http://127.0.0.1:8899",
+        }
+    }
+
+    /// Public websocket endpoint of the cluster
+    pub fn ws_url(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "wss://api.mainnet-beta.solana.com",
+            Network::Devnet => "wss://api.devnet.solana.com",
+            Network::Localnet => "ws://127.0.0.1:8900",
+        }
+    }
+}
+
+impl FromStr for Network {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "mainnet" => Ok(Network::Mainnet),
+            "devnet" => Ok(Network::Devnet),
+            "localnet" => Ok(Network::Localnet),
+            other => Err(anyhow!(
+                "{:?} must be one of mainnet, devnet, localnet",
+                other
+            )),
+        }
+    }
+}
+
+/// Which cluster to trade on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    /// RPC URLs left at the mainnet defaults follow this to the cluster's public endpoints
+    pub cluster: Network,
+    /// Programs (`<program id>.so`) and accounts (`<pubkey>.json`) a localnet
+    /// `solana-test-validator` is started with
+    pub fixture_dir: String,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            cluster: Network::Mainnet,
+            fixture_dir: "fixtures/localnet".to_string(),
+        }
+    }
+}
+
+/// RPC endpoints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RpcConfig {
+    pub url: String,
+    pub ws_url: String,
+    /// Extra endpoints the RPC pool fails over to, tried after `url`
+    pub fallback_urls: Vec<String>,
+    /// How long a fetched bonding curve is reused by the curve cache (0 = always refetch)
+    pub curve_cache_ms: u64,
+    /// Request rate each endpoint is held to (0 = unlimited)
+    pub requests_per_second: f64,
+    /// Per-endpoint overrides of `requests_per_second`, by URL
+    pub rate_limits: HashMap<String, f64>,
+    /// How old the cached blockhash transactions are signed against may get
+    pub blockhash_max_age_ms: u64,
+}
+
+impl RpcConfig {
+    /// Requests per second allowed to `url`, 0 if unlimited
+    pub fn rate_limit(&self, url: &str) -> f64 {
+        self.rate_limits
+            .get(url)
+            .copied()
+            .unwrap_or(self.requests_per_second)
+    }
+}
+
+impl Default for RpcConfig {
+    fn default() -> Self {
+        Self {
+            url: Network::Mainnet.rpc_url().to_string(),
+            ws_url: Network::Mainnet.ws_url().to_string(),
+            fallback_urls: Vec::new(),
+            curve_cache_ms: 400,
+            requests_per_second: 0.0,
+            rate_limits: HashMap::new(),
+            blockhash_max_age_ms: 2_000,
+        }
+    }
+}
+
+/// What the exit engine does with a position whose bonding curve completes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MigrationPolicy {
+    /// Sell the whole position as soon as the curve completes
+    Sell,
+    /// Keep the take-profit and stop levels, priced off the AMM the token migrates to
+    Follow,
+}
+
+impl FromStr for MigrationPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "sell" => Ok(MigrationPolicy::Sell),
+            "follow" => Ok(MigrationPolicy::Follow),
+            other => Err(anyhow!("{:?} must be one of sell, follow", other)),
+        }
+    }
+}
+
+/// Where live account and transaction updates come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamBackend {
+    /// `accountSubscribe` / `logsSubscribe` over `rpc.ws_url`
+    WebSocket,
+    /// Yellowstone gRPC (built with the `geyser` feature)
+    Geyser,
+}
+
+impl FromStr for StreamBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "websocket" => Ok(StreamBackend::WebSocket),
+            "geyser" => Ok(StreamBackend::Geyser),
+            other => Err(anyhow!("{:?} must be one of websocket, geyser", other)),
+        }
+    }
+}
+
+/// Live update streams
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StreamConfig {
+    pub backend: StreamBackend,
+    /// Yellowstone gRPC endpoint used by the `geyser` backend
+    pub geyser_url: String,
+    /// Sent as the `x-token` header; empty sends none
+    pub geyser_x_token: String,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            backend: StreamBackend::WebSocket,
+            geyser_url: String::new(),
+            geyser_x_token: String::new(),
+        }
+    }
+}
+
+/// How the wallet pool picks the wallet for the next trade
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WalletSelection {
+    RoundRobin,
+    Random,
+}
+
+/// Trading wallets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WalletConfig {
+    /// Base58 encoded secret key
+    pub private_key: String,
+    /// More base58 secret keys that join `private_key` in the wallet pool
+    pub extra_private_keys: Vec<String>,
+    pub selection: WalletSelection,
+}
+
+impl Default for WalletConfig {
+    fn default() -> Self {
+        Self {
+            private_key: "priv-key".to_string(),
+            extra_private_keys: Vec::new(),
+            selection: WalletSelection::RoundRobin,
+        }
+    }
+}
+
+/// Where transactions are signed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignerBackend {
+    /// With the secret keys in `wallet.private_key` and `extra_private_keys`
+    Local,
+    /// By the signing service at `signer.url`, for the public keys in their place
+    Remote,
+}
+
+impl FromStr for SignerBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "local" => Ok(SignerBackend::Local),
+            "remote" => Ok(SignerBackend::Remote),
+            other => Err(anyhow!("{:?} must be one of local, remote", other)),
+        }
+    }
+}
+
+/// Transaction signing, in process or delegated to an external service so the bot never
+/// holds the secret keys
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SignerConfig {
+    pub backend: SignerBackend,
+    /// Base URL of the signing service; messages are POSTed to `<url>/sign`
+    pub url: String,
+    /// Sent as a bearer token; empty sends none
+    pub auth_token: String,
+    pub timeout_ms: u64,
+}
+
+impl Default for SignerConfig {
+    fn default() -> Self {
+        Self {
+            backend: SignerBackend::Local,
+            url: String::new(),
+            auth_token: String::new(),
+            timeout_ms: 2_000,
+        }
+    }
+}
+
+/// Named priority fee levels in micro-lamports per compute unit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PriorityFeeLevels {
+    pub low: u64,
+    pub medium: u64,
+    pub high: u64,
+}
+
+impl Default for PriorityFeeLevels {
+    fn default() -> Self {
+        Self {
+            low: 10_000,
+            medium: 100_000,
+            high: 1_000_000,
+        }
+    }
+}
+
+/// Protocol fee recipient and network priority fees
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeeConfig {
+    /// Protocol fee recipient used until the Global account's list has been fetched
+    pub fee_recipient: String,
+    /// Fee recipients to rotate between instead of the Global account's list
+    pub fee_recipients: Vec<String>,
+    /// One of `none`, `low`, `medium`, `high`
+    pub priority_level: String,
+    pub priority_levels: PriorityFeeLevels,
+    /// Compute unit prices a live transaction steps up through, in micro-lamports, when
+    /// it hasn't landed after `escalation_blocks` (empty = never escalate). The last step
+    /// caps the fee.
+    pub escalation_ladder: Vec<u64>,
+    pub escalation_blocks: u64,
+    /// Compute unit limit requested with priority fees (0 = leave the default)
+    pub compute_unit_limit: u32,
+    /// Simulate live trades first and request the units they used plus a margin,
+    /// replacing `compute_unit_limit`
+    pub estimate_compute_units: bool,
+    pub compute_unit_margin_percent: u32,
+}
+
+impl Default for FeeConfig {
+    fn default() -> Self {
+        Self {
+            fee_recipient: "CebN5WGQ4jvEPvsVU4EoHEpgzq1VV7AbicfhtW4xC9iM".to_string(),
+            fee_recipients: Vec::new(),
+            priority_level: "none".to_string(),
+            priority_levels: PriorityFeeLevels::default(),
+            escalation_ladder: Vec::new(),
+            escalation_blocks: 10,
+            compute_unit_limit: 0,
+            estimate_compute_units: true,
+            compute_unit_margin_percent: 10,
+        }
+    }
+}
+
+impl FeeConfig {
+    pub fn fee_recipient(&self) -> Pubkey {
+        Pubkey::from_str(&self.fee_recipient).expect("fee_recipient validated on load")
+    }
+
+    pub fn fee_recipients(&self) -> Vec<Pubkey> {
+        self.fee_recipients
+            .iter()
+            .map(|recipient| Pubkey::from_str(recipient).expect("fee_recipients validated on load"))
+            .collect()
+    }
+
+    /// Compute unit price for the configured priority level
+    pub fn priority_fee_micro_lamports(&self) -> u64 {
+        match self.priority_level.as_str() {
+            "low" => self.priority_levels.low,
+            "medium" => self.priority_levels.medium,
+            "high" => self.priority_levels.high,
+            _ => 0,
+        }
+    }
+
+    /// Compute budget instructions to prepend to a trade (empty when priority fees are off)
+    pub fn compute_budget_instructions(&self) -> Vec<Instruction> {
+        let mut instructions = Vec::new();
+        if self.compute_unit_limit > 0 {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(
+                self.compute_unit_limit,
+            ));
+        }
+        let price = self.priority_fee_micro_lamports();
+        if price > 0 {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_price(price));
+        }
+        instructions
+    }
+}
+
+/// How trades are executed once they are built
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionMode {
+    /// Sign and send
+    Live,
+    /// Simulate against the RPC and print the logs
+    Simulate,
+    /// Record a fill at the live quote in the paper portfolio without signing anything
+    Paper,
+}
+
+impl ExecutionMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExecutionMode::Live => "live",
+            ExecutionMode::Simulate => "simulate",
+            ExecutionMode::Paper => "paper",
+        }
+    }
+}
+
+impl FromStr for ExecutionMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "live" => Ok(ExecutionMode::Live),
+            "simulate" => Ok(ExecutionMode::Simulate),
+            "paper" => Ok(ExecutionMode::Paper),
+            other => Err(anyhow!("{:?} must be one of live, simulate, paper", other)),
+        }
+    }
+}
+
+/// How a live transaction is broadcast
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SendStrategy {
+    /// Through the RPC pool, one endpoint per send
+    Single,
+    /// To several pool endpoints (and optionally Jito) at once until it lands
+    Spam,
+    /// Only to the Jito block engine as a bundle-only transaction with a tip, never to
+    /// a public RPC, so it can't be seen and sandwiched before it lands
+    Protected,
+}
+
+impl FromStr for SendStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "single" => Ok(SendStrategy::Single),
+            "spam" => Ok(SendStrategy::Spam),
+            "protected" => Ok(SendStrategy::Protected),
+            other => Err(anyhow!(
+                "{:?} must be one of single, spam, protected",
+                other
+            )),
+        }
+    }
+}
+
+/// Default trade sizing and slippage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TradingConfig {
+    pub buy_slippage_bps: u64,
+    pub sell_slippage_bps: u64,
+    /// Slippage accepted by pre-signed emergency exits - landing matters more than price
+    pub exit_slippage_bps: u64,
+    /// Extra slippage for one retry of a trade whose simulation exceeded its slippage (0 = off)
+    pub slippage_retry_bps: u64,
+    /// Default buy size in lamports
+    pub buy_amount_lamports: u64,
+    pub mode: ExecutionMode,
+    /// Trades the executor runs at once (trades on one mint always run one at a time)
+    pub max_in_flight: usize,
+    /// Close token accounts a sell empties to reclaim their rent
+    pub close_ata: bool,
+    /// Buy Token-2022 mints with a permanent delegate or transfer hook anyway
+    pub allow_dangerous_extensions: bool,
+    /// Durable nonce account pre-signed buys are signed against (empty = none)
+    pub nonce_account: String,
+    pub send_strategy: SendStrategy,
+    /// Overrides `send_strategy` for buys
+    pub buy_send_strategy: Option<SendStrategy>,
+    /// Overrides `send_strategy` for sells, e.g. `protected` for large exits
+    pub sell_send_strategy: Option<SendStrategy>,
+    /// Pool endpoints the spam strategy broadcasts to (0 = all of them)
+    pub spam_endpoints: usize,
+    /// Also broadcast spammed transactions through `jito.block_engine_url`
+    pub spam_jito: bool,
+    /// Refuse buys and sells on the curve that move the price more than this, unless
+    /// forced (0 = no limit)
+    pub max_price_impact_bps: u64,
+}
+
+impl TradingConfig {
+    /// How buys (`is_buy`) or sells are broadcast
+    pub fn send_strategy_for(&self, is_buy: bool) -> SendStrategy {
+        let side = if is_buy {
+            self.buy_send_strategy
+        } else {
+            self.sell_send_strategy
+        };
+        side.unwrap_or(self.send_strategy)
+    }
+}
+
+impl Default for TradingConfig {
+    fn default() -> Self {
+        Self {
+            buy_slippage_bps: 500,
+            sell_slippage_bps: 500,
+            exit_slippage_bps: 3_000,
+            slippage_retry_bps: 0,
+            buy_amount_lamports: 100_000_000,
+            mode: ExecutionMode::Simulate,
+            max_in_flight: 4,
+            close_ata: false,
+            allow_dangerous_extensions: false,
+            nonce_account: String::new(),
+            send_strategy: SendStrategy::Single,
+            buy_send_strategy: None,
+            sell_send_strategy: None,
+            spam_endpoints: 0,
+            spam_jito: false,
+            max_price_impact_bps: 0,
+        }
+    }
+}
+
+/// How the sizing engine picks a strategy's buy size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizingMode {
+    /// `trading.buy_amount_lamports` every time
+    Fixed,
+    /// `sizing.balance_pct` of the free SOL balance (Kelly-lite)
+    PercentOfBalance,
+    /// `trading.buy_amount_lamports`, scaled down when the curve is more volatile than
+    /// `sizing.target_volatility_pct`
+    Volatility,
+    /// `trading.buy_amount_lamports`, cut down to whatever the exposure limits leave
+    ExposureCapped,
+    /// What `sizing.supply_pct` percent of the token supply costs on the curve
+    SupplyPct,
+    /// What lifting the market cap to `sizing.target_market_cap_sol` costs on the curve
+    TargetMarketCap,
+}
+
+impl FromStr for SizingMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "fixed" => Ok(SizingMode::Fixed),
+            "percent_of_balance" => Ok(SizingMode::PercentOfBalance),
+            "volatility" => Ok(SizingMode::Volatility),
+            "exposure_capped" => Ok(SizingMode::ExposureCapped),
+            "supply_pct" => Ok(SizingMode::SupplyPct),
+            "target_market_cap" => Ok(SizingMode::TargetMarketCap),
+            other => Err(anyhow!(
+                "{:?} must be one of fixed, percent_of_balance, volatility, exposure_capped, \
+                 supply_pct, target_market_cap",
+                other
+            )),
+        }
+    }
+}
+
+/// Buy sizing and exposure limits
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SizingConfig {
+    pub mode: SizingMode,
+    /// Percent of the free balance each buy spends in `percent_of_balance` mode
+    pub balance_pct: f64,
+    /// Volatility (std dev of trade-to-trade price moves, in percent) at or below which
+    /// `volatility` mode buys the full size
+    pub target_volatility_pct: f64,
+    /// Percent of the total supply each buy takes in `supply_pct` mode
+    pub supply_pct: f64,
+    /// Market cap in SOL each buy lifts the curve to in `target_market_cap` mode
+    pub target_market_cap_sol: f64,
+    /// Lamports one mint may tie up in open positions and queued buys (0 = no limit)
+    pub max_mint_exposure_lamports: u64,
+    /// Lamports every mint together may tie up (0 = no limit)
+    pub max_total_exposure_lamports: u64,
+    /// Mints held or being bought at once; buys on other mints are refused (0 = no limit)
+    pub max_open_positions: usize,
+    /// Refuse buys leaving a position that can't be sold in one go without moving the
+    /// curve price more than this (0 = no limit)
+    pub max_exit_impact_bps: u64,
+}
+
+impl Default for SizingConfig {
+    fn default() -> Self {
+        Self {
+            mode: SizingMode::Fixed,
+            balance_pct: 5.0,
+            target_volatility_pct: 10.0,
+            supply_pct: 1.0,
+            target_market_cap_sol: 50.0,
+            max_mint_exposure_lamports: 0,
+            max_total_exposure_lamports: 0,
+            max_open_positions: 0,
+            max_exit_impact_bps: 0,
+        }
+    }
+}
+
+/// SOL kept in the trading wallet for fees and rent, and how it is refilled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BalanceConfig {
+    /// Lamports buys never spend; live buys that would dip into it are refused
+    pub reserve_lamports: u64,
+    /// Top up (or warn) once the wallet holds less than this
+    pub low_balance_lamports: u64,
+    /// Lamports moved from the treasury per top-up
+    pub top_up_lamports: u64,
+    /// Base58 secret key of the wallet top-ups come from (empty = only warn)
+    pub treasury_private_key: String,
+    /// Close wrapped SOL accounts left in the wallet to get the SOL back
+    pub unwrap_wsol: bool,
+    pub check_interval_secs: u64,
+}
+
+impl Default for BalanceConfig {
+    fn default() -> Self {
+        Self {
+            reserve_lamports: 10_000_000,
+            low_balance_lamports: 100_000_000,
+            top_up_lamports: 500_000_000,
+            treasury_private_key: String::new(),
+            unwrap_wsol: true,
+            check_interval_secs: 60,
+        }
+    }
+}
+
+/// Take-profit / stop-loss strategy parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StrategyConfig {
+    /// (gain %, sell % of remaining) pairs
+    pub take_profits: Vec<(f64, f64)>,
+    /// (gain %, sell % of the initial position) tranches. Replaces `take_profits` when set;
+    /// whatever the tranches leave is kept as a runner.
+    pub ladder: Vec<(f64, f64)>,
+    pub stop_loss_pct: Option<f64>,
+    /// Retrace from the highest price seen, in percent, that triggers a full exit
+    pub trailing_stop_pct: Option<f64>,
+    /// Retrace from the highest price seen, in percent, that sells the runner once every
+    /// `ladder` tranche has been sold
+    pub runner_trailing_stop_pct: Option<f64>,
+    /// Sell whatever is left of a position this many seconds after it was opened
+    pub max_hold_secs: Option<u64>,
+    pub poll_interval_secs: u64,
+    /// Percent of its holdings the creator has to sell or move out for the `creator_exit`
+    /// strategy to sell the whole position
+    pub creator_exit_pct: f64,
+    pub migration_policy: MigrationPolicy,
+}
+
+impl Default for StrategyConfig {
+    fn default() -> Self {
+        Self {
+            take_profits: vec![(100.0, 50.0), (400.0, 100.0)],
+            ladder: Vec::new(),
+            stop_loss_pct: Some(50.0),
+            trailing_stop_pct: None,
+            runner_trailing_stop_pct: None,
+            max_hold_secs: None,
+            poll_interval_secs: 2,
+            creator_exit_pct: 50.0,
+            migration_policy: MigrationPolicy::Follow,
+        }
+    }
+}
+
+/// Where the SOL/USD price comes from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PriceFeedConfig {
+    /// Pyth `PriceUpdateV2` account for SOL/USD
+    pub pyth_account: String,
+    /// Pyth prices older than this are ignored in favour of `rest_url`
+    pub max_staleness_secs: u64,
+    /// CoinGecko-style `simple/price` endpoint used when Pyth is unavailable
+    pub rest_url: String,
+    /// How long a fetched price is reused before it is refreshed
+    pub refresh_secs: u64,
+}
+
+impl Default for PriceFeedConfig {
+    fn default() -> Self {
+        Self {
+            pyth_account: "7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE".to_string(),
+            max_staleness_secs: 60,
+            rest_url: "https://api.coingecko.com/api/v3/simple/price?ids=solana&vs_currencies=usd"
+                .to_string(),
+            refresh_secs: 30,
+        }
+    }
+}
+
+/// Jupiter swap API, used to sell mints that trade on neither the curve nor PumpSwap
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JupiterConfig {
+    /// Route such sells through Jupiter instead of failing them
+    pub enabled: bool,
+    /// Base URL of the `/quote` and `/swap` endpoints
+    pub api_url: String,
+    /// Sent as `x-api-key`; empty for the keyless endpoint
+    pub api_key: String,
+}
+
+impl Default for JupiterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            api_url: "https://lite-api.jup.ag/swap/v1".to_string(),
+            api_key: String::new(),
+        }
+    }
+}
+
+/// Jito block engine, which lands bundles of transactions atomically
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JitoConfig {
+    /// Base URL of the block engine; bundles are posted to `/api/v1/bundles`
+    pub block_engine_url: String,
+    /// Tip paid to the block engine with every bundle
+    pub tip_lamports: u64,
+}
+
+impl Default for JitoConfig {
+    fn default() -> Self {
+        Self {
+            block_engine_url: "https://mainnet.block-engine.jito.wtf".to_string(),
+            tip_lamports: 100_000,
+        }
+    }
+}
+
+/// Launch sniping, where buys are patched from pre-built templates as Create events arrive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SnipeConfig {
+    /// Lamports spent on each launch (0 = `trading.buy_amount_lamports`)
+    pub buy_amount_lamports: u64,
+    /// Slippage over the opening price, wide since other snipers buy in the same block
+    pub slippage_bps: u64,
+    /// Launches are Token-2022 mints; false for SPL Token launches
+    pub token_2022: bool,
+    /// How often the blockhash snipes are signed against is refreshed
+    pub blockhash_refresh_ms: u64,
+    /// Stop after this many snipes (0 = no limit)
+    pub max_snipes: usize,
+    /// Skip launches where wallets funded from one source bought in the same slot. The
+    /// snipe waits for the launch's first transactions to confirm, so it lands later.
+    pub skip_bundled_launches: bool,
+    /// Transactions on a new bonding curve inspected for bundled buys
+    pub launch_transactions: usize,
+    /// Longest a launch waits for its bundle check, after which it is sniped unchecked
+    pub launch_analysis_timeout_ms: u64,
+    /// (compute unit price in micro-lamports, Jito tip in lamports) of each variant of a
+    /// snipe raced against the others. Empty sends one buy at the `[fees]` price.
+    pub race_tiers: Vec<(u64, u64)>,
+}
+
+impl Default for SnipeConfig {
+    fn default() -> Self {
+        Self {
+            buy_amount_lamports: 0,
+            slippage_bps: 2_000,
+            token_2022: true,
+            blockhash_refresh_ms: 1_000,
+            max_snipes: 1,
+            skip_bundled_launches: false,
+            launch_transactions: 10,
+            launch_analysis_timeout_ms: 1_500,
+            race_tiers: Vec::new(),
+        }
+    }
+}
+
+/// Token launches
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LaunchConfig {
+    /// (wallet pool index, lamports) buys bundled after the dev buy, in this order.
+    /// Index 0 is `wallet.private_key`, then `wallet.extra_private_keys`.
+    pub bundle_buys: Vec<(usize, u64)>,
+}
+
+/// Pre-buy rug checks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SafetyConfig {
+    /// Refuse strategy buys of mints scoring above `max_risk_score`
+    pub enabled: bool,
+    /// 0 (no red flags) to 100
+    pub max_risk_score: u32,
+    /// Number of largest holders summed for the concentration check
+    pub top_holders: usize,
+    pub max_top_holders_pct: f64,
+    /// Most of the supply the creator may still hold
+    pub max_dev_holdings_pct: f64,
+    /// Most of the supply wallets with near-identical balances (split bundled buys) may hold
+    pub max_bundler_pct: f64,
+    /// Other transactions allowed in the launch slot
+    pub max_bundled_buys: usize,
+    /// Creator transactions scanned for earlier rugs (0 skips the check)
+    pub creator_history: usize,
+    /// Refuse every buy whose mint, creator or symbol isn't on the allowlist
+    pub allowlist_only: bool,
+}
+
+impl Default for SafetyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_risk_score: 50,
+            top_holders: 10,
+            max_top_holders_pct: 30.0,
+            max_dev_holdings_pct: 10.0,
+            max_bundler_pct: 20.0,
+            max_bundled_buys: 3,
+            creator_history: 20,
+            allowlist_only: false,
+        }
+    }
+}
+
+/// Telegram bot (built with the `telegram` feature)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelegramConfig {
+    /// Token from @BotFather; empty disables the bot
+    pub bot_token: String,
+    /// The only chat allowed to send commands, and where notifications go
+    pub chat_id: i64,
+}
+
+/// REST API daemon (built with the `api` feature)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfig {
+    /// Address the API listens on
+    pub bind: String,
+    /// Required in the `x-api-key` header of every request but `/health`; empty refuses to start
+    pub api_key: String,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            bind: "127.0.0.1:8080".to_string(),
+            api_key: String::new(),
+        }
+    }
+}
+
+/// Log line format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines
+    Text,
+    /// One JSON object per event, for log shippers
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(anyhow!("{:?} must be one of text, json", other)),
+        }
+    }
+}
+
+/// Log level, format and file output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// `tracing` filter directive such as `info` or `trading_bot_rust=debug` (`RUST_LOG` wins)
+    pub level: String,
+    pub format: LogFormat,
+    /// Directory for daily rotated log files; empty logs to stdout only
+    pub dir: String,
+    pub file_prefix: String,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            format: LogFormat::Text,
+            dir: String::new(),
+            file_prefix: "pumpbot.log".to_string(),
+        }
+    }
+}
+
+/// Where the bot keeps its state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    pub db_path: String,
+    /// Portfolio used instead of `db_path` in paper mode
+    pub paper_db_path: String,
+    pub address_book_path: String,
+    /// Event archive written by `archive`
+    pub archive_db_path: String,
+    /// Reconcile the portfolio with on-chain balances before live trading starts
+    pub reconcile_on_startup: bool,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            db_path: "portfolio.db".to_string(),
+            paper_db_path: "paper_portfolio.db".to_string(),
+            address_book_path: "address_book.txt".to_string(),
+            archive_db_path: "archive.db".to_string(),
+            reconcile_on_startup: true,
+        }
+    }
+}
+
+/// Everything loaded from `config.toml`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub network: NetworkConfig,
+    pub rpc: RpcConfig,
+    pub stream: StreamConfig,
+    pub wallet: WalletConfig,
+    pub signer: SignerConfig,
+    pub fees: FeeConfig,
+    pub trading: TradingConfig,
+    pub sizing: SizingConfig,
+    pub balance: BalanceConfig,
+    pub strategy: StrategyConfig,
+    pub price_feed: PriceFeedConfig,
+    pub jupiter: JupiterConfig,
+    pub jito: JitoConfig,
+    pub snipe: SnipeConfig,
+    pub launch: LaunchConfig,
+    pub safety: SafetyConfig,
+    pub telegram: TelegramConfig,
+    pub api: ApiConfig,
+    pub logging: LoggingConfig,
+    pub storage: StorageConfig,
+}
+
+/// Overwrite `field` from env var `PUMPBOT_<NAME>` if it is set
+fn env_override<T: FromStr>(name: &str, field: &mut T) -> Result<()>
+where
+    T::Err: std::fmt::Display,
+{
+    let var = format!("{}{}", ENV_PREFIX, name);
+    if let Ok(value) = std::env::var(&var) {
+        *field = value
+            .parse()
+            .map_err(|e| anyhow!("Invalid value for {}: {}", var, e))?;
+    }
+    Ok(())
+}
+
+fn check_bps(field: &str, value: u64) -> Result<()> {
+    if value > 10_000 {
+        return Err(anyhow!(
+            "Config field `{}`: {} bps is more than 100%",
+            field,
+            value
+        ));
+    }
+    Ok(())
+}
+
+impl Config {
+    /// Parse a TOML document. Missing fields keep their defaults.
+    pub fn from_toml(contents: &str) -> Result<Self> {
+        toml::from_str(contents).map_err(|e| anyhow!("Invalid config: {}", e))
+    }
+
+    /// Load `path` (defaults if it doesn't exist), apply env overrides and validate
+    pub fn load(path: &str) -> Result<Self> {
+        let mut config = if Path::new(path).exists() {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| anyhow!("Failed to read config {}: {}", path, e))?;
+            Self::from_toml(&contents).map_err(|e| anyhow!("{}: {}", path, e))?
+        } else {
+            Self::default()
+        };
+        config.apply_env_overrides()?;
+        config.apply_network();
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Point RPC URLs still at the mainnet defaults to `network.cluster`, and turn off
+    /// Jupiter routing, which only quotes mainnet pools, off mainnet
+    pub fn apply_network(&mut self) {
+        let cluster = self.network.cluster;
+        if cluster == Network::Mainnet {
+            return;
+        }
+        if self.rpc.url == Network::Mainnet.rpc_url() {
+            self.rpc.url = cluster.rpc_url().to_string();
+        }
+        if self.rpc.ws_url == Network::Mainnet.ws_url() {
+            self.rpc.ws_url = cluster.ws_url().to_string();
+        }
+        self.jupiter.enabled = false;
+    }
+
+    /// Apply `PUMPBOT_*` environment variable overrides
+    pub fn apply_env_overrides(&mut self) -> Result<()> {
+        env_override("NETWORK", &mut self.network.cluster)?;
+        env_override("FIXTURE_DIR", &mut self.network.fixture_dir)?;
+        env_override("RPC_URL", &mut self.rpc.url)?;
+        env_override("WS_URL", &mut self.rpc.ws_url)?;
+        env_override("CURVE_CACHE_MS", &mut self.rpc.curve_cache_ms)?;
+        env_override("RPC_REQUESTS_PER_SECOND", &mut self.rpc.requests_per_second)?;
+        env_override("BLOCKHASH_MAX_AGE_MS", &mut self.rpc.blockhash_max_age_ms)?;
+        env_override("STREAM_BACKEND", &mut self.stream.backend)?;
+        env_override("GEYSER_URL", &mut self.stream.geyser_url)?;
+        env_override("GEYSER_X_TOKEN", &mut self.stream.geyser_x_token)?;
+        env_override("PRIVATE_KEY", &mut self.wallet.private_key)?;
+        env_override("SIGNER_BACKEND", &mut self.signer.backend)?;
+        env_override("SIGNER_URL", &mut self.signer.url)?;
+        env_override("SIGNER_AUTH_TOKEN", &mut self.signer.auth_token)?;
+        env_override("FEE_RECIPIENT", &mut self.fees.fee_recipient)?;
+        env_override("PRIORITY_LEVEL", &mut self.fees.priority_level)?;
+        env_override("COMPUTE_UNIT_LIMIT", &mut self.fees.compute_unit_limit)?;
+        env_override("ESCALATION_BLOCKS", &mut self.fees.escalation_blocks)?;
+        env_override("ESTIMATE_COMPUTE_UNITS", &mut self.fees.estimate_compute_units)?;
+        env_override("BUY_SLIPPAGE_BPS", &mut self.trading.buy_slippage_bps)?;
+        env_override("SELL_SLIPPAGE_BPS", &mut self.trading.sell_slippage_bps)?;
+        env_override("EXIT_SLIPPAGE_BPS", &mut self.trading.exit_slippage_bps)?;
+        env_override("SLIPPAGE_RETRY_BPS", &mut self.trading.slippage_retry_bps)?;
+        env_override("BUY_AMOUNT_LAMPORTS", &mut self.trading.buy_amount_lamports)?;
+        env_override("MODE", &mut self.trading.mode)?;
+        env_override("MAX_IN_FLIGHT", &mut self.trading.max_in_flight)?;
+        env_override(
+            "MAX_PRICE_IMPACT_BPS",
+            &mut self.trading.max_price_impact_bps,
+        )?;
+        env_override("SEND_STRATEGY", &mut self.trading.send_strategy)?;
+        env_override("SPAM_ENDPOINTS", &mut self.trading.spam_endpoints)?;
+        env_override("SPAM_JITO", &mut self.trading.spam_jito)?;
+        env_override("CLOSE_ATA", &mut self.trading.close_ata)?;
+        env_override(
+            "ALLOW_DANGEROUS_EXTENSIONS",
+            &mut self.trading.allow_dangerous_extensions,
+        )?;
+        env_override("NONCE_ACCOUNT", &mut self.trading.nonce_account)?;
+        env_override("SIZING_MODE", &mut self.sizing.mode)?;
+        env_override(
+            "MAX_MINT_EXPOSURE_LAMPORTS",
+            &mut self.sizing.max_mint_exposure_lamports,
+        )?;
+        env_override(
+            "MAX_TOTAL_EXPOSURE_LAMPORTS",
+            &mut self.sizing.max_total_exposure_lamports,
+        )?;
+        env_override("MAX_OPEN_POSITIONS", &mut self.sizing.max_open_positions)?;
+        env_override("MAX_EXIT_IMPACT_BPS", &mut self.sizing.max_exit_impact_bps)?;
+        env_override("RESERVE_LAMPORTS", &mut self.balance.reserve_lamports)?;
+        env_override("TREASURY_PRIVATE_KEY", &mut self.balance.treasury_private_key)?;
+        env_override("JUPITER_API_URL", &mut self.jupiter.api_url)?;
+        env_override("JUPITER_API_KEY", &mut self.jupiter.api_key)?;
+        env_override("JITO_BLOCK_ENGINE_URL", &mut self.jito.block_engine_url)?;
+        env_override("JITO_TIP_LAMPORTS", &mut self.jito.tip_lamports)?;
+        env_override("MIGRATION_POLICY", &mut self.strategy.migration_policy)?;
+        env_override("SNIPE_BUY_AMOUNT_LAMPORTS", &mut self.snipe.buy_amount_lamports)?;
+        env_override("SNIPE_MAX_SNIPES", &mut self.snipe.max_snipes)?;
+        env_override(
+            "SNIPE_SKIP_BUNDLED_LAUNCHES",
+            &mut self.snipe.skip_bundled_launches,
+        )?;
+        env_override("SAFETY_ENABLED", &mut self.safety.enabled)?;
+        env_override("MAX_RISK_SCORE", &mut self.safety.max_risk_score)?;
+        env_override("TELEGRAM_BOT_TOKEN", &mut self.telegram.bot_token)?;
+        env_override("TELEGRAM_CHAT_ID", &mut self.telegram.chat_id)?;
+        env_override("API_BIND", &mut self.api.bind)?;
+        env_override("API_KEY", &mut self.api.api_key)?;
+        env_override("LOG_LEVEL", &mut self.logging.level)?;
+        env_override("LOG_FORMAT", &mut self.logging.format)?;
+        env_override("LOG_DIR", &mut self.logging.dir)?;
+        env_override("DB_PATH", &mut self.storage.db_path)?;
+        env_override("PAPER_DB_PATH", &mut self.storage.paper_db_path)?;
+        env_override("ARCHIVE_DB_PATH", &mut self.storage.archive_db_path)?;
+        env_override("RECONCILE_ON_STARTUP", &mut self.storage.reconcile_on_startup)?;
+        Ok(())
+    }
+
+    /// Check every field, naming the first one that is invalid
+    pub fn validate(&self) -> Result<()> {
+        if self.network.cluster != Network::Mainnet
+            && self.rpc.url.contains("mainnet")
+        {
+            return Err(anyhow!(
+                "Config field `rpc.url`: {:?} is mainnet but `network.cluster` is {}",
+                self.rpc.url,
+                self.network.cluster.as_str()
+            ));
+        }
+        if self.network.cluster == Network::Localnet && self.network.fixture_dir.is_empty() {
+            return Err(anyhow!(
+                "Config field `network.fixture_dir`: must be set on localnet"
+            ));
+        }
+
+        for (field, url, schemes) in [
+            ("rpc.url", &self.rpc.url, ["http://", "https://"]),
+            ("rpc.ws_url", &self.rpc.ws_url, ["ws://", "wss://"]),
+        ] {
+            if !schemes.iter().any(|s| url.starts_with(s)) {
+                return Err(anyhow!(
+                    "Config field `{}`: {:?} must start with {} or {}",
+                    field,
+                    url,
+                    schemes[0],
+                    schemes[1]
+                ));
+            }
+        }
+        for (i, url) in self.rpc.fallback_urls.iter().enumerate() {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err(anyhow!(
+                    "Config field `rpc.fallback_urls[{}]`: {:?} must start with http:// or https://",
+                    i,
+                    url
+                ));
+            }
+        }
+        let mut rates = vec![(
+            "rpc.requests_per_second".to_string(),
+            self.rpc.requests_per_second,
+        )];
+        rates.extend(
+            self.rpc
+                .rate_limits
+                .iter()
+                .map(|(url, rate)| (format!("rpc.rate_limits.{:?}", url), *rate)),
+        );
+        for (field, rate) in rates {
+            if !rate.is_finite() || rate < 0.0 {
+                return Err(anyhow!(
+                    "Config field `{}`: {} must be 0 (unlimited) or a positive rate",
+                    field,
+                    rate
+                ));
+            }
+        }
+        if let Some(url) = self
+            .rpc
+            .rate_limits
+            .keys()
+            .find(|url| **url != self.rpc.url && !self.rpc.fallback_urls.contains(url))
+        {
+            return Err(anyhow!(
+                "Config field `rpc.rate_limits`: {:?} is neither `rpc.url` nor in `rpc.fallback_urls`",
+                url
+            ));
+        }
+        if self.rpc.blockhash_max_age_ms == 0 {
+            return Err(anyhow!(
+                "Config field `rpc.blockhash_max_age_ms`: must be greater than 0"
+            ));
+        }
+
+        if self.stream.backend == StreamBackend::Geyser
+            && !self.stream.geyser_url.starts_with("http://")
+            && !self.stream.geyser_url.starts_with("https://")
+        {
+            return Err(anyhow!(
+                "Config field `stream.geyser_url`: {:?} must start with http:// or https://",
+                self.stream.geyser_url
+            ));
+        }
+
+        if self.wallet.private_key.is_empty() {
+            return Err(anyhow!("Config field `wallet.private_key`: must be set"));
+        }
+        if let Some(i) = self.wallet.extra_private_keys.iter().position(|k| k.is_empty()) {
+            return Err(anyhow!(
+                "Config field `wallet.extra_private_keys[{}]`: must not be empty",
+                i
+            ));
+        }
+        if self.signer.backend == SignerBackend::Remote {
+            if !self.signer.url.starts_with("http://") && !self.signer.url.starts_with("https://") {
+                return Err(anyhow!(
+                    "Config field `signer.url`: {:?} must start with http:// or https://",
+                    self.signer.url
+                ));
+            }
+            if self.signer.timeout_ms == 0 {
+                return Err(anyhow!(
+                    "Config field `signer.timeout_ms`: must be greater than 0"
+                ));
+            }
+            // The remote signer signs for public keys given in place of the secret keys
+            let mut keys = vec![("wallet.private_key".to_string(), &self.wallet.private_key)];
+            for (i, key) in self.wallet.extra_private_keys.iter().enumerate() {
+                keys.push((format!("wallet.extra_private_keys[{}]", i), key));
+            }
+            if !self.balance.treasury_private_key.is_empty() {
+                keys.push((
+                    "balance.treasury_private_key".to_string(),
+                    &self.balance.treasury_private_key,
+                ));
+            }
+            for (field, key) in keys {
+                Pubkey::from_str(key).map_err(|e| {
+                    anyhow!(
+                        "Config field `{}`: {:?} must be the public key the remote signer signs for ({})",
+                        field,
+                        key,
+                        e
+                    )
+                })?;
+            }
+        }
+
+        Pubkey::from_str(&self.fees.fee_recipient).map_err(|e| {
+            anyhow!(
+                "Config field `fees.fee_recipient`: {:?} is not a valid pubkey ({})",
+                self.fees.fee_recipient,
+                e
+            )
+        })?;
+        for (i, recipient) in self.fees.fee_recipients.iter().enumerate() {
+            Pubkey::from_str(recipient).map_err(|e| {
+                anyhow!(
+                    "Config field `fees.fee_recipients[{}]`: {:?} is not a valid pubkey ({})",
+                    i,
+                    recipient,
+                    e
+                )
+            })?;
+        }
+        if self.fees.escalation_ladder.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(anyhow!(
+                "Config field `fees.escalation_ladder`: {:?} must be strictly increasing",
+                self.fees.escalation_ladder
+            ));
+        }
+        if !self.fees.escalation_ladder.is_empty() && self.fees.escalation_blocks == 0 {
+            return Err(anyhow!(
+                "Config field `fees.escalation_blocks`: must be greater than 0"
+            ));
+        }
+        if !["none", "low", "medium", "high"].contains(&self.fees.priority_level.as_str()) {
+            return Err(anyhow!(
+                "Config field `fees.priority_level`: {:?} must be one of none, low, medium, high",
+                self.fees.priority_level
+            ));
+        }
+
+        check_bps("trading.buy_slippage_bps", self.trading.buy_slippage_bps)?;
+        check_bps("trading.sell_slippage_bps", self.trading.sell_slippage_bps)?;
+        check_bps("trading.exit_slippage_bps", self.trading.exit_slippage_bps)?;
+        check_bps("trading.slippage_retry_bps", self.trading.slippage_retry_bps)?;
+        if self.trading.buy_amount_lamports == 0 {
+            return Err(anyhow!(
+                "Config field `trading.buy_amount_lamports`: must be greater than 0"
+            ));
+        }
+        if self.trading.max_in_flight == 0 {
+            return Err(anyhow!(
+                "Config field `trading.max_in_flight`: must be greater than 0"
+            ));
+        }
+        let protected = [true, false]
+            .iter()
+            .any(|is_buy| self.trading.send_strategy_for(*is_buy) == SendStrategy::Protected);
+        if protected && self.jito.tip_lamports < MIN_JITO_TIP_LAMPORTS {
+            return Err(anyhow!(
+                "Config field `jito.tip_lamports`: protected sends need a tip of at least {}",
+                MIN_JITO_TIP_LAMPORTS
+            ));
+        }
+
+        if !self.trading.nonce_account.is_empty() {
+            Pubkey::from_str(&self.trading.nonce_account).map_err(|e| {
+                anyhow!(
+                    "Config field `trading.nonce_account`: {:?} is not a valid pubkey ({})",
+                    self.trading.nonce_account,
+                    e
+                )
+            })?;
+        }
+
+        if self.sizing.max_exit_impact_bps > 10_000 {
+            return Err(anyhow!(
+                "Config field `sizing.max_exit_impact_bps`: {} must be at most 10000",
+                self.sizing.max_exit_impact_bps
+            ));
+        }
+        if self.sizing.balance_pct <= 0.0 || self.sizing.balance_pct > 100.0 {
+            return Err(anyhow!(
+                "Config field `sizing.balance_pct`: {} must be in (0, 100]",
+                self.sizing.balance_pct
+            ));
+        }
+        if self.sizing.target_volatility_pct <= 0.0 {
+            return Err(anyhow!(
+                "Config field `sizing.target_volatility_pct`: must be greater than 0"
+            ));
+        }
+        if self.sizing.supply_pct <= 0.0 || self.sizing.supply_pct > 100.0 {
+            return Err(anyhow!(
+                "Config field `sizing.supply_pct`: {} must be in (0, 100]",
+                self.sizing.supply_pct
+            ));
+        }
+        if self.sizing.target_market_cap_sol <= 0.0 {
+            return Err(anyhow!(
+                "Config field `sizing.target_market_cap_sol`: must be greater than 0"
+            ));
+        }
+
+        if self.balance.low_balance_lamports < self.balance.reserve_lamports {
+            return Err(anyhow!(
+                "Config field `balance.low_balance_lamports`: {} is below the reserve {}",
+                self.balance.low_balance_lamports,
+                self.balance.reserve_lamports
+            ));
+        }
+        if self.balance.check_interval_secs == 0 {
+            return Err(anyhow!(
+                "Config field `balance.check_interval_secs`: must be greater than 0"
+            ));
+        }
+
+        for (i, (gain_pct, sell_pct)) in self.strategy.take_profits.iter().enumerate() {
+            if *gain_pct <= 0.0 || *sell_pct <= 0.0 || *sell_pct > 100.0 {
+                return Err(anyhow!(
+                    "Config field `strategy.take_profits[{}]`: gain must be > 0 and sell in (0, 100]",
+                    i
+                ));
+            }
+        }
+        for (i, (gain_pct, size_pct)) in self.strategy.ladder.iter().enumerate() {
+            if *gain_pct <= 0.0 || *size_pct <= 0.0 || *size_pct > 100.0 {
+                return Err(anyhow!(
+                    "Config field `strategy.ladder[{}]`: gain must be > 0 and size in (0, 100]",
+                    i
+                ));
+            }
+        }
+        let ladder_pct: f64 = self.strategy.ladder.iter().map(|(_, size)| size).sum();
+        if ladder_pct > 100.0 + 1e-9 {
+            return Err(anyhow!(
+                "Config field `strategy.ladder`: tranches add up to {}% of the position, more than 100%",
+                ladder_pct
+            ));
+        }
+        if let Some(stop_loss_pct) = self.strategy.stop_loss_pct {
+            if stop_loss_pct <= 0.0 || stop_loss_pct >= 100.0 {
+                return Err(anyhow!(
+                    "Config field `strategy.stop_loss_pct`: {} must be in (0, 100)",
+                    stop_loss_pct
+                ));
+            }
+        }
+        if let Some(trailing_stop_pct) = self.strategy.trailing_stop_pct {
+            if trailing_stop_pct <= 0.0 || trailing_stop_pct >= 100.0 {
+                return Err(anyhow!(
+                    "Config field `strategy.trailing_stop_pct`: {} must be in (0, 100)",
+                    trailing_stop_pct
+                ));
+            }
+        }
+        if let Some(runner_trailing_stop_pct) = self.strategy.runner_trailing_stop_pct {
+            if runner_trailing_stop_pct <= 0.0 || runner_trailing_stop_pct >= 100.0 {
+                return Err(anyhow!(
+                    "Config field `strategy.runner_trailing_stop_pct`: {} must be in (0, 100)",
+                    runner_trailing_stop_pct
+                ));
+            }
+        }
+        if self.strategy.max_hold_secs == Some(0) {
+            return Err(anyhow!(
+                "Config field `strategy.max_hold_secs`: must be greater than 0"
+            ));
+        }
+        if self.strategy.creator_exit_pct <= 0.0 || self.strategy.creator_exit_pct > 100.0 {
+            return Err(anyhow!(
+                "Config field `strategy.creator_exit_pct`: {} must be in (0, 100]",
+                self.strategy.creator_exit_pct
+            ));
+        }
+
+        Pubkey::from_str(&self.price_feed.pyth_account).map_err(|e| {
+            anyhow!(
+                "Config field `price_feed.pyth_account`: {:?} is not a valid pubkey ({})",
+                self.price_feed.pyth_account,
+                e
+            )
+        })?;
+        if !self.price_feed.rest_url.starts_with("http://")
+            && !self.price_feed.rest_url.starts_with("https://")
+        {
+            return Err(anyhow!(
+                "Config field `price_feed.rest_url`: {:?} must start with http:// or https://",
+                self.price_feed.rest_url
+            ));
+        }
+        if !self.jupiter.api_url.starts_with("http://")
+            && !self.jupiter.api_url.starts_with("https://")
+        {
+            return Err(anyhow!(
+                "Config field `jupiter.api_url`: {:?} must start with http:// or https://",
+                self.jupiter.api_url
+            ));
+        }
+
+        if !self.jito.block_engine_url.starts_with("http://")
+            && !self.jito.block_engine_url.starts_with("https://")
+        {
+            return Err(anyhow!(
+                "Config field `jito.block_engine_url`: {:?} must start with http:// or https://",
+                self.jito.block_engine_url
+            ));
+        }
+
+        check_bps("snipe.slippage_bps", self.snipe.slippage_bps)?;
+        if self.snipe.skip_bundled_launches && self.snipe.launch_transactions == 0 {
+            return Err(anyhow!(
+                "Config field `snipe.launch_transactions`: must be greater than 0 to skip bundled launches"
+            ));
+        }
+        if self.snipe.skip_bundled_launches && self.snipe.launch_analysis_timeout_ms == 0 {
+            return Err(anyhow!(
+                "Config field `snipe.launch_analysis_timeout_ms`: must be greater than 0 to skip bundled launches"
+            ));
+        }
+        if self.snipe.blockhash_refresh_ms == 0 {
+            return Err(anyhow!(
+                "Config field `snipe.blockhash_refresh_ms`: must be greater than 0"
+            ));
+        }
+        if self.snipe.race_tiers.len() > MAX_RACE_TIERS {
+            return Err(anyhow!(
+                "Config field `snipe.race_tiers`: at most {} tiers",
+                MAX_RACE_TIERS
+            ));
+        }
+        if self
+            .snipe
+            .race_tiers
+            .iter()
+            .any(|(_, tip)| *tip > 0 && *tip < MIN_JITO_TIP_LAMPORTS)
+        {
+            return Err(anyhow!(
+                "Config field `snipe.race_tiers`: tips must be 0 or at least {} lamports",
+                MIN_JITO_TIP_LAMPORTS
+            ));
+        }
+
+        for (i, (_, lamports)) in self.launch.bundle_buys.iter().enumerate() {
+            if *lamports == 0 {
+                return Err(anyhow!(
+                    "Config field `launch.bundle_buys[{}]`: lamports must be greater than 0",
+                    i
+                ));
+            }
+        }
+
+        if self.safety.max_risk_score > 100 {
+            return Err(anyhow!(
+                "Config field `safety.max_risk_score`: {} must be at most 100",
+                self.safety.max_risk_score
+            ));
+        }
+        if self.safety.top_holders == 0 || self.safety.top_holders > 20 {
+            return Err(anyhow!(
+                "Config field `safety.top_holders`: {} must be in 1..=20",
+                self.safety.top_holders
+            ));
+        }
+
+        if !self.telegram.bot_token.is_empty() && self.telegram.chat_id == 0 {
+            return Err(anyhow!(
+                "Config field `telegram.chat_id`: must be set when `telegram.bot_token` is"
+            ));
+        }
+        std::net::SocketAddr::from_str(&self.api.bind).map_err(|e| {
+            anyhow!(
+                "Config field `api.bind`: {:?} is not a socket address ({})",
+                self.api.bind,
+                e
+            )
+        })?;
+
+        Ok(())
+    }
+}
+
+/// Config file path: `PUMPBOT_CONFIG` if set, else `config.toml`
+fn config_path() -> String {
+    std::env::var(CONFIG_PATH_ENV).unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string())
+}
+
+/// Load and validate the config. Call once at startup so bad configs fail fast.
+pub fn init() -> Result<&'static Config> {
+    let config = Config::load(&config_path())?;
+    Ok(CONFIG.get_or_init(|| config))
+}
+
+/// The loaded config. Falls back to defaults if `init` was never called and loading fails.
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(|| {
+        Config::load(&config_path()).unwrap_or_else(|e| {
+            // Logging reads the config, so it isn't installed yet
+            eprintln!("Failed to load config, using defaults: {}", e);
+            Config::default()
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_toml_and_validation() {
+        let config = Config::from_toml(
+            r#"
+            [rpc]
+            url = "https://my-node.example.com"
+
+            [trading]
+            sell_slippage_bps = 1000
+            mode = "paper"
+            sell_send_strategy = "protected"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.rpc.url, "https://my-node.example.com");
+        assert_eq!(config.trading.sell_slippage_bps, 1000);
+        assert_eq!(config.trading.buy_slippage_bps, 500);
+        assert_eq!(config.trading.mode, ExecutionMode::Paper);
+        assert_eq!(config.logging.format, LogFormat::Text);
+        assert_eq!(config.stream.backend, StreamBackend::WebSocket);
+        assert_eq!("json".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert_eq!(config.trading.send_strategy, SendStrategy::Single);
+        assert_eq!("spam".parse::<SendStrategy>().unwrap(), SendStrategy::Spam);
+        assert_eq!(config.trading.send_strategy_for(true), SendStrategy::Single);
+        assert_eq!(config.trading.send_strategy_for(false), SendStrategy::Protected);
+        assert_eq!(
+            "percent_of_balance".parse::<SizingMode>().unwrap(),
+            SizingMode::PercentOfBalance
+        );
+        assert!(config.validate().is_ok());
+
+        let bad = Config::from_toml("[trading]\nbuy_slippage_bps = 20000").unwrap();
+        let error = bad.validate().unwrap_err().to_string();
+        assert!(error.contains("trading.buy_slippage_bps"), "{}", error);
+
+        let bad = Config::from_toml("[stream]\nbackend = \"geyser\"").unwrap();
+        let error = bad.validate().unwrap_err().to_string();
+        assert!(error.contains("stream.geyser_url"), "{}", error);
+
+        let bad = Config::from_toml("[signer]\nbackend = \"remote\"\nurl = \"https://signer.local\"")
+            .unwrap();
+        let error = bad.validate().unwrap_err().to_string();
+        assert!(error.contains("wallet.private_key"), "{}", error);
+
+        let bad =
+            Config::from_toml("[rpc.rate_limits]\n\"https://other.example.com\" = 5.0").unwrap();
+        let error = bad.validate().unwrap_err().to_string();
+        assert!(error.contains("rpc.rate_limits"), "{}", error);
+
+        let bad = Config::from_toml("[strategy]\nladder = [[50.0, 60.0], [100.0, 60.0]]").unwrap();
+        let error = bad.validate().unwrap_err().to_string();
+        assert!(error.contains("strategy.ladder"), "{}", error);
+
+        assert!(Config::from_toml("[rpc]\nurl_typo = \"x\"").is_err());
+    }
+
+    #[test]
+    fn test_network_switches_default_urls() {
+        let mut config = Config::from_toml("[network]\ncluster = \"devnet\"").unwrap();
+        config.apply_network();
+        assert_eq!(config.rpc.url, "https://api.devnet.solana.com");
+        assert_eq!(config.rpc.ws_url, "wss://api.devnet.solana.com");
+        assert!(!config.jupiter.enabled);
+        assert!(config.validate().is_ok());
+
+        let mut config = Config::from_toml(
+            "[network]\ncluster = \"localnet\"\n[rpc]\nurl = \"http://127.0.0.1:9000\"",
+        )
+        .unwrap();
+        config.apply_network();
+        assert_eq!(config.rpc.url, "http://127.0.0.1:9000");
+        assert_eq!(config.rpc.ws_url, "ws://127.0.0.1:8900");
+
+        let mut config = Config::default();
+        config.apply_network();
+        assert_eq!(config.rpc.url, Network::Mainnet.rpc_url());
+        assert!(config.jupiter.enabled);
+        assert_eq!("localnet".parse::<Network>().unwrap(), Network::Localnet);
+
+        let mut bad = Config::default();
+        bad.network.cluster = Network::Devnet;
+        let error = bad.validate().unwrap_err().to_string();
+        assert!(error.contains("rpc.url"), "{}", error);
+    }
+}
@@ -0,0 +1,362 @@
+use anyhow::{anyhow, Result};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::events::{self, PumpEvent, StreamedEvent, TradeEvent};
+use crate::executor::{self, IdempotencyKey, TradeIntent};
+use crate::strategy::{Context, LiveMarket, Market, Strategy};
+use crate::{address_book, cal, config, error, pump_buy, safety, sizing};
+
+/// Copy-trading settings
+#[derive(Debug, Clone)]
+pub struct CopyTradeConfig {
+    pub ws_url: String,
+    pub rpc_url: String,
+    /// Wallets whose trades are mirrored
+    pub targets: Vec<Pubkey>,
+    /// Our SOL size relative to the target's (0.1 = a tenth of their size)
+    pub size_scale: f64,
+    /// Size buys with the `[sizing]` engine instead of scaling the target's size.
+    /// The per-trade and per-mint caps below still apply.
+    pub use_sizing: bool,
+    /// Cap on SOL spent by a single mirrored buy
+    pub max_sol_per_trade: u64,
+    /// Cap on total SOL spent on one mint
+    pub max_sol_per_mint: u64,
+    /// Ignore target buys smaller than this after scaling
+    pub min_sol_per_trade: u64,
+    /// Mirror target sells proportionally
+    pub mirror_sells: bool,
+    /// Wait before mirroring (lets obvious rugs reveal themselves)
+    pub delay: Duration,
+    pub slippage_bps: u64,
+}
+
+impl Default for CopyTradeConfig {
+    fn default() -> Self {
+        Self {
+            ws_url: config::get().rpc.ws_url.clone(),
+            rpc_url: config::get().rpc.url.clone(),
+            targets: Vec::new(),
+            size_scale: 0.1,
+            use_sizing: false,
+            max_sol_per_trade: 500_000_000,  // 0.5 SOL
+            max_sol_per_mint: 1_000_000_000, // 1 SOL
+            min_sol_per_trade: 10_000_000,   // 0.01 SOL
+            mirror_sells: true,
+            delay: Duration::from_millis(0),
+            slippage_bps: 1_000,
+        }
+    }
+}
+
+/// What the copy trader decided to do about a target's trade
+#[derive(Debug, Clone, PartialEq)]
+pub enum MirrorAction {
+    Buy {
+        mint: Pubkey,
+        sol_amount: u64,
+    },
+    /// Sell this fraction (0.0 - 1.0] of our holdings
+    Sell {
+        mint: Pubkey,
+        fraction: f64,
+    },
+}
+
+/// Mirrors pump.fun trades made by target wallets
+pub struct CopyTrader {
+    config: CopyTradeConfig,
+    spent_per_mint: HashMap<Pubkey, u64>,
+}
+
+impl CopyTrader {
+    pub fn new(config: CopyTradeConfig) -> Self {
+        Self {
+            config,
+            spent_per_mint: HashMap::new(),
+        }
+    }
+
+    /// Decide how to mirror a target trade. `target_remaining` is the target's
+    /// token balance after a sell, used to size our proportional exit. `sized` is the
+    /// sizing engine's buy amount, used instead of the scaled target size when set.
+    /// Blacklisted mints are refused by the executor, as for every other buy.
+    pub fn plan(
+        &mut self,
+        event: &TradeEvent,
+        target_remaining: u64,
+        sized: Option<u64>,
+    ) -> Option<MirrorAction> {
+        if !self.config.targets.contains(&event.user) {
+            return None;
+        }
+
+        if !event.is_buy {
+            if !self.config.mirror_sells || event.token_amount == 0 {
+                return None;
+            }
+            let fraction =
+                event.token_amount as f64 / (event.token_amount + target_remaining) as f64;
+            return Some(MirrorAction::Sell {
+                mint: event.mint,
+                fraction,
+            });
+        }
+
+        let spent = self.spent_per_mint.get(&event.mint).copied().unwrap_or(0);
+        let wanted =
+            sized.unwrap_or((event.sol_amount as f64 * self.config.size_scale) as u64);
+        let sol_amount = wanted
+            .min(self.config.max_sol_per_trade)
+            .min(self.config.max_sol_per_mint.saturating_sub(spent));
+
+        if sol_amount < self.config.min_sol_per_trade {
+            return None;
+        }
+
+        *self.spent_per_mint.entry(event.mint).or_insert(0) += sol_amount;
+        Some(MirrorAction::Buy {
+            mint: event.mint,
+            sol_amount,
+        })
+    }
+
+    /// Trade carrying out a mirror action on the bonding curve, None when we hold
+    /// nothing to sell. Safety checks need the chain, so a backtest skips them.
+    fn intent(
+        &self,
+        market: &dyn Market,
+        user: &Pubkey,
+        action: &MirrorAction,
+    ) -> error::Result<Option<TradeIntent>> {
+        Ok(Some(match action {
+            MirrorAction::Buy { mint, sol_amount } => {
+                if let Some(rpc) = market.rpc() {
+                    safety::gate(rpc, mint)?;
+                }
+                let curve = market.bonding_curve(mint)?;
+                let global = market.global(&curve);
+                let tokens = cal::get_tokens_for_sol(&global, Some(&curve), *sol_amount);
+                let max_sol_cost = pump_buy::with_slippage(*sol_amount, self.config.slippage_bps);
+                TradeIntent::Buy {
+                    mint: *mint,
+                    token_amount: tokens,
+                    max_sol_cost,
+                }
+            }
+            MirrorAction::Sell { mint, fraction } => {
+                let held = market.token_balance(user, mint)?;
+                let tokens = (held as f64 * fraction.min(1.0)) as u64;
+                if tokens == 0 {
+                    return Ok(None);
+                }
+                let curve = market.bonding_curve(mint)?;
+                let global = market.global(&curve);
+                let expected_sol = cal::get_sol_from_tokens(&global, Some(&curve), tokens);
+                let min_sol_output =
+                    expected_sol - expected_sol * self.config.slippage_bps.min(10_000) / 10_000;
+                TradeIntent::Sell {
+                    mint: *mint,
+                    token_amount: tokens,
+                    min_sol_output,
+                }
+            }
+        }))
+    }
+
+    /// Plan and build the trade mirroring a target's trade, if it should be mirrored.
+    /// Sleeps for `delay` before building it. Without the chain, `use_sizing` falls back
+    /// to the scaled target size.
+    fn mirror(
+        &mut self,
+        market: &dyn Market,
+        user: &Pubkey,
+        event: &TradeEvent,
+    ) -> Option<TradeIntent> {
+        if !self.config.targets.contains(&event.user) {
+            return None;
+        }
+        info!(
+            target = %address_book::label(&event.user),
+            mint = %event.mint,
+            is_buy = event.is_buy,
+            token_amount = event.token_amount,
+            sol_amount = event.sol_amount,
+            "Target traded"
+        );
+
+        let target_remaining = if event.is_buy {
+            0
+        } else {
+            market.token_balance(&event.user, &event.mint).unwrap_or(0)
+        };
+
+        let sized = match market.rpc() {
+            Some(rpc) if event.is_buy && self.config.use_sizing => {
+                match sizing::buy_amount(rpc, &event.mint) {
+                    Ok(lamports) => Some(lamports),
+                    Err(e) => {
+                        warn!(mint = %event.mint, error = %e, "Failed to size mirrored buy");
+                        return None;
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        let action = self.plan(event, target_remaining, sized)?;
+        // Replayed history has no time to wait out
+        if market.rpc().is_some() && !self.config.delay.is_zero() {
+            std::thread::sleep(self.config.delay);
+        }
+        info!(mint = %event.mint, ?action, "Mirroring trade");
+        self.intent(market, user, &action)
+            .map_err(|e| warn!(mint = %event.mint, error = %e, "Failed to mirror trade"))
+            .ok()
+            .flatten()
+    }
+
+    /// Subscribe to every target's transactions and mirror their trades until the streams close
+    pub fn run(&mut self, user: &Pubkey) -> Result<()> {
+        if self.config.targets.is_empty() {
+            return Err(anyhow!("No copy-trade targets configured"));
+        }
+
+        let market = LiveMarket::new(RpcClient::new(self.config.rpc_url.clone()));
+        let (tx, rx) = mpsc::channel::<(TradeEvent, Signature, usize)>();
+
+        for target in self.config.targets.clone() {
+            let tx = tx.clone();
+            let ws_url = self.config.ws_url.clone();
+            std::thread::spawn(move || {
+                let stream = match events::subscribe_events(&ws_url, &target) {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        warn!(
+                            target = %address_book::label(&target),
+                            error = %e,
+                            "Failed to subscribe to copy-trade target"
+                        );
+                        return;
+                    }
+                };
+                info!(target = %address_book::label(&target), "Watching copy-trade target");
+
+                for streamed in stream.streamed() {
+                    let StreamedEvent {
+                        signature,
+                        index,
+                        event: PumpEvent::Trade(event),
+                    } = streamed
+                    else {
+                        continue;
+                    };
+                    if event.user == target && tx.send((event, signature, index)).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        for (event, signature, index) in rx {
+            let Some(intent) = self.mirror(&market, user, &event) else {
+                continue;
+            };
+            // A trade the stream delivers again after reconnecting is mirrored only once,
+            // while identical trades in separate transactions are each mirrored
+            let key = IdempotencyKey::new(format!("copy-{}-{}", signature, index));
+            if let Err(e) = executor::global().execute_keyed(key, intent) {
+                warn!(mint = %event.mint, error = %e, "Failed to mirror trade");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Copy-trading under the strategy runner, which streams the targets' trades
+impl Strategy for CopyTrader {
+    fn name(&self) -> &str {
+        "copytrade"
+    }
+
+    fn subscriptions(&self) -> Vec<Pubkey> {
+        self.config.targets.clone()
+    }
+
+    fn on_price_update(&mut self, ctx: &Context, trade: &TradeEvent) -> Vec<TradeIntent> {
+        self.mirror(ctx.market, ctx.user, trade).into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_scales_and_caps_per_mint() {
+        let target = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let mut trader = CopyTrader::new(CopyTradeConfig {
+            targets: vec![target],
+            size_scale: 0.5,
+            max_sol_per_trade: 400_000_000,
+            max_sol_per_mint: 600_000_000,
+            ..Default::default()
+        });
+        let buy = TradeEvent {
+            mint,
+            sol_amount: 1_000_000_000,
+            token_amount: 1,
+            is_buy: true,
+            user: target,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            trader.plan(&buy, 0, None),
+            Some(MirrorAction::Buy {
+                mint,
+                sol_amount: 400_000_000
+            })
+        );
+        assert_eq!(
+            trader.plan(&buy, 0, None),
+            Some(MirrorAction::Buy {
+                mint,
+                sol_amount: 200_000_000
+            })
+        );
+        assert_eq!(trader.plan(&buy, 0, None), None);
+        let other = TradeEvent {
+            mint: Pubkey::new_unique(),
+            ..buy.clone()
+        };
+        assert_eq!(
+            trader.plan(&other, 0, Some(50_000_000)),
+            Some(MirrorAction::Buy {
+                mint: other.mint,
+                sol_amount: 50_000_000
+            })
+        );
+
+        let sell = TradeEvent {
+            is_buy: false,
+            token_amount: 250,
+            ..buy
+        };
+        assert_eq!(
+            trader.plan(&sell, 750, None),
+            Some(MirrorAction::Sell {
+                mint,
+                fraction: 0.25
+            })
+        );
+    }
+}
@@ -0,0 +1,354 @@
+use anyhow::Result;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::events::TradeEvent;
+use crate::executor::TradeIntent;
+use crate::portfolio::{Fill, Portfolio, Position, Side};
+use crate::strategy::{Context, Market, Strategy};
+use crate::{config, telegram};
+
+lazy_static::lazy_static! {
+    static ref PUMP_PROGRAM_ID: Pubkey = Pubkey::from_str("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P").unwrap();
+}
+
+/// Creator-dump exit settings
+#[derive(Debug, Clone)]
+pub struct CreatorExitConfig {
+    /// Percent of its holdings the creator has to sell or move out to trigger the exit
+    pub exit_pct: f64,
+    /// How often the creator's token accounts are re-read, catching transfers that
+    /// aren't trades
+    pub poll_interval: Duration,
+}
+
+impl Default for CreatorExitConfig {
+    /// Settings from the `[strategy]` section of `config.toml`
+    fn default() -> Self {
+        let config = config::get();
+        Self {
+            exit_pct: config.strategy.creator_exit_pct,
+            poll_interval: Duration::from_secs(config.strategy.poll_interval_secs),
+        }
+    }
+}
+
+/// The creator's holdings of a mint we hold
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CreatorWatch {
+    creator: Pubkey,
+    /// Most the creator has held since we started watching
+    peak: u64,
+    held: u64,
+    /// Our exit was sent and has neither filled nor failed yet
+    selling: bool,
+}
+
+impl CreatorWatch {
+    fn new(creator: Pubkey, held: u64) -> Self {
+        Self {
+            creator,
+            peak: held,
+            held,
+            selling: false,
+        }
+    }
+
+    fn update(&mut self, held: u64) {
+        self.held = held;
+        self.peak = self.peak.max(held);
+    }
+
+    /// Percent of its peak holdings the creator has sold or moved out
+    fn dumped_pct(&self) -> f64 {
+        if self.peak == 0 {
+            return 0.0;
+        }
+        (self.peak - self.held) as f64 / self.peak as f64 * 100.0
+    }
+}
+
+/// Sells a whole position as soon as the token's creator dumps. Creator trades on the
+/// curve are followed as they stream in, and the creator's token accounts are re-read
+/// every `poll_interval` for tokens moved out some other way.
+pub struct CreatorExitStrategy {
+    config: CreatorExitConfig,
+    watches: HashMap<Pubkey, CreatorWatch>,
+}
+
+impl CreatorExitStrategy {
+    pub fn new(config: CreatorExitConfig) -> Self {
+        Self {
+            config,
+            watches: HashMap::new(),
+        }
+    }
+
+    /// Also watch the creators of the positions already open in the portfolio. A position
+    /// whose creator can't be read is left unwatched.
+    pub fn with_open_positions(mut self, market: &dyn Market) -> Result<Self> {
+        for position in Portfolio::open_default()?.get_open_positions()? {
+            if let Err(e) = self.watch(market, &position.mint) {
+                warn!(mint = %position.mint, error = %e, "Failed to watch creator");
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn watched(&self) -> usize {
+        self.watches.len()
+    }
+
+    /// Start following `mint`'s creator from its current holdings
+    fn watch(&mut self, market: &dyn Market, mint: &Pubkey) -> Result<()> {
+        let creator = market.bonding_curve(mint)?.creator;
+        let held = market.token_balance(&creator, mint)?;
+        info!(%mint, %creator, held, "Watching creator");
+        self.watches.insert(*mint, CreatorWatch::new(creator, held));
+        Ok(())
+    }
+
+    /// Sell the whole position in `mint` at any price if its creator has dumped. The
+    /// creator stays watched until the sell fills, and while it is in flight nothing else
+    /// is sold.
+    fn check(&mut self, market: &dyn Market, mint: &Pubkey) -> Vec<TradeIntent> {
+        let Some(watch) = self.watches.get(mint).filter(|w| !w.selling) else {
+            return Vec::new();
+        };
+        let dumped_pct = watch.dumped_pct();
+        if dumped_pct < self.config.exit_pct {
+            return Vec::new();
+        }
+        let creator = watch.creator;
+        let token_amount = match market.position(mint) {
+            Ok(position) => position
+                .filter(Position::is_open)
+                .map_or(0, |p| p.token_amount),
+            Err(e) => {
+                warn!(%mint, error = %e, "Failed to read position");
+                return Vec::new();
+            }
+        };
+        if token_amount == 0 {
+            self.watches.remove(mint);
+            return Vec::new();
+        }
+        if let Some(watch) = self.watches.get_mut(mint) {
+            watch.selling = true;
+        }
+
+        warn!(%mint, %creator, dumped_pct, token_amount, "Creator dumping, selling position");
+        telegram::notify(format!(
+            "Creator {} has dumped {:.1}% of {} - selling {} tokens",
+            creator, dumped_pct, mint, token_amount
+        ));
+        vec![TradeIntent::Sell {
+            mint: *mint,
+            token_amount,
+            min_sol_output: 0,
+        }]
+    }
+}
+
+impl Strategy for CreatorExitStrategy {
+    fn name(&self) -> &str {
+        "creator_exit"
+    }
+
+    fn subscriptions(&self) -> Vec<Pubkey> {
+        vec![*PUMP_PROGRAM_ID]
+    }
+
+    fn timer_interval(&self) -> Option<Duration> {
+        Some(self.config.poll_interval)
+    }
+
+    fn on_price_update(&mut self, ctx: &Context, trade: &TradeEvent) -> Vec<TradeIntent> {
+        let Some(watch) = self.watches.get_mut(&trade.mint) else {
+            return Vec::new();
+        };
+        if trade.user != watch.creator {
+            return Vec::new();
+        }
+        let held = if trade.is_buy {
+            watch.held.saturating_add(trade.token_amount)
+        } else {
+            watch.held.saturating_sub(trade.token_amount)
+        };
+        watch.update(held);
+        self.check(ctx.market, &trade.mint)
+    }
+
+    /// Watch the creator of every mint bought, and stop once the position is closed. A
+    /// sell that leaves some of the position is followed by another.
+    fn on_fill(&mut self, ctx: &Context, fill: &Fill) -> Vec<TradeIntent> {
+        match (fill.side, self.watches.contains_key(&fill.mint)) {
+            (Side::Buy, false) => {
+                if let Err(e) = self.watch(ctx.market, &fill.mint) {
+                    warn!(mint = %fill.mint, error = %e, "Failed to watch creator");
+                }
+            }
+            (Side::Sell, true) => match ctx.market.position(&fill.mint) {
+                Ok(position) if position.as_ref().is_some_and(Position::is_open) => {
+                    if let Some(watch) = self.watches.get_mut(&fill.mint) {
+                        watch.selling = false;
+                    }
+                }
+                Ok(_) => {
+                    self.watches.remove(&fill.mint);
+                }
+                Err(e) => warn!(mint = %fill.mint, error = %e, "Failed to read position"),
+            },
+            _ => {}
+        }
+        Vec::new()
+    }
+
+    /// A failed exit leaves the position held, so the creator is checked again
+    fn on_trade_failed(&mut self, _ctx: &Context, intent: &TradeIntent) -> Vec<TradeIntent> {
+        if let TradeIntent::Sell { mint, .. } = intent {
+            if let Some(watch) = self.watches.get_mut(mint) {
+                watch.selling = false;
+            }
+        }
+        Vec::new()
+    }
+
+    fn on_timer(&mut self, ctx: &Context) -> Vec<TradeIntent> {
+        let mints: Vec<Pubkey> = self.watches.keys().copied().collect();
+        let mut intents = Vec::new();
+        for mint in mints {
+            let creator = self.watches[&mint].creator;
+            match ctx.market.token_balance(&creator, &mint) {
+                Ok(held) => {
+                    if let Some(watch) = self.watches.get_mut(&mint) {
+                        watch.update(held);
+                    }
+                    intents.extend(self.check(ctx.market, &mint));
+                }
+                Err(e) => warn!(%mint, %creator, error = %e, "Failed to read creator balance"),
+            }
+        }
+        intents
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounts::BondingCurve;
+    use crate::cal::Global;
+    use crate::error::{self, BotError};
+    use crate::indicators::Indicators;
+    use crate::portfolio;
+    use solana_client::rpc_client::RpcClient;
+
+    /// A market where we hold a position of the given size in every mint
+    struct Holding(u64);
+
+    impl Market for Holding {
+        fn rpc(&self) -> Option<&RpcClient> {
+            None
+        }
+
+        fn bonding_curve(&self, mint: &Pubkey) -> error::Result<BondingCurve> {
+            Err(BotError::AccountNotFound(*mint))
+        }
+
+        fn global(&self, _curve: &BondingCurve) -> Global {
+            Global::default()
+        }
+
+        fn token_balance(&self, _owner: &Pubkey, _mint: &Pubkey) -> error::Result<u64> {
+            Ok(0)
+        }
+
+        fn position(&self, mint: &Pubkey) -> Result<Option<Position>> {
+            let buy = Fill::new(*mint, Side::Buy, self.0, 10_000, 0, String::new());
+            Ok(portfolio::build_position(mint, &[buy]))
+        }
+    }
+
+    #[test]
+    fn test_creator_trades_count_toward_the_exit() {
+        let market = Holding(5_000);
+        let user = Pubkey::new_unique();
+        let indicators = Indicators::new();
+        let ctx = Context {
+            market: &market,
+            user: &user,
+            indicators: &indicators,
+        };
+        let (mint, creator) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut strategy = CreatorExitStrategy::new(CreatorExitConfig {
+            exit_pct: 50.0,
+            poll_interval: Duration::from_secs(2),
+        });
+        strategy
+            .watches
+            .insert(mint, CreatorWatch::new(creator, 1_000));
+        let trade = |user, is_buy, token_amount| TradeEvent {
+            mint,
+            sol_amount: 0,
+            token_amount,
+            is_buy,
+            user,
+            timestamp: 0,
+            virtual_sol_reserves: 0,
+            virtual_token_reserves: 0,
+            real_sol_reserves: 0,
+            real_token_reserves: 0,
+            fee_recipient: Pubkey::default(),
+            fee_basis_points: 0,
+            fee: 0,
+            creator,
+            creator_fee_basis_points: 0,
+            creator_fee: 0,
+        };
+
+        // Other wallets' sells and the creator's buys don't count as dumping
+        assert!(strategy
+            .on_price_update(&ctx, &trade(Pubkey::new_unique(), false, 900))
+            .is_empty());
+        assert!(strategy
+            .on_price_update(&ctx, &trade(creator, true, 1_000))
+            .is_empty());
+        assert!(strategy
+            .on_price_update(&ctx, &trade(creator, false, 900))
+            .is_empty());
+        let watch = &strategy.watches[&mint];
+        assert_eq!((watch.peak, watch.held), (2_000, 1_100));
+        assert!((watch.dumped_pct() - 45.0).abs() < 1e-9);
+
+        // Crossing the threshold sells the whole position, once
+        let sells = strategy.on_price_update(&ctx, &trade(creator, false, 100));
+        assert!(matches!(
+            sells[..],
+            [TradeIntent::Sell {
+                token_amount: 5_000,
+                min_sol_output: 0,
+                ..
+            }]
+        ));
+        assert!(strategy
+            .on_price_update(&ctx, &trade(creator, false, 100))
+            .is_empty());
+
+        // The creator stays watched until the sell fills, so a failed one is taken again
+        assert_eq!(strategy.watched(), 1);
+        strategy.on_trade_failed(&ctx, &sells[0]);
+        assert_eq!(
+            strategy
+                .on_price_update(&ctx, &trade(creator, false, 100))
+                .len(),
+            1
+        );
+
+        let mut empty = CreatorWatch::new(creator, 0);
+        empty.update(0);
+        assert_eq!(empty.dumped_pct(), 0.0);
+    }
+}
@@ -0,0 +1,279 @@
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use tracing::warn;
+
+use crate::error::BotError;
+use crate::events::{self, PumpEvent};
+use crate::portfolio::now_unix;
+use crate::{cal, config, signatures};
+
+/// How an earlier launch of a creator turned out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LaunchOutcome {
+    /// The curve completed and the token moved to PumpSwap
+    Migrated,
+    /// The creator sold on a curve that never completed
+    Dumped,
+    /// Still on the curve and the creator hasn't sold
+    Active,
+}
+
+/// One token a creator launched
+#[derive(Debug, Clone, PartialEq)]
+pub struct Launch {
+    pub mint: Pubkey,
+    /// Unix seconds
+    pub created_at: i64,
+    pub outcome: LaunchOutcome,
+}
+
+/// A creator's launches found in their recent transactions, newest first
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreatorHistory {
+    pub creator: Pubkey,
+    pub launches: Vec<Launch>,
+}
+
+impl CreatorHistory {
+    fn count(&self, outcome: LaunchOutcome) -> usize {
+        self.launches
+            .iter()
+            .filter(|l| l.outcome == outcome)
+            .count()
+    }
+
+    pub fn migrated(&self) -> usize {
+        self.count(LaunchOutcome::Migrated)
+    }
+
+    pub fn dumped(&self) -> usize {
+        self.count(LaunchOutcome::Dumped)
+    }
+}
+
+/// Classify a launch from whether its curve completed and whether the creator sold
+pub(crate) fn outcome(complete: bool, creator_sold: bool) -> LaunchOutcome {
+    match (complete, creator_sold) {
+        (true, _) => LaunchOutcome::Migrated,
+        (false, true) => LaunchOutcome::Dumped,
+        (false, false) => LaunchOutcome::Active,
+    }
+}
+
+/// Look up the launches in `creator`'s last `limit` transactions and how each turned out
+pub fn fetch_history(rpc: &RpcClient, creator: &Pubkey, limit: usize) -> Result<CreatorHistory> {
+    let history = signatures::recent(rpc, creator, limit)?;
+
+    let mut launched: Vec<(Pubkey, i64)> = Vec::new();
+    let mut sold = HashSet::new();
+    for status in history.iter().filter(|s| s.err.is_none()) {
+        let signature = Signature::from_str(&status.signature)?;
+        let events = match events::fetch_transaction_events(rpc, &signature) {
+            Ok(events) => events,
+            Err(e) => {
+                warn!(%signature, error = %e, "Skipping creator transaction");
+                continue;
+            }
+        };
+        for event in events {
+            match event {
+                PumpEvent::Create(e) if e.creator == *creator || e.user == *creator => {
+                    launched.push((e.mint, e.timestamp));
+                }
+                PumpEvent::Trade(e) if e.user == *creator && !e.is_buy => {
+                    sold.insert(e.mint);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mints: Vec<Pubkey> = launched.iter().map(|(mint, _)| *mint).collect();
+    let mut complete = HashMap::new();
+    for (mint, curve) in mints.iter().zip(cal::fetch_bonding_curves(rpc, &mints)) {
+        let done = match curve {
+            Ok(curve) => curve.complete,
+            Err(BotError::AccountNotFound(_)) => false,
+            Err(e) => return Err(e.into()),
+        };
+        complete.insert(*mint, done);
+    }
+
+    Ok(CreatorHistory {
+        creator: *creator,
+        launches: launched
+            .into_iter()
+            .map(|(mint, created_at)| Launch {
+                mint,
+                created_at,
+                outcome: outcome(complete[&mint], sold.contains(&mint)),
+            })
+            .collect(),
+    })
+}
+
+/// Whether a creator's launches are always or never bought
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListStatus {
+    Allow,
+    Deny,
+}
+
+impl ListStatus {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            ListStatus::Allow => "allow",
+            ListStatus::Deny => "deny",
+        }
+    }
+
+    pub(crate) fn parse(s: &str) -> Result<Self> {
+        match s {
+            "allow" => Ok(ListStatus::Allow),
+            "deny" => Ok(ListStatus::Deny),
+            other => Err(anyhow!("Unknown list status: {}", other)),
+        }
+    }
+}
+
+/// An allow- or deny-listed creator
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreatorEntry {
+    pub creator: Pubkey,
+    pub status: ListStatus,
+    pub reason: Option<String>,
+    pub added_at: i64,
+}
+
+/// Creator allow/deny list persisted in SQLite
+pub struct CreatorList {
+    conn: Connection,
+}
+
+impl CreatorList {
+    /// Open (or create) the creator list table in the database at `path`
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)
+            .map_err(|e| anyhow!("Failed to open creator database {}: {}", path, e))?;
+        Self::init(conn)
+    }
+
+    /// Open the list in the portfolio database
+    pub fn open_default() -> Result<Self> {
+        Self::open(&config::get().storage.db_path)
+    }
+
+    /// Open a throwaway in-memory list
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS creator_list (
+                creator TEXT PRIMARY KEY,
+                status TEXT NOT NULL,
+                reason TEXT,
+                added_at INTEGER NOT NULL
+            );",
+        )?;
+        Ok(Self { conn })
+    }
+
+    /// Put `creator` on the allow or deny list, replacing any earlier entry
+    pub fn set(&self, creator: &Pubkey, status: ListStatus, reason: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO creator_list (creator, status, reason, added_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![creator.to_string(), status.as_str(), reason, now_unix()],
+        )?;
+        Ok(())
+    }
+
+    /// Take `creator` off either list. Returns false if it wasn't listed.
+    pub fn remove(&self, creator: &Pubkey) -> Result<bool> {
+        let changed = self.conn.execute(
+            "DELETE FROM creator_list WHERE creator = ?1",
+            params![creator.to_string()],
+        )?;
+        Ok(changed == 1)
+    }
+
+    pub fn status(&self, creator: &Pubkey) -> Result<Option<ListStatus>> {
+        Ok(self
+            .query_entries("WHERE creator = ?1", params![creator.to_string()])?
+            .into_iter()
+            .next()
+            .map(|entry| entry.status))
+    }
+
+    /// Every listed creator, oldest first
+    pub fn entries(&self) -> Result<Vec<CreatorEntry>> {
+        self.query_entries("", params![])
+    }
+
+    fn query_entries(
+        &self,
+        filter: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<CreatorEntry>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT creator, status, reason, added_at FROM creator_list {} ORDER BY added_at",
+            filter
+        ))?;
+        let rows = stmt
+            .query_map(params, |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(|(creator, status, reason, added_at)| {
+                Ok(CreatorEntry {
+                    creator: Pubkey::from_str(&creator)?,
+                    status: ListStatus::parse(&status)?,
+                    reason,
+                    added_at,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outcomes_and_creator_list() {
+        assert_eq!(outcome(true, true), LaunchOutcome::Migrated);
+        assert_eq!(outcome(false, true), LaunchOutcome::Dumped);
+        assert_eq!(outcome(false, false), LaunchOutcome::Active);
+
+        let list = CreatorList::open_in_memory().unwrap();
+        let creator = Pubkey::new_unique();
+        assert_eq!(list.status(&creator).unwrap(), None);
+
+        list.set(&creator, ListStatus::Deny, Some("dumped 3 launches"))
+            .unwrap();
+        assert_eq!(list.status(&creator).unwrap(), Some(ListStatus::Deny));
+        list.set(&creator, ListStatus::Allow, None).unwrap();
+        let entries = list.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].status, ListStatus::Allow);
+        assert_eq!(entries[0].reason, None);
+
+        assert!(list.remove(&creator).unwrap());
+        assert!(!list.remove(&creator).unwrap());
+        assert!(list.entries().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,196 @@
+use once_cell::sync::Lazy;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::accounts::BondingCurve;
+use crate::cal::{self, MintExtensions, Quote};
+use crate::config;
+use crate::error;
+use crate::events::PumpEvent;
+
+// Constants
+/// How long parsed mint extensions are reused. They only change when a transfer fee is
+/// updated, which takes effect epochs later.
+const EXTENSIONS_TTL: Duration = Duration::from_secs(60);
+
+/// Cache shared by every strategy, with `rpc.curve_cache_ms` as its max age
+static CACHE: Lazy<CurveCache> = Lazy::new(CurveCache::from_config);
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Bonding curves by mint, reused until they are `max_age` old. Trades and completions
+/// seen on an event stream drop the mint's curve early, so a busy curve is refetched
+/// while a quiet one keeps being served from memory.
+pub struct CurveCache {
+    max_age: Duration,
+    curves: Mutex<HashMap<Pubkey, (Instant, BondingCurve)>>,
+    extensions: Mutex<HashMap<Pubkey, (Instant, MintExtensions)>>,
+}
+
+impl CurveCache {
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            curves: Mutex::new(HashMap::new()),
+            extensions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_config() -> Self {
+        Self::new(Duration::from_millis(config::get().rpc.curve_cache_ms))
+    }
+
+    fn cached(&self, mint: &Pubkey) -> Option<BondingCurve> {
+        let curves = lock(&self.curves);
+        let (fetched_at, curve) = curves.get(mint)?;
+        (fetched_at.elapsed() < self.max_age).then(|| curve.clone())
+    }
+
+    /// The curve of `mint`, fetched only if the cached one is missing or stale
+    pub fn get(&self, rpc: &RpcClient, mint: &Pubkey) -> error::Result<BondingCurve> {
+        if let Some(curve) = self.cached(mint) {
+            return Ok(curve);
+        }
+        let curve = cal::fetch_bonding_curve(rpc, mint)?;
+        self.insert(mint, curve.clone());
+        Ok(curve)
+    }
+
+    /// `get` for many mints, fetching every stale curve in one round trip (per 100
+    /// mints). Results are in the order of `mints`.
+    pub fn get_many(&self, rpc: &RpcClient, mints: &[Pubkey]) -> Vec<error::Result<BondingCurve>> {
+        let mut curves: Vec<_> = mints.iter().map(|mint| self.cached(mint)).collect();
+        let stale: Vec<Pubkey> = mints
+            .iter()
+            .zip(&curves)
+            .filter(|(_, curve)| curve.is_none())
+            .map(|(mint, _)| *mint)
+            .collect();
+        let mut fetched = cal::fetch_bonding_curves(rpc, &stale).into_iter();
+        curves
+            .iter_mut()
+            .zip(mints)
+            .map(|(curve, mint)| match curve.take() {
+                Some(curve) => Ok(curve),
+                None => {
+                    let curve = fetched.next().expect("one fetch per stale mint")?;
+                    self.insert(mint, curve.clone());
+                    Ok(curve)
+                }
+            })
+            .collect()
+    }
+
+    /// Store a curve known to be current, e.g. from a bonding curve stream
+    pub fn insert(&self, mint: &Pubkey, curve: BondingCurve) {
+        lock(&self.curves).insert(*mint, (Instant::now(), curve));
+    }
+
+    /// Drop the cached curve of `mint` so the next `get` refetches it
+    pub fn invalidate(&self, mint: &Pubkey) {
+        lock(&self.curves).remove(mint);
+    }
+
+    /// Drop the curve an event changed
+    pub fn on_event(&self, event: &PumpEvent) {
+        match event {
+            PumpEvent::Trade(_) | PumpEvent::Complete(_) => self.invalidate(&event.mint()),
+            PumpEvent::Create(_) => {}
+        }
+    }
+
+    /// Token-2022 extensions of `mint`, reused for `EXTENSIONS_TTL`
+    pub fn mint_extensions(&self, rpc: &RpcClient, mint: &Pubkey) -> error::Result<MintExtensions> {
+        if let Some((fetched_at, extensions)) = lock(&self.extensions).get(mint) {
+            if fetched_at.elapsed() < EXTENSIONS_TTL {
+                return Ok(extensions.clone());
+            }
+        }
+        let extensions = cal::fetch_mint_extensions(rpc, mint)?;
+        lock(&self.extensions).insert(*mint, (Instant::now(), extensions.clone()));
+        Ok(extensions)
+    }
+
+    /// `cal::quote_buy` against the cached curve
+    pub fn quote_buy(
+        &self,
+        rpc: &RpcClient,
+        mint: &Pubkey,
+        sol_amount: u64,
+    ) -> error::Result<Quote> {
+        let curve = self.get(rpc, mint)?;
+        let extensions = self.mint_extensions(rpc, mint)?;
+        cal::quote_buy_on_curve(rpc, mint, &curve, &extensions, sol_amount)
+    }
+
+    /// `cal::quote_sell` against the cached curve
+    pub fn quote_sell(
+        &self,
+        rpc: &RpcClient,
+        mint: &Pubkey,
+        token_amount: u64,
+    ) -> error::Result<Quote> {
+        let curve = self.get(rpc, mint)?;
+        let extensions = self.mint_extensions(rpc, mint)?;
+        cal::quote_sell_on_curve(rpc, mint, &curve, &extensions, token_amount)
+    }
+}
+
+/// The cache shared by strategies
+pub fn global() -> &'static CurveCache {
+    &CACHE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{CreateEvent, TradeEvent};
+
+    #[test]
+    fn test_cache_serves_fresh_curves_until_invalidated() {
+        // Every fetch fails
+        let rpc = RpcClient::new_mock("fails".to_string());
+        let cache = CurveCache::new(Duration::from_secs(60));
+        let mint = Pubkey::new_unique();
+        let curve = cal::new_bonding_curve(&cal::Global::default());
+
+        assert!(cache.get(&rpc, &mint).is_err());
+        cache.insert(&mint, curve.clone());
+        let cached = cache.get(&rpc, &mint).unwrap();
+        assert_eq!(cached.virtual_sol_reserves, curve.virtual_sol_reserves);
+        assert!(cache.get_many(&rpc, &[mint]).into_iter().all(|c| c.is_ok()));
+
+        // A launch doesn't touch existing curves, a trade on the mint does
+        let create = PumpEvent::Create(CreateEvent {
+            name: String::new(),
+            symbol: String::new(),
+            uri: String::new(),
+            mint: Pubkey::new_unique(),
+            bonding_curve: Pubkey::new_unique(),
+            user: Pubkey::new_unique(),
+            creator: Pubkey::new_unique(),
+            timestamp: 0,
+            virtual_token_reserves: 0,
+            virtual_sol_reserves: 0,
+            real_token_reserves: 0,
+            token_total_supply: 0,
+        });
+        cache.on_event(&create);
+        assert!(cache.get(&rpc, &mint).is_ok());
+        cache.on_event(&PumpEvent::Trade(TradeEvent {
+            mint,
+            ..Default::default()
+        }));
+        assert!(cache.get(&rpc, &mint).is_err());
+
+        // With no max age every call refetches
+        let uncached = CurveCache::new(Duration::ZERO);
+        uncached.insert(&mint, curve);
+        assert!(uncached.get(&rpc, &mint).is_err());
+    }
+}
@@ -0,0 +1,178 @@
+use anyhow::{anyhow, Result};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::time::Duration;
+
+use crate::accounts::BondingCurve;
+use crate::cal::{self, Global};
+use crate::events::TradeEvent;
+use crate::fees;
+use crate::portfolio::Side;
+
+/// Net trading on one bonding curve between two snapshots of it. Buys and sells in
+/// between offset each other, so only the net flow is visible: it is a lower bound on
+/// the volume actually traded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CurveDiff {
+    /// Lamports that entered the reserves, fees excluded (negative = left them)
+    pub net_sol: i64,
+    /// Tokens bought off the curve (negative = sold back into it)
+    pub net_tokens: i64,
+    /// Protocol and creator fees on the net flow, estimated at the current rates
+    pub estimated_fees: u64,
+    /// The curve completed in between
+    pub completed: bool,
+}
+
+impl CurveDiff {
+    /// Infer the net flow from `before` to `after`. Errors when the snapshots can't be of
+    /// the same curve in that order: a different creator, reserves moving inconsistently
+    /// or a completion being undone.
+    pub fn between(global: &Global, before: &BondingCurve, after: &BondingCurve) -> Result<Self> {
+        if before.creator != after.creator {
+            return Err(anyhow!("Snapshots belong to curves of different creators"));
+        }
+        if before.complete && !after.complete {
+            return Err(anyhow!(
+                "Snapshots are out of order: the curve was already complete"
+            ));
+        }
+
+        let delta = |from: u64, to: u64| to as i64 - from as i64;
+        let net_sol = delta(before.real_sol_reserves, after.real_sol_reserves);
+        let net_tokens = delta(after.real_token_reserves, before.real_token_reserves);
+        // Every trade moves the virtual reserves by exactly as much as the real ones
+        if delta(before.virtual_sol_reserves, after.virtual_sol_reserves) != net_sol
+            || delta(after.virtual_token_reserves, before.virtual_token_reserves) != net_tokens
+        {
+            return Err(anyhow!(
+                "Virtual and real reserves moved by different amounts between snapshots"
+            ));
+        }
+        if net_sol.signum() * net_tokens.signum() < 0 {
+            return Err(anyhow!(
+                "SOL and tokens moved the same way between snapshots ({} lamports, {} tokens)",
+                net_sol,
+                net_tokens
+            ));
+        }
+
+        Ok(Self {
+            net_sol,
+            net_tokens,
+            estimated_fees: cal::get_fee(global, after, net_sol.unsigned_abs(), false),
+            completed: !before.complete && after.complete,
+        })
+    }
+
+    /// Net flow of decoded trade `events`, fees as reported by them
+    pub fn from_events(events: &[TradeEvent]) -> Self {
+        events.iter().fold(Self::default(), |mut diff, event| {
+            let sign = if event.is_buy { 1 } else { -1 };
+            diff.net_sol += sign * event.sol_amount as i64;
+            diff.net_tokens += sign * event.token_amount as i64;
+            diff.estimated_fees += event.fee + event.creator_fee;
+            diff
+        })
+    }
+
+    /// Which side dominated, or None if the curve didn't move
+    pub fn side(&self) -> Option<Side> {
+        match self.net_tokens.signum() {
+            1 => Some(Side::Buy),
+            -1 => Some(Side::Sell),
+            _ => None,
+        }
+    }
+
+    /// Net lamports buyers paid (positive) or sellers received (negative), fees included.
+    /// Buyers pay fees on top of what enters the reserves, sellers out of what leaves them.
+    pub fn net_sol_with_fees(&self) -> i64 {
+        self.net_sol + self.estimated_fees as i64
+    }
+
+    /// Flow in this diff that `events` don't account for, as (lamports, tokens). Zero
+    /// for both when every trade between the snapshots was decoded.
+    pub fn unexplained(&self, events: &[TradeEvent]) -> (i64, i64) {
+        let decoded = Self::from_events(events);
+        (
+            self.net_sol - decoded.net_sol,
+            self.net_tokens - decoded.net_tokens,
+        )
+    }
+}
+
+/// Snapshot `mint`'s curve every `interval` and pass each inferred diff to `on_diff`
+/// until it returns false or the curve completes
+pub fn watch(
+    rpc: &RpcClient,
+    mint: &Pubkey,
+    interval: Duration,
+    mut on_diff: impl FnMut(&CurveDiff, &BondingCurve) -> bool,
+) -> Result<()> {
+    let mut before = cal::fetch_bonding_curve(rpc, mint)?;
+    let global = fees::current_global(rpc, &before);
+    loop {
+        std::thread::sleep(interval);
+        let after = cal::fetch_bonding_curve(rpc, mint)?;
+        let diff = CurveDiff::between(&global, &before, &after)?;
+        if !on_diff(&diff, &after) || diff.completed {
+            return Ok(());
+        }
+        before = after;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_matches_simulated_trades() {
+        let global = Global::default();
+        let mut before = cal::new_bonding_curve(&global);
+        before.creator = Pubkey::new_unique();
+
+        let bought = cal::curve_after_buy(&global, &before, 2_000_000_000);
+        let tokens = before.real_token_reserves - bought.real_token_reserves;
+        let after = cal::curve_after_sell(&global, &bought, tokens / 2);
+
+        let diff = CurveDiff::between(&global, &before, &after).unwrap();
+        assert_eq!(diff.side(), Some(Side::Buy));
+        assert_eq!(diff.net_tokens as u64, tokens - tokens / 2);
+        assert_eq!(
+            diff.net_sol as u64,
+            after.real_sol_reserves - before.real_sol_reserves
+        );
+        assert!(diff.net_sol_with_fees() > diff.net_sol);
+        assert!(!diff.completed);
+
+        let events = [
+            TradeEvent {
+                sol_amount: bought.real_sol_reserves - before.real_sol_reserves,
+                token_amount: tokens,
+                is_buy: true,
+                ..Default::default()
+            },
+            TradeEvent {
+                sol_amount: bought.real_sol_reserves - after.real_sol_reserves,
+                token_amount: tokens / 2,
+                is_buy: false,
+                ..Default::default()
+            },
+        ];
+        assert_eq!(diff.unexplained(&events), (0, 0));
+        assert_eq!(
+            diff.unexplained(&events[..1]),
+            (-(events[1].sol_amount as i64), -((tokens / 2) as i64))
+        );
+
+        let reversed = CurveDiff::between(&global, &after, &before).unwrap();
+        assert_eq!(reversed.side(), Some(Side::Sell));
+        assert_eq!(reversed.net_sol, -diff.net_sol);
+
+        let mut other = after.clone();
+        other.creator = Pubkey::new_unique();
+        assert!(CurveDiff::between(&global, &before, &other).is_err());
+    }
+}
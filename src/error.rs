@@ -0,0 +1,348 @@
+use solana_client::client_error::ClientError;
+use solana_sdk::{program_error::ProgramError, pubkey::Pubkey};
+use std::fmt;
+use thiserror::Error;
+
+use crate::pump_idl::PUMP_PROGRAM_ID;
+use crate::sender::TradeStatus;
+
+// Constants
+/// pump.fun `TooMuchSolRequired`
+const TOO_MUCH_SOL_REQUIRED: u32 = 6002;
+/// pump.fun `TooLittleSolReceived`
+const TOO_LITTLE_SOL_RECEIVED: u32 = 6003;
+/// pump.fun `BondingCurveComplete`
+const BONDING_CURVE_COMPLETE: u32 = 6005;
+/// pump.fun error codes from its IDL, for failures without an Anchor error log
+const PUMP_ERRORS: [(u32, &str); 14] = [
+    (6000, "NotAuthorized"),
+    (6001, "AlreadyInitialized"),
+    (TOO_MUCH_SOL_REQUIRED, "TooMuchSolRequired"),
+    (TOO_LITTLE_SOL_RECEIVED, "TooLittleSolReceived"),
+    (6004, "MintDoesNotMatchBondingCurve"),
+    (BONDING_CURVE_COMPLETE, "BondingCurveComplete"),
+    (6006, "BondingCurveNotComplete"),
+    (6007, "NotInitialized"),
+    (6008, "WithdrawTooFrequent"),
+    (6020, "BuyZeroAmount"),
+    (6021, "NotEnoughTokensToBuy"),
+    (6022, "SellZeroAmount"),
+    (6023, "NotEnoughTokensToSell"),
+    (6024, "Overflow"),
+];
+/// Anchor error names that mean the price moved past the slippage limit (pump.fun, PumpSwap)
+const SLIPPAGE_ERRORS: [&str; 3] = [
+    "TooMuchSolRequired",
+    "TooLittleSolReceived",
+    "ExceededSlippage",
+];
+
+/// Failure modes of the trade paths that callers may want to branch on
+#[derive(Debug, Error)]
+pub enum BotError {
+    #[error("{mint} has migrated off its bonding curve")]
+    CurveMigrated { mint: Pubkey },
+
+    #[error("Insufficient SOL balance: need {needed} lamports, have {available}")]
+    InsufficientBalance { needed: u64, available: u64 },
+
+    #[error("Insufficient token balance: need {needed}, have {available}")]
+    InsufficientTokens { needed: u64, available: u64 },
+
+    /// The program rejected the trade because the price moved past the slippage limit
+    #[error("Slippage exceeded: {0}")]
+    SlippageExceeded(String),
+
+    /// Boxed because the client error is much larger than every other variant
+    #[error("RPC error: {0}")]
+    RpcError(Box<ClientError>),
+
+    #[error("Simulation failed: {0}")]
+    SimulationFailed(#[from] SimulationError),
+
+    /// Token-2022 extensions that let someone else take or freeze the tokens
+    #[error("{mint} has dangerous Token-2022 extensions: {extensions}")]
+    DangerousMint { mint: Pubkey, extensions: String },
+
+    /// Pre-buy safety checks scored the mint above `safety.max_risk_score`
+    #[error("{mint} failed safety checks with risk {score}: {reasons}")]
+    UnsafeMint {
+        mint: Pubkey,
+        score: u32,
+        reasons: String,
+    },
+
+    /// A buy would take exposure past a `[sizing]` limit
+    #[error("Buy of {requested} lamports on {mint} exceeds the {scope} exposure limit {limit}")]
+    ExposureLimit {
+        mint: Pubkey,
+        scope: &'static str,
+        requested: u64,
+        current: u64,
+        limit: u64,
+    },
+
+    /// A buy on a new mint would open more positions than `sizing.max_open_positions`
+    #[error("Buy on {mint} would open more than {limit} positions")]
+    PositionLimit { mint: Pubkey, open: usize, limit: usize },
+
+    /// The mint, its creator or its symbol is blacklisted, or isn't allow-listed while
+    /// `safety.allowlist_only` is set
+    #[error("Buy of {mint} refused: {reason}")]
+    Blacklisted { mint: Pubkey, reason: String },
+
+    /// A buy would leave a position that can't be sold in one go within
+    /// `sizing.max_exit_impact_bps`
+    #[error("Buy on {mint} leaves a position whose exit moves the price {impact_bps} bps, over the limit {limit_bps}")]
+    ExitLiquidity {
+        mint: Pubkey,
+        impact_bps: u64,
+        limit_bps: u64,
+    },
+
+    /// A trade would move the price past `trading.max_price_impact_bps` and wasn't forced
+    #[error("{side} on {mint} moves the price {impact_bps} bps, over the limit {limit_bps}")]
+    PriceImpactTooHigh {
+        mint: Pubkey,
+        side: &'static str,
+        impact_bps: u64,
+        limit_bps: u64,
+    },
+
+    /// The executor already accepted a trade under this idempotency key
+    #[error("Duplicate trade: key {0} was already submitted")]
+    DuplicateTrade(String),
+
+    #[error("Account not found: {0}")]
+    AccountNotFound(Pubkey),
+
+    /// Landed (or failed preflight) with an error other than slippage
+    #[error("Transaction failed: {0}")]
+    TransactionFailed(String),
+
+    #[error("Transaction expired before landing")]
+    TransactionExpired,
+
+    #[error("Failed to build instruction: {0}")]
+    InvalidInstruction(#[from] ProgramError),
+
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+pub type Result<T> = std::result::Result<T, BotError>;
+
+impl From<ClientError> for BotError {
+    fn from(e: ClientError) -> Self {
+        BotError::RpcError(Box::new(e))
+    }
+}
+
+/// Extract the custom program error code from a transaction error message
+fn custom_error_code(error: &str) -> Option<u32> {
+    let hex = error.split("custom program error: 0x").nth(1)?;
+    let digits: String = hex.chars().take_while(|c| c.is_ascii_hexdigit()).collect();
+    u32::from_str_radix(&digits, 16).ok()
+}
+
+/// What a failed simulation ran into
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimulationFailure {
+    /// The price moved past the trade's slippage limit
+    SlippageExceeded,
+    /// The bonding curve completed and the mint trades on PumpSwap now
+    CurveComplete,
+    /// Not enough SOL or tokens for the trade, its fees or rent
+    InsufficientFunds,
+    /// Any other program error, named when the logs or the pump.fun IDL name it
+    ProgramError {
+        code: u32,
+        name: Option<String>,
+    },
+    Other,
+}
+
+impl fmt::Display for SimulationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimulationFailure::SlippageExceeded => write!(f, "slippage exceeded"),
+            SimulationFailure::CurveComplete => write!(f, "bonding curve complete"),
+            SimulationFailure::InsufficientFunds => write!(f, "insufficient funds"),
+            SimulationFailure::ProgramError {
+                code,
+                name: Some(name),
+            } => write!(f, "program error {} ({})", code, name),
+            SimulationFailure::ProgramError { code, name: None } => {
+                write!(f, "program error {}", code)
+            }
+            SimulationFailure::Other => write!(f, "transaction error"),
+        }
+    }
+}
+
+/// A simulated transaction that failed, classified from its error and program logs
+#[derive(Debug, Clone, Error)]
+#[error("{failure}: {error}")]
+pub struct SimulationError {
+    pub failure: SimulationFailure,
+    /// The transaction error as the RPC reported it
+    pub error: String,
+    pub logs: Vec<String>,
+}
+
+/// Name and number of the error an Anchor program logged before failing
+fn anchor_error(logs: &[String]) -> Option<(String, u32)> {
+    logs.iter().find_map(|log| {
+        let rest = log.split("Error Code: ").nth(1)?;
+        let (name, rest) = rest.split_once(". Error Number: ")?;
+        let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+        Some((name.to_string(), digits.parse().ok()?))
+    })
+}
+
+/// Code the pump.fun program failed with. Without logs, assume the custom error in the
+/// transaction error came from pump.fun.
+fn pump_error_code(error: &str, logs: &[String]) -> Option<u32> {
+    if logs.is_empty() {
+        return custom_error_code(error);
+    }
+    let failed = format!("Program {} failed: ", *PUMP_PROGRAM_ID);
+    logs.iter()
+        .find_map(|log| custom_error_code(log.strip_prefix(&failed)?))
+}
+
+impl SimulationError {
+    /// Classify a failed simulation
+    pub fn new(error: String, logs: Vec<String>) -> Self {
+        let program_error = anchor_error(&logs).or_else(|| {
+            let code = pump_error_code(&error, &logs)?;
+            let name = PUMP_ERRORS.iter().find(|(c, _)| *c == code)?.1;
+            Some((name.to_string(), code))
+        });
+        let insufficient = std::iter::once(&error).chain(&logs).any(|line| {
+            let line = line.to_lowercase();
+            line.contains("insufficient funds") || line.contains("insufficient lamports")
+        });
+
+        let failure = match program_error {
+            Some((name, _)) if SLIPPAGE_ERRORS.contains(&name.as_str()) => {
+                SimulationFailure::SlippageExceeded
+            }
+            Some((name, _)) if name == "BondingCurveComplete" => SimulationFailure::CurveComplete,
+            Some((name, _)) if name.starts_with("NotEnoughTokens") => {
+                SimulationFailure::InsufficientFunds
+            }
+            _ if insufficient => SimulationFailure::InsufficientFunds,
+            Some((name, code)) => SimulationFailure::ProgramError {
+                code,
+                name: Some(name),
+            },
+            None => match custom_error_code(&error) {
+                Some(code) => SimulationFailure::ProgramError { code, name: None },
+                None => SimulationFailure::Other,
+            },
+        };
+        Self {
+            failure,
+            error,
+            logs,
+        }
+    }
+}
+
+impl BotError {
+    /// Error for a trade that did not confirm. None for confirmed trades.
+    pub fn from_trade_status(mint: &Pubkey, status: &TradeStatus) -> Option<Self> {
+        match status {
+            TradeStatus::Confirmed => None,
+            TradeStatus::Expired => Some(BotError::TransactionExpired),
+            TradeStatus::Failed(error) => Some(match custom_error_code(error) {
+                Some(TOO_MUCH_SOL_REQUIRED) | Some(TOO_LITTLE_SOL_RECEIVED) => {
+                    BotError::SlippageExceeded(error.clone())
+                }
+                Some(BONDING_CURVE_COMPLETE) => BotError::CurveMigrated { mint: *mint },
+                _ => BotError::TransactionFailed(error.clone()),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classifies_program_errors() {
+        let mint = Pubkey::new_unique();
+        let failed = |e: &str| BotError::from_trade_status(&mint, &TradeStatus::Failed(e.into()));
+
+        assert!(BotError::from_trade_status(&mint, &TradeStatus::Confirmed).is_none());
+        assert!(matches!(
+            failed("Error processing Instruction 2: custom program error: 0x1773"),
+            Some(BotError::SlippageExceeded(_))
+        ));
+        assert!(matches!(
+            failed("Error processing Instruction 1: custom program error: 0x1775"),
+            Some(BotError::CurveMigrated { .. })
+        ));
+        assert!(matches!(
+            failed("InsufficientFundsForRent"),
+            Some(BotError::TransactionFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_classifies_simulation_logs() {
+        let logs = |lines: &[&str]| lines.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+        let failure =
+            |error: &str, lines: &[&str]| SimulationError::new(error.into(), logs(lines)).failure;
+        let error = "Error processing Instruction 3: custom program error: 0x1772";
+
+        assert_eq!(
+            failure(
+                error,
+                &[
+                    "Program 6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P invoke [1]",
+                    "Program log: AnchorError thrown in programs/pump/src/lib.rs:633. \
+                     Error Code: TooMuchSolRequired. Error Number: 6002. \
+                     Error Message: slippage: Too much SOL required.",
+                    "Program 6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P failed: \
+                     custom program error: 0x1772",
+                ]
+            ),
+            SimulationFailure::SlippageExceeded
+        );
+        assert_eq!(
+            failure(error.replace("1772", "1775").as_str(), &[]),
+            SimulationFailure::CurveComplete
+        );
+        assert_eq!(
+            failure(
+                "Error processing Instruction 2: custom program error: 0x1",
+                &[
+                    "Program 11111111111111111111111111111111 invoke [2]",
+                    "Transfer: insufficient lamports 1000, need 2000",
+                    "Program 11111111111111111111111111111111 failed: custom program error: 0x1",
+                ]
+            ),
+            SimulationFailure::InsufficientFunds
+        );
+        assert_eq!(
+            failure(
+                "Error processing Instruction 2: custom program error: 0x1774",
+                &[
+                    "Program 6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P failed: \
+                     custom program error: 0x1774"
+                ]
+            ),
+            SimulationFailure::ProgramError {
+                code: 6004,
+                name: Some("MintDoesNotMatchBondingCurve".to_string())
+            }
+        );
+        assert_eq!(
+            failure("Blockhash not found", &[]),
+            SimulationFailure::Other
+        );
+    }
+}
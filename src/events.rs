@@ -0,0 +1,350 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_client::{
+    pubsub_client::{LogsSubscription, PubsubClient},
+    rpc_client::RpcClient,
+    rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter},
+};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::{EncodedConfirmedTransactionWithStatusMeta, UiInstruction};
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use crate::config::{self, StreamBackend};
+use crate::{geyser, tx_fees};
+
+// Constants
+/// Prefix of the self-CPI instruction data Anchor's `emit_cpi!` writes events with
+pub const EVENT_IX_TAG: [u8; 8] = [228, 69, 165, 46, 81, 203, 154, 29];
+
+/// An event emitted by a program, identified by the first 8 bytes of
+/// `sha256("event:<Name>")`
+pub trait AnchorEvent: BorshDeserialize {
+    const DISCRIMINATOR: [u8; 8];
+    const NAME: &'static str;
+
+    /// Check the discriminator and deserialize. Trailing bytes are ignored so
+    /// fields appended by program upgrades don't break parsing.
+    fn try_from_event_data(data: &[u8]) -> Result<Self> {
+        if data.len() < 8 || data[..8] != Self::DISCRIMINATOR {
+            return Err(anyhow!("Event is not a {}", Self::NAME));
+        }
+        Self::deserialize(&mut &data[8..])
+            .map_err(|e| anyhow!("Failed to deserialize {}: {}", Self::NAME, e))
+    }
+}
+
+/// A buy or sell on the bonding curve
+#[derive(Debug, Clone, Default, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct TradeEvent {
+    pub mint: Pubkey,
+    pub sol_amount: u64,
+    pub token_amount: u64,
+    pub is_buy: bool,
+    pub user: Pubkey,
+    /// Unix seconds
+    pub timestamp: i64,
+    pub virtual_sol_reserves: u64,
+    pub virtual_token_reserves: u64,
+    pub real_sol_reserves: u64,
+    pub real_token_reserves: u64,
+    pub fee_recipient: Pubkey,
+    pub fee_basis_points: u64,
+    /// Protocol fee in lamports
+    pub fee: u64,
+    pub creator: Pubkey,
+    pub creator_fee_basis_points: u64,
+    /// Creator fee in lamports
+    pub creator_fee: u64,
+}
+
+impl AnchorEvent for TradeEvent {
+    const DISCRIMINATOR: [u8; 8] = [189, 219, 127, 211, 78, 230, 97, 238];
+    const NAME: &'static str = "TradeEvent";
+}
+
+/// A new token launched on a bonding curve
+#[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct CreateEvent {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub mint: Pubkey,
+    pub bonding_curve: Pubkey,
+    pub user: Pubkey,
+    pub creator: Pubkey,
+    /// Unix seconds
+    pub timestamp: i64,
+    pub virtual_token_reserves: u64,
+    pub virtual_sol_reserves: u64,
+    pub real_token_reserves: u64,
+    pub token_total_supply: u64,
+}
+
+impl AnchorEvent for CreateEvent {
+    const DISCRIMINATOR: [u8; 8] = [27, 114, 169, 77, 222, 235, 99, 118];
+    const NAME: &'static str = "CreateEvent";
+}
+
+/// A bonding curve filling up, after which the token migrates to PumpSwap
+#[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
+pub struct CompleteEvent {
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub bonding_curve: Pubkey,
+    /// Unix seconds
+    pub timestamp: i64,
+}
+
+impl AnchorEvent for CompleteEvent {
+    const DISCRIMINATOR: [u8; 8] = [95, 114, 97, 156, 212, 46, 152, 8];
+    const NAME: &'static str = "CompleteEvent";
+}
+
+/// Any pump.fun event the bot understands
+#[derive(Debug, Clone, PartialEq)]
+pub enum PumpEvent {
+    Trade(TradeEvent),
+    Create(CreateEvent),
+    Complete(CompleteEvent),
+}
+
+impl PumpEvent {
+    pub fn mint(&self) -> Pubkey {
+        match self {
+            PumpEvent::Trade(e) => e.mint,
+            PumpEvent::Create(e) => e.mint,
+            PumpEvent::Complete(e) => e.mint,
+        }
+    }
+
+    /// Unix seconds
+    pub fn timestamp(&self) -> i64 {
+        match self {
+            PumpEvent::Trade(e) => e.timestamp,
+            PumpEvent::Create(e) => e.timestamp,
+            PumpEvent::Complete(e) => e.timestamp,
+        }
+    }
+}
+
+/// Event data as the program emits it: the discriminator followed by the borsh fields
+pub fn encode_event(event: &PumpEvent) -> Result<Vec<u8>> {
+    let (mut data, fields) = match event {
+        PumpEvent::Trade(e) => (TradeEvent::DISCRIMINATOR.to_vec(), borsh::to_vec(e)),
+        PumpEvent::Create(e) => (CreateEvent::DISCRIMINATOR.to_vec(), borsh::to_vec(e)),
+        PumpEvent::Complete(e) => (CompleteEvent::DISCRIMINATOR.to_vec(), borsh::to_vec(e)),
+    };
+    data.extend(fields.map_err(|e| anyhow!("Failed to serialize event: {}", e))?);
+    Ok(data)
+}
+
+/// Decode event data (discriminator followed by the borsh fields)
+pub fn decode_event(data: &[u8]) -> Option<PumpEvent> {
+    let discriminator: [u8; 8] = data.get(..8)?.try_into().ok()?;
+    match discriminator {
+        TradeEvent::DISCRIMINATOR => TradeEvent::try_from_event_data(data)
+            .ok()
+            .map(PumpEvent::Trade),
+        CreateEvent::DISCRIMINATOR => CreateEvent::try_from_event_data(data)
+            .ok()
+            .map(PumpEvent::Create),
+        CompleteEvent::DISCRIMINATOR => CompleteEvent::try_from_event_data(data)
+            .ok()
+            .map(PumpEvent::Complete),
+        _ => None,
+    }
+}
+
+/// Decode an event from a `Program data:` log line, if it is one
+pub fn decode_log(log: &str) -> Option<PumpEvent> {
+    let data = BASE64.decode(log.strip_prefix("Program data: ")?).ok()?;
+    decode_event(&data)
+}
+
+/// Decode an event from the data of an `emit_cpi!` inner instruction
+pub fn decode_cpi(data: &[u8]) -> Option<PumpEvent> {
+    decode_event(data.strip_prefix(&EVENT_IX_TAG)?)
+}
+
+/// Every pump.fun event in one transaction, in execution order. Events are read from
+/// the inner instruction data (self-CPIs), falling back to the program logs for
+/// transactions that only logged them.
+pub fn decode_transaction<'a>(
+    inner_instructions: impl IntoIterator<Item = &'a [u8]>,
+    logs: &[String],
+) -> Vec<PumpEvent> {
+    let from_cpi: Vec<PumpEvent> = inner_instructions
+        .into_iter()
+        .filter_map(decode_cpi)
+        .collect();
+    if !from_cpi.is_empty() {
+        return from_cpi;
+    }
+    logs.iter().filter_map(|log| decode_log(log)).collect()
+}
+
+/// Every pump.fun event in a fetched transaction, in execution order
+pub fn from_transaction(transaction: &EncodedConfirmedTransactionWithStatusMeta) -> Vec<PumpEvent> {
+    let Some(meta) = &transaction.transaction.meta else {
+        return Vec::new();
+    };
+
+    let inner: Option<&Vec<_>> = meta.inner_instructions.as_ref().into();
+    let data: Vec<Vec<u8>> = inner
+        .into_iter()
+        .flatten()
+        .flat_map(|set| &set.instructions)
+        .filter_map(|ix| match ix {
+            UiInstruction::Compiled(ix) => bs58::decode(&ix.data).into_vec().ok(),
+            _ => None,
+        })
+        .collect();
+    let logs: Option<&Vec<String>> = meta.log_messages.as_ref().into();
+    decode_transaction(
+        data.iter().map(Vec::as_slice),
+        logs.map_or(&[], Vec::as_slice),
+    )
+}
+
+/// A live event and where in the chain it came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamedEvent {
+    pub signature: Signature,
+    /// Position among the transaction's pump.fun events
+    pub index: usize,
+    pub event: PumpEvent,
+}
+
+enum EventSource {
+    WebSocket(LogsSubscription),
+    Geyser(geyser::TransactionUpdates),
+}
+
+/// Live pump.fun events from successful transactions that mention an account, read
+/// from a `logsSubscribe` WebSocket or a Geyser transaction stream (`stream.backend`).
+/// Iterating blocks until the next event; it ends when the stream closes.
+/// Dropping the stream unsubscribes.
+pub struct EventStream {
+    source: EventSource,
+    pending: VecDeque<StreamedEvent>,
+}
+
+impl EventStream {
+    /// The next event with its transaction signature
+    pub fn next_streamed(&mut self) -> Option<StreamedEvent> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+            let (signature, events) = match &mut self.source {
+                EventSource::WebSocket((_, receiver)) => {
+                    let response = receiver.recv().ok()?;
+                    let Ok(signature) = Signature::from_str(&response.value.signature) else {
+                        continue;
+                    };
+                    if response.value.err.is_some() {
+                        continue;
+                    }
+                    let events = response.value.logs.iter().filter_map(|l| decode_log(l));
+                    (signature, events.collect())
+                }
+                EventSource::Geyser(updates) => updates.next()?,
+            };
+            let streamed = events.into_iter().enumerate();
+            self.pending
+                .extend(streamed.map(|(index, event)| StreamedEvent {
+                    signature,
+                    index,
+                    event,
+                }));
+        }
+    }
+
+    /// Iterate events with their transaction signatures
+    pub fn streamed(mut self) -> impl Iterator<Item = StreamedEvent> {
+        std::iter::from_fn(move || self.next_streamed())
+    }
+}
+
+impl Iterator for EventStream {
+    type Item = PumpEvent;
+
+    fn next(&mut self) -> Option<PumpEvent> {
+        self.next_streamed().map(|streamed| streamed.event)
+    }
+}
+
+/// Subscribe to the pump.fun events of transactions that mention `account`. The
+/// WebSocket backend connects to `ws_url`.
+pub fn subscribe_events(ws_url: &str, account: &Pubkey) -> Result<EventStream> {
+    let source = match config::get().stream.backend {
+        StreamBackend::WebSocket => EventSource::WebSocket(
+            PubsubClient::logs_subscribe(
+                ws_url,
+                RpcTransactionLogsFilter::Mentions(vec![account.to_string()]),
+                RpcTransactionLogsConfig {
+                    commitment: Some(CommitmentConfig::confirmed()),
+                },
+            )
+            .map_err(|e| anyhow!("Failed to subscribe to logs of {}: {}", account, e))?,
+        ),
+        StreamBackend::Geyser => EventSource::Geyser(geyser::subscribe_transactions(account)?),
+    };
+    Ok(EventStream {
+        source,
+        pending: VecDeque::new(),
+    })
+}
+
+/// Fetch a landed transaction and decode its pump.fun events
+pub fn fetch_transaction_events(rpc: &RpcClient, signature: &Signature) -> Result<Vec<PumpEvent>> {
+    let transaction = tx_fees::fetch_transaction(rpc, signature)?;
+    Ok(from_transaction(&transaction))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_log_and_cpi_events() {
+        let trade = TradeEvent {
+            mint: Pubkey::new_unique(),
+            sol_amount: 1_000_000_000,
+            token_amount: 35_000_000_000_000,
+            is_buy: true,
+            user: Pubkey::new_unique(),
+            timestamp: 1_700_000_000,
+            fee: 9_500_000,
+            creator_fee: 500_000,
+            ..Default::default()
+        };
+        let mut data = TradeEvent::DISCRIMINATOR.to_vec();
+        data.extend(borsh::to_vec(&trade).unwrap());
+        // Fields appended by later program versions are ignored
+        data.extend([1, 0, 0, 0]);
+
+        let log = format!("Program data: {}", BASE64.encode(&data));
+        assert_eq!(decode_log(&log), Some(PumpEvent::Trade(trade.clone())));
+        assert_eq!(decode_log("Program log: Instruction: Buy"), None);
+
+        let complete = CompleteEvent {
+            user: trade.user,
+            mint: trade.mint,
+            bonding_curve: Pubkey::new_unique(),
+            timestamp: 1_700_000_001,
+        };
+        let mut cpi = EVENT_IX_TAG.to_vec();
+        cpi.extend(CompleteEvent::DISCRIMINATOR);
+        cpi.extend(borsh::to_vec(&complete).unwrap());
+        let event = decode_cpi(&cpi).unwrap();
+        assert_eq!(event.mint(), trade.mint);
+        assert_eq!(event, PumpEvent::Complete(complete));
+
+        // Data without the event tag is not an event CPI
+        assert_eq!(decode_cpi(&data), None);
+        assert_eq!(decode_event(&data[..40]), None);
+    }
+}
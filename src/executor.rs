@@ -0,0 +1,676 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use rand::Rng;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot, Semaphore};
+use tracing::{error, info_span, warn};
+
+use crate::balance::{self, BalanceManager};
+use crate::error::{BotError, SimulationFailure};
+use crate::pump_buy::BuyRequest;
+use crate::pump_sell::SellRequest;
+use crate::sizing::{self, ExposureGuard};
+use crate::{blacklist, config, error, pump_buy, pump_sell, rpc_pool, telegram};
+
+// Constants
+/// How long an accepted idempotency key is remembered and refused if submitted again
+const IDEMPOTENCY_KEY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+type MintLock = Arc<tokio::sync::Mutex<()>>;
+
+/// Per-mint locks shared by every executor in the process, so the strategy runner's
+/// executor (TP/SL exits) and the shared one (Telegram and API sells) never trade the
+/// same mint at once
+static MINT_LOCKS: Lazy<Mutex<HashMap<Pubkey, MintLock>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+/// Idempotency keys of accepted trades and when they were accepted
+static ACCEPTED_KEYS: Lazy<Mutex<HashMap<IdempotencyKey, Instant>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Executor shared by the strategies, sized by `trading.max_in_flight`
+static GLOBAL: Lazy<ExecutorHandle> = Lazy::new(|| {
+    let (handle, mut results) = Executor::start(config::get().trading.max_in_flight)
+        .expect("failed to start trade executor");
+    std::thread::spawn(move || {
+        while let Some(outcome) = results.blocking_recv() {
+            if let Err(e) = &outcome.result {
+                error!(
+                    trade_id = outcome.id,
+                    mint = %outcome.intent.mint(),
+                    intent = ?outcome.intent,
+                    error = %e,
+                    "Trade failed"
+                );
+                telegram::notify(format!(
+                    "Trade #{} ({:?}) failed: {}",
+                    outcome.id, outcome.intent, e
+                ));
+            }
+        }
+    });
+    handle
+});
+
+/// Client-generated key identifying one trade. The executor refuses a trade whose key it
+/// has already accepted, so a retried request or a redelivered event can't trade twice.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IdempotencyKey(String);
+
+impl IdempotencyKey {
+    pub fn new(key: impl Into<String>) -> Self {
+        Self(key.into())
+    }
+
+    /// A fresh key, for trades nothing could submit twice
+    pub fn random() -> Self {
+        Self(format!("{:032x}", rand::thread_rng().gen::<u128>()))
+    }
+}
+
+impl fmt::Display for IdempotencyKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Record `key` as accepted, refusing it if it already was within the TTL
+fn accept_key(key: &IdempotencyKey) -> error::Result<()> {
+    let mut accepted = ACCEPTED_KEYS
+        .lock()
+        .map_err(|_| anyhow!("Idempotency keys lock poisoned"))?;
+    accepted.retain(|_, at| at.elapsed() < IDEMPOTENCY_KEY_TTL);
+    if accepted.contains_key(key) {
+        return Err(BotError::DuplicateTrade(key.to_string()));
+    }
+    accepted.insert(key.clone(), Instant::now());
+    Ok(())
+}
+
+/// Forget `key` for a trade that was refused before it was queued, so it can be retried
+fn forget_key(key: &IdempotencyKey) {
+    if let Ok(mut accepted) = ACCEPTED_KEYS.lock() {
+        accepted.remove(key);
+    }
+}
+
+fn mint_lock(mint: &Pubkey) -> MintLock {
+    MINT_LOCKS
+        .lock()
+        .map(|mut locks| locks.entry(*mint).or_default().clone())
+        .unwrap_or_default()
+}
+
+/// Drop the locks of mints no trade holds or waits on
+fn prune_mint_locks() {
+    if let Ok(mut locks) = MINT_LOCKS.lock() {
+        locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+    }
+}
+
+/// A trade a strategy wants executed
+#[derive(Debug, Clone)]
+pub enum TradeIntent {
+    Buy {
+        mint: Pubkey,
+        token_amount: u64,
+        max_sol_cost: u64,
+    },
+    /// A resolved `request` that places its TP/SL orders as soon as the buy lands
+    ProtectedBuy {
+        request: BuyRequest,
+        token_amount: u64,
+        max_sol_cost: u64,
+    },
+    Sell {
+        mint: Pubkey,
+        token_amount: u64,
+        min_sol_output: u64,
+    },
+    SellRequest {
+        request: SellRequest,
+        slippage_bps: u64,
+    },
+}
+
+impl TradeIntent {
+    pub fn mint(&self) -> Pubkey {
+        match self {
+            TradeIntent::Buy { mint, .. } | TradeIntent::Sell { mint, .. } => *mint,
+            TradeIntent::ProtectedBuy { request, .. } => request.mint,
+            TradeIntent::SellRequest { request, .. } => request.mint,
+        }
+    }
+
+    /// Most SOL the trade can add to exposure, counting a slippage retry
+    fn exposure_lamports(&self) -> u64 {
+        match self.widen_slippage(config::get().trading.slippage_retry_bps) {
+            TradeIntent::Buy { max_sol_cost, .. }
+            | TradeIntent::ProtectedBuy { max_sol_cost, .. } => max_sol_cost,
+            TradeIntent::Sell { .. } | TradeIntent::SellRequest { .. } => 0,
+        }
+    }
+
+    /// The same trade accepting `extra_bps` more slippage
+    fn widen_slippage(&self, extra_bps: u64) -> TradeIntent {
+        match self.clone() {
+            TradeIntent::Buy {
+                mint,
+                token_amount,
+                max_sol_cost,
+            } => TradeIntent::Buy {
+                mint,
+                token_amount,
+                max_sol_cost: pump_buy::with_slippage(max_sol_cost, extra_bps),
+            },
+            TradeIntent::ProtectedBuy {
+                request,
+                token_amount,
+                max_sol_cost,
+            } => TradeIntent::ProtectedBuy {
+                request,
+                token_amount,
+                max_sol_cost: pump_buy::with_slippage(max_sol_cost, extra_bps),
+            },
+            TradeIntent::Sell {
+                mint,
+                token_amount,
+                min_sol_output,
+            } => TradeIntent::Sell {
+                mint,
+                token_amount,
+                min_sol_output: pump_sell::with_slippage(min_sol_output, extra_bps),
+            },
+            TradeIntent::SellRequest {
+                request,
+                slippage_bps,
+            } => TradeIntent::SellRequest {
+                request,
+                slippage_bps: (slippage_bps + extra_bps).min(10_000),
+            },
+        }
+    }
+
+    /// Run the trade, retrying it once with `trading.slippage_retry_bps` more slippage
+    /// if its simulation exceeded the slippage limit. Buys the blacklist refuses, or that
+    /// leave a position too big to exit within `sizing.max_exit_impact_bps`, never run.
+    fn run(&self) -> error::Result<()> {
+        if let TradeIntent::Buy { token_amount, .. }
+        | TradeIntent::ProtectedBuy { token_amount, .. } = self
+        {
+            blacklist::screen(&self.mint())?;
+            sizing::check_exit_liquidity(&rpc_pool::get_client(), &self.mint(), *token_amount)?;
+        }
+        let retry_bps = config::get().trading.slippage_retry_bps;
+        match self.run_once() {
+            Err(BotError::SimulationFailed(e))
+                if e.failure == SimulationFailure::SlippageExceeded && retry_bps > 0 =>
+            {
+                warn!(
+                    retry_bps,
+                    "Slippage exceeded in simulation, retrying with wider slippage"
+                );
+                self.widen_slippage(retry_bps).run_once()
+            }
+            result => result,
+        }
+    }
+
+    /// Run the trade with the configured RPC and wallet
+    fn run_once(&self) -> error::Result<()> {
+        match self {
+            TradeIntent::Buy {
+                mint,
+                token_amount,
+                max_sol_cost,
+            } => pump_buy::run_pump_buy(*token_amount, *mint, *max_sol_cost).map(|_| ()),
+            TradeIntent::ProtectedBuy {
+                request,
+                token_amount,
+                max_sol_cost,
+            } => pump_buy::run_protected_buy(request, *token_amount, *max_sol_cost),
+            TradeIntent::Sell {
+                mint,
+                token_amount,
+                min_sol_output,
+            } => pump_sell::run_pump_sell(*token_amount, *mint, *min_sol_output),
+            TradeIntent::SellRequest {
+                request,
+                slippage_bps,
+            } => pump_sell::run_sell_request(request, *slippage_bps),
+        }
+    }
+}
+
+/// Result of one executed intent
+#[derive(Debug)]
+pub struct TradeOutcome {
+    pub id: u64,
+    pub key: IdempotencyKey,
+    pub intent: TradeIntent,
+    pub result: error::Result<()>,
+    /// Time from submission to completion, including time spent queued
+    pub elapsed: Duration,
+}
+
+type Runner = Arc<dyn Fn(&TradeIntent) -> error::Result<()> + Send + Sync>;
+
+struct Queued {
+    id: u64,
+    key: IdempotencyKey,
+    intent: TradeIntent,
+    submitted_at: Instant,
+    /// Lamports reserved with the exposure guard and claimed from the balance manager,
+    /// released once the trade has run
+    reserved: u64,
+    /// Where `execute` waits for the outcome; None sends it to the results channel
+    reply: Option<oneshot::Sender<TradeOutcome>>,
+}
+
+/// Cheap handle for submitting intents to a running executor
+#[derive(Clone)]
+pub struct ExecutorHandle {
+    queue: mpsc::UnboundedSender<Queued>,
+    next_id: Arc<AtomicU64>,
+    exposure: Arc<ExposureGuard>,
+    balance: Arc<BalanceManager>,
+}
+
+impl ExecutorHandle {
+    /// Accept `intent` if its key is new and it fits the exposure limits and the balance
+    /// reserve, and queue it
+    fn enqueue(
+        &self,
+        key: IdempotencyKey,
+        intent: TradeIntent,
+        reply: Option<oneshot::Sender<TradeOutcome>>,
+    ) -> error::Result<u64> {
+        accept_key(&key)?;
+        let mint = intent.mint();
+        let reserved = intent.exposure_lamports();
+        if reserved > 0 {
+            if let Err(e) = self.exposure.reserve(&mint, reserved) {
+                forget_key(&key);
+                return Err(e);
+            }
+            if let Err(e) = self.balance.claim(reserved) {
+                self.exposure.release(&mint, reserved);
+                forget_key(&key);
+                return Err(e);
+            }
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let queued = Queued {
+            id,
+            key: key.clone(),
+            intent,
+            submitted_at: Instant::now(),
+            reserved,
+            reply,
+        };
+        if self.queue.send(queued).is_err() {
+            self.exposure.release(&mint, reserved);
+            self.balance.release(reserved);
+            forget_key(&key);
+            return Err(anyhow!("Trade executor has stopped").into());
+        }
+        Ok(id)
+    }
+
+    /// Queue `intent` under a fresh key; its outcome arrives on the results channel under
+    /// the returned id. Buys past the `[sizing]` exposure limits or into the `[balance]`
+    /// reserve are refused.
+    pub fn submit(&self, intent: TradeIntent) -> error::Result<u64> {
+        self.submit_keyed(IdempotencyKey::random(), intent)
+    }
+
+    /// `submit`, refusing `intent` if a trade under `key` was already accepted
+    pub fn submit_keyed(&self, key: IdempotencyKey, intent: TradeIntent) -> error::Result<u64> {
+        self.enqueue(key, intent, None)
+    }
+
+    /// Queue `intent` under a fresh key and block until it has run. The outcome is
+    /// returned here instead of going to the results channel. Must not be called from
+    /// async code.
+    pub fn execute(&self, intent: TradeIntent) -> error::Result<()> {
+        self.execute_keyed(IdempotencyKey::random(), intent)
+    }
+
+    /// `execute`, refusing `intent` if a trade under `key` was already accepted
+    pub fn execute_keyed(&self, key: IdempotencyKey, intent: TradeIntent) -> error::Result<()> {
+        let (reply, outcome) = oneshot::channel();
+        self.enqueue(key, intent, Some(reply))?;
+        outcome
+            .blocking_recv()
+            .map_err(|_| anyhow!("Trade executor dropped the trade"))?
+            .result
+    }
+}
+
+/// Runs queued trades with at most `max_in_flight` at once and never two on the
+/// same mint at the same time, across every executor in the process. Intents on one
+/// mint run in submission order.
+pub struct Executor;
+
+impl Executor {
+    /// Start an executor on its own thread, running intents through the trade paths
+    pub fn start(
+        max_in_flight: usize,
+    ) -> Result<(ExecutorHandle, mpsc::UnboundedReceiver<TradeOutcome>)> {
+        Self::start_with(
+            max_in_flight,
+            Arc::new(|intent: &TradeIntent| intent.run()),
+            sizing::guard(),
+            balance::manager(),
+        )
+    }
+
+    fn start_with(
+        max_in_flight: usize,
+        runner: Runner,
+        exposure: Arc<ExposureGuard>,
+        balance: Arc<BalanceManager>,
+    ) -> Result<(ExecutorHandle, mpsc::UnboundedReceiver<TradeOutcome>)> {
+        if max_in_flight == 0 {
+            return Err(anyhow!("Executor needs max_in_flight of at least 1"));
+        }
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let (queue, intents) = mpsc::unbounded_channel();
+        let (results, outcomes) = mpsc::unbounded_channel();
+        std::thread::Builder::new()
+            .name("trade-executor".to_string())
+            .spawn({
+                let (exposure, balance) = (exposure.clone(), balance.clone());
+                move || {
+                    runtime.block_on(dispatch(
+                        intents,
+                        results,
+                        max_in_flight,
+                        runner,
+                        exposure,
+                        balance,
+                    ))
+                }
+            })?;
+
+        Ok((
+            ExecutorHandle {
+                queue,
+                next_id: Arc::new(AtomicU64::new(1)),
+                exposure,
+                balance,
+            },
+            outcomes,
+        ))
+    }
+}
+
+/// Pull intents off the queue until every handle is dropped, then wait for in-flight trades
+async fn dispatch(
+    mut intents: mpsc::UnboundedReceiver<Queued>,
+    results: mpsc::UnboundedSender<TradeOutcome>,
+    max_in_flight: usize,
+    runner: Runner,
+    exposure: Arc<ExposureGuard>,
+    balance: Arc<BalanceManager>,
+) {
+    let permits = Arc::new(Semaphore::new(max_in_flight));
+    let mut tasks = tokio::task::JoinSet::new();
+
+    while let Some(queued) = intents.recv().await {
+        let mint = queued.intent.mint();
+        let mint_lock = mint_lock(&mint);
+        let permits = permits.clone();
+        let results = results.clone();
+        let runner = runner.clone();
+        let exposure = exposure.clone();
+        let balance = balance.clone();
+
+        tasks.spawn(async move {
+            // Wait for the mint before taking a slot so a blocked mint doesn't hold one
+            let mint_guard = mint_lock.lock_owned().await;
+            let Ok(_permit) = permits.acquire_owned().await else {
+                exposure.release(&mint, queued.reserved);
+                balance.release(queued.reserved);
+                return;
+            };
+            let intent = queued.intent.clone();
+            // Everything the trade path logs carries the trade id and mint
+            let span = info_span!("trade", trade_id = queued.id, %mint);
+            let result = tokio::task::spawn_blocking(move || span.in_scope(|| runner(&intent)))
+                .await
+                .unwrap_or_else(|e| Err(anyhow!("Trade panicked: {}", e).into()));
+            drop(mint_guard);
+            // Landed buys are in the portfolio now, failed ones spent nothing
+            exposure.release(&mint, queued.reserved);
+            balance.release(queued.reserved);
+            prune_mint_locks();
+
+            let outcome = TradeOutcome {
+                id: queued.id,
+                key: queued.key,
+                intent: queued.intent,
+                result,
+                elapsed: queued.submitted_at.elapsed(),
+            };
+            match queued.reply {
+                Some(reply) => {
+                    let _ = reply.send(outcome);
+                }
+                None => {
+                    let _ = results.send(outcome);
+                }
+            }
+        });
+        // Reap finished tasks so the set doesn't grow with the queue's history
+        while tasks.try_join_next().is_some() {}
+    }
+    while tasks.join_next().await.is_some() {}
+}
+
+/// The shared executor, started on first use
+pub fn global() -> &'static ExecutorHandle {
+    &GLOBAL
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn test_concurrency_limit_and_per_mint_serialization() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_seen = Arc::new(AtomicUsize::new(0));
+        let busy_mints = Arc::new(Mutex::new(Vec::<Pubkey>::new()));
+        let overlap = Arc::new(AtomicUsize::new(0));
+
+        let runner: Runner = {
+            let (in_flight, max_seen) = (in_flight.clone(), max_seen.clone());
+            let (busy_mints, overlap) = (busy_mints.clone(), overlap.clone());
+            Arc::new(move |intent: &TradeIntent| {
+                let mint = intent.mint();
+                {
+                    let mut busy = busy_mints.lock().unwrap();
+                    if busy.contains(&mint) {
+                        overlap.fetch_add(1, Ordering::SeqCst);
+                    }
+                    busy.push(mint);
+                }
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_seen.fetch_max(now, Ordering::SeqCst);
+                std::thread::sleep(Duration::from_millis(20));
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                busy_mints.lock().unwrap().retain(|m| *m != mint);
+                Ok(())
+            })
+        };
+
+        let unlimited = Arc::new(ExposureGuard::new(
+            sizing::ExposureLimits::default(),
+            Arc::new(|| Ok(sizing::Exposure::default())),
+        ));
+        let unreserved = Arc::new(BalanceManager::new(0, false, Arc::new(|| Ok(0))));
+        let (handle, mut results) = Executor::start_with(2, runner, unlimited, unreserved).unwrap();
+        let mints = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        for i in 0..9 {
+            handle
+                .submit(TradeIntent::Sell {
+                    mint: mints[i % 3],
+                    token_amount: 1,
+                    min_sol_output: 0,
+                })
+                .unwrap();
+        }
+        for _ in 0..9 {
+            assert!(results.blocking_recv().unwrap().result.is_ok());
+        }
+
+        assert_eq!(max_seen.load(Ordering::SeqCst), 2);
+        assert_eq!(overlap.load(Ordering::SeqCst), 0);
+        assert!(handle
+            .execute(TradeIntent::Sell {
+                mint: mints[0],
+                token_amount: 1,
+                min_sol_output: 0,
+            })
+            .is_ok());
+    }
+
+    #[test]
+    fn test_duplicate_keys_and_mint_locks_shared_across_executors() {
+        let mint = Pubkey::new_unique();
+        let sells = Arc::new(AtomicUsize::new(0));
+        let overlap = Arc::new(AtomicUsize::new(0));
+        let runner: Runner = {
+            let (sells, overlap) = (sells.clone(), overlap.clone());
+            let busy = Arc::new(AtomicUsize::new(0));
+            Arc::new(move |_: &TradeIntent| {
+                if busy.fetch_add(1, Ordering::SeqCst) > 0 {
+                    overlap.fetch_add(1, Ordering::SeqCst);
+                }
+                std::thread::sleep(Duration::from_millis(20));
+                busy.fetch_sub(1, Ordering::SeqCst);
+                sells.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            })
+        };
+        let start = || {
+            let unlimited = Arc::new(ExposureGuard::new(
+                sizing::ExposureLimits::default(),
+                Arc::new(|| Ok(sizing::Exposure::default())),
+            ));
+            let unreserved = Arc::new(BalanceManager::new(0, false, Arc::new(|| Ok(0))));
+            Executor::start_with(4, runner.clone(), unlimited, unreserved).unwrap()
+        };
+        // Like the strategy runner's TP/SL exits and a manual sell on the shared executor
+        let (tp_sl, mut tp_sl_results) = start();
+        let (manual, mut manual_results) = start();
+        let sell = TradeIntent::Sell {
+            mint,
+            token_amount: 1,
+            min_sol_output: 0,
+        };
+
+        let key = IdempotencyKey::random();
+        tp_sl.submit_keyed(key.clone(), sell.clone()).unwrap();
+        assert!(matches!(
+            manual.submit_keyed(key, sell.clone()),
+            Err(BotError::DuplicateTrade(_))
+        ));
+        for _ in 0..3 {
+            tp_sl.submit(sell.clone()).unwrap();
+            manual.submit(sell.clone()).unwrap();
+        }
+        for _ in 0..4 {
+            assert!(tp_sl_results.blocking_recv().unwrap().result.is_ok());
+        }
+        for _ in 0..3 {
+            assert!(manual_results.blocking_recv().unwrap().result.is_ok());
+        }
+        assert_eq!(sells.load(Ordering::SeqCst), 7);
+        assert_eq!(overlap.load(Ordering::SeqCst), 0);
+
+        // A trade refused before it was queued frees its key for a retry
+        let key = IdempotencyKey::random();
+        let capped = Arc::new(ExposureGuard::new(
+            sizing::ExposureLimits {
+                per_mint: 1,
+                total: 0,
+                positions: 0,
+            },
+            Arc::new(|| Ok(sizing::Exposure::default())),
+        ));
+        let unreserved = Arc::new(BalanceManager::new(0, false, Arc::new(|| Ok(0))));
+        let (capped, _results) = Executor::start_with(1, runner, capped, unreserved).unwrap();
+        let buy = TradeIntent::Buy {
+            mint,
+            token_amount: 1,
+            max_sol_cost: 1_000,
+        };
+        assert!(matches!(
+            capped.submit_keyed(key.clone(), buy.clone()),
+            Err(BotError::ExposureLimit { .. })
+        ));
+        assert!(tp_sl.execute_keyed(key, sell).is_ok());
+    }
+
+    #[test]
+    fn test_widen_slippage() {
+        let mint = Pubkey::new_unique();
+        let buy = TradeIntent::Buy {
+            mint,
+            token_amount: 1_000,
+            max_sol_cost: 1_000_000,
+        };
+        assert!(matches!(
+            buy.widen_slippage(500),
+            TradeIntent::Buy {
+                max_sol_cost: 1_050_000,
+                ..
+            }
+        ));
+        let sell = TradeIntent::Sell {
+            mint,
+            token_amount: 1_000,
+            min_sol_output: 1_000_000,
+        };
+        assert!(matches!(
+            sell.widen_slippage(500),
+            TradeIntent::Sell {
+                min_sol_output: 950_000,
+                ..
+            }
+        ));
+        assert!(matches!(
+            sell.widen_slippage(20_000),
+            TradeIntent::Sell {
+                min_sol_output: 0,
+                ..
+            }
+        ));
+        // A huge cap saturates instead of overflowing
+        let huge = TradeIntent::Buy {
+            mint,
+            token_amount: 1_000,
+            max_sol_cost: u64::MAX,
+        };
+        assert!(matches!(
+            huge.widen_slippage(500),
+            TradeIntent::Buy {
+                max_sol_cost: u64::MAX,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,238 @@
+use anyhow::{anyhow, Result};
+use solana_client::{rpc_client::RpcClient, rpc_config::RpcSendTransactionConfig};
+use solana_sdk::{
+    hash::Hash, instruction::Instruction, pubkey::Pubkey, signature::Signature, signer::Signer,
+    transaction::Transaction,
+};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::signer::Wallet;
+use crate::{blockhash_cache, cal, fees, pump_sell};
+
+// Constants
+/// Re-sign well inside the ~60s blockhash lifetime
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A full-position sell transaction that is built and signed ahead of time
+pub struct ArmedExit {
+    pub mint: Pubkey,
+    pub token_amount: u64,
+    pub min_sol_output: u64,
+    instructions: Vec<Instruction>,
+    transaction: Transaction,
+    blockhash: Hash,
+    signed_at: Instant,
+}
+
+impl ArmedExit {
+    /// Build and sign an exit for `token_amount` tokens of `mint` held in the user's ATA
+    pub fn arm(
+        rpc: &RpcClient,
+        user: &dyn Signer,
+        mint: &Pubkey,
+        token_amount: u64,
+        slippage_bps: u64,
+    ) -> Result<Self> {
+        let curve = cal::fetch_bonding_curve(rpc, mint)?;
+        if curve.complete {
+            return Err(anyhow!("Bonding curve complete - cannot arm a bonding curve exit"));
+        }
+
+        let global = fees::current_global(rpc, &curve);
+        let expected_sol = cal::get_sol_from_tokens(&global, Some(&curve), token_amount);
+        let min_sol_output =
+            (expected_sol as u128 * 10_000u128.saturating_sub(slippage_bps as u128) / 10_000) as u64;
+
+        let sell_ix = pump_sell::build_sell_instruction(
+            rpc,
+            &user.pubkey(),
+            mint,
+            token_amount,
+            min_sol_output,
+        )?;
+
+        let (blockhash, _) = blockhash_cache::global().get(rpc)?;
+        Self::signed(
+            user,
+            mint,
+            token_amount,
+            min_sol_output,
+            vec![sell_ix],
+            blockhash,
+        )
+    }
+
+    /// An exit of `instructions` signed against `blockhash`
+    fn signed(
+        user: &dyn Signer,
+        mint: &Pubkey,
+        token_amount: u64,
+        min_sol_output: u64,
+        instructions: Vec<Instruction>,
+        blockhash: Hash,
+    ) -> Result<Self> {
+        let mut transaction = Transaction::new_with_payer(&instructions, Some(&user.pubkey()));
+        transaction
+            .try_sign(&[user], blockhash)
+            .map_err(|e| anyhow!("Failed to sign exit for {}: {}", mint, e))?;
+
+        Ok(Self {
+            mint: *mint,
+            token_amount,
+            min_sol_output,
+            instructions,
+            transaction,
+            blockhash,
+            signed_at: Instant::now(),
+        })
+    }
+
+    /// Whether the signed transaction is close to its blockhash expiring
+    pub fn is_stale(&self) -> bool {
+        self.signed_at.elapsed() >= REFRESH_INTERVAL
+    }
+
+    /// Re-sign the cached instructions against a fresh blockhash
+    pub fn refresh(&mut self, rpc: &RpcClient, user: &dyn Signer) -> Result<()> {
+        let (blockhash, _) = blockhash_cache::global().get(rpc)?;
+        self.resign(user, blockhash)
+    }
+
+    /// Re-sign the cached instructions against `blockhash`
+    fn resign(&mut self, user: &dyn Signer, blockhash: Hash) -> Result<()> {
+        let mut transaction = Transaction::new_with_payer(&self.instructions, Some(&user.pubkey()));
+        transaction
+            .try_sign(&[user], blockhash)
+            .map_err(|e| anyhow!("Failed to re-sign exit for {}: {}", self.mint, e))?;
+        self.transaction = transaction;
+        self.blockhash = blockhash;
+        self.signed_at = Instant::now();
+        Ok(())
+    }
+
+    /// The blockhash the armed transaction is currently signed against
+    pub fn blockhash(&self) -> Hash {
+        self.blockhash
+    }
+
+    /// Send the pre-signed exit immediately, skipping preflight
+    pub fn fire(&self, rpc: &RpcClient) -> Result<Signature> {
+        rpc.send_transaction_with_config(
+            &self.transaction,
+            RpcSendTransactionConfig {
+                skip_preflight: true,
+                ..Default::default()
+            },
+        )
+        .map_err(|e| anyhow!("Failed to fire armed exit for {}: {}", self.mint, e))
+    }
+}
+
+/// Keep an armed exit signed against a recent blockhash until `stop` is set
+pub fn spawn_refresher(
+    exit: Arc<Mutex<ArmedExit>>,
+    rpc: Arc<RpcClient>,
+    user: Arc<Wallet>,
+    stop: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        while !stop.load(Ordering::Relaxed) {
+            std::thread::sleep(Duration::from_secs(1));
+
+            let mut exit = match exit.lock() {
+                Ok(exit) => exit,
+                Err(_) => return,
+            };
+            if exit.is_stale() {
+                if let Err(e) = exit.refresh(&rpc, &user) {
+                    warn!(mint = %exit.mint, error = %e, "Failed to refresh armed exit");
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounts::{AnchorAccount, BondingCurve};
+    use crate::pump_idl::PUMP_PROGRAM_ID;
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+    use serde_json::json;
+    use solana_client::rpc_request::RpcRequest;
+    use solana_sdk::{signature::Keypair, system_instruction};
+    use std::collections::HashMap;
+
+    fn armed(user: &Keypair, blockhash: Hash) -> ArmedExit {
+        let ix = system_instruction::transfer(&user.pubkey(), &Pubkey::new_unique(), 1);
+        ArmedExit::signed(user, &Pubkey::new_unique(), 1_000, 500, vec![ix], blockhash).unwrap()
+    }
+
+    #[test]
+    fn test_arm_refuses_a_completed_curve() {
+        let curve = BondingCurve {
+            complete: true,
+            ..cal::new_bonding_curve(&cal::Global::default())
+        };
+        let mut data = BondingCurve::DISCRIMINATOR.to_vec();
+        data.extend(borsh::to_vec(&curve).unwrap());
+        let account = json!({
+            "context": { "slot": 1 },
+            "value": {
+                "data": [BASE64.encode(&data), "base64"],
+                "executable": false,
+                "lamports": 1_000_000,
+                "owner": PUMP_PROGRAM_ID.to_string(),
+                "rentEpoch": 0,
+                "space": data.len(),
+            },
+        });
+        let rpc = RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            HashMap::from([(RpcRequest::GetAccountInfo, account)]),
+        );
+
+        let user = Keypair::new();
+        let error = ArmedExit::arm(&rpc, &user, &Pubkey::new_unique(), 1_000, 500)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("complete"));
+    }
+
+    #[test]
+    fn test_armed_exit_is_signed_and_resigned() {
+        let user = Keypair::new();
+        let first = Hash::new_unique();
+        let mut exit = armed(&user, first);
+        assert_eq!(exit.blockhash(), first);
+        assert_eq!(exit.transaction.message.recent_blockhash, first);
+        assert!(exit.transaction.verify().is_ok());
+
+        let second = Hash::new_unique();
+        exit.resign(&user, second).unwrap();
+        assert_eq!(exit.blockhash(), second);
+        assert_eq!(exit.transaction.message.recent_blockhash, second);
+        assert!(exit.transaction.verify().is_ok());
+        assert_eq!(exit.instructions.len(), 1);
+    }
+
+    #[test]
+    fn test_exit_goes_stale_until_resigned() {
+        let user = Keypair::new();
+        let mut exit = armed(&user, Hash::new_unique());
+        assert!(!exit.is_stale());
+
+        // Signed long enough ago that the blockhash is close to expiring
+        exit.signed_at = Instant::now().checked_sub(REFRESH_INTERVAL).unwrap();
+        assert!(exit.is_stale());
+
+        exit.resign(&user, Hash::new_unique()).unwrap();
+        assert!(!exit.is_stale());
+    }
+}
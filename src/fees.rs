@@ -0,0 +1,259 @@
+use once_cell::sync::Lazy;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::accounts::{self, BondingCurve, FeeConfig, Fees, Global};
+use crate::cal;
+use crate::config;
+use crate::error;
+
+// Constants
+/// How long a fetched fee config is reused before it is refetched
+const FEE_CONFIG_TTL: Duration = Duration::from_secs(60);
+
+lazy_static::lazy_static! {
+    static ref FEE_CONFIG: Pubkey = Pubkey::from_str("8Wf5TiAheLUqBrKXeYg2JtAFFMWtKdG2BSFgqUcPVwTt").unwrap();
+    static ref GLOBAL_ADDRESS: Pubkey = Pubkey::from_str("4wTV1YmiEkRvAtNtsSGPtUrqRYQMe5SKy2uB4Jjaxnjf").unwrap();
+}
+
+/// A fetched value and when it was fetched
+type Cached<T> = Mutex<Option<(Instant, T)>>;
+
+static FEE_CONFIG_CACHE: Lazy<Cached<FeeConfig>> = Lazy::new(|| Mutex::new(None));
+/// Fee recipients read from the Global account, reused for `FEE_CONFIG_TTL`
+static FEE_RECIPIENTS: Lazy<Cached<Vec<Pubkey>>> = Lazy::new(|| Mutex::new(None));
+/// Rotation position shared by every instruction builder
+static NEXT_FEE_RECIPIENT: AtomicUsize = AtomicUsize::new(0);
+
+/// Market cap of a curve in lamports, as the fee program measures it
+pub fn market_cap_lamports(curve: &BondingCurve) -> u128 {
+    if curve.virtual_token_reserves == 0 {
+        return 0;
+    }
+    curve.virtual_sol_reserves as u128 * curve.token_total_supply as u128
+        / curve.virtual_token_reserves as u128
+}
+
+/// Fees of the highest tier whose threshold `market_cap_lamports` has reached,
+/// or the flat fees when there are no tiers
+pub fn fees_for_market_cap(config: &FeeConfig, market_cap_lamports: u128) -> Fees {
+    config
+        .fee_tiers
+        .iter()
+        .filter(|tier| tier.market_cap_lamports_threshold <= market_cap_lamports)
+        .max_by_key(|tier| tier.market_cap_lamports_threshold)
+        .map_or(config.flat_fees, |tier| tier.fees)
+}
+
+/// Quote parameters charging `fees` on top of the default curve parameters
+pub fn global_with_fees(fees: &Fees) -> cal::Global {
+    cal::Global {
+        fee_basis_points: fees.lp_fee_bps + fees.protocol_fee_bps,
+        creator_fee_basis_points: fees.creator_fee_bps,
+        ..cal::Global::default()
+    }
+}
+
+/// Fetch the pump.fun fee config, reusing a copy younger than `FEE_CONFIG_TTL`
+pub fn fetch_fee_config(rpc: &RpcClient) -> error::Result<FeeConfig> {
+    if let Ok(cache) = FEE_CONFIG_CACHE.lock() {
+        if let Some((fetched_at, config)) = cache.as_ref() {
+            if fetched_at.elapsed() < FEE_CONFIG_TTL {
+                return Ok(config.clone());
+            }
+        }
+    }
+
+    let config: FeeConfig = accounts::fetch(rpc, &FEE_CONFIG)?;
+    if let Ok(mut cache) = FEE_CONFIG_CACHE.lock() {
+        *cache = Some((Instant::now(), config.clone()));
+    }
+    Ok(config)
+}
+
+/// Quote parameters with the fees currently charged on `curve`.
+/// Falls back to the hardcoded defaults if the fee config can't be fetched.
+pub fn current_global(rpc: &RpcClient, curve: &BondingCurve) -> cal::Global {
+    match fetch_fee_config(rpc) {
+        Ok(config) => global_with_fees(&fees_for_market_cap(&config, market_cap_lamports(curve))),
+        Err(e) => {
+            warn!(error = %e, "Failed to fetch fee config, using default fees");
+            cal::Global::default()
+        }
+    }
+}
+
+/// Every fee recipient `global` accepts trades to pay: its primary one and the rotation set
+pub fn global_fee_recipients(global: &Global) -> Vec<Pubkey> {
+    let mut recipients = vec![global.fee_recipient];
+    for recipient in global.fee_recipients {
+        if recipient != Pubkey::default() && !recipients.contains(&recipient) {
+            recipients.push(recipient);
+        }
+    }
+    recipients
+}
+
+/// Fetch the fee recipients from the pump.fun Global account, reusing a list younger
+/// than `FEE_CONFIG_TTL`
+pub fn fetch_fee_recipients(rpc: &RpcClient) -> error::Result<Vec<Pubkey>> {
+    if let Ok(cache) = FEE_RECIPIENTS.lock() {
+        if let Some((fetched_at, recipients)) = cache.as_ref() {
+            if fetched_at.elapsed() < FEE_CONFIG_TTL {
+                return Ok(recipients.clone());
+            }
+        }
+    }
+
+    let global: Global = accounts::fetch(rpc, &GLOBAL_ADDRESS)?;
+    let recipients = global_fee_recipients(&global);
+    if let Ok(mut cache) = FEE_RECIPIENTS.lock() {
+        *cache = Some((Instant::now(), recipients.clone()));
+    }
+    Ok(recipients)
+}
+
+/// Refetch the Global account's fee recipients if they are stale, so `next_fee_recipient`
+/// rotates over the current list. Unneeded with `fees.fee_recipients` set; a failed fetch
+/// keeps the previous list.
+pub fn refresh_fee_recipients(rpc: &RpcClient) {
+    if !config::get().fees.fee_recipients.is_empty() {
+        return;
+    }
+    if let Err(e) = fetch_fee_recipients(rpc) {
+        warn!(error = %e, "Failed to fetch fee recipients");
+    }
+}
+
+/// The recipient after `position` in `recipients`, wrapping around
+fn rotate(recipients: &[Pubkey], position: usize) -> Option<Pubkey> {
+    (!recipients.is_empty()).then(|| recipients[position % recipients.len()])
+}
+
+/// The next protocol fee recipient for a buy or sell, without any RPC lookups. Rotates
+/// between `fees.fee_recipients` if set, else the list last fetched from the Global
+/// account, falling back to `fees.fee_recipient` before it has been fetched.
+pub fn next_fee_recipient() -> Pubkey {
+    let position = NEXT_FEE_RECIPIENT.fetch_add(1, Ordering::Relaxed);
+    let cache = FEE_RECIPIENTS.lock().ok();
+    let fetched = cache
+        .as_ref()
+        .and_then(|cache| cache.as_ref())
+        .map(|(_, recipients)| recipients.as_slice());
+    fee_recipient_at(&config::get().fees, fetched, position)
+}
+
+/// The recipient at `position` of the rotation `next_fee_recipient` follows, given the
+/// list fetched from the Global account if any
+fn fee_recipient_at(
+    fees: &config::FeeConfig,
+    fetched: Option<&[Pubkey]>,
+    position: usize,
+) -> Pubkey {
+    rotate(&fees.fee_recipients(), position)
+        .or_else(|| rotate(fetched?, position))
+        .unwrap_or_else(|| fees.fee_recipient())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounts::FeeTier;
+
+    #[test]
+    fn test_tier_selection_by_market_cap() {
+        let fees = |protocol_fee_bps| Fees {
+            lp_fee_bps: 0,
+            protocol_fee_bps,
+            creator_fee_bps: 30,
+        };
+        let config = FeeConfig {
+            bump: 255,
+            admin: Pubkey::default(),
+            flat_fees: fees(100),
+            fee_tiers: vec![
+                FeeTier {
+                    market_cap_lamports_threshold: 0,
+                    fees: fees(95),
+                },
+                FeeTier {
+                    market_cap_lamports_threshold: 400_000_000_000,
+                    fees: fees(50),
+                },
+            ],
+        };
+
+        let curve = cal::new_bonding_curve(&cal::Global::default());
+        let market_cap = market_cap_lamports(&curve);
+        assert_eq!(market_cap, 27_958_993_476);
+        assert_eq!(
+            fees_for_market_cap(&config, market_cap).protocol_fee_bps,
+            95
+        );
+        assert_eq!(
+            fees_for_market_cap(&config, 500_000_000_000).protocol_fee_bps,
+            50
+        );
+
+        let global = global_with_fees(&fees_for_market_cap(&config, market_cap));
+        assert_eq!(global.fee_basis_points, 95);
+        assert_eq!(global.creator_fee_basis_points, 30);
+
+        let flat = FeeConfig {
+            fee_tiers: Vec::new(),
+            ..config
+        };
+        assert_eq!(fees_for_market_cap(&flat, market_cap), flat.flat_fees);
+    }
+
+    #[test]
+    fn test_fee_recipient_rotation() {
+        let (primary, second, third) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mut fee_recipients = [Pubkey::default(); 7];
+        fee_recipients[..3].copy_from_slice(&[second, primary, third]);
+        let global = Global {
+            initialized: true,
+            authority: Pubkey::default(),
+            fee_recipient: primary,
+            initial_virtual_token_reserves: 0,
+            initial_virtual_sol_reserves: 0,
+            initial_real_token_reserves: 0,
+            token_total_supply: 0,
+            fee_basis_points: 0,
+            withdraw_authority: Pubkey::default(),
+            enable_migrate: true,
+            pool_migration_fee: 0,
+            creator_fee_basis_points: 0,
+            fee_recipients,
+            set_creator_authority: Pubkey::default(),
+            admin_set_creator_authority: Pubkey::default(),
+        };
+
+        // Unset slots and repeats of the primary are skipped
+        let recipients = global_fee_recipients(&global);
+        assert_eq!(recipients, vec![primary, second, third]);
+        let rotation: Vec<Pubkey> = (5..9).filter_map(|i| rotate(&recipients, i)).collect();
+        assert_eq!(rotation, vec![third, primary, second, third]);
+        assert_eq!(rotate(&[], 0), None);
+
+        // The configured list wins over the fetched one, and the configured default is
+        // used while there is neither
+        let mut fees = config::FeeConfig {
+            fee_recipient: primary.to_string(),
+            ..Default::default()
+        };
+        assert_eq!(fee_recipient_at(&fees, None, 5), primary);
+        assert_eq!(fee_recipient_at(&fees, Some(&recipients), 5), third);
+        fees.fee_recipients = vec![second.to_string()];
+        assert_eq!(fee_recipient_at(&fees, Some(&recipients), 5), second);
+    }
+}
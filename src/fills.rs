@@ -0,0 +1,181 @@
+use anyhow::{anyhow, Result};
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, EncodedConfirmedTransactionWithStatusMeta,
+    UiTransactionTokenBalance,
+};
+
+use crate::events::{self, PumpEvent};
+use crate::portfolio::{Fill, Side};
+
+/// Raw token amount `owner` holds of `mint` across `balances`
+fn token_total(
+    balances: &OptionSerializer<Vec<UiTransactionTokenBalance>>,
+    owner: &Pubkey,
+    mint: &Pubkey,
+) -> u64 {
+    let balances: Option<&Vec<UiTransactionTokenBalance>> = balances.as_ref().into();
+    let (owner, mint) = (owner.to_string(), mint.to_string());
+    balances
+        .into_iter()
+        .flatten()
+        .filter(|b| b.mint == mint && Option::<&String>::from(b.owner.as_ref()) == Some(&owner))
+        .filter_map(|b| b.ui_token_amount.amount.parse::<u64>().ok())
+        .sum()
+}
+
+/// What `quoted` actually filled at in a landed transaction signed by `owner`.
+///
+/// Amounts come from the pump.fun Trade events when there are any. Otherwise (PumpSwap)
+/// they come from `owner`'s token and SOL balance changes, with the network fee taken
+/// out. The pool fee can't be separated from balances, so it stays in `sol_amount` and
+/// `fee_lamports` is 0, and any rent from token accounts opened or closed in the same
+/// transaction is counted too. `owner` must be the fee payer.
+pub fn realized_fill(
+    transaction: &EncodedConfirmedTransactionWithStatusMeta,
+    owner: &Pubkey,
+    quoted: &Fill,
+) -> Result<Fill> {
+    let is_buy = quoted.side == Side::Buy;
+    let trades: Vec<_> = events::from_transaction(transaction)
+        .into_iter()
+        .filter_map(|event| match event {
+            PumpEvent::Trade(trade)
+                if trade.user == *owner && trade.mint == quoted.mint && trade.is_buy == is_buy =>
+            {
+                Some(trade)
+            }
+            _ => None,
+        })
+        .collect();
+
+    let mut fill = Fill {
+        timestamp: transaction.block_time.unwrap_or(quoted.timestamp),
+        ..quoted.clone()
+    };
+    if !trades.is_empty() {
+        let sol: u64 = trades.iter().map(|t| t.sol_amount).sum();
+        let fee: u64 = trades.iter().map(|t| t.fee + t.creator_fee).sum();
+        fill.token_amount = trades.iter().map(|t| t.token_amount).sum();
+        // Fees are charged on top of a buy and taken out of a sell
+        fill.sol_amount = if is_buy {
+            sol + fee
+        } else {
+            sol.saturating_sub(fee)
+        };
+        fill.fee_lamports = fee;
+        return Ok(fill);
+    }
+
+    let meta = transaction
+        .transaction
+        .meta
+        .as_ref()
+        .ok_or_else(|| anyhow!("Transaction {} has no status meta", fill.signature))?;
+    let tokens_before = token_total(&meta.pre_token_balances, owner, &quoted.mint);
+    let tokens_after = token_total(&meta.post_token_balances, owner, &quoted.mint);
+    let (Some(sol_before), Some(sol_after)) =
+        (meta.pre_balances.first(), meta.post_balances.first())
+    else {
+        return Err(anyhow!(
+            "Transaction {} has no SOL balances",
+            fill.signature
+        ));
+    };
+    // The fee payer's change without the network fee
+    let sol_change = *sol_after as i128 - *sol_before as i128 + meta.fee as i128;
+
+    fill.token_amount = tokens_after.abs_diff(tokens_before);
+    fill.sol_amount = sol_change.unsigned_abs() as u64;
+    fill.fee_lamports = 0;
+    Ok(fill)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{AnchorEvent, TradeEvent, EVENT_IX_TAG};
+    use solana_transaction_status::{
+        EncodedTransaction, EncodedTransactionWithStatusMeta, UiCompiledInstruction,
+        UiInnerInstructions, UiInstruction, UiTransactionStatusMeta,
+    };
+
+    fn transaction(
+        inner: Vec<UiInstruction>,
+        sol: (u64, u64),
+        fee: u64,
+    ) -> EncodedConfirmedTransactionWithStatusMeta {
+        let meta: UiTransactionStatusMeta = serde_json::from_value(serde_json::json!({
+            "err": null,
+            "status": { "Ok": null },
+            "fee": fee,
+            "preBalances": [sol.0],
+            "postBalances": [sol.1],
+            "innerInstructions": [],
+            "logMessages": [],
+            "preTokenBalances": [],
+            "postTokenBalances": [],
+            "rewards": [],
+        }))
+        .unwrap();
+        EncodedConfirmedTransactionWithStatusMeta {
+            slot: 1,
+            block_time: Some(1_700_000_000),
+            transaction: EncodedTransactionWithStatusMeta {
+                transaction: EncodedTransaction::LegacyBinary(String::new()),
+                meta: Some(UiTransactionStatusMeta {
+                    inner_instructions: OptionSerializer::Some(vec![UiInnerInstructions {
+                        index: 0,
+                        instructions: inner,
+                    }]),
+                    ..meta
+                }),
+                version: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_realized_fill_from_trade_event_or_balances() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let quoted = Fill::new(mint, Side::Buy, 1_000, 1_010_000, 10_000, "sig".to_string());
+
+        let trade = TradeEvent {
+            mint,
+            sol_amount: 990_000,
+            token_amount: 980,
+            is_buy: true,
+            user: owner,
+            fee: 9_000,
+            creator_fee: 1_000,
+            ..Default::default()
+        };
+        let mut data = EVENT_IX_TAG.to_vec();
+        data.extend(TradeEvent::DISCRIMINATOR);
+        data.extend(borsh::to_vec(&trade).unwrap());
+        let cpi = UiInstruction::Compiled(UiCompiledInstruction {
+            program_id_index: 0,
+            accounts: Vec::new(),
+            data: bs58::encode(data).into_string(),
+            stack_height: None,
+        });
+
+        let fill = realized_fill(&transaction(vec![cpi], (0, 0), 5_000), &owner, &quoted).unwrap();
+        assert_eq!(fill.token_amount, 980);
+        assert_eq!(fill.sol_amount, 1_000_000);
+        assert_eq!(fill.fee_lamports, 10_000);
+        assert_eq!(fill.signature, "sig");
+        assert_eq!(fill.timestamp, 1_700_000_000);
+
+        // Without events the SOL change minus the network fee is used
+        let fill = realized_fill(
+            &transaction(Vec::new(), (10_000_000, 8_995_000), 5_000),
+            &owner,
+            &quoted,
+        )
+        .unwrap();
+        assert_eq!(fill.sol_amount, 1_000_000);
+        assert_eq!(fill.fee_lamports, 0);
+    }
+}
@@ -0,0 +1,219 @@
+use anyhow::Result;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::sync::mpsc;
+
+use crate::events::PumpEvent;
+
+/// What a subscription asks the Geyser endpoint for
+#[cfg_attr(not(feature = "geyser"), allow(dead_code))]
+enum Filter {
+    /// Every write to one account
+    Account(Pubkey),
+    /// Successful pump.fun transactions that touch an account
+    Transactions(Pubkey),
+}
+
+#[cfg_attr(not(feature = "geyser"), allow(dead_code))]
+enum Update {
+    Account(Vec<u8>),
+    Transaction(Signature, Vec<PumpEvent>),
+}
+
+/// Data of an account after each write, from a Yellowstone gRPC account subscription.
+/// Iterating blocks until the next update; it ends when the stream closes.
+/// Dropping it closes the subscription.
+pub struct AccountUpdates {
+    updates: mpsc::Receiver<Update>,
+}
+
+impl Iterator for AccountUpdates {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        loop {
+            if let Update::Account(data) = self.updates.recv().ok()? {
+                return Some(data);
+            }
+        }
+    }
+}
+
+/// Signature and pump.fun events of each matching transaction, from a Yellowstone gRPC
+/// transaction subscription. Iterating blocks until the next transaction; it ends when the stream closes.
+pub struct TransactionUpdates {
+    updates: mpsc::Receiver<Update>,
+}
+
+impl Iterator for TransactionUpdates {
+    type Item = (Signature, Vec<PumpEvent>);
+
+    fn next(&mut self) -> Option<(Signature, Vec<PumpEvent>)> {
+        loop {
+            if let Update::Transaction(signature, events) = self.updates.recv().ok()? {
+                return Some((signature, events));
+            }
+        }
+    }
+}
+
+/// Subscribe to writes to `address` over `stream.geyser_url`
+pub fn subscribe_account(address: &Pubkey) -> Result<AccountUpdates> {
+    let updates = client::subscribe(Filter::Account(*address))?;
+    Ok(AccountUpdates { updates })
+}
+
+/// Subscribe to successful transactions that invoke pump.fun and touch `account`
+pub fn subscribe_transactions(account: &Pubkey) -> Result<TransactionUpdates> {
+    let updates = client::subscribe(Filter::Transactions(*account))?;
+    Ok(TransactionUpdates { updates })
+}
+
+#[cfg(not(feature = "geyser"))]
+mod client {
+    use anyhow::{anyhow, Result};
+    use std::sync::mpsc;
+
+    use super::{Filter, Update};
+
+    pub fn subscribe(_filter: Filter) -> Result<mpsc::Receiver<Update>> {
+        Err(anyhow!(
+            "The geyser stream backend needs a build with `--features geyser`"
+        ))
+    }
+}
+
+#[cfg(feature = "geyser")]
+mod client {
+    use anyhow::{anyhow, Result};
+    use futures::{SinkExt, StreamExt};
+    use solana_sdk::{pubkey::Pubkey, signature::Signature};
+    use std::str::FromStr;
+    use std::sync::mpsc;
+    use tracing::warn;
+    use yellowstone_grpc_client::{ClientTlsConfig, GeyserGrpcClient};
+    use yellowstone_grpc_proto::prelude::{
+        subscribe_update::UpdateOneof, CommitmentLevel, SubscribeRequest,
+        SubscribeRequestFilterAccounts, SubscribeRequestFilterTransactions, SubscribeRequestPing,
+        SubscribeUpdateTransactionInfo,
+    };
+
+    use super::{Filter, Update};
+    use crate::config;
+    use crate::events::{self, PumpEvent};
+
+    lazy_static::lazy_static! {
+        static ref PUMP_PROGRAM_ID: Pubkey = Pubkey::from_str("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P").unwrap();
+    }
+
+    fn request(filter: &Filter) -> SubscribeRequest {
+        let mut request = SubscribeRequest {
+            commitment: Some(CommitmentLevel::Confirmed as i32),
+            ..Default::default()
+        };
+        match filter {
+            Filter::Account(address) => {
+                request.accounts.insert(
+                    "account".to_string(),
+                    SubscribeRequestFilterAccounts {
+                        account: vec![address.to_string()],
+                        ..Default::default()
+                    },
+                );
+            }
+            Filter::Transactions(account) => {
+                request.transactions.insert(
+                    "transactions".to_string(),
+                    SubscribeRequestFilterTransactions {
+                        vote: Some(false),
+                        failed: Some(false),
+                        account_include: vec![account.to_string()],
+                        account_required: vec![PUMP_PROGRAM_ID.to_string()],
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+        request
+    }
+
+    /// The transaction's signature and pump.fun events, None if it has neither
+    fn transaction_events(
+        transaction: SubscribeUpdateTransactionInfo,
+    ) -> Option<(Signature, Vec<PumpEvent>)> {
+        let signature = Signature::try_from(transaction.signature.as_slice()).ok()?;
+        let meta = transaction.meta?;
+        let inner = meta
+            .inner_instructions
+            .iter()
+            .flat_map(|set| &set.instructions)
+            .map(|ix| ix.data.as_slice());
+        let events = events::decode_transaction(inner, &meta.log_messages);
+        (!events.is_empty()).then_some((signature, events))
+    }
+
+    /// Connect and subscribe, then forward updates from a background thread until
+    /// the stream ends or the receiver is dropped
+    pub fn subscribe(filter: Filter) -> Result<mpsc::Receiver<Update>> {
+        let config = &config::get().stream;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let (mut sink, mut stream) = runtime
+            .block_on(async {
+                let token =
+                    (!config.geyser_x_token.is_empty()).then(|| config.geyser_x_token.clone());
+                let mut builder = GeyserGrpcClient::build_from_shared(config.geyser_url.clone())?
+                    .x_token(token)?;
+                if config.geyser_url.starts_with("https://") {
+                    builder = builder.tls_config(ClientTlsConfig::new().with_native_roots())?;
+                }
+                let mut client = builder.connect().await?;
+                let subscription = client
+                    .subscribe_with_request(Some(request(&filter)))
+                    .await?;
+                Ok::<_, anyhow::Error>(subscription)
+            })
+            .map_err(|e| anyhow!("Failed to subscribe to {}: {}", config.geyser_url, e))?;
+
+        let (updates, receiver) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("geyser".to_string())
+            .spawn(move || {
+                runtime.block_on(async move {
+                    while let Some(message) = stream.next().await {
+                        let update = match message.map(|m| m.update_oneof) {
+                            Ok(Some(UpdateOneof::Account(account))) => {
+                                account.account.map(|a| Update::Account(a.data))
+                            }
+                            Ok(Some(UpdateOneof::Transaction(transaction))) => transaction
+                                .transaction
+                                .and_then(transaction_events)
+                                .map(|(signature, events)| Update::Transaction(signature, events)),
+                            Ok(Some(UpdateOneof::Ping(_))) => {
+                                // Answer pings so proxies don't drop an idle stream
+                                let ping = SubscribeRequest {
+                                    ping: Some(SubscribeRequestPing { id: 1 }),
+                                    ..Default::default()
+                                };
+                                if sink.send(ping).await.is_err() {
+                                    break;
+                                }
+                                None
+                            }
+                            Ok(_) => None,
+                            Err(e) => {
+                                warn!(error = %e, "Geyser stream failed");
+                                break;
+                            }
+                        };
+                        if let Some(update) = update {
+                            if updates.send(update).is_err() {
+                                break;
+                            }
+                        }
+                    }
+                })
+            })?;
+        Ok(receiver)
+    }
+}
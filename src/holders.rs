@@ -0,0 +1,294 @@
+use anyhow::{anyhow, Result};
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::str::FromStr;
+use tracing::{debug, warn};
+
+use crate::{cal, safety};
+
+// Constants
+/// Token account layout (SPL Token and Token-2022): mint, owner, amount
+const OWNER_OFFSET: usize = 32;
+const ACCOUNT_HEAD_LEN: usize = 72;
+/// Largest holders searched for bundler clusters; `getTokenLargestAccounts` returns 20
+const CLUSTER_SCAN_DEPTH: usize = 20;
+/// Fewest holders with near-identical balances counted as a bundler cluster
+const MIN_CLUSTER_SIZE: usize = 3;
+/// How far below the cluster's largest balance the others may be, in basis points
+const CLUSTER_TOLERANCE_BPS: u64 = 100;
+
+/// One wallet's tokens across all its token accounts for the mint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Holder {
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+/// Wallets holding near-identical balances, the mark of one buyer splitting a bundled
+/// buy across wallets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundlerCluster {
+    pub owners: Vec<Pubkey>,
+    pub amount: u64,
+}
+
+/// Who holds a mint's supply, bonding curve excluded
+#[derive(Debug, Clone, Default)]
+pub struct HolderDistribution {
+    pub mint: Pubkey,
+    pub supply: u64,
+    /// Holders known to the scan, largest first. Every holder when `getProgramAccounts`
+    /// answered, otherwise the owners of the 20 largest token accounts.
+    pub holders: Vec<Holder>,
+    /// Wallets holding any of the mint, None when only the largest accounts were read
+    pub holder_count: Option<usize>,
+    /// Tokens held by the creator's wallet, None when the creator is unknown
+    pub dev_amount: Option<u64>,
+    pub clusters: Vec<BundlerCluster>,
+}
+
+impl HolderDistribution {
+    /// Group token account balances `(owner, amount)` by owner, leaving out `excluded`
+    /// (the bonding curve), and find the creator's share and bundler clusters
+    pub fn from_balances(
+        mint: Pubkey,
+        supply: u64,
+        balances: impl IntoIterator<Item = (Pubkey, u64)>,
+        excluded: &Pubkey,
+        creator: Option<Pubkey>,
+        complete: bool,
+    ) -> Self {
+        let mut by_owner: HashMap<Pubkey, u64> = HashMap::new();
+        for (owner, amount) in balances {
+            if owner != *excluded && amount > 0 {
+                *by_owner.entry(owner).or_insert(0) += amount;
+            }
+        }
+        let mut holders: Vec<Holder> = by_owner
+            .into_iter()
+            .map(|(owner, amount)| Holder { owner, amount })
+            .collect();
+        holders.sort_by(|a, b| b.amount.cmp(&a.amount).then(a.owner.cmp(&b.owner)));
+        let dev_amount = creator.map(|creator| {
+            holders
+                .iter()
+                .find(|h| h.owner == creator)
+                .map_or(0, |h| h.amount)
+        });
+        let clusters = find_clusters(&holders[..holders.len().min(CLUSTER_SCAN_DEPTH)]);
+        Self {
+            mint,
+            supply,
+            holder_count: complete.then_some(holders.len()),
+            holders,
+            dev_amount,
+            clusters,
+        }
+    }
+
+    fn pct(&self, amount: u64) -> f64 {
+        if self.supply == 0 {
+            return 0.0;
+        }
+        amount as f64 / self.supply as f64 * 100.0
+    }
+
+    /// Percent of supply held by the `count` largest holders
+    pub fn top_pct(&self, count: usize) -> f64 {
+        self.pct(self.holders.iter().take(count).map(|h| h.amount).sum())
+    }
+
+    pub fn top10_pct(&self) -> f64 {
+        self.top_pct(10)
+    }
+
+    /// Percent of supply the creator still holds
+    pub fn dev_pct(&self) -> Option<f64> {
+        self.dev_amount.map(|amount| self.pct(amount))
+    }
+
+    /// Percent of supply held by bundler clusters
+    pub fn bundler_pct(&self) -> f64 {
+        self.pct(self.clusters.iter().map(|c| c.amount).sum())
+    }
+}
+
+/// Runs of at least `MIN_CLUSTER_SIZE` holders (largest first) within
+/// `CLUSTER_TOLERANCE_BPS` of the run's largest balance
+fn find_clusters(holders: &[Holder]) -> Vec<BundlerCluster> {
+    let mut clusters = Vec::new();
+    let mut start = 0;
+    while start < holders.len() {
+        let top = holders[start].amount as u128;
+        let end = start
+            + holders[start..]
+                .iter()
+                .take_while(|h| {
+                    (top - h.amount as u128) * 10_000 <= top * CLUSTER_TOLERANCE_BPS as u128
+                })
+                .count();
+        if end - start >= MIN_CLUSTER_SIZE {
+            let run = &holders[start..end];
+            clusters.push(BundlerCluster {
+                owners: run.iter().map(|h| h.owner).collect(),
+                amount: run.iter().map(|h| h.amount).sum(),
+            });
+        }
+        start = end;
+    }
+    clusters
+}
+
+/// `(owner, amount)` of every token account of `mint` under `token_program`
+fn fetch_all_balances(
+    rpc: &RpcClient,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Result<Vec<(Pubkey, u64)>> {
+    let accounts = rpc.get_program_accounts_with_config(
+        token_program,
+        RpcProgramAccountsConfig {
+            filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                0,
+                mint.as_ref(),
+            ))]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                // Only the owner and amount
+                data_slice: Some(UiDataSliceConfig {
+                    offset: OWNER_OFFSET,
+                    length: ACCOUNT_HEAD_LEN - OWNER_OFFSET,
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    )?;
+    accounts
+        .into_iter()
+        .map(|(address, account)| {
+            parse_owner_and_amount(&account.data)
+                .ok_or_else(|| anyhow!("Token account {} is too short", address))
+        })
+        .collect()
+}
+
+/// Owner and amount from token account data starting at the owner
+fn parse_owner_and_amount(data: &[u8]) -> Option<(Pubkey, u64)> {
+    let owner = Pubkey::try_from(data.get(..32)?).ok()?;
+    let amount = u64::from_le_bytes(data.get(32..40)?.try_into().ok()?);
+    Some((owner, amount))
+}
+
+/// `(owner, amount)` of the 20 largest token accounts of `mint`
+fn fetch_largest_balances(rpc: &RpcClient, mint: &Pubkey) -> Result<Vec<(Pubkey, u64)>> {
+    let addresses = rpc
+        .get_token_largest_accounts(mint)?
+        .iter()
+        .map(|account| Pubkey::from_str(&account.address))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(cal::fetch_accounts(rpc, &addresses)
+        .into_iter()
+        .filter_map(|account| parse_owner_and_amount(account.ok()?.data.get(OWNER_OFFSET..)?))
+        .collect())
+}
+
+/// Scan `mint`'s holders with every token account from `getProgramAccounts`, falling back
+/// to the owners of the `getTokenLargestAccounts` accounts where that is disabled
+pub fn scan(
+    rpc: &RpcClient,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    supply: u64,
+    creator: Option<Pubkey>,
+) -> Result<HolderDistribution> {
+    let (bonding_curve, _) = cal::get_bonding_curve_pda(mint);
+    let (balances, complete) = match fetch_all_balances(rpc, mint, token_program) {
+        Ok(balances) => (balances, true),
+        Err(e) => {
+            warn!(%mint, error = %e, "getProgramAccounts failed, reading the largest holders only");
+            (fetch_largest_balances(rpc, mint)?, false)
+        }
+    };
+    let distribution = HolderDistribution::from_balances(
+        *mint,
+        supply,
+        balances,
+        &bonding_curve,
+        creator,
+        complete,
+    );
+    debug!(
+        %mint,
+        holders = ?distribution.holder_count,
+        top10_pct = distribution.top10_pct(),
+        dev_pct = ?distribution.dev_pct(),
+        bundler_pct = distribution.bundler_pct(),
+        "Scanned holders"
+    );
+    Ok(distribution)
+}
+
+/// `scan` for a mint known only by its address, with the creator from its bonding curve
+pub fn fetch(rpc: &RpcClient, mint: &Pubkey) -> Result<HolderDistribution> {
+    let mint_account = cal::fetch_account(rpc, mint)?;
+    let supply = safety::parse_mint_authorities(&mint_account.data)?.supply;
+    let creator = cal::fetch_bonding_curve(rpc, mint)
+        .map(|curve| curve.creator)
+        .ok();
+    scan(rpc, mint, &mint_account.owner, supply, creator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concentration_dev_share_and_bundler_clusters() {
+        let mint = Pubkey::new_unique();
+        let curve = Pubkey::new_unique();
+        let dev = Pubkey::new_unique();
+        let bundlers: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let mut balances = vec![
+            (curve, 600_000),
+            (dev, 100_000),
+            // The dev's second token account
+            (dev, 20_000),
+            (Pubkey::new_unique(), 70_000),
+            (Pubkey::new_unique(), 0),
+        ];
+        // One buy split four ways, a little off from rounding
+        for (i, bundler) in bundlers.iter().enumerate() {
+            balances.push((*bundler, 50_000 - i as u64 * 100));
+        }
+        balances.push((Pubkey::new_unique(), 10_000));
+
+        let distribution =
+            HolderDistribution::from_balances(mint, 1_000_000, balances, &curve, Some(dev), true);
+        assert_eq!(distribution.holder_count, Some(7));
+        assert_eq!(
+            distribution.holders[0],
+            Holder {
+                owner: dev,
+                amount: 120_000
+            }
+        );
+        assert!((distribution.top10_pct() - 39.94).abs() < 1e-9);
+        assert!((distribution.top_pct(2) - 19.0).abs() < 1e-9);
+        assert!((distribution.dev_pct().unwrap() - 12.0).abs() < 1e-9);
+        assert_eq!(distribution.clusters.len(), 1);
+        assert_eq!(distribution.clusters[0].owners, bundlers);
+        assert!((distribution.bundler_pct() - 19.94).abs() < 1e-9);
+
+        let partial = HolderDistribution::from_balances(mint, 0, [(dev, 5)], &curve, None, false);
+        assert_eq!(partial.holder_count, None);
+        assert_eq!(partial.dev_pct(), None);
+        assert_eq!(partial.top10_pct(), 0.0);
+    }
+}
@@ -0,0 +1,327 @@
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::events::{PumpEvent, TradeEvent};
+
+// Constants
+/// How often, in event seconds, mints without a trade in the longest window are dropped
+const PRUNE_INTERVAL_SECS: i64 = 60;
+/// Spot prices kept per mint for the price signals, newest last
+const MAX_PRICE_SAMPLES: usize = 256;
+
+/// Span the trade flow is summed over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Window {
+    OneMinute,
+    FiveMinutes,
+}
+
+impl Window {
+    pub fn secs(self) -> i64 {
+        match self {
+            Window::OneMinute => 60,
+            Window::FiveMinutes => 300,
+        }
+    }
+
+    /// The longest window, which decides how long trades are kept
+    const LONGEST: Window = Window::FiveMinutes;
+}
+
+/// One mint's trade flow over a window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FlowStats {
+    /// Lamports spent on buys
+    pub buy_volume: u64,
+    /// Lamports received from sells
+    pub sell_volume: u64,
+    pub buys: usize,
+    pub sells: usize,
+    /// Distinct wallets that bought
+    pub unique_buyers: usize,
+}
+
+impl FlowStats {
+    /// Lamports bought minus lamports sold
+    pub fn net_flow(&self) -> i64 {
+        self.buy_volume as i64 - self.sell_volume as i64
+    }
+
+    /// Buys per sell, None while nobody has sold
+    pub fn buy_sell_ratio(&self) -> Option<f64> {
+        (self.sells > 0).then(|| self.buys as f64 / self.sells as f64)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Trade {
+    timestamp: i64,
+    user: Pubkey,
+    is_buy: bool,
+    sol_amount: u64,
+}
+
+/// What is kept of one mint's trades
+#[derive(Debug, Default)]
+struct History {
+    /// Trades within the longest window, oldest first
+    trades: VecDeque<Trade>,
+    /// Spot price after each of the last `MAX_PRICE_SAMPLES` trades, in lamports per raw
+    /// token unit
+    prices: VecDeque<f64>,
+}
+
+/// Simple moving average of the last `period` prices
+pub fn sma(prices: &[f64], period: usize) -> Option<f64> {
+    if period == 0 || prices.len() < period {
+        return None;
+    }
+    Some(prices[prices.len() - period..].iter().sum::<f64>() / period as f64)
+}
+
+/// Exponential moving average over `prices`, seeded with the SMA of the first `period`
+pub fn ema(prices: &[f64], period: usize) -> Option<f64> {
+    let seed = sma(&prices[..period.min(prices.len())], period)?;
+    let alpha = 2.0 / (period as f64 + 1.0);
+    Some(
+        prices[period..]
+            .iter()
+            .fold(seed, |ema, price| ema + alpha * (price - ema)),
+    )
+}
+
+/// Wilder's relative strength index over `prices`, from 0 to 100. Needs `period + 1`
+/// prices; 100 when the price never fell.
+pub fn rsi(prices: &[f64], period: usize) -> Option<f64> {
+    if period == 0 || prices.len() <= period {
+        return None;
+    }
+    let changes: Vec<f64> = prices.windows(2).map(|w| w[1] - w[0]).collect();
+    let (gains, losses) = changes[..period]
+        .iter()
+        .fold((0.0, 0.0), |(gains, losses), change| {
+            (gains + change.max(0.0), losses + (-change).max(0.0))
+        });
+    let (mut avg_gain, mut avg_loss) = (gains / period as f64, losses / period as f64);
+    for change in &changes[period..] {
+        avg_gain = (avg_gain * (period - 1) as f64 + change.max(0.0)) / period as f64;
+        avg_loss = (avg_loss * (period - 1) as f64 + (-change).max(0.0)) / period as f64;
+    }
+    if avg_loss == 0.0 {
+        return Some(100.0);
+    }
+    Some(100.0 - 100.0 / (1.0 + avg_gain / avg_loss))
+}
+
+/// Percent change of the last price from the one `period` prices before it
+pub fn rate_of_change(prices: &[f64], period: usize) -> Option<f64> {
+    let last = *prices.last()?;
+    let before = *prices.get(prices.len().checked_sub(period + 1)?)?;
+    (before > 0.0).then(|| (last / before - 1.0) * 100.0)
+}
+
+/// Rolling buy and sell flow and price signals per mint, built from decoded Trade events.
+/// Windows end at the newest event's timestamp, so a backtest sees the same figures a
+/// live run did. Signal periods count trades, each of which moves the curve's price.
+#[derive(Debug, Default)]
+pub struct Indicators {
+    mints: HashMap<Pubkey, History>,
+    /// Newest event timestamp seen, Unix seconds
+    now: i64,
+    pruned_at: i64,
+}
+
+impl Indicators {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a trade. Mints whose curve completed stop trading here and are dropped.
+    pub fn on_event(&mut self, event: &PumpEvent) {
+        match event {
+            PumpEvent::Trade(trade) => self.on_trade(trade),
+            PumpEvent::Complete(event) => {
+                self.mints.remove(&event.mint);
+            }
+            PumpEvent::Create(_) => {}
+        }
+    }
+
+    pub fn on_trade(&mut self, trade: &TradeEvent) {
+        self.now = self.now.max(trade.timestamp);
+        let cutoff = self.now - Window::LONGEST.secs();
+        let history = self.mints.entry(trade.mint).or_default();
+        history.trades.push_back(Trade {
+            timestamp: trade.timestamp,
+            user: trade.user,
+            is_buy: trade.is_buy,
+            sol_amount: trade.sol_amount,
+        });
+        while history
+            .trades
+            .front()
+            .is_some_and(|t| t.timestamp <= cutoff)
+        {
+            history.trades.pop_front();
+        }
+        if trade.virtual_token_reserves > 0 {
+            if history.prices.len() == MAX_PRICE_SAMPLES {
+                history.prices.pop_front();
+            }
+            history
+                .prices
+                .push_back(trade.virtual_sol_reserves as f64 / trade.virtual_token_reserves as f64);
+        }
+
+        if self.now - self.pruned_at >= PRUNE_INTERVAL_SECS {
+            self.mints
+                .retain(|_, history| history.trades.back().is_some_and(|t| t.timestamp > cutoff));
+            self.pruned_at = self.now;
+        }
+    }
+
+    /// `mint`'s flow over the last `window`
+    pub fn stats(&self, mint: &Pubkey, window: Window) -> FlowStats {
+        let mut stats = FlowStats::default();
+        let Some(history) = self.mints.get(mint) else {
+            return stats;
+        };
+        let cutoff = self.now - window.secs();
+        let mut buyers = HashSet::new();
+        for trade in history
+            .trades
+            .iter()
+            .rev()
+            .take_while(|t| t.timestamp > cutoff)
+        {
+            if trade.is_buy {
+                stats.buy_volume += trade.sol_amount;
+                stats.buys += 1;
+                buyers.insert(trade.user);
+            } else {
+                stats.sell_volume += trade.sol_amount;
+                stats.sells += 1;
+            }
+        }
+        stats.unique_buyers = buyers.len();
+        stats
+    }
+
+    /// Mints traded within the longest window
+    pub fn mints(&self) -> impl Iterator<Item = &Pubkey> {
+        self.mints.keys()
+    }
+
+    /// `mint`'s spot price after each of its recent trades, oldest first
+    pub fn prices(&self, mint: &Pubkey) -> Vec<f64> {
+        self.mints
+            .get(mint)
+            .map_or_else(Vec::new, |history| history.prices.iter().copied().collect())
+    }
+
+    pub fn sma(&self, mint: &Pubkey, period: usize) -> Option<f64> {
+        sma(&self.prices(mint), period)
+    }
+
+    pub fn ema(&self, mint: &Pubkey, period: usize) -> Option<f64> {
+        ema(&self.prices(mint), period)
+    }
+
+    pub fn rsi(&self, mint: &Pubkey, period: usize) -> Option<f64> {
+        rsi(&self.prices(mint), period)
+    }
+
+    pub fn rate_of_change(&self, mint: &Pubkey, period: usize) -> Option<f64> {
+        rate_of_change(&self.prices(mint), period)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows_roll_with_the_event_clock() {
+        let (mint, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let trade = |mint, user, is_buy, sol_amount, timestamp| TradeEvent {
+            mint,
+            sol_amount,
+            token_amount: 0,
+            is_buy,
+            user,
+            timestamp,
+            virtual_sol_reserves: 0,
+            virtual_token_reserves: 0,
+            real_sol_reserves: 0,
+            real_token_reserves: 0,
+            fee_recipient: Pubkey::default(),
+            fee_basis_points: 0,
+            fee: 0,
+            creator: Pubkey::default(),
+            creator_fee_basis_points: 0,
+            creator_fee: 0,
+        };
+
+        let mut indicators = Indicators::new();
+        indicators.on_trade(&trade(mint, alice, true, 3_000, 1_000));
+        indicators.on_trade(&trade(mint, bob, true, 2_000, 1_210));
+        indicators.on_trade(&trade(mint, alice, true, 1_000, 1_250));
+        indicators.on_trade(&trade(mint, bob, false, 500, 1_260));
+
+        let last_minute = indicators.stats(&mint, Window::OneMinute);
+        assert_eq!(
+            last_minute,
+            FlowStats {
+                buy_volume: 3_000,
+                sell_volume: 500,
+                buys: 2,
+                sells: 1,
+                unique_buyers: 2,
+            }
+        );
+        assert_eq!(last_minute.net_flow(), 2_500);
+        assert_eq!(last_minute.buy_sell_ratio(), Some(2.0));
+        let five_minutes = indicators.stats(&mint, Window::FiveMinutes);
+        assert_eq!((five_minutes.buys, five_minutes.buy_volume), (3, 6_000));
+        assert_eq!(five_minutes.unique_buyers, 2);
+        // No reserves on these events, so no prices
+        assert_eq!(indicators.rsi(&mint, 2), None);
+
+        // Another mint's trade moves the clock; the first buy falls out of five minutes
+        // and the rest out of one minute
+        indicators.on_trade(&trade(other, alice, true, 100, 1_320));
+        assert_eq!(indicators.stats(&mint, Window::FiveMinutes).buys, 2);
+        assert_eq!(
+            indicators.stats(&mint, Window::OneMinute),
+            FlowStats::default()
+        );
+        assert_eq!(
+            indicators.stats(&other, Window::OneMinute).buy_sell_ratio(),
+            None
+        );
+
+        // Idle mints are dropped
+        indicators.on_trade(&trade(other, bob, true, 100, 1_700));
+        assert_eq!(indicators.mints().collect::<Vec<_>>(), vec![&other]);
+    }
+
+    #[test]
+    fn test_price_signals() {
+        let prices = [10.0, 11.0, 12.0, 11.0, 13.0];
+        assert_eq!(sma(&prices, 2), Some(12.0));
+        assert_eq!(sma(&prices, 6), None);
+        // Seeded at 11, then 11 + 0.5 * 0 = 11, then 11 + 0.5 * 2 = 12
+        assert_eq!(ema(&prices, 3), Some(12.0));
+        assert_eq!(ema(&prices, 0), None);
+        assert!((rate_of_change(&prices, 4).unwrap() - 30.0).abs() < 1e-9);
+        assert_eq!(rate_of_change(&prices, 5), None);
+
+        // Gains 1, 1, 2 and one loss of 1 over four changes: RS = 4 / 1
+        assert!((rsi(&prices, 4).unwrap() - 80.0).abs() < 1e-9);
+        // Smoothed: gains (2/3 * 2 + 2) / 3 = 10/9, losses (1/3 * 2 + 0) / 3 = 2/9, RS = 5
+        assert!((rsi(&prices, 3).unwrap() - (100.0 - 100.0 / 6.0)).abs() < 1e-9);
+        assert_eq!(rsi(&[1.0, 2.0, 3.0], 2), Some(100.0));
+        assert_eq!(rsi(&prices, 5), None);
+    }
+}
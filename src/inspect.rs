@@ -0,0 +1,327 @@
+use anyhow::anyhow;
+use serde_json::{json, Value};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+use spl_token_2022::extension::{BaseStateWithExtensions, StateWithExtensions};
+use spl_token_2022::ID as TOKEN_2022_PROGRAM_ID;
+use tracing::warn;
+
+use crate::accounts::BondingCurve;
+use crate::amount::TokenAmount;
+use crate::error;
+use crate::holders::{self, HolderDistribution};
+use crate::metadata::{self, TokenMetadata};
+use crate::migrations::{self, MigrationRecord};
+use crate::pumpswap::{self, PoolReserves};
+use crate::{cal, price_feed};
+
+// Constants
+/// Largest holders shown in the report
+const TOP_HOLDERS: usize = 10;
+
+/// Where a mint trades
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarketStatus {
+    /// Still trading on its bonding curve
+    Curve,
+    /// The curve completed but no PumpSwap pool exists yet
+    Completed,
+    /// Trading in its PumpSwap pool
+    Migrated,
+    /// No pump.fun curve or pool was found
+    Unknown,
+}
+
+/// One report of everything known about a mint. Parts that could not be fetched are None.
+#[derive(Debug, Clone)]
+pub struct MintReport {
+    pub mint: Pubkey,
+    pub token_program: Pubkey,
+    /// Names of the Token-2022 extensions on the mint, empty for SPL Token mints
+    pub extensions: Vec<String>,
+    pub decimals: u8,
+    pub supply: u64,
+    pub mint_authority: Option<Pubkey>,
+    pub freeze_authority: Option<Pubkey>,
+    pub metadata: Option<TokenMetadata>,
+    pub curve: Option<BondingCurve>,
+    pub migration: Option<MigrationRecord>,
+    /// PumpSwap pool and its reserves, once migrated
+    pub pool: Option<Pubkey>,
+    pub pool_reserves: Option<PoolReserves>,
+    pub holders: Option<HolderDistribution>,
+    pub sol_usd: Option<f64>,
+}
+
+impl MintReport {
+    pub fn status(&self) -> MarketStatus {
+        match (&self.curve, self.pool) {
+            (_, Some(_)) => MarketStatus::Migrated,
+            (Some(curve), None) if curve.complete => MarketStatus::Completed,
+            (Some(_), None) => MarketStatus::Curve,
+            (None, None) => MarketStatus::Unknown,
+        }
+    }
+
+    /// Lamports per raw token: the pool's price once migrated, otherwise the curve's
+    pub fn spot_price(&self) -> Option<f64> {
+        match (&self.pool_reserves, &self.curve) {
+            (Some(pool), _) if pool.base_reserve > 0 => {
+                Some(pool.quote_reserve as f64 / pool.base_reserve as f64)
+            }
+            (None, Some(curve)) if !curve.complete => {
+                Some(cal::spot_price_lamports_per_token(curve))
+            }
+            _ => None,
+        }
+    }
+
+    /// Fully diluted market cap in SOL at `spot_price`
+    pub fn market_cap_sol(&self) -> Option<f64> {
+        self.spot_price()
+            .map(|price| price * self.supply as f64 / LAMPORTS_PER_SOL as f64)
+    }
+
+    pub fn market_cap_usd(&self) -> Option<f64> {
+        Some(self.market_cap_sol()? * self.sol_usd?)
+    }
+
+    pub fn graduation_progress(&self) -> Option<f64> {
+        self.curve.as_ref().map(cal::graduation_progress)
+    }
+
+    pub fn print(&self) {
+        let show = |value: Option<String>| value.unwrap_or_else(|| "unknown".to_string());
+        let pct = |value: Option<f64>| show(value.map(|p| format!("{:.1}%", p)));
+        let name = self.metadata.as_ref().map(TokenMetadata::display_name);
+        println!("\n=== Mint: {} ===", self.mint);
+        println!("  Token:            {}", show(name));
+        if let Some(uri) = self.metadata.as_ref().map(|m| &m.uri) {
+            println!("  Metadata URI:     {}", uri);
+        }
+        let program = if self.token_program == TOKEN_2022_PROGRAM_ID {
+            "Token-2022"
+        } else {
+            "SPL Token"
+        };
+        println!("  Program:          {}", program);
+        if !self.extensions.is_empty() {
+            println!("  Extensions:       {}", self.extensions.join(", "));
+        }
+        println!(
+            "  Supply:           {}",
+            TokenAmount::new(self.supply, self.decimals)
+        );
+        println!(
+            "  Mint authority:   {}",
+            self.mint_authority
+                .map_or("revoked".to_string(), |a| a.to_string())
+        );
+        println!(
+            "  Freeze authority: {}",
+            self.freeze_authority
+                .map_or("none".to_string(), |a| a.to_string())
+        );
+        println!(
+            "  Creator:          {}",
+            show(self.curve.as_ref().map(|c| c.creator.to_string()))
+        );
+        println!("  Status:           {:?}", self.status());
+        if let Some(pool) = self.pool {
+            println!("  Pool:             {}", pool);
+        }
+        println!("  Progress:         {}", pct(self.graduation_progress()));
+        if let Some(curve) = self.curve.as_ref().filter(|c| !c.complete) {
+            println!(
+                "  Curve reserves:   {:.4} SOL / {} tokens",
+                curve.real_sol_reserves as f64 / LAMPORTS_PER_SOL as f64,
+                TokenAmount::new(curve.real_token_reserves, self.decimals)
+            );
+        }
+        println!(
+            "  Spot price:       {}",
+            show(
+                self.spot_price()
+                    .map(|p| format!("{:.6} lamports/token", p))
+            )
+        );
+        println!(
+            "  Market cap:       {}{}",
+            show(self.market_cap_sol().map(|m| format!("{:.2} SOL", m))),
+            self.market_cap_usd()
+                .map_or(String::new(), |usd| format!(" (${:.0})", usd))
+        );
+        if let Some(holders) = &self.holders {
+            println!(
+                "  Holders:          {}",
+                show(holders.holder_count.map(|c| c.to_string()))
+            );
+            println!("  Top 10 hold:      {:.1}%", holders.top10_pct());
+            println!("  Creator holds:    {}", pct(holders.dev_pct()));
+            println!("  Bundlers hold:    {:.1}%", holders.bundler_pct());
+        }
+    }
+
+    /// The report as JSON. Raw amounts stay integers; unknown values are null.
+    pub fn to_json(&self) -> Value {
+        let key = |key: Option<Pubkey>| key.map(|k| k.to_string());
+        json!({
+            "mint": self.mint.to_string(),
+            "token_program": self.token_program.to_string(),
+            "token_2022": self.token_program == TOKEN_2022_PROGRAM_ID,
+            "extensions": self.extensions,
+            "decimals": self.decimals,
+            "supply": self.supply,
+            "mint_authority": key(self.mint_authority),
+            "freeze_authority": key(self.freeze_authority),
+            "metadata": self.metadata.as_ref().map(|m| json!({
+                "name": m.name,
+                "symbol": m.symbol,
+                "uri": m.uri,
+            })),
+            "creator": key(self.curve.as_ref().map(|c| c.creator)),
+            "status": format!("{:?}", self.status()).to_lowercase(),
+            "curve": self.curve.as_ref().map(|c| json!({
+                "virtual_sol_reserves": c.virtual_sol_reserves,
+                "virtual_token_reserves": c.virtual_token_reserves,
+                "real_sol_reserves": c.real_sol_reserves,
+                "real_token_reserves": c.real_token_reserves,
+                "complete": c.complete,
+            })),
+            "graduation_progress_pct": self.graduation_progress(),
+            "pool": key(self.pool),
+            "migrated_at": self.migration.as_ref().and_then(|m| m.migrated_at),
+            "spot_price_lamports_per_token": self.spot_price(),
+            "market_cap_sol": self.market_cap_sol(),
+            "market_cap_usd": self.market_cap_usd(),
+            "holders": self.holders.as_ref().map(|h| json!({
+                "count": h.holder_count,
+                "top10_pct": h.top10_pct(),
+                "creator_pct": h.dev_pct(),
+                "bundler_pct": h.bundler_pct(),
+                "top": h.holders.iter().take(TOP_HOLDERS).map(|holder| json!({
+                    "owner": holder.owner.to_string(),
+                    "amount": holder.amount,
+                })).collect::<Vec<_>>(),
+            })),
+        })
+    }
+}
+
+/// Gather the report for `mint`. Only the mint account is required; every other part
+/// that can't be fetched is logged and left out.
+pub fn inspect(rpc: &RpcClient, mint: &Pubkey) -> error::Result<MintReport> {
+    let mint_account = cal::fetch_account(rpc, mint)?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_account.data)
+        .map_err(|e| anyhow!("{} is not a mint: {}", mint, e))?;
+    let extensions = state
+        .get_extension_types()
+        .map_err(|e| anyhow!("Failed to read extensions of {}: {}", mint, e))?
+        .iter()
+        .map(|extension| format!("{:?}", extension))
+        .collect();
+
+    let curve = cal::fetch_bonding_curve(rpc, mint)
+        .map_err(|e| warn!(%mint, error = %e, "No bonding curve"))
+        .ok();
+    let migration = match &curve {
+        Some(curve) if curve.complete => migrations::detect_migration(rpc, mint)
+            .map_err(|e| warn!(%mint, error = %e, "Failed to check migration"))
+            .ok()
+            .flatten(),
+        _ => None,
+    };
+    // Tokens launched straight into a pool have no curve to complete
+    let pool = match (&curve, &migration) {
+        (Some(curve), _) if !curve.complete => None,
+        (_, Some(MigrationRecord { pool: None, .. })) => None,
+        _ => pumpswap::fetch_pool(rpc, mint).ok(),
+    };
+    let pool_reserves = pool.as_ref().and_then(|(_, pool)| {
+        pumpswap::fetch_pool_reserves(rpc, pool)
+            .map_err(|e| warn!(%mint, error = %e, "Failed to fetch pool reserves"))
+            .ok()
+    });
+
+    let metadata = metadata::fetch_metadata(rpc, mint, false)
+        .map_err(|e| warn!(%mint, error = %e, "Failed to fetch metadata"))
+        .ok();
+    let holders = holders::scan(
+        rpc,
+        mint,
+        &mint_account.owner,
+        state.base.supply,
+        curve.as_ref().map(|c| c.creator),
+    )
+    .map_err(|e| warn!(%mint, error = %e, "Failed to scan holders"))
+    .ok();
+    let sol_usd = price_feed::sol_usd(rpc)
+        .map_err(|e| warn!(error = %e, "Failed to fetch SOL price"))
+        .ok()
+        .map(|price| price.usd);
+
+    Ok(MintReport {
+        mint: *mint,
+        token_program: mint_account.owner,
+        extensions,
+        decimals: state.base.decimals,
+        supply: state.base.supply,
+        mint_authority: state.base.mint_authority.into(),
+        freeze_authority: state.base.freeze_authority.into(),
+        metadata,
+        curve,
+        migration,
+        pool: pool.map(|(address, _)| address),
+        pool_reserves,
+        holders,
+        sol_usd,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_and_price_follow_the_venue() {
+        let global = cal::Global::default();
+        let mut report = MintReport {
+            mint: Pubkey::new_unique(),
+            token_program: spl_token::ID,
+            extensions: Vec::new(),
+            decimals: 6,
+            supply: global.token_total_supply,
+            mint_authority: None,
+            freeze_authority: None,
+            metadata: None,
+            curve: Some(cal::new_bonding_curve(&global)),
+            migration: None,
+            pool: None,
+            pool_reserves: None,
+            holders: None,
+            sol_usd: Some(200.0),
+        };
+        assert_eq!(report.status(), MarketStatus::Curve);
+        let curve_price = report.spot_price().unwrap();
+        assert!(curve_price > 0.0);
+        assert!(report.market_cap_usd().unwrap() > report.market_cap_sol().unwrap());
+        assert_eq!(report.to_json()["status"], "curve");
+
+        report.curve.as_mut().unwrap().complete = true;
+        assert_eq!(report.status(), MarketStatus::Completed);
+        assert_eq!(report.spot_price(), None);
+        assert_eq!(report.graduation_progress(), Some(100.0));
+
+        report.pool = Some(Pubkey::new_unique());
+        report.pool_reserves = Some(PoolReserves {
+            base_reserve: 200_000_000_000_000,
+            quote_reserve: 85_000_000_000,
+        });
+        assert_eq!(report.status(), MarketStatus::Migrated);
+        assert_eq!(
+            report.spot_price(),
+            Some(85_000_000_000.0 / 200_000_000_000_000.0)
+        );
+        assert!(report.to_json()["market_cap_sol"].as_f64().unwrap() > 0.0);
+    }
+}
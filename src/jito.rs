@@ -0,0 +1,194 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rand::seq::SliceRandom;
+use serde_json::{json, Value};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig, instruction::Instruction, pubkey::Pubkey,
+    signature::Signature, system_instruction, transaction::TransactionError,
+    transaction::VersionedTransaction,
+};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+use crate::config;
+
+// Constants
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Most transactions the block engine accepts in one bundle
+pub const MAX_BUNDLE_TRANSACTIONS: usize = 5;
+const CONFIRM_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+lazy_static::lazy_static! {
+    /// Accounts the block engine takes tips on; any one of them will do
+    static ref TIP_ACCOUNTS: [Pubkey; 8] = [
+        "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5",
+        "HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe",
+        "Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY",
+        "ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49",
+        "DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh",
+        "ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt",
+        "DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL",
+        "3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT",
+    ]
+    .map(|tip| Pubkey::from_str(tip).unwrap());
+}
+
+/// Transfer of `lamports` from `payer` to a random tip account. Bundles are only
+/// auctioned with a tip in one of their transactions.
+pub fn tip_instruction(payer: &Pubkey, lamports: u64) -> Instruction {
+    let tip_account = TIP_ACCOUNTS
+        .choose(&mut rand::thread_rng())
+        .expect("tip accounts are not empty");
+    system_instruction::transfer(payer, tip_account, lamports)
+}
+
+/// Whether `address` is one of the block engine's tip accounts
+pub fn is_tip_account(address: &Pubkey) -> bool {
+    TIP_ACCOUNTS.contains(address)
+}
+
+/// `sendBundle` request for `transactions`, base64 encoded
+fn bundle_request(transactions: &[VersionedTransaction]) -> Result<Value> {
+    let encoded = transactions
+        .iter()
+        .map(|tx| Ok(BASE64.encode(bincode::serialize(tx)?)))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "sendBundle",
+        "params": [encoded, { "encoding": "base64" }],
+    }))
+}
+
+/// POST a JSON-RPC `request` to `path` on `jito.block_engine_url` and return its result
+fn block_engine_request(path: &str, request: &Value) -> Result<Value> {
+    let url = format!(
+        "{}{}",
+        config::get().jito.block_engine_url.trim_end_matches('/'),
+        path
+    );
+    let client = reqwest::blocking::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?;
+    let response: Value = client
+        .post(url)
+        .json(request)
+        .send()?
+        .error_for_status()?
+        .json()?;
+    if let Some(error) = response.get("error") {
+        return Err(anyhow!("Block engine rejected request: {}", error));
+    }
+    Ok(response["result"].clone())
+}
+
+/// Submit `transactions` to `jito.block_engine_url` as one bundle: they land together, in
+/// order, in the same block, or not at all. Returns the bundle id.
+pub fn send_bundle(transactions: &[VersionedTransaction]) -> Result<String> {
+    if transactions.is_empty() || transactions.len() > MAX_BUNDLE_TRANSACTIONS {
+        return Err(anyhow!(
+            "A bundle holds 1 to {} transactions, not {}",
+            MAX_BUNDLE_TRANSACTIONS,
+            transactions.len()
+        ));
+    }
+    let result = block_engine_request("/api/v1/bundles", &bundle_request(transactions)?)?;
+    let bundle_id = result
+        .as_str()
+        .ok_or_else(|| anyhow!("Block engine returned no bundle id"))?
+        .to_string();
+    info!(%bundle_id, transactions = transactions.len(), "Sent bundle");
+    Ok(bundle_id)
+}
+
+/// Forward one signed `transaction` to the leader through the block engine, outside any
+/// bundle. Without a tip it is not auctioned, just another route to the leader.
+pub fn send_transaction(transaction: &VersionedTransaction) -> Result<()> {
+    forward_transaction("/api/v1/transactions", transaction)
+}
+
+/// Submit one signed `transaction` through the block engine as a single-transaction
+/// bundle only: it is never forwarded to the leader's public TPU, so it can't be seen
+/// and sandwiched before it lands. It must pay a tip to be auctioned at all.
+pub fn send_bundle_only(transaction: &VersionedTransaction) -> Result<()> {
+    forward_transaction("/api/v1/transactions?bundleOnly=true", transaction)
+}
+
+/// Whether `transaction` pays one of the tip accounts
+pub fn has_tip(transaction: &VersionedTransaction) -> bool {
+    transaction
+        .message
+        .static_account_keys()
+        .iter()
+        .any(is_tip_account)
+}
+
+/// `sendTransaction` request for `transaction` posted to `path`
+fn forward_transaction(path: &str, transaction: &VersionedTransaction) -> Result<()> {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "sendTransaction",
+        "params": [
+            BASE64.encode(bincode::serialize(transaction)?),
+            { "encoding": "base64" },
+        ],
+    });
+    block_engine_request(path, &request)?;
+    Ok(())
+}
+
+/// Wait for a bundle containing `signature` to land, until its blockhash expires after
+/// `last_valid_block_height`. A bundle lands whole, so any one of its signatures will
+/// do. Returns None if it never landed.
+pub fn confirm_bundle(
+    rpc: &RpcClient,
+    signature: &Signature,
+    last_valid_block_height: u64,
+) -> Result<Option<std::result::Result<(), TransactionError>>> {
+    let started = Instant::now();
+    loop {
+        let status =
+            rpc.get_signature_status_with_commitment(signature, CommitmentConfig::confirmed())?;
+        if status.is_some() {
+            debug!(%signature, elapsed = ?started.elapsed(), "Bundle landed");
+            return Ok(status);
+        }
+        if rpc.get_block_height()? > last_valid_block_height {
+            return Ok(None);
+        }
+        std::thread::sleep(CONFIRM_POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{signature::Keypair, signer::Signer, transaction::Transaction};
+
+    #[test]
+    fn test_tip_and_bundle_encoding() {
+        let payer = Keypair::new();
+        let tip = tip_instruction(&payer.pubkey(), 10_000);
+        assert!(TIP_ACCOUNTS.contains(&tip.accounts[1].pubkey));
+
+        let tx = VersionedTransaction::from(Transaction::new_signed_with_payer(
+            &[tip],
+            Some(&payer.pubkey()),
+            &[&payer],
+            Default::default(),
+        ));
+        let request = bundle_request(&[tx.clone(), tx.clone()]).unwrap();
+        let encoded = request["params"][0].as_array().unwrap();
+        assert_eq!(encoded.len(), 2);
+        let decoded: VersionedTransaction =
+            bincode::deserialize(&BASE64.decode(encoded[0].as_str().unwrap()).unwrap()).unwrap();
+        assert_eq!(decoded.signatures, tx.signatures);
+
+        assert!(send_bundle(&[]).is_err());
+        assert!(send_bundle(&vec![tx; MAX_BUNDLE_TRANSACTIONS + 1]).is_err());
+    }
+}
@@ -0,0 +1,485 @@
+use anyhow::{anyhow, Result};
+use serde_json::json;
+use solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::str::FromStr;
+
+use crate::address_book;
+use crate::portfolio::{Fill, Portfolio, Side};
+
+// Constants
+/// Lots held longer than this are long-term disposals
+const LONG_TERM_SECS: i64 = 365 * 24 * 60 * 60;
+
+/// Which lots a sell is matched against first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LotMethod {
+    /// Oldest lots first
+    #[default]
+    Fifo,
+    /// Newest lots first
+    Lifo,
+}
+
+impl LotMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LotMethod::Fifo => "fifo",
+            LotMethod::Lifo => "lifo",
+        }
+    }
+}
+
+impl FromStr for LotMethod {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "fifo" => Ok(LotMethod::Fifo),
+            "lifo" => Ok(LotMethod::Lifo),
+            other => Err(anyhow!("{:?} must be one of fifo, lifo", other)),
+        }
+    }
+}
+
+/// Tokens bought in one fill and not yet sold
+#[derive(Debug, Clone)]
+struct Lot {
+    acquired_at: i64,
+    signature: String,
+    token_amount: u64,
+    cost_basis_lamports: u64,
+    sol_usd: Option<f64>,
+}
+
+/// Part of a sell matched against one lot. Tokens sold beyond every recorded lot (e.g.
+/// received outside the bot) are matched at zero cost with no acquisition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LotMatch {
+    /// Unix seconds the lot was bought, None for tokens with no recorded buy
+    pub acquired_at: Option<i64>,
+    pub acquired_signature: Option<String>,
+    pub token_amount: u64,
+    pub cost_basis_lamports: u64,
+    pub proceeds_lamports: u64,
+    /// Cost basis at SOL/USD when the lot was bought
+    pub cost_basis_usd: Option<f64>,
+    /// Proceeds at SOL/USD when the lot was sold
+    pub proceeds_usd: Option<f64>,
+}
+
+impl LotMatch {
+    pub fn gain_lamports(&self) -> i64 {
+        self.proceeds_lamports as i64 - self.cost_basis_lamports as i64
+    }
+
+    /// Gain in USD, None unless both sides were priced. Unknown acquisitions have no cost.
+    pub fn gain_usd(&self) -> Option<f64> {
+        let cost = match self.acquired_at {
+            Some(_) => self.cost_basis_usd?,
+            None => 0.0,
+        };
+        Some(self.proceeds_usd? - cost)
+    }
+
+    /// Whether the lot was held longer than a year when sold at `disposed_at`
+    pub fn is_long_term(&self, disposed_at: i64) -> bool {
+        self.acquired_at
+            .is_some_and(|acquired_at| disposed_at - acquired_at > LONG_TERM_SECS)
+    }
+}
+
+/// One fill with its USD value and, for sells, the lots it disposed of
+#[derive(Debug, Clone)]
+pub struct JournalEntry {
+    pub fill: Fill,
+    /// SOL/USD recorded when the fill executed
+    pub sol_usd: Option<f64>,
+    /// Empty for buys
+    pub lots: Vec<LotMatch>,
+}
+
+impl JournalEntry {
+    pub fn usd_value(&self) -> Option<f64> {
+        self.sol_usd
+            .map(|usd| lamports_to_usd(self.fill.sol_amount, usd))
+    }
+
+    pub fn cost_basis_lamports(&self) -> u64 {
+        self.lots.iter().map(|lot| lot.cost_basis_lamports).sum()
+    }
+
+    /// Realized gain of a sell, None for buys
+    pub fn gain_lamports(&self) -> Option<i64> {
+        (self.fill.side == Side::Sell).then(|| self.lots.iter().map(LotMatch::gain_lamports).sum())
+    }
+
+    /// Realized USD gain of a sell, None for buys or when any lot is unpriced
+    pub fn gain_usd(&self) -> Option<f64> {
+        if self.fill.side != Side::Sell {
+            return None;
+        }
+        self.lots.iter().map(LotMatch::gain_usd).sum()
+    }
+}
+
+/// Every fill in execution order, with sells matched to lots for capital-gains reporting
+#[derive(Debug, Clone)]
+pub struct Journal {
+    pub method: LotMethod,
+    pub entries: Vec<JournalEntry>,
+}
+
+fn lamports_to_usd(lamports: u64, sol_usd: f64) -> f64 {
+    lamports as f64 / LAMPORTS_PER_SOL as f64 * sol_usd
+}
+
+/// `part` of `total` of `amount`, rounded down
+fn pro_rata(amount: u64, part: u64, total: u64) -> u64 {
+    if total == 0 {
+        return 0;
+    }
+    (amount as u128 * part as u128 / total as u128) as u64
+}
+
+/// Take `token_amount` tokens out of `lots` (one mint's, oldest first) per `method`
+fn match_lots(
+    lots: &mut VecDeque<Lot>,
+    method: LotMethod,
+    token_amount: u64,
+) -> Vec<(Option<Lot>, u64)> {
+    let mut matched = Vec::new();
+    let mut remaining = token_amount;
+    while remaining > 0 {
+        let lot = match method {
+            LotMethod::Fifo => lots.front_mut(),
+            LotMethod::Lifo => lots.back_mut(),
+        };
+        let Some(lot) = lot else {
+            matched.push((None, remaining));
+            break;
+        };
+        let take = remaining.min(lot.token_amount);
+        let cost = pro_rata(lot.cost_basis_lamports, take, lot.token_amount);
+        matched.push((
+            Some(Lot {
+                token_amount: take,
+                cost_basis_lamports: cost,
+                ..lot.clone()
+            }),
+            take,
+        ));
+        lot.token_amount -= take;
+        lot.cost_basis_lamports -= cost;
+        remaining -= take;
+        if lot.token_amount == 0 {
+            match method {
+                LotMethod::Fifo => lots.pop_front(),
+                LotMethod::Lifo => lots.pop_back(),
+            };
+        }
+    }
+    matched
+}
+
+impl Journal {
+    /// Journal `fills` (execution order), valuing them at `prices` (SOL/USD by signature)
+    pub fn build(fills: &[Fill], prices: &HashMap<String, f64>, method: LotMethod) -> Self {
+        let mut open_lots: HashMap<Pubkey, VecDeque<Lot>> = HashMap::new();
+        let mut entries = Vec::with_capacity(fills.len());
+        for fill in fills {
+            let sol_usd = prices.get(&fill.signature).copied();
+            let lots = open_lots.entry(fill.mint).or_default();
+            let mut entry = JournalEntry {
+                fill: fill.clone(),
+                sol_usd,
+                lots: Vec::new(),
+            };
+            match fill.side {
+                Side::Buy => lots.push_back(Lot {
+                    acquired_at: fill.timestamp,
+                    signature: fill.signature.clone(),
+                    token_amount: fill.token_amount,
+                    cost_basis_lamports: fill.sol_amount,
+                    sol_usd,
+                }),
+                Side::Sell => {
+                    // Proceeds are split across lots by tokens, the last lot taking the
+                    // rounding so they add up to the fill
+                    let matched = match_lots(lots, method, fill.token_amount);
+                    let mut proceeds_left = fill.sol_amount;
+                    let last = matched.len().saturating_sub(1);
+                    for (i, (lot, tokens)) in matched.into_iter().enumerate() {
+                        let proceeds = if i == last {
+                            proceeds_left
+                        } else {
+                            pro_rata(fill.sol_amount, tokens, fill.token_amount)
+                        };
+                        proceeds_left -= proceeds;
+                        entry.lots.push(LotMatch {
+                            acquired_at: lot.as_ref().map(|lot| lot.acquired_at),
+                            acquired_signature: lot.as_ref().map(|lot| lot.signature.clone()),
+                            token_amount: tokens,
+                            cost_basis_lamports: lot
+                                .as_ref()
+                                .map_or(0, |lot| lot.cost_basis_lamports),
+                            proceeds_lamports: proceeds,
+                            cost_basis_usd: lot.as_ref().and_then(|lot| {
+                                Some(lamports_to_usd(lot.cost_basis_lamports, lot.sol_usd?))
+                            }),
+                            proceeds_usd: sol_usd.map(|usd| lamports_to_usd(proceeds, usd)),
+                        });
+                    }
+                }
+            }
+            entries.push(entry);
+        }
+        Journal { method, entries }
+    }
+
+    /// Realized gain in lamports over every sell, split into (short-term, long-term)
+    pub fn realized_gain_lamports(&self) -> (i64, i64) {
+        let (mut short, mut long) = (0, 0);
+        for entry in &self.entries {
+            for lot in &entry.lots {
+                if lot.is_long_term(entry.fill.timestamp) {
+                    long += lot.gain_lamports();
+                } else {
+                    short += lot.gain_lamports();
+                }
+            }
+        }
+        (short, long)
+    }
+
+    /// Sells with a lot that couldn't be valued in USD
+    pub fn unpriced_sells(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.fill.side == Side::Sell && entry.gain_usd().is_none())
+            .count()
+    }
+
+    /// One row per fill. Unknown values are left empty.
+    pub fn to_csv(&self) -> String {
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        let mut csv = "timestamp,signature,mint,side,token_amount,sol_amount,fee_lamports,sol_usd,\
+                       usd_value,cost_basis_lamports,gain_lamports,gain_usd\n"
+            .to_string();
+        for entry in &self.entries {
+            let fill = &entry.fill;
+            let is_sell = fill.side == Side::Sell;
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                fill.timestamp,
+                fill.signature,
+                fill.mint,
+                fill.side.as_str(),
+                fill.token_amount,
+                fill.sol_amount,
+                fill.fee_lamports,
+                optional(entry.sol_usd.map(|v| v.to_string())),
+                optional(entry.usd_value().map(|v| format!("{:.2}", v))),
+                optional(is_sell.then(|| entry.cost_basis_lamports().to_string())),
+                optional(entry.gain_lamports().map(|v| v.to_string())),
+                optional(entry.gain_usd().map(|v| format!("{:.2}", v))),
+            ));
+        }
+        csv
+    }
+
+    /// One row per lot disposed of, with its holding term
+    pub fn to_lots_csv(&self) -> String {
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        let mut csv = "mint,acquired_at,acquired_signature,disposed_at,disposed_signature,\
+                       token_amount,cost_basis_lamports,proceeds_lamports,gain_lamports,\
+                       cost_basis_usd,proceeds_usd,gain_usd,term\n"
+            .to_string();
+        for entry in &self.entries {
+            for lot in &entry.lots {
+                csv.push_str(&format!(
+                    "{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                    entry.fill.mint,
+                    optional(lot.acquired_at.map(|v| v.to_string())),
+                    optional(lot.acquired_signature.clone()),
+                    entry.fill.timestamp,
+                    entry.fill.signature,
+                    lot.token_amount,
+                    lot.cost_basis_lamports,
+                    lot.proceeds_lamports,
+                    lot.gain_lamports(),
+                    optional(lot.cost_basis_usd.map(|v| format!("{:.2}", v))),
+                    optional(lot.proceeds_usd.map(|v| format!("{:.2}", v))),
+                    optional(lot.gain_usd().map(|v| format!("{:.2}", v))),
+                    term(lot, entry.fill.timestamp),
+                ));
+            }
+        }
+        csv
+    }
+
+    /// Totals and every fill with its matched lots
+    pub fn to_json(&self) -> Result<String> {
+        let entries: Vec<_> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let fill = &entry.fill;
+                let lots: Vec<_> = entry
+                    .lots
+                    .iter()
+                    .map(|lot| {
+                        json!({
+                            "acquired_at": lot.acquired_at,
+                            "acquired_signature": lot.acquired_signature,
+                            "token_amount": lot.token_amount,
+                            "cost_basis_lamports": lot.cost_basis_lamports,
+                            "proceeds_lamports": lot.proceeds_lamports,
+                            "gain_lamports": lot.gain_lamports(),
+                            "cost_basis_usd": lot.cost_basis_usd,
+                            "proceeds_usd": lot.proceeds_usd,
+                            "gain_usd": lot.gain_usd(),
+                            "term": term(lot, fill.timestamp),
+                        })
+                    })
+                    .collect();
+                json!({
+                    "timestamp": fill.timestamp,
+                    "signature": fill.signature,
+                    "mint": fill.mint.to_string(),
+                    "side": fill.side.as_str(),
+                    "token_amount": fill.token_amount,
+                    "sol_amount": fill.sol_amount,
+                    "fee_lamports": fill.fee_lamports,
+                    "sol_usd": entry.sol_usd,
+                    "usd_value": entry.usd_value(),
+                    "gain_lamports": entry.gain_lamports(),
+                    "gain_usd": entry.gain_usd(),
+                    "lots": lots,
+                })
+            })
+            .collect();
+        let (short_term, long_term) = self.realized_gain_lamports();
+        let journal = json!({
+            "method": self.method.as_str(),
+            "short_term_gain_lamports": short_term,
+            "long_term_gain_lamports": long_term,
+            "entries": entries,
+        });
+        serde_json::to_string_pretty(&journal)
+            .map_err(|e| anyhow!("Failed to encode journal: {}", e))
+    }
+
+    pub fn print(&self) {
+        let sol = |lamports: i64| lamports as f64 / LAMPORTS_PER_SOL as f64;
+        println!(
+            "\n=== Trade Journal ({}) ===",
+            self.method.as_str().to_uppercase()
+        );
+        let mut by_mint: BTreeMap<String, (usize, i64)> = BTreeMap::new();
+        for entry in &self.entries {
+            let row = by_mint
+                .entry(address_book::label(&entry.fill.mint))
+                .or_default();
+            row.0 += 1;
+            row.1 += entry.gain_lamports().unwrap_or(0);
+        }
+        for (mint, (fills, gain)) in &by_mint {
+            println!(
+                "  {:<44} {:>4} fills, {:+.4} SOL realized",
+                mint,
+                fills,
+                sol(*gain)
+            );
+        }
+        let (short_term, long_term) = self.realized_gain_lamports();
+        println!("Short-term: {:+.4} SOL", sol(short_term));
+        println!("Long-term:  {:+.4} SOL", sol(long_term));
+        let unpriced = self.unpriced_sells();
+        if unpriced > 0 {
+            println!("{} sells have no USD value recorded", unpriced);
+        }
+    }
+}
+
+fn term(lot: &LotMatch, disposed_at: i64) -> &'static str {
+    if lot.is_long_term(disposed_at) {
+        "long"
+    } else {
+        "short"
+    }
+}
+
+/// Journal every fill in `portfolio`
+pub fn generate(portfolio: &Portfolio, method: LotMethod) -> Result<Journal> {
+    let fills = portfolio.get_fills(None)?;
+    let prices = portfolio.get_fill_prices()?;
+    Ok(Journal::build(&fills, &prices, method))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(mint: Pubkey, side: Side, tokens: u64, sol: u64, timestamp: i64) -> Fill {
+        Fill {
+            timestamp,
+            ..Fill::new(mint, side, tokens, sol, 0, format!("sig{}", timestamp))
+        }
+    }
+
+    #[test]
+    fn test_fifo_and_lifo_lot_matching() {
+        let mint = Pubkey::new_unique();
+        let sol = LAMPORTS_PER_SOL;
+        let fills = vec![
+            fill(mint, Side::Buy, 1_000, sol, 10),
+            fill(mint, Side::Buy, 1_000, 3 * sol, 20 + LONG_TERM_SECS),
+            fill(mint, Side::Sell, 1_500, 6 * sol, 30 + LONG_TERM_SECS),
+            // 500 more than was ever bought
+            fill(mint, Side::Sell, 1_000, sol, 40 + LONG_TERM_SECS),
+        ];
+        let prices = HashMap::from([
+            ("sig10".to_string(), 100.0),
+            (format!("sig{}", 30 + LONG_TERM_SECS), 200.0),
+        ]);
+
+        let fifo = Journal::build(&fills, &prices, LotMethod::Fifo);
+        let sell = &fifo.entries[2];
+        assert_eq!(sell.lots.len(), 2);
+        assert_eq!(sell.lots[0].acquired_at, Some(10));
+        assert_eq!(sell.lots[0].cost_basis_lamports, sol);
+        assert_eq!(sell.lots[1].cost_basis_lamports, 3 * sol / 2);
+        assert_eq!(sell.lots[0].proceeds_lamports, 4 * sol);
+        assert_eq!(sell.lots[1].proceeds_lamports, 2 * sol);
+        assert_eq!(sell.gain_lamports(), Some(7 * sol as i64 / 2));
+        assert!(sell.lots[0].is_long_term(sell.fill.timestamp));
+        assert!(!sell.lots[1].is_long_term(sell.fill.timestamp));
+        assert_eq!(sell.usd_value(), Some(1_200.0));
+        assert_eq!(sell.lots[0].gain_usd(), Some(800.0 - 100.0));
+        // The second lot's buy was never priced
+        assert_eq!(sell.gain_usd(), None);
+        assert_eq!(fifo.unpriced_sells(), 2);
+
+        let oversold = &fifo.entries[3];
+        assert_eq!(oversold.lots[1].acquired_at, None);
+        assert_eq!(oversold.lots[1].token_amount, 500);
+        assert_eq!(oversold.cost_basis_lamports(), 3 * sol / 2);
+        assert_eq!(fifo.realized_gain_lamports(), (0, 3 * sol as i64));
+
+        let lifo = Journal::build(&fills, &prices, LotMethod::Lifo);
+        let sell = &lifo.entries[2];
+        assert_eq!(sell.lots[0].cost_basis_lamports, 3 * sol);
+        assert_eq!(sell.lots[1].cost_basis_lamports, sol / 2);
+        assert_eq!(sell.gain_lamports(), Some(5 * sol as i64 / 2));
+        assert_eq!(lifo.entries[0].gain_lamports(), None);
+
+        assert_eq!(fifo.to_csv().lines().count(), 5);
+        assert_eq!(fifo.to_lots_csv().lines().count(), 5);
+        let json: serde_json::Value = serde_json::from_str(&fifo.to_json().unwrap()).unwrap();
+        assert_eq!(json["method"], "fifo");
+        assert_eq!(json["long_term_gain_lamports"], 3 * sol);
+        assert_eq!("lifo".parse::<LotMethod>().unwrap(), LotMethod::Lifo);
+        assert!("hifo".parse::<LotMethod>().is_err());
+    }
+}
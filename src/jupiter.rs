@@ -0,0 +1,270 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde_json::{json, Value};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{pubkey::Pubkey, signer::Signer, transaction::VersionedTransaction};
+use spl_token::native_mint::ID as WSOL_MINT;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{debug, info, instrument};
+
+use crate::config::{self, SendStrategy};
+use crate::error::{self, BotError};
+use crate::portfolio::{Fill, Side};
+use crate::sender;
+
+// Constants
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A route quoted by Jupiter's `/quote`
+#[derive(Debug, Clone)]
+pub struct Quote {
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub in_amount: u64,
+    pub out_amount: u64,
+    /// Least the swap will accept: `out_amount` less `slippage_bps`
+    pub min_out_amount: u64,
+    pub slippage_bps: u64,
+    pub price_impact_pct: f64,
+    /// Labels of the AMMs the route goes through, e.g. "Raydium"
+    pub route: Vec<String>,
+    /// The response as returned, which `/swap` takes back
+    raw: Value,
+}
+
+fn parse_amount(raw: &Value, field: &str) -> Result<u64> {
+    raw[field]
+        .as_str()
+        .ok_or_else(|| anyhow!("Jupiter quote has no {}", field))?
+        .parse()
+        .map_err(|e| anyhow!("Invalid {} in Jupiter quote: {}", field, e))
+}
+
+fn parse_mint(raw: &Value, field: &str) -> Result<Pubkey> {
+    let mint = raw[field]
+        .as_str()
+        .ok_or_else(|| anyhow!("Jupiter quote has no {}", field))?;
+    Pubkey::from_str(mint).map_err(|e| anyhow!("Invalid {} in Jupiter quote: {}", field, e))
+}
+
+impl Quote {
+    /// Parse a `/quote` response
+    pub fn parse(raw: Value) -> Result<Self> {
+        let route = raw["routePlan"]
+            .as_array()
+            .map(|steps| {
+                steps
+                    .iter()
+                    .filter_map(|step| step["swapInfo"]["label"].as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Self {
+            input_mint: parse_mint(&raw, "inputMint")?,
+            output_mint: parse_mint(&raw, "outputMint")?,
+            in_amount: parse_amount(&raw, "inAmount")?,
+            out_amount: parse_amount(&raw, "outAmount")?,
+            min_out_amount: parse_amount(&raw, "otherAmountThreshold")?,
+            slippage_bps: raw["slippageBps"].as_u64().unwrap_or_default(),
+            price_impact_pct: raw["priceImpactPct"]
+                .as_str()
+                .and_then(|pct| pct.parse().ok())
+                .unwrap_or_default(),
+            route,
+            raw,
+        })
+    }
+
+    /// The same route, with its slippage set so the swap returns at least `min_out_amount`.
+    /// Fails if the route already quotes less than that.
+    pub fn with_min_out(mut self, min_out_amount: u64) -> error::Result<Self> {
+        if self.out_amount == 0 || self.out_amount < min_out_amount {
+            return Err(BotError::SlippageExceeded(format!(
+                "Jupiter quotes {} for {}, below the minimum {}",
+                self.out_amount, self.output_mint, min_out_amount
+            )));
+        }
+        // The route enforces out_amount less slippage_bps, rounded so it stays >= the minimum
+        self.slippage_bps =
+            ((self.out_amount - min_out_amount) as u128 * 10_000 / self.out_amount as u128) as u64;
+        self.min_out_amount = min_out_amount;
+        self.raw["slippageBps"] = json!(self.slippage_bps);
+        self.raw["otherAmountThreshold"] = json!(min_out_amount.to_string());
+        Ok(self)
+    }
+}
+
+fn client() -> Result<reqwest::blocking::Client> {
+    Ok(reqwest::blocking::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?)
+}
+
+/// `request` with the configured API key, if any
+fn authorized(request: reqwest::blocking::RequestBuilder) -> reqwest::blocking::RequestBuilder {
+    let api_key = &config::get().jupiter.api_key;
+    if api_key.is_empty() {
+        request
+    } else {
+        request.header("x-api-key", api_key)
+    }
+}
+
+fn endpoint(path: &str) -> String {
+    format!(
+        "{}/{}",
+        config::get().jupiter.api_url.trim_end_matches('/'),
+        path
+    )
+}
+
+/// Best route swapping `amount` of `input_mint` into `output_mint`
+pub fn quote(
+    input_mint: &Pubkey,
+    output_mint: &Pubkey,
+    amount: u64,
+    slippage_bps: u64,
+) -> Result<Quote> {
+    let request = client()?.get(endpoint("quote")).query(&[
+        ("inputMint", input_mint.to_string()),
+        ("outputMint", output_mint.to_string()),
+        ("amount", amount.to_string()),
+        ("slippageBps", slippage_bps.to_string()),
+    ]);
+    let raw: Value = authorized(request)
+        .send()
+        .map_err(|e| anyhow!("Jupiter quote request failed: {}", e))?
+        .error_for_status()
+        .map_err(|e| anyhow!("Jupiter has no route for {}: {}", input_mint, e))?
+        .json()?;
+    Quote::parse(raw)
+}
+
+/// Fetch the unsigned transaction executing `quote` for `user` from `/swap`, with the
+/// last block height its blockhash is valid for. Under a protected sell strategy the
+/// swap pays `jito.tip_lamports` as a tip instead of a priority fee.
+pub fn swap_transaction(quote: &Quote, user: &Pubkey) -> Result<(VersionedTransaction, u64)> {
+    let mut body = json!({
+        "quoteResponse": quote.raw,
+        "userPublicKey": user.to_string(),
+        "wrapAndUnwrapSol": true,
+        "dynamicComputeUnitLimit": true,
+    });
+    let config = config::get();
+    let price = config.fees.priority_fee_micro_lamports();
+    if config.trading.send_strategy_for(false) == SendStrategy::Protected {
+        // Protected sends are bundle-only, which the block engine drops without a tip
+        body["prioritizationFeeLamports"] = json!({ "jitoTipLamports": config.jito.tip_lamports });
+    } else if price > 0 {
+        body["computeUnitPriceMicroLamports"] = json!(price);
+    }
+
+    let response: Value = authorized(client()?.post(endpoint("swap")).json(&body))
+        .send()
+        .map_err(|e| anyhow!("Jupiter swap request failed: {}", e))?
+        .error_for_status()?
+        .json()?;
+    let encoded = response["swapTransaction"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Jupiter swap response has no swapTransaction"))?;
+    let last_valid_block_height = response["lastValidBlockHeight"]
+        .as_u64()
+        .ok_or_else(|| anyhow!("Jupiter swap response has no lastValidBlockHeight"))?;
+    let transaction = bincode::deserialize(&BASE64.decode(encoded)?)
+        .map_err(|e| anyhow!("Failed to decode Jupiter swap transaction: {}", e))?;
+    Ok((transaction, last_valid_block_height))
+}
+
+/// Sign a `/swap` transaction as `user`, which must be its fee payer and only signer
+pub fn sign(transaction: VersionedTransaction, user: &dyn Signer) -> Result<VersionedTransaction> {
+    let payer = transaction.message.static_account_keys().first().copied();
+    if payer != Some(user.pubkey()) {
+        return Err(anyhow!(
+            "Jupiter swap transaction is paid by {:?}, not {}",
+            payer,
+            user.pubkey()
+        ));
+    }
+    VersionedTransaction::try_new(transaction.message, &[user])
+        .map_err(|e| anyhow!("Failed to sign Jupiter swap transaction: {}", e))
+}
+
+/// Sell `token_amount` tokens of `mint` for SOL along Jupiter's best route (e.g. on
+/// Raydium), receiving at least `min_sol_output` lamports
+#[instrument(name = "jupiter_sell", skip_all, fields(%mint, token_amount, min_sol_output))]
+pub fn sell(
+    connection: &RpcClient,
+    user: &dyn Signer,
+    token_amount: u64,
+    mint: Pubkey,
+    min_sol_output: u64,
+) -> error::Result<()> {
+    info!("Starting Jupiter sell");
+
+    let quote = quote(&mint, &WSOL_MINT, token_amount, 0)?.with_min_out(min_sol_output)?;
+    debug!(
+        out_amount = quote.out_amount,
+        slippage_bps = quote.slippage_bps,
+        price_impact_pct = quote.price_impact_pct,
+        route = ?quote.route,
+        "Quoted Jupiter sell"
+    );
+
+    let (transaction, last_valid_block_height) = swap_transaction(&quote, &user.pubkey())?;
+    let transaction = sign(transaction, user)?;
+    // Route fees are taken out of the output and can't be separated from it
+    let fill = Fill::new(
+        mint,
+        Side::Sell,
+        token_amount,
+        quote.out_amount,
+        0,
+        String::new(),
+    );
+    sender::execute_signed_trade(
+        connection,
+        &transaction,
+        last_valid_block_height,
+        user,
+        fill,
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_with_min_out() {
+        let mint = Pubkey::new_unique();
+        let raw = json!({
+            "inputMint": mint.to_string(),
+            "inAmount": "1000000",
+            "outputMint": WSOL_MINT.to_string(),
+            "outAmount": "2000000",
+            "otherAmountThreshold": "1990000",
+            "slippageBps": 50,
+            "priceImpactPct": "0.012",
+            "routePlan": [{ "swapInfo": { "label": "Raydium" }, "percent": 100 }],
+        });
+        let quote = Quote::parse(raw).unwrap();
+        assert_eq!(quote.input_mint, mint);
+        assert_eq!(quote.out_amount, 2_000_000);
+        assert_eq!(quote.route, vec!["Raydium".to_string()]);
+
+        let limited = quote.clone().with_min_out(1_900_000).unwrap();
+        assert_eq!(limited.slippage_bps, 500);
+        assert!(limited.out_amount * (10_000 - limited.slippage_bps) / 10_000 >= 1_900_000);
+        assert_eq!(limited.raw["otherAmountThreshold"], "1900000");
+        // Rounding never lets the enforced minimum drop below the one asked for
+        let odd = quote.clone().with_min_out(1_999_999).unwrap();
+        assert!(odd.out_amount * (10_000 - odd.slippage_bps) / 10_000 >= 1_999_999);
+        assert!(matches!(
+            quote.with_min_out(2_000_001),
+            Err(BotError::SlippageExceeded(_))
+        ));
+    }
+}
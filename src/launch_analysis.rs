@@ -0,0 +1,287 @@
+use anyhow::Result;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::events::{self, PumpEvent};
+use crate::{cal, signatures, tx_fees};
+
+// Constants
+/// Most signatures `getSignaturesForAddress` returns at once
+const MAX_SIGNATURES: usize = 1_000;
+/// A wallet with at least this many transactions is an established one, not a fresh
+/// bundler wallet, and its funding isn't traced
+const FUNDING_LOOKBACK: usize = 50;
+/// Fewest wallets buying in one slot from one funding source that make a launch bundled
+const MIN_BUNDLED_WALLETS: usize = 2;
+/// How long to wait for the launch transaction to confirm before giving up
+const CONFIRM_ATTEMPTS: usize = 5;
+const CONFIRM_POLL_INTERVAL: Duration = Duration::from_millis(400);
+
+/// A buy among the first transactions on a bonding curve
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LaunchBuy {
+    pub signature: String,
+    pub slot: u64,
+    pub buyer: Pubkey,
+    /// Pays the transaction fee; someone other than the buyer when one wallet signs for many
+    pub fee_payer: Pubkey,
+    pub sol_amount: u64,
+}
+
+/// Wallets buying in the same slot with SOL from one source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FundingCluster {
+    pub slot: u64,
+    pub source: Pubkey,
+    pub wallets: Vec<Pubkey>,
+}
+
+/// Who bought a launch first and whether the buys look like one buyer's bundle
+#[derive(Debug, Clone, Default)]
+pub struct LaunchAnalysis {
+    pub mint: Pubkey,
+    pub buys: Vec<LaunchBuy>,
+    pub clusters: Vec<FundingCluster>,
+    /// Several wallets funded from a common source bought in the same slot
+    pub bundled_launch: bool,
+}
+
+impl LaunchAnalysis {
+    /// Find same-slot buys by wallets sharing a funding source. `funders` maps buyers to
+    /// the wallet that funded them; a source that bought in the slot itself counts too.
+    pub fn from_buys(
+        mint: Pubkey,
+        buys: Vec<LaunchBuy>,
+        funders: &HashMap<Pubkey, Pubkey>,
+    ) -> Self {
+        let mut by_slot: BTreeMap<u64, BTreeSet<Pubkey>> = BTreeMap::new();
+        for buy in &buys {
+            by_slot.entry(buy.slot).or_default().insert(buy.buyer);
+        }
+        let mut clusters = Vec::new();
+        for (slot, buyers) in by_slot {
+            let mut by_source: BTreeMap<Pubkey, BTreeSet<Pubkey>> = BTreeMap::new();
+            for buyer in &buyers {
+                let Some(source) = funders.get(buyer) else {
+                    continue;
+                };
+                let wallets = by_source.entry(*source).or_default();
+                wallets.insert(*buyer);
+                if buyers.contains(source) {
+                    wallets.insert(*source);
+                }
+            }
+            clusters.extend(
+                by_source
+                    .into_iter()
+                    .filter(|(_, wallets)| wallets.len() >= MIN_BUNDLED_WALLETS)
+                    .map(|(source, wallets)| FundingCluster {
+                        slot,
+                        source,
+                        wallets: wallets.into_iter().collect(),
+                    }),
+            );
+        }
+        Self {
+            mint,
+            buys,
+            bundled_launch: !clusters.is_empty(),
+            clusters,
+        }
+    }
+}
+
+/// The account that paid the most SOL in a transaction where `wallet` gained SOL
+fn funder_from_balances(
+    keys: &[Pubkey],
+    pre_balances: &[u64],
+    post_balances: &[u64],
+    wallet: &Pubkey,
+) -> Option<Pubkey> {
+    let index = keys.iter().position(|key| key == wallet)?;
+    if post_balances.get(index)? <= pre_balances.get(index)? {
+        return None;
+    }
+    keys.iter()
+        .zip(pre_balances.iter().zip(post_balances))
+        .filter(|(key, (pre, post))| *key != wallet && post < pre)
+        .max_by_key(|(_, (pre, post))| *pre - *post)
+        .map(|(key, _)| *key)
+}
+
+/// Who sent `wallet` its first SOL, None for wallets with too much history to trace
+pub fn funding_source(rpc: &RpcClient, wallet: &Pubkey) -> Result<Option<Pubkey>> {
+    let history = signatures::recent(rpc, wallet, FUNDING_LOOKBACK)?;
+    if history.len() >= FUNDING_LOOKBACK {
+        return Ok(None);
+    }
+    // Newest first, so the wallet's first transaction is last
+    let Some(first) = history.last() else {
+        return Ok(None);
+    };
+    let transaction = tx_fees::fetch_transaction(rpc, &Signature::from_str(&first.signature)?)?;
+    let Some(meta) = &transaction.transaction.meta else {
+        return Ok(None);
+    };
+    Ok(funder_from_balances(
+        &tx_fees::account_keys(&transaction),
+        &meta.pre_balances,
+        &meta.post_balances,
+        wallet,
+    ))
+}
+
+/// Buys of `mint` in the first `transactions` successful transactions on its bonding
+/// curve, waiting briefly for the launch itself to confirm
+fn fetch_launch_buys(
+    rpc: &RpcClient,
+    mint: &Pubkey,
+    transactions: usize,
+) -> Result<Vec<LaunchBuy>> {
+    let (bonding_curve, _) = cal::get_bonding_curve_pda(mint);
+    let mut history = Vec::new();
+    for attempt in 1..=CONFIRM_ATTEMPTS {
+        history = signatures::recent(rpc, &bonding_curve, MAX_SIGNATURES)?;
+        if !history.is_empty() || attempt == CONFIRM_ATTEMPTS {
+            break;
+        }
+        std::thread::sleep(CONFIRM_POLL_INTERVAL);
+    }
+
+    let mut buys = Vec::new();
+    for status in history
+        .iter()
+        .rev()
+        .filter(|s| s.err.is_none())
+        .take(transactions)
+    {
+        let signature = Signature::from_str(&status.signature)?;
+        let transaction = match tx_fees::fetch_transaction(rpc, &signature) {
+            Ok(transaction) => transaction,
+            Err(e) => {
+                warn!(%signature, error = %e, "Skipping launch transaction");
+                continue;
+            }
+        };
+        let Some(fee_payer) = tx_fees::account_keys(&transaction).first().copied() else {
+            continue;
+        };
+        for event in events::from_transaction(&transaction) {
+            if let PumpEvent::Trade(trade) = event {
+                if trade.is_buy && trade.mint == *mint {
+                    buys.push(LaunchBuy {
+                        signature: status.signature.clone(),
+                        slot: status.slot,
+                        buyer: trade.user,
+                        fee_payer,
+                        sol_amount: trade.sol_amount,
+                    });
+                }
+            }
+        }
+    }
+    Ok(buys)
+}
+
+/// Inspect the first `transactions` transactions on `mint`'s bonding curve for wallets
+/// buying in the same slot with SOL from a common source. A buyer whose fee was paid by
+/// another wallet counts as funded by it; otherwise its first SOL transfer is traced.
+pub fn analyze(rpc: &RpcClient, mint: &Pubkey, transactions: usize) -> Result<LaunchAnalysis> {
+    let buys = fetch_launch_buys(rpc, mint, transactions)?;
+
+    // Only buyers sharing a slot with another wallet can be part of a bundle
+    let mut slot_buyers: HashMap<u64, BTreeSet<Pubkey>> = HashMap::new();
+    for buy in &buys {
+        slot_buyers.entry(buy.slot).or_default().insert(buy.buyer);
+    }
+    let mut funders = HashMap::new();
+    for buy in &buys {
+        if slot_buyers[&buy.slot].len() < MIN_BUNDLED_WALLETS || funders.contains_key(&buy.buyer) {
+            continue;
+        }
+        if buy.fee_payer != buy.buyer {
+            funders.insert(buy.buyer, buy.fee_payer);
+            continue;
+        }
+        match funding_source(rpc, &buy.buyer) {
+            Ok(Some(source)) => {
+                funders.insert(buy.buyer, source);
+            }
+            Ok(None) => {}
+            Err(e) => warn!(wallet = %buy.buyer, error = %e, "Failed to trace funding"),
+        }
+    }
+
+    let analysis = LaunchAnalysis::from_buys(*mint, buys, &funders);
+    debug!(%mint, buys = analysis.buys.len(), clusters = ?analysis.clusters, "Analyzed launch");
+    if analysis.bundled_launch {
+        info!(%mint, clusters = analysis.clusters.len(), "Bundled launch detected");
+    }
+    Ok(analysis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_slot_buys_from_one_source_are_bundled() {
+        let (dev, bundler_a, bundler_b, sniper, funder) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let buy = |slot, buyer| LaunchBuy {
+            signature: String::new(),
+            slot,
+            buyer,
+            fee_payer: buyer,
+            sol_amount: 1_000_000_000,
+        };
+
+        // The dev funded two wallets that bought alongside the launch; a sniper with its
+        // own funding bought in the same slot
+        let buys = vec![
+            buy(100, dev),
+            buy(100, bundler_a),
+            buy(100, bundler_b),
+            buy(100, sniper),
+        ];
+        let funders = HashMap::from([(bundler_a, dev), (bundler_b, dev), (sniper, funder)]);
+        let analysis = LaunchAnalysis::from_buys(Pubkey::new_unique(), buys.clone(), &funders);
+        assert!(analysis.bundled_launch);
+        assert_eq!(analysis.clusters.len(), 1);
+        assert_eq!(analysis.clusters[0].source, dev);
+        let mut expected = vec![dev, bundler_a, bundler_b];
+        expected.sort();
+        assert_eq!(analysis.clusters[0].wallets, expected);
+
+        // The same wallets spread over different slots are not a bundle
+        let spread: Vec<LaunchBuy> = buys
+            .into_iter()
+            .enumerate()
+            .map(|(i, b)| LaunchBuy {
+                slot: 100 + i as u64,
+                ..b
+            })
+            .collect();
+        assert!(!LaunchAnalysis::from_buys(Pubkey::new_unique(), spread, &funders).bundled_launch);
+
+        // SOL moved from the funder to the fresh wallet, plus the fee
+        let keys = [funder, bundler_a, Pubkey::new_unique()];
+        assert_eq!(
+            funder_from_balances(&keys, &[10_000, 0, 1], &[4_995, 5_000, 1], &bundler_a),
+            Some(funder)
+        );
+        assert_eq!(
+            funder_from_balances(&keys, &[10_000, 5_000, 1], &[10_000, 4_000, 1], &bundler_a),
+            None
+        );
+    }
+}
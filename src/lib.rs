@@ -0,0 +1,77 @@
+//! pump.fun / PumpSwap trading library.
+//!
+//! [`PumpFunClient`] wraps an RPC connection and a wallet for programmatic use;
+//! the modules below expose the lower-level building blocks.
+
+pub mod accounts;
+pub mod address_book;
+pub mod alt;
+pub mod amount;
+pub mod api;
+pub mod archiver;
+pub mod backtest;
+pub mod balance;
+pub mod blacklist;
+pub mod blockhash_cache;
+pub mod cal;
+pub mod client;
+pub mod config;
+pub mod copytrade;
+pub mod creator_exit;
+pub mod creators;
+pub mod curve_cache;
+pub mod curve_diff;
+pub mod error;
+pub mod events;
+pub mod executor;
+pub mod exit_arm;
+pub mod fees;
+pub mod fills;
+pub mod geyser;
+pub mod holders;
+pub mod indicators;
+pub mod inspect;
+pub mod jito;
+pub mod journal;
+pub mod jupiter;
+pub mod launch_analysis;
+pub mod limit_orders;
+pub mod localnet;
+pub mod logging;
+pub mod metadata;
+pub mod migrations;
+pub mod nonce;
+pub mod orders;
+pub mod pnl;
+pub mod portfolio;
+pub mod positions;
+pub mod price_feed;
+pub mod pump_buy;
+pub mod pump_claim;
+pub mod pump_create;
+pub mod pump_idl;
+pub mod pump_sell;
+pub mod pumpswap;
+pub mod rate_limit;
+pub mod recovery;
+pub mod router;
+pub mod rpc_pool;
+pub mod safety;
+pub mod scanner;
+pub mod scheduler;
+pub mod selftest;
+pub mod sender;
+pub mod signatures;
+pub mod signer;
+pub mod sizing;
+pub mod snipe;
+pub mod strategy;
+pub mod telegram;
+pub mod token_accounts;
+pub mod tp_sl;
+pub mod tx_fees;
+pub mod wallets;
+pub mod watchlist;
+
+pub use client::PumpFunClient;
+pub use error::BotError;
@@ -0,0 +1,880 @@
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection};
+use serde::Deserialize;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, instrument, warn};
+
+use crate::accounts::BondingCurve;
+use crate::config::ExecutionMode;
+use crate::executor::{self, IdempotencyKey, TradeIntent};
+use crate::portfolio::{now_unix, Side};
+use crate::{cal, config, fees, pump_buy, rpc_pool, telegram};
+
+// Constants
+/// How often `run` looks for mints with newly placed orders
+const RESCAN_INTERVAL: Duration = Duration::from_secs(5);
+/// Limit price of a DCA leg that buys at any price
+pub const NO_PRICE_LIMIT: f64 = f64::MAX;
+
+/// Where a limit order is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderStatus {
+    Open,
+    /// Condition met and handed to the executor
+    Triggered,
+    Filled,
+    /// Condition met in paper or simulate mode: the fill was simulated and nothing was sent
+    Simulated,
+    Failed,
+    Cancelled,
+}
+
+impl OrderStatus {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            OrderStatus::Open => "open",
+            OrderStatus::Triggered => "triggered",
+            OrderStatus::Filled => "filled",
+            OrderStatus::Simulated => "simulated",
+            OrderStatus::Failed => "failed",
+            OrderStatus::Cancelled => "cancelled",
+        }
+    }
+
+    fn parse(s: &str) -> Result<Self> {
+        match s {
+            "open" => Ok(OrderStatus::Open),
+            "triggered" => Ok(OrderStatus::Triggered),
+            "filled" => Ok(OrderStatus::Filled),
+            "simulated" => Ok(OrderStatus::Simulated),
+            "failed" => Ok(OrderStatus::Failed),
+            "cancelled" => Ok(OrderStatus::Cancelled),
+            other => Err(anyhow!("Unknown limit order status: {}", other)),
+        }
+    }
+}
+
+/// Which way the price has to cross `limit_price` for an order to fire
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderKind {
+    /// Buys at or below the limit, sells at or above it
+    Limit,
+    /// Buys at or above the trigger, sells at or below it (stop-loss)
+    Stop,
+    /// One scheduled buy of a DCA plan: fires once `not_before` has passed, at or below
+    /// the limit
+    Dca,
+    /// A take-profit tranche of a position's exit ladder, sold at or above the limit by
+    /// the exit engine rather than by `run`. Recorded here so it can be listed and cancelled.
+    Ladder,
+}
+
+impl OrderKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            OrderKind::Limit => "limit",
+            OrderKind::Stop => "stop",
+            OrderKind::Dca => "dca",
+            OrderKind::Ladder => "ladder",
+        }
+    }
+
+    fn parse(s: &str) -> Result<Self> {
+        match s {
+            "limit" => Ok(OrderKind::Limit),
+            "stop" => Ok(OrderKind::Stop),
+            "dca" => Ok(OrderKind::Dca),
+            "ladder" => Ok(OrderKind::Ladder),
+            other => Err(anyhow!("Unknown limit order kind: {}", other)),
+        }
+    }
+}
+
+/// Staged buys on dips after an entry: `legs` limit buys, the n-th `step_pct` × n percent
+/// below the entry price, sharing `budget_lamports` equally
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EntryLadder {
+    /// Most the legs may spend together, slippage included
+    pub budget_lamports: u64,
+    pub legs: u32,
+    /// Percent below the entry price between one leg and the next
+    pub step_pct: f64,
+}
+
+impl EntryLadder {
+    pub fn validate(&self) -> Result<()> {
+        if self.budget_lamports == 0 || self.legs == 0 {
+            return Err(anyhow!(
+                "An entry ladder needs a budget and at least one leg"
+            ));
+        }
+        if !(self.step_pct > 0.0 && self.step_pct * (self.legs as f64) < 100.0) {
+            return Err(anyhow!(
+                "Entry ladder steps of {}% put {} legs at or below zero",
+                self.step_pct,
+                self.legs
+            ));
+        }
+        Ok(())
+    }
+
+    /// (limit price, lamports) of each leg below `entry_price`, highest first. Legs are
+    /// sized so that every one filling at its slippage-padded cost still fits the budget.
+    pub fn legs(&self, entry_price: f64, slippage_bps: u64) -> Vec<(f64, u64)> {
+        let per_leg = self.budget_lamports / self.legs as u64;
+        let lamports = (per_leg as u128 * 10_000 / (10_000 + slippage_bps as u128)) as u64;
+        (1..=self.legs)
+            .map(|n| {
+                let price = entry_price * (1.0 - self.step_pct * n as f64 / 100.0);
+                (price, lamports)
+            })
+            .collect()
+    }
+}
+
+/// Buy `amount` lamports worth once the price drops to `limit_price`, or sell
+/// `amount` tokens once it rises to `limit_price`. Stop orders fire on the opposite cross.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LimitOrder {
+    pub id: i64,
+    pub mint: Pubkey,
+    pub side: Side,
+    pub kind: OrderKind,
+    /// Lamports to spend for buys, tokens to sell for sells
+    pub amount: u64,
+    /// Spot price in lamports per token, as `cal::spot_price_lamports_per_token`
+    pub limit_price: f64,
+    pub slippage_bps: u64,
+    pub status: OrderStatus,
+    pub created_at: i64,
+    pub triggered_at: Option<i64>,
+    /// Why the order failed, if it did
+    pub error: Option<String>,
+    /// The other half of a one-cancels-other pair, cancelled when this order fires
+    pub linked_id: Option<i64>,
+    /// Earliest unix time the order may fire (DCA legs)
+    pub not_before: Option<i64>,
+}
+
+impl LimitOrder {
+    /// Whether the order should fire at spot `price`
+    pub fn is_triggered(&self, price: f64) -> bool {
+        match (self.side, self.kind) {
+            (Side::Buy, OrderKind::Limit | OrderKind::Dca) | (Side::Sell, OrderKind::Stop) => {
+                price <= self.limit_price
+            }
+            (Side::Sell, OrderKind::Limit | OrderKind::Dca | OrderKind::Ladder)
+            | (Side::Buy, OrderKind::Stop) => price >= self.limit_price,
+            // Ladders only ever sell
+            (Side::Buy, OrderKind::Ladder) => false,
+        }
+    }
+
+    /// Whether the order's time has come at unix time `now`
+    pub fn is_due(&self, now: i64) -> bool {
+        self.not_before.is_none_or(|not_before| now >= not_before)
+    }
+
+    /// Trade to execute against `curve`, with `slippage_bps` applied to the quote
+    pub fn intent(&self, global: &cal::Global, curve: &BondingCurve) -> TradeIntent {
+        match self.side {
+            Side::Buy => TradeIntent::Buy {
+                mint: self.mint,
+                token_amount: cal::get_tokens_for_sol(global, Some(curve), self.amount),
+                max_sol_cost: pump_buy::with_slippage(self.amount, self.slippage_bps),
+            },
+            Side::Sell => {
+                let expected_sol = cal::get_sol_from_tokens(global, Some(curve), self.amount);
+                TradeIntent::Sell {
+                    mint: self.mint,
+                    token_amount: self.amount,
+                    min_sol_output: (expected_sol as u128
+                        * 10_000u128.saturating_sub(self.slippage_bps as u128)
+                        / 10_000) as u64,
+                }
+            }
+        }
+    }
+}
+
+/// Limit orders persisted in SQLite so they survive restarts
+pub struct LimitOrderBook {
+    conn: Connection,
+}
+
+impl LimitOrderBook {
+    /// Open (or create) the limit order table in the database at `path`
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)
+            .map_err(|e| anyhow!("Failed to open limit order database {}: {}", path, e))?;
+        Self::init(conn)
+    }
+
+    /// Open the order book in the portfolio database
+    pub fn open_default() -> Result<Self> {
+        Self::open(&config::get().storage.db_path)
+    }
+
+    /// Open a throwaway in-memory order book
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS limit_orders (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                mint TEXT NOT NULL,
+                side TEXT NOT NULL,
+                amount INTEGER NOT NULL,
+                limit_price REAL NOT NULL,
+                slippage_bps INTEGER NOT NULL,
+                status TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                triggered_at INTEGER,
+                error TEXT,
+                kind TEXT NOT NULL DEFAULT 'limit',
+                linked_id INTEGER,
+                not_before INTEGER
+            );
+            CREATE INDEX IF NOT EXISTS idx_limit_orders_status ON limit_orders (status, mint);",
+        )?;
+        // Order books created before stop and linked orders lack their columns
+        if conn.prepare("SELECT kind FROM limit_orders").is_err() {
+            conn.execute_batch(
+                "ALTER TABLE limit_orders ADD COLUMN kind TEXT NOT NULL DEFAULT 'limit';
+                 ALTER TABLE limit_orders ADD COLUMN linked_id INTEGER;",
+            )?;
+        }
+        if conn.prepare("SELECT not_before FROM limit_orders").is_err() {
+            conn.execute_batch("ALTER TABLE limit_orders ADD COLUMN not_before INTEGER;")?;
+        }
+        Ok(Self { conn })
+    }
+
+    /// Register a new open limit order
+    pub fn place(
+        &self,
+        mint: Pubkey,
+        side: Side,
+        amount: u64,
+        limit_price: f64,
+        slippage_bps: u64,
+    ) -> Result<LimitOrder> {
+        self.place_order(
+            mint,
+            side,
+            OrderKind::Limit,
+            amount,
+            limit_price,
+            slippage_bps,
+        )
+    }
+
+    /// Register a new open order of `kind`
+    pub fn place_order(
+        &self,
+        mint: Pubkey,
+        side: Side,
+        kind: OrderKind,
+        amount: u64,
+        limit_price: f64,
+        slippage_bps: u64,
+    ) -> Result<LimitOrder> {
+        let id = self.insert(mint, side, kind, amount, limit_price, slippage_bps, None)?;
+        self.get(id)?
+            .ok_or_else(|| anyhow!("Limit order vanished after insert"))
+    }
+
+    /// Protect `token_amount` tokens of `mint` with a take-profit sell at
+    /// `take_profit_price` and a stop-loss sell at `stop_loss_price`, either of which may
+    /// be left out. Both orders are written together and cancel each other when one fires.
+    pub fn place_bracket(
+        &self,
+        mint: Pubkey,
+        token_amount: u64,
+        take_profit_price: Option<f64>,
+        stop_loss_price: Option<f64>,
+        slippage_bps: u64,
+    ) -> Result<Vec<LimitOrder>> {
+        let legs: Vec<(OrderKind, f64)> = [
+            take_profit_price.map(|price| (OrderKind::Limit, price)),
+            stop_loss_price.map(|price| (OrderKind::Stop, price)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        // Dropped without a commit on any error, so a bracket is never left half placed
+        let transaction = self.conn.unchecked_transaction()?;
+        let mut ids = Vec::new();
+        for (kind, price) in legs {
+            ids.push(self.insert(
+                mint,
+                Side::Sell,
+                kind,
+                token_amount,
+                price,
+                slippage_bps,
+                None,
+            )?);
+        }
+        if let [take_profit, stop_loss] = ids[..] {
+            self.conn.execute(
+                "UPDATE limit_orders SET linked_id = CASE id WHEN ?1 THEN ?2 ELSE ?1 END
+                 WHERE id IN (?1, ?2)",
+                params![take_profit, stop_loss],
+            )?;
+        }
+        transaction.commit()?;
+
+        ids.into_iter()
+            .map(|id| {
+                self.get(id)?
+                    .ok_or_else(|| anyhow!("Limit order vanished after insert"))
+            })
+            .collect()
+    }
+
+    /// Split `total_lamports` into `legs` DCA buys of `mint`, the first due now and each
+    /// next one `interval_secs` later. Legs only fire at or below `max_price`
+    /// (`NO_PRICE_LIMIT` for any price); one that is due but priced out waits for the price.
+    pub fn place_dca(
+        &self,
+        mint: Pubkey,
+        total_lamports: u64,
+        legs: u32,
+        interval_secs: i64,
+        max_price: f64,
+        slippage_bps: u64,
+    ) -> Result<Vec<LimitOrder>> {
+        if legs == 0 || interval_secs < 0 {
+            return Err(anyhow!(
+                "A DCA plan needs at least one leg and a non-negative interval"
+            ));
+        }
+        let leg_lamports = total_lamports / legs as u64;
+        if leg_lamports == 0 {
+            return Err(anyhow!(
+                "A DCA plan of {} lamports can't be split into {} legs",
+                total_lamports,
+                legs
+            ));
+        }
+        // Legs are equal; the first takes the remainder of the split
+        let remainder = total_lamports % legs as u64;
+        let start = now_unix();
+
+        let transaction = self.conn.unchecked_transaction()?;
+        let mut ids = Vec::new();
+        for leg in 0..legs {
+            ids.push(self.insert(
+                mint,
+                Side::Buy,
+                OrderKind::Dca,
+                leg_lamports + if leg == 0 { remainder } else { 0 },
+                max_price,
+                slippage_bps,
+                Some(start + leg as i64 * interval_secs),
+            )?);
+        }
+        transaction.commit()?;
+
+        ids.into_iter()
+            .map(|id| {
+                self.get(id)?
+                    .ok_or_else(|| anyhow!("Limit order vanished after insert"))
+            })
+            .collect()
+    }
+
+    /// Place `ladder`'s limit buys of `mint` below `entry_price`, all or none
+    pub fn place_entry_ladder(
+        &self,
+        mint: Pubkey,
+        ladder: &EntryLadder,
+        entry_price: f64,
+        slippage_bps: u64,
+    ) -> Result<Vec<LimitOrder>> {
+        ladder.validate()?;
+        let transaction = self.conn.unchecked_transaction()?;
+        let mut ids = Vec::new();
+        for (price, lamports) in ladder.legs(entry_price, slippage_bps) {
+            ids.push(self.insert(
+                mint,
+                Side::Buy,
+                OrderKind::Limit,
+                lamports,
+                price,
+                slippage_bps,
+                None,
+            )?);
+        }
+        transaction.commit()?;
+
+        ids.into_iter()
+            .map(|id| {
+                self.get(id)?
+                    .ok_or_else(|| anyhow!("Limit order vanished after insert"))
+            })
+            .collect()
+    }
+
+    /// Cancel open order `id` and place a copy of it with a new `amount` and/or
+    /// `limit_price` in one step, moving any one-cancels-other link to the copy. Returns
+    /// the new order, or None if `id` wasn't open.
+    pub fn replace(
+        &self,
+        id: i64,
+        amount: Option<u64>,
+        limit_price: Option<f64>,
+    ) -> Result<Option<LimitOrder>> {
+        let transaction = self.conn.unchecked_transaction()?;
+        let Some(order) = self.get(id)?.filter(|o| o.status == OrderStatus::Open) else {
+            return Ok(None);
+        };
+        if !self.cancel(id)? {
+            return Ok(None);
+        }
+        let replacement = self.insert(
+            order.mint,
+            order.side,
+            order.kind,
+            amount.unwrap_or(order.amount),
+            limit_price.unwrap_or(order.limit_price),
+            order.slippage_bps,
+            order.not_before,
+        )?;
+        if let Some(linked_id) = order.linked_id {
+            self.conn.execute(
+                "UPDATE limit_orders SET linked_id = CASE id WHEN ?1 THEN ?2 ELSE ?1 END
+                 WHERE id IN (?1, ?2)",
+                params![replacement, linked_id],
+            )?;
+        }
+        transaction.commit()?;
+        self.get(replacement)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn insert(
+        &self,
+        mint: Pubkey,
+        side: Side,
+        kind: OrderKind,
+        amount: u64,
+        limit_price: f64,
+        slippage_bps: u64,
+        not_before: Option<i64>,
+    ) -> Result<i64> {
+        if amount == 0 {
+            return Err(anyhow!("Limit order amount must be positive"));
+        }
+        if !(limit_price > 0.0 && limit_price.is_finite()) {
+            return Err(anyhow!("Invalid limit price {}", limit_price));
+        }
+        self.conn.execute(
+            "INSERT INTO limit_orders
+                (mint, side, kind, amount, limit_price, slippage_bps, status, created_at,
+                 not_before)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                mint.to_string(),
+                side.as_str(),
+                kind.as_str(),
+                amount as i64,
+                limit_price,
+                slippage_bps as i64,
+                OrderStatus::Open.as_str(),
+                now_unix(),
+                not_before,
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn get(&self, id: i64) -> Result<Option<LimitOrder>> {
+        Ok(self
+            .query_orders("WHERE id = ?1", params![id])?
+            .into_iter()
+            .next())
+    }
+
+    /// Open orders, optionally only those on `mint`, oldest first
+    pub fn open_orders(&self, mint: Option<&Pubkey>) -> Result<Vec<LimitOrder>> {
+        self.query_orders(
+            "WHERE status = 'open' AND (?1 IS NULL OR mint = ?1)",
+            params![mint.map(|m| m.to_string())],
+        )
+    }
+
+    /// Every order, oldest first
+    pub fn all_orders(&self) -> Result<Vec<LimitOrder>> {
+        self.query_orders("", params![])
+    }
+
+    /// Open orders on `mint` that `run` executes: all but ladder tranches
+    fn executable_orders(&self, mint: &Pubkey) -> Result<Vec<LimitOrder>> {
+        Ok(self
+            .open_orders(Some(mint))?
+            .into_iter()
+            .filter(|o| o.kind != OrderKind::Ladder)
+            .collect())
+    }
+
+    /// Mints with at least one open order that `run` executes
+    pub fn open_mints(&self) -> Result<HashSet<Pubkey>> {
+        Ok(self
+            .open_orders(None)?
+            .iter()
+            .filter(|o| o.kind != OrderKind::Ladder)
+            .map(|o| o.mint)
+            .collect())
+    }
+
+    /// Mints with an open order whose `not_before` has passed by `now`
+    pub fn due_mints(&self, now: i64) -> Result<HashSet<Pubkey>> {
+        Ok(self
+            .query_orders("WHERE status = 'open' AND not_before <= ?1", params![now])?
+            .iter()
+            .map(|o| o.mint)
+            .collect())
+    }
+
+    /// Cancel an open order. Returns false if it wasn't open.
+    pub fn cancel(&self, id: i64) -> Result<bool> {
+        let changed = self.conn.execute(
+            "UPDATE limit_orders SET status = 'cancelled' WHERE id = ?1 AND status = 'open'",
+            params![id],
+        )?;
+        Ok(changed == 1)
+    }
+
+    /// Move an open order to `Triggered` and cancel the order linked to it. Returns false
+    /// if something else got there first, so an order is never handed to the executor twice.
+    pub fn claim(&self, id: i64) -> Result<bool> {
+        let changed = self.conn.execute(
+            "UPDATE limit_orders SET status = 'triggered', triggered_at = ?2
+             WHERE id = ?1 AND status = 'open'",
+            params![id, now_unix()],
+        )?;
+        if changed == 1 {
+            self.conn.execute(
+                "UPDATE limit_orders SET status = 'cancelled'
+                 WHERE status = 'open' AND id = (SELECT linked_id FROM limit_orders WHERE id = ?1)",
+                params![id],
+            )?;
+        }
+        Ok(changed == 1)
+    }
+
+    /// Record how a triggered order ended. A success outside live `mode` is recorded
+    /// as `Simulated`, since no transaction was sent.
+    pub fn finish(
+        &self,
+        id: i64,
+        result: std::result::Result<(), String>,
+        mode: ExecutionMode,
+    ) -> Result<()> {
+        let (status, error) = match result {
+            Ok(()) if mode == ExecutionMode::Live => (OrderStatus::Filled, None),
+            Ok(()) => (OrderStatus::Simulated, None),
+            Err(e) => (OrderStatus::Failed, Some(e)),
+        };
+        self.conn.execute(
+            "UPDATE limit_orders SET status = ?2, error = ?3 WHERE id = ?1",
+            params![id, status.as_str(), error],
+        )?;
+        Ok(())
+    }
+
+    fn query_orders(&self, filter: &str, params: impl rusqlite::Params) -> Result<Vec<LimitOrder>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, mint, side, amount, limit_price, slippage_bps, status, created_at,
+                    triggered_at, error, kind, linked_id, not_before
+             FROM limit_orders {} ORDER BY id",
+            filter
+        ))?;
+        let rows = stmt
+            .query_map(params, |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, f64>(4)?,
+                    row.get::<_, i64>(5)?,
+                    row.get::<_, String>(6)?,
+                    row.get::<_, i64>(7)?,
+                    row.get::<_, Option<i64>>(8)?,
+                    row.get::<_, Option<String>>(9)?,
+                    row.get::<_, String>(10)?,
+                    row.get::<_, Option<i64>>(11)?,
+                    row.get::<_, Option<i64>>(12)?,
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(
+                |(
+                    id,
+                    mint,
+                    side,
+                    amount,
+                    limit_price,
+                    slippage_bps,
+                    status,
+                    created_at,
+                    triggered_at,
+                    error,
+                    kind,
+                    linked_id,
+                    not_before,
+                )| {
+                    Ok(LimitOrder {
+                        id,
+                        mint: Pubkey::from_str(&mint)?,
+                        side: Side::parse(&side)?,
+                        kind: OrderKind::parse(&kind)?,
+                        amount: amount as u64,
+                        limit_price,
+                        slippage_bps: slippage_bps as u64,
+                        status: OrderStatus::parse(&status)?,
+                        created_at,
+                        triggered_at,
+                        error,
+                        linked_id,
+                        not_before,
+                    })
+                },
+            )
+            .collect()
+    }
+}
+
+/// Fire every open order on `curve`'s mint whose price condition holds
+fn check_orders(
+    book: &LimitOrderBook,
+    rpc: &RpcClient,
+    mint: &Pubkey,
+    curve: &BondingCurve,
+) -> Result<()> {
+    let mode = config::get().trading.mode;
+    if curve.complete {
+        for order in book.executable_orders(mint)? {
+            if book.claim(order.id)? {
+                book.finish(order.id, Err("Bonding curve complete".to_string()), mode)?;
+            }
+        }
+        return Ok(());
+    }
+
+    let price = cal::spot_price_lamports_per_token(curve);
+    let global = fees::current_global(rpc, curve);
+    let filled = match mode {
+        ExecutionMode::Live => "filled",
+        _ => "simulated a fill",
+    };
+    let now = now_unix();
+    for order in book.executable_orders(mint)? {
+        if !order.is_due(now) || !order.is_triggered(price) || !book.claim(order.id)? {
+            continue;
+        }
+        info!(
+            order_id = order.id,
+            side = ?order.side,
+            amount = order.amount,
+            limit_price = order.limit_price,
+            price,
+            kind = ?order.kind,
+            "Limit order triggered"
+        );
+        let key = IdempotencyKey::new(format!("limit-{}", order.id));
+        let result = executor::global()
+            .execute_keyed(key, order.intent(&global, curve))
+            .map_err(|e| e.to_string());
+        telegram::notify(match &result {
+            Ok(()) => format!(
+                "Limit {:?} #{} on {} {} at {:.9} lamports/token",
+                order.side, order.id, mint, filled, price
+            ),
+            Err(e) => format!(
+                "Limit {:?} #{} on {} failed: {}",
+                order.side, order.id, mint, e
+            ),
+        });
+        book.finish(order.id, result, mode)?;
+    }
+    Ok(())
+}
+
+/// Check `mint`'s open orders on every curve update until none are left or the socket closes
+#[instrument(name = "limit_orders", skip_all, fields(%mint))]
+fn watch_mint(mint: Pubkey) -> Result<()> {
+    let book = LimitOrderBook::open_default()?;
+    let rpc = rpc_pool::get_client();
+    let stream = cal::subscribe_bonding_curve(&mint)?;
+    // Catch orders that are already in range before the first update
+    check_orders(&book, &rpc, &mint, &cal::fetch_bonding_curve(&rpc, &mint)?)?;
+
+    for curve in stream {
+        if book.executable_orders(&mint)?.is_empty() {
+            break;
+        }
+        check_orders(&book, &rpc, &mint, &curve)?;
+    }
+    Ok(())
+}
+
+/// Watch every mint with open orders, picking up new orders as they are placed. Never returns.
+pub fn run() -> Result<()> {
+    let book = LimitOrderBook::open_default()?;
+    let rpc = rpc_pool::get_client();
+    let watching: Arc<Mutex<HashSet<Pubkey>>> = Arc::new(Mutex::new(HashSet::new()));
+    loop {
+        // Orders that fall due aren't waiting on a price move, so they are checked here
+        // too in case their curve is quiet. Claiming keeps each from firing twice.
+        for mint in book.due_mints(now_unix())? {
+            if let Err(e) = cal::fetch_bonding_curve(&rpc, &mint)
+                .map_err(Into::into)
+                .and_then(|curve| check_orders(&book, &rpc, &mint, &curve))
+            {
+                warn!(%mint, error = %e, "Failed to check due orders");
+            }
+        }
+        for mint in book.open_mints()? {
+            let Ok(mut active) = watching.lock() else {
+                continue;
+            };
+            if !active.insert(mint) {
+                continue;
+            }
+            let watching = watching.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = watch_mint(mint) {
+                    warn!(%mint, error = %e, "Limit order watcher stopped");
+                }
+                if let Ok(mut active) = watching.lock() {
+                    active.remove(&mint);
+                }
+            });
+        }
+        std::thread::sleep(RESCAN_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orders_persist_and_trigger_once() {
+        let book = LimitOrderBook::open_in_memory().unwrap();
+        let mint = Pubkey::new_unique();
+        let buy = book.place(mint, Side::Buy, 100_000_000, 0.03, 500).unwrap();
+        let sell = book.place(mint, Side::Sell, 1_000_000, 0.05, 500).unwrap();
+        assert!(book.place(mint, Side::Sell, 0, 0.05, 500).is_err());
+
+        assert!(buy.is_triggered(0.029) && !buy.is_triggered(0.031));
+        assert!(sell.is_triggered(0.05) && !sell.is_triggered(0.049));
+        assert_eq!(book.open_mints().unwrap(), HashSet::from([mint]));
+
+        assert!(book.claim(buy.id).unwrap());
+        assert!(!book.claim(buy.id).unwrap());
+        book.finish(buy.id, Ok(()), ExecutionMode::Live).unwrap();
+        assert!(book.cancel(sell.id).unwrap());
+
+        let orders = book.all_orders().unwrap();
+        assert_eq!(orders[0].status, OrderStatus::Filled);
+        assert!(orders[0].triggered_at.is_some());
+        assert_eq!(orders[1].status, OrderStatus::Cancelled);
+        assert!(book.open_orders(None).unwrap().is_empty());
+
+        // Nothing is sent in paper mode, so the fill is recorded as simulated
+        let paper = book.place(mint, Side::Buy, 100_000_000, 0.03, 500).unwrap();
+        assert!(book.claim(paper.id).unwrap());
+        book.finish(paper.id, Ok(()), ExecutionMode::Paper).unwrap();
+        assert_eq!(
+            book.get(paper.id).unwrap().unwrap().status,
+            OrderStatus::Simulated
+        );
+
+        let curve = cal::new_bonding_curve(&cal::Global::default());
+        let TradeIntent::Buy { max_sol_cost, .. } = buy.intent(&cal::Global::default(), &curve)
+        else {
+            panic!("buy order should produce a buy intent");
+        };
+        assert_eq!(max_sol_cost, 105_000_000);
+    }
+
+    #[test]
+    fn test_entry_ladder_stays_within_budget() {
+        let book = LimitOrderBook::open_in_memory().unwrap();
+        let mint = Pubkey::new_unique();
+        let ladder = EntryLadder {
+            budget_lamports: 1_000_000_000,
+            legs: 3,
+            step_pct: 10.0,
+        };
+        let legs = book.place_entry_ladder(mint, &ladder, 1.0, 500).unwrap();
+        let prices: Vec<f64> = legs.iter().map(|leg| leg.limit_price).collect();
+        assert!((prices[0] - 0.9).abs() < 1e-12 && (prices[2] - 0.7).abs() < 1e-12);
+        assert!(legs
+            .iter()
+            .all(|leg| leg.side == Side::Buy && leg.is_triggered(0.69)));
+        assert!(!legs[0].is_triggered(0.95));
+
+        // Even with every leg filling at its worst cost
+        let curve = cal::new_bonding_curve(&cal::Global::default());
+        let worst: u64 = legs
+            .iter()
+            .map(|leg| match leg.intent(&cal::Global::default(), &curve) {
+                TradeIntent::Buy { max_sol_cost, .. } => max_sol_cost,
+                _ => panic!("ladder legs are buys"),
+            })
+            .sum();
+        assert!(worst <= ladder.budget_lamports);
+
+        let too_deep = EntryLadder {
+            step_pct: 25.0,
+            legs: 4,
+            ..ladder
+        };
+        assert!(book.place_entry_ladder(mint, &too_deep, 1.0, 500).is_err());
+        assert_eq!(book.open_orders(Some(&mint)).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_bracket_legs_cancel_each_other() {
+        let book = LimitOrderBook::open_in_memory().unwrap();
+        let mint = Pubkey::new_unique();
+        let bracket = book
+            .place_bracket(mint, 1_000_000, Some(0.05), Some(0.02), 500)
+            .unwrap();
+        let [take_profit, stop_loss] = &bracket[..] else {
+            panic!("bracket should have two legs");
+        };
+        assert_eq!(take_profit.linked_id, Some(stop_loss.id));
+        assert_eq!(stop_loss.linked_id, Some(take_profit.id));
+        assert!(stop_loss.is_triggered(0.019) && !stop_loss.is_triggered(0.021));
+        assert!(take_profit.is_triggered(0.05) && !take_profit.is_triggered(0.049));
+
+        assert!(book.claim(stop_loss.id).unwrap());
+        let take_profit = book.get(take_profit.id).unwrap().unwrap();
+        assert_eq!(take_profit.status, OrderStatus::Cancelled);
+        assert!(book.open_orders(Some(&mint)).unwrap().is_empty());
+
+        // A failed leg leaves nothing behind
+        assert!(book
+            .place_bracket(mint, 1_000_000, Some(0.05), Some(-1.0), 500)
+            .is_err());
+        assert_eq!(book.all_orders().unwrap().len(), 2);
+    }
+}
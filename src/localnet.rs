@@ -0,0 +1,135 @@
+use anyhow::{anyhow, Result};
+use solana_account_decoder::{encode_ui_account, UiAccountEncoding};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    pubkey::Pubkey,
+};
+use std::path::Path;
+use std::str::FromStr;
+use tracing::info;
+
+use crate::config;
+use crate::metadata::METAPLEX_PROGRAM_ID;
+use crate::pump_idl::{
+    get_global_volume_accumulator_pda, FEE_CONFIG, FEE_PROGRAM, GLOBAL_ADDRESS, PUMP_PROGRAM_ID,
+};
+
+/// Programs a launch, buy and sell on the pump.fun curve run through
+fn fixture_programs() -> [Pubkey; 3] {
+    [*PUMP_PROGRAM_ID, *FEE_PROGRAM, *METAPLEX_PROGRAM_ID]
+}
+
+/// Accounts those programs read that a fresh validator doesn't have
+fn fixture_accounts() -> [Pubkey; 3] {
+    [
+        *GLOBAL_ADDRESS,
+        *FEE_CONFIG,
+        get_global_volume_accumulator_pda().0,
+    ]
+}
+
+/// The ELF of the upgradeable program at `program_id`, out of its program data account
+fn fetch_program_elf(rpc: &RpcClient, program_id: &Pubkey) -> Result<Vec<u8>> {
+    let program = rpc.get_account(program_id)?;
+    if program.owner != bpf_loader_upgradeable::ID {
+        return Err(anyhow!("{} is not an upgradeable program", program_id));
+    }
+    let UpgradeableLoaderState::Program {
+        programdata_address,
+    } = bincode::deserialize(&program.data)?
+    else {
+        return Err(anyhow!("{} is not a program account", program_id));
+    };
+    let data = rpc.get_account_data(&programdata_address)?;
+    let offset = UpgradeableLoaderState::size_of_programdata_metadata();
+    data.get(offset..)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| anyhow!("Program data of {} is truncated", program_id))
+}
+
+/// Write the programs and accounts a pump.fun launch, buy and sell need into `dir`, as
+/// `rpc` (normally mainnet) has them now, in the layout `test_validator_args` reads
+pub fn clone_fixtures(rpc: &RpcClient, dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    for program_id in fixture_programs() {
+        let elf = fetch_program_elf(rpc, &program_id)?;
+        std::fs::write(dir.join(format!("{}.so", program_id)), &elf)?;
+        info!(%program_id, bytes = elf.len(), "Cloned program");
+    }
+    for address in fixture_accounts() {
+        let account = rpc.get_account(&address)?;
+        let ui = encode_ui_account(&address, &account, UiAccountEncoding::Base64, None, None);
+        let json = serde_json::json!({ "pubkey": address.to_string(), "account": ui });
+        std::fs::write(
+            dir.join(format!("{}.json", address)),
+            serde_json::to_vec_pretty(&json)?,
+        )?;
+        info!(%address, bytes = account.data.len(), "Cloned account");
+    }
+    Ok(())
+}
+
+/// `solana-test-validator` arguments loading every fixture in `dir`: `<program id>.so`
+/// files as programs and `<pubkey>.json` files, as `solana account --output json` writes
+/// them, as accounts. Other files are ignored.
+pub fn test_validator_args(dir: &Path) -> Result<Vec<String>> {
+    let mut paths = std::fs::read_dir(dir)
+        .map_err(|e| anyhow!("Failed to read fixture dir {}: {}", dir.display(), e))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    paths.sort();
+
+    let mut args = vec!["--reset".to_string()];
+    for path in paths {
+        let flag = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("so") => "--bpf-program",
+            Some("json") => "--account",
+            _ => continue,
+        };
+        let stem = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default();
+        let address = Pubkey::from_str(stem)
+            .map_err(|_| anyhow!("Fixture {} is not named after its address", path.display()))?;
+        args.extend([
+            flag.to_string(),
+            address.to_string(),
+            path.display().to_string(),
+        ]);
+    }
+    Ok(args)
+}
+
+/// `test_validator_args` for `network.fixture_dir`
+pub fn configured_validator_args() -> Result<Vec<String>> {
+    test_validator_args(Path::new(&config::get().network.fixture_dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixture_args() {
+        let dir = std::env::temp_dir().join(format!("localnet-{}", Pubkey::new_unique()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (program, account) = (Pubkey::new_unique(), Pubkey::new_unique());
+        std::fs::write(dir.join(format!("{}.so", program)), b"").unwrap();
+        std::fs::write(dir.join(format!("{}.json", account)), b"{}").unwrap();
+        std::fs::write(dir.join("README.md"), b"").unwrap();
+
+        let args = test_validator_args(&dir).unwrap();
+        assert_eq!(args[0], "--reset");
+        assert_eq!(args.len(), 7);
+        let program_flag = args.iter().position(|a| a == "--bpf-program").unwrap();
+        assert_eq!(args[program_flag + 1], program.to_string());
+        let account_flag = args.iter().position(|a| a == "--account").unwrap();
+        assert_eq!(args[account_flag + 1], account.to_string());
+
+        std::fs::write(dir.join("pump.so"), b"").unwrap();
+        assert!(test_validator_args(&dir).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
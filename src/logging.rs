@@ -0,0 +1,38 @@
+use anyhow::{anyhow, Result};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::fmt::writer::{BoxMakeWriter, MakeWriterExt};
+use tracing_subscriber::EnvFilter;
+
+use crate::config::{self, LogFormat, LoggingConfig};
+
+/// Filter from `RUST_LOG`, falling back to `logging.level`
+fn env_filter(config: &LoggingConfig) -> Result<EnvFilter> {
+    EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(&config.level))
+        .map_err(|e| anyhow!("Invalid log level {:?}: {}", config.level, e))
+}
+
+/// Install the global subscriber from `[logging]`. Hold the returned guard until
+/// exit so buffered file output gets flushed.
+pub fn init() -> Result<Option<WorkerGuard>> {
+    let config = &config::get().logging;
+    let (writer, guard) = if config.dir.is_empty() {
+        (BoxMakeWriter::new(std::io::stdout), None)
+    } else {
+        let appender = tracing_appender::rolling::daily(&config.dir, &config.file_prefix);
+        let (file, guard) = tracing_appender::non_blocking(appender);
+        (BoxMakeWriter::new(std::io::stdout.and(file)), Some(guard))
+    };
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(env_filter(config)?)
+        .with_writer(writer)
+        // Colour codes would end up in the log files
+        .with_ansi(config.dir.is_empty());
+    match config.format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder.json().try_init(),
+    }
+    .map_err(|e| anyhow!("Failed to install log subscriber: {}", e))?;
+    Ok(guard)
+}
@@ -1,17 +1,27 @@
-mod cal;
-mod pump_buy;
-mod pump_sell;
-
 use solana_client::rpc_client::RpcClient;
-use solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+use solana_sdk::{
+    native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signature::Keypair, signer::Signer,
+};
 use std::str::FromStr;
+use tracing::{info, warn};
+use trading_bot_rust::{
+    address_book, amount::SolAmount, archiver, backtest, balance, blacklist, cal, config,
+    copytrade, creator_exit, creators, curve_diff, fees, inspect, journal, limit_orders, localnet,
+    logging, metadata, migrations, nonce, orders, pnl, portfolio, positions, price_feed, pump_buy,
+    pump_claim, pump_create, pumpswap, recovery, rpc_pool, safety, scanner, selftest, signer,
+    snipe, strategy, token_accounts, tp_sl, wallets, watchlist,
+};
 
 fn test_trade() {
-    let rpc = RpcClient::new("https://api.mainnet-beta.solana.com".to_string());
+    let rpc = rpc_pool::get_client();
     
     let mint = Pubkey::from_str("Ar4vi1BZXHVgQFRYD8AF7rBe7gsh3D1nM2hZG153pump").unwrap();
     
     println!("=== Testing Calculations for Mint: {} ===\n", mint);
+    match metadata::fetch_metadata(&rpc, &mint, false) {
+        Ok(token) => println!("Token: {}\n", token.display_name()),
+        Err(e) => warn!(%mint, error = %e, "Failed to fetch token metadata"),
+    }
     
     match cal::fetch_bonding_curve(&rpc, &mint) {
         Ok(bc) => {
@@ -22,21 +32,52 @@ fn test_trade() {
                 bc.virtual_sol_reserves as f64 / 1_000_000_000.0
             );
             println!("  Real Token Reserves: {}", bc.real_token_reserves);
-            println!("  Creator: {}", bc.creator);
+            println!("  Creator: {}", address_book::label(&bc.creator));
+            match price_feed::sol_usd(&rpc) {
+                Ok(price) => println!(
+                    "  Market Cap: {:.2} SOL (${:.0})",
+                    cal::market_cap_sol(&bc),
+                    cal::market_cap_usd(&bc, price.usd)
+                ),
+                Err(_) => println!("  Market Cap: {:.2} SOL", cal::market_cap_sol(&bc)),
+            }
+            println!("  Graduation Progress: {:.2}%", cal::graduation_progress(&bc));
             println!();
+
+            if bc.complete {
+                println!("Bonding curve complete - token trades on PumpSwap");
+                match migrations::MigrationTracker::open_default().and_then(|t| t.check(&rpc, &mint)) {
+                    Ok(Some(record)) => println!(
+                        "  Migrated at slot {:?} (tx {:?})",
+                        record.migration_slot, record.signature
+                    ),
+                    Ok(None) => {}
+                    Err(e) => warn!(%mint, error = %e, "Failed to record migration"),
+                }
+                match pumpswap::fetch_pool(&rpc, &mint) {
+                    Ok((pool_address, pool)) => {
+                        println!("  Pool: {}", pool_address);
+                        println!("  Pool Base Account: {}", pool.pool_base_token_account);
+                        println!("  Pool Quote Account: {}", pool.pool_quote_token_account);
+                    }
+                    Err(e) => warn!(%mint, error = %e, "Failed to fetch PumpSwap pool"),
+                }
+                return;
+            }
             
             let global = cal::Global::default();
-            let sol_amount = (0.1*LAMPORTS_PER_SOL as f64) as u64;
+            let sol_amount = config::get().trading.buy_amount_lamports;
             // Test buying with different SOL amounts
             println!("--- BUY Calculations ---");
 
-            let tokens = cal::get_tokens_for_sol(&global, Some(&bc), sol_amount);
-            println!("0.1 SOL -> {} tokens", tokens);
+            let sol = SolAmount::from_lamports(sol_amount);
+            let tokens = cal::tokens_for_sol(&global, Some(&bc), sol);
+            println!("{} -> {} tokens", sol, tokens);
 
-            let _ =  pump_buy::run_pump_buy(tokens, mint, sol_amount);
+            let _ =  pump_buy::run_pump_buy(tokens.raw(), mint, sol_amount);
 
-            let sol_get = cal::get_sol_for_tokens(&global, Some(&bc), tokens);
-            println!("{} tokens -> {} SOL", tokens, sol_get as f64 / LAMPORTS_PER_SOL as f64);
+            let sol_get = cal::get_sol_for_tokens(&global, Some(&bc), tokens.raw());
+            println!("{} tokens -> {}", tokens, SolAmount::from_lamports(sol_get));
 
             
             // Test selling different token amounts
@@ -68,18 +109,1266 @@ fn test_trade() {
             // }
         }
         Err(e) => {
-            println!("Failed to fetch bonding curve: {}", e);
-            println!("Token might have migrated to Raydium or doesn't exist.");
+            warn!(
+                %mint,
+                error = %e,
+                "Failed to fetch bonding curve, token might have migrated or doesn't exist"
+            );
+        }
+    }
+}
+
+fn print_fee_report() {
+    match portfolio::Portfolio::open_default().and_then(|p| p.fee_summary()) {
+        Ok(summary) => {
+            println!("\n=== Fee Report ===");
+            println!(
+                "  Successful: {} txs, {} lamports base + {} lamports priority + {} lamports tips",
+                summary.succeeded_count,
+                summary.success_base_fees,
+                summary.success_priority_fees,
+                summary.success_tips
+            );
+            println!(
+                "  Failed: {} txs, expired: {} txs ({:.1}% failure rate)",
+                summary.failed_count,
+                summary.expired_count,
+                summary.failure_rate() * 100.0
+            );
+            println!(
+                "  Fees wasted on failures: {} lamports ({:.6} SOL)",
+                summary.fees_wasted_on_failures(),
+                summary.fees_wasted_on_failures() as f64 / LAMPORTS_PER_SOL as f64
+            );
+        }
+        Err(e) => warn!(error = %e, "Failed to load fee report"),
+    }
+}
+
+/// `selftest <MINT> [SOL]`: buy and sell `mint` on devnet / a local validator, reporting each stage
+fn run_selftest(args: &[String]) {
+    let Some(mint) = args.first().and_then(|m| Pubkey::from_str(m).ok()) else {
+        eprintln!("Usage: trading-bot-rust selftest <MINT> [SOL]");
+        std::process::exit(2);
+    };
+    let sol_amount = match args.get(1) {
+        Some(sol) => match SolAmount::parse_sol(sol) {
+            Ok(sol) => sol.lamports(),
+            Err(e) => {
+                eprintln!("Invalid SOL amount {}: {}", sol, e);
+                std::process::exit(2);
+            }
+        },
+        None => config::get().trading.buy_amount_lamports,
+    };
+
+    let report = selftest::run_selftest(mint, sol_amount);
+    report.print();
+    if !report.passed() {
+        std::process::exit(1);
+    }
+}
+
+/// `wallets`: print the SOL balance of every wallet in the pool
+fn run_wallets() {
+    let pool = match wallets::WalletPool::from_config() {
+        Ok(pool) => pool,
+        Err(e) => {
+            eprintln!("Failed to load wallets: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = pool.refresh_balances(&rpc_pool::get_client()) {
+        eprintln!("Failed to fetch wallet balances: {}", e);
+        std::process::exit(1);
+    }
+    pool.print_summary();
+}
+
+/// `balance` runs the balance job once, then prints what the trading wallet can spend
+fn run_balance() {
+    let rpc = rpc_pool::get_client();
+    let sol = |lamports: u64| lamports as f64 / LAMPORTS_PER_SOL as f64;
+    let result = signer::load_default_wallet()
+        .map_err(Into::into)
+        .and_then(|user| balance::maintain(&rpc, &user))
+        .and_then(|lamports| {
+            let spendable = balance::spendable_lamports()?;
+            println!("Balance:   {:.4} SOL", sol(lamports));
+            println!("Reserve:   {:.4} SOL", sol(balance::manager().reserve_lamports()));
+            println!("Spendable: {:.4} SOL", sol(spendable));
+            Ok(())
+        });
+    if let Err(e) = result {
+        eprintln!("Balance Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run_cleanup() {
+    let result = signer::load_default_wallet()
+        .map_err(Into::into)
+        .and_then(|user| token_accounts::cleanup(&rpc_pool::get_client(), &user));
+    match result {
+        Ok(reclaimed) => println!(
+            "Reclaimed {} SOL",
+            reclaimed as f64 / LAMPORTS_PER_SOL as f64
+        ),
+        Err(e) => {
+            eprintln!("Cleanup Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// `claim` sweeps the wallet's pump.fun creator fees; `claim --check` only shows them
+fn run_claim(args: &[String]) {
+    let sol = |lamports: u64| lamports as f64 / LAMPORTS_PER_SOL as f64;
+    let rpc = rpc_pool::get_client();
+    let check = match args.first().map(String::as_str) {
+        None => false,
+        Some("--check") => true,
+        Some(_) => {
+            eprintln!("Usage: trading-bot-rust claim [--check]");
+            std::process::exit(2);
+        }
+    };
+    let result = signer::load_default_wallet()
+        .and_then(|user| {
+            if check {
+                let fees = pump_claim::fetch_creator_fees(&rpc, &user.pubkey())?;
+                println!("Creator vault: {}", fees.vault);
+                println!("Claimable:     {:.9} SOL", sol(fees.claimable()));
+            } else {
+                let claimed = pump_claim::claim_creator_fees(&rpc, &user)?;
+                println!("Claimed {:.9} SOL of creator fees", sol(claimed));
+            }
+            Ok(())
+        });
+    if let Err(e) = result {
+        eprintln!("Claim Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Parse `<MINT> <AMOUNT> <PRICE>` and place the order. Buy amounts are in SOL.
+fn place_limit_order(
+    book: &limit_orders::LimitOrderBook,
+    side: portfolio::Side,
+    args: &[&str],
+) -> anyhow::Result<limit_orders::LimitOrder> {
+    let [mint, amount, price] = args else {
+        return Err(anyhow::anyhow!("Expected <MINT> <AMOUNT> <PRICE>"));
+    };
+    let mint = Pubkey::from_str(mint)?;
+    let price: f64 = price.parse()?;
+    let trading = &config::get().trading;
+    match side {
+        portfolio::Side::Buy => book.place(
+            mint,
+            side,
+            SolAmount::parse_sol(amount)?.lamports(),
+            price,
+            trading.buy_slippage_bps,
+        ),
+        portfolio::Side::Sell => book.place(
+            mint,
+            side,
+            amount.parse()?,
+            price,
+            trading.sell_slippage_bps,
+        ),
+    }
+}
+
+/// Parse `<MINT> <BUDGET_SOL> <LEGS> <STEP_PCT>` and stage the ladder's buys below the
+/// position's average entry, or below the spot price when nothing is held
+fn place_entry_ladder(
+    book: &limit_orders::LimitOrderBook,
+    args: &[&str],
+) -> anyhow::Result<Vec<limit_orders::LimitOrder>> {
+    let [mint, budget, legs, step_pct] = args else {
+        return Err(anyhow::anyhow!(
+            "Expected <MINT> <BUDGET_SOL> <LEGS> <STEP_PCT>"
+        ));
+    };
+    let mint = Pubkey::from_str(mint)?;
+    let ladder = limit_orders::EntryLadder {
+        budget_lamports: SolAmount::parse_sol(budget)?.lamports(),
+        legs: legs.parse()?,
+        step_pct: step_pct.parse()?,
+    };
+    let entry_price = match portfolio::Portfolio::open_default()?
+        .get_position(&mint)?
+        .filter(portfolio::Position::is_open)
+    {
+        Some(position) => position.avg_entry_price(),
+        None => cal::spot_price_lamports_per_token(&cal::fetch_bonding_curve(
+            &rpc_pool::get_client(),
+            &mint,
+        )?),
+    };
+    book.place_entry_ladder(
+        mint,
+        &ladder,
+        entry_price,
+        config::get().trading.buy_slippage_bps,
+    )
+}
+
+/// `limit buy <MINT> <SOL> <PRICE>`, `limit sell <MINT> <TOKENS> <PRICE>`,
+/// `limit ladder <MINT> <BUDGET_SOL> <LEGS> <STEP_PCT>`, `limit list`, `limit cancel <ID>`
+/// or `limit watch`. Prices are in lamports per token.
+fn run_limit(args: &[String]) {
+    let book = match limit_orders::LimitOrderBook::open_default() {
+        Ok(book) => book,
+        Err(e) => {
+            eprintln!("Failed to open limit orders: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = match args.as_slice() {
+        ["buy", rest @ ..] => place_limit_order(&book, portfolio::Side::Buy, rest)
+            .map(|order| println!("Placed limit order #{}", order.id)),
+        ["sell", rest @ ..] => place_limit_order(&book, portfolio::Side::Sell, rest)
+            .map(|order| println!("Placed limit order #{}", order.id)),
+        ["ladder", rest @ ..] => place_entry_ladder(&book, rest).map(|legs| {
+            for leg in legs {
+                println!(
+                    "Placed ladder buy #{}: {} at {:.9} lamports/token",
+                    leg.id,
+                    SolAmount::from_lamports(leg.amount),
+                    leg.limit_price
+                );
+            }
+        }),
+        ["list"] => book.all_orders().map(|orders| {
+            for order in orders {
+                println!(
+                    "#{:<5} {:<10} {:<10} {} of {} at {:.9} lamports/token {}",
+                    order.id,
+                    format!("{:?}", order.status),
+                    format!("{:?} {:?}", order.side, order.kind),
+                    order.amount,
+                    address_book::label(&order.mint),
+                    order.limit_price,
+                    order.error.unwrap_or_default()
+                );
+            }
+        }),
+        ["cancel", id] => id
+            .parse::<i64>()
+            .map_err(Into::into)
+            .and_then(|id| book.cancel(id).map(|cancelled| (id, cancelled)))
+            .map(|(id, cancelled)| {
+                if cancelled {
+                    println!("Cancelled limit order #{}", id);
+                } else {
+                    println!("Limit order #{} is not open", id);
+                }
+            }),
+        ["watch"] => limit_orders::run(),
+        _ => {
+            eprintln!(
+                "Usage: trading-bot-rust limit buy <MINT> <SOL> <PRICE> | sell <MINT> <TOKENS> <PRICE>"
+            );
+            eprintln!("       trading-bot-rust limit ladder <MINT> <BUDGET_SOL> <LEGS> <STEP_PCT>");
+            eprintln!("       trading-bot-rust limit list | cancel <ID> | watch");
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("Limit Order Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Parse the `replace` arguments of `orders`, `-` keeping the current value. The amount
+/// is in SOL for buys and raw tokens for sells, like `limit`.
+fn replace_order(
+    book: &limit_orders::LimitOrderBook,
+    id: &str,
+    amount: &str,
+    price: &str,
+) -> anyhow::Result<limit_orders::LimitOrder> {
+    let id: i64 = id.parse()?;
+    let order = book
+        .get(id)?
+        .ok_or_else(|| anyhow::anyhow!("Order #{} not found", id))?;
+    let amount = match (amount, order.side) {
+        ("-", _) => None,
+        (amount, portfolio::Side::Buy) => Some(SolAmount::parse_sol(amount)?.lamports()),
+        (amount, portfolio::Side::Sell) => Some(amount.parse()?),
+    };
+    let price = match price {
+        "-" => None,
+        price => Some(price.parse()?),
+    };
+    Ok(orders::replace(book, id, amount, price)?)
+}
+
+/// Parse `<MINT> <SOL> <LEGS> <MINUTES> [MAX_PRICE]` and place the DCA legs
+fn place_dca(
+    book: &limit_orders::LimitOrderBook,
+    args: &[&str],
+) -> anyhow::Result<Vec<limit_orders::LimitOrder>> {
+    let (mint, sol, legs, minutes, max_price) = match args {
+        [mint, sol, legs, minutes] => (mint, sol, legs, minutes, limit_orders::NO_PRICE_LIMIT),
+        [mint, sol, legs, minutes, max_price] => (mint, sol, legs, minutes, max_price.parse()?),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Expected <MINT> <SOL> <LEGS> <MINUTES> [MAX_PRICE]"
+            ))
+        }
+    };
+    let minutes: i64 = minutes.parse()?;
+    book.place_dca(
+        Pubkey::from_str(mint)?,
+        SolAmount::parse_sol(sol)?.lamports(),
+        legs.parse()?,
+        minutes * 60,
+        max_price,
+        config::get().trading.buy_slippage_bps,
+    )
+}
+
+/// Print open orders, or every order with `include_closed`, optionally only on `mint`
+fn print_orders(
+    book: &limit_orders::LimitOrderBook,
+    mint: Option<&str>,
+    include_closed: bool,
+) -> anyhow::Result<()> {
+    let mint = mint.map(Pubkey::from_str).transpose()?;
+    for order in orders::list(book, mint.as_ref(), include_closed)? {
+        println!("{}", orders::describe(&order));
+    }
+    Ok(())
+}
+
+/// `orders [--all] [MINT]`, `orders cancel <ID>`, `orders replace <ID> <AMOUNT|-> <PRICE|->`
+/// or `orders dca <MINT> <SOL> <LEGS> <MINUTES> [MAX_PRICE]`. Lists the bot's pending
+/// limit, TP/SL and DCA orders, executed by `limit watch`.
+fn run_orders(args: &[String]) {
+    let book = match limit_orders::LimitOrderBook::open_default() {
+        Ok(book) => book,
+        Err(e) => {
+            eprintln!("Failed to open limit orders: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = match args.as_slice() {
+        ["cancel", id] => id
+            .parse::<i64>()
+            .map_err(Into::into)
+            .and_then(|id| Ok(orders::cancel(&book, id)?))
+            .map(|order| println!("Cancelled order #{}", order.id)),
+        ["replace", id, amount, price] => replace_order(&book, id, amount, price)
+            .map(|order| println!("Replaced with order #{}", order.id)),
+        ["dca", rest @ ..] => place_dca(&book, rest).map(|legs| {
+            for leg in legs {
+                println!("Placed DCA leg #{}", leg.id);
+            }
+        }),
+        [] => print_orders(&book, None, false),
+        ["--all"] => print_orders(&book, None, true),
+        [mint] => print_orders(&book, Some(*mint), false),
+        ["--all", mint] => print_orders(&book, Some(*mint), true),
+        _ => {
+            eprintln!("Usage: trading-bot-rust orders [--all] [MINT]");
+            eprintln!(
+                "       trading-bot-rust orders cancel <ID> | replace <ID> <AMOUNT|-> <PRICE|->"
+            );
+            eprintln!(
+                "       trading-bot-rust orders dca <MINT> <SOL> <LEGS> <MINUTES> [MAX_PRICE]"
+            );
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("Order Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// `watchlist add <MINT> <KIND> <THRESHOLD>`, `watchlist list`, `watchlist remove <ID>` or
+/// `watchlist watch`. Kinds are price-above / price-below (lamports per token), mcap-above /
+/// mcap-below (SOL), progress (graduation percent) and top10-above / dev-above /
+/// bundlers-above (percent of supply).
+fn run_watchlist(args: &[String]) {
+    let list = match watchlist::Watchlist::open_default() {
+        Ok(list) => list,
+        Err(e) => {
+            eprintln!("Failed to open watchlist: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = match args.as_slice() {
+        ["add", mint, kind, threshold] => watchlist::AlertKind::parse(kind)
+            .and_then(|kind| list.add(Pubkey::from_str(mint)?, kind, threshold.parse()?))
+            .map(|alert| println!("Added watchlist alert #{}", alert.id)),
+        ["list"] => list.alerts(None).map(|alerts| {
+            for alert in alerts {
+                println!(
+                    "#{:<5} {} {} {}{}",
+                    alert.id,
+                    address_book::label(&alert.mint),
+                    alert.kind.as_str(),
+                    alert.threshold,
+                    if alert.active { " (holding)" } else { "" }
+                );
+            }
+        }),
+        ["remove", id] => id
+            .parse::<i64>()
+            .map_err(Into::into)
+            .and_then(|id| list.remove(id).map(|removed| (id, removed)))
+            .map(|(id, removed)| {
+                if removed {
+                    println!("Removed watchlist alert #{}", id);
+                } else {
+                    println!("No watchlist alert #{}", id);
+                }
+            }),
+        ["watch"] => watchlist::run(),
+        _ => {
+            eprintln!("Usage: trading-bot-rust watchlist add <MINT> <KIND> <THRESHOLD>");
+            eprintln!("       trading-bot-rust watchlist list | remove <ID> | watch");
+            eprintln!("Kinds: price-above, price-below, mcap-above, mcap-below, progress,");
+            eprintln!("       top10-above, dev-above, bundlers-above");
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("Watchlist Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// `inspect <MINT> [--json]`: print everything known about a mint in one report
+fn run_inspect(args: &[String]) {
+    let json = args.len() == 2 && args[1] == "--json";
+    let Some(mint) = args
+        .first()
+        .filter(|_| args.len() == 1 || json)
+        .and_then(|m| Pubkey::from_str(m).ok())
+    else {
+        eprintln!("Usage: trading-bot-rust inspect <MINT> [--json]");
+        std::process::exit(2);
+    };
+    match inspect::inspect(&rpc_pool::get_client(), &mint) {
+        Ok(report) if json => println!("{:#}", report.to_json()),
+        Ok(report) => report.print(),
+        Err(e) => {
+            eprintln!("Inspect Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// `safety <MINT>`: run the pre-buy checks and print the risk score
+fn run_safety(args: &[String]) {
+    let Some(mint) = args.first().and_then(|m| Pubkey::from_str(m).ok()) else {
+        eprintln!("Usage: trading-bot-rust safety <MINT>");
+        std::process::exit(2);
+    };
+    match safety::check(&rpc_pool::get_client(), &mint) {
+        Ok(report) => report.print(),
+        Err(e) => {
+            eprintln!("Safety Check Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// `flow <MINT> [SECONDS]`: poll the curve every SECONDS (default 10) and print the net
+/// buying or selling inferred between snapshots, until the curve completes
+fn run_flow(args: &[String]) {
+    let Some(mint) = args.first().and_then(|m| Pubkey::from_str(m).ok()) else {
+        eprintln!("Usage: trading-bot-rust flow <MINT> [SECONDS]");
+        std::process::exit(2);
+    };
+    let seconds = args.get(1).and_then(|s| s.parse().ok()).unwrap_or(10);
+    let result = curve_diff::watch(
+        &rpc_pool::get_client(),
+        &mint,
+        std::time::Duration::from_secs(seconds),
+        |diff, curve| {
+            let side = diff
+                .side()
+                .map_or("-".to_string(), |side| format!("{:?}", side));
+            println!(
+                "{:<4} {:>+16} tokens {:>+12.4} SOL ({:+.4} SOL with fees), mcap {:.2} SOL",
+                side,
+                diff.net_tokens,
+                diff.net_sol as f64 / LAMPORTS_PER_SOL as f64,
+                diff.net_sol_with_fees() as f64 / LAMPORTS_PER_SOL as f64,
+                cal::market_cap_sol(curve)
+            );
+            if diff.completed {
+                println!("Bonding curve complete");
+            }
+            true
+        },
+    );
+    if let Err(e) = result {
+        eprintln!("Flow Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn print_launches(history: &creators::CreatorHistory) {
+    println!("\n=== Launches by {} ===", history.creator);
+    for launch in &history.launches {
+        println!(
+            "  {} {:<8} {}",
+            launch.created_at,
+            format!("{:?}", launch.outcome),
+            address_book::label(&launch.mint)
+        );
+    }
+    println!(
+        "{} launches, {} migrated, {} dumped",
+        history.launches.len(),
+        history.migrated(),
+        history.dumped()
+    );
+}
+
+fn print_creator_history(list: &creators::CreatorList, creator: &Pubkey) -> anyhow::Result<()> {
+    let limit = config::get().safety.creator_history;
+    let history = creators::fetch_history(&rpc_pool::get_client(), creator, limit)?;
+    print_launches(&history);
+    if let Some(status) = list.status(creator)? {
+        println!("Listed: {:?}", status);
+    }
+    Ok(())
+}
+
+/// `creators <PUBKEY>` prints a creator's earlier launches. `creators allow|deny <PUBKEY>
+/// [REASON]`, `creators remove <PUBKEY>` and `creators list` manage the allow/deny list.
+fn run_creators(args: &[String]) {
+    let list = match creators::CreatorList::open_default() {
+        Ok(list) => list,
+        Err(e) => {
+            eprintln!("Failed to open creator list: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let set = |status, creator: &str, reason: &[&str]| -> anyhow::Result<()> {
+        let creator = Pubkey::from_str(creator)?;
+        let reason = reason.join(" ");
+        list.set(&creator, status, (!reason.is_empty()).then_some(reason.as_str()))?;
+        println!("{} is now {:?}-listed", creator, status);
+        Ok(())
+    };
+    let result = match args.as_slice() {
+        ["allow", creator, reason @ ..] => set(creators::ListStatus::Allow, creator, reason),
+        ["deny", creator, reason @ ..] => set(creators::ListStatus::Deny, creator, reason),
+        ["remove", creator] => Pubkey::from_str(creator)
+            .map_err(Into::into)
+            .and_then(|creator| list.remove(&creator).map(|removed| (creator, removed)))
+            .map(|(creator, removed)| {
+                if removed {
+                    println!("Removed {} from the creator list", creator);
+                } else {
+                    println!("{} is not listed", creator);
+                }
+            }),
+        ["list"] => list.entries().map(|entries| {
+            for entry in entries {
+                println!(
+                    "{:<44} {:<5} {}",
+                    entry.creator,
+                    format!("{:?}", entry.status),
+                    entry.reason.unwrap_or_default()
+                );
+            }
+        }),
+        [creator] => Pubkey::from_str(creator)
+            .map_err(Into::into)
+            .and_then(|creator| print_creator_history(&list, &creator)),
+        _ => {
+            eprintln!("Usage: trading-bot-rust creators <PUBKEY> | list | remove <PUBKEY>");
+            eprintln!("       trading-bot-rust creators allow|deny <PUBKEY> [REASON]");
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("Creators Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// `blacklist deny|allow mint|creator|symbol <VALUE> [REASON]`, `blacklist remove <KIND>
+/// <VALUE>` and `blacklist list` manage the lists every buy is screened against. `blacklist
+/// check <MINT>` screens a mint without buying it.
+fn run_blacklist(args: &[String]) {
+    let list = match blacklist::TokenList::open_default() {
+        Ok(list) => list,
+        Err(e) => {
+            eprintln!("Failed to open blacklist: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let set = |status, kind: &str, value: &str, reason: &[&str]| -> anyhow::Result<()> {
+        let kind = blacklist::EntryKind::parse(kind)?;
+        let reason = reason.join(" ");
+        let reason = (!reason.is_empty()).then_some(reason.as_str());
+        list.set(kind, value, status, reason)?;
+        println!("{} {} is now {:?}-listed", kind.as_str(), value, status);
+        Ok(())
+    };
+    let result = match args.as_slice() {
+        ["deny", kind, value, reason @ ..] => set(creators::ListStatus::Deny, kind, value, reason),
+        ["allow", kind, value, reason @ ..] => {
+            set(creators::ListStatus::Allow, kind, value, reason)
+        }
+        ["remove", kind, value] => blacklist::EntryKind::parse(kind)
+            .and_then(|kind| list.remove(kind, value).map(|removed| (kind, removed)))
+            .map(|(kind, removed)| {
+                if removed {
+                    println!("Removed {} {}", kind.as_str(), value);
+                } else {
+                    println!("{} {} is not listed", kind.as_str(), value);
+                }
+            }),
+        ["list"] => list.entries().map(|entries| {
+            for entry in entries {
+                println!(
+                    "{:<7} {:<5} {:<44} {}",
+                    entry.kind.as_str(),
+                    format!("{:?}", entry.status),
+                    entry.value,
+                    entry.reason.unwrap_or_default()
+                );
+            }
+        }),
+        ["check", mint] => Pubkey::from_str(mint)
+            .map_err(Into::into)
+            .and_then(|mint| blacklist::screen(&mint).map_err(Into::into))
+            .map(|()| println!("{} may be bought", mint)),
+        _ => {
+            eprintln!("Usage: trading-bot-rust blacklist list | check <MINT>");
+            eprintln!("       trading-bot-rust blacklist deny|allow <KIND> <VALUE> [REASON]");
+            eprintln!("       trading-bot-rust blacklist remove <KIND> <VALUE>");
+            eprintln!("       KIND is mint, creator or symbol (a case-insensitive regex)");
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("Blacklist Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// `nonce create` makes a durable nonce account for pre-signed buys. `nonce show` prints
+/// the current nonce of `trading.nonce_account`.
+fn run_nonce(args: &[String]) {
+    let rpc = rpc_pool::get_client();
+    let private_key = &config::get().wallet.private_key;
+    let result = match args.first().map(String::as_str) {
+        Some("create") => signer::load_wallet(private_key).and_then(|payer| {
+            let account = Keypair::new();
+            nonce::create_nonce_account(&rpc, &payer, &account)?;
+            println!("Created nonce account {}", account.pubkey());
+            println!("Set trading.nonce_account to use it");
+            Ok(())
+        }),
+        Some("show") => nonce::DurableNonce::configured(&rpc).map(|nonce| match nonce {
+            Some(nonce) => {
+                println!("Nonce account: {}", nonce.account);
+                println!("Authority:     {}", nonce.authority);
+                println!("Nonce:         {}", nonce.nonce);
+            }
+            None => println!("trading.nonce_account is not set"),
+        }),
+        _ => {
+            eprintln!("Usage: trading-bot-rust nonce create | show");
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("Nonce Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// `localnet` prints the `solana-test-validator` command loading `network.fixture_dir`.
+/// `localnet clone [RPC_URL]` fills the fixture dir from mainnet, or from `RPC_URL`.
+fn run_localnet(args: &[String]) {
+    let fixture_dir = &config::get().network.fixture_dir;
+    let result = match args {
+        [] => localnet::configured_validator_args()
+            .map(|args| println!("solana-test-validator {}", args.join(" "))),
+        [command, rest @ ..] if command == "clone" && rest.len() <= 1 => {
+            let url = rest
+                .first()
+                .map(String::as_str)
+                .unwrap_or(config::Network::Mainnet.rpc_url());
+            localnet::clone_fixtures(
+                &RpcClient::new(url.to_string()),
+                std::path::Path::new(fixture_dir),
+            )
+            .map(|()| println!("Cloned localnet fixtures from {} into {}", url, fixture_dir))
+        }
+        _ => {
+            eprintln!("Usage: trading-bot-rust localnet");
+            eprintln!("       trading-bot-rust localnet clone [RPC_URL]");
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("Localnet Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// `pnl [--csv PATH] [--json PATH]`: realized and unrealized PnL of every position,
+/// optionally exported
+fn run_pnl(args: &[String]) {
+    let mut exports = Vec::new();
+    for pair in args.chunks(2) {
+        match pair {
+            [flag, path] if flag == "--csv" || flag == "--json" => exports.push((flag, path)),
+            _ => {
+                eprintln!("Usage: trading-bot-rust pnl [--csv PATH] [--json PATH]");
+                std::process::exit(2);
+            }
+        }
+    }
+    let rpc = rpc_pool::get_client();
+    let result = portfolio::Portfolio::open_default()
+        .and_then(|portfolio| pnl::generate(&rpc, &portfolio))
+        .and_then(|report| {
+            report.print();
+            for (flag, path) in exports {
+                let contents = match flag.as_str() {
+                    "--csv" => report.to_csv(),
+                    _ => report.to_json()?,
+                };
+                std::fs::write(path, contents)?;
+                println!("Wrote {}", path);
+            }
+            Ok(())
+        });
+    if let Err(e) = result {
+        eprintln!("PnL Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// `journal [--method fifo|lifo] [--csv PATH] [--lots-csv PATH] [--json PATH]` exports
+/// every fill with its USD value and the lots each sell disposed of
+fn run_journal(args: &[String]) {
+    let mut method = journal::LotMethod::default();
+    let mut exports = Vec::new();
+    for pair in args.chunks(2) {
+        match pair {
+            [flag, value] if flag == "--method" => match value.parse() {
+                Ok(parsed) => method = parsed,
+                Err(e) => {
+                    eprintln!("Journal Error: {}", e);
+                    std::process::exit(2);
+                }
+            },
+            [flag, path] if flag == "--csv" || flag == "--lots-csv" || flag == "--json" => {
+                exports.push((flag, path))
+            }
+            _ => {
+                eprintln!(
+                    "Usage: trading-bot-rust journal [--method fifo|lifo] [--csv PATH] \
+                     [--lots-csv PATH] [--json PATH]"
+                );
+                std::process::exit(2);
+            }
+        }
+    }
+    let result = portfolio::Portfolio::open_default()
+        .and_then(|portfolio| journal::generate(&portfolio, method))
+        .and_then(|journal| {
+            journal.print();
+            for (flag, path) in exports {
+                let contents = match flag.as_str() {
+                    "--csv" => journal.to_csv(),
+                    "--lots-csv" => journal.to_lots_csv(),
+                    _ => journal.to_json()?,
+                };
+                std::fs::write(path, contents)?;
+                println!("Wrote {}", path);
+            }
+            Ok(())
+        });
+    if let Err(e) = result {
+        eprintln!("Journal Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Reconcile the portfolio of every pool wallet per `storage.reconcile_on_startup`
+fn recover_on_startup() -> anyhow::Result<()> {
+    let owners = wallets::WalletPool::from_config()?.pubkeys();
+    recovery::on_startup(&rpc_pool::get_client(), &owners)?;
+    Ok(())
+}
+
+/// `recover` reconciles the portfolio with the chain now, whatever the config says
+fn run_recover() {
+    let result = wallets::WalletPool::from_config()
+        .and_then(|pool| {
+            let portfolio = portfolio::Portfolio::open_default()?;
+            recovery::recover(&rpc_pool::get_client(), &portfolio, &pool.pubkeys())
+        })
+        .map(|report| {
+            for fill in &report.recovered {
+                println!(
+                    "Recovered {:?} of {} {} ({})",
+                    fill.side,
+                    fill.token_amount,
+                    address_book::label(&fill.mint),
+                    fill.signature
+                );
+            }
+            for d in &report.discrepancies {
+                println!(
+                    "Adjusted {} from {} to {} tokens",
+                    address_book::label(&d.mint),
+                    d.recorded,
+                    d.on_chain
+                );
+            }
+            println!(
+                "{} fills recovered, {} positions adjusted, {} untracked mints held",
+                report.recovered.len(),
+                report.discrepancies.len(),
+                report.untracked_mints
+            );
+        });
+    if let Err(e) = result {
+        eprintln!("Recovery Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// `snipe` buys new launches as their Create events arrive, per `[snipe]`
+fn run_snipe() {
+    let result = recover_on_startup()
+        .and_then(|_| signer::load_default_wallet())
+        .and_then(snipe::run);
+    if let Err(e) = result {
+        eprintln!("Snipe Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// `positions` exits every open position per `[strategy]`, one task per mint, picking up
+/// positions opened by other commands as they appear
+fn run_positions() {
+    let result = tp_sl::TpSlConfig::from_config(config::get()).and_then(|exits| {
+        recover_on_startup().and_then(|_| positions::PositionManager::new(exits).run())
+    });
+    if let Err(e) = result {
+        eprintln!("Positions Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// `launch` creates a token with a dev buy, uploading its image and metadata to IPFS
+/// unless a metadata URI is given with `--uri`. `--bundle` adds `launch.bundle_buys`
+/// from the wallet pool in one Jito bundle.
+fn run_launch(args: &[String]) {
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let bundled = args.first() == Some(&"--bundle");
+    if bundled {
+        args.remove(0);
+    }
+    // Either metadata to upload or the URI of metadata already pinned
+    let (name, symbol, lamports, upload, uri) = match args.as_slice() {
+        ["--uri", uri, name, symbol, lamports] => (*name, *symbol, *lamports, None, *uri),
+        [name, symbol, image, lamports, description @ ..] => {
+            let info = pump_create::TokenInfo {
+                name: name.to_string(),
+                symbol: symbol.to_string(),
+                description: description.join(" "),
+                image_path: image.to_string(),
+                ..Default::default()
+            };
+            (*name, *symbol, *lamports, Some(info), "")
+        }
+        _ => {
+            eprintln!(
+                "Usage: trading-bot-rust launch [--bundle] <NAME> <SYMBOL> <IMAGE> <DEV_BUY_LAMPORTS> [DESCRIPTION]"
+            );
+            eprintln!(
+                "       trading-bot-rust launch [--bundle] --uri <METADATA_URI> <NAME> <SYMBOL> <DEV_BUY_LAMPORTS>"
+            );
+            std::process::exit(2);
+        }
+    };
+    let result = signer::load_default_wallet()
+        .and_then(|user| {
+            let dev_buy_lamports: u64 = lamports.parse()?;
+            let uri = match upload {
+                Some(info) => pump_create::upload_metadata(&info)?,
+                None => uri.to_string(),
+            };
+            let create = pump_create::CreateArgs {
+                name: name.to_string(),
+                symbol: symbol.to_string(),
+                uri,
+                creator: user.pubkey(),
+            };
+            let rpc = rpc_pool::get_client();
+            let mint = if bundled {
+                let pool = wallets::WalletPool::from_config()?;
+                let bundle = pump_create::LaunchBundle::new(&user, create.clone(), dev_buy_lamports)
+                    .with_configured_buys(&pool)?;
+                pump_create::launch_bundle(&rpc, &bundle)?
+            } else {
+                pump_create::launch(&rpc, &user, &create, dev_buy_lamports)?
+            };
+            println!("Launched {} ({})", mint, create.symbol);
+            Ok(())
+        });
+    if let Err(e) = result {
+        eprintln!("Launch Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// The strategies named in `args`: `snipe`, `tp_sl`, `creator_exit`, `copy:<WALLET>` and
+/// `copy_list:<PATH>` for every wallet in a `scanner` candidate list. Live TP/SL and
+/// creator exits also monitor the positions already open.
+fn parse_strategies(
+    args: &[String],
+    live: bool,
+) -> anyhow::Result<Vec<Box<dyn strategy::Strategy>>> {
+    let rpc = rpc_pool::get_client();
+    let exits = tp_sl::TpSlConfig::from_config(config::get())?;
+    let mut strategies: Vec<Box<dyn strategy::Strategy>> = Vec::new();
+    let mut targets = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "snipe" => strategies.push(Box::new(snipe::SnipeStrategy::new(&rpc)?)),
+            "tp_sl" if live => {
+                let tp_sl = tp_sl::TpSlStrategy::new(exits.clone()).with_open_positions()?;
+                info!(positions = tp_sl.monitored(), "Monitoring open positions");
+                strategies.push(Box::new(tp_sl));
+            }
+            "tp_sl" => strategies.push(Box::new(tp_sl::TpSlStrategy::new(exits.clone()))),
+            "creator_exit" if live => {
+                let market = strategy::LiveMarket::new(rpc_pool::get_client());
+                let creator_exit = creator_exit::CreatorExitStrategy::new(Default::default())
+                    .with_open_positions(&market)?;
+                info!(positions = creator_exit.watched(), "Watching creators");
+                strategies.push(Box::new(creator_exit));
+            }
+            "creator_exit" => strategies.push(Box::new(creator_exit::CreatorExitStrategy::new(
+                Default::default(),
+            ))),
+            arg => {
+                if let Some(target) = arg.strip_prefix("copy:") {
+                    targets.push(Pubkey::from_str(target)?);
+                } else if let Some(path) = arg.strip_prefix("copy_list:") {
+                    targets.extend(scanner::read_candidates(path)?);
+                } else {
+                    return Err(anyhow::anyhow!("Unknown strategy: {}", arg));
+                }
+            }
+        }
+    }
+    if !targets.is_empty() {
+        strategies.push(Box::new(copytrade::CopyTrader::new(copytrade::CopyTradeConfig {
+            targets,
+            ..Default::default()
+        })));
+    }
+    Ok(strategies)
+}
+
+/// Register the strategies named in `args` and run them until their streams close
+fn start_strategies(args: &[String]) -> anyhow::Result<()> {
+    let user = signer::load_default_wallet()?;
+    // TP/SL picks up open positions, so they are repaired first
+    recover_on_startup()?;
+    let mut runner = strategy::StrategyRunner::new();
+    for strategy in parse_strategies(args, true)? {
+        runner.register(strategy);
+    }
+    runner.run(&user.pubkey())
+}
+
+/// `strategies [snipe] [tp_sl] [creator_exit] [copy:<WALLET>]...` runs the named strategies
+/// together, e.g. `strategies snipe tp_sl` snipes launches and exits them per `[strategy]`
+fn run_strategies(args: &[String]) {
+    if let Err(e) = start_strategies(args) {
+        eprintln!("Strategy Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Replay the archive at `path` through the strategies named in `args`, optionally
+/// followed by `--equity PATH` to write the equity curve
+fn replay_archive(path: &str, args: &[String]) -> anyhow::Result<()> {
+    let (names, equity_path) = match args {
+        [names @ .., flag, equity_path] if flag == "--equity" => (names, Some(equity_path)),
+        names => (names, None),
+    };
+    let events = backtest::load_archive(path)?;
+    let mut run = backtest::Backtest::new(backtest::BacktestConfig {
+        fee_config: fees::fetch_fee_config(&rpc_pool::get_client()).ok(),
+        ..Default::default()
+    });
+    for strategy in parse_strategies(names, false)? {
+        run.register(strategy);
+    }
+    let report = run.run(&events);
+    report.print();
+    if let Some(equity_path) = equity_path {
+        std::fs::write(equity_path, report.equity_csv())?;
+        println!("Wrote {}", equity_path);
+    }
+    Ok(())
+}
+
+/// `backtest fetch <ACCOUNT> <TRANSACTIONS> <ARCHIVE>` archives the events of an
+/// account's last transactions. `backtest <ARCHIVE> <STRATEGY>... [--equity PATH]`
+/// replays an archive through the named strategies, as for `strategies`.
+fn run_backtest(args: &[String]) {
+    let result = match args {
+        [command, account, limit, path] if command == "fetch" => Pubkey::from_str(account)
+            .map_err(Into::into)
+            .and_then(|account| Ok((account, limit.parse()?)))
+            .and_then(|(account, limit)| {
+                backtest::fetch_history(&rpc_pool::get_client(), &account, limit)
+            })
+            .and_then(|events| {
+                backtest::save_archive(path, &events)?;
+                println!("Archived {} events to {}", events.len(), path);
+                Ok(())
+            }),
+        [path, names @ ..] if !names.is_empty() => replay_archive(path, names),
+        _ => {
+            eprintln!("Usage: trading-bot-rust backtest fetch <ACCOUNT> <TRANSACTIONS> <ARCHIVE>");
+            eprintln!("       trading-bot-rust backtest <ARCHIVE> <STRATEGY>... [--equity PATH]");
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("Backtest Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn print_top_wallets(archive: &archiver::EventArchive, days: i64) -> anyhow::Result<()> {
+    let since = portfolio::now_unix() - days * 86_400;
+    println!("\n=== Top Wallets, Last {} Days ===", days);
+    for activity in archive.top_wallets(since, 5, 20)? {
+        println!(
+            "  {:<44} {:>6} trades {:>5} mints {:>+12.4} SOL",
+            address_book::label(&activity.wallet),
+            activity.trades,
+            activity.mints,
+            activity.net_sol() as f64 / LAMPORTS_PER_SOL as f64
+        );
+    }
+    Ok(())
+}
+
+/// `scanner [DAYS] [--out PATH]` ranks the wallets trading in the event archive over the
+/// last DAYS (default 7) by realized PnL and win rate, optionally writing them to PATH as
+/// a candidate list for `strategies copy_list:<PATH>`
+fn run_scanner(args: &[String]) {
+    let (args, out) = match args {
+        [rest @ .., flag, path] if flag == "--out" => (rest, Some(path)),
+        args => (args, None),
+    };
+    let window_days = match args {
+        [] => Some(scanner::ScanConfig::default().window_days),
+        [days] => days.parse().ok(),
+        _ => None,
+    };
+    let Some(window_days) = window_days else {
+        eprintln!("Usage: trading-bot-rust scanner [DAYS] [--out PATH]");
+        std::process::exit(2);
+    };
+    let config = scanner::ScanConfig {
+        window_days,
+        ..Default::default()
+    };
+    let result = archiver::EventArchive::open_default()
+        .and_then(|archive| scanner::scan_archive(&archive, &config))
+        .and_then(|scores| {
+            println!(
+                "\n=== Copy-Trade Candidates, Last {} Days ===",
+                config.window_days
+            );
+            for score in &scores {
+                println!(
+                    "  {:<44} {:>6} trades {:>4}/{:<4} wins {:>5.1}% {:>+12.4} SOL",
+                    address_book::label(&score.wallet),
+                    score.trades,
+                    score.wins,
+                    score.closed,
+                    score.win_rate() * 100.0,
+                    score.realized_pnl_lamports as f64 / LAMPORTS_PER_SOL as f64
+                );
+            }
+            if let Some(path) = out {
+                scanner::write_candidates(path, &scores)?;
+                println!("Wrote {} candidates to {}", scores.len(), path);
+            }
+            Ok(())
+        });
+    if let Err(e) = result {
+        eprintln!("Scanner Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// `archive` archives pump.fun events until stopped. `archive import <ARCHIVE>` adds a
+/// backtest archive, `archive export <ARCHIVE> [MINT]` writes one, `archive wallets
+/// [DAYS]` ranks wallets by SOL taken out and `archive creator <PUBKEY>` prints a
+/// creator's archived launches.
+fn run_archive(args: &[String]) {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    if args.is_empty() {
+        if let Err(e) = archiver::run() {
+            eprintln!("Archiver Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let mut archive = match archiver::EventArchive::open_default() {
+        Ok(archive) => archive,
+        Err(e) => {
+            eprintln!("Failed to open event archive: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let result = match args.as_slice() {
+        ["import", path] => backtest::load_archive(path).and_then(|events| {
+            let added = archive.insert(&events)?;
+            println!("Imported {} new of {} events from {}", added, events.len(), path);
+            Ok(())
+        }),
+        ["export", path, mint @ ..] if mint.len() <= 1 => mint
+            .first()
+            .map(|mint| Pubkey::from_str(mint))
+            .transpose()
+            .map_err(Into::into)
+            .and_then(|mint| {
+                archive.events(&archiver::EventFilter {
+                    mint,
+                    ..Default::default()
+                })
+            })
+            .and_then(|events| {
+                backtest::save_archive(path, &events)?;
+                println!("Exported {} events to {}", events.len(), path);
+                Ok(())
+            }),
+        ["wallets"] => print_top_wallets(&archive, 7),
+        ["wallets", days] => days
+            .parse()
+            .map_err(Into::into)
+            .and_then(|days| print_top_wallets(&archive, days)),
+        ["creator", creator] => Pubkey::from_str(creator)
+            .map_err(Into::into)
+            .and_then(|creator| archive.creator_history(&creator))
+            .map(|history| print_launches(&history)),
+        _ => {
+            eprintln!("Usage: trading-bot-rust archive");
+            eprintln!("       trading-bot-rust archive import <ARCHIVE>");
+            eprintln!("       trading-bot-rust archive export <ARCHIVE> [MINT]");
+            eprintln!("       trading-bot-rust archive wallets [DAYS]");
+            eprintln!("       trading-bot-rust archive creator <PUBKEY>");
+            std::process::exit(2);
         }
+    };
+    if let Err(e) = result {
+        eprintln!("Archive Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(feature = "telegram")]
+fn run_telegram() {
+    if let Err(e) = trading_bot_rust::telegram::run() {
+        eprintln!("Telegram Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(feature = "api")]
+fn run_api() {
+    if let Err(e) = trading_bot_rust::api::run() {
+        eprintln!("API Error: {}", e);
+        std::process::exit(1);
     }
 }
 
 fn main() {
-    println!("Starting Pump.fun Trading Bot...\n");
+    if let Err(e) = config::init() {
+        eprintln!("Config Error: {}", e);
+        std::process::exit(1);
+    }
+    // Held until exit so buffered file logs are flushed
+    let _log_guard = match logging::init() {
+        Ok(guard) => guard,
+        Err(e) => {
+            eprintln!("Logging Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    info!(
+        network = config::get().network.cluster.as_str(),
+        "Starting Pump.fun Trading Bot"
+    );
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("selftest") => return run_selftest(&args[1..]),
+        Some("wallets") => return run_wallets(),
+        Some("cleanup") => return run_cleanup(),
+        Some("claim") => return run_claim(&args[1..]),
+        Some("recover") => return run_recover(),
+        Some("balance") => return run_balance(),
+        Some("limit") => return run_limit(&args[1..]),
+        Some("orders") => return run_orders(&args[1..]),
+        Some("watchlist") => return run_watchlist(&args[1..]),
+        Some("inspect") => return run_inspect(&args[1..]),
+        Some("safety") => return run_safety(&args[1..]),
+        Some("flow") => return run_flow(&args[1..]),
+        Some("creators") => return run_creators(&args[1..]),
+        Some("blacklist") => return run_blacklist(&args[1..]),
+        Some("nonce") => return run_nonce(&args[1..]),
+        Some("localnet") => return run_localnet(&args[1..]),
+        Some("pnl") => return run_pnl(&args[1..]),
+        Some("journal") => return run_journal(&args[1..]),
+        Some("snipe") => return run_snipe(),
+        Some("positions") => return run_positions(),
+        Some("launch") => return run_launch(&args[1..]),
+        Some("strategies") => return run_strategies(&args[1..]),
+        Some("backtest") => return run_backtest(&args[1..]),
+        Some("archive") => return run_archive(&args[1..]),
+        Some("scanner") => return run_scanner(&args[1..]),
+        #[cfg(feature = "telegram")]
+        Some("telegram") => return run_telegram(),
+        #[cfg(feature = "api")]
+        Some("api") => return run_api(),
+        _ => {}
+    }
+
    test_trade();
+    print_fee_report();
 
     // Run sell
-    // if let Err(e) = pump_sell::run_pump_sell() {
+    // if let Err(e) = pump_sell::run_pump_sell(token_amount, mint, min_sol_output) {
     //     eprintln!("Sell Error: {}", e);
     //     std::process::exit(1);
     // }
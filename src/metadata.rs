@@ -0,0 +1,184 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use spl_token_2022::ID as TOKEN_2022_PROGRAM_ID;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::cal;
+use crate::error;
+
+// Constants
+/// Token-2022 `TokenMetadata` extension type
+const TOKEN_METADATA_EXTENSION: u16 = 19;
+const OFFCHAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+lazy_static::lazy_static! {
+    pub static ref METAPLEX_PROGRAM_ID: Pubkey = Pubkey::from_str("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s").unwrap();
+}
+
+/// Fields of the off-chain metadata JSON that `uri` points to
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct OffchainMetadata {
+    pub description: Option<String>,
+    pub image: Option<String>,
+    pub twitter: Option<String>,
+    pub telegram: Option<String>,
+    pub website: Option<String>,
+}
+
+/// Human-readable info about a mint
+#[derive(Debug, Clone)]
+pub struct TokenMetadata {
+    pub mint: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    /// Only set when requested and the JSON could be fetched
+    pub offchain: Option<OffchainMetadata>,
+}
+
+impl TokenMetadata {
+    /// `NAME ($SYMBOL)` for logs
+    pub fn display_name(&self) -> String {
+        format!("{} (${})", self.name, self.symbol)
+    }
+}
+
+/// Derive the Metaplex metadata PDA for a mint
+pub fn get_metadata_pda(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"metadata", METAPLEX_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &METAPLEX_PROGRAM_ID,
+    )
+}
+
+/// Read a borsh string (u32 length prefix) at `offset`, advancing it.
+/// Metaplex pads fixed-size fields with NULs, which are trimmed.
+fn read_string(data: &[u8], offset: &mut usize) -> Result<String> {
+    let len_bytes = data
+        .get(*offset..*offset + 4)
+        .ok_or_else(|| anyhow!("Metadata truncated at offset {}", offset))?;
+    let len = u32::from_le_bytes(len_bytes.try_into().unwrap()) as usize;
+    *offset += 4;
+    let bytes = data
+        .get(*offset..*offset + len)
+        .ok_or_else(|| anyhow!("Metadata string of {} bytes is truncated", len))?;
+    *offset += len;
+    Ok(String::from_utf8_lossy(bytes)
+        .trim_end_matches('\0')
+        .to_string())
+}
+
+/// Parse name, symbol and uri from a Metaplex metadata account
+/// Layout: 1 (key) + 32 (update_authority) + 32 (mint) + name + symbol + uri
+pub fn parse_metaplex_metadata(mint: &Pubkey, data: &[u8]) -> Result<TokenMetadata> {
+    let mut offset = 65;
+    Ok(TokenMetadata {
+        mint: *mint,
+        name: read_string(data, &mut offset)?,
+        symbol: read_string(data, &mut offset)?,
+        uri: read_string(data, &mut offset)?,
+        offchain: None,
+    })
+}
+
+/// Parse the `TokenMetadata` extension of a Token-2022 mint account
+/// Value layout: 32 (update_authority) + 32 (mint) + name + symbol + uri + additional_metadata
+pub fn parse_token_2022_metadata(mint: &Pubkey, data: &[u8]) -> Result<TokenMetadata> {
+    let (_, value) = cal::mint_extension_entries(data)?
+        .into_iter()
+        .find(|(extension_type, _)| *extension_type == TOKEN_METADATA_EXTENSION)
+        .ok_or_else(|| anyhow!("Mint {} has no token metadata extension", mint))?;
+    let mut offset = 64;
+    Ok(TokenMetadata {
+        mint: *mint,
+        name: read_string(value, &mut offset)?,
+        symbol: read_string(value, &mut offset)?,
+        uri: read_string(value, &mut offset)?,
+        offchain: None,
+    })
+}
+
+/// Fetch the off-chain metadata JSON at `uri`
+pub fn fetch_offchain_metadata(uri: &str) -> Result<OffchainMetadata> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(OFFCHAIN_TIMEOUT)
+        .build()?;
+    Ok(client.get(uri).send()?.error_for_status()?.json()?)
+}
+
+/// Resolve the on-chain metadata of `mint` from its Token-2022 extension or
+/// Metaplex PDA, optionally fetching the off-chain JSON for image and socials
+pub fn fetch_metadata(
+    rpc: &RpcClient,
+    mint: &Pubkey,
+    include_offchain: bool,
+) -> error::Result<TokenMetadata> {
+    let mint_account = cal::fetch_account(rpc, mint)?;
+    let embedded = (mint_account.owner == TOKEN_2022_PROGRAM_ID)
+        .then(|| parse_token_2022_metadata(mint, &mint_account.data).ok())
+        .flatten();
+    let mut metadata = match embedded {
+        Some(metadata) => metadata,
+        None => {
+            let account = cal::fetch_account(rpc, &get_metadata_pda(mint).0)?;
+            parse_metaplex_metadata(mint, &account.data)?
+        }
+    };
+
+    if include_offchain && !metadata.uri.is_empty() {
+        match fetch_offchain_metadata(&metadata.uri) {
+            Ok(offchain) => metadata.offchain = Some(offchain),
+            Err(e) => warn!(%mint, error = %e, "Failed to fetch metadata JSON"),
+        }
+    }
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn borsh_string(s: &str, padded_len: usize) -> Vec<u8> {
+        let mut bytes = s.as_bytes().to_vec();
+        bytes.resize(padded_len.max(s.len()), 0);
+        let mut out = (bytes.len() as u32).to_le_bytes().to_vec();
+        out.extend(bytes);
+        out
+    }
+
+    #[test]
+    fn test_parse_metaplex_and_token_2022_metadata() {
+        let mint = Pubkey::new_unique();
+        let fields = [
+            borsh_string("Pepe", 32),
+            borsh_string("PEPE", 10),
+            borsh_string("https://ipfs.io/ipfs/x", 200),
+        ]
+        .concat();
+
+        let mut metaplex = vec![4u8; 65];
+        metaplex.extend(&fields);
+        let metadata = parse_metaplex_metadata(&mint, &metaplex).unwrap();
+        assert_eq!(metadata.display_name(), "Pepe ($PEPE)");
+        assert_eq!(metadata.uri, "https://ipfs.io/ipfs/x");
+
+        let mut token_2022 = vec![0u8; cal::TOKEN_2022_EXTENSIONS_OFFSET];
+        // An unrelated extension first, then the metadata
+        token_2022.extend(1u16.to_le_bytes());
+        token_2022.extend(2u16.to_le_bytes());
+        token_2022.extend([0, 0]);
+        token_2022.extend(TOKEN_METADATA_EXTENSION.to_le_bytes());
+        token_2022.extend(((64 + fields.len()) as u16).to_le_bytes());
+        token_2022.extend([0u8; 64]);
+        token_2022.extend(&fields);
+        let metadata = parse_token_2022_metadata(&mint, &token_2022).unwrap();
+        assert_eq!(metadata.symbol, "PEPE");
+
+        assert!(parse_token_2022_metadata(&mint, &token_2022[..200]).is_err());
+    }
+}
@@ -0,0 +1,417 @@
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection};
+use solana_client::{
+    rpc_client::RpcClient, rpc_response::RpcConfirmedTransactionStatusWithSignature,
+};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, UiCompiledInstruction,
+    UiInstruction, UiMessage,
+};
+use std::str::FromStr;
+use tracing::{info, warn};
+
+use crate::portfolio::now_unix;
+use crate::pump_idl::{MIGRATE_DISCRIMINATOR, PUMP_PROGRAM_ID};
+use crate::{cal, config, pumpswap, signatures, tx_fees};
+
+// Constants
+/// Most recent bonding curve transactions searched for the migration. Trades sent after the
+/// curve completed fail, but they still land on it.
+const MIGRATION_LOOKBACK: usize = 50;
+
+/// A token graduating from the bonding curve to its PumpSwap pool
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationRecord {
+    pub mint: Pubkey,
+    pub bonding_curve: Pubkey,
+    /// PumpSwap pool the liquidity moved to (None if not created yet)
+    pub pool: Option<Pubkey>,
+    /// Slot of the migration transaction, if it could be found
+    pub migration_slot: Option<u64>,
+    pub signature: Option<String>,
+    /// Block time of the migration transaction (unix seconds)
+    pub migrated_at: Option<i64>,
+    /// When the bot noticed the migration (unix seconds)
+    pub detected_at: i64,
+}
+
+impl MigrationRecord {
+    /// Seconds between the migration and `now`, if the block time is known
+    pub fn age_secs(&self, now: i64) -> Option<i64> {
+        self.migrated_at.map(|t| now - t)
+    }
+}
+
+/// Whether a fetched transaction calls pump.fun's `migrate`, directly or through a CPI
+fn calls_migrate(transaction: &EncodedConfirmedTransactionWithStatusMeta) -> bool {
+    let keys = tx_fees::account_keys(transaction);
+    let mut instructions: Vec<&UiCompiledInstruction> = Vec::new();
+    if let EncodedTransaction::Json(ui) = &transaction.transaction.transaction {
+        if let UiMessage::Raw(message) = &ui.message {
+            instructions.extend(&message.instructions);
+        }
+    }
+    if let Some(meta) = &transaction.transaction.meta {
+        let inner: Option<&Vec<_>> = meta.inner_instructions.as_ref().into();
+        instructions.extend(
+            inner
+                .into_iter()
+                .flatten()
+                .flat_map(|set| &set.instructions)
+                .filter_map(|ix| match ix {
+                    UiInstruction::Compiled(ix) => Some(ix),
+                    _ => None,
+                }),
+        );
+    }
+    instructions.iter().any(|ix| {
+        keys.get(ix.program_id_index as usize) == Some(&*PUMP_PROGRAM_ID)
+            && bs58::decode(&ix.data)
+                .into_vec()
+                .is_ok_and(|data| data.starts_with(&MIGRATE_DISCRIMINATOR))
+    })
+}
+
+/// The successful transaction that migrated a completed curve, among its recent history
+fn find_migration_transaction(
+    rpc: &RpcClient,
+    bonding_curve: &Pubkey,
+) -> Result<Option<RpcConfirmedTransactionStatusWithSignature>> {
+    let history = signatures::recent(rpc, bonding_curve, MIGRATION_LOOKBACK)?;
+    for status in history.into_iter().filter(|s| s.err.is_none()) {
+        let signature = Signature::from_str(&status.signature)?;
+        if calls_migrate(&tx_fees::fetch_transaction(rpc, &signature)?) {
+            return Ok(Some(status));
+        }
+    }
+    Ok(None)
+}
+
+/// Check on-chain whether `mint` has graduated. Returns None while it still trades on the curve.
+pub fn detect_migration(rpc: &RpcClient, mint: &Pubkey) -> Result<Option<MigrationRecord>> {
+    let (bonding_curve, _) = cal::get_bonding_curve_pda(mint);
+    let curve = cal::fetch_bonding_curve(rpc, mint)?;
+    if !curve.complete {
+        return Ok(None);
+    }
+
+    let pool = pumpswap::fetch_pool(rpc, mint)
+        .ok()
+        .map(|(address, _)| address);
+    let migration_tx = find_migration_transaction(rpc, &bonding_curve).unwrap_or_else(|e| {
+        warn!(%mint, error = %e, "Failed to look up migration transaction");
+        None
+    });
+
+    Ok(Some(MigrationRecord {
+        mint: *mint,
+        bonding_curve,
+        pool,
+        migration_slot: migration_tx.as_ref().map(|tx| tx.slot),
+        signature: migration_tx.as_ref().map(|tx| tx.signature.clone()),
+        migrated_at: migration_tx.and_then(|tx| tx.block_time),
+        detected_at: now_unix(),
+    }))
+}
+
+/// Persistent history of graduations for mints the bot has traded or watched
+pub struct MigrationTracker {
+    conn: Connection,
+}
+
+impl MigrationTracker {
+    /// Open (or create) the migration tables in the database at `path`
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)
+            .map_err(|e| anyhow!("Failed to open migration database {}: {}", path, e))?;
+        Self::init(conn)
+    }
+
+    /// Open the tracker in the portfolio database
+    pub fn open_default() -> Result<Self> {
+        Self::open(&config::get().storage.db_path)
+    }
+
+    /// Open a throwaway in-memory tracker
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS watched_mints (
+                mint TEXT PRIMARY KEY,
+                first_seen INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS migrations (
+                mint TEXT PRIMARY KEY,
+                bonding_curve TEXT NOT NULL,
+                pool TEXT,
+                migration_slot INTEGER,
+                signature TEXT,
+                migrated_at INTEGER,
+                detected_at INTEGER NOT NULL
+            );",
+        )?;
+        Ok(Self { conn })
+    }
+
+    /// Start tracking `mint` so `poll_watched` checks it for graduation
+    pub fn watch(&self, mint: &Pubkey) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO watched_mints (mint, first_seen) VALUES (?1, ?2)",
+            params![mint.to_string(), now_unix()],
+        )?;
+        Ok(())
+    }
+
+    /// Record a migration. The first record for a mint is kept.
+    pub fn record_migration(&self, record: &MigrationRecord) -> Result<()> {
+        self.watch(&record.mint)?;
+        self.conn.execute(
+            "INSERT OR IGNORE INTO migrations
+                (mint, bonding_curve, pool, migration_slot, signature, migrated_at, detected_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                record.mint.to_string(),
+                record.bonding_curve.to_string(),
+                record.pool.map(|p| p.to_string()),
+                record.migration_slot.map(|s| s as i64),
+                record.signature,
+                record.migrated_at,
+                record.detected_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Get the migration of `mint`, if it has graduated
+    pub fn get_migration(&self, mint: &Pubkey) -> Result<Option<MigrationRecord>> {
+        Ok(self
+            .query_migrations("WHERE mint = ?1", params![mint.to_string()])?
+            .into_iter()
+            .next())
+    }
+
+    pub fn is_migrated(&self, mint: &Pubkey) -> Result<bool> {
+        Ok(self.get_migration(mint)?.is_some())
+    }
+
+    /// Migrations detected at or after `since` (unix seconds), oldest first
+    pub fn get_migrations(&self, since: Option<i64>) -> Result<Vec<MigrationRecord>> {
+        self.query_migrations("WHERE (?1 IS NULL OR detected_at >= ?1)", params![since])
+    }
+
+    /// Watched mints that have not graduated yet
+    pub fn pending_mints(&self) -> Result<Vec<Pubkey>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT mint FROM watched_mints
+             WHERE mint NOT IN (SELECT mint FROM migrations) ORDER BY first_seen",
+        )?;
+        let mints = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        mints
+            .iter()
+            .map(|m| Pubkey::from_str(m).map_err(|e| anyhow!("Invalid mint in migrations: {}", e)))
+            .collect()
+    }
+
+    /// Check one mint on-chain and record its migration if it has graduated
+    pub fn check(&self, rpc: &RpcClient, mint: &Pubkey) -> Result<Option<MigrationRecord>> {
+        if let Some(record) = self.get_migration(mint)? {
+            return Ok(Some(record));
+        }
+        self.watch(mint)?;
+        let record = detect_migration(rpc, mint)?;
+        if let Some(record) = &record {
+            self.record_migration(record)?;
+        }
+        Ok(record)
+    }
+
+    /// Check every pending watched mint. Returns the newly detected migrations.
+    pub fn poll_watched(&self, rpc: &RpcClient) -> Result<Vec<MigrationRecord>> {
+        let mut migrated = Vec::new();
+        for mint in self.pending_mints()? {
+            match detect_migration(rpc, &mint) {
+                Ok(Some(record)) => {
+                    info!(
+                        %mint,
+                        pool = ?record.pool,
+                        slot = ?record.migration_slot,
+                        "Mint migrated"
+                    );
+                    self.record_migration(&record)?;
+                    migrated.push(record);
+                }
+                Ok(None) => {}
+                Err(e) => warn!(%mint, error = %e, "Failed to check migration"),
+            }
+        }
+        Ok(migrated)
+    }
+
+    fn query_migrations(
+        &self,
+        filter: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<MigrationRecord>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT mint, bonding_curve, pool, migration_slot, signature, migrated_at, detected_at
+             FROM migrations {} ORDER BY detected_at, mint",
+            filter
+        ))?;
+        let rows = stmt
+            .query_map(params, |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<i64>>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, Option<i64>>(5)?,
+                    row.get::<_, i64>(6)?,
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(
+                |(
+                    mint,
+                    bonding_curve,
+                    pool,
+                    migration_slot,
+                    signature,
+                    migrated_at,
+                    detected_at,
+                )| {
+                    Ok(MigrationRecord {
+                        mint: Pubkey::from_str(&mint)?,
+                        bonding_curve: Pubkey::from_str(&bonding_curve)?,
+                        pool: pool.map(|p| Pubkey::from_str(&p)).transpose()?,
+                        migration_slot: migration_slot.map(|s| s as u64),
+                        signature,
+                        migrated_at,
+                        detected_at,
+                    })
+                },
+            )
+            .collect()
+    }
+}
+
+/// Record `mint` as touched so its graduation gets tracked. Errors are logged, not returned.
+pub fn watch_mint(mint: &Pubkey) {
+    if let Err(e) = MigrationTracker::open_default().and_then(|t| t.watch(mint)) {
+        warn!(%mint, error = %e, "Failed to watch mint for migration");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_until_recorded() {
+        let tracker = MigrationTracker::open_in_memory().unwrap();
+        let mint = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        tracker.watch(&mint).unwrap();
+        tracker.watch(&other).unwrap();
+        assert_eq!(tracker.pending_mints().unwrap().len(), 2);
+
+        let record = MigrationRecord {
+            mint,
+            bonding_curve: Pubkey::new_unique(),
+            pool: Some(Pubkey::new_unique()),
+            migration_slot: Some(300_000_000),
+            signature: Some("sig".to_string()),
+            migrated_at: Some(1_700_000_000),
+            detected_at: 1_700_000_010,
+        };
+        tracker.record_migration(&record).unwrap();
+
+        assert_eq!(tracker.pending_mints().unwrap(), vec![other]);
+        assert_eq!(tracker.get_migration(&mint).unwrap(), Some(record.clone()));
+        assert!(tracker
+            .get_migrations(Some(1_700_000_011))
+            .unwrap()
+            .is_empty());
+        assert_eq!(record.age_secs(1_700_000_060), Some(60));
+    }
+
+    fn transaction(
+        keys: &[Pubkey],
+        instructions: serde_json::Value,
+        inner: serde_json::Value,
+    ) -> EncodedConfirmedTransactionWithStatusMeta {
+        serde_json::from_value(serde_json::json!({
+            "slot": 1,
+            "blockTime": 1_700_000_000,
+            "transaction": {
+                "signatures": [],
+                "message": {
+                    "header": {
+                        "numRequiredSignatures": 1,
+                        "numReadonlySignedAccounts": 0,
+                        "numReadonlyUnsignedAccounts": 1,
+                    },
+                    "accountKeys": keys.iter().map(Pubkey::to_string).collect::<Vec<_>>(),
+                    "recentBlockhash": Pubkey::default().to_string(),
+                    "instructions": instructions,
+                },
+            },
+            "meta": {
+                "err": null,
+                "status": { "Ok": null },
+                "fee": 5_000,
+                "preBalances": [],
+                "postBalances": [],
+                "innerInstructions": [{ "index": 0, "instructions": inner }],
+                "logMessages": [],
+                "preTokenBalances": [],
+                "postTokenBalances": [],
+                "rewards": [],
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_calls_migrate() {
+        let keys = [Pubkey::new_unique(), *PUMP_PROGRAM_ID, Pubkey::new_unique()];
+        let call = |program: usize, discriminator: &[u8]| {
+            serde_json::json!([{
+                "programIdIndex": program,
+                "accounts": [],
+                "data": bs58::encode(discriminator).into_string(),
+            }])
+        };
+        let none = serde_json::json!([]);
+
+        assert!(calls_migrate(&transaction(
+            &keys,
+            call(1, &MIGRATE_DISCRIMINATOR),
+            none.clone()
+        )));
+        assert!(calls_migrate(&transaction(
+            &keys,
+            call(2, &[0; 8]),
+            call(1, &MIGRATE_DISCRIMINATOR)
+        )));
+        // A trade on the completed curve, and a migrate discriminator on another program
+        assert!(!calls_migrate(&transaction(
+            &keys,
+            call(1, &crate::pump_idl::SELL_DISCRIMINATOR),
+            none.clone()
+        )));
+        assert!(!calls_migrate(&transaction(
+            &keys,
+            call(2, &MIGRATE_DISCRIMINATOR),
+            none
+        )));
+    }
+}
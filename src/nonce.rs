@@ -0,0 +1,227 @@
+use anyhow::{anyhow, Result};
+use solana_client::{nonce_utils, rpc_client::RpcClient, rpc_config::RpcSendTransactionConfig};
+#[allow(deprecated)]
+use solana_sdk::{
+    address_lookup_table::AddressLookupTableAccount,
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    instruction::Instruction,
+    nonce::State,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+    system_instruction::{self, SystemInstruction},
+    system_program,
+    transaction::{Transaction, VersionedTransaction},
+};
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use std::str::FromStr;
+use tracing::info;
+
+use crate::{alt, config, pump_buy};
+
+/// A durable nonce account and the nonce transactions are currently signed against.
+/// Unlike a blockhash the nonce never expires; it only changes when a transaction
+/// using it lands (or the authority advances it).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DurableNonce {
+    pub account: Pubkey,
+    pub authority: Pubkey,
+    pub nonce: Hash,
+}
+
+impl DurableNonce {
+    /// Read the current nonce stored in `account`
+    pub fn fetch(rpc: &RpcClient, account: &Pubkey) -> Result<Self> {
+        let data =
+            nonce_utils::get_account_with_commitment(rpc, account, CommitmentConfig::confirmed())
+                .and_then(|a| nonce_utils::data_from_account(&a))
+                .map_err(|e| anyhow!("Failed to read nonce account {}: {}", account, e))?;
+        Ok(Self {
+            account: *account,
+            authority: data.authority,
+            nonce: data.blockhash(),
+        })
+    }
+
+    /// The nonce account in `trading.nonce_account`, if one is set
+    pub fn configured(rpc: &RpcClient) -> Result<Option<Self>> {
+        let account = &config::get().trading.nonce_account;
+        if account.is_empty() {
+            return Ok(None);
+        }
+        Self::fetch(rpc, &Pubkey::from_str(account)?).map(Some)
+    }
+}
+
+/// The nonce account `transaction` was signed against, None when it was signed against a
+/// recent blockhash. The runtime requires the advance-nonce instruction to come first.
+pub fn nonce_account(transaction: &VersionedTransaction) -> Option<Pubkey> {
+    let message = &transaction.message;
+    let keys = message.static_account_keys();
+    let first = message.instructions().first()?;
+    let advances = keys.get(first.program_id_index as usize) == Some(&system_program::ID)
+        && bincode::deserialize::<SystemInstruction>(&first.data)
+            .is_ok_and(|ix| ix == SystemInstruction::AdvanceNonceAccount);
+    if !advances {
+        return None;
+    }
+    keys.get(*first.accounts.first()? as usize).copied()
+}
+
+/// Create a nonce account at `nonce_account`'s address, funded for rent by `payer`
+/// and with `payer` as its authority
+pub fn create_nonce_account(
+    rpc: &RpcClient,
+    payer: &dyn Signer,
+    nonce_account: &Keypair,
+) -> Result<Signature> {
+    let rent = rpc.get_minimum_balance_for_rent_exemption(State::size())?;
+    let instructions = system_instruction::create_nonce_account(
+        &payer.pubkey(),
+        &nonce_account.pubkey(),
+        &payer.pubkey(),
+        rent,
+    );
+    let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
+    let signers: [&dyn Signer; 2] = [payer, nonce_account];
+    transaction
+        .try_sign(&signers, rpc.get_latest_blockhash()?)
+        .map_err(|e| anyhow!("Failed to sign nonce account creation: {}", e))?;
+    let signature = rpc
+        .send_and_confirm_transaction(&transaction)
+        .map_err(|e| anyhow!("Failed to create nonce account: {}", e))?;
+    info!(nonce_account = %nonce_account.pubkey(), %signature, "Created nonce account");
+    Ok(signature)
+}
+
+/// Sign `instructions` against `nonce` instead of a recent blockhash, with the
+/// advance-nonce instruction the runtime requires first. `payer` must be the nonce authority.
+pub fn build_nonce_transaction(
+    instructions: &[Instruction],
+    payer: &dyn Signer,
+    lookup_tables: &[AddressLookupTableAccount],
+    nonce: &DurableNonce,
+) -> Result<VersionedTransaction> {
+    if nonce.authority != payer.pubkey() {
+        return Err(anyhow!(
+            "Nonce account {} is controlled by {}, not {}",
+            nonce.account,
+            nonce.authority,
+            payer.pubkey()
+        ));
+    }
+    let instructions: Vec<Instruction> = std::iter::once(
+        system_instruction::advance_nonce_account(&nonce.account, &nonce.authority),
+    )
+    .chain(instructions.iter().cloned())
+    .collect();
+    alt::build_versioned_transaction(&instructions, payer, lookup_tables, nonce.nonce)
+}
+
+/// A bonding curve buy signed against a durable nonce, so it can be prepared as soon
+/// as a launch is seen and fired whenever the caller decides, without re-signing
+pub struct PresignedBuy {
+    pub mint: Pubkey,
+    pub token_amount: u64,
+    pub max_sol_cost: u64,
+    nonce: DurableNonce,
+    transaction: VersionedTransaction,
+}
+
+impl PresignedBuy {
+    /// Sign a buy of `token_amount` tokens of `mint` launched by `creator`. Nothing is
+    /// fetched, so this works before the launch itself has confirmed.
+    pub fn prepare(
+        user: &dyn Signer,
+        mint: &Pubkey,
+        creator: &Pubkey,
+        token_program: &Pubkey,
+        token_amount: u64,
+        max_sol_cost: u64,
+        nonce: DurableNonce,
+    ) -> Result<Self> {
+        let mut instructions = config::get().fees.compute_budget_instructions();
+        instructions.push(create_associated_token_account_idempotent(
+            &user.pubkey(),
+            &user.pubkey(),
+            mint,
+            token_program,
+        ));
+        instructions.push(pump_buy::buy_instruction(
+            &user.pubkey(),
+            mint,
+            creator,
+            token_program,
+            token_amount,
+            max_sol_cost,
+        ));
+        let transaction = build_nonce_transaction(&instructions, user, &[], &nonce)?;
+        Ok(Self {
+            mint: *mint,
+            token_amount,
+            max_sol_cost,
+            nonce,
+            transaction,
+        })
+    }
+
+    pub fn signature(&self) -> Signature {
+        self.transaction.signatures[0]
+    }
+
+    /// Whether the nonce is still the one the buy was signed against. It stops being
+    /// valid once this or any other transaction using the nonce lands.
+    pub fn is_valid(&self, rpc: &RpcClient) -> Result<bool> {
+        Ok(DurableNonce::fetch(rpc, &self.nonce.account)?.nonce == self.nonce.nonce)
+    }
+
+    /// Send the pre-signed buy immediately, skipping preflight
+    pub fn fire(&self, rpc: &RpcClient) -> Result<Signature> {
+        rpc.send_transaction_with_config(
+            &self.transaction,
+            RpcSendTransactionConfig {
+                skip_preflight: true,
+                ..Default::default()
+            },
+        )
+        .map_err(|e| anyhow!("Failed to fire pre-signed buy for {}: {}", self.mint, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nonce_transaction_advances_nonce_first() {
+        let payer = Keypair::new();
+        let nonce = DurableNonce {
+            account: Pubkey::new_unique(),
+            authority: payer.pubkey(),
+            nonce: Hash::new_unique(),
+        };
+        let memo = Instruction::new_with_bytes(Pubkey::new_unique(), b"buy", Vec::new());
+
+        let transaction = build_nonce_transaction(&[memo], &payer, &[], &nonce).unwrap();
+        let message = &transaction.message;
+        assert_eq!(*message.recent_blockhash(), nonce.nonce);
+        let first = &message.instructions()[0];
+        assert_eq!(
+            message.static_account_keys()[first.program_id_index as usize],
+            solana_sdk::system_program::ID
+        );
+        assert_eq!(nonce_account(&transaction), Some(nonce.account));
+        assert_eq!(
+            message.static_account_keys()[first.accounts[0] as usize],
+            nonce.account
+        );
+        assert_eq!(message.instructions().len(), 2);
+
+        let other = DurableNonce {
+            authority: Pubkey::new_unique(),
+            ..nonce
+        };
+        assert!(build_nonce_transaction(&[], &payer, &[], &other).is_err());
+    }
+}
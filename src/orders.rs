@@ -0,0 +1,262 @@
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use thiserror::Error;
+
+use crate::address_book;
+use crate::limit_orders::{LimitOrder, LimitOrderBook, OrderKind, OrderStatus, NO_PRICE_LIMIT};
+use crate::portfolio::Side;
+
+/// What a pending order is for, as shown to users
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderType {
+    LimitBuy,
+    LimitSell,
+    StopBuy,
+    /// A sell above the price, usually one leg of a TP/SL bracket
+    TakeProfit,
+    StopLoss,
+    DcaLeg,
+    /// A take-profit tranche of a position's exit ladder, sold by the exit engine
+    LadderTranche,
+}
+
+impl OrderType {
+    pub fn of(order: &LimitOrder) -> Self {
+        match (order.side, order.kind) {
+            (_, OrderKind::Dca) => OrderType::DcaLeg,
+            (_, OrderKind::Ladder) => OrderType::LadderTranche,
+            (Side::Buy, OrderKind::Limit) => OrderType::LimitBuy,
+            (Side::Buy, OrderKind::Stop) => OrderType::StopBuy,
+            (Side::Sell, OrderKind::Stop) => OrderType::StopLoss,
+            (Side::Sell, OrderKind::Limit) if order.linked_id.is_some() => OrderType::TakeProfit,
+            (Side::Sell, OrderKind::Limit) => OrderType::LimitSell,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderType::LimitBuy => "limit_buy",
+            OrderType::LimitSell => "limit_sell",
+            OrderType::StopBuy => "stop_buy",
+            OrderType::TakeProfit => "take_profit",
+            OrderType::StopLoss => "stop_loss",
+            OrderType::DcaLeg => "dca_leg",
+            OrderType::LadderTranche => "ladder_tranche",
+        }
+    }
+}
+
+/// Why an order couldn't be cancelled or replaced
+#[derive(Debug, Error)]
+pub enum OrderError {
+    #[error("Order #{0} not found")]
+    NotFound(i64),
+
+    #[error("Order #{id} is {status:?}, not open")]
+    NotOpen { id: i64, status: OrderStatus },
+
+    #[error("{0}")]
+    Invalid(String),
+
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Orders, open ones only unless `include_closed`, optionally only those on `mint`
+pub fn list(
+    book: &LimitOrderBook,
+    mint: Option<&Pubkey>,
+    include_closed: bool,
+) -> anyhow::Result<Vec<LimitOrder>> {
+    if !include_closed {
+        return book.open_orders(mint);
+    }
+    Ok(book
+        .all_orders()?
+        .into_iter()
+        .filter(|order| mint.is_none_or(|mint| order.mint == *mint))
+        .collect())
+}
+
+/// The order `id` if it is still open
+fn open_order(book: &LimitOrderBook, id: i64) -> Result<LimitOrder, OrderError> {
+    let order = book.get(id)?.ok_or(OrderError::NotFound(id))?;
+    if order.status != OrderStatus::Open {
+        return Err(OrderError::NotOpen {
+            id,
+            status: order.status,
+        });
+    }
+    Ok(order)
+}
+
+/// Cancel open order `id`, returning it as cancelled
+pub fn cancel(book: &LimitOrderBook, id: i64) -> Result<LimitOrder, OrderError> {
+    let order = open_order(book, id)?;
+    // It may have fired in between
+    if !book.cancel(id)? {
+        return open_order(book, id);
+    }
+    Ok(LimitOrder {
+        status: OrderStatus::Cancelled,
+        ..order
+    })
+}
+
+/// Cancel open order `id` and place it again with a new `amount` (lamports for buys,
+/// tokens for sells) and/or limit price. The replacement gets a new id.
+pub fn replace(
+    book: &LimitOrderBook,
+    id: i64,
+    amount: Option<u64>,
+    limit_price: Option<f64>,
+) -> Result<LimitOrder, OrderError> {
+    if amount.is_none() && limit_price.is_none() {
+        return Err(OrderError::Invalid(
+            "Give a new amount, price or both".to_string(),
+        ));
+    }
+    if amount == Some(0) {
+        return Err(OrderError::Invalid("Amount must be positive".to_string()));
+    }
+    if let Some(price) = limit_price.filter(|p| !(*p > 0.0 && p.is_finite())) {
+        return Err(OrderError::Invalid(format!(
+            "Invalid limit price {}",
+            price
+        )));
+    }
+    if open_order(book, id)?.kind == OrderKind::Ladder {
+        return Err(OrderError::Invalid(format!(
+            "Order #{} is a ladder tranche, which follows the position; cancel it instead",
+            id
+        )));
+    }
+    match book.replace(id, amount, limit_price)? {
+        Some(replacement) => Ok(replacement),
+        // It fired or was cancelled in between
+        None => open_order(book, id),
+    }
+}
+
+/// The limit price for display: "any" for DCA legs without one
+fn price_label(order: &LimitOrder) -> String {
+    if order.limit_price == NO_PRICE_LIMIT {
+        "any price".to_string()
+    } else {
+        format!("{:.9} lamports/token", order.limit_price)
+    }
+}
+
+/// One line describing `order` for the CLI
+pub fn describe(order: &LimitOrder) -> String {
+    let amount = match order.side {
+        Side::Buy => format!("{} lamports", order.amount),
+        Side::Sell => format!("{} tokens", order.amount),
+    };
+    let due = order
+        .not_before
+        .map_or(String::new(), |at| format!(" from {}", at));
+    let linked = order
+        .linked_id
+        .map_or(String::new(), |id| format!(" (OCO #{})", id));
+    format!(
+        "#{:<5} {:<10} {:<12} {} of {} at {}{}{} {}",
+        order.id,
+        order.status.as_str(),
+        OrderType::of(order).as_str(),
+        amount,
+        address_book::label(&order.mint),
+        price_label(order),
+        due,
+        linked,
+        order.error.as_deref().unwrap_or_default()
+    )
+    .trim_end()
+    .to_string()
+}
+
+/// `order` as JSON for the API. A DCA leg without a price limit has a null `limit_price`.
+pub fn to_json(order: &LimitOrder) -> Value {
+    json!({
+        "id": order.id,
+        "type": OrderType::of(order).as_str(),
+        "mint": order.mint.to_string(),
+        "label": address_book::label(&order.mint),
+        "side": order.side.as_str(),
+        "amount": order.amount,
+        "limit_price": (order.limit_price != NO_PRICE_LIMIT).then_some(order.limit_price),
+        "slippage_bps": order.slippage_bps,
+        "status": order.status.as_str(),
+        "created_at": order.created_at,
+        "not_before": order.not_before,
+        "triggered_at": order.triggered_at,
+        "linked_id": order.linked_id,
+        "error": order.error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_replace_keeps_brackets_linked() {
+        let book = LimitOrderBook::open_in_memory().unwrap();
+        let mint = Pubkey::new_unique();
+        let bracket = book
+            .place_bracket(mint, 1_000_000, Some(0.05), Some(0.02), 500)
+            .unwrap();
+        let (take_profit, stop_loss) = (&bracket[0], &bracket[1]);
+        assert_eq!(OrderType::of(take_profit), OrderType::TakeProfit);
+        assert_eq!(OrderType::of(stop_loss), OrderType::StopLoss);
+
+        let moved = replace(&book, stop_loss.id, None, Some(0.03)).unwrap();
+        assert_ne!(moved.id, stop_loss.id);
+        assert_eq!((moved.amount, moved.limit_price), (1_000_000, 0.03));
+        assert_eq!(moved.linked_id, Some(take_profit.id));
+        let take_profit = book.get(take_profit.id).unwrap().unwrap();
+        assert_eq!(take_profit.linked_id, Some(moved.id));
+        assert!(matches!(
+            replace(&book, stop_loss.id, None, Some(0.03)),
+            Err(OrderError::NotOpen { .. })
+        ));
+        assert!(matches!(
+            replace(&book, moved.id, Some(0), None),
+            Err(OrderError::Invalid(_))
+        ));
+
+        // Firing the replacement still cancels the take-profit
+        assert!(book.claim(moved.id).unwrap());
+        assert_eq!(
+            book.get(take_profit.id).unwrap().unwrap().status,
+            OrderStatus::Cancelled
+        );
+        assert!(matches!(cancel(&book, 999), Err(OrderError::NotFound(999))));
+        assert_eq!(list(&book, Some(&mint), true).unwrap().len(), 3);
+        assert!(list(&book, Some(&mint), false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_dca_legs_fall_due_in_turn() {
+        let book = LimitOrderBook::open_in_memory().unwrap();
+        let mint = Pubkey::new_unique();
+        let legs = book
+            .place_dca(mint, 1_000_000_001, 3, 600, NO_PRICE_LIMIT, 500)
+            .unwrap();
+        assert_eq!(legs.len(), 3);
+        assert_eq!(legs.iter().map(|l| l.amount).sum::<u64>(), 1_000_000_001);
+        assert!(legs.iter().all(|l| OrderType::of(l) == OrderType::DcaLeg));
+
+        let start = legs[0].not_before.unwrap();
+        assert!(legs[0].is_due(start) && !legs[1].is_due(start));
+        assert!(legs[1].is_due(start + 600) && !legs[2].is_due(start + 600));
+        assert!(legs[0].is_triggered(1e9));
+        assert_eq!(book.due_mints(start).unwrap().len(), 1);
+        assert!(to_json(&legs[0])["limit_price"].is_null());
+
+        let capped = cancel(&book, legs[2].id).unwrap();
+        assert_eq!(capped.status, OrderStatus::Cancelled);
+        assert!(book.place_dca(mint, 1_000, 0, 600, 0.03, 500).is_err());
+        assert!(book.place_dca(mint, 2, 3, 600, 0.03, 500).is_err());
+    }
+}
@@ -0,0 +1,382 @@
+use anyhow::{anyhow, Result};
+use serde_json::json;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::address_book;
+use crate::cal;
+use crate::portfolio::{self, now_unix, Fill, MintCosts, Portfolio, Side};
+
+/// PnL of one mint. Unrealized PnL is None when the position is closed or couldn't
+/// be marked (e.g. it migrated off the curve).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionPnl {
+    pub mint: Pubkey,
+    pub token_amount: u64,
+    pub cost_basis_lamports: u64,
+    /// What selling the remaining tokens on the curve would return now
+    pub value_lamports: Option<u64>,
+    pub realized_pnl_lamports: i64,
+    pub unrealized_pnl_lamports: Option<i64>,
+    /// pump.fun / pool fees, already counted in the realized and unrealized PnL
+    pub fees_paid_lamports: u64,
+    /// Network fees and Jito tips of every transaction sent for the mint, failed ones too
+    pub costs: MintCosts,
+    pub opened_at: i64,
+    pub last_fill_at: i64,
+}
+
+/// A position from its first buy until it was sold down to zero
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoundTrip {
+    pub mint: Pubkey,
+    pub opened_at: i64,
+    pub closed_at: i64,
+    pub pnl_lamports: i64,
+}
+
+impl RoundTrip {
+    pub fn hold_secs(&self) -> i64 {
+        self.closed_at - self.opened_at
+    }
+}
+
+/// Realized and unrealized PnL across the portfolio
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PnlReport {
+    pub positions: Vec<PositionPnl>,
+    pub round_trips: Vec<RoundTrip>,
+    /// Unix seconds
+    pub generated_at: i64,
+}
+
+/// Closed round trips in `fills` (one mint, execution order), using average cost basis
+fn round_trips(mint: &Pubkey, fills: &[Fill]) -> Vec<RoundTrip> {
+    let mut trips = Vec::new();
+    let (mut tokens, mut cost, mut pnl, mut opened_at) = (0u64, 0u64, 0i64, 0i64);
+    for fill in fills {
+        match fill.side {
+            Side::Buy => {
+                if tokens == 0 {
+                    opened_at = fill.timestamp;
+                    pnl = 0;
+                }
+                tokens += fill.token_amount;
+                cost += fill.sol_amount;
+            }
+            Side::Sell => {
+                if tokens == 0 {
+                    continue;
+                }
+                let sold = fill.token_amount.min(tokens);
+                let cost_removed = (cost as u128 * sold as u128 / tokens as u128) as u64;
+                pnl += fill.sol_amount as i64 - cost_removed as i64;
+                cost -= cost_removed;
+                tokens -= sold;
+                if tokens == 0 {
+                    trips.push(RoundTrip {
+                        mint: *mint,
+                        opened_at,
+                        closed_at: fill.timestamp,
+                        pnl_lamports: pnl,
+                    });
+                }
+            }
+        }
+    }
+    trips
+}
+
+impl PnlReport {
+    /// Build the report from every fill, marking open positions at `values`
+    /// (lamports the remaining tokens would sell for, by mint)
+    pub fn build(fills: &[Fill], values: &HashMap<Pubkey, u64>) -> Self {
+        let mut by_mint: BTreeMap<Pubkey, Vec<Fill>> = BTreeMap::new();
+        for fill in fills {
+            by_mint.entry(fill.mint).or_default().push(fill.clone());
+        }
+
+        let mut report = PnlReport {
+            generated_at: now_unix(),
+            ..Default::default()
+        };
+        for (mint, fills) in &by_mint {
+            let Some(position) = portfolio::build_position(mint, fills) else {
+                continue;
+            };
+            let value_lamports = values.get(mint).copied().filter(|_| position.is_open());
+            report.positions.push(PositionPnl {
+                mint: *mint,
+                token_amount: position.token_amount,
+                cost_basis_lamports: position.cost_basis_lamports,
+                value_lamports,
+                realized_pnl_lamports: position.realized_pnl_lamports,
+                unrealized_pnl_lamports: value_lamports
+                    .map(|value| value as i64 - position.cost_basis_lamports as i64),
+                fees_paid_lamports: position.fees_paid_lamports,
+                costs: MintCosts::default(),
+                opened_at: position.opened_at,
+                last_fill_at: position.last_fill_at,
+            });
+            report.round_trips.extend(round_trips(mint, fills));
+        }
+        report
+    }
+
+    /// Attach the network fees and tips spent on each position
+    pub fn with_costs(mut self, costs: &HashMap<Pubkey, MintCosts>) -> Self {
+        for position in &mut self.positions {
+            position.costs = costs.get(&position.mint).copied().unwrap_or_default();
+        }
+        self
+    }
+
+    pub fn realized_pnl_lamports(&self) -> i64 {
+        self.positions.iter().map(|p| p.realized_pnl_lamports).sum()
+    }
+
+    pub fn unrealized_pnl_lamports(&self) -> i64 {
+        self.positions
+            .iter()
+            .filter_map(|p| p.unrealized_pnl_lamports)
+            .sum()
+    }
+
+    pub fn total_pnl_lamports(&self) -> i64 {
+        self.realized_pnl_lamports() + self.unrealized_pnl_lamports()
+    }
+
+    pub fn fees_paid_lamports(&self) -> u64 {
+        self.positions.iter().map(|p| p.fees_paid_lamports).sum()
+    }
+
+    pub fn network_fees_lamports(&self) -> u64 {
+        self.positions.iter().map(|p| p.costs.network_fees()).sum()
+    }
+
+    pub fn tips_lamports(&self) -> u64 {
+        self.positions.iter().map(|p| p.costs.tip_lamports).sum()
+    }
+
+    /// Total PnL after network fees and tips
+    pub fn net_pnl_lamports(&self) -> i64 {
+        self.total_pnl_lamports()
+            - self.network_fees_lamports() as i64
+            - self.tips_lamports() as i64
+    }
+
+    /// Share of closed round trips that made money, None before the first one closes
+    pub fn win_rate(&self) -> Option<f64> {
+        if self.round_trips.is_empty() {
+            return None;
+        }
+        let wins = self
+            .round_trips
+            .iter()
+            .filter(|t| t.pnl_lamports > 0)
+            .count();
+        Some(wins as f64 / self.round_trips.len() as f64)
+    }
+
+    /// Mean time from first buy to final sell over closed round trips
+    pub fn average_hold_secs(&self) -> Option<f64> {
+        if self.round_trips.is_empty() {
+            return None;
+        }
+        let total: i64 = self.round_trips.iter().map(RoundTrip::hold_secs).sum();
+        Some(total as f64 / self.round_trips.len() as f64)
+    }
+
+    /// One row per position. Unknown values are left empty.
+    pub fn to_csv(&self) -> String {
+        let optional = |value: Option<i64>| value.map_or(String::new(), |v| v.to_string());
+        let mut csv = "mint,token_amount,cost_basis_lamports,value_lamports,realized_pnl_lamports,\
+                       unrealized_pnl_lamports,fees_paid_lamports,base_fees_lamports,\
+                       priority_fees_lamports,tip_lamports,opened_at,last_fill_at\n"
+            .to_string();
+        for p in &self.positions {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                p.mint,
+                p.token_amount,
+                p.cost_basis_lamports,
+                optional(p.value_lamports.map(|v| v as i64)),
+                p.realized_pnl_lamports,
+                optional(p.unrealized_pnl_lamports),
+                p.fees_paid_lamports,
+                p.costs.base_fees,
+                p.costs.priority_fees,
+                p.costs.tip_lamports,
+                p.opened_at,
+                p.last_fill_at
+            ));
+        }
+        csv
+    }
+
+    /// Totals, per-position rows and closed round trips
+    pub fn to_json(&self) -> Result<String> {
+        let positions: Vec<_> = self
+            .positions
+            .iter()
+            .map(|p| {
+                json!({
+                    "mint": p.mint.to_string(),
+                    "token_amount": p.token_amount,
+                    "cost_basis_lamports": p.cost_basis_lamports,
+                    "value_lamports": p.value_lamports,
+                    "realized_pnl_lamports": p.realized_pnl_lamports,
+                    "unrealized_pnl_lamports": p.unrealized_pnl_lamports,
+                    "fees_paid_lamports": p.fees_paid_lamports,
+                    "base_fees_lamports": p.costs.base_fees,
+                    "priority_fees_lamports": p.costs.priority_fees,
+                    "tip_lamports": p.costs.tip_lamports,
+                    "opened_at": p.opened_at,
+                    "last_fill_at": p.last_fill_at,
+                })
+            })
+            .collect();
+        let round_trips: Vec<_> = self
+            .round_trips
+            .iter()
+            .map(|t| {
+                json!({
+                    "mint": t.mint.to_string(),
+                    "opened_at": t.opened_at,
+                    "closed_at": t.closed_at,
+                    "pnl_lamports": t.pnl_lamports,
+                })
+            })
+            .collect();
+        let report = json!({
+            "generated_at": self.generated_at,
+            "realized_pnl_lamports": self.realized_pnl_lamports(),
+            "unrealized_pnl_lamports": self.unrealized_pnl_lamports(),
+            "total_pnl_lamports": self.total_pnl_lamports(),
+            "fees_paid_lamports": self.fees_paid_lamports(),
+            "network_fees_lamports": self.network_fees_lamports(),
+            "tips_lamports": self.tips_lamports(),
+            "net_pnl_lamports": self.net_pnl_lamports(),
+            "win_rate": self.win_rate(),
+            "average_hold_secs": self.average_hold_secs(),
+            "positions": positions,
+            "round_trips": round_trips,
+        });
+        serde_json::to_string_pretty(&report).map_err(|e| anyhow!("Failed to encode PnL: {}", e))
+    }
+
+    pub fn print(&self) {
+        let sol = |lamports: i64| lamports as f64 / LAMPORTS_PER_SOL as f64;
+        println!("\n=== PnL Report ===");
+        for p in &self.positions {
+            let unrealized = match (p.token_amount, p.unrealized_pnl_lamports) {
+                (0, _) => "closed".to_string(),
+                (_, Some(pnl)) => format!("{:+.4} SOL unrealized", sol(pnl)),
+                (_, None) => "unpriced".to_string(),
+            };
+            println!(
+                "  {:<44} {:+.4} SOL realized, {}",
+                address_book::label(&p.mint),
+                sol(p.realized_pnl_lamports),
+                unrealized
+            );
+        }
+        println!("Realized:   {:+.4} SOL", sol(self.realized_pnl_lamports()));
+        println!(
+            "Unrealized: {:+.4} SOL",
+            sol(self.unrealized_pnl_lamports())
+        );
+        println!("Total:      {:+.4} SOL", sol(self.total_pnl_lamports()));
+        println!(
+            "Fees paid:  {:.4} SOL trading, {:.4} SOL network, {:.4} SOL tips",
+            sol(self.fees_paid_lamports() as i64),
+            sol(self.network_fees_lamports() as i64),
+            sol(self.tips_lamports() as i64)
+        );
+        println!("Net:        {:+.4} SOL", sol(self.net_pnl_lamports()));
+        match (self.win_rate(), self.average_hold_secs()) {
+            (Some(win_rate), Some(hold)) => println!(
+                "Win rate:   {:.1}% of {} round trips, average hold {:.0}s",
+                win_rate * 100.0,
+                self.round_trips.len(),
+                hold
+            ),
+            _ => println!("No closed round trips yet"),
+        }
+    }
+}
+
+/// Report on every fill in `portfolio`, marking open positions with live sell quotes
+pub fn generate(rpc: &RpcClient, portfolio: &Portfolio) -> Result<PnlReport> {
+    let fills = portfolio.get_fills(None)?;
+    let holdings: Vec<(Pubkey, u64)> = portfolio
+        .get_open_positions()?
+        .iter()
+        .map(|p| (p.mint, p.token_amount))
+        .collect();
+    // Migrated mints can't be marked on the curve and are left unpriced
+    let values: HashMap<Pubkey, u64> = holdings
+        .iter()
+        .zip(cal::quote_sells(rpc, &holdings))
+        .filter_map(|((mint, _), quote)| Some((*mint, quote.ok()?.output_amount)))
+        .collect();
+    Ok(PnlReport::build(&fills, &values).with_costs(&portfolio.costs_by_mint()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(mint: Pubkey, side: Side, tokens: u64, sol: u64, timestamp: i64) -> Fill {
+        Fill {
+            timestamp,
+            ..Fill::new(mint, side, tokens, sol, 1_000, String::new())
+        }
+    }
+
+    #[test]
+    fn test_report_from_fills() {
+        let (winner, loser, open) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let fills = vec![
+            fill(winner, Side::Buy, 1_000, 100_000, 10),
+            fill(winner, Side::Sell, 500, 80_000, 20),
+            fill(winner, Side::Sell, 500, 70_000, 40),
+            fill(loser, Side::Buy, 1_000, 100_000, 100),
+            fill(loser, Side::Sell, 1_000, 60_000, 110),
+            fill(open, Side::Buy, 2_000, 200_000, 200),
+        ];
+        let values = HashMap::from([(open, 250_000), (winner, 1)]);
+        let costs = HashMap::from([(
+            loser,
+            MintCosts {
+                base_fees: 10_000,
+                priority_fees: 20_000,
+                tip_lamports: 5_000,
+            },
+        )]);
+        let report = PnlReport::build(&fills, &values).with_costs(&costs);
+
+        assert_eq!(report.positions.len(), 3);
+        assert_eq!(report.realized_pnl_lamports(), 50_000 - 40_000);
+        assert_eq!(report.unrealized_pnl_lamports(), 50_000);
+        assert_eq!(report.fees_paid_lamports(), 6_000);
+        assert_eq!(report.network_fees_lamports(), 30_000);
+        assert_eq!(report.net_pnl_lamports(), 60_000 - 35_000);
+        assert_eq!(report.win_rate(), Some(0.5));
+        assert_eq!(report.average_hold_secs(), Some(20.0));
+
+        let csv = report.to_csv();
+        assert_eq!(csv.lines().count(), 4);
+        assert!(csv.contains(&format!("{},0,0,,", winner)));
+        assert!(csv.contains(",10000,20000,5000,"));
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["total_pnl_lamports"], 60_000);
+        assert_eq!(json["net_pnl_lamports"], 25_000);
+        assert_eq!(json["positions"].as_array().unwrap().len(), 3);
+    }
+}
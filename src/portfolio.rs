@@ -0,0 +1,584 @@
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::amount::{SolAmount, TokenAmount};
+use crate::config::{self, ExecutionMode};
+use crate::tx_fees::{TxCost, TxOutcome};
+
+/// Direction of an executed trade
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        }
+    }
+
+    pub(crate) fn parse(s: &str) -> Result<Self> {
+        match s {
+            "buy" => Ok(Side::Buy),
+            "sell" => Ok(Side::Sell),
+            other => Err(anyhow!("Unknown trade side in portfolio: {}", other)),
+        }
+    }
+}
+
+/// A single executed buy or sell
+#[derive(Debug, Clone)]
+pub struct Fill {
+    pub mint: Pubkey,
+    pub side: Side,
+    pub token_amount: u64,
+    /// SOL paid (buy) or received (sell) in lamports, net of fees
+    pub sol_amount: u64,
+    pub fee_lamports: u64,
+    pub signature: String,
+    /// Unix timestamp in seconds
+    pub timestamp: i64,
+}
+
+impl Fill {
+    /// Create a fill stamped with the current time
+    pub fn new(
+        mint: Pubkey,
+        side: Side,
+        token_amount: u64,
+        sol_amount: u64,
+        fee_lamports: u64,
+        signature: String,
+    ) -> Self {
+        Self {
+            mint,
+            side,
+            token_amount,
+            sol_amount,
+            fee_lamports,
+            signature,
+            timestamp: now_unix(),
+        }
+    }
+
+    /// Execution price in lamports per raw token unit
+    pub fn price(&self) -> f64 {
+        if self.token_amount == 0 {
+            return 0.0;
+        }
+        self.sol_amount as f64 / self.token_amount as f64
+    }
+}
+
+/// Aggregated state of one mint derived from its fills (average cost basis)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Position {
+    pub mint: Pubkey,
+    pub token_amount: u64,
+    /// Lamports paid for the tokens still held
+    pub cost_basis_lamports: u64,
+    pub realized_pnl_lamports: i64,
+    pub fees_paid_lamports: u64,
+    pub opened_at: i64,
+    pub last_fill_at: i64,
+    pub fill_count: usize,
+}
+
+impl Position {
+    /// Average entry price in lamports per raw token unit
+    pub fn avg_entry_price(&self) -> f64 {
+        if self.token_amount == 0 {
+            return 0.0;
+        }
+        self.cost_basis_lamports as f64 / self.token_amount as f64
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.token_amount > 0
+    }
+
+    /// Tokens still held, with pump.fun's decimals
+    pub fn tokens(&self) -> TokenAmount {
+        TokenAmount::pump(self.token_amount)
+    }
+
+    pub fn cost_basis(&self) -> SolAmount {
+        SolAmount::from_lamports(self.cost_basis_lamports)
+    }
+}
+
+/// Persistent record of every fill the bot has executed
+pub struct Portfolio {
+    conn: Connection,
+}
+
+/// Current unix time in seconds
+pub fn now_unix() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+impl Portfolio {
+    /// Open (or create) the portfolio database at `path`
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)
+            .map_err(|e| anyhow!("Failed to open portfolio database {}: {}", path, e))?;
+        Self::init(conn)
+    }
+
+    /// Open the portfolio database at the configured path, or the paper
+    /// portfolio when running in paper mode
+    pub fn open_default() -> Result<Self> {
+        let config = config::get();
+        match config.trading.mode {
+            ExecutionMode::Paper => Self::open(&config.storage.paper_db_path),
+            _ => Self::open(&config.storage.db_path),
+        }
+    }
+
+    /// Open a throwaway in-memory portfolio
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS fills (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                mint TEXT NOT NULL,
+                side TEXT NOT NULL,
+                token_amount INTEGER NOT NULL,
+                sol_amount INTEGER NOT NULL,
+                price REAL NOT NULL,
+                fee_lamports INTEGER NOT NULL,
+                signature TEXT NOT NULL UNIQUE,
+                timestamp INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS fills_mint ON fills (mint);
+            CREATE TABLE IF NOT EXISTS tx_costs (
+                signature TEXT PRIMARY KEY,
+                mint TEXT NOT NULL,
+                side TEXT NOT NULL,
+                outcome TEXT NOT NULL,
+                error TEXT,
+                base_fee INTEGER NOT NULL,
+                priority_fee INTEGER NOT NULL,
+                timestamp INTEGER NOT NULL,
+                tip_lamports INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE TABLE IF NOT EXISTS fill_prices (
+                signature TEXT PRIMARY KEY,
+                sol_usd REAL NOT NULL
+            );",
+        )?;
+        // Databases created before tips were tracked lack the column
+        if conn.prepare("SELECT tip_lamports FROM tx_costs").is_err() {
+            conn.execute(
+                "ALTER TABLE tx_costs ADD COLUMN tip_lamports INTEGER NOT NULL DEFAULT 0",
+                [],
+            )?;
+        }
+        Ok(Self { conn })
+    }
+
+    /// Record an executed fill. Re-recording the same signature is a no-op.
+    pub fn record_fill(&self, fill: &Fill) -> Result<()> {
+        self.record_fill_with_price(fill, None)
+    }
+
+    /// `record_fill`, with the SOL/USD price at fill time for the trade journal when known
+    pub fn record_fill_with_price(&self, fill: &Fill, sol_usd: Option<f64>) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO fills
+                (mint, side, token_amount, sol_amount, price, fee_lamports, signature, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                fill.mint.to_string(),
+                fill.side.as_str(),
+                fill.token_amount as i64,
+                fill.sol_amount as i64,
+                fill.price(),
+                fill.fee_lamports as i64,
+                fill.signature,
+                fill.timestamp,
+            ],
+        )?;
+        if let Some(sol_usd) = sol_usd {
+            self.record_fill_price(&fill.signature, sol_usd)?;
+        }
+        Ok(())
+    }
+
+    /// Record the SOL/USD price a fill executed at. The first price recorded is kept.
+    pub fn record_fill_price(&self, signature: &str, sol_usd: f64) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO fill_prices (signature, sol_usd) VALUES (?1, ?2)",
+            params![signature, sol_usd],
+        )?;
+        Ok(())
+    }
+
+    /// SOL/USD at fill time by signature, for the fills a price was recorded for
+    pub fn get_fill_prices(&self) -> Result<HashMap<String, f64>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT signature, sol_usd FROM fill_prices")?;
+        let prices = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(prices)
+    }
+
+    /// Get fills in execution order, optionally restricted to one mint
+    pub fn get_fills(&self, mint: Option<&Pubkey>) -> Result<Vec<Fill>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT mint, side, token_amount, sol_amount, fee_lamports, signature, timestamp
+             FROM fills WHERE (?1 IS NULL OR mint = ?1) ORDER BY timestamp, id",
+        )?;
+        let rows = stmt.query_map(params![mint.map(|m| m.to_string())], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, i64>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, i64>(6)?,
+            ))
+        })?;
+
+        let mut fills = Vec::new();
+        for row in rows {
+            let (mint, side, token_amount, sol_amount, fee_lamports, signature, timestamp) = row?;
+            fills.push(Fill {
+                mint: Pubkey::from_str(&mint)?,
+                side: Side::parse(&side)?,
+                token_amount: token_amount as u64,
+                sol_amount: sol_amount as u64,
+                fee_lamports: fee_lamports as u64,
+                signature,
+                timestamp,
+            });
+        }
+        Ok(fills)
+    }
+
+    /// Get the position for a mint, or None if it has never been traded
+    pub fn get_position(&self, mint: &Pubkey) -> Result<Option<Position>> {
+        let fills = self.get_fills(Some(mint))?;
+        Ok(build_position(mint, &fills))
+    }
+
+    /// Get every position that still holds tokens
+    pub fn get_open_positions(&self) -> Result<Vec<Position>> {
+        let mut stmt = self.conn.prepare("SELECT DISTINCT mint FROM fills ORDER BY mint")?;
+        let mints: Vec<String> = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let mut positions = Vec::new();
+        for mint in mints {
+            if let Some(position) = self.get_position(&Pubkey::from_str(&mint)?)? {
+                if position.is_open() {
+                    positions.push(position);
+                }
+            }
+        }
+        Ok(positions)
+    }
+
+    /// Look up a fill by its transaction signature
+    pub fn get_fill_by_signature(&self, signature: &str) -> Result<Option<Fill>> {
+        let mint: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT mint FROM fills WHERE signature = ?1",
+                params![signature],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        match mint {
+            Some(mint) => Ok(self
+                .get_fills(Some(&Pubkey::from_str(&mint)?))?
+                .into_iter()
+                .find(|f| f.signature == signature)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Network fees split by whether the transaction did anything useful
+#[derive(Debug, Clone, Default)]
+pub struct FeeSummary {
+    pub succeeded_count: u64,
+    pub failed_count: u64,
+    pub expired_count: u64,
+    pub success_base_fees: u64,
+    pub success_priority_fees: u64,
+    /// Jito tips paid by landed transactions
+    pub success_tips: u64,
+    /// Base fees burned by transactions that landed but failed
+    pub failed_base_fees: u64,
+    /// Priority fees burned by transactions that landed but failed
+    pub failed_priority_fees: u64,
+}
+
+impl FeeSummary {
+    /// Total lamports spent on transactions that failed
+    pub fn fees_wasted_on_failures(&self) -> u64 {
+        self.failed_base_fees + self.failed_priority_fees
+    }
+
+    /// Share of attempts that did not land successfully
+    pub fn failure_rate(&self) -> f64 {
+        let total = self.succeeded_count + self.failed_count + self.expired_count;
+        if total == 0 {
+            return 0.0;
+        }
+        (self.failed_count + self.expired_count) as f64 / total as f64
+    }
+}
+
+/// Network fees and tips spent on one mint's transactions, landed or not
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MintCosts {
+    pub base_fees: u64,
+    pub priority_fees: u64,
+    pub tip_lamports: u64,
+}
+
+impl MintCosts {
+    pub fn network_fees(&self) -> u64 {
+        self.base_fees + self.priority_fees
+    }
+
+    pub fn total(&self) -> u64 {
+        self.network_fees() + self.tip_lamports
+    }
+}
+
+impl Portfolio {
+    /// Record the network fees a sent transaction was charged
+    pub fn record_tx_cost(&self, mint: &Pubkey, side: Side, cost: &TxCost) -> Result<()> {
+        let error = match &cost.outcome {
+            TxOutcome::Failed(e) => Some(e.clone()),
+            _ => None,
+        };
+        self.conn.execute(
+            "INSERT OR REPLACE INTO tx_costs
+                (signature, mint, side, outcome, error, base_fee, priority_fee, timestamp,
+                 tip_lamports)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                cost.signature.to_string(),
+                mint.to_string(),
+                side.as_str(),
+                cost.outcome.as_str(),
+                error,
+                cost.base_fee as i64,
+                cost.priority_fee as i64,
+                now_unix(),
+                cost.tip_lamports as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Summarize network fees across successful, failed and expired transactions
+    pub fn fee_summary(&self) -> Result<FeeSummary> {
+        let mut stmt = self.conn.prepare(
+            "SELECT outcome, COUNT(*), SUM(base_fee), SUM(priority_fee), SUM(tip_lamports)
+             FROM tx_costs GROUP BY outcome",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, i64>(4)?,
+            ))
+        })?;
+
+        let mut summary = FeeSummary::default();
+        for row in rows {
+            let (outcome, count, base, priority, tips) = row?;
+            let (count, base, priority) = (count as u64, base as u64, priority as u64);
+            match outcome.as_str() {
+                "success" => {
+                    summary.succeeded_count = count;
+                    summary.success_base_fees = base;
+                    summary.success_priority_fees = priority;
+                    summary.success_tips = tips as u64;
+                }
+                "failed" => {
+                    summary.failed_count = count;
+                    summary.failed_base_fees = base;
+                    summary.failed_priority_fees = priority;
+                }
+                _ => summary.expired_count = count,
+            }
+        }
+        Ok(summary)
+    }
+
+    /// Network fees and tips by mint, including transactions that failed
+    pub fn costs_by_mint(&self) -> Result<HashMap<Pubkey, MintCosts>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT mint, SUM(base_fee), SUM(priority_fee), SUM(tip_lamports)
+             FROM tx_costs GROUP BY mint",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })?;
+
+        let mut costs = HashMap::new();
+        for row in rows {
+            let (mint, base, priority, tips) = row?;
+            costs.insert(
+                Pubkey::from_str(&mint)?,
+                MintCosts {
+                    base_fees: base as u64,
+                    priority_fees: priority as u64,
+                    tip_lamports: tips as u64,
+                },
+            );
+        }
+        Ok(costs)
+    }
+}
+
+/// Replay fills into a position using average cost basis
+pub(crate) fn build_position(mint: &Pubkey, fills: &[Fill]) -> Option<Position> {
+    let first = fills.first()?;
+    let mut position = Position {
+        mint: *mint,
+        token_amount: 0,
+        cost_basis_lamports: 0,
+        realized_pnl_lamports: 0,
+        fees_paid_lamports: 0,
+        opened_at: first.timestamp,
+        last_fill_at: first.timestamp,
+        fill_count: 0,
+    };
+
+    for fill in fills {
+        match fill.side {
+            Side::Buy => {
+                if position.token_amount == 0 {
+                    position.opened_at = fill.timestamp;
+                }
+                position.token_amount += fill.token_amount;
+                position.cost_basis_lamports += fill.sol_amount;
+            }
+            Side::Sell => {
+                let sold = fill.token_amount.min(position.token_amount);
+                let cost_removed = if position.token_amount == 0 {
+                    0
+                } else {
+                    (position.cost_basis_lamports as u128 * sold as u128
+                        / position.token_amount as u128) as u64
+                };
+                position.realized_pnl_lamports += fill.sol_amount as i64 - cost_removed as i64;
+                position.cost_basis_lamports -= cost_removed;
+                position.token_amount -= sold;
+            }
+        }
+        position.fees_paid_lamports += fill.fee_lamports;
+        position.last_fill_at = fill.timestamp;
+        position.fill_count += 1;
+    }
+
+    Some(position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position_average_cost() {
+        let portfolio = Portfolio::open_in_memory().unwrap();
+        let mint = Pubkey::new_unique();
+
+        portfolio
+            .record_fill(&Fill::new(mint, Side::Buy, 1_000, 100_000, 2_000, "sig1".into()))
+            .unwrap();
+        portfolio
+            .record_fill(&Fill::new(mint, Side::Buy, 1_000, 300_000, 6_000, "sig2".into()))
+            .unwrap();
+        portfolio
+            .record_fill(&Fill::new(mint, Side::Sell, 1_000, 250_000, 5_000, "sig3".into()))
+            .unwrap();
+        // Duplicate signature is ignored
+        portfolio
+            .record_fill(&Fill::new(mint, Side::Sell, 1_000, 250_000, 5_000, "sig3".into()))
+            .unwrap();
+
+        let position = portfolio.get_position(&mint).unwrap().unwrap();
+        println!("Position: {:?}", position);
+        assert_eq!(position.token_amount, 1_000);
+        assert_eq!(position.cost_basis_lamports, 200_000);
+        assert_eq!(position.realized_pnl_lamports, 50_000);
+        assert_eq!(position.fees_paid_lamports, 13_000);
+        assert_eq!(portfolio.get_open_positions().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_fee_summary_separates_failures() {
+        let portfolio = Portfolio::open_in_memory().unwrap();
+        let mint = Pubkey::new_unique();
+        let cost = |outcome, base_fee, priority_fee| TxCost {
+            outcome,
+            base_fee,
+            priority_fee,
+            ..TxCost::unlanded(solana_sdk::signature::Signature::new_unique())
+        };
+
+        portfolio
+            .record_tx_cost(&mint, Side::Buy, &cost(TxOutcome::Succeeded, 5_000, 20_000))
+            .unwrap();
+        portfolio
+            .record_tx_cost(&mint, Side::Buy, &cost(TxOutcome::Failed("slippage".into()), 5_000, 50_000))
+            .unwrap();
+        portfolio
+            .record_tx_cost(&mint, Side::Sell, &cost(TxOutcome::Expired, 0, 0))
+            .unwrap();
+
+        portfolio
+            .record_tx_cost(
+                &mint,
+                Side::Sell,
+                &TxCost {
+                    tip_lamports: 10_000,
+                    ..cost(TxOutcome::Succeeded, 5_000, 0)
+                },
+            )
+            .unwrap();
+
+        let summary = portfolio.fee_summary().unwrap();
+        assert_eq!(summary.fees_wasted_on_failures(), 55_000);
+        assert_eq!(summary.success_priority_fees, 20_000);
+        assert_eq!(summary.success_tips, 10_000);
+        assert_eq!(summary.expired_count, 1);
+
+        let costs = portfolio.costs_by_mint().unwrap()[&mint];
+        assert_eq!(costs.network_fees(), 85_000);
+        assert_eq!(costs.total(), 95_000);
+    }
+}
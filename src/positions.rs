@@ -0,0 +1,215 @@
+use anyhow::Result;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tracing::{info, instrument, warn};
+
+use crate::limit_orders::LimitOrderBook;
+use crate::portfolio::{Portfolio, Position};
+use crate::rpc_pool;
+use crate::tp_sl::{TpSlConfig, TpSlMonitor};
+
+// Constants
+/// How often the portfolio is re-read for opened, changed and closed positions
+const RESCAN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// What a rescan means for one mint's exit task
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    /// A position with no task running
+    Start(Position),
+    /// The position changed since the task last heard of it
+    Update(Position),
+    /// The position is closed
+    Stop(Pubkey),
+}
+
+/// Steps that bring the tasks (the last position each was given) in line with
+/// `positions`. Finished tasks are left out of `tracked`, so still-open positions restart.
+fn steps(tracked: &HashMap<Pubkey, Position>, positions: &[Position]) -> Vec<Step> {
+    let mut steps: Vec<Step> = positions
+        .iter()
+        .filter(|position| position.is_open())
+        .filter_map(|position| match tracked.get(&position.mint) {
+            None => Some(Step::Start(position.clone())),
+            Some(last) if last != position => Some(Step::Update(position.clone())),
+            Some(_) => None,
+        })
+        .collect();
+    steps.extend(
+        tracked
+            .keys()
+            .filter(|mint| !positions.iter().any(|p| p.mint == **mint && p.is_open()))
+            .map(|mint| Step::Stop(*mint)),
+    );
+    steps
+}
+
+enum Update {
+    Position(Position),
+    Stop,
+}
+
+struct Task {
+    /// The position as last sent to the task
+    position: Position,
+    updates: mpsc::Sender<Update>,
+    handle: JoinHandle<()>,
+}
+
+/// Price one position every poll interval and exit it through the trade executor until it
+/// is sold or the manager stops the task. Its pending take-profit levels are kept in the
+/// order book as ladder orders meanwhile.
+#[instrument(name = "exit_task", skip_all, fields(mint = %monitor.mint))]
+fn run_task(monitor: TpSlMonitor, updates: mpsc::Receiver<Update>) {
+    let mut monitor = match LimitOrderBook::open_default() {
+        Ok(book) => monitor.with_order_book(book),
+        Err(e) => {
+            warn!(error = %e, "Failed to open the order book, ladder orders aren't recorded");
+            monitor
+        }
+    };
+    let rpc = rpc_pool::get_client();
+    loop {
+        monitor.poll(&rpc);
+        if monitor.is_done() {
+            info!("Position fully exited");
+            return;
+        }
+        match updates.recv_timeout(monitor.poll_interval()) {
+            Ok(Update::Position(position)) => monitor.sync(&position),
+            Ok(Update::Stop) | Err(RecvTimeoutError::Disconnected) => {
+                monitor.cancel_levels();
+                return;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+    }
+}
+
+/// Exits every open position in the portfolio with one TP/SL task per mint, so dozens of
+/// positions are priced and sold side by side. Sells go through the global trade executor,
+/// which runs them `trading.max_in_flight` at a time and one at a time per mint.
+pub struct PositionManager {
+    config: TpSlConfig,
+    tasks: HashMap<Pubkey, Task>,
+}
+
+impl PositionManager {
+    pub fn new(config: TpSlConfig) -> Self {
+        Self {
+            config,
+            tasks: HashMap::new(),
+        }
+    }
+
+    /// Positions with a task running
+    pub fn tracked(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Start tasks for new positions, pass changed ones on and stop the closed ones
+    pub fn sync(&mut self, positions: &[Position]) {
+        self.tasks.retain(|_, task| !task.handle.is_finished());
+        let tracked: HashMap<Pubkey, Position> = self
+            .tasks
+            .iter()
+            .map(|(mint, task)| (*mint, task.position.clone()))
+            .collect();
+        for step in steps(&tracked, positions) {
+            match step {
+                Step::Start(position) => {
+                    info!(mint = %position.mint, tokens = position.token_amount, "Tracking position");
+                    let monitor = TpSlMonitor::from_position(&position, self.config.clone());
+                    let (updates, rx) = mpsc::channel();
+                    let handle = std::thread::spawn(move || run_task(monitor, rx));
+                    self.tasks.insert(
+                        position.mint,
+                        Task {
+                            position,
+                            updates,
+                            handle,
+                        },
+                    );
+                }
+                Step::Update(position) => {
+                    if let Some(task) = self.tasks.get_mut(&position.mint) {
+                        let _ = task.updates.send(Update::Position(position.clone()));
+                        task.position = position;
+                    }
+                }
+                Step::Stop(mint) => {
+                    info!(%mint, "Position closed");
+                    if let Some(task) = self.tasks.remove(&mint) {
+                        let _ = task.updates.send(Update::Stop);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Rescan the portfolio every few seconds and keep a task on every open position.
+    /// Runs until the process exits.
+    pub fn run(mut self) -> Result<()> {
+        info!("Managing open positions");
+        loop {
+            match Portfolio::open_default().and_then(|p| p.get_open_positions()) {
+                Ok(positions) => self.sync(&positions),
+                Err(e) => warn!(error = %e, "Failed to read open positions"),
+            }
+            std::thread::sleep(RESCAN_INTERVAL);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rescans_start_update_and_stop_tasks() {
+        let position = |token_amount| Position {
+            mint: Pubkey::new_unique(),
+            token_amount,
+            cost_basis_lamports: token_amount,
+            realized_pnl_lamports: 0,
+            fees_paid_lamports: 0,
+            opened_at: 0,
+            last_fill_at: 0,
+            fill_count: 1,
+        };
+        let (held, sold_down, closed, new) = (
+            position(1_000),
+            position(1_000),
+            position(1_000),
+            position(500),
+        );
+        let tracked: HashMap<Pubkey, Position> = [&held, &sold_down, &closed]
+            .into_iter()
+            .map(|p| (p.mint, p.clone()))
+            .collect();
+        let sold_down_now = Position {
+            token_amount: 400,
+            cost_basis_lamports: 400,
+            fill_count: 2,
+            ..sold_down.clone()
+        };
+
+        let mut steps = steps(&tracked, &[held, sold_down_now.clone(), new.clone()]);
+        steps.sort_by_key(|step| match step {
+            Step::Start(_) => 0,
+            Step::Update(_) => 1,
+            Step::Stop(_) => 2,
+        });
+        assert_eq!(
+            steps,
+            vec![
+                Step::Start(new),
+                Step::Update(sold_down_now),
+                Step::Stop(closed.mint),
+            ]
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use anyhow::{anyhow, Result};
+use borsh::{BorshDeserialize, BorshSerialize};
+use once_cell::sync::Lazy;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::accounts::{self, AnchorAccount};
+use crate::config;
+use crate::portfolio::now_unix;
+
+// Constants
+const REST_TIMEOUT: Duration = Duration::from_secs(10);
+
+static CACHE: Lazy<Mutex<Option<(Instant, SolPrice)>>> = Lazy::new(|| Mutex::new(None));
+
+/// How many guardian signatures a Pyth update was verified with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum VerificationLevel {
+    Partial { num_signatures: u8 },
+    Full,
+}
+
+/// Price as published by Pyth: `price * 10^exponent`
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct PriceFeedMessage {
+    pub feed_id: [u8; 32],
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    /// Unix seconds
+    pub publish_time: i64,
+    pub prev_publish_time: i64,
+    pub ema_price: i64,
+    pub ema_conf: u64,
+}
+
+/// Pyth receiver price account
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct PriceUpdateV2 {
+    pub write_authority: Pubkey,
+    pub verification_level: VerificationLevel,
+    pub price_message: PriceFeedMessage,
+    pub posted_slot: u64,
+}
+
+impl AnchorAccount for PriceUpdateV2 {
+    const DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+    const NAME: &'static str = "PriceUpdateV2";
+}
+
+/// Where a price came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceSource {
+    Pyth,
+    Rest,
+}
+
+/// SOL price in USD
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolPrice {
+    pub usd: f64,
+    pub source: PriceSource,
+    /// Unix seconds the price was published (Pyth) or fetched (REST)
+    pub timestamp: i64,
+}
+
+impl SolPrice {
+    pub fn lamports_to_usd(&self, lamports: u64) -> f64 {
+        lamports as f64 / LAMPORTS_PER_SOL as f64 * self.usd
+    }
+
+    pub fn usd_to_lamports(&self, usd: f64) -> u64 {
+        (usd / self.usd * LAMPORTS_PER_SOL as f64) as u64
+    }
+}
+
+/// Price in `update`, rejecting it if published more than `max_staleness_secs` before `now`
+pub fn price_from_update(
+    update: &PriceUpdateV2,
+    now: i64,
+    max_staleness_secs: u64,
+) -> Result<SolPrice> {
+    let message = &update.price_message;
+    let age = now - message.publish_time;
+    if age > max_staleness_secs as i64 {
+        return Err(anyhow!("Pyth price is {}s old", age));
+    }
+    if message.price <= 0 {
+        return Err(anyhow!("Pyth price {} is not positive", message.price));
+    }
+    Ok(SolPrice {
+        usd: message.price as f64 * 10f64.powi(message.exponent),
+        source: PriceSource::Pyth,
+        timestamp: message.publish_time,
+    })
+}
+
+/// Read SOL/USD from the configured Pyth account
+pub fn fetch_pyth(rpc: &RpcClient) -> Result<SolPrice> {
+    let feed = &config::get().price_feed;
+    let account = Pubkey::from_str(&feed.pyth_account)?;
+    let update: PriceUpdateV2 = accounts::fetch(rpc, &account)?;
+    price_from_update(&update, now_unix(), feed.max_staleness_secs)
+}
+
+/// Fetch SOL/USD from the configured REST endpoint
+pub fn fetch_rest() -> Result<SolPrice> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(REST_TIMEOUT)
+        .build()?;
+    let body: serde_json::Value = client
+        .get(&config::get().price_feed.rest_url)
+        .send()?
+        .error_for_status()?
+        .json()?;
+    let usd = body["solana"]["usd"]
+        .as_f64()
+        .ok_or_else(|| anyhow!("No solana.usd in price response"))?;
+    Ok(SolPrice {
+        usd,
+        source: PriceSource::Rest,
+        timestamp: now_unix(),
+    })
+}
+
+/// Fetch a fresh price from Pyth, falling back to REST, and cache it
+pub fn refresh(rpc: &RpcClient) -> Result<SolPrice> {
+    let price = fetch_pyth(rpc).or_else(|e| {
+        warn!(error = %e, "Pyth SOL/USD unavailable, using REST fallback");
+        fetch_rest()
+    })?;
+    if let Ok(mut cache) = CACHE.lock() {
+        *cache = Some((Instant::now(), price));
+    }
+    Ok(price)
+}
+
+/// Last cached price, however old
+pub fn cached() -> Option<SolPrice> {
+    CACHE
+        .lock()
+        .ok()
+        .and_then(|cache| cache.map(|(_, price)| price))
+}
+
+/// The cached SOL/USD if it was read within `price_feed.max_staleness_secs` of `timestamp`,
+/// for recording the price a fill executed at
+pub fn cached_at(timestamp: i64) -> Option<f64> {
+    let max_staleness = config::get().price_feed.max_staleness_secs as i64;
+    cached()
+        .filter(|price| (timestamp - price.timestamp).abs() <= max_staleness)
+        .map(|price| price.usd)
+}
+
+/// SOL/USD, refetched once the cached price is older than `price_feed.refresh_secs`
+pub fn sol_usd(rpc: &RpcClient) -> Result<SolPrice> {
+    let refresh_after = Duration::from_secs(config::get().price_feed.refresh_secs);
+    if let Ok(cache) = CACHE.lock() {
+        if let Some((fetched_at, price)) = *cache {
+            if fetched_at.elapsed() < refresh_after {
+                return Ok(price);
+            }
+        }
+    }
+    refresh(rpc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_price_update_and_staleness() {
+        let update = PriceUpdateV2 {
+            write_authority: Pubkey::new_unique(),
+            verification_level: VerificationLevel::Full,
+            price_message: PriceFeedMessage {
+                feed_id: [7; 32],
+                price: 15_012_345_678,
+                conf: 1_000_000,
+                exponent: -8,
+                publish_time: 1_700_000_000,
+                prev_publish_time: 1_699_999_999,
+                ema_price: 15_000_000_000,
+                ema_conf: 1_000_000,
+            },
+            posted_slot: 250_000_000,
+        };
+        let mut data = PriceUpdateV2::DISCRIMINATOR.to_vec();
+        data.extend(borsh::to_vec(&update).unwrap());
+
+        let parsed = PriceUpdateV2::try_from_account_data(&data).unwrap();
+        assert_eq!(parsed.verification_level, VerificationLevel::Full);
+        let price = price_from_update(&parsed, 1_700_000_030, 60).unwrap();
+        assert!((price.usd - 150.12345678).abs() < 1e-9);
+        assert_eq!(price.source, PriceSource::Pyth);
+        assert!((price.lamports_to_usd(2 * LAMPORTS_PER_SOL) - 300.24691356).abs() < 1e-6);
+        assert_eq!(price.usd_to_lamports(price.usd), LAMPORTS_PER_SOL);
+
+        assert!(price_from_update(&parsed, 1_700_000_061, 60).is_err());
+    }
+}
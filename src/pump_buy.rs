@@ -2,13 +2,10 @@ use anyhow::{anyhow, Result};
 use solana_client::rpc_client::RpcClient;
 #[allow(deprecated)]
 use solana_sdk::{
-    instruction::{AccountMeta, Instruction},
-    native_token::LAMPORTS_PER_SOL,
+    instruction::Instruction,
     pubkey::Pubkey,
     signature::Keypair,
     signer::Signer,
-    system_program,
-    transaction::Transaction,
 };
 use spl_associated_token_account::{
     get_associated_token_address_with_program_id,
@@ -16,263 +13,412 @@ use spl_associated_token_account::{
 };
 use spl_token::ID as TOKEN_PROGRAM_ID;
 use spl_token_2022::ID as TOKEN_2022_PROGRAM_ID;
-use std::str::FromStr;
-use crate::cal;
+use tracing::{debug, info, instrument, warn};
+use crate::accounts::{AnchorAccount, BondingCurve};
+use crate::amount::{SolAmount, TokenAmount};
+use crate::config::ExecutionMode;
+use crate::error::{self, BotError};
+use crate::exit_arm::ArmedExit;
+use crate::limit_orders::{EntryLadder, LimitOrder, LimitOrderBook};
+use crate::portfolio::{Fill, Side};
+use crate::pump_idl::{
+    create_buy_instruction, extend_bonding_curve, get_bonding_curve_pda, BuyAccounts, BuyArgs,
+};
+use crate::sender;
+use crate::{
+    address_book, cal, config, fees, migrations, pumpswap, rpc_pool, signer, sizing, telegram,
+    token_accounts,
+};
 
 
 // Constants
-const MAINNET_RPC: &str = "https://api.mainnet-beta.solana.com";
-const PRIVATE_KEY: &str = "priv-key";
-const FEE_RECIPIENT: &str = "CebN5WGQ4jvEPvsVU4EoHEpgzq1VV7AbicfhtW4xC9iM";
-
-lazy_static::lazy_static! {
-    static ref PUMP_PROGRAM_ID: Pubkey = Pubkey::from_str("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P").unwrap();
-    static ref GLOBAL_ADDRESS: Pubkey = Pubkey::from_str("4wTV1YmiEkRvAtNtsSGPtUrqRYQMe5SKy2uB4Jjaxnjf").unwrap();
-    static ref EVENT_AUTHORITY: Pubkey = Pubkey::from_str("Ce6TQqeHC9p8KetsN6JsjHK7UTZk7nasjjnr7XxXp9F1").unwrap();
-    static ref FEE_PROGRAM: Pubkey = Pubkey::from_str("pfeeUxB6jkeY1Hxd7CsFCAjcbHA9rWtchMGdZ6VojVZ").unwrap();
-    static ref FEE_CONFIG: Pubkey = Pubkey::from_str("8Wf5TiAheLUqBrKXeYg2JtAFFMWtKdG2BSFgqUcPVwTt").unwrap();
+/// Pre-build and sign a full-position exit after every buy
+const ARM_EXIT_ON_BUY: bool = false;
+
+/// How much of a mint to buy
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BuyAmount {
+    /// Spend this many lamports, plus slippage
+    Sol(u64),
+    /// Receive exactly this many raw tokens, paying what the curve asks plus slippage
+    ExactTokens(u64),
+}
+
+impl From<SolAmount> for BuyAmount {
+    fn from(sol: SolAmount) -> Self {
+        BuyAmount::Sol(sol.lamports())
+    }
+}
+
+impl From<TokenAmount> for BuyAmount {
+    fn from(tokens: TokenAmount) -> Self {
+        BuyAmount::ExactTokens(tokens.raw())
+    }
 }
 
-/// Buy instruction discriminator
-const BUY_DISCRIMINATOR: [u8; 8] = [102, 6, 61, 18, 1, 218, 235, 234];
+impl BuyAmount {
+    /// Resolve to the buy instruction's `amount` and `max_sol_cost` on a bonding curve,
+    /// allowing `slippage_bps` on the cost. An exact amount is grown by any Token-2022
+    /// transfer fee withheld on the way to the buyer.
+    pub fn resolve(
+        &self,
+        global: &cal::Global,
+        curve: &BondingCurve,
+        extensions: &cal::MintExtensions,
+        slippage_bps: u64,
+    ) -> Result<(u64, u64)> {
+        let (token_amount, sol_cost) = match *self {
+            BuyAmount::Sol(lamports) => {
+                (cal::get_tokens_for_sol(global, Some(curve), lamports), lamports)
+            }
+            BuyAmount::ExactTokens(tokens) => {
+                let amount = extensions.gross_amount(tokens);
+                if amount > curve.real_token_reserves {
+                    return Err(anyhow!(
+                        "Buying {} tokens needs {} but only {} are left on the curve",
+                        tokens,
+                        amount,
+                        curve.real_token_reserves
+                    ));
+                }
+                (amount, cal::get_sol_for_tokens(global, Some(curve), amount))
+            }
+        };
+
+        if token_amount == 0 {
+            return Err(anyhow!("Buy amount resolves to zero tokens"));
+        }
+        Ok((token_amount, with_slippage(sol_cost, slippage_bps)))
+    }
+}
 
-/// Accounts needed for the buy instruction
-pub struct BuyAccounts {
-    pub global: Pubkey,
-    pub fee_recipient: Pubkey,
+/// A buy of some amount of one mint
+#[derive(Debug, Clone)]
+pub struct BuyRequest {
     pub mint: Pubkey,
-    pub bonding_curve: Pubkey,
-    pub associated_bonding_curve: Pubkey,
-    pub associated_user: Pubkey,
-    pub user: Pubkey,
-    pub system_program: Pubkey,
-    pub token_program: Pubkey,
-    pub creator_vault: Pubkey,
-    pub event_authority: Pubkey,
-    pub program: Pubkey,
-    pub global_volume_accumulator: Pubkey,
-    pub user_volume_accumulator: Pubkey,
-    pub fee_config: Pubkey,
-    pub fee_program: Pubkey,
+    pub amount: BuyAmount,
+    /// Place a take-profit sell this many percent above the fill price once the buy lands
+    pub tp_pct: Option<f64>,
+    /// Place a stop-loss sell this many percent below the fill price once the buy lands
+    pub sl_pct: Option<f64>,
+    /// Stage limit buys below the fill price once the buy lands
+    pub entry_ladder: Option<EntryLadder>,
+    /// Buy even if it moves the price past `trading.max_price_impact_bps`
+    pub force: bool,
 }
 
-/// Arguments for the buy instruction
-pub struct BuyArgs {
-    pub amount: u64,
-    pub max_sol_cost: u64,
-    pub track_volume: bool,
+impl BuyRequest {
+    /// Plain buy without take-profit or stop-loss orders
+    pub fn new(mint: Pubkey, amount: BuyAmount) -> Self {
+        Self {
+            mint,
+            amount,
+            tp_pct: None,
+            sl_pct: None,
+            entry_ladder: None,
+            force: false,
+        }
+    }
+
+    /// Whether the buy places TP/SL orders when it lands
+    pub fn is_protected(&self) -> bool {
+        self.tp_pct.is_some() || self.sl_pct.is_some()
+    }
+
+    /// Whether the buy places any orders when it lands
+    pub fn has_follow_up_orders(&self) -> bool {
+        self.is_protected() || self.entry_ladder.is_some()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if let Some(pct) = self.tp_pct.filter(|pct| !(*pct > 0.0 && pct.is_finite())) {
+            return Err(anyhow!("Take-profit percent must be positive, got {}", pct));
+        }
+        if let Some(pct) = self.sl_pct.filter(|pct| !(*pct > 0.0 && *pct < 100.0)) {
+            return Err(anyhow!(
+                "Stop-loss percent must be in (0, 100), got {}",
+                pct
+            ));
+        }
+        if let Some(ladder) = &self.entry_ladder {
+            ladder.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Take-profit and stop-loss prices for a fill at `entry_price` lamports per token
+    pub fn bracket_prices(&self, entry_price: f64) -> (Option<f64>, Option<f64>) {
+        (
+            self.tp_pct.map(|pct| entry_price * (1.0 + pct / 100.0)),
+            self.sl_pct.map(|pct| entry_price * (1.0 - pct / 100.0)),
+        )
+    }
+}
+
+/// `sol_cost` plus `slippage_bps`
+pub(crate) fn with_slippage(sol_cost: u64, slippage_bps: u64) -> u64 {
+    (sol_cost as u128 * (10_000 + slippage_bps as u128) / 10_000).min(u64::MAX as u128) as u64
 }
 
 /// Load wallet from base58 encoded private key
-fn load_wallet_from_private_key(private_key: &str) -> Result<Keypair> {
+pub fn load_wallet_from_private_key(private_key: &str) -> Result<Keypair> {
     let secret_key = bs58::decode(private_key)
         .into_vec()
         .map_err(|e| anyhow!("Failed to decode private key: {}", e))?;
-    Keypair::try_from(secret_key.as_slice()).map_err(|e| anyhow!("Failed to create keypair: {}", e))
+    Keypair::from_bytes(&secret_key).map_err(|e| anyhow!("Failed to create keypair: {}", e))
 }
 
-/// Create the buy instruction
-fn create_buy_instruction(accounts: BuyAccounts, args: BuyArgs) -> Instruction {
-    // Build instruction data: discriminator (8) + amount (8) + max_sol_cost (8) + Option<bool> (2)
-    let mut data = Vec::with_capacity(26);
-
-    // Add discriminator
-    data.extend_from_slice(&BUY_DISCRIMINATOR);
-
-    // Add amount (u64 little-endian)
-    data.extend_from_slice(&args.amount.to_le_bytes());
-
-    // Add max_sol_cost (u64 little-endian)
-    data.extend_from_slice(&args.max_sol_cost.to_le_bytes());
-
-    // Add track_volume as Option<bool>: Some = 1, then value
-    data.push(1); // Some
-    data.push(if args.track_volume { 1 } else { 0 });
-
-    // Build account metas
-    let keys = vec![
-        AccountMeta::new_readonly(accounts.global, false),
-        AccountMeta::new(accounts.fee_recipient, false),
-        AccountMeta::new_readonly(accounts.mint, false),
-        AccountMeta::new(accounts.bonding_curve, false),
-        AccountMeta::new(accounts.associated_bonding_curve, false),
-        AccountMeta::new(accounts.associated_user, false),
-        AccountMeta::new(accounts.user, true),
-        AccountMeta::new_readonly(accounts.system_program, false),
-        AccountMeta::new_readonly(accounts.token_program, false),
-        AccountMeta::new(accounts.creator_vault, false),
-        AccountMeta::new_readonly(accounts.event_authority, false),
-        AccountMeta::new_readonly(accounts.program, false),
-        AccountMeta::new(accounts.global_volume_accumulator, false),
-        AccountMeta::new(accounts.user_volume_accumulator, false),
-        AccountMeta::new_readonly(accounts.fee_config, false),
-        AccountMeta::new_readonly(accounts.fee_program, false),
-    ];
-
-    Instruction {
-        program_id: *PUMP_PROGRAM_ID,
-        accounts: keys,
-        data,
-    }
+/// Buy instruction for `token_amount` tokens of `mint` launched by `creator`, derived
+/// without any RPC lookups. The user's token account must exist by the time it runs.
+pub fn buy_instruction(
+    user: &Pubkey,
+    mint: &Pubkey,
+    creator: &Pubkey,
+    token_program: &Pubkey,
+    token_amount: u64,
+    max_sol_cost: u64,
+) -> Instruction {
+    create_buy_instruction(
+        BuyAccounts::new(user, mint, creator, token_program, fees::next_fee_recipient()),
+        BuyArgs {
+            amount: token_amount,
+            max_sol_cost,
+            track_volume: Some(true),
+        },
+    )
 }
 
-/// Derive the bonding curve PDA
-fn get_bonding_curve_pda(mint: &Pubkey) -> (Pubkey, u8) {
-    Pubkey::find_program_address(&[b"bonding-curve", mint.as_ref()], &PUMP_PROGRAM_ID)
+/// Price impact of buying `token_amount` tokens on `curve`. The cost already includes
+/// the fee, which `price_impact_bps` takes out itself.
+fn buy_impact_bps(global: &cal::Global, curve: &BondingCurve, token_amount: u64) -> u64 {
+    let sol_cost = cal::get_sol_for_tokens(global, Some(curve), token_amount);
+    cal::price_impact_bps(global, curve, sol_cost)
 }
 
-/// Derive the creator vault PDA
-fn get_creator_vault_pda(creator: &Pubkey) -> (Pubkey, u8) {
-    Pubkey::find_program_address(&[b"creator-vault", creator.as_ref()], &PUMP_PROGRAM_ID)
+/// Resolve a `BuyRequest` against the live curve, or the PumpSwap pool once the curve has
+/// completed, to the `token_amount` and `max_sol_cost` that `buy` takes
+pub fn resolve_buy_request(
+    connection: &RpcClient,
+    request: &BuyRequest,
+    slippage_bps: u64,
+) -> error::Result<(u64, u64)> {
+    let mint = request.mint;
+    request.validate()?;
+    let curve = cal::fetch_bonding_curve(connection, &mint)?;
+    let (token_amount, max_sol_cost) = if curve.complete {
+        let (token_amount, sol_cost) = match request.amount {
+            BuyAmount::Sol(lamports) => {
+                (pumpswap::quote_buy(connection, &mint, lamports)?, lamports)
+            }
+            BuyAmount::ExactTokens(tokens) => {
+                (tokens, pumpswap::quote_buy_exact(connection, &mint, tokens)?)
+            }
+        };
+        if token_amount == 0 {
+            return Err(anyhow!("Buy amount resolves to zero tokens").into());
+        }
+        (token_amount, with_slippage(sol_cost, slippage_bps))
+    } else {
+        let global = fees::current_global(connection, &curve);
+        let extensions = cal::fetch_mint_extensions(connection, &mint)?;
+        let (token_amount, max_sol_cost) =
+            request
+                .amount
+                .resolve(&global, &curve, &extensions, slippage_bps)?;
+        let impact_bps = buy_impact_bps(&global, &curve, token_amount);
+        sizing::check_price_impact(&mint, true, impact_bps, request.force)?;
+        (token_amount, max_sol_cost)
+    };
+    info!(
+        %mint,
+        amount = ?request.amount,
+        token_amount,
+        max_sol_cost,
+        "Resolved buy request"
+    );
+    Ok((token_amount, max_sol_cost))
 }
 
-/// Derive the global volume accumulator PDA
-fn get_global_volume_accumulator_pda() -> (Pubkey, u8) {
-    Pubkey::find_program_address(&[b"global_volume_accumulator"], &PUMP_PROGRAM_ID)
-}
+/// Main function to execute the pump.fun buy with the configured RPC and wallet. Returns
+/// the fill recorded for it, None when simulated.
+pub fn run_pump_buy(
+    token_amount: u64,
+    mint: Pubkey,
+    max_sol_cost: u64,
+) -> error::Result<Option<Fill>> {
+    // Initialize RPC client
+    let connection = rpc_pool::get_client();
 
-/// Derive the user volume accumulator PDA
-fn get_user_volume_accumulator_pda(user: &Pubkey) -> (Pubkey, u8) {
-    Pubkey::find_program_address(&[b"user_volume_accumulator", user.as_ref()], &PUMP_PROGRAM_ID)
-}
+    // Load wallet
+    debug!("Loading wallet from private key");
+    let user = signer::load_default_wallet()?;
 
-/// Parse creator pubkey from bonding curve account data
-/// Layout: 8 (discriminator) + 8*5 (u64 fields) + 1 (bool) = 49 bytes, then 32 bytes for creator
-fn parse_creator_from_bonding_curve(data: &[u8]) -> Result<Pubkey> {
-    const CREATOR_OFFSET: usize = 8 + 8 + 8 + 8 + 8 + 8 + 1; // 49 bytes
+    buy(&connection, &user, token_amount, mint, max_sol_cost)
+}
 
-    if data.len() < CREATOR_OFFSET + 32 {
-        return Err(anyhow!(
-            "Bonding curve data too short: {} bytes",
-            data.len()
-        ));
+/// `run_pump_buy`, then place `request`'s take-profit and stop-loss orders and entry
+/// ladder on the fill as soon as the buy lands. A failure to place them is reported, not returned, since
+/// the buy itself went through.
+pub fn run_protected_buy(
+    request: &BuyRequest,
+    token_amount: u64,
+    max_sol_cost: u64,
+) -> error::Result<()> {
+    let fill = run_pump_buy(token_amount, request.mint, max_sol_cost)?;
+    // Simulated buys record no fill to protect
+    let Some(fill) = fill.filter(|_| request.has_follow_up_orders()) else {
+        return Ok(());
+    };
+    match protect_buy(request, &fill) {
+        Ok(orders) => {
+            let ids: Vec<i64> = orders.iter().map(|o| o.id).collect();
+            info!(mint = %request.mint, ?ids, "Placed follow-up orders for buy");
+        }
+        Err(e) => {
+            warn!(mint = %request.mint, error = %e, "Failed to place follow-up orders for buy");
+            telegram::notify(format!(
+                "Bought {} but failed to place its follow-up orders: {}",
+                request.mint, e
+            ));
+        }
     }
-
-    let creator_bytes: [u8; 32] = data[CREATOR_OFFSET..CREATOR_OFFSET + 32]
-        .try_into()
-        .map_err(|_| anyhow!("Failed to parse creator bytes"))?;
-
-    Ok(Pubkey::new_from_array(creator_bytes))
+    Ok(())
 }
 
-/// Main function to execute the pump.fun buy
-pub fn run_pump_buy(token_amount: u64,mint: Pubkey, max_sol_cost: u64) -> Result<()> {
+/// Place `request`'s TP/SL orders and entry ladder on `fill`, the buy it made. TP/SL
+/// orders are sized to the tokens that buy received, and both are priced off its fill
+/// price.
+pub fn protect_buy(request: &BuyRequest, fill: &Fill) -> Result<Vec<LimitOrder>> {
+    place_follow_up_orders(&LimitOrderBook::open_default()?, request, fill)
+}
 
-   
+/// `protect_buy` into `book`
+fn place_follow_up_orders(
+    book: &LimitOrderBook,
+    request: &BuyRequest,
+    fill: &Fill,
+) -> Result<Vec<LimitOrder>> {
+    let trading = &config::get().trading;
+    let mut orders = Vec::new();
+    if request.is_protected() {
+        let (take_profit, stop_loss) = request.bracket_prices(fill.price());
+        orders.extend(book.place_bracket(
+            request.mint,
+            fill.token_amount,
+            take_profit,
+            stop_loss,
+            trading.sell_slippage_bps,
+        )?);
+    }
+    if let Some(ladder) = &request.entry_ladder {
+        orders.extend(book.place_entry_ladder(
+            request.mint,
+            ladder,
+            fill.price(),
+            trading.buy_slippage_bps,
+        )?);
+    }
+    Ok(orders)
+}
 
-    println!("Starting mainnet buy test...");
-    println!("Token mint: {}", mint);
+/// Buy `token_amount` tokens of `mint` for `user`, paying at most `max_sol_cost` lamports.
+/// Returns the fill recorded for it, None when simulated.
+#[instrument(name = "buy", skip_all, fields(%mint, token_amount, max_sol_cost))]
+pub fn buy(
+    connection: &RpcClient,
+    user: &dyn Signer,
+    token_amount: u64,
+    mint: Pubkey,
+    max_sol_cost: u64,
+) -> error::Result<Option<Fill>> {
+    info!("Starting buy");
 
-    // Initialize RPC client
-    let connection = RpcClient::new(MAINNET_RPC.to_string());
+    // Track graduation of every mint the bot trades
+    migrations::watch_mint(&mint);
 
-    // Load wallet
-    println!("Loading wallet from private key...");
-    let user = load_wallet_from_private_key(PRIVATE_KEY)?;
-    println!("User address: {}", user.pubkey());
+    let config = config::get();
 
     // Check balance
     let balance = connection.get_balance(&user.pubkey())?;
-    let balance_sol = balance as f64 / LAMPORTS_PER_SOL as f64;
-    println!("Wallet balance: {} SOL", balance_sol);
+    debug!(
+        user = %address_book::label(&user.pubkey()),
+        balance = %SolAmount::from_lamports(balance),
+        "Wallet balance"
+    );
 
-    if balance < max_sol_cost + 10_000_000 {
-        return Err(anyhow!(
-            "Insufficient balance. Need at least {} SOL",
-            (max_sol_cost + 10_000_000) as f64 / LAMPORTS_PER_SOL as f64
-        ));
+    // Paper trades spend no SOL
+    if config.trading.mode != ExecutionMode::Paper && balance < max_sol_cost + 10_000_000 {
+        return Err(BotError::InsufficientBalance {
+            needed: max_sol_cost + 10_000_000,
+            available: balance,
+        });
     }
 
-    // Parse addresses
-    let fee_recipient = Pubkey::from_str(FEE_RECIPIENT)?;
-
     // Derive bonding curve PDA
     let (bonding_curve, _) = get_bonding_curve_pda(&mint);
-    println!("Bonding Curve: {}", bonding_curve);
+    debug!(%bonding_curve, "Bonding curve");
 
     // Get mint info to determine token program
-    let mint_info = connection
-        .get_account(&mint)
-        .map_err(|e| anyhow!("Failed to get mint account: {}", e))?;
+    let mint_info = cal::fetch_account(connection, &mint)?;
 
     let token_program_id = if mint_info.owner == TOKEN_2022_PROGRAM_ID {
         TOKEN_2022_PROGRAM_ID
     } else {
         TOKEN_PROGRAM_ID
     };
-    println!("Token Program: {}", token_program_id);
+    debug!(%token_program_id, "Token program");
 
     // Get associated token addresses
     let associated_bonding_curve =
         get_associated_token_address_with_program_id(&bonding_curve, &mint, &token_program_id);
-    println!("Associated Bonding Curve: {}", associated_bonding_curve);
+    debug!(%associated_bonding_curve, "Associated bonding curve");
 
     let associated_user =
         get_associated_token_address_with_program_id(&user.pubkey(), &mint, &token_program_id);
-    println!("Associated Token Account: {}", associated_user);
+    debug!(%associated_user, "Associated token account");
 
     // Fetch bonding curve to get creator
-    let bonding_curve_info = connection
-        .get_account(&bonding_curve)
-        .map_err(|_| anyhow!("Bonding curve account not found - token may have migrated"))?;
-
-    let creator = parse_creator_from_bonding_curve(&bonding_curve_info.data)?;
-    println!("Token Creator: {}", creator);
+    let bonding_curve_info = cal::fetch_account(connection, &bonding_curve)?;
 
-    // Derive creator vault PDA
-    let (creator_vault, _) = get_creator_vault_pda(&creator);
-    println!("Creator Vault: {}", creator_vault);
+    // Graduated tokens can only be bought through the PumpSwap pool
+    let curve = BondingCurve::try_from_account_data(&bonding_curve_info.data)?;
+    if curve.complete {
+        info!("Bonding curve complete, routing buy through PumpSwap");
+        return pumpswap::buy(connection, user, token_amount, mint, max_sol_cost);
+    }
 
-    // Derive volume accumulator PDAs
-    let (global_volume_accumulator, _) = get_global_volume_accumulator_pda();
-    println!("Global Volume Accumulator: {}", global_volume_accumulator);
+    let extensions = cal::mint_extensions(connection, &mint_info)?;
+    extensions.check(&mint)?;
 
-    let (user_volume_accumulator, _) = get_user_volume_accumulator_pda(&user.pubkey());
-    println!("User Volume Accumulator: {}", user_volume_accumulator);
+    let creator = curve.creator;
+    debug!(creator = %address_book::label(&creator), "Token creator");
 
-    println!("\nBuilding buy instruction...");
-    println!("  Amount: {} tokens", token_amount);
-    println!(
-        "  Max SOL cost: {} SOL",
-        max_sol_cost as f64 / LAMPORTS_PER_SOL as f64
-    );
+    debug!("Building buy instruction");
+    fees::refresh_fee_recipients(connection);
 
     // Create buy instruction
-    let buy_ix = create_buy_instruction(
-        BuyAccounts {
-            global: *GLOBAL_ADDRESS,
-            fee_recipient,
-            mint,
-            bonding_curve,
-            associated_bonding_curve,
-            associated_user,
-            user: user.pubkey(),
-            system_program: system_program::ID,
-            token_program: token_program_id,
-            creator_vault,
-            event_authority: *EVENT_AUTHORITY,
-            program: *PUMP_PROGRAM_ID,
-            global_volume_accumulator,
-            user_volume_accumulator,
-            fee_config: *FEE_CONFIG,
-            fee_program: *FEE_PROGRAM,
-        },
-        BuyArgs {
-            amount: token_amount,
-            max_sol_cost: max_sol_cost,
-            track_volume: true,
-        },
+    let buy_ix = buy_instruction(
+        &user.pubkey(),
+        &mint,
+        &creator,
+        &token_program_id,
+        token_amount,
+        max_sol_cost,
     );
 
-    // Get latest blockhash
-    let blockhash = connection.get_latest_blockhash()?;
+    // Build transaction, starting with any configured priority fee
+    let mut instructions = config.fees.compute_budget_instructions();
 
-    // Build transaction
-    let mut instructions = Vec::new();
+    // Curves created under an older IDL are grown to the size the program reads
+    instructions.extend(extend_bonding_curve(
+        &bonding_curve,
+        &bonding_curve_info.data,
+        &user.pubkey(),
+    ));
 
     // Check if ATA exists, if not, create it
-    if connection.get_account(&associated_user).is_err() {
-        println!("Creating associated token account for user...");
+    if !token_accounts::account_exists(connection, &associated_user) {
+        debug!("Creating associated token account for user");
         let create_ata_ix = create_associated_token_account(
             &user.pubkey(),   // payer
             &user.pubkey(),   // wallet
@@ -284,55 +430,175 @@ pub fn run_pump_buy(token_amount: u64,mint: Pubkey, max_sol_cost: u64) -> Result
 
     instructions.push(buy_ix);
 
-    let transaction = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&user.pubkey()),
-        &[&user],
-        blockhash,
+    let global = fees::current_global(connection, &curve);
+    let quote = cal::quote_sol_for_tokens_with_rounding(
+        &global,
+        Some(&curve),
+        token_amount,
+        cal::RoundingPolicy::default(),
     );
-
-    // Simulate transaction
-    println!("\nSimulating transaction...");
-    
-    // match connection.send_transaction(&transaction) {
-    //     Ok(signature) => {
-    //         println!("Transaction sent: {}", signature);
-    //     }
-    //     Err(e) => {
-    //         println!("Failed to send transaction: {}", e);
-    //     }
-    // }
-        
-    
-
-    match connection.simulate_transaction(&transaction) {
-        Ok(simulation) => {
-            println!("Simulation result:");
-            println!("  Error: {:?}", simulation.value.err);
-            println!("  Logs:");
-            if let Some(logs) = &simulation.value.logs {
-                for log in logs {
-                    println!("    {}", log);
-                }
-            }
-            println!("  Units consumed: {:?}", simulation.value.units_consumed);
-
-            if simulation.value.err.is_none() {
-                println!("\n✓ Simulation successful! Ready to send transaction.");
-
-                // Uncomment below to actually send the transaction:
-                // println!("\nSending transaction...");
-                // let signature = connection.send_and_confirm_transaction(&transaction)?;
-                // println!("✓ Buy successful!");
-                // println!("Signature: {}", signature);
-                // println!("View on Solscan: https://solscan.io/tx/{}", signature);
-            }
-        }
+    // A Token-2022 transfer fee is withheld from the tokens on their way to us
+    let received = token_amount - extensions.transfer_fee(token_amount);
+    let fill = Fill::new(
+        mint,
+        Side::Buy,
+        received,
+        quote.input_amount,
+        quote.fee,
+        String::new(),
+    );
+    let recorded = match sender::execute_trade(connection, &instructions, user, fill) {
+        Ok(recorded) => recorded,
         Err(e) => {
-            println!("✗ Failed to simulate transaction: {}", e);
+            // The account may have been closed outside the bot; look it up again next time
+            token_accounts::forget_account(&associated_user);
+            return Err(e);
         }
+    };
+    if config.trading.mode == ExecutionMode::Live {
+        token_accounts::mark_account_exists(&associated_user);
     }
 
-    Ok(())
+    // Pre-sign the exit so a rug signal can fire it without building anything
+    if ARM_EXIT_ON_BUY && config.trading.mode == ExecutionMode::Live {
+        match ArmedExit::arm(
+            connection,
+            user,
+            &mint,
+            token_amount,
+            config.trading.exit_slippage_bps,
+        ) {
+            Ok(exit) => info!(
+                token_amount = exit.token_amount,
+                min_sol_output = exit.min_sol_output,
+                "Exit armed"
+            ),
+            Err(e) => warn!(error = %e, "Failed to arm exit"),
+        }
+    }
+
+    Ok(recorded)
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cal::{MintExtensions, TransferFeeRate};
+
+    #[test]
+    fn test_exact_token_buys_resolve_to_their_cost() {
+        let global = cal::Global::default();
+        let curve = cal::new_bonding_curve(&global);
+        let plain = MintExtensions::default();
+
+        let tokens = 10_000_000_000_000; // 1% of a 1B supply with 6 decimals
+        let (amount, max_sol_cost) = BuyAmount::ExactTokens(tokens)
+            .resolve(&global, &curve, &plain, 100)
+            .unwrap();
+        let cost = cal::get_sol_for_tokens(&global, Some(&curve), tokens);
+        assert_eq!(amount, tokens);
+        assert_eq!(max_sol_cost, cost + cost / 100);
+
+        // The transfer fee is bought on top, so the exact amount still arrives
+        let taxed = MintExtensions {
+            transfer_fee: Some(TransferFeeRate {
+                basis_points: 100,
+                maximum_fee: u64::MAX,
+            }),
+            ..Default::default()
+        };
+        let (amount, _) = BuyAmount::ExactTokens(9_900)
+            .resolve(&global, &curve, &taxed, 0)
+            .unwrap();
+        assert_eq!(amount - taxed.transfer_fee(amount), 9_900);
+
+        let (amount, max_sol_cost) = BuyAmount::Sol(1_000_000_000)
+            .resolve(&global, &curve, &plain, 500)
+            .unwrap();
+        assert_eq!(amount, cal::get_tokens_for_sol(&global, Some(&curve), 1_000_000_000));
+        assert_eq!(max_sol_cost, 1_050_000_000);
+
+        assert!(BuyAmount::ExactTokens(0).resolve(&global, &curve, &plain, 0).is_err());
+        assert!(BuyAmount::ExactTokens(curve.real_token_reserves + 1)
+            .resolve(&global, &curve, &plain, 0)
+            .is_err());
+    }
+
+    #[test]
+    fn test_buy_at_the_impact_limit_passes() {
+        let global = cal::Global::default();
+        let curve = cal::new_bonding_curve(&global);
+        let mint = Pubkey::new_unique();
+        let tokens = cal::get_tokens_for_sol(&global, Some(&curve), 2_000_000_000);
+        let sol_cost = cal::get_sol_for_tokens(&global, Some(&curve), tokens);
+
+        let impact_bps = buy_impact_bps(&global, &curve, tokens);
+        assert_eq!(impact_bps, cal::price_impact_bps(&global, &curve, sol_cost));
+        assert!(sizing::check_impact_limit(&mint, true, impact_bps, impact_bps, false).is_ok());
+        assert!(matches!(
+            sizing::check_impact_limit(&mint, true, impact_bps, impact_bps - 1, false),
+            Err(BotError::PriceImpactTooHigh { .. })
+        ));
+        // Charging the fee a second time would have refused it
+        let double_fee = sol_cost + cal::get_fee(&global, &curve, sol_cost, false);
+        assert!(cal::price_impact_bps(&global, &curve, double_fee) > impact_bps);
+    }
+
+    #[test]
+    fn test_buy_request_bracket_prices() {
+        let mint = Pubkey::new_unique();
+        let plain = BuyRequest::new(mint, BuyAmount::Sol(1_000_000));
+        assert!(!plain.is_protected());
+        assert_eq!(plain.bracket_prices(2.0), (None, None));
+
+        let request = BuyRequest {
+            tp_pct: Some(50.0),
+            sl_pct: Some(25.0),
+            ..plain.clone()
+        };
+        assert!(request.is_protected() && request.validate().is_ok());
+        assert_eq!(request.bracket_prices(2.0), (Some(3.0), Some(1.5)));
+
+        let laddered = BuyRequest {
+            entry_ladder: Some(EntryLadder {
+                budget_lamports: 0,
+                legs: 2,
+                step_pct: 10.0,
+            }),
+            ..plain.clone()
+        };
+        assert!(laddered.has_follow_up_orders() && !laddered.is_protected());
+        assert!(laddered.validate().is_err());
+
+        let no_stop = BuyRequest {
+            sl_pct: Some(100.0),
+            ..plain
+        };
+        assert!(no_stop.validate().is_err());
+    }
+
+    #[test]
+    fn test_follow_up_orders_are_priced_off_the_given_fill() {
+        let mint = Pubkey::new_unique();
+        let request = BuyRequest {
+            tp_pct: Some(50.0),
+            entry_ladder: Some(EntryLadder {
+                budget_lamports: 2_000_000,
+                legs: 2,
+                step_pct: 10.0,
+            }),
+            ..BuyRequest::new(mint, BuyAmount::Sol(1_000_000))
+        };
+        let book = LimitOrderBook::open_in_memory().unwrap();
+        // Priced off this buy alone, not whichever buy of the mint was recorded last
+        let fill = Fill::new(mint, Side::Buy, 1_000, 2_000, 0, String::new());
+        let orders = place_follow_up_orders(&book, &request, &fill).unwrap();
+
+        let prices: Vec<f64> = orders.iter().map(|o| o.limit_price).collect();
+        assert_eq!(orders.len(), 3);
+        assert!((prices[0] - 3.0).abs() < 1e-9);
+        assert!((prices[1] - 1.8).abs() < 1e-9 && (prices[2] - 1.6).abs() < 1e-9);
+        assert_eq!(orders[0].amount, 1_000);
+    }
+}
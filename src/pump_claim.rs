@@ -0,0 +1,111 @@
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    instruction::Instruction, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signer::Signer,
+    system_program,
+};
+use tracing::{info, instrument};
+
+use crate::config::{self, ExecutionMode};
+use crate::error::{self, BotError};
+use crate::pump_idl::{
+    create_collect_creator_fee_instruction, get_creator_vault_pda, CollectCreatorFeeAccounts,
+    EVENT_AUTHORITY, PUMP_PROGRAM_ID,
+};
+use crate::sender::{self, SendConfig};
+
+/// SOL sitting in a creator vault
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CreatorFees {
+    pub vault: Pubkey,
+    /// Lamports in the vault, 0 if no trade has paid into it yet
+    pub balance: u64,
+    /// Lamports the vault keeps to stay rent exempt
+    pub rent_exempt_minimum: u64,
+}
+
+impl CreatorFees {
+    /// Lamports a claim would pay out
+    pub fn claimable(&self) -> u64 {
+        self.balance.saturating_sub(self.rent_exempt_minimum)
+    }
+}
+
+/// The fees `creator` can claim from the bonding curve creator vault
+pub fn fetch_creator_fees(rpc: &RpcClient, creator: &Pubkey) -> error::Result<CreatorFees> {
+    let (vault, _) = get_creator_vault_pda(creator);
+    Ok(CreatorFees {
+        vault,
+        balance: rpc.get_balance(&vault)?,
+        // The vault holds no data, only lamports
+        rent_exempt_minimum: rpc.get_minimum_balance_for_rent_exemption(0)?,
+    })
+}
+
+/// Instruction moving everything above rent exemption from `creator`'s vault to `creator`
+pub fn collect_creator_fee_instruction(creator: &Pubkey) -> Instruction {
+    let (creator_vault, _) = get_creator_vault_pda(creator);
+    create_collect_creator_fee_instruction(CollectCreatorFeeAccounts {
+        creator: *creator,
+        creator_vault,
+        system_program: system_program::ID,
+        event_authority: *EVENT_AUTHORITY,
+        program: *PUMP_PROGRAM_ID,
+    })
+}
+
+/// Claim `creator`'s bonding curve fees. Live mode sends the claim, other modes simulate
+/// it. Returns the lamports claimable beforehand, 0 when there was nothing to claim.
+#[instrument(skip_all, fields(creator = %creator.pubkey()))]
+pub fn claim_creator_fees(rpc: &RpcClient, creator: &dyn Signer) -> error::Result<u64> {
+    let fees = fetch_creator_fees(rpc, &creator.pubkey())?;
+    let claimable = fees.claimable();
+    if claimable == 0 {
+        info!(vault = %fees.vault, "No creator fees to claim");
+        return Ok(0);
+    }
+
+    let mut instructions = config::get().fees.compute_budget_instructions();
+    instructions.push(collect_creator_fee_instruction(&creator.pubkey()));
+    if config::get().trading.mode != ExecutionMode::Live {
+        sender::simulate_and_print(rpc, &instructions, creator)?;
+        return Ok(claimable);
+    }
+    let instructions = sender::simulate_for_send(rpc, &instructions, creator)?;
+    let result =
+        sender::send_and_confirm_with_retries(rpc, &instructions, creator, &SendConfig::default())?;
+    if let Some(e) = BotError::from_trade_status(&creator.pubkey(), &result.status) {
+        return Err(e);
+    }
+    info!(
+        signature = %result.signature,
+        sol = claimable as f64 / LAMPORTS_PER_SOL as f64,
+        "Claimed creator fees"
+    );
+    Ok(claimable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pump_idl::COLLECT_CREATOR_FEE_DISCRIMINATOR;
+
+    #[test]
+    fn test_collect_creator_fee_instruction() {
+        let creator = Pubkey::new_unique();
+        let ix = collect_creator_fee_instruction(&creator);
+        assert_eq!(ix.data, COLLECT_CREATOR_FEE_DISCRIMINATOR);
+        assert_eq!(ix.accounts[0].pubkey, creator);
+        assert!(ix.accounts[0].is_signer && ix.accounts[0].is_writable);
+        assert_eq!(ix.accounts[1].pubkey, get_creator_vault_pda(&creator).0);
+        assert!(ix.accounts[1].is_writable);
+
+        let fees = CreatorFees {
+            vault: ix.accounts[1].pubkey,
+            balance: 1_000_000,
+            rent_exempt_minimum: 890_880,
+        };
+        assert_eq!(fees.claimable(), 109_120);
+        let empty = CreatorFees { balance: 0, ..fees };
+        assert_eq!(empty.claimable(), 0);
+    }
+}
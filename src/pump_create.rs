@@ -0,0 +1,625 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_program, sysvar,
+    transaction::{Transaction, VersionedTransaction},
+};
+use spl_associated_token_account::{
+    get_associated_token_address, instruction::create_associated_token_account_idempotent,
+};
+use spl_token::ID as TOKEN_PROGRAM_ID;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{info, instrument, warn};
+
+use crate::accounts::{BondingCurve, FeeConfig};
+use crate::config::ExecutionMode;
+use crate::error;
+use crate::metadata;
+use crate::portfolio::{Fill, Portfolio, Side};
+use crate::pump_idl::{
+    create_create_instruction, get_bonding_curve_pda, get_mint_authority_pda, CreateAccounts,
+    EVENT_AUTHORITY, GLOBAL_ADDRESS, PUMP_PROGRAM_ID,
+};
+use crate::tx_fees::{self, TxOutcome};
+use crate::wallets::WalletPool;
+use crate::{cal, config, jito, migrations, price_feed, pump_buy, sender, snipe};
+
+// Constants
+lazy_static::lazy_static! {
+    static ref METAPLEX_PROGRAM_ID: Pubkey = Pubkey::from_str("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s").unwrap();
+}
+/// pump.fun's metadata upload endpoint, which pins the image and metadata JSON to IPFS
+const IPFS_UPLOAD_URL: &str = "https://pump.fun/api/ipfs";
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(60);
+/// Metaplex limits on the metadata fields, in bytes
+const MAX_NAME_LEN: usize = 32;
+const MAX_SYMBOL_LEN: usize = 10;
+const MAX_URI_LEN: usize = 200;
+/// Compute units for creating the mint, curve and metadata and then buying
+const LAUNCH_COMPUTE_UNIT_LIMIT: u32 = 400_000;
+
+/// What a launched token shows: uploaded as its off-chain metadata
+#[derive(Debug, Clone, Default)]
+pub struct TokenInfo {
+    pub name: String,
+    pub symbol: String,
+    pub description: String,
+    /// Path of the image file to upload
+    pub image_path: String,
+    pub twitter: Option<String>,
+    pub telegram: Option<String>,
+    pub website: Option<String>,
+}
+
+/// Arguments for the create instruction, `uri` pointing at the off-chain metadata JSON
+pub use crate::pump_idl::CreateArgs;
+
+impl CreateArgs {
+    /// Reject fields the Metaplex metadata program would
+    pub fn validate(&self) -> Result<()> {
+        let limits = [
+            ("name", &self.name, MAX_NAME_LEN),
+            ("symbol", &self.symbol, MAX_SYMBOL_LEN),
+            ("uri", &self.uri, MAX_URI_LEN),
+        ];
+        for (field, value, max) in limits {
+            if value.is_empty() || value.len() > max {
+                return Err(anyhow!("Token {} must be 1 to {} bytes", field, max));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UploadResponse {
+    metadata_uri: String,
+}
+
+/// A `multipart/form-data` body of text `fields` followed by one file part
+fn multipart_body(
+    boundary: &str,
+    fields: &[(&str, &str)],
+    file_name: &str,
+    file: &[u8],
+) -> Vec<u8> {
+    let mut body = Vec::with_capacity(file.len() + 512);
+    for (name, value) in fields {
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                boundary, name, value
+            )
+            .as_bytes(),
+        );
+    }
+    body.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n",
+            boundary, file_name
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(file);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    body
+}
+
+/// Upload `info` and its image to IPFS, returning the metadata URI a create points at
+pub fn upload_metadata(info: &TokenInfo) -> Result<String> {
+    let image = std::fs::read(&info.image_path)
+        .map_err(|e| anyhow!("Failed to read image {}: {}", info.image_path, e))?;
+    let file_name = Path::new(&info.image_path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("image");
+    let mut fields = vec![
+        ("name", info.name.as_str()),
+        ("symbol", info.symbol.as_str()),
+        ("description", info.description.as_str()),
+        ("showName", "true"),
+    ];
+    for (name, value) in [
+        ("twitter", &info.twitter),
+        ("telegram", &info.telegram),
+        ("website", &info.website),
+    ] {
+        if let Some(value) = value {
+            fields.push((name, value.as_str()));
+        }
+    }
+    let boundary = format!("----pump-create-{}", Keypair::new().pubkey());
+    let body = multipart_body(&boundary, &fields, file_name, &image);
+
+    let client = reqwest::blocking::Client::builder()
+        .timeout(UPLOAD_TIMEOUT)
+        .build()?;
+    let response: UploadResponse = client
+        .post(IPFS_UPLOAD_URL)
+        .header(
+            reqwest::header::CONTENT_TYPE,
+            format!("multipart/form-data; boundary={}", boundary),
+        )
+        .body(body)
+        .send()?
+        .error_for_status()?
+        .json()?;
+    Ok(response.metadata_uri)
+}
+
+/// Create instruction launching `mint` with a fresh bonding curve and Metaplex metadata.
+/// Both `user` and `mint` must sign.
+pub fn create_instruction(user: &Pubkey, mint: &Pubkey, args: &CreateArgs) -> Instruction {
+    let (mint_authority, _) = get_mint_authority_pda();
+    let (bonding_curve, _) = get_bonding_curve_pda(mint);
+    let (metadata, _) = metadata::get_metadata_pda(mint);
+
+    create_create_instruction(
+        CreateAccounts {
+            mint: *mint,
+            mint_authority,
+            bonding_curve,
+            associated_bonding_curve: get_associated_token_address(&bonding_curve, mint),
+            global: *GLOBAL_ADDRESS,
+            mpl_token_metadata: *METAPLEX_PROGRAM_ID,
+            metadata,
+            user: *user,
+            system_program: system_program::ID,
+            token_program: TOKEN_PROGRAM_ID,
+            associated_token_program: spl_associated_token_account::ID,
+            rent: sysvar::rent::ID,
+            event_authority: *EVENT_AUTHORITY,
+            program: *PUMP_PROGRAM_ID,
+        },
+        args.clone(),
+    )
+}
+
+/// The curve a launch by `creator` opens with
+fn opening_curve(creator: &Pubkey) -> BondingCurve {
+    BondingCurve {
+        creator: *creator,
+        ..cal::new_bonding_curve(&cal::Global::default())
+    }
+}
+
+/// Token account creation and a buy of `token_amount` tokens of a just launched `mint`
+fn buy_instructions(
+    user: &Pubkey,
+    mint: &Pubkey,
+    creator: &Pubkey,
+    token_amount: u64,
+    max_sol_cost: u64,
+) -> [Instruction; 2] {
+    [
+        create_associated_token_account_idempotent(user, user, mint, &TOKEN_PROGRAM_ID),
+        pump_buy::buy_instruction(
+            user,
+            mint,
+            creator,
+            &TOKEN_PROGRAM_ID,
+            token_amount,
+            max_sol_cost,
+        ),
+    ]
+}
+
+/// A token launch: create `mint` and buy into it in the same transaction, so nobody
+/// trades ahead of the creator
+#[derive(Debug, Clone)]
+pub struct Launch {
+    pub instructions: Vec<Instruction>,
+    /// The dev buy as it would fill at the opening reserves
+    pub fill: Fill,
+}
+
+/// Compute budget, create and dev buy instructions launching `mint` as `user`.
+/// `fee_config` prices the dev buy; None charges the default fees.
+pub fn launch_instructions(
+    user: &Pubkey,
+    mint: &Pubkey,
+    args: &CreateArgs,
+    fee_config: Option<&FeeConfig>,
+    dev_buy_lamports: u64,
+    slippage_bps: u64,
+) -> Launch {
+    let mut instructions = sender::with_compute_unit_limit(
+        &config::get().fees.compute_budget_instructions(),
+        LAUNCH_COMPUTE_UNIT_LIMIT,
+    );
+    instructions.push(create_instruction(user, mint, args));
+
+    let (token_amount, fee) =
+        snipe::quote_launch(fee_config, &opening_curve(&args.creator), dev_buy_lamports);
+    instructions.extend(buy_instructions(
+        user,
+        mint,
+        &args.creator,
+        token_amount,
+        pump_buy::with_slippage(dev_buy_lamports, slippage_bps),
+    ));
+    Launch {
+        instructions,
+        fill: Fill::new(
+            *mint,
+            Side::Buy,
+            token_amount,
+            dev_buy_lamports,
+            fee,
+            String::new(),
+        ),
+    }
+}
+
+/// Launch a token described by `args` from `user` with a fresh mint, buying
+/// `dev_buy_lamports` worth in the same transaction with `trading.buy_slippage_bps`.
+/// Goes through the sender, so simulate and paper modes send nothing. Returns the mint.
+#[instrument(skip_all, fields(user = %user.pubkey(), symbol = %args.symbol))]
+pub fn launch(
+    rpc: &RpcClient,
+    user: &dyn Signer,
+    args: &CreateArgs,
+    dev_buy_lamports: u64,
+) -> error::Result<Pubkey> {
+    args.validate()?;
+    if dev_buy_lamports == 0 {
+        return Err(anyhow!("A launch needs a dev buy").into());
+    }
+    let mint = Keypair::new();
+    let launch = launch_instructions(
+        &user.pubkey(),
+        &mint.pubkey(),
+        args,
+        snipe::fetch_fee_config(rpc).as_ref(),
+        dev_buy_lamports,
+        config::get().trading.buy_slippage_bps,
+    );
+
+    let (blockhash, last_valid_block_height) =
+        rpc.get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())?;
+    let mut transaction = Transaction::new_with_payer(&launch.instructions, Some(&user.pubkey()));
+    let signers: [&dyn Signer; 2] = [user, &mint];
+    transaction
+        .try_sign(&signers, blockhash)
+        .map_err(|e| anyhow!("Failed to sign launch: {}", e))?;
+    let transaction = VersionedTransaction::from(transaction);
+    info!(mint = %mint.pubkey(), dev_buy_lamports, "Launching token");
+
+    migrations::watch_mint(&mint.pubkey());
+    sender::execute_signed_trade(
+        rpc,
+        &transaction,
+        last_valid_block_height,
+        user,
+        launch.fill,
+    )?;
+    Ok(mint.pubkey())
+}
+
+/// A launch sent as a Jito bundle: the create and dev buy, then buys from other wallets,
+/// landing in one block with nobody trading in between. Buys run in the order they are
+/// added, each quoted on the curve the buys before it leave.
+pub struct LaunchBundle<'a> {
+    creator: &'a dyn Signer,
+    mint: Keypair,
+    args: CreateArgs,
+    dev_buy_lamports: u64,
+    buys: Vec<(&'a dyn Signer, u64)>,
+    slippage_bps: u64,
+    tip_lamports: u64,
+}
+
+/// Signed bundle transactions with the fill each one's buy is quoted at
+#[derive(Debug, Clone)]
+pub struct SignedBundle {
+    pub transactions: Vec<VersionedTransaction>,
+    pub fills: Vec<Fill>,
+}
+
+impl<'a> LaunchBundle<'a> {
+    /// A launch of a fresh mint by `creator` with no bundled buys yet, using
+    /// `trading.buy_slippage_bps` and `jito.tip_lamports`
+    pub fn new(creator: &'a dyn Signer, args: CreateArgs, dev_buy_lamports: u64) -> Self {
+        let config = config::get();
+        Self {
+            creator,
+            mint: Keypair::new(),
+            args,
+            dev_buy_lamports,
+            buys: Vec::new(),
+            slippage_bps: config.trading.buy_slippage_bps,
+            tip_lamports: config.jito.tip_lamports,
+        }
+    }
+
+    /// Buy `lamports` worth from `wallet` after the buys already added
+    pub fn with_buy(mut self, wallet: &'a dyn Signer, lamports: u64) -> Self {
+        self.buys.push((wallet, lamports));
+        self
+    }
+
+    /// Add the `launch.bundle_buys` of `pool`'s wallets, in order
+    pub fn with_configured_buys(mut self, pool: &'a WalletPool) -> Result<Self> {
+        for (index, lamports) in &config::get().launch.bundle_buys {
+            let wallet = pool
+                .get(*index)
+                .ok_or_else(|| anyhow!("No wallet {} in a pool of {}", index, pool.len()))?;
+            self = self.with_buy(wallet, *lamports);
+        }
+        Ok(self)
+    }
+
+    pub fn with_slippage_bps(mut self, slippage_bps: u64) -> Self {
+        self.slippage_bps = slippage_bps;
+        self
+    }
+
+    pub fn with_tip_lamports(mut self, tip_lamports: u64) -> Self {
+        self.tip_lamports = tip_lamports;
+        self
+    }
+
+    pub fn mint(&self) -> Pubkey {
+        self.mint.pubkey()
+    }
+
+    /// Sign every transaction against `blockhash`. The create and dev buy go first,
+    /// then one transaction per bundled buy, and the creator pays the tip in the last.
+    pub fn build(&self, fee_config: Option<&FeeConfig>, blockhash: Hash) -> Result<SignedBundle> {
+        self.args.validate()?;
+        if self.dev_buy_lamports == 0 {
+            return Err(anyhow!("A launch needs a dev buy"));
+        }
+        if self.buys.len() >= jito::MAX_BUNDLE_TRANSACTIONS {
+            return Err(anyhow!(
+                "At most {} buys fit in a launch bundle, not {}",
+                jito::MAX_BUNDLE_TRANSACTIONS - 1,
+                self.buys.len()
+            ));
+        }
+
+        let creator = self.creator.pubkey();
+        let mint = self.mint.pubkey();
+        let launch = launch_instructions(
+            &creator,
+            &mint,
+            &self.args,
+            fee_config,
+            self.dev_buy_lamports,
+            self.slippage_bps,
+        );
+        let opening = opening_curve(&self.args.creator);
+        let mut curve = cal::curve_after_buy(
+            &snipe::launch_global(fee_config, &opening),
+            &opening,
+            self.dev_buy_lamports,
+        );
+
+        // (instructions, fee payer, fill) of every transaction, in bundle order
+        let mut unsigned = vec![(launch.instructions, self.creator, launch.fill)];
+        for (wallet, lamports) in &self.buys {
+            let global = snipe::launch_global(fee_config, &curve);
+            let token_amount = cal::get_tokens_for_sol(&global, Some(&curve), *lamports);
+            if token_amount == 0 {
+                return Err(anyhow!(
+                    "Bundled buy of {} lamports gets no tokens",
+                    lamports
+                ));
+            }
+            let fee = cal::get_fee(&global, &curve, *lamports, false);
+            let mut instructions = config::get().fees.compute_budget_instructions();
+            instructions.extend(buy_instructions(
+                &wallet.pubkey(),
+                &mint,
+                &self.args.creator,
+                token_amount,
+                pump_buy::with_slippage(*lamports, self.slippage_bps),
+            ));
+            let fill = Fill::new(mint, Side::Buy, token_amount, *lamports, fee, String::new());
+            unsigned.push((instructions, *wallet, fill));
+            curve = cal::curve_after_buy(&global, &curve, *lamports);
+        }
+        if let Some((instructions, _, _)) = unsigned.last_mut() {
+            instructions.push(jito::tip_instruction(&creator, self.tip_lamports));
+        }
+
+        let last = unsigned.len() - 1;
+        let mut bundle = SignedBundle {
+            transactions: Vec::with_capacity(unsigned.len()),
+            fills: Vec::with_capacity(unsigned.len()),
+        };
+        for (i, (instructions, payer, fill)) in unsigned.into_iter().enumerate() {
+            let mut signers = vec![payer];
+            if i == 0 {
+                signers.push(&self.mint);
+            }
+            if i == last && payer.pubkey() != creator {
+                signers.push(self.creator);
+            }
+            let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
+            transaction
+                .try_sign(&signers, blockhash)
+                .map_err(|e| anyhow!("Failed to sign bundle transaction {}: {}", i, e))?;
+            bundle
+                .transactions
+                .push(VersionedTransaction::from(transaction));
+            bundle.fills.push(fill);
+        }
+        Ok(bundle)
+    }
+}
+
+/// Launch `bundle` through the Jito block engine and record every buy once it lands.
+/// Simulate mode simulates only the create transaction, since every later one buys from
+/// the curve it creates, and paper mode records the quoted fills. Returns the mint.
+#[instrument(skip_all, fields(creator = %bundle.creator.pubkey(), symbol = %bundle.args.symbol))]
+pub fn launch_bundle(rpc: &RpcClient, bundle: &LaunchBundle) -> error::Result<Pubkey> {
+    let (blockhash, last_valid_block_height) =
+        rpc.get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())?;
+    let signed = bundle.build(snipe::fetch_fee_config(rpc).as_ref(), blockhash)?;
+    let mint = bundle.mint();
+    info!(%mint, transactions = signed.transactions.len(), "Launching token in a bundle");
+    migrations::watch_mint(&mint);
+
+    let mut fills = signed.fills;
+    let mut costs = Vec::new();
+    match config::get().trading.mode {
+        ExecutionMode::Simulate => {
+            sender::simulate_transaction(rpc, &signed.transactions[0])?;
+            info!("Create simulation successful, ready to send bundle");
+            return Ok(mint);
+        }
+        ExecutionMode::Paper => {
+            for fill in &mut fills {
+                fill.signature = sender::paper_signature();
+            }
+        }
+        ExecutionMode::Live => {
+            let bundle_id = jito::send_bundle(&signed.transactions)?;
+            let signature = signed.transactions[0].signatures[0];
+            match jito::confirm_bundle(rpc, &signature, last_valid_block_height)? {
+                Some(Ok(())) => info!(%bundle_id, %signature, "Launch bundle landed"),
+                Some(Err(e)) => {
+                    return Err(anyhow!("Launch bundle {} failed: {}", bundle_id, e).into())
+                }
+                None => return Err(anyhow!("Launch bundle {} expired", bundle_id).into()),
+            }
+            for (fill, transaction) in fills.iter_mut().zip(&signed.transactions) {
+                fill.signature = transaction.signatures[0].to_string();
+            }
+            // Every transaction in a landed bundle landed, and one of them paid the tip
+            for transaction in &signed.transactions {
+                let signature = transaction.signatures[0];
+                match tx_fees::fetch_transaction_cost(
+                    rpc,
+                    &signature,
+                    transaction.signatures.len(),
+                    TxOutcome::Succeeded,
+                ) {
+                    Ok(cost) => costs.push(cost),
+                    Err(e) => warn!(%signature, error = %e, "Failed to fetch transaction fee"),
+                }
+            }
+        }
+    }
+
+    let portfolio = Portfolio::open_default()?;
+    for cost in &costs {
+        portfolio.record_tx_cost(&mint, Side::Buy, cost)?;
+    }
+    for fill in &fills {
+        portfolio.record_fill_with_price(fill, price_feed::cached_at(fill.timestamp))?;
+    }
+    Ok(mint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pump_idl::CREATE_DISCRIMINATOR;
+
+    fn args(creator: Pubkey) -> CreateArgs {
+        CreateArgs {
+            name: "Test Token".to_string(),
+            symbol: "TEST".to_string(),
+            uri: "https://ipfs.io/ipfs/test".to_string(),
+            creator,
+        }
+    }
+
+    #[test]
+    fn test_create_instruction_layout() {
+        let (user, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let ix = create_instruction(&user, &mint, &args(user));
+
+        // Discriminator, then borsh strings (u32 length prefix) and the creator
+        assert_eq!(ix.data[..8], CREATE_DISCRIMINATOR);
+        assert_eq!(ix.data[8..12], 10u32.to_le_bytes());
+        assert_eq!(&ix.data[12..22], b"Test Token");
+        assert_eq!(ix.data[ix.data.len() - 32..], user.to_bytes());
+        assert_eq!(ix.data.len(), 8 + (4 + 10) + (4 + 4) + (4 + 25) + 32);
+
+        assert_eq!(ix.accounts.len(), 14);
+        let signers: Vec<_> = ix.accounts.iter().filter(|a| a.is_signer).collect();
+        assert_eq!(signers.len(), 2);
+        assert_eq!((signers[0].pubkey, signers[1].pubkey), (mint, user));
+        assert_eq!(ix.accounts[6].pubkey, metadata::get_metadata_pda(&mint).0);
+
+        let mut long = args(user);
+        long.symbol = "WAYTOOLONGSYMBOL".to_string();
+        assert!(long.validate().is_err());
+        assert!(args(user).validate().is_ok());
+    }
+
+    #[test]
+    fn test_launch_buys_into_its_opening_curve() {
+        let (user, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let launch = launch_instructions(&user, &mint, &args(user), None, 1_000_000_000, 500);
+        let create = launch.instructions.len() - 3;
+        assert_eq!(launch.instructions[create].data[..8], CREATE_DISCRIMINATOR);
+        let fill = launch.fill;
+        assert_eq!(fill.sol_amount, 1_000_000_000);
+        assert!(fill.token_amount > 0);
+
+        // The buy is for the quoted tokens, capped at the lamports plus slippage
+        let buy = launch.instructions.last().unwrap();
+        assert_eq!(buy.data[8..16], fill.token_amount.to_le_bytes());
+        assert_eq!(buy.data[16..24], 1_050_000_000u64.to_le_bytes());
+    }
+
+    #[test]
+    fn test_bundle_buys_in_order_on_a_moving_curve() {
+        let creator = Keypair::new();
+        let (first, second) = (Keypair::new(), Keypair::new());
+        let bundle = LaunchBundle::new(&creator, args(creator.pubkey()), 1_000_000_000)
+            .with_buy(&first, 500_000_000)
+            .with_buy(&second, 500_000_000)
+            .with_tip_lamports(10_000);
+        let signed = bundle.build(None, Hash::default()).unwrap();
+        assert_eq!(signed.transactions.len(), 3);
+
+        // The mint co-signs the create and the creator the tip
+        let signers: Vec<usize> = signed
+            .transactions
+            .iter()
+            .map(|tx| tx.signatures.len())
+            .collect();
+        assert_eq!(signers, vec![2, 1, 2]);
+        assert!(signed
+            .transactions
+            .iter()
+            .all(|tx| tx.verify_with_results().iter().all(|ok| *ok)));
+
+        // Equal buys get fewer tokens the later they run
+        let tokens: Vec<u64> = signed.fills.iter().map(|f| f.token_amount).collect();
+        assert!(tokens[1] > tokens[2]);
+
+        let crowded = (0..jito::MAX_BUNDLE_TRANSACTIONS).fold(
+            LaunchBundle::new(&creator, args(creator.pubkey()), 1),
+            |bundle, _| bundle.with_buy(&first, 1_000),
+        );
+        assert!(crowded.build(None, Hash::default()).is_err());
+    }
+
+    #[test]
+    fn test_multipart_body() {
+        let body = multipart_body("b", &[("name", "Test")], "a.png", b"PNG");
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            "--b\r\nContent-Disposition: form-data; name=\"name\"\r\n\r\nTest\r\n\
+             --b\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a.png\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\nPNG\r\n--b--\r\n"
+        );
+    }
+}
@@ -0,0 +1,211 @@
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program,
+};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use std::str::FromStr;
+
+// Discriminators, `<Name>Accounts` and `<Name>Args` structs and `create_<name>_instruction`
+// builders for every instruction in `idl.json`, and `<NAME>_LEN` data lengths of its
+// fixed-size accounts, generated by build.rs. When the IDL appends a bonding curve field,
+// curves created before that are extended in the same transaction as the next trade.
+include!(concat!(env!("OUT_DIR"), "/pump_idl.rs"));
+
+// Constants
+lazy_static::lazy_static! {
+    pub static ref PUMP_PROGRAM_ID: Pubkey = PROGRAM_ID;
+    pub static ref GLOBAL_ADDRESS: Pubkey = Pubkey::from_str("4wTV1YmiEkRvAtNtsSGPtUrqRYQMe5SKy2uB4Jjaxnjf").unwrap();
+    pub static ref EVENT_AUTHORITY: Pubkey = Pubkey::from_str("Ce6TQqeHC9p8KetsN6JsjHK7UTZk7nasjjnr7XxXp9F1").unwrap();
+    pub static ref FEE_PROGRAM: Pubkey = Pubkey::from_str("pfeeUxB6jkeY1Hxd7CsFCAjcbHA9rWtchMGdZ6VojVZ").unwrap();
+    pub static ref FEE_CONFIG: Pubkey = Pubkey::from_str("8Wf5TiAheLUqBrKXeYg2JtAFFMWtKdG2BSFgqUcPVwTt").unwrap();
+}
+
+/// Derive the bonding curve PDA for a mint
+pub fn get_bonding_curve_pda(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"bonding-curve", mint.as_ref()], &PUMP_PROGRAM_ID)
+}
+
+/// Derive the creator vault PDA that bonding curve trades pay `creator`'s fees into
+pub fn get_creator_vault_pda(creator: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"creator-vault", creator.as_ref()], &PUMP_PROGRAM_ID)
+}
+
+/// Derive the global volume accumulator PDA
+pub fn get_global_volume_accumulator_pda() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"global_volume_accumulator"], &PUMP_PROGRAM_ID)
+}
+
+/// Derive the user volume accumulator PDA
+pub fn get_user_volume_accumulator_pda(user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"user_volume_accumulator", user.as_ref()],
+        &PUMP_PROGRAM_ID,
+    )
+}
+
+/// Derive the mint authority PDA every pump.fun mint is created with
+pub fn get_mint_authority_pda() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"mint-authority"], &PUMP_PROGRAM_ID)
+}
+
+impl BuyAccounts {
+    /// Accounts for `user` buying `mint`, launched by `creator`, into its ATA
+    pub fn new(
+        user: &Pubkey,
+        mint: &Pubkey,
+        creator: &Pubkey,
+        token_program: &Pubkey,
+        fee_recipient: Pubkey,
+    ) -> Self {
+        let (bonding_curve, _) = get_bonding_curve_pda(mint);
+        Self {
+            global: *GLOBAL_ADDRESS,
+            fee_recipient,
+            mint: *mint,
+            bonding_curve,
+            associated_bonding_curve: get_associated_token_address_with_program_id(
+                &bonding_curve,
+                mint,
+                token_program,
+            ),
+            associated_user: get_associated_token_address_with_program_id(
+                user,
+                mint,
+                token_program,
+            ),
+            user: *user,
+            system_program: system_program::ID,
+            token_program: *token_program,
+            creator_vault: get_creator_vault_pda(creator).0,
+            event_authority: *EVENT_AUTHORITY,
+            program: *PUMP_PROGRAM_ID,
+            global_volume_accumulator: get_global_volume_accumulator_pda().0,
+            user_volume_accumulator: get_user_volume_accumulator_pda(user).0,
+            fee_config: *FEE_CONFIG,
+            fee_program: *FEE_PROGRAM,
+        }
+    }
+}
+
+impl SellAccounts {
+    /// Accounts for `user` selling `mint`, launched by `creator`, out of its ATA
+    pub fn new(
+        user: &Pubkey,
+        mint: &Pubkey,
+        creator: &Pubkey,
+        token_program: &Pubkey,
+        fee_recipient: Pubkey,
+    ) -> Self {
+        let (bonding_curve, _) = get_bonding_curve_pda(mint);
+        Self {
+            global: *GLOBAL_ADDRESS,
+            fee_recipient,
+            mint: *mint,
+            bonding_curve,
+            associated_bonding_curve: get_associated_token_address_with_program_id(
+                &bonding_curve,
+                mint,
+                token_program,
+            ),
+            associated_user: get_associated_token_address_with_program_id(
+                user,
+                mint,
+                token_program,
+            ),
+            user: *user,
+            system_program: system_program::ID,
+            creator_vault: get_creator_vault_pda(creator).0,
+            token_program: *token_program,
+            event_authority: *EVENT_AUTHORITY,
+            program: *PUMP_PROGRAM_ID,
+            fee_config: *FEE_CONFIG,
+            fee_program: *FEE_PROGRAM,
+        }
+    }
+}
+
+/// `extend_account` for a bonding curve whose data is shorter than the program reads,
+/// None when it is already full size
+pub fn extend_bonding_curve(
+    bonding_curve: &Pubkey,
+    data: &[u8],
+    user: &Pubkey,
+) -> Option<Instruction> {
+    (data.len() < BONDING_CURVE_LEN).then(|| {
+        create_extend_account_instruction(ExtendAccountAccounts {
+            account: *bonding_curve,
+            user: *user,
+            system_program: system_program::ID,
+            event_authority: *EVENT_AUTHORITY,
+            program: PROGRAM_ID,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounts::{AnchorAccount, BondingCurve};
+    use crate::cal;
+
+    #[test]
+    fn test_buy_and_sell_share_the_curve_accounts() {
+        let (user, mint, creator) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let token_program = spl_token::ID;
+        let fee_recipient = Pubkey::new_unique();
+
+        let buy = create_buy_instruction(
+            BuyAccounts::new(&user, &mint, &creator, &token_program, fee_recipient),
+            BuyArgs {
+                amount: 1_000,
+                max_sol_cost: 2_000,
+                track_volume: Some(true),
+            },
+        );
+        assert_eq!(buy.data[..8], BUY_DISCRIMINATOR);
+        assert_eq!(buy.data[8..16], 1_000u64.to_le_bytes());
+        assert_eq!(buy.data[16..24], 2_000u64.to_le_bytes());
+        assert_eq!(buy.data[24..], [1, 1]);
+        assert_eq!(buy.accounts.len(), 16);
+
+        let sell = create_sell_instruction(
+            SellAccounts::new(&user, &mint, &creator, &token_program, fee_recipient),
+            SellArgs {
+                amount: 1_000,
+                min_sol_output: 500,
+            },
+        );
+        assert_eq!(sell.data[..8], SELL_DISCRIMINATOR);
+        assert_eq!(sell.data.len(), 24);
+        assert_eq!(sell.accounts.len(), 14);
+
+        // Same curve, token accounts, signer and vault in both, the vault moved one slot
+        assert_eq!(buy.accounts[..8], sell.accounts[..8]);
+        assert_eq!(buy.accounts[3].pubkey, get_bonding_curve_pda(&mint).0);
+        assert!(buy.accounts[6].is_signer);
+        assert_eq!(buy.accounts[9].pubkey, get_creator_vault_pda(&creator).0);
+        assert_eq!(sell.accounts[8].pubkey, get_creator_vault_pda(&creator).0);
+    }
+
+    #[test]
+    fn test_short_bonding_curves_are_extended() {
+        let curve = cal::new_bonding_curve(&cal::Global::default());
+        let mut data = BondingCurve::DISCRIMINATOR.to_vec();
+        data.extend(borsh::to_vec(&curve).unwrap());
+        assert_eq!(data.len(), BONDING_CURVE_LEN);
+
+        let (bonding_curve, user) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert!(extend_bonding_curve(&bonding_curve, &data, &user).is_none());
+        let extend = extend_bonding_curve(&bonding_curve, &data[..49], &user).unwrap();
+        assert_eq!(extend.data, EXTEND_ACCOUNT_DISCRIMINATOR);
+        assert_eq!(extend.accounts[0].pubkey, bonding_curve);
+        assert!(extend.accounts[0].is_writable);
+        assert!(extend.accounts[1].is_signer && extend.accounts[1].is_writable);
+        assert_eq!(extend.program_id, *PUMP_PROGRAM_ID);
+    }
+}
@@ -1,311 +1,422 @@
-use anyhow::{anyhow, Result};
+use anyhow::anyhow;
 use solana_client::rpc_client::RpcClient;
 #[allow(deprecated)]
 use solana_sdk::{
-    instruction::{AccountMeta, Instruction},
-    native_token::LAMPORTS_PER_SOL,
+    instruction::Instruction,
     pubkey::Pubkey,
-    signature::Keypair,
     signer::Signer,
-    system_program,
-    transaction::Transaction,
 };
 use spl_associated_token_account::get_associated_token_address_with_program_id;
 use spl_token::ID as TOKEN_PROGRAM_ID;
 use spl_token_2022::ID as TOKEN_2022_PROGRAM_ID;
-use std::str::FromStr;
-
-// Constants
-const MAINNET_RPC: &str = "https://api.mainnet-beta.solana.com";
-const PRIVATE_KEY: &str = "priv-key";
-
-const FEE_RECIPIENT: &str = "CebN5WGQ4jvEPvsVU4EoHEpgzq1VV7AbicfhtW4xC9iM";
-
-
-lazy_static::lazy_static! {
-    static ref PUMP_PROGRAM_ID: Pubkey = Pubkey::from_str("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P").unwrap();
-    static ref GLOBAL_ADDRESS: Pubkey = Pubkey::from_str("4wTV1YmiEkRvAtNtsSGPtUrqRYQMe5SKy2uB4Jjaxnjf").unwrap();
-    static ref EVENT_AUTHORITY: Pubkey = Pubkey::from_str("Ce6TQqeHC9p8KetsN6JsjHK7UTZk7nasjjnr7XxXp9F1").unwrap();
-    static ref FEE_PROGRAM: Pubkey = Pubkey::from_str("pfeeUxB6jkeY1Hxd7CsFCAjcbHA9rWtchMGdZ6VojVZ").unwrap();
-    static ref FEE_CONFIG: Pubkey = Pubkey::from_str("8Wf5TiAheLUqBrKXeYg2JtAFFMWtKdG2BSFgqUcPVwTt").unwrap();
-}
-
-/// Sell instruction discriminator (from IDL: [51, 230, 133, 164, 1, 127, 131, 173])
-const SELL_DISCRIMINATOR: [u8; 8] = [51, 230, 133, 164, 1, 127, 131, 173];
-
-/// Accounts needed for the sell instruction
-pub struct SellAccounts {
-    pub global: Pubkey,
-    pub fee_recipient: Pubkey,
-    pub mint: Pubkey,
-    pub bonding_curve: Pubkey,
-    pub associated_bonding_curve: Pubkey,
-    pub associated_user: Pubkey,
-    pub user: Pubkey,
-    pub system_program: Pubkey,
-    pub creator_vault: Pubkey,
-    pub token_program: Pubkey,
-    pub event_authority: Pubkey,
-    pub program: Pubkey,
-    pub fee_config: Pubkey,
-    pub fee_program: Pubkey,
-}
-
-/// Arguments for the sell instruction
-pub struct SellArgs {
-    pub amount: u64,
-    pub min_sol_output: u64,
-}
-
-/// Load wallet from base58 encoded private key
-fn load_wallet_from_private_key(private_key: &str) -> Result<Keypair> {
-    let secret_key = bs58::decode(private_key)
-        .into_vec()
-        .map_err(|e| anyhow!("Failed to decode private key: {}", e))?;
-    Keypair::try_from(secret_key.as_slice()).map_err(|e| anyhow!("Failed to create keypair: {}", e))
+use tracing::{debug, info, instrument};
+use crate::accounts::{AnchorAccount, BondingCurve};
+use crate::amount::{SolAmount, TokenAmount};
+use crate::config::ExecutionMode;
+use crate::portfolio::{Fill, Portfolio, Side};
+use crate::pump_idl::{
+    create_sell_instruction, extend_bonding_curve, get_bonding_curve_pda, get_creator_vault_pda,
+    SellAccounts, SellArgs,
+};
+use crate::error::{self, BotError};
+use crate::router::{self, Venue};
+use crate::sender;
+use crate::{cal, config, fees, rpc_pool, signer, sizing, token_accounts};
+
+/// How much of a position to sell
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SellAmount {
+    /// Entire spendable balance
+    All,
+    /// Percent of the spendable balance (0.0 - 100.0]
+    Percent(f64),
+    /// Exact raw token amount
+    Tokens(u64),
+    /// Fewest tokens whose sale nets at least this many lamports
+    SolTarget(u64),
 }
 
-/// Create the sell instruction
-fn create_sell_instruction(accounts: SellAccounts, args: SellArgs) -> Instruction {
-    // Build instruction data: discriminator (8) + amount (8) + min_sol_output (8)
-    let mut data = Vec::with_capacity(24);
-
-    // Add discriminator
-    data.extend_from_slice(&SELL_DISCRIMINATOR);
-
-    // Add amount (u64 little-endian)
-    data.extend_from_slice(&args.amount.to_le_bytes());
-
-    // Add min_sol_output (u64 little-endian)
-    data.extend_from_slice(&args.min_sol_output.to_le_bytes());
-
-    // Build account metas (order from IDL)
-    let keys = vec![
-        AccountMeta::new_readonly(accounts.global, false),
-        AccountMeta::new(accounts.fee_recipient, false),
-        AccountMeta::new_readonly(accounts.mint, false),
-        AccountMeta::new(accounts.bonding_curve, false),
-        AccountMeta::new(accounts.associated_bonding_curve, false),
-        AccountMeta::new(accounts.associated_user, false),
-        AccountMeta::new(accounts.user, true),
-        AccountMeta::new_readonly(accounts.system_program, false),
-        AccountMeta::new(accounts.creator_vault, false),
-        AccountMeta::new_readonly(accounts.token_program, false),
-        AccountMeta::new_readonly(accounts.event_authority, false),
-        AccountMeta::new_readonly(accounts.program, false),
-        AccountMeta::new_readonly(accounts.fee_config, false),
-        AccountMeta::new_readonly(accounts.fee_program, false),
-    ];
-
-    Instruction {
-        program_id: *PUMP_PROGRAM_ID,
-        accounts: keys,
-        data,
+impl From<TokenAmount> for SellAmount {
+    fn from(tokens: TokenAmount) -> Self {
+        SellAmount::Tokens(tokens.raw())
     }
 }
 
-/// Derive the bonding curve PDA
-fn get_bonding_curve_pda(mint: &Pubkey) -> (Pubkey, u8) {
-    Pubkey::find_program_address(&[b"bonding-curve", mint.as_ref()], &PUMP_PROGRAM_ID)
+impl SellAmount {
+    /// Resolve to a raw token amount given the wallet's spendable `balance`. Never
+    /// exceeds it: an exact amount the wallet can't cover is an `InsufficientTokens` error.
+    pub fn resolve(
+        &self,
+        balance: u64,
+        global: &cal::Global,
+        curve: Option<&cal::BondingCurve>,
+    ) -> error::Result<u64> {
+        let amount = match *self {
+            SellAmount::All => balance,
+            SellAmount::Percent(pct) => {
+                if !(pct > 0.0 && pct <= 100.0) {
+                    return Err(anyhow!("Sell percent must be in (0, 100], got {}", pct).into());
+                }
+                // In integers, as f64 can't hold every raw balance exactly
+                let ppm = (pct * 10_000.0).round() as u128;
+                (balance as u128 * ppm / 1_000_000) as u64
+            }
+            SellAmount::Tokens(tokens) if tokens > balance => {
+                return Err(BotError::InsufficientTokens {
+                    needed: tokens,
+                    available: balance,
+                });
+            }
+            SellAmount::Tokens(tokens) => tokens,
+            SellAmount::SolTarget(lamports) => cal::get_tokens_for_sol_output(
+                global, curve, lamports, balance,
+            )
+            .ok_or_else(|| {
+                anyhow!(
+                    "Selling the whole balance of {} tokens nets less than {} lamports",
+                    balance,
+                    lamports
+                )
+            })?,
+        };
+
+        if amount == 0 {
+            return Err(anyhow!("Sell amount resolves to zero tokens").into());
+        }
+        Ok(amount)
+    }
 }
 
-/// Derive the creator vault PDA
-fn get_creator_vault_pda(creator: &Pubkey) -> (Pubkey, u8) {
-    Pubkey::find_program_address(&[b"creator-vault", creator.as_ref()], &PUMP_PROGRAM_ID)
+/// A sell of some amount of one mint
+#[derive(Debug, Clone)]
+pub struct SellRequest {
+    pub mint: Pubkey,
+    pub amount: SellAmount,
+    /// Sell even if it moves the price past `trading.max_price_impact_bps`
+    pub force: bool,
 }
 
-/// Parse creator pubkey from bonding curve account data
-/// Layout: 8 (discriminator) + 8*5 (u64 fields) + 1 (bool) = 49 bytes, then 32 bytes for creator
-fn parse_creator_from_bonding_curve(data: &[u8]) -> Result<Pubkey> {
-    const CREATOR_OFFSET: usize = 8 + 8 + 8 + 8 + 8 + 8 + 1; // 49 bytes
-
-    if data.len() < CREATOR_OFFSET + 32 {
-        return Err(anyhow!(
-            "Bonding curve data too short: {} bytes",
-            data.len()
-        ));
-    }
+/// Resolve accounts and build a sell instruction that sells from the user's ATA
+pub fn build_sell_instruction(
+    connection: &RpcClient,
+    user: &Pubkey,
+    mint: &Pubkey,
+    token_amount: u64,
+    min_sol_output: u64,
+) -> error::Result<Instruction> {
+    fees::refresh_fee_recipients(connection);
+    let fee_recipient = fees::next_fee_recipient();
+    let (bonding_curve, _) = get_bonding_curve_pda(mint);
+
+    let mint_info = cal::fetch_account(connection, mint)?;
+    let token_program_id = if mint_info.owner == TOKEN_2022_PROGRAM_ID {
+        TOKEN_2022_PROGRAM_ID
+    } else {
+        TOKEN_PROGRAM_ID
+    };
 
-    let creator_bytes: [u8; 32] = data[CREATOR_OFFSET..CREATOR_OFFSET + 32]
-        .try_into()
-        .map_err(|_| anyhow!("Failed to parse creator bytes"))?;
+    let bonding_curve_info = cal::fetch_account(connection, &bonding_curve)?;
+    let creator = BondingCurve::try_from_account_data(&bonding_curve_info.data)?.creator;
 
-    Ok(Pubkey::new_from_array(creator_bytes))
+    Ok(create_sell_instruction(
+        SellAccounts::new(user, mint, &creator, &token_program_id, fee_recipient),
+        SellArgs {
+            amount: token_amount,
+            min_sol_output,
+        },
+    ))
 }
 
-/// Main function to execute the pump.fun sell
-pub fn run_pump_sell() -> Result<()> {
+/// Main function to execute a sell with the configured RPC and wallet, routed to
+/// wherever the mint trades
+pub fn run_pump_sell(token_amount: u64, mint: Pubkey, min_sol_output: u64) -> error::Result<()> {
+    // Initialize RPC client
+    let connection = rpc_pool::get_client();
 
+    // Load wallet
+    debug!("Loading wallet from private key");
+    let user = signer::load_default_wallet()?;
 
-    let mint = Pubkey::from_str("Ar4vi1BZXHVgQFRYD8AF7rBe7gsh3D1nM2hZG153pump").unwrap();
-    let min_sol_output: u64 = 0; // Minimum SOL to receive (slippage protection)
-    let mut token_amount: u64 = 1000;
-    println!("Starting mainnet sell test...");
-    println!("Token mint: {}", mint);
+    router::sell(&connection, &user, token_amount, mint, min_sol_output)
+}
 
-    // Initialize RPC client
-    let connection = RpcClient::new(MAINNET_RPC.to_string());
+/// Sell `token_amount` tokens of `mint` from `user`, receiving at least `min_sol_output` lamports
+#[instrument(name = "sell", skip_all, fields(%mint, token_amount, min_sol_output))]
+pub fn sell(
+    connection: &RpcClient,
+    user: &dyn Signer,
+    token_amount: u64,
+    mint: Pubkey,
+    min_sol_output: u64,
+) -> error::Result<()> {
+    info!("Starting sell");
 
-    // Load wallet
-    println!("Loading wallet from private key...");
-    let user = load_wallet_from_private_key(PRIVATE_KEY)?;
-    println!("User address: {}", user.pubkey());
+    let config = config::get();
 
     // Check SOL balance
     let balance = connection.get_balance(&user.pubkey())?;
-    let balance_sol = balance as f64 / LAMPORTS_PER_SOL as f64;
-    println!("Wallet SOL balance: {} SOL", balance_sol);
+    debug!(user = %user.pubkey(), balance = %SolAmount::from_lamports(balance), "Wallet balance");
 
-    // Parse addresses
-    let fee_recipient = Pubkey::from_str(FEE_RECIPIENT)?;
+    // Rotate to the next protocol fee recipient
+    fees::refresh_fee_recipients(connection);
+    let fee_recipient = fees::next_fee_recipient();
 
     // Derive bonding curve PDA
     let (bonding_curve, _) = get_bonding_curve_pda(&mint);
-    println!("Bonding Curve: {}", bonding_curve);
+    debug!(%bonding_curve, "Bonding curve");
 
     // Get mint info to determine token program
-    let mint_info = connection
-        .get_account(&mint)
-        .map_err(|e| anyhow!("Failed to get mint account: {}", e))?;
+    let mint_info = cal::fetch_account(connection, &mint)?;
 
     let token_program_id = if mint_info.owner == TOKEN_2022_PROGRAM_ID {
         TOKEN_2022_PROGRAM_ID
     } else {
         TOKEN_PROGRAM_ID
     };
-    println!("Token Program: {}", token_program_id);
+    debug!(%token_program_id, "Token program");
 
     // Get associated token addresses
     let associated_bonding_curve =
         get_associated_token_address_with_program_id(&bonding_curve, &mint, &token_program_id);
-    println!("Associated Bonding Curve: {}", associated_bonding_curve);
+    debug!(%associated_bonding_curve, "Associated bonding curve");
 
     let associated_user =
         get_associated_token_address_with_program_id(&user.pubkey(), &mint, &token_program_id);
-    println!("Associated Token Account: {}", associated_user);
-
-    // Check if user has tokens to sell
-    match connection.get_account(&associated_user) {
-        Ok(ata_info) => {
-            // Parse token balance (offset 64 for amount in token account)
-            if ata_info.data.len() >= 72 {
-                let amount_bytes: [u8; 8] = ata_info.data[64..72].try_into().unwrap();
-                let token_balance = u64::from_le_bytes(amount_bytes);
-                println!("Token balance: {}", token_balance);
-
-                if token_balance == 0 {
-                    return Err(anyhow!("No tokens to sell"));
-                }
+    debug!(%associated_user, "Associated token account");
+
+    // Check if user has tokens to sell across every account holding the mint
+    let holdings =
+        token_accounts::fetch_token_holdings(connection, &user.pubkey(), &mint, &token_program_id)?;
+    // Paper sells draw on the paper position rather than the wallet
+    let paper = config.trading.mode == ExecutionMode::Paper;
+    let token_balance = if paper {
+        sender::paper_balance(&mint)?
+    } else {
+        holdings.total_spendable()
+    };
+    debug!(
+        token_balance,
+        accounts = holdings.accounts.len(),
+        "Token balance"
+    );
 
-                if token_balance < token_amount {
-                    return Err(anyhow!(
-                        "Insufficient token balance. Have {} but trying to sell {}",
-                        token_balance,
-                        token_amount
-                    ));
-                }
-                token_amount = token_balance
-            }
-        }
-        Err(_) => {
-            return Err(anyhow!("Token account not found - no tokens to sell"));
-        }
+    if token_balance == 0 || token_balance < token_amount {
+        return Err(BotError::InsufficientTokens {
+            needed: token_amount,
+            available: token_balance,
+        });
     }
+    let allocations = if paper {
+        vec![(associated_user, token_amount)]
+    } else {
+        holdings.allocate(token_amount)?
+    };
 
     // Fetch bonding curve to get creator
-    let bonding_curve_info = connection
-        .get_account(&bonding_curve)
-        .map_err(|_| anyhow!("Bonding curve account not found - token may have migrated"))?;
+    let bonding_curve_info = cal::fetch_account(connection, &bonding_curve)?;
 
-    let creator = parse_creator_from_bonding_curve(&bonding_curve_info.data)?;
-    println!("Token Creator: {}", creator);
+    // Graduated tokens are exited through PumpSwap, or Jupiter when they have no pool
+    let curve = BondingCurve::try_from_account_data(&bonding_curve_info.data)?;
+    if curve.complete {
+        info!("Bonding curve complete, routing sell off the curve");
+        return router::sell(connection, user, token_amount, mint, min_sol_output);
+    }
+
+    let creator = curve.creator;
+    debug!(%creator, "Token creator");
 
     // Derive creator vault PDA
     let (creator_vault, _) = get_creator_vault_pda(&creator);
-    println!("Creator Vault: {}", creator_vault);
+    debug!(%creator_vault, "Creator vault");
+
+    // Show cost basis of the position being exited, if we have one on record
+    if let Ok(Some(position)) = Portfolio::open_default().and_then(|p| p.get_position(&mint)) {
+        info!(
+            cost_basis_lamports = position.cost_basis_lamports,
+            position_tokens = position.token_amount,
+            realized_pnl_lamports = position.realized_pnl_lamports,
+            "Exiting position"
+        );
+    }
 
-    println!("\nBuilding sell instruction...");
-    println!("  Amount: {} tokens", token_amount);
-    println!(
-        "  Min SOL output: {} SOL",
-        min_sol_output as f64 / LAMPORTS_PER_SOL as f64
-    );
+    debug!(accounts = allocations.len(), "Building sell instructions");
+
+    // Create one sell instruction per source token account, splitting the
+    // slippage floor proportionally to the amount each one sells
+    let sell_ixs: Vec<Instruction> = allocations
+        .iter()
+        .map(|(source, amount)| {
+            create_sell_instruction(
+                SellAccounts {
+                    associated_user: *source,
+                    ..SellAccounts::new(
+                        &user.pubkey(),
+                        &mint,
+                        &creator,
+                        &token_program_id,
+                        fee_recipient,
+                    )
+                },
+                SellArgs {
+                    amount: *amount,
+                    min_sol_output: (min_sol_output as u128 * *amount as u128
+                        / token_amount as u128) as u64,
+                },
+            )
+        })
+        .collect();
+
+    let mut instructions = config.fees.compute_budget_instructions();
+    // Curves created under an older IDL are grown to the size the program reads
+    instructions.extend(extend_bonding_curve(
+        &bonding_curve,
+        &bonding_curve_info.data,
+        &user.pubkey(),
+    ));
+    instructions.extend(sell_ixs);
+    if config.trading.close_ata && !paper {
+        instructions.extend(holdings.close_emptied(&allocations, &token_program_id)?);
+    }
 
-    // Create sell instruction
-    let sell_ix = create_sell_instruction(
-        SellAccounts {
-            global: *GLOBAL_ADDRESS,
-            fee_recipient,
-            mint,
-            bonding_curve,
-            associated_bonding_curve,
-            associated_user,
-            user: user.pubkey(),
-            system_program: system_program::ID,
-            creator_vault,
-            token_program: token_program_id,
-            event_authority: *EVENT_AUTHORITY,
-            program: *PUMP_PROGRAM_ID,
-            fee_config: *FEE_CONFIG,
-            fee_program: *FEE_PROGRAM,
-        },
-        SellArgs {
-            amount: token_amount,
-            min_sol_output: min_sol_output,
-        },
+    let global = fees::current_global(connection, &curve);
+    // The fee is taken from the gross proceeds, before `output_amount`
+    let quote = cal::quote_sell_with_rounding(
+        &global,
+        Some(&curve),
+        token_amount,
+        cal::RoundingPolicy::default(),
+    );
+    let fill = Fill::new(
+        mint,
+        Side::Sell,
+        token_amount,
+        quote.output_amount,
+        quote.fee,
+        String::new(),
     );
+    sender::execute_trade(connection, &instructions, user, fill)?;
+
+    Ok(())
+}
 
-    // Get latest blockhash
-    let blockhash = connection.get_latest_blockhash()?;
+/// `sol_output` less `slippage_bps`, the sell side of `pump_buy::with_slippage`
+pub(crate) fn with_slippage(sol_output: u64, slippage_bps: u64) -> u64 {
+    (sol_output as u128 * 10_000u128.saturating_sub(slippage_bps as u128) / 10_000) as u64
+}
 
-    // Build transaction
-    let transaction = Transaction::new_signed_with_payer(
-        &[sell_ix],
-        Some(&user.pubkey()),
-        &[&user],
-        blockhash,
-    );
+/// Run a `SellRequest` with the configured RPC and wallet
+pub fn run_sell_request(request: &SellRequest, slippage_bps: u64) -> error::Result<()> {
+    let connection = rpc_pool::get_client();
+    let user = signer::load_default_wallet()?;
+    sell_request(&connection, &user, request, slippage_bps)
+}
+
+/// Resolve a `SellRequest` against `user`'s balance and the live curve, then sell it
+pub fn sell_request(
+    connection: &RpcClient,
+    user: &dyn Signer,
+    request: &SellRequest,
+    slippage_bps: u64,
+) -> error::Result<()> {
+    let mint = request.mint;
+
+    let token_program_id = cal::fetch_account(connection, &mint)?.owner;
+    let holdings = token_accounts::fetch_token_holdings(
+        connection,
+        &user.pubkey(),
+        &mint,
+        &token_program_id,
+    )?;
+    let balance = match config::get().trading.mode {
+        ExecutionMode::Paper => sender::paper_balance(&mint)?,
+        _ => holdings.total_spendable(),
+    };
+    if balance == 0 {
+        let needed = match request.amount {
+            SellAmount::Tokens(tokens) => tokens,
+            // Any amount at all
+            _ => 1,
+        };
+        return Err(BotError::InsufficientTokens {
+            needed,
+            available: 0,
+        });
+    }
 
-    // Simulate transaction
-    println!("\nSimulating transaction...");
-    
-    match connection.send_transaction(&transaction) {
-        Ok(signature) => {
-            println!("Transaction sent: {}", signature);
+    let venue = router::venue(connection, &mint)?;
+    let (token_amount, expected_sol) = match venue {
+        Venue::BondingCurve => {
+            let curve = cal::fetch_bonding_curve(connection, &mint)?;
+            let global = fees::current_global(connection, &curve);
+            let token_amount = request.amount.resolve(balance, &global, Some(&curve))?;
+            let impact_bps = cal::sell_price_impact_bps(&global, &curve, token_amount);
+            sizing::check_price_impact(&mint, false, impact_bps, request.force)?;
+            (
+                token_amount,
+                cal::get_sol_from_tokens(&global, Some(&curve), token_amount),
+            )
+        }
+        // SOL-target sells are only supported on the bonding curve
+        _ if matches!(request.amount, SellAmount::SolTarget(_)) => {
+            return Err(BotError::CurveMigrated { mint });
         }
-        Err(e) => {
-            println!("Failed to send transaction: {}", e);
+        venue => {
+            let token_amount = request.amount.resolve(balance, &cal::Global::default(), None)?;
+            (
+                token_amount,
+                router::quote_sell(connection, &mint, venue, token_amount)?,
+            )
         }
+    };
+    let min_sol_output = with_slippage(expected_sol, slippage_bps);
+    info!(
+        %mint,
+        amount = ?request.amount,
+        ?venue,
+        token_amount,
+        balance,
+        "Resolved sell request"
+    );
+
+    router::sell_at(connection, user, venue, token_amount, mint, min_sol_output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_never_exceeds_balance() {
+        let global = cal::Global::default();
+        // Past 2^53, where f64 would round the balance
+        let balance = (1u64 << 53) + 3;
+        let resolve = |amount: SellAmount| amount.resolve(balance, &global, None);
+
+        assert_eq!(resolve(SellAmount::All).unwrap(), balance);
+        assert_eq!(resolve(SellAmount::Percent(100.0)).unwrap(), balance);
+        assert_eq!(resolve(SellAmount::Percent(50.0)).unwrap(), balance / 2);
+        assert_eq!(resolve(SellAmount::Tokens(balance)).unwrap(), balance);
+        assert!(matches!(
+            resolve(SellAmount::Tokens(balance + 1)),
+            Err(BotError::InsufficientTokens { needed, available })
+                if needed == balance + 1 && available == balance
+        ));
+        assert!(resolve(SellAmount::Percent(0.0)).is_err());
     }
 
-    // match connection.simulate_transaction(&transaction) {
-    //     Ok(simulation) => {
-    //         println!("Simulation result:");
-    //         println!("  Error: {:?}", simulation.value.err);
-    //         println!("  Logs:");
-    //         if let Some(logs) = &simulation.value.logs {
-    //             for log in logs {
-    //                 println!("    {}", log);
-    //             }
-    //         }
-    //         println!("  Units consumed: {:?}", simulation.value.units_consumed);
-
-    //         if simulation.value.err.is_none() {
-    //             println!("\n✓ Simulation successful! Ready to send transaction.");
-
-    //             // Uncomment below to actually send the transaction:
-    //             // println!("\nSending transaction...");
-    //             // let signature = connection.send_and_confirm_transaction(&transaction)?;
-    //             // println!("✓ Sell successful!");
-    //             // println!("Signature: {}", signature);
-    //             // println!("View on Solscan: https://solscan.io/tx/{}", signature);
-    //         }
-    //     }
-    //     Err(e) => {
-    //         println!("✗ Failed to simulate transaction: {}", e);
-    //     }
-    // }
+    #[test]
+    fn test_build_sell_instruction_reports_missing_accounts() {
+        let rpc = RpcClient::new_mock("succeeds".to_string());
+        let mint = Pubkey::new_unique();
+        assert!(matches!(
+            build_sell_instruction(&rpc, &Pubkey::new_unique(), &mint, 1_000, 0),
+            Err(BotError::AccountNotFound(address)) if address == mint
+        ));
 
-    Ok(())
+        let rpc = RpcClient::new_mock("fails".to_string());
+        assert!(!matches!(
+            build_sell_instruction(&rpc, &Pubkey::new_unique(), &mint, 1_000, 0),
+            Err(BotError::AccountNotFound(_)) | Ok(_)
+        ));
+    }
 }
-
@@ -0,0 +1,666 @@
+use anyhow::{anyhow, Result};
+use solana_client::rpc_client::RpcClient;
+#[allow(deprecated)]
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signer::Signer,
+    system_instruction, system_program,
+};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account_idempotent,
+};
+use spl_token::native_mint::ID as WSOL_MINT;
+use spl_token::ID as TOKEN_PROGRAM_ID;
+use spl_token_2022::ID as TOKEN_2022_PROGRAM_ID;
+use std::str::FromStr;
+use tracing::{debug, info, instrument};
+
+use crate::config::ExecutionMode;
+use crate::error::{self, BotError};
+use crate::{cal, config, rpc_pool, signer, token_accounts};
+use crate::portfolio::{Fill, Side};
+use crate::sender;
+
+lazy_static::lazy_static! {
+    static ref PUMP_PROGRAM_ID: Pubkey = Pubkey::from_str("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P").unwrap();
+    static ref PUMP_AMM_PROGRAM_ID: Pubkey = Pubkey::from_str("pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA").unwrap();
+    static ref FEE_PROGRAM: Pubkey = Pubkey::from_str("pfeeUxB6jkeY1Hxd7CsFCAjcbHA9rWtchMGdZ6VojVZ").unwrap();
+}
+
+/// PumpSwap buy instruction discriminator
+const BUY_DISCRIMINATOR: [u8; 8] = [102, 6, 61, 18, 1, 218, 235, 234];
+
+/// PumpSwap sell instruction discriminator
+const SELL_DISCRIMINATOR: [u8; 8] = [51, 230, 133, 164, 1, 127, 131, 173];
+
+/// Pool state from the PumpSwap AMM program
+#[derive(Debug, Clone)]
+pub struct Pool {
+    pub index: u16,
+    pub creator: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub lp_mint: Pubkey,
+    pub pool_base_token_account: Pubkey,
+    pub pool_quote_token_account: Pubkey,
+    pub lp_supply: u64,
+    pub coin_creator: Pubkey,
+}
+
+/// Global config state from the PumpSwap AMM program
+#[derive(Debug, Clone)]
+pub struct GlobalConfig {
+    pub lp_fee_basis_points: u64,
+    pub protocol_fee_basis_points: u64,
+    pub coin_creator_fee_basis_points: u64,
+    pub protocol_fee_recipients: Vec<Pubkey>,
+}
+
+/// Live pool reserves (token account balances)
+#[derive(Debug, Clone, Copy)]
+pub struct PoolReserves {
+    pub base_reserve: u64,
+    pub quote_reserve: u64,
+}
+
+/// Accounts shared by the PumpSwap buy and sell instructions
+pub struct SwapAccounts {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub global_config: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub user_base_token_account: Pubkey,
+    pub user_quote_token_account: Pubkey,
+    pub pool_base_token_account: Pubkey,
+    pub pool_quote_token_account: Pubkey,
+    pub protocol_fee_recipient: Pubkey,
+    pub protocol_fee_recipient_token_account: Pubkey,
+    pub base_token_program: Pubkey,
+    pub quote_token_program: Pubkey,
+    pub coin_creator_vault_ata: Pubkey,
+    pub coin_creator_vault_authority: Pubkey,
+}
+
+/// Derive the pool authority PDA the pump program uses when migrating a mint
+pub fn get_pool_authority_pda(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"pool-authority", mint.as_ref()], &PUMP_PROGRAM_ID)
+}
+
+/// Derive the canonical PumpSwap pool PDA for a migrated pump.fun mint
+pub fn get_pool_pda(mint: &Pubkey) -> (Pubkey, u8) {
+    let (pool_authority, _) = get_pool_authority_pda(mint);
+    let index: u16 = 0;
+    Pubkey::find_program_address(
+        &[
+            b"pool",
+            &index.to_le_bytes(),
+            pool_authority.as_ref(),
+            mint.as_ref(),
+            WSOL_MINT.as_ref(),
+        ],
+        &PUMP_AMM_PROGRAM_ID,
+    )
+}
+
+/// Derive the PumpSwap global config PDA
+fn get_global_config_pda() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"global_config"], &PUMP_AMM_PROGRAM_ID)
+}
+
+/// Derive the PumpSwap event authority PDA
+fn get_event_authority_pda() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"__event_authority"], &PUMP_AMM_PROGRAM_ID)
+}
+
+/// Derive the coin creator vault authority PDA
+fn get_coin_creator_vault_authority_pda(coin_creator: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"creator_vault", coin_creator.as_ref()], &PUMP_AMM_PROGRAM_ID)
+}
+
+/// Derive the global volume accumulator PDA
+fn get_global_volume_accumulator_pda() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"global_volume_accumulator"], &PUMP_AMM_PROGRAM_ID)
+}
+
+/// Derive the user volume accumulator PDA
+fn get_user_volume_accumulator_pda(user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"user_volume_accumulator", user.as_ref()],
+        &PUMP_AMM_PROGRAM_ID,
+    )
+}
+
+/// Derive the fee config PDA for the PumpSwap program
+fn get_fee_config_pda() -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"fee_config", PUMP_AMM_PROGRAM_ID.as_ref()],
+        &FEE_PROGRAM,
+    )
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
+    Pubkey::new_from_array(data[offset..offset + 32].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+/// Parse pool data from on-chain account
+/// Layout: 8 (discriminator) + 1 (bump) + 2 (index) + 32 (creator) + 32 (base_mint) +
+///         32 (quote_mint) + 32 (lp_mint) + 32 (pool_base_token_account) +
+///         32 (pool_quote_token_account) + 8 (lp_supply) + 32 (coin_creator)
+pub fn parse_pool(data: &[u8]) -> Result<Pool> {
+    if data.len() < 243 {
+        return Err(anyhow!("Pool data too short: {} bytes", data.len()));
+    }
+
+    Ok(Pool {
+        index: u16::from_le_bytes(data[9..11].try_into().unwrap()),
+        creator: read_pubkey(data, 11),
+        base_mint: read_pubkey(data, 43),
+        quote_mint: read_pubkey(data, 75),
+        lp_mint: read_pubkey(data, 107),
+        pool_base_token_account: read_pubkey(data, 139),
+        pool_quote_token_account: read_pubkey(data, 171),
+        lp_supply: read_u64(data, 203),
+        coin_creator: read_pubkey(data, 211),
+    })
+}
+
+/// Parse global config data from on-chain account
+/// Layout: 8 (discriminator) + 32 (admin) + 8 (lp_fee_basis_points) + 8 (protocol_fee_basis_points) +
+///         1 (disable_flags) + 32*8 (protocol_fee_recipients) + 8 (coin_creator_fee_basis_points)
+pub fn parse_global_config(data: &[u8]) -> Result<GlobalConfig> {
+    const RECIPIENTS_OFFSET: usize = 8 + 32 + 8 + 8 + 1; // 57 bytes
+    const CREATOR_FEE_OFFSET: usize = RECIPIENTS_OFFSET + 32 * 8; // 313 bytes
+
+    if data.len() < CREATOR_FEE_OFFSET + 8 {
+        return Err(anyhow!("Global config data too short: {} bytes", data.len()));
+    }
+
+    let protocol_fee_recipients = (0..8)
+        .map(|i| read_pubkey(data, RECIPIENTS_OFFSET + i * 32))
+        .filter(|recipient| *recipient != Pubkey::default())
+        .collect();
+
+    Ok(GlobalConfig {
+        lp_fee_basis_points: read_u64(data, 40),
+        protocol_fee_basis_points: read_u64(data, 48),
+        coin_creator_fee_basis_points: read_u64(data, CREATOR_FEE_OFFSET),
+        protocol_fee_recipients,
+    })
+}
+
+/// Fetch and parse the PumpSwap pool for a mint
+pub fn fetch_pool(rpc: &RpcClient, mint: &Pubkey) -> Result<(Pubkey, Pool)> {
+    let (pool_address, _) = get_pool_pda(mint);
+    let account = rpc
+        .get_account(&pool_address)
+        .map_err(|e| anyhow!("Failed to fetch PumpSwap pool: {}", e))?;
+    Ok((pool_address, parse_pool(&account.data)?))
+}
+
+/// Fetch and parse the PumpSwap global config
+pub fn fetch_global_config(rpc: &RpcClient) -> Result<GlobalConfig> {
+    let (global_config, _) = get_global_config_pda();
+    let account = rpc
+        .get_account(&global_config)
+        .map_err(|e| anyhow!("Failed to fetch PumpSwap global config: {}", e))?;
+    parse_global_config(&account.data)
+}
+
+/// Fetch current pool reserves from the pool token accounts
+pub fn fetch_pool_reserves(rpc: &RpcClient, pool: &Pool) -> Result<PoolReserves> {
+    let base = rpc
+        .get_token_account_balance(&pool.pool_base_token_account)
+        .map_err(|e| anyhow!("Failed to fetch pool base reserve: {}", e))?;
+    let quote = rpc
+        .get_token_account_balance(&pool.pool_quote_token_account)
+        .map_err(|e| anyhow!("Failed to fetch pool quote reserve: {}", e))?;
+
+    Ok(PoolReserves {
+        base_reserve: base.amount.parse()?,
+        quote_reserve: quote.amount.parse()?,
+    })
+}
+
+/// Check whether a mint has graduated from its bonding curve to PumpSwap
+pub fn is_migrated(rpc: &RpcClient, mint: &Pubkey) -> Result<bool> {
+    match cal::fetch_bonding_curve(rpc, mint) {
+        Ok(curve) => Ok(curve.complete),
+        // A missing curve only counts as migrated if the pool exists
+        Err(_) => Ok(rpc.get_account(&get_pool_pda(mint).0).is_ok()),
+    }
+}
+
+/// Total fee basis points charged on a swap
+fn total_fee_basis_points(config: &GlobalConfig, pool: &Pool) -> u64 {
+    let creator_fee = if pool.coin_creator != Pubkey::default() {
+        config.coin_creator_fee_basis_points
+    } else {
+        0
+    };
+    config.lp_fee_basis_points + config.protocol_fee_basis_points + creator_fee
+}
+
+/// Calculate quote (SOL) needed to buy `base_amount_out` tokens, including fees
+pub fn get_quote_in_for_base_out(reserves: &PoolReserves, fee_basis_points: u64, base_amount_out: u64) -> u64 {
+    if base_amount_out == 0 {
+        return 0;
+    }
+    if base_amount_out >= reserves.base_reserve {
+        return u64::MAX; // Would drain the pool
+    }
+
+    // Constant product formula (inverse), rounded up
+    let numerator = reserves.quote_reserve as u128 * base_amount_out as u128;
+    let denominator = (reserves.base_reserve - base_amount_out) as u128;
+    let quote_in = numerator.div_ceil(denominator);
+
+    let fee = (quote_in * fee_basis_points as u128).div_ceil(10_000);
+    (quote_in + fee).min(u64::MAX as u128) as u64
+}
+
+/// Calculate quote (SOL) received for selling `base_amount_in` tokens, after fees
+pub fn get_quote_out_for_base_in(reserves: &PoolReserves, fee_basis_points: u64, base_amount_in: u64) -> u64 {
+    if base_amount_in == 0 || reserves.base_reserve == 0 {
+        return 0;
+    }
+
+    // Constant product formula: quote_out = (quote_reserve * base_in) / (base_reserve + base_in)
+    let quote_out = (reserves.quote_reserve as u128 * base_amount_in as u128)
+        / (reserves.base_reserve as u128 + base_amount_in as u128);
+
+    let fee = (quote_out * fee_basis_points as u128).div_ceil(10_000);
+    quote_out.saturating_sub(fee) as u64
+}
+
+/// Calculate tokens received for spending `quote_amount_in` SOL, fees included in the spend
+pub fn get_base_out_for_quote_in(
+    reserves: &PoolReserves,
+    fee_basis_points: u64,
+    quote_amount_in: u64,
+) -> u64 {
+    if quote_amount_in == 0 || reserves.quote_reserve == 0 {
+        return 0;
+    }
+
+    // Fees are charged on top of the swapped amount, so take them out first
+    let quote_in = quote_amount_in as u128 * 10_000 / (10_000 + fee_basis_points as u128);
+    let base_out =
+        reserves.base_reserve as u128 * quote_in / (reserves.quote_reserve as u128 + quote_in);
+    base_out as u64
+}
+
+/// Tokens received for spending `sol_amount` on a migrated mint at current reserves
+pub fn quote_buy(rpc: &RpcClient, mint: &Pubkey, sol_amount: u64) -> Result<u64> {
+    let (_, pool) = fetch_pool(rpc, mint)?;
+    let config = fetch_global_config(rpc)?;
+    let reserves = fetch_pool_reserves(rpc, &pool)?;
+    Ok(get_base_out_for_quote_in(
+        &reserves,
+        total_fee_basis_points(&config, &pool),
+        sol_amount,
+    ))
+}
+
+/// SOL needed to buy exactly `token_amount` of a migrated mint at current reserves, fees
+/// included
+pub fn quote_buy_exact(rpc: &RpcClient, mint: &Pubkey, token_amount: u64) -> Result<u64> {
+    let (_, pool) = fetch_pool(rpc, mint)?;
+    let config = fetch_global_config(rpc)?;
+    let reserves = fetch_pool_reserves(rpc, &pool)?;
+    Ok(get_quote_in_for_base_out(
+        &reserves,
+        total_fee_basis_points(&config, &pool),
+        token_amount,
+    ))
+}
+
+/// Quote (SOL) received for selling `token_amount` of a migrated mint at current reserves
+pub fn quote_sell(rpc: &RpcClient, mint: &Pubkey, token_amount: u64) -> Result<u64> {
+    let (_, pool) = fetch_pool(rpc, mint)?;
+    let config = fetch_global_config(rpc)?;
+    let reserves = fetch_pool_reserves(rpc, &pool)?;
+    Ok(get_quote_out_for_base_in(
+        &reserves,
+        total_fee_basis_points(&config, &pool),
+        token_amount,
+    ))
+}
+
+/// Build the account metas shared by buy and sell
+fn swap_account_metas(accounts: &SwapAccounts) -> Vec<AccountMeta> {
+    let (event_authority, _) = get_event_authority_pda();
+
+    vec![
+        AccountMeta::new(accounts.pool, false),
+        AccountMeta::new(accounts.user, true),
+        AccountMeta::new_readonly(accounts.global_config, false),
+        AccountMeta::new_readonly(accounts.base_mint, false),
+        AccountMeta::new_readonly(accounts.quote_mint, false),
+        AccountMeta::new(accounts.user_base_token_account, false),
+        AccountMeta::new(accounts.user_quote_token_account, false),
+        AccountMeta::new(accounts.pool_base_token_account, false),
+        AccountMeta::new(accounts.pool_quote_token_account, false),
+        AccountMeta::new_readonly(accounts.protocol_fee_recipient, false),
+        AccountMeta::new(accounts.protocol_fee_recipient_token_account, false),
+        AccountMeta::new_readonly(accounts.base_token_program, false),
+        AccountMeta::new_readonly(accounts.quote_token_program, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+        AccountMeta::new_readonly(spl_associated_token_account::ID, false),
+        AccountMeta::new_readonly(event_authority, false),
+        AccountMeta::new_readonly(*PUMP_AMM_PROGRAM_ID, false),
+        AccountMeta::new(accounts.coin_creator_vault_ata, false),
+        AccountMeta::new_readonly(accounts.coin_creator_vault_authority, false),
+    ]
+}
+
+/// Create the PumpSwap buy instruction
+pub fn create_buy_instruction(accounts: &SwapAccounts, base_amount_out: u64, max_quote_amount_in: u64) -> Instruction {
+    // Build instruction data: discriminator (8) + base_amount_out (8) + max_quote_amount_in (8) + Option<bool> (2)
+    let mut data = Vec::with_capacity(26);
+    data.extend_from_slice(&BUY_DISCRIMINATOR);
+    data.extend_from_slice(&base_amount_out.to_le_bytes());
+    data.extend_from_slice(&max_quote_amount_in.to_le_bytes());
+    data.push(1); // Some
+    data.push(1); // track_volume
+
+    let (global_volume_accumulator, _) = get_global_volume_accumulator_pda();
+    let (user_volume_accumulator, _) = get_user_volume_accumulator_pda(&accounts.user);
+    let (fee_config, _) = get_fee_config_pda();
+
+    let mut keys = swap_account_metas(accounts);
+    keys.push(AccountMeta::new(global_volume_accumulator, false));
+    keys.push(AccountMeta::new(user_volume_accumulator, false));
+    keys.push(AccountMeta::new_readonly(fee_config, false));
+    keys.push(AccountMeta::new_readonly(*FEE_PROGRAM, false));
+
+    Instruction {
+        program_id: *PUMP_AMM_PROGRAM_ID,
+        accounts: keys,
+        data,
+    }
+}
+
+/// Create the PumpSwap sell instruction
+pub fn create_sell_instruction(accounts: &SwapAccounts, base_amount_in: u64, min_quote_amount_out: u64) -> Instruction {
+    // Build instruction data: discriminator (8) + base_amount_in (8) + min_quote_amount_out (8)
+    let mut data = Vec::with_capacity(24);
+    data.extend_from_slice(&SELL_DISCRIMINATOR);
+    data.extend_from_slice(&base_amount_in.to_le_bytes());
+    data.extend_from_slice(&min_quote_amount_out.to_le_bytes());
+
+    let (fee_config, _) = get_fee_config_pda();
+
+    let mut keys = swap_account_metas(accounts);
+    keys.push(AccountMeta::new_readonly(fee_config, false));
+    keys.push(AccountMeta::new_readonly(*FEE_PROGRAM, false));
+
+    Instruction {
+        program_id: *PUMP_AMM_PROGRAM_ID,
+        accounts: keys,
+        data,
+    }
+}
+
+/// Resolve every account needed to swap `mint` on PumpSwap for `user`
+pub fn resolve_swap_accounts(
+    rpc: &RpcClient,
+    mint: &Pubkey,
+    user: &Pubkey,
+) -> Result<(SwapAccounts, Pool, GlobalConfig)> {
+    let (pool_address, pool) = fetch_pool(rpc, mint)?;
+    let config = fetch_global_config(rpc)?;
+
+    let protocol_fee_recipient = *config
+        .protocol_fee_recipients
+        .first()
+        .ok_or_else(|| anyhow!("PumpSwap global config has no protocol fee recipients"))?;
+
+    // Get mint info to determine token program
+    let mint_info = rpc
+        .get_account(mint)
+        .map_err(|e| anyhow!("Failed to get mint account: {}", e))?;
+    let base_token_program = if mint_info.owner == TOKEN_2022_PROGRAM_ID {
+        TOKEN_2022_PROGRAM_ID
+    } else {
+        TOKEN_PROGRAM_ID
+    };
+
+    let (global_config, _) = get_global_config_pda();
+    let (coin_creator_vault_authority, _) = get_coin_creator_vault_authority_pda(&pool.coin_creator);
+
+    let accounts = SwapAccounts {
+        pool: pool_address,
+        user: *user,
+        global_config,
+        base_mint: pool.base_mint,
+        quote_mint: pool.quote_mint,
+        user_base_token_account: get_associated_token_address_with_program_id(
+            user,
+            &pool.base_mint,
+            &base_token_program,
+        ),
+        user_quote_token_account: get_associated_token_address_with_program_id(
+            user,
+            &pool.quote_mint,
+            &TOKEN_PROGRAM_ID,
+        ),
+        pool_base_token_account: pool.pool_base_token_account,
+        pool_quote_token_account: pool.pool_quote_token_account,
+        protocol_fee_recipient,
+        protocol_fee_recipient_token_account: get_associated_token_address_with_program_id(
+            &protocol_fee_recipient,
+            &pool.quote_mint,
+            &TOKEN_PROGRAM_ID,
+        ),
+        base_token_program,
+        quote_token_program: TOKEN_PROGRAM_ID,
+        coin_creator_vault_ata: get_associated_token_address_with_program_id(
+            &coin_creator_vault_authority,
+            &pool.quote_mint,
+            &TOKEN_PROGRAM_ID,
+        ),
+        coin_creator_vault_authority,
+    };
+
+    Ok((accounts, pool, config))
+}
+
+/// Buy `token_amount` tokens of a migrated mint on PumpSwap with the configured RPC and wallet
+pub fn run_pumpswap_buy(token_amount: u64, mint: Pubkey, max_sol_cost: u64) -> error::Result<()> {
+    let connection = rpc_pool::get_client();
+    let user = signer::load_default_wallet()?;
+    buy(&connection, &user, token_amount, mint, max_sol_cost)?;
+    Ok(())
+}
+
+/// Buy `token_amount` tokens of a migrated mint on PumpSwap for `user`, returning the
+/// fill recorded for it (None when simulated)
+#[instrument(name = "pumpswap_buy", skip_all, fields(%mint, token_amount, max_sol_cost))]
+pub fn buy(
+    connection: &RpcClient,
+    user: &dyn Signer,
+    token_amount: u64,
+    mint: Pubkey,
+    max_sol_cost: u64,
+) -> error::Result<Option<Fill>> {
+    info!("Starting PumpSwap buy");
+
+    let config = config::get();
+    let extensions = cal::fetch_mint_extensions(connection, &mint)?;
+    extensions.check(&mint)?;
+
+    let (accounts, pool, global_config) =
+        resolve_swap_accounts(connection, &mint, &user.pubkey())?;
+    debug!(user = %user.pubkey(), pool = %accounts.pool, "Resolved swap accounts");
+
+    let reserves = fetch_pool_reserves(connection, &pool)?;
+    let expected_cost = get_quote_in_for_base_out(
+        &reserves,
+        total_fee_basis_points(&global_config, &pool),
+        token_amount,
+    );
+    debug!(expected_cost, "Quoted PumpSwap buy");
+
+    // Wrap SOL into the WSOL account, swap, then unwrap whatever is left
+    let mut instructions = config.fees.compute_budget_instructions();
+    instructions.extend([
+        create_associated_token_account_idempotent(
+            &user.pubkey(),
+            &user.pubkey(),
+            &accounts.quote_mint,
+            &TOKEN_PROGRAM_ID,
+        ),
+        system_instruction::transfer(&user.pubkey(), &accounts.user_quote_token_account, max_sol_cost),
+        spl_token::instruction::sync_native(&TOKEN_PROGRAM_ID, &accounts.user_quote_token_account)?,
+        create_associated_token_account_idempotent(
+            &user.pubkey(),
+            &user.pubkey(),
+            &accounts.base_mint,
+            &accounts.base_token_program,
+        ),
+        create_buy_instruction(&accounts, token_amount, max_sol_cost),
+        spl_token::instruction::close_account(
+            &TOKEN_PROGRAM_ID,
+            &accounts.user_quote_token_account,
+            &user.pubkey(),
+            &user.pubkey(),
+            &[],
+        )?,
+    ]);
+
+    let gross_cost = get_quote_in_for_base_out(&reserves, 0, token_amount);
+    let fill = Fill::new(
+        mint,
+        Side::Buy,
+        token_amount - extensions.transfer_fee(token_amount),
+        expected_cost,
+        expected_cost.saturating_sub(gross_cost),
+        String::new(),
+    );
+    sender::execute_trade(connection, &instructions, user, fill)
+}
+
+/// Sell `token_amount` tokens of a migrated mint on PumpSwap with the configured RPC and wallet
+pub fn run_pumpswap_sell(
+    token_amount: u64,
+    mint: Pubkey,
+    min_sol_output: u64,
+) -> error::Result<()> {
+    let connection = rpc_pool::get_client();
+    let user = signer::load_default_wallet()?;
+    sell(&connection, &user, token_amount, mint, min_sol_output)
+}
+
+/// Sell `token_amount` tokens of a migrated mint on PumpSwap for `user`
+#[instrument(name = "pumpswap_sell", skip_all, fields(%mint, token_amount, min_sol_output))]
+pub fn sell(
+    connection: &RpcClient,
+    user: &dyn Signer,
+    token_amount: u64,
+    mint: Pubkey,
+    min_sol_output: u64,
+) -> error::Result<()> {
+    info!("Starting PumpSwap sell");
+
+    let config = config::get();
+
+    let (accounts, pool, global_config) =
+        resolve_swap_accounts(connection, &mint, &user.pubkey())?;
+    debug!(user = %user.pubkey(), pool = %accounts.pool, "Resolved swap accounts");
+
+    // Swaps sell from the ATA alone, so tokens held in other accounts can't cover them.
+    // Paper sells draw on the paper position instead.
+    let paper = config.trading.mode == ExecutionMode::Paper;
+    let balance = if paper {
+        sender::paper_balance(&mint)?
+    } else {
+        token_accounts::fetch_token_balance(connection, &accounts.user_base_token_account)?
+    };
+    if balance < token_amount {
+        return Err(BotError::InsufficientTokens {
+            needed: token_amount,
+            available: balance,
+        });
+    }
+
+    let reserves = fetch_pool_reserves(connection, &pool)?;
+    let expected_output = get_quote_out_for_base_in(
+        &reserves,
+        total_fee_basis_points(&global_config, &pool),
+        token_amount,
+    );
+    debug!(expected_output, "Quoted PumpSwap sell");
+
+    // Receive WSOL into a temporary account, then unwrap it back to SOL
+    let mut instructions = config.fees.compute_budget_instructions();
+    instructions.extend([
+        create_associated_token_account_idempotent(
+            &user.pubkey(),
+            &user.pubkey(),
+            &accounts.quote_mint,
+            &TOKEN_PROGRAM_ID,
+        ),
+        create_sell_instruction(&accounts, token_amount, min_sol_output),
+        spl_token::instruction::close_account(
+            &TOKEN_PROGRAM_ID,
+            &accounts.user_quote_token_account,
+            &user.pubkey(),
+            &user.pubkey(),
+            &[],
+        )?,
+    ]);
+    if config.trading.close_ata && !paper && balance == token_amount {
+        instructions.push(token_accounts::close_account(
+            &accounts.user_base_token_account,
+            &user.pubkey(),
+            &accounts.base_token_program,
+        )?);
+    }
+
+    let gross_output = get_quote_out_for_base_in(&reserves, 0, token_amount);
+    let fill = Fill::new(
+        mint,
+        Side::Sell,
+        token_amount,
+        expected_output,
+        gross_output.saturating_sub(expected_output),
+        String::new(),
+    );
+    sender::execute_trade(connection, &instructions, user, fill)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swap_round_trip_loses_fees() {
+        let reserves = PoolReserves {
+            base_reserve: 200_000_000_000_000,
+            quote_reserve: 85_000_000_000,
+        };
+
+        let tokens = 1_000_000_000_000;
+        let cost = get_quote_in_for_base_out(&reserves, 125, tokens);
+        let proceeds = get_quote_out_for_base_in(&reserves, 125, tokens);
+
+        println!("Buying {} tokens costs {} lamports, selling returns {}", tokens, cost, proceeds);
+        assert!(cost > proceeds);
+        let bought = get_base_out_for_quote_in(&reserves, 125, cost);
+        assert!(bought.abs_diff(tokens) <= tokens / 10_000);
+    }
+
+    #[test]
+    fn test_parse_pool_rejects_short_data() {
+        assert!(parse_pool(&[0u8; 100]).is_err());
+    }
+}
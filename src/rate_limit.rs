@@ -0,0 +1,172 @@
+use solana_client::rpc_request::RpcRequest;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+// Constants
+/// Bounds on how long a waiting request sleeps before checking the bucket again. The upper
+/// bound lets a request that yielded to a higher priority one notice promptly when it may go.
+const MIN_POLL_INTERVAL: Duration = Duration::from_millis(1);
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// How urgently a request has to reach the RPC. While a request waits for a token, no
+/// request of a lower priority is let through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// History scans and large account queries for reports and recovery
+    Background = 0,
+    /// Quotes, balances and everything else
+    Normal = 1,
+    /// Blockhashes, sends and confirmations, which decide whether a trade lands in time
+    Critical = 2,
+}
+
+impl Priority {
+    pub fn of(request: RpcRequest) -> Self {
+        match request {
+            RpcRequest::GetLatestBlockhash
+            | RpcRequest::IsBlockhashValid
+            | RpcRequest::GetBlockHeight
+            | RpcRequest::GetRecentPrioritizationFees
+            | RpcRequest::SimulateTransaction
+            | RpcRequest::SendTransaction
+            | RpcRequest::GetSignatureStatuses => Priority::Critical,
+            RpcRequest::GetSignaturesForAddress
+            | RpcRequest::GetTransaction
+            | RpcRequest::GetBlock
+            | RpcRequest::GetProgramAccounts
+            | RpcRequest::GetTokenLargestAccounts => Priority::Background,
+            _ => Priority::Normal,
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Counts a request as waiting until dropped, so a cancelled request stops holding back
+/// lower priorities
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl<'a> Waiting<'a> {
+    fn new(count: &'a AtomicUsize) -> Self {
+        count.fetch_add(1, Ordering::AcqRel);
+        Self(count)
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Token bucket allowing `requests_per_second` on average, in bursts of up to one
+/// second's worth. Queued requests are served highest priority first.
+pub struct RateLimiter {
+    rate: f64,
+    capacity: f64,
+    bucket: Mutex<Bucket>,
+    /// Requests waiting for a token, indexed by priority
+    waiting: [AtomicUsize; 3],
+}
+
+impl RateLimiter {
+    /// Limiter starting with a full bucket. `requests_per_second` must be positive.
+    pub fn new(requests_per_second: f64) -> Self {
+        let capacity = requests_per_second.max(1.0);
+        Self {
+            rate: requests_per_second,
+            capacity,
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                refilled_at: Instant::now(),
+            }),
+            waiting: Default::default(),
+        }
+    }
+
+    /// Take a token for a `priority` request at `now`, or how long to sleep before trying
+    /// again
+    fn try_acquire(&self, priority: Priority, now: Instant) -> Result<(), Duration> {
+        let outranked = self.waiting[priority as usize + 1..]
+            .iter()
+            .any(|count| count.load(Ordering::Acquire) > 0);
+        let mut bucket = lock(&self.bucket);
+        let elapsed = now.saturating_duration_since(bucket.refilled_at);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * self.rate).min(self.capacity);
+        bucket.refilled_at = bucket.refilled_at.max(now);
+        if !outranked && bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        let refill = Duration::from_secs_f64((1.0 - bucket.tokens).max(0.0) / self.rate);
+        Err(refill.clamp(MIN_POLL_INTERVAL, MAX_POLL_INTERVAL))
+    }
+
+    /// Wait for a token behind every queued request of a higher priority. Returns how long
+    /// the request was held back.
+    pub async fn acquire(&self, priority: Priority) -> Duration {
+        let started = Instant::now();
+        let Err(mut wait) = self.try_acquire(priority, started) else {
+            return Duration::ZERO;
+        };
+        let _waiting = Waiting::new(&self.waiting[priority as usize]);
+        loop {
+            tokio::time::sleep(wait).await;
+            match self.try_acquire(priority, Instant::now()) {
+                Ok(()) => return started.elapsed(),
+                Err(next) => wait = next,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_refills_and_serves_higher_priorities_first() {
+        let limiter = RateLimiter::new(2.0);
+        let now = Instant::now();
+        assert!(limiter.try_acquire(Priority::Normal, now).is_ok());
+        assert!(limiter.try_acquire(Priority::Normal, now).is_ok());
+        let wait = limiter.try_acquire(Priority::Normal, now).unwrap_err();
+        assert_eq!(wait, MAX_POLL_INTERVAL);
+
+        // Half a second at 2 req/s refills one token, which a waiting send takes first
+        let later = now + Duration::from_millis(500);
+        let send = Waiting::new(&limiter.waiting[Priority::Critical as usize]);
+        assert!(limiter.try_acquire(Priority::Background, later).is_err());
+        assert!(limiter.try_acquire(Priority::Normal, later).is_err());
+        assert!(limiter.try_acquire(Priority::Critical, later).is_ok());
+        drop(send);
+        assert!(limiter
+            .try_acquire(Priority::Background, later + Duration::from_millis(500))
+            .is_ok());
+
+        // Idle time never banks more than one second's worth
+        let idle = later + Duration::from_secs(60);
+        for _ in 0..2 {
+            assert!(limiter.try_acquire(Priority::Normal, idle).is_ok());
+        }
+        assert!(limiter.try_acquire(Priority::Normal, idle).is_err());
+
+        assert_eq!(
+            Priority::of(RpcRequest::SendTransaction),
+            Priority::Critical
+        );
+        assert_eq!(Priority::of(RpcRequest::GetAccountInfo), Priority::Normal);
+        assert_eq!(
+            Priority::of(RpcRequest::GetSignaturesForAddress),
+            Priority::Background
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use anyhow::Result;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+use tracing::{info, instrument, warn};
+
+use crate::config::{self, ExecutionMode};
+use crate::events::{self, PumpEvent};
+use crate::portfolio::{self, Fill, Portfolio, Side};
+use crate::{fills, price_feed, signatures, token_accounts, tx_fees};
+
+// Constants
+/// How far before the last recorded fill to look for fills that landed unrecorded, for
+/// sends still confirming when an earlier fill was written
+const RECOVERY_WINDOW_SECS: i64 = 3_600;
+/// Most wallet signatures scanned for missed fills
+const MAX_SCANNED_SIGNATURES: usize = 1_000;
+
+/// A mint whose position in the portfolio disagrees with the wallet's token accounts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Discrepancy {
+    pub mint: Pubkey,
+    /// Tokens the portfolio's fills add up to
+    pub recorded: u64,
+    /// Tokens the wallets actually hold
+    pub on_chain: u64,
+}
+
+impl Discrepancy {
+    /// Zero-cost fill that moves the position to the on-chain amount. Tokens that left
+    /// without a recorded sell are written off at their cost basis.
+    pub fn adjustment(&self) -> Fill {
+        let (side, token_amount) = if self.on_chain > self.recorded {
+            (Side::Buy, self.on_chain - self.recorded)
+        } else {
+            (Side::Sell, self.recorded - self.on_chain)
+        };
+        let timestamp = portfolio::now_unix();
+        Fill {
+            mint: self.mint,
+            side,
+            token_amount,
+            sol_amount: 0,
+            fee_lamports: 0,
+            signature: format!("reconcile-{}-{}", self.mint, timestamp),
+            timestamp,
+        }
+    }
+}
+
+/// What `recover` found and repaired
+#[derive(Debug, Clone, Default)]
+pub struct RecoveryReport {
+    /// Fills that landed on-chain but were never recorded, now recorded
+    pub recovered: Vec<Fill>,
+    /// Positions that still disagreed with on-chain balances, now adjusted
+    pub discrepancies: Vec<Discrepancy>,
+    /// Mints held on-chain that the portfolio has never traded, left alone
+    pub untracked_mints: usize,
+}
+
+/// Every mint the portfolio has traded whose position differs from `balances`
+pub fn find_discrepancies(
+    portfolio: &Portfolio,
+    balances: &HashMap<Pubkey, u64>,
+) -> Result<Vec<Discrepancy>> {
+    let traded: BTreeSet<Pubkey> = portfolio.get_fills(None)?.iter().map(|f| f.mint).collect();
+    let mut discrepancies = Vec::new();
+    for mint in traded {
+        let recorded = portfolio.get_position(&mint)?.map_or(0, |p| p.token_amount);
+        let on_chain = balances.get(&mint).copied().unwrap_or(0);
+        if recorded != on_chain {
+            discrepancies.push(Discrepancy {
+                mint,
+                recorded,
+                on_chain,
+            });
+        }
+    }
+    Ok(discrepancies)
+}
+
+/// Successful signatures of `owner` no older than `since`, oldest first
+fn recent_signatures(rpc: &RpcClient, owner: &Pubkey, since: i64) -> Result<Vec<Signature>> {
+    let history = signatures::recent_until(rpc, owner, MAX_SCANNED_SIGNATURES, |status| {
+        status.block_time.is_some_and(|time| time < since)
+    })?;
+    history
+        .iter()
+        .rev()
+        .filter(|status| status.err.is_none())
+        .map(|status| Ok(Signature::from_str(&status.signature)?))
+        .collect()
+}
+
+/// Bonding curve trades `owner` made in landed transactions since `since` that the
+/// portfolio has no fill for. PumpSwap trades carry no Trade event and are only caught
+/// by the balance check.
+pub fn find_missed_fills(
+    rpc: &RpcClient,
+    portfolio: &Portfolio,
+    owner: &Pubkey,
+    since: i64,
+) -> Result<Vec<Fill>> {
+    let mut missed = Vec::new();
+    for signature in recent_signatures(rpc, owner, since)? {
+        if portfolio
+            .get_fill_by_signature(&signature.to_string())?
+            .is_some()
+        {
+            continue;
+        }
+        let transaction = match tx_fees::fetch_transaction(rpc, &signature) {
+            Ok(transaction) => transaction,
+            Err(e) => {
+                warn!(%signature, error = %e, "Skipping transaction");
+                continue;
+            }
+        };
+        // The first trade of the transaction; fills are keyed by signature
+        let trade =
+            events::from_transaction(&transaction)
+                .into_iter()
+                .find_map(|event| match event {
+                    PumpEvent::Trade(trade) if trade.user == *owner => Some(trade),
+                    _ => None,
+                });
+        if let Some(trade) = trade {
+            let side = if trade.is_buy { Side::Buy } else { Side::Sell };
+            let quoted = Fill::new(trade.mint, side, 0, 0, 0, signature.to_string());
+            missed.push(fills::realized_fill(&transaction, owner, &quoted)?);
+        }
+    }
+    Ok(missed)
+}
+
+/// Bring `portfolio` in line with the chain for the wallets `owners`: record bonding curve
+/// fills that landed after the last recorded one without being written (e.g. the bot
+/// crashed while confirming), then adjust every position that still differs from the
+/// wallets' token balances. Each repair is logged.
+#[instrument(skip_all, fields(wallets = owners.len()))]
+pub fn recover(
+    rpc: &RpcClient,
+    portfolio: &Portfolio,
+    owners: &[Pubkey],
+) -> Result<RecoveryReport> {
+    let mut report = RecoveryReport::default();
+
+    // A fresh portfolio has nothing to fall behind on
+    if let Some(last) = portfolio.get_fills(None)?.iter().map(|f| f.timestamp).max() {
+        for owner in owners {
+            for fill in find_missed_fills(rpc, portfolio, owner, last - RECOVERY_WINDOW_SECS)? {
+                warn!(
+                    mint = %fill.mint,
+                    side = fill.side.as_str(),
+                    token_amount = fill.token_amount,
+                    signature = %fill.signature,
+                    "Recovered unrecorded fill"
+                );
+                portfolio.record_fill_with_price(&fill, price_feed::cached_at(fill.timestamp))?;
+                report.recovered.push(fill);
+            }
+        }
+    }
+
+    let mut balances: HashMap<Pubkey, u64> = HashMap::new();
+    for owner in owners {
+        for (mint, amount) in token_accounts::fetch_owned_balances(rpc, owner)? {
+            *balances.entry(mint).or_insert(0) += amount;
+        }
+    }
+    report.discrepancies = find_discrepancies(portfolio, &balances)?;
+    for discrepancy in &report.discrepancies {
+        warn!(
+            mint = %discrepancy.mint,
+            recorded = discrepancy.recorded,
+            on_chain = discrepancy.on_chain,
+            "Position differs from on-chain balance, adjusting"
+        );
+        let adjustment = discrepancy.adjustment();
+        let sol_usd = price_feed::cached_at(adjustment.timestamp);
+        portfolio.record_fill_with_price(&adjustment, sol_usd)?;
+    }
+    let traded: BTreeSet<Pubkey> = portfolio.get_fills(None)?.iter().map(|f| f.mint).collect();
+    report.untracked_mints = balances.keys().filter(|m| !traded.contains(m)).count();
+
+    info!(
+        recovered = report.recovered.len(),
+        adjusted = report.discrepancies.len(),
+        untracked_mints = report.untracked_mints,
+        "Portfolio reconciled"
+    );
+    Ok(report)
+}
+
+/// `recover` the live portfolio for `owners` if `storage.reconcile_on_startup` is set.
+/// Paper portfolios have nothing on-chain to match and are skipped.
+pub fn on_startup(rpc: &RpcClient, owners: &[Pubkey]) -> Result<Option<RecoveryReport>> {
+    let config = config::get();
+    if !config.storage.reconcile_on_startup || config.trading.mode == ExecutionMode::Paper {
+        return Ok(None);
+    }
+    recover(rpc, &Portfolio::open_default()?, owners).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discrepancies_are_adjusted_to_on_chain_balances() {
+        let portfolio = Portfolio::open_in_memory().unwrap();
+        let (held, sold_elsewhere, closed) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let buy =
+            |mint, tokens, sig: &str| Fill::new(mint, Side::Buy, tokens, 1_000, 0, sig.into());
+        portfolio.record_fill(&buy(held, 500, "a")).unwrap();
+        portfolio
+            .record_fill(&buy(sold_elsewhere, 300, "b"))
+            .unwrap();
+        portfolio.record_fill(&buy(closed, 100, "c")).unwrap();
+        portfolio
+            .record_fill(&Fill::new(closed, Side::Sell, 100, 900, 0, "d".into()))
+            .unwrap();
+
+        // A buy confirmed after a crash left more tokens than recorded, another position
+        // was sold outside the bot, and an airdrop is untracked
+        let balances = HashMap::from([(held, 800), (closed, 0), (Pubkey::new_unique(), 42)]);
+        let mut discrepancies = find_discrepancies(&portfolio, &balances).unwrap();
+        discrepancies.sort_by_key(|d| d.recorded);
+        assert_eq!(
+            discrepancies,
+            vec![
+                Discrepancy {
+                    mint: sold_elsewhere,
+                    recorded: 300,
+                    on_chain: 0
+                },
+                Discrepancy {
+                    mint: held,
+                    recorded: 500,
+                    on_chain: 800
+                },
+            ]
+        );
+
+        for discrepancy in &discrepancies {
+            let adjustment = discrepancy.adjustment();
+            assert_eq!(adjustment.sol_amount, 0);
+            portfolio.record_fill(&adjustment).unwrap();
+        }
+        assert!(find_discrepancies(&portfolio, &balances)
+            .unwrap()
+            .is_empty());
+        let held = portfolio.get_position(&held).unwrap().unwrap();
+        assert_eq!((held.token_amount, held.cost_basis_lamports), (800, 1_000));
+    }
+}
@@ -0,0 +1,87 @@
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signer::Signer};
+use spl_token::native_mint::ID as WSOL_MINT;
+use tracing::info;
+
+use crate::error::{self, BotError};
+use crate::{cal, config, jupiter, pump_sell, pumpswap};
+
+/// Where a mint's liquidity lives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Venue {
+    /// Still trading on its pump.fun bonding curve
+    BondingCurve,
+    /// Migrated to its PumpSwap pool
+    PumpSwap,
+    /// Anywhere else Jupiter can route, e.g. a Raydium pool
+    Jupiter,
+}
+
+/// Venue for `mint`: its bonding curve until it completes, then its PumpSwap pool, and
+/// Jupiter for mints with neither (unless `jupiter.enabled` is off)
+pub fn venue(rpc: &RpcClient, mint: &Pubkey) -> error::Result<Venue> {
+    match cal::fetch_bonding_curve(rpc, mint) {
+        Ok(curve) if !curve.complete => return Ok(Venue::BondingCurve),
+        Ok(_) | Err(BotError::AccountNotFound(_)) => {}
+        Err(e) => return Err(e),
+    }
+
+    let (pool, _) = pumpswap::get_pool_pda(mint);
+    if rpc
+        .get_account_with_commitment(&pool, CommitmentConfig::confirmed())?
+        .value
+        .is_some()
+    {
+        return Ok(Venue::PumpSwap);
+    }
+    if config::get().jupiter.enabled {
+        Ok(Venue::Jupiter)
+    } else {
+        Err(BotError::CurveMigrated { mint: *mint })
+    }
+}
+
+/// Lamports selling `token_amount` of `mint` at `venue` returns now
+pub fn quote_sell(
+    rpc: &RpcClient,
+    mint: &Pubkey,
+    venue: Venue,
+    token_amount: u64,
+) -> error::Result<u64> {
+    Ok(match venue {
+        Venue::BondingCurve => cal::quote_sell(rpc, mint, token_amount)?.output_amount,
+        Venue::PumpSwap => pumpswap::quote_sell(rpc, mint, token_amount)?,
+        Venue::Jupiter => jupiter::quote(mint, &WSOL_MINT, token_amount, 0)?.out_amount,
+    })
+}
+
+/// Sell `token_amount` tokens of `mint` at `venue`, receiving at least `min_sol_output`
+pub fn sell_at(
+    connection: &RpcClient,
+    user: &dyn Signer,
+    venue: Venue,
+    token_amount: u64,
+    mint: Pubkey,
+    min_sol_output: u64,
+) -> error::Result<()> {
+    info!(%mint, ?venue, "Routing sell");
+    match venue {
+        Venue::BondingCurve => {
+            pump_sell::sell(connection, user, token_amount, mint, min_sol_output)
+        }
+        Venue::PumpSwap => pumpswap::sell(connection, user, token_amount, mint, min_sol_output),
+        Venue::Jupiter => jupiter::sell(connection, user, token_amount, mint, min_sol_output),
+    }
+}
+
+/// Sell `token_amount` tokens of `mint` wherever its liquidity is
+pub fn sell(
+    connection: &RpcClient,
+    user: &dyn Signer,
+    token_amount: u64,
+    mint: Pubkey,
+    min_sol_output: u64,
+) -> error::Result<()> {
+    let venue = venue(connection, &mint)?;
+    sell_at(connection, user, venue, token_amount, mint, min_sol_output)
+}
@@ -0,0 +1,356 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind, Result as ClientResult},
+    rpc_client::{RpcClient, RpcClientConfig},
+    rpc_custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+    rpc_request::{RpcError, RpcRequest},
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use solana_rpc_client::http_sender::HttpSender;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::config;
+use crate::rate_limit::{Priority, RateLimiter};
+
+// Constants
+/// Consecutive failures before an endpoint is taken out of rotation
+const MAX_CONSECUTIVE_ERRORS: u32 = 3;
+/// How long an unhealthy endpoint sits out before it is tried again
+const UNHEALTHY_COOLDOWN: Duration = Duration::from_secs(30);
+/// Weight of the newest sample in the latency moving average
+const LATENCY_EWMA_ALPHA: f64 = 0.2;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Pool built from `rpc.url` followed by `rpc.fallback_urls`, each limited to its
+/// configured request rate
+static POOL: Lazy<RpcPool> = Lazy::new(|| {
+    let rpc = &config::get().rpc;
+    let endpoints: Vec<(String, f64)> = std::iter::once(&rpc.url)
+        .chain(&rpc.fallback_urls)
+        .map(|url| (url.clone(), rpc.rate_limit(url)))
+        .collect();
+    RpcPool::with_rate_limits(&endpoints).expect("rpc.url is always set")
+});
+
+/// Request counters and latency of one endpoint
+#[derive(Debug, Clone, Default)]
+pub struct EndpointStats {
+    pub requests: u64,
+    pub errors: u64,
+    pub consecutive_errors: u32,
+    /// Exponential moving average of successful request latency
+    pub avg_latency: Option<Duration>,
+    /// Out of rotation until this instant
+    pub unhealthy_until: Option<Instant>,
+    /// Total time requests waited on the endpoint's rate limit
+    pub rate_limited: Duration,
+}
+
+impl EndpointStats {
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            return 0.0;
+        }
+        self.errors as f64 / self.requests as f64
+    }
+
+    pub fn is_healthy(&self, now: Instant) -> bool {
+        self.unhealthy_until.is_none_or(|until| now >= until)
+    }
+
+    fn record_success(&mut self, latency: Duration) {
+        self.requests += 1;
+        self.consecutive_errors = 0;
+        self.unhealthy_until = None;
+        self.avg_latency = Some(match self.avg_latency {
+            Some(avg) => {
+                avg.mul_f64(1.0 - LATENCY_EWMA_ALPHA) + latency.mul_f64(LATENCY_EWMA_ALPHA)
+            }
+            None => latency,
+        });
+    }
+
+    fn record_failure(&mut self, now: Instant) {
+        self.requests += 1;
+        self.errors += 1;
+        self.consecutive_errors += 1;
+        if self.consecutive_errors >= MAX_CONSECUTIVE_ERRORS {
+            self.unhealthy_until = Some(now + UNHEALTHY_COOLDOWN);
+        }
+    }
+}
+
+/// Snapshot of one endpoint for health reporting
+#[derive(Debug, Clone)]
+pub struct EndpointStatus {
+    pub url: String,
+    pub healthy: bool,
+    pub stats: EndpointStats,
+}
+
+struct Endpoint {
+    url: String,
+    stats: Mutex<EndpointStats>,
+    /// None when the endpoint is unlimited
+    limiter: Option<RateLimiter>,
+}
+
+impl Endpoint {
+    fn stats(&self) -> EndpointStats {
+        self.stats.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut EndpointStats)) {
+        if let Ok(mut stats) = self.stats.lock() {
+            f(&mut stats);
+        }
+    }
+}
+
+struct PoolInner {
+    endpoints: Vec<Endpoint>,
+    next: AtomicUsize,
+}
+
+impl PoolInner {
+    /// Endpoint indices to try for the next request: healthy ones in round-robin
+    /// order, then unhealthy ones as a last resort
+    fn order(&self) -> Vec<usize> {
+        let len = self.endpoints.len();
+        let start = self.next.fetch_add(1, Ordering::Relaxed) % len;
+        let now = Instant::now();
+        let (healthy, unhealthy): (Vec<usize>, Vec<usize>) = (0..len)
+            .map(|i| (start + i) % len)
+            .partition(|&i| self.endpoints[i].stats().is_healthy(now));
+        healthy.into_iter().chain(unhealthy).collect()
+    }
+}
+
+/// Whether an error says the endpoint is down rather than the request being bad
+fn is_endpoint_failure(error: &ClientError) -> bool {
+    match error.kind() {
+        ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_) => true,
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => {
+            *code == JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY
+        }
+        _ => false,
+    }
+}
+
+/// Round-robins requests across several RPC endpoints, failing over when one is down
+#[derive(Clone)]
+pub struct RpcPool {
+    inner: Arc<PoolInner>,
+}
+
+/// `RpcSender` that routes every call through the pool. Each client gets its own
+/// HTTP senders since their connections are bound to the client's runtime.
+struct PoolSender {
+    inner: Arc<PoolInner>,
+    senders: Vec<HttpSender>,
+    /// The one endpoint every call goes to, without failover
+    pinned: Option<usize>,
+}
+
+#[async_trait]
+impl RpcSender for PoolSender {
+    async fn send(
+        &self,
+        request: RpcRequest,
+        params: serde_json::Value,
+    ) -> ClientResult<serde_json::Value> {
+        let mut last_error = None;
+        let order = match self.pinned {
+            Some(i) => vec![i],
+            None => self.inner.order(),
+        };
+        for i in order {
+            let endpoint = &self.inner.endpoints[i];
+            if let Some(limiter) = &endpoint.limiter {
+                let waited = limiter.acquire(Priority::of(request)).await;
+                if !waited.is_zero() {
+                    debug!(url = %endpoint.url, %request, ?waited, "Rate limited");
+                    endpoint.update(|s| s.rate_limited += waited);
+                }
+            }
+            let started = Instant::now();
+            match self.senders[i].send(request, params.clone()).await {
+                Err(e) if is_endpoint_failure(&e) => {
+                    endpoint.update(|s| s.record_failure(Instant::now()));
+                    warn!(url = %endpoint.url, error = %e, "RPC failed, trying next endpoint");
+                    last_error = Some(e);
+                }
+                result => {
+                    endpoint.update(|s| s.record_success(started.elapsed()));
+                    return result;
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            ClientError::from(ClientErrorKind::Custom("No RPC endpoints".into()))
+        }))
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        let mut total = RpcTransportStats::default();
+        for sender in &self.senders {
+            let stats = sender.get_transport_stats();
+            total.request_count += stats.request_count;
+            total.elapsed_time += stats.elapsed_time;
+            total.rate_limited_time += stats.rate_limited_time;
+        }
+        total
+    }
+
+    fn url(&self) -> String {
+        self.inner.endpoints[self.pinned.unwrap_or(0)].url.clone()
+    }
+}
+
+impl RpcPool {
+    /// Pool over `urls` with no rate limits
+    pub fn new(urls: &[String]) -> Result<Self> {
+        let endpoints: Vec<(String, f64)> = urls.iter().map(|url| (url.clone(), 0.0)).collect();
+        Self::with_rate_limits(&endpoints)
+    }
+
+    /// Pool over `(url, requests per second)` pairs, where a rate of 0 is unlimited
+    pub fn with_rate_limits(endpoints: &[(String, f64)]) -> Result<Self> {
+        if endpoints.is_empty() {
+            return Err(anyhow!("RPC pool needs at least one endpoint"));
+        }
+        let endpoints = endpoints
+            .iter()
+            .map(|(url, requests_per_second)| Endpoint {
+                url: url.clone(),
+                stats: Mutex::new(EndpointStats::default()),
+                limiter: (*requests_per_second > 0.0)
+                    .then(|| RateLimiter::new(*requests_per_second)),
+            })
+            .collect();
+        Ok(Self {
+            inner: Arc::new(PoolInner {
+                endpoints,
+                next: AtomicUsize::new(0),
+            }),
+        })
+    }
+
+    fn client(&self, pinned: Option<usize>) -> RpcClient {
+        RpcClient::new_sender(
+            PoolSender {
+                inner: self.inner.clone(),
+                senders: self
+                    .inner
+                    .endpoints
+                    .iter()
+                    .map(|e| HttpSender::new_with_timeout(&e.url, REQUEST_TIMEOUT))
+                    .collect(),
+                pinned,
+            },
+            RpcClientConfig::default(),
+        )
+    }
+
+    /// Client whose requests are spread across the pool's endpoints
+    pub fn get_client(&self) -> RpcClient {
+        self.client(None)
+    }
+
+    /// One client per endpoint, healthy ones first, for broadcasting to each. They stay
+    /// within the endpoint's rate limit and count towards its stats.
+    pub fn endpoint_clients(&self) -> Vec<RpcClient> {
+        self.inner
+            .order()
+            .into_iter()
+            .map(|i| self.client(Some(i)))
+            .collect()
+    }
+
+    /// Endpoint URLs, healthy ones first
+    pub fn urls(&self) -> Vec<String> {
+        self.inner
+            .order()
+            .into_iter()
+            .map(|i| self.inner.endpoints[i].url.clone())
+            .collect()
+    }
+
+    pub fn status(&self) -> Vec<EndpointStatus> {
+        let now = Instant::now();
+        self.inner
+            .endpoints
+            .iter()
+            .map(|e| {
+                let stats = e.stats();
+                EndpointStatus {
+                    url: e.url.clone(),
+                    healthy: stats.is_healthy(now),
+                    stats,
+                }
+            })
+            .collect()
+    }
+
+    /// Probe every endpoint with `getHealth` so downed ones rejoin once they recover
+    pub fn check_health(&self) -> Vec<EndpointStatus> {
+        for endpoint in &self.inner.endpoints {
+            let started = Instant::now();
+            match RpcClient::new_with_timeout(endpoint.url.clone(), REQUEST_TIMEOUT).get_health() {
+                Ok(()) => endpoint.update(|s| s.record_success(started.elapsed())),
+                Err(e) => {
+                    warn!(url = %endpoint.url, error = %e, "RPC unhealthy");
+                    endpoint.update(|s| s.record_failure(Instant::now()));
+                }
+            }
+        }
+        self.status()
+    }
+}
+
+/// The configured endpoint pool
+pub fn pool() -> &'static RpcPool {
+    &POOL
+}
+
+/// Client backed by the configured endpoint pool
+pub fn get_client() -> RpcClient {
+    POOL.get_client()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint_leaves_and_rejoins_rotation() {
+        let pool = RpcPool::new(&[
+            "http://127.0.0.1:1".to_string(),
+            "http://127.0.0.1:2".to_string(),
+        ])
+        .unwrap();
+        let now = Instant::now();
+        for _ in 0..MAX_CONSECUTIVE_ERRORS {
+            pool.inner.endpoints[0].update(|s| s.record_failure(now));
+        }
+
+        assert!(!pool.status()[0].healthy);
+        assert_eq!(pool.inner.order(), vec![1, 0]);
+        assert_eq!(pool.inner.order(), vec![1, 0]);
+
+        pool.inner.endpoints[0].update(|s| s.record_success(Duration::from_millis(50)));
+        assert!(pool.status()[0].healthy);
+        assert_eq!(pool.status()[0].stats.error_rate(), 0.75);
+
+        let mut urls: Vec<String> = pool.endpoint_clients().iter().map(RpcClient::url).collect();
+        urls.sort();
+        assert_eq!(urls, ["http://127.0.0.1:1", "http://127.0.0.1:2"]);
+    }
+}
@@ -0,0 +1,345 @@
+use anyhow::{anyhow, Result};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use tracing::{info, warn};
+
+use crate::config::{self, SafetyConfig};
+use crate::creators::{self, CreatorList, LaunchOutcome, ListStatus};
+use crate::error::{self, BotError};
+use crate::{cal, holders, signatures};
+
+// Constants
+/// Size of the SPL mint layout, which Token-2022 mints start with
+const MINT_LEN: usize = 82;
+/// Most signatures `getSignaturesForAddress` returns at once
+const MAX_SIGNATURES: usize = 1_000;
+/// Risk added by each red flag (the score is capped at 100)
+const MINT_AUTHORITY_RISK: u32 = 40;
+const FREEZE_AUTHORITY_RISK: u32 = 40;
+const RUG_RISK: u32 = 20;
+const MAX_RUG_RISK: u32 = 40;
+const CONCENTRATION_RISK: u32 = 20;
+const DEV_HOLDINGS_RISK: u32 = 20;
+const BUNDLER_CLUSTER_RISK: u32 = 20;
+const BUNDLE_RISK: u32 = 20;
+const DENIED_CREATOR_RISK: u32 = 100;
+
+/// Authorities and supply from the base mint layout
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MintAuthorities {
+    pub mint_authority: Option<Pubkey>,
+    pub freeze_authority: Option<Pubkey>,
+    pub supply: u64,
+}
+
+/// `COption<Pubkey>`: 4 byte tag followed by the key
+fn read_coption_pubkey(data: &[u8], offset: usize) -> Option<Pubkey> {
+    let tag = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+    (tag == 1).then(|| Pubkey::try_from(&data[offset + 4..offset + 36]).unwrap())
+}
+
+/// Parse a mint account (SPL Token or Token-2022)
+/// Layout: 36 (mint_authority) + 8 (supply) + 1 (decimals) + 1 (is_initialized) + 36 (freeze_authority)
+pub fn parse_mint_authorities(data: &[u8]) -> Result<MintAuthorities> {
+    if data.len() < MINT_LEN {
+        return Err(anyhow!(
+            "Mint account is {} bytes, expected at least {}",
+            data.len(),
+            MINT_LEN
+        ));
+    }
+    Ok(MintAuthorities {
+        mint_authority: read_coption_pubkey(data, 0),
+        supply: u64::from_le_bytes(data[36..44].try_into().unwrap()),
+        freeze_authority: read_coption_pubkey(data, 46),
+    })
+}
+
+/// Pre-buy checks on a mint. Checks that could not run are left as None and add no risk.
+#[derive(Debug, Clone, Default)]
+pub struct SafetyReport {
+    pub mint: Pubkey,
+    /// Can still mint more supply
+    pub mint_authority: Option<Pubkey>,
+    /// Can freeze holders' token accounts, blocking sells
+    pub freeze_authority: Option<Pubkey>,
+    pub creator: Option<Pubkey>,
+    /// The creator's entry on the allow/deny list
+    pub creator_listed: Option<ListStatus>,
+    /// Earlier launches the creator sold into before they migrated
+    pub creator_rugs: Option<usize>,
+    /// Percent of supply held by the largest holders, excluding the bonding curve
+    pub top_holders_pct: Option<f64>,
+    /// Percent of supply the creator still holds
+    pub dev_holdings_pct: Option<f64>,
+    /// Percent of supply held by wallets with near-identical balances
+    pub bundler_pct: Option<f64>,
+    /// Other transactions landing in the launch slot
+    pub bundled_buys: Option<usize>,
+    /// 0 (no red flags) to 100
+    pub score: u32,
+    /// One line per red flag that added to `score`
+    pub reasons: Vec<String>,
+}
+
+impl SafetyReport {
+    /// Score the checks against the thresholds in `config`
+    fn assess(&mut self, config: &SafetyConfig) {
+        let mut risks: Vec<(u32, String)> = Vec::new();
+        if let Some(authority) = self.mint_authority {
+            risks.push((
+                MINT_AUTHORITY_RISK,
+                format!("mint authority {} not revoked", authority),
+            ));
+        }
+        if let Some(authority) = self.freeze_authority {
+            risks.push((
+                FREEZE_AUTHORITY_RISK,
+                format!("freeze authority {} set", authority),
+            ));
+        }
+        if let (Some(creator), Some(ListStatus::Deny)) = (self.creator, self.creator_listed) {
+            risks.push((
+                DENIED_CREATOR_RISK,
+                format!("creator {} is deny-listed", creator),
+            ));
+        }
+        if let Some(rugs) = self.creator_rugs.filter(|rugs| *rugs > 0) {
+            risks.push((
+                (RUG_RISK * rugs as u32).min(MAX_RUG_RISK),
+                format!("creator dumped {} earlier launches", rugs),
+            ));
+        }
+        if let Some(pct) = self
+            .top_holders_pct
+            .filter(|pct| *pct > config.max_top_holders_pct)
+        {
+            risks.push((
+                CONCENTRATION_RISK,
+                format!(
+                    "top {} holders own {:.1}% of supply",
+                    config.top_holders, pct
+                ),
+            ));
+        }
+        if let Some(pct) = self
+            .dev_holdings_pct
+            .filter(|pct| *pct > config.max_dev_holdings_pct)
+        {
+            risks.push((
+                DEV_HOLDINGS_RISK,
+                format!("creator holds {:.1}% of supply", pct),
+            ));
+        }
+        if let Some(pct) = self.bundler_pct.filter(|pct| *pct > config.max_bundler_pct) {
+            risks.push((
+                BUNDLER_CLUSTER_RISK,
+                format!("bundler clusters hold {:.1}% of supply", pct),
+            ));
+        }
+        if let Some(bundled) = self.bundled_buys.filter(|b| *b > config.max_bundled_buys) {
+            risks.push((
+                BUNDLE_RISK,
+                format!("{} transactions bundled with the launch", bundled),
+            ));
+        }
+
+        self.score = risks.iter().map(|(risk, _)| risk).sum::<u32>().min(100);
+        self.reasons = risks.into_iter().map(|(_, reason)| reason).collect();
+    }
+
+    pub fn passes(&self, max_risk_score: u32) -> bool {
+        self.score <= max_risk_score
+    }
+
+    pub fn print(&self) {
+        let show = |value: Option<String>| value.unwrap_or_else(|| "unknown".to_string());
+        println!("\n=== Safety Report: {} ===", self.mint);
+        println!(
+            "  Mint authority:   {}",
+            self.mint_authority
+                .map_or("revoked".to_string(), |a| a.to_string())
+        );
+        println!(
+            "  Freeze authority: {}",
+            self.freeze_authority
+                .map_or("none".to_string(), |a| a.to_string())
+        );
+        println!(
+            "  Creator:          {}",
+            show(self.creator.map(|c| c.to_string()))
+        );
+        if let Some(status) = self.creator_listed {
+            println!("  Creator list:     {:?}", status);
+        }
+        println!(
+            "  Creator rugs:     {}",
+            show(self.creator_rugs.map(|r| r.to_string()))
+        );
+        println!(
+            "  Top holders:      {}",
+            show(self.top_holders_pct.map(|p| format!("{:.1}%", p)))
+        );
+        println!(
+            "  Creator holds:    {}",
+            show(self.dev_holdings_pct.map(|p| format!("{:.1}%", p)))
+        );
+        println!(
+            "  Bundlers hold:    {}",
+            show(self.bundler_pct.map(|p| format!("{:.1}%", p)))
+        );
+        println!(
+            "  Bundled buys:     {}",
+            show(self.bundled_buys.map(|b| b.to_string()))
+        );
+        for reason in &self.reasons {
+            println!("  ! {}", reason);
+        }
+        println!("Risk score: {}/100", self.score);
+    }
+}
+
+/// Transactions on the bonding curve in the same slot as its creation. None when the curve
+/// has too much history to reach the launch.
+pub fn bundled_buy_count(rpc: &RpcClient, mint: &Pubkey) -> Result<Option<usize>> {
+    let (bonding_curve, _) = cal::get_bonding_curve_pda(mint);
+    let history = signatures::recent(rpc, &bonding_curve, MAX_SIGNATURES)?;
+    if history.len() >= MAX_SIGNATURES {
+        return Ok(None);
+    }
+    // Newest first, so the creation is last
+    let Some(launch) = history.last() else {
+        return Ok(None);
+    };
+    let bundled = history
+        .iter()
+        .filter(|s| s.slot == launch.slot && s.err.is_none())
+        .count();
+    Ok(Some(bundled.saturating_sub(1)))
+}
+
+/// Run every pre-buy check on `mint` and score it with `[safety]`
+pub fn check(rpc: &RpcClient, mint: &Pubkey) -> error::Result<SafetyReport> {
+    let config = &config::get().safety;
+    let mint_account = cal::fetch_account(rpc, mint)?;
+    let authorities = parse_mint_authorities(&mint_account.data)?;
+    let mut report = SafetyReport {
+        mint: *mint,
+        mint_authority: authorities.mint_authority,
+        freeze_authority: authorities.freeze_authority,
+        ..Default::default()
+    };
+
+    report.creator = cal::fetch_bonding_curve(rpc, mint)
+        .map(|curve| curve.creator)
+        .map_err(|e| warn!(%mint, error = %e, "Failed to fetch creator"))
+        .ok();
+    if let Some(creator) = report.creator {
+        report.creator_listed = CreatorList::open_default()
+            .and_then(|list| list.status(&creator))
+            .map_err(|e| warn!(%creator, error = %e, "Failed to read creator list"))
+            .ok()
+            .flatten();
+    }
+    // Allow-listed creators skip the history scan
+    if let Some(creator) = report
+        .creator
+        .filter(|_| report.creator_listed.is_none() && config.creator_history > 0)
+    {
+        report.creator_rugs = creators::fetch_history(rpc, &creator, config.creator_history)
+            .map(|history| {
+                history
+                    .launches
+                    .iter()
+                    .filter(|l| l.mint != *mint && l.outcome == LaunchOutcome::Dumped)
+                    .count()
+            })
+            .map_err(|e| warn!(%creator, error = %e, "Failed to check creator history"))
+            .ok();
+    }
+    if let Ok(distribution) = holders::scan(
+        rpc,
+        mint,
+        &mint_account.owner,
+        authorities.supply,
+        report.creator,
+    )
+    .map_err(|e| warn!(%mint, error = %e, "Failed to check holders"))
+    {
+        report.top_holders_pct = Some(distribution.top_pct(config.top_holders));
+        report.dev_holdings_pct = distribution.dev_pct();
+        report.bundler_pct = Some(distribution.bundler_pct());
+    }
+    report.bundled_buys = bundled_buy_count(rpc, mint)
+        .map_err(|e| warn!(%mint, error = %e, "Failed to check launch bundles"))
+        .ok()
+        .flatten();
+
+    report.assess(config);
+    info!(%mint, score = report.score, reasons = ?report.reasons, "Safety check");
+    Ok(report)
+}
+
+/// Refuse `mint` when `safety.enabled` and its risk score is above `safety.max_risk_score`
+pub fn gate(rpc: &RpcClient, mint: &Pubkey) -> error::Result<()> {
+    let config = &config::get().safety;
+    if !config.enabled {
+        return Ok(());
+    }
+    let report = check(rpc, mint)?;
+    if report.passes(config.max_risk_score) {
+        return Ok(());
+    }
+    Err(BotError::UnsafeMint {
+        mint: *mint,
+        score: report.score,
+        reasons: report.reasons.join("; "),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mint_and_score() {
+        let authority = Pubkey::new_unique();
+        let mut data = vec![0u8; MINT_LEN];
+        data[..4].copy_from_slice(&1u32.to_le_bytes());
+        data[4..36].copy_from_slice(authority.as_ref());
+        data[36..44].copy_from_slice(&1_000_000u64.to_le_bytes());
+        let mint = parse_mint_authorities(&data).unwrap();
+        assert_eq!(mint.mint_authority, Some(authority));
+        assert_eq!(mint.freeze_authority, None);
+        assert_eq!(mint.supply, 1_000_000);
+        assert!(parse_mint_authorities(&data[..80]).is_err());
+
+        let config = SafetyConfig::default();
+        let mut report = SafetyReport {
+            mint_authority: mint.mint_authority,
+            creator_rugs: Some(3),
+            top_holders_pct: Some(config.max_top_holders_pct - 1.0),
+            dev_holdings_pct: Some(config.max_dev_holdings_pct + 1.0),
+            bundled_buys: Some(config.max_bundled_buys + 1),
+            ..Default::default()
+        };
+        report.assess(&config);
+        assert_eq!(report.score, 100);
+        assert_eq!(report.reasons.len(), 4);
+        assert!(!report.passes(config.max_risk_score));
+
+        let mut denied = SafetyReport {
+            creator: Some(Pubkey::new_unique()),
+            creator_listed: Some(ListStatus::Deny),
+            ..Default::default()
+        };
+        denied.assess(&config);
+        assert_eq!(denied.score, 100);
+
+        // Unknown checks add no risk
+        let mut clean = SafetyReport::default();
+        clean.assess(&config);
+        assert_eq!(clean.score, 0);
+        assert!(clean.passes(config.max_risk_score));
+    }
+}
@@ -0,0 +1,228 @@
+use anyhow::{anyhow, Result};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::archiver::{EventArchive, EventFilter, EventKind};
+use crate::events::{PumpEvent, TradeEvent};
+use crate::portfolio;
+
+/// Which wallets `scan` keeps as copy-trade candidates
+#[derive(Debug, Clone)]
+pub struct ScanConfig {
+    /// Only trades from the last this many days are analyzed
+    pub window_days: i64,
+    /// Fewest trades a wallet needs in the window
+    pub min_trades: u64,
+    /// Fewest positions a wallet needs to have sold out of (partly or fully)
+    pub min_closed: u64,
+    /// Lowest share of closed positions that must have been profitable (0.0 - 1.0)
+    pub min_win_rate: f64,
+    /// Most candidates returned
+    pub limit: usize,
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self {
+            window_days: 7,
+            min_trades: 10,
+            min_closed: 5,
+            min_win_rate: 0.5,
+            limit: 20,
+        }
+    }
+}
+
+/// One wallet's trading of one mint, at average cost
+#[derive(Debug, Default)]
+struct MintPosition {
+    tokens: u64,
+    cost: u64,
+    realized: i64,
+    closed: bool,
+}
+
+impl MintPosition {
+    fn apply(&mut self, trade: &TradeEvent) {
+        let fees = trade.fee + trade.creator_fee;
+        if trade.is_buy {
+            self.tokens += trade.token_amount;
+            self.cost += trade.sol_amount + fees;
+            return;
+        }
+        // Tokens bought before the window have no known cost and are left out
+        let matched = trade.token_amount.min(self.tokens);
+        if matched == 0 {
+            return;
+        }
+        let proceeds = trade.sol_amount.saturating_sub(fees) as u128 * matched as u128
+            / trade.token_amount as u128;
+        let cost = self.cost as u128 * matched as u128 / self.tokens as u128;
+        self.realized += proceeds as i64 - cost as i64;
+        self.tokens -= matched;
+        self.cost -= cost as u64;
+        self.closed = true;
+    }
+}
+
+/// A wallet's realized results over the scan window
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalletScore {
+    pub wallet: Pubkey,
+    pub trades: u64,
+    /// Distinct mints traded
+    pub mints: u64,
+    /// Mints it sold tokens of that it bought in the window
+    pub closed: u64,
+    /// Closed mints with a positive realized PnL
+    pub wins: u64,
+    /// Lamports realized on sells, net of fees and the average cost of the tokens sold
+    pub realized_pnl_lamports: i64,
+}
+
+impl WalletScore {
+    /// Share of closed mints that were profitable
+    pub fn win_rate(&self) -> f64 {
+        if self.closed == 0 {
+            return 0.0;
+        }
+        self.wins as f64 / self.closed as f64
+    }
+}
+
+/// Score every wallet trading in `events` and return those passing `config`, highest
+/// realized PnL first. Events must be oldest first.
+pub fn scan(events: &[PumpEvent], config: &ScanConfig) -> Vec<WalletScore> {
+    let mut trades: HashMap<Pubkey, u64> = HashMap::new();
+    let mut positions: HashMap<Pubkey, HashMap<Pubkey, MintPosition>> = HashMap::new();
+    for event in events {
+        let PumpEvent::Trade(trade) = event else {
+            continue;
+        };
+        *trades.entry(trade.user).or_default() += 1;
+        positions
+            .entry(trade.user)
+            .or_default()
+            .entry(trade.mint)
+            .or_default()
+            .apply(trade);
+    }
+
+    let mut scores: Vec<WalletScore> = positions
+        .into_iter()
+        .map(|(wallet, mints)| {
+            let closed: Vec<&MintPosition> = mints.values().filter(|p| p.closed).collect();
+            WalletScore {
+                wallet,
+                trades: trades[&wallet],
+                mints: mints.len() as u64,
+                closed: closed.len() as u64,
+                wins: closed.iter().filter(|p| p.realized > 0).count() as u64,
+                realized_pnl_lamports: closed.iter().map(|p| p.realized).sum(),
+            }
+        })
+        .filter(|score| {
+            score.trades >= config.min_trades
+                && score.closed >= config.min_closed
+                && score.win_rate() >= config.min_win_rate
+                && score.realized_pnl_lamports > 0
+        })
+        .collect();
+    scores.sort_by(|a, b| {
+        b.realized_pnl_lamports
+            .cmp(&a.realized_pnl_lamports)
+            .then_with(|| a.wallet.cmp(&b.wallet))
+    });
+    scores.truncate(config.limit);
+    scores
+}
+
+/// `scan` the trades archived over the last `config.window_days`
+pub fn scan_archive(archive: &EventArchive, config: &ScanConfig) -> Result<Vec<WalletScore>> {
+    let events = archive.events(&EventFilter {
+        kind: Some(EventKind::Trade),
+        since: Some(portfolio::now_unix() - config.window_days * 86_400),
+        ..Default::default()
+    })?;
+    Ok(scan(&events, config))
+}
+
+/// Write `scores` as a candidate list for copy trading, one wallet per line
+pub fn write_candidates(path: &str, scores: &[WalletScore]) -> Result<()> {
+    let lines: Vec<String> = scores.iter().map(|s| s.wallet.to_string()).collect();
+    std::fs::write(path, lines.join("\n") + "\n")
+        .map_err(|e| anyhow!("Failed to write candidates to {}: {}", path, e))
+}
+
+/// Read a candidate list written by `write_candidates`. Blank lines and `#` comments
+/// are skipped, so the list can be pruned by hand.
+pub fn read_candidates(path: &str) -> Result<Vec<Pubkey>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read candidates from {}: {}", path, e))?;
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| Pubkey::from_str(line).map_err(|e| anyhow!("Bad wallet {}: {}", line, e)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(user: Pubkey, mint: Pubkey, is_buy: bool, sol_amount: u64) -> PumpEvent {
+        PumpEvent::Trade(TradeEvent {
+            mint,
+            user,
+            is_buy,
+            sol_amount,
+            token_amount: 1_000,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_scan_ranks_by_realized_pnl() {
+        let (good, bad) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut events = Vec::new();
+        for (i, mint) in [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            // Two winners and a loser for `good`, all losers for `bad`
+            let exit = if i < 2 { 300 } else { 50 };
+            events.push(trade(good, mint, true, 100));
+            events.push(trade(good, mint, false, exit));
+            events.push(trade(bad, mint, true, 100));
+            events.push(trade(bad, mint, false, 90));
+        }
+        // A sell of tokens bought before the window has no cost and doesn't count
+        events.push(trade(good, Pubkey::new_unique(), false, 1_000));
+
+        let config = ScanConfig {
+            min_trades: 2,
+            min_closed: 2,
+            min_win_rate: 0.5,
+            ..Default::default()
+        };
+        let scores = scan(&events, &config);
+        assert_eq!(scores.len(), 1);
+        let score = &scores[0];
+        assert_eq!(score.wallet, good);
+        assert_eq!(score.trades, 7);
+        assert_eq!((score.closed, score.wins), (3, 2));
+        assert_eq!(score.realized_pnl_lamports, 200 + 200 - 50);
+
+        let loose = ScanConfig {
+            min_win_rate: 0.0,
+            ..config
+        };
+        assert_eq!(scan(&events, &loose).len(), 1);
+    }
+}
@@ -0,0 +1,292 @@
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::migrations::MigrationTracker;
+use crate::portfolio::{now_unix, Portfolio};
+use crate::{balance, config, price_feed, rpc_pool, signer};
+
+// Constants
+/// How often the run loop checks for due tasks
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+type Job = Box<dyn FnMut() -> Result<()> + Send>;
+
+/// Result of the most recent run of a task
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskStatus {
+    Ok,
+    Failed(String),
+}
+
+/// Last-run status of one task, safe to hand to other threads
+#[derive(Debug, Clone)]
+pub struct TaskReport {
+    pub name: String,
+    pub enabled: bool,
+    pub interval: Duration,
+    /// Unix seconds of the last run
+    pub last_run_at: Option<i64>,
+    pub last_duration: Option<Duration>,
+    pub last_status: Option<TaskStatus>,
+    pub run_count: u64,
+    pub failure_count: u64,
+}
+
+struct ScheduledTask {
+    name: String,
+    interval: Duration,
+    enabled: bool,
+    next_run: Instant,
+    job: Job,
+}
+
+/// Shared view of every task's last-run status (for health reporting)
+#[derive(Clone, Default)]
+pub struct SchedulerStatus {
+    reports: Arc<Mutex<BTreeMap<String, TaskReport>>>,
+}
+
+impl SchedulerStatus {
+    pub fn reports(&self) -> Vec<TaskReport> {
+        self.reports
+            .lock()
+            .map(|r| r.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Whether every enabled task's last run succeeded
+    pub fn is_healthy(&self) -> bool {
+        self.reports()
+            .iter()
+            .all(|r| !r.enabled || !matches!(r.last_status, Some(TaskStatus::Failed(_))))
+    }
+
+    /// Task statuses in the shape served by the health endpoint
+    pub fn health_json(&self) -> Value {
+        let tasks: Vec<Value> = self
+            .reports()
+            .iter()
+            .map(|r| {
+                json!({
+                    "name": r.name,
+                    "enabled": r.enabled,
+                    "interval_secs": r.interval.as_secs(),
+                    "last_run_at": r.last_run_at,
+                    "last_duration_ms": r.last_duration.map(|d| d.as_millis() as u64),
+                    "last_status": match &r.last_status {
+                        Some(TaskStatus::Ok) => json!("ok"),
+                        Some(TaskStatus::Failed(e)) => json!({ "failed": e }),
+                        None => Value::Null,
+                    },
+                    "run_count": r.run_count,
+                    "failure_count": r.failure_count,
+                })
+            })
+            .collect();
+        json!({ "healthy": self.is_healthy(), "tasks": tasks })
+    }
+
+    fn update(&self, name: &str, f: impl FnOnce(&mut TaskReport)) {
+        if let Ok(mut reports) = self.reports.lock() {
+            if let Some(report) = reports.get_mut(name) {
+                f(report);
+            }
+        }
+    }
+}
+
+/// Cron-like runner for recurring maintenance jobs
+#[derive(Default)]
+pub struct Scheduler {
+    tasks: Vec<ScheduledTask>,
+    status: SchedulerStatus,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a job that runs every `interval`, first run one interval from now
+    pub fn add(
+        &mut self,
+        name: &str,
+        interval: Duration,
+        enabled: bool,
+        job: impl FnMut() -> Result<()> + Send + 'static,
+    ) {
+        self.tasks.push(ScheduledTask {
+            name: name.to_string(),
+            interval,
+            enabled,
+            next_run: Instant::now() + interval,
+            job: Box::new(job),
+        });
+        if let Ok(mut reports) = self.status.reports.lock() {
+            reports.insert(
+                name.to_string(),
+                TaskReport {
+                    name: name.to_string(),
+                    enabled,
+                    interval,
+                    last_run_at: None,
+                    last_duration: None,
+                    last_status: None,
+                    run_count: 0,
+                    failure_count: 0,
+                },
+            );
+        }
+    }
+
+    /// Turn a task on or off. Returns false if no task has that name.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        let Some(task) = self.tasks.iter_mut().find(|t| t.name == name) else {
+            return false;
+        };
+        task.enabled = enabled;
+        self.status.update(name, |r| r.enabled = enabled);
+        true
+    }
+
+    /// Handle for reading task statuses from another thread
+    pub fn status(&self) -> SchedulerStatus {
+        self.status.clone()
+    }
+
+    /// Run every enabled task that is due. Returns how many ran.
+    pub fn run_pending(&mut self) -> usize {
+        let now = Instant::now();
+        let mut ran = 0;
+        for task in self.tasks.iter_mut() {
+            if !task.enabled || now < task.next_run {
+                continue;
+            }
+
+            let started = Instant::now();
+            let result = (task.job)();
+            let duration = started.elapsed();
+            task.next_run = Instant::now() + task.interval;
+            ran += 1;
+
+            if let Err(e) = &result {
+                warn!(task = %task.name, error = %e, "Scheduled task failed");
+            }
+            self.status.update(&task.name, |r| {
+                r.last_run_at = Some(now_unix());
+                r.last_duration = Some(duration);
+                r.run_count += 1;
+                r.last_status = Some(match &result {
+                    Ok(()) => TaskStatus::Ok,
+                    Err(e) => {
+                        r.failure_count += 1;
+                        TaskStatus::Failed(e.to_string())
+                    }
+                });
+            });
+        }
+        ran
+    }
+
+    /// Run due tasks until `stop` is set
+    pub fn run(&mut self, stop: Arc<AtomicBool>) {
+        while !stop.load(Ordering::Relaxed) {
+            self.run_pending();
+            std::thread::sleep(TICK_INTERVAL);
+        }
+    }
+}
+
+/// Scheduler with the bot's built-in maintenance jobs
+pub fn default_scheduler() -> Scheduler {
+    let mut scheduler = Scheduler::new();
+
+    // Record graduations of every mint the bot has touched
+    let rpc = rpc_pool::get_client();
+    scheduler.add("migration_poll", Duration::from_secs(60), true, move || {
+        MigrationTracker::open_default()?.poll_watched(&rpc)?;
+        Ok(())
+    });
+
+    // Bring failed RPC endpoints back into rotation once they recover
+    scheduler.add("rpc_health", Duration::from_secs(30), true, || {
+        let unhealthy: Vec<String> = rpc_pool::pool()
+            .check_health()
+            .into_iter()
+            .filter(|e| !e.healthy)
+            .map(|e| e.url)
+            .collect();
+        if unhealthy.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("Unhealthy RPC endpoints: {}", unhealthy.join(", ")))
+        }
+    });
+
+    // Keep the cached SOL/USD price warm for USD reporting
+    let rpc = rpc_pool::get_client();
+    let refresh = Duration::from_secs(config::get().price_feed.refresh_secs);
+    scheduler.add("sol_price", refresh, true, move || {
+        price_feed::refresh(&rpc)?;
+        Ok(())
+    });
+
+    // Unwrap stray WSOL and top the trading wallet up before it runs dry
+    let rpc = rpc_pool::get_client();
+    let interval = Duration::from_secs(config::get().balance.check_interval_secs);
+    scheduler.add("balance", interval, true, move || {
+        let wallet = signer::load_default_wallet()?;
+        balance::maintain(&rpc, &wallet)?;
+        Ok(())
+    });
+
+    // Periodic fee / position summary
+    scheduler.add("fee_report", Duration::from_secs(3_600), true, || {
+        let portfolio = Portfolio::open_default()?;
+        let summary = portfolio.fee_summary()?;
+        info!(
+            succeeded = summary.succeeded_count,
+            failed = summary.failed_count,
+            expired = summary.expired_count,
+            wasted_lamports = summary.fees_wasted_on_failures(),
+            tip_lamports = summary.success_tips,
+            open_positions = portfolio.get_open_positions()?.len(),
+            "Fee report"
+        );
+        Ok(())
+    });
+
+    scheduler
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_runs_due_tasks_and_tracks_status() {
+        let mut scheduler = Scheduler::new();
+        scheduler.add("ok", Duration::ZERO, true, || Ok(()));
+        scheduler.add("broken", Duration::ZERO, true, || Err(anyhow!("boom")));
+        scheduler.add("off", Duration::ZERO, false, || Ok(()));
+
+        assert_eq!(scheduler.run_pending(), 2);
+        let status = scheduler.status();
+        assert!(!status.is_healthy());
+
+        assert!(scheduler.set_enabled("broken", false));
+        assert!(status.is_healthy());
+        let health = status.health_json();
+        assert_eq!(health["tasks"][0]["name"], "broken");
+        assert_eq!(health["tasks"][0]["last_status"]["failed"], "boom");
+        assert_eq!(health["tasks"][2]["run_count"], 1);
+    }
+}
@@ -0,0 +1,267 @@
+use anyhow::{anyhow, Result};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signer::Signer};
+use std::time::{Duration, Instant};
+
+use crate::config::ExecutionMode;
+use crate::portfolio::{Portfolio, Position};
+use crate::pump_sell::{SellAmount, SellRequest};
+use crate::{cal, config, pump_buy, pump_sell, rpc_pool, signer, token_accounts};
+
+/// Outcome of one pipeline stage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StageStatus {
+    Passed(String),
+    Failed(String),
+    /// Not run because an earlier stage failed
+    Skipped,
+}
+
+#[derive(Debug, Clone)]
+pub struct StageReport {
+    pub name: &'static str,
+    pub status: StageStatus,
+    pub elapsed: Duration,
+}
+
+/// Per-stage results of a selftest run
+#[derive(Debug, Clone, Default)]
+pub struct SelftestReport {
+    pub stages: Vec<StageReport>,
+}
+
+impl SelftestReport {
+    pub fn passed(&self) -> bool {
+        !self.stages.is_empty()
+            && self
+                .stages
+                .iter()
+                .all(|s| matches!(s.status, StageStatus::Passed(_)))
+    }
+
+    /// Run `stage` unless an earlier one failed, recording how it went
+    fn run<T>(
+        &mut self,
+        name: &'static str,
+        stage: impl FnOnce() -> Result<(T, String)>,
+    ) -> Option<T> {
+        if self
+            .stages
+            .iter()
+            .any(|s| matches!(s.status, StageStatus::Failed(_)))
+        {
+            self.skip(name);
+            return None;
+        }
+
+        let started = Instant::now();
+        let (status, value) = match stage() {
+            Ok((value, detail)) => (StageStatus::Passed(detail), Some(value)),
+            Err(e) => (StageStatus::Failed(e.to_string()), None),
+        };
+        self.stages.push(StageReport {
+            name,
+            status,
+            elapsed: started.elapsed(),
+        });
+        value
+    }
+
+    fn skip(&mut self, name: &'static str) {
+        self.stages.push(StageReport {
+            name,
+            status: StageStatus::Skipped,
+            elapsed: Duration::ZERO,
+        });
+    }
+
+    pub fn print(&self) {
+        println!("\n=== Selftest Report ===");
+        for stage in &self.stages {
+            match &stage.status {
+                StageStatus::Passed(detail) => {
+                    println!("  ✓ {:<12} {:>6.1?}  {}", stage.name, stage.elapsed, detail)
+                }
+                StageStatus::Failed(e) => {
+                    println!("  ✗ {:<12} {:>6.1?}  {}", stage.name, stage.elapsed, e)
+                }
+                StageStatus::Skipped => println!("  - {:<12} skipped", stage.name),
+            }
+        }
+        println!("Result: {}", if self.passed() { "PASS" } else { "FAIL" });
+    }
+}
+
+/// Only devnet and local validators are allowed, never mainnet
+fn is_test_cluster(rpc_url: &str) -> bool {
+    let url = rpc_url.to_lowercase();
+    url.contains("devnet") || url.contains("localhost") || url.contains("127.0.0.1")
+}
+
+fn position(mint: &Pubkey) -> Result<Option<Position>> {
+    Portfolio::open_default()?.get_position(mint)
+}
+
+/// Run the full trade pipeline on `mint` with a `sol_amount` buy:
+/// wallet load, quote, buy, confirmation, position open, sell, PnL update
+pub fn run_selftest(mint: Pubkey, sol_amount: u64) -> SelftestReport {
+    let config = config::get();
+    let mut report = SelftestReport::default();
+
+    report.run("cluster", || {
+        if !is_test_cluster(&config.rpc.url) {
+            return Err(anyhow!(
+                "Refusing to trade against {}: selftest only runs on devnet or a local validator",
+                config.rpc.url
+            ));
+        }
+        if config.trading.mode != ExecutionMode::Live {
+            return Err(anyhow!(
+                "trading.mode is {}; set it to live (PUMPBOT_MODE=live) to send trades",
+                config.trading.mode.as_str()
+            ));
+        }
+        Ok(((), config.rpc.url.clone()))
+    });
+    let rpc = rpc_pool::get_client();
+
+    let user = report.run("wallet", || {
+        let user = signer::load_wallet(&config.wallet.private_key)?;
+        let balance = rpc.get_balance(&user.pubkey())?;
+        // The buy must leave the reserve kept for rent and fees
+        let needed = sol_amount + config.balance.reserve_lamports;
+        if balance < needed {
+            return Err(anyhow!(
+                "Balance {} SOL is below the {} SOL needed",
+                balance as f64 / LAMPORTS_PER_SOL as f64,
+                needed as f64 / LAMPORTS_PER_SOL as f64
+            ));
+        }
+        let detail = format!(
+            "{} with {} SOL",
+            user.pubkey(),
+            balance as f64 / LAMPORTS_PER_SOL as f64
+        );
+        Ok((user, detail))
+    });
+
+    let quote = report.run("quote", || {
+        let quote = cal::quote_buy(&rpc, &mint, sol_amount)?;
+        if quote.output_amount == 0 {
+            return Err(anyhow!("Quote returned zero tokens"));
+        }
+        let detail = format!("{} lamports -> {} tokens", sol_amount, quote.output_amount);
+        Ok((quote, detail))
+    });
+
+    let (Some(user), Some(quote)) = (user, quote) else {
+        for name in ["buy", "confirmation", "position", "sell", "pnl"] {
+            report.skip(name);
+        }
+        return report;
+    };
+    let token_program = rpc
+        .get_account(&mint)
+        .map(|a| a.owner)
+        .unwrap_or(spl_token::ID);
+    let held = |rpc: &RpcClient| -> Result<u64> {
+        Ok(
+            token_accounts::fetch_token_holdings(rpc, &user.pubkey(), &mint, &token_program)?
+                .total_owned(),
+        )
+    };
+    let balance_before = held(&rpc).unwrap_or(0);
+    let position_before = position(&mint).ok().flatten();
+
+    let max_sol_cost = pump_buy::with_slippage(sol_amount, config.trading.buy_slippage_bps);
+    report.run("buy", || {
+        pump_buy::run_pump_buy(quote.output_amount, mint, max_sol_cost)?;
+        Ok((
+            (),
+            format!(
+                "{} tokens for at most {} lamports",
+                quote.output_amount, max_sol_cost
+            ),
+        ))
+    });
+
+    report.run("confirmation", || {
+        let balance = held(&rpc)?;
+        if balance <= balance_before {
+            return Err(anyhow!(
+                "Token balance did not increase (still {})",
+                balance
+            ));
+        }
+        Ok((
+            (),
+            format!("token balance {} -> {}", balance_before, balance),
+        ))
+    });
+
+    let opened = report.run("position", || {
+        let position = position(&mint)?.ok_or_else(|| anyhow!("No position recorded"))?;
+        let fills_before = position_before.as_ref().map_or(0, |p| p.fill_count);
+        if !position.is_open() || position.fill_count <= fills_before {
+            return Err(anyhow!("Buy fill was not recorded"));
+        }
+        let detail = format!(
+            "{} tokens, cost basis {} lamports",
+            position.token_amount, position.cost_basis_lamports
+        );
+        Ok((position, detail))
+    });
+
+    report.run("sell", || {
+        let request = SellRequest {
+            mint,
+            amount: SellAmount::All,
+            force: false,
+        };
+        pump_sell::run_sell_request(&request, config.trading.sell_slippage_bps)?;
+        Ok(((), "sold full balance".to_string()))
+    });
+
+    report.run("pnl", || {
+        let opened = opened.ok_or_else(|| anyhow!("No opening position"))?;
+        let closed = position(&mint)?.ok_or_else(|| anyhow!("Position disappeared"))?;
+        if closed.fill_count <= opened.fill_count {
+            return Err(anyhow!("Sell fill was not recorded"));
+        }
+        let pnl = closed.realized_pnl_lamports - opened.realized_pnl_lamports;
+        Ok((
+            (),
+            format!(
+                "realized {} lamports, {} tokens left",
+                pnl, closed.token_amount
+            ),
+        ))
+    });
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stages_after_failure_are_skipped() {
+        let mut report = SelftestReport::default();
+        assert_eq!(report.run("first", || Ok((1, "ok".to_string()))), Some(1));
+        assert_eq!(
+            report.run("second", || Err::<((), String), _>(anyhow!("boom"))),
+            None
+        );
+        assert_eq!(report.run("third", || Ok(((), "ok".to_string()))), None);
+
+        assert!(!report.passed());
+        assert_eq!(
+            report.stages[1].status,
+            StageStatus::Failed("boom".to_string())
+        );
+        assert_eq!(report.stages[2].status, StageStatus::Skipped);
+        assert!(is_test_cluster("https://api.devnet.solana.com"));
+        assert!(!is_test_cluster("https://api.mainnet-beta.solana.com"));
+    }
+}
@@ -0,0 +1,1114 @@
+use anyhow::{anyhow, Result};
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcSendTransactionConfig, RpcSimulateTransactionConfig},
+};
+use solana_sdk::{
+    address_lookup_table::AddressLookupTableAccount,
+    commitment_config::CommitmentConfig,
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::Signature,
+    signer::Signer,
+    transaction::{TransactionError, VersionedTransaction},
+};
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, instrument, warn};
+
+use crate::alt;
+use crate::blockhash_cache;
+use crate::config::{self, ExecutionMode, SendStrategy, TradingConfig};
+use crate::error::{self, BotError, SimulationError};
+use crate::fills;
+use crate::jito;
+use crate::nonce::{self, DurableNonce};
+use crate::portfolio::{Fill, Portfolio, Side};
+use crate::price_feed;
+use crate::rpc_pool::{self, RpcPool};
+use crate::telegram;
+use crate::tx_fees::{self, TxCost, TxOutcome};
+
+// Constants
+/// Most compute units a transaction may request
+const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+/// How often spam threads check whether the transaction has landed
+const SPAM_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How transactions are (re)sent and confirmed
+#[derive(Debug, Clone)]
+pub struct SendConfig {
+    /// Number of times to re-sign with a fresh blockhash after expiry
+    pub max_attempts: u32,
+    /// How often to rebroadcast while waiting for confirmation
+    pub rebroadcast_interval: Duration,
+    /// How often to poll signature status
+    pub poll_interval: Duration,
+    pub skip_preflight: bool,
+    pub commitment: CommitmentConfig,
+    /// Lookup tables to compile v0 transactions against (legacy transactions when empty)
+    pub lookup_tables: Vec<AddressLookupTableAccount>,
+    /// Defaults to `trading.send_strategy`
+    pub strategy: SendStrategy,
+    /// Compute unit prices to step up through while a transaction isn't landing,
+    /// defaulting to `fees.escalation_ladder` (empty = never escalate)
+    pub escalation_ladder: Vec<u64>,
+    /// Blocks to wait for a transaction to land before stepping up its price
+    pub escalation_blocks: u64,
+}
+
+impl Default for SendConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            rebroadcast_interval: Duration::from_secs(2),
+            poll_interval: Duration::from_millis(400),
+            skip_preflight: false,
+            commitment: CommitmentConfig::confirmed(),
+            lookup_tables: Vec::new(),
+            strategy: config::get().trading.send_strategy,
+            escalation_ladder: config::get().fees.escalation_ladder.clone(),
+            escalation_blocks: config::get().fees.escalation_blocks,
+        }
+    }
+}
+
+/// Final status of a trade transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TradeStatus {
+    /// Landed and executed successfully
+    Confirmed,
+    /// Landed (or failed preflight) with a program error
+    Failed(String),
+    /// Every attempt expired without landing
+    Expired,
+}
+
+/// Outcome of `send_and_confirm_with_retries`
+#[derive(Debug)]
+pub struct TradeResult {
+    /// Signature of the last attempt (the one that landed, if any)
+    pub signature: Signature,
+    pub status: TradeStatus,
+    /// Number of distinct signed transactions sent
+    pub attempts: u32,
+    pub elapsed: Duration,
+    /// Network fees charged to the attempt that landed
+    pub cost: TxCost,
+    /// The landed transaction with its status meta, if it could be fetched
+    pub transaction: Option<EncodedConfirmedTransactionWithStatusMeta>,
+    /// What the trade actually filled at, set by `execute_trade` once it lands
+    pub fill: Option<Fill>,
+}
+
+impl TradeResult {
+    pub fn is_confirmed(&self) -> bool {
+        self.status == TradeStatus::Confirmed
+    }
+
+    /// Network fees, tips and the realized trading fees, in lamports
+    pub fn total_fees(&self) -> u64 {
+        self.cost.total_cost() + self.fill.as_ref().map_or(0, |f| f.fee_lamports)
+    }
+}
+
+/// Whether a send error just means the transaction already landed
+fn is_already_processed(error: &str) -> bool {
+    error.contains("AlreadyProcessed") || error.contains("already been processed")
+}
+
+/// Check whether `signature` has landed. Returns None while still pending.
+fn check_status(
+    rpc: &RpcClient,
+    signature: &Signature,
+    commitment: CommitmentConfig,
+) -> Result<Option<std::result::Result<(), TransactionError>>> {
+    Ok(rpc.get_signature_status_with_commitment(signature, commitment)?)
+}
+
+/// Check `transactions` in one batch and return the first that has landed, with its status
+fn check_statuses<'a>(
+    rpc: &RpcClient,
+    transactions: &'a [VersionedTransaction],
+    commitment: CommitmentConfig,
+) -> Result<
+    Option<(
+        &'a VersionedTransaction,
+        std::result::Result<(), TransactionError>,
+    )>,
+> {
+    if transactions.is_empty() {
+        return Ok(None);
+    }
+    let signatures: Vec<Signature> = transactions.iter().map(|tx| tx.signatures[0]).collect();
+    let statuses = rpc.get_signature_statuses(&signatures)?.value;
+    Ok(transactions
+        .iter()
+        .zip(statuses)
+        .find_map(|(transaction, status)| {
+            let status = status?;
+            status
+                .satisfies_commitment(commitment)
+                .then_some((transaction, status.status))
+        }))
+}
+
+/// Whether any of `transactions` has been processed at all
+fn any_seen(rpc: &RpcClient, transactions: &[VersionedTransaction]) -> Result<bool> {
+    let signatures: Vec<Signature> = transactions.iter().map(|tx| tx.signatures[0]).collect();
+    Ok(rpc
+        .get_signature_statuses(&signatures)?
+        .value
+        .iter()
+        .any(Option::is_some))
+}
+
+/// Build the result for a landed transaction, including the fee it was charged
+fn landed(
+    rpc: &RpcClient,
+    transaction: &VersionedTransaction,
+    status: std::result::Result<(), TransactionError>,
+    attempts: u32,
+    started: Instant,
+) -> TradeResult {
+    let signature = transaction.signatures[0];
+    let (status, outcome) = match status {
+        Ok(()) => (TradeStatus::Confirmed, TxOutcome::Succeeded),
+        Err(e) => (
+            TradeStatus::Failed(e.to_string()),
+            TxOutcome::Failed(e.to_string()),
+        ),
+    };
+    let fetched = tx_fees::fetch_transaction(rpc, &signature);
+    let cost = fetched
+        .as_ref()
+        .map_err(|e| anyhow!("{}", e))
+        .and_then(|landed| {
+            tx_fees::transaction_cost(
+                landed,
+                &signature,
+                transaction.signatures.len(),
+                outcome.clone(),
+            )
+        })
+        .unwrap_or_else(|e| {
+            warn!(%signature, error = %e, "Failed to fetch transaction fee");
+            TxCost {
+                outcome,
+                ..TxCost::unlanded(signature)
+            }
+        });
+
+    TradeResult {
+        signature,
+        status,
+        attempts,
+        elapsed: started.elapsed(),
+        cost,
+        transaction: fetched.ok(),
+        fill: None,
+    }
+}
+
+/// Result for a transaction that never landed, so was charged nothing
+fn unlanded(
+    signature: Signature,
+    status: TradeStatus,
+    attempts: u32,
+    started: Instant,
+) -> TradeResult {
+    TradeResult {
+        signature,
+        status,
+        attempts,
+        elapsed: started.elapsed(),
+        cost: TxCost::unlanded(signature),
+        transaction: None,
+        fill: None,
+    }
+}
+
+/// A route a spammed transaction is broadcast along besides the RPC pool
+enum SpamTarget {
+    Rpc(RpcClient),
+    Jito,
+}
+
+impl SpamTarget {
+    /// One client per endpoint of `pool`, `trading.spam_endpoints` of them, plus Jito if
+    /// `trading.spam_jito` is set
+    fn new(pool: &RpcPool, trading: &TradingConfig) -> Vec<Self> {
+        let mut clients = pool.endpoint_clients();
+        if trading.spam_endpoints > 0 {
+            clients.truncate(trading.spam_endpoints);
+        }
+        let mut targets: Vec<Self> = clients.into_iter().map(SpamTarget::Rpc).collect();
+        if trading.spam_jito {
+            targets.push(SpamTarget::Jito);
+        }
+        targets
+    }
+
+    fn name(&self) -> String {
+        match self {
+            SpamTarget::Rpc(rpc) => rpc.url(),
+            SpamTarget::Jito => "jito".to_string(),
+        }
+    }
+
+    fn send(&self, transaction: &VersionedTransaction) -> Result<()> {
+        match self {
+            // Preflight already passed on the pool's first send
+            SpamTarget::Rpc(rpc) => rpc
+                .send_transaction_with_config(
+                    transaction,
+                    RpcSendTransactionConfig {
+                        skip_preflight: true,
+                        max_retries: Some(0),
+                        ..Default::default()
+                    },
+                )
+                .map(|_| ())
+                .map_err(Into::into),
+            SpamTarget::Jito => jito::send_transaction(transaction),
+        }
+    }
+}
+
+/// Sets the flag it holds when dropped
+struct StopOnDrop(Arc<AtomicBool>);
+
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Release);
+    }
+}
+
+/// Rebroadcast `transaction` along every target in `targets` every `interval`, each on
+/// its own thread, until `done` is set. A send still in flight then finishes on its own
+/// without holding up the caller.
+fn spawn_spam(
+    targets: Vec<SpamTarget>,
+    transaction: &VersionedTransaction,
+    interval: Duration,
+    done: &Arc<AtomicBool>,
+) {
+    let signature = transaction.signatures[0];
+    info!(%signature, targets = targets.len(), "Spamming transaction");
+    let transaction = Arc::new(transaction.clone());
+    for target in targets {
+        let (transaction, done) = (transaction.clone(), done.clone());
+        std::thread::spawn(move || {
+            let mut last_broadcast: Option<Instant> = None;
+            while !done.load(Ordering::Acquire) {
+                if last_broadcast.is_none_or(|t| t.elapsed() >= interval) {
+                    if let Err(e) = target.send(&transaction) {
+                        let error = e.to_string();
+                        if !is_already_processed(&error) {
+                            debug!(%signature, target = %target.name(), %error, "Spam send failed");
+                        }
+                    }
+                    last_broadcast = Some(Instant::now());
+                }
+                std::thread::sleep(SPAM_POLL_INTERVAL);
+            }
+        });
+    }
+}
+
+/// How a broadcast ended
+enum Broadcast {
+    /// Landed, or was rejected by preflight
+    Done(Box<TradeResult>),
+    /// Its blockhash expired without anything landing
+    Expired,
+    /// `escalation_blocks` passed without anything landing
+    Escalate,
+}
+
+/// Broadcast the last of `transactions` every `rebroadcast_interval` until any of them
+/// lands, it is rejected by preflight or their blockhash passes `last_valid_block_height`.
+/// The earlier ones are lower fee steps of the same trade that may still land. With
+/// `escalate`, gives up after `escalation_blocks` so the caller can raise the fee.
+///
+/// With `SendStrategy::Spam`, once the first send passes preflight it is also
+/// rebroadcast along every spam target at once. The one signature they share is polled
+/// for all of them, and every rebroadcast stops as soon as it lands or expires. With
+/// `SendStrategy::Protected` every send goes to the block engine as bundle-only instead
+/// of the RPC, so the transaction never reaches a public mempool.
+fn broadcast(
+    rpc: &RpcClient,
+    transactions: &[VersionedTransaction],
+    last_valid_block_height: u64,
+    config: &SendConfig,
+    sent: u32,
+    started: Instant,
+    escalate: bool,
+) -> Result<Broadcast> {
+    let send_config = RpcSendTransactionConfig {
+        skip_preflight: config.skip_preflight,
+        preflight_commitment: Some(config.commitment.commitment),
+        max_retries: Some(0),
+        ..Default::default()
+    };
+    let transaction = transactions
+        .last()
+        .expect("at least one transaction to broadcast");
+    let signature = transaction.signatures[0];
+    let mut spam = (config.strategy == SendStrategy::Spam)
+        .then(|| SpamTarget::new(rpc_pool::pool(), &config::get().trading));
+    // Stops the spam threads however the loop below returns
+    let stop = StopOnDrop(Arc::new(AtomicBool::new(false)));
+
+    let mut last_broadcast: Option<Instant> = None;
+    let mut escalate_at: Option<u64> = None;
+    let mut rejected = false;
+    loop {
+        if !rejected && last_broadcast.is_none_or(|t| t.elapsed() >= config.rebroadcast_interval) {
+            let result = match config.strategy {
+                SendStrategy::Protected => jito::send_bundle_only(transaction),
+                _ => rpc
+                    .send_transaction_with_config(transaction, send_config)
+                    .map(|_| ())
+                    .map_err(Into::into),
+            };
+            if let Err(e) = result {
+                let error = e.to_string();
+                if !is_already_processed(&error) {
+                    if last_broadcast.is_none() {
+                        // Preflight rejected it - retrying the same instructions won't help
+                        if transactions.len() == 1 {
+                            let status = TradeStatus::Failed(error);
+                            return Ok(Broadcast::Done(Box::new(unlanded(
+                                signature, status, sent, started,
+                            ))));
+                        }
+                        // Likely because a lower fee step already landed
+                        warn!(%signature, %error, "Escalated send rejected, watching earlier ones");
+                        rejected = true;
+                    } else {
+                        warn!(%signature, %error, "Rebroadcast failed");
+                    }
+                }
+            }
+            last_broadcast = Some(Instant::now());
+            if let Some(targets) = spam.take().filter(|_| !rejected) {
+                spawn_spam(targets, transaction, config.rebroadcast_interval, &stop.0);
+            }
+        }
+
+        if let Some((landed_tx, status)) = check_statuses(rpc, transactions, config.commitment)? {
+            return Ok(Broadcast::Done(Box::new(landed(
+                rpc, landed_tx, status, sent, started,
+            ))));
+        }
+
+        let block_height = rpc.get_block_height()?;
+        if block_height > last_valid_block_height {
+            warn!(%signature, "Blockhash expired");
+            return Ok(Broadcast::Expired);
+        }
+        let deadline = *escalate_at.get_or_insert(block_height + config.escalation_blocks);
+        if escalate && !rejected && block_height >= deadline {
+            return Ok(Broadcast::Escalate);
+        }
+
+        std::thread::sleep(config.poll_interval);
+    }
+}
+
+/// Send `instructions` signed by `payer`, rebroadcasting until confirmed and
+/// re-signing with a fresh blockhash whenever the previous one expires. With an
+/// `escalation_ladder`, a transaction that hasn't landed after `escalation_blocks` is
+/// re-signed against the same blockhash at the ladder's next compute unit price, while
+/// the lower steps are still watched in case one of them lands.
+pub fn send_and_confirm_with_retries(
+    rpc: &RpcClient,
+    instructions: &[Instruction],
+    payer: &dyn Signer,
+    config: &SendConfig,
+) -> Result<TradeResult> {
+    let started = Instant::now();
+    let mut instructions = instructions.to_vec();
+    // Bundle-only transactions are dropped by the block engine unless they tip
+    if config.strategy == SendStrategy::Protected && !pays_tip(&instructions) {
+        instructions.push(jito::tip_instruction(
+            &payer.pubkey(),
+            config::get().jito.tip_lamports,
+        ));
+    }
+    let mut sent = 0;
+    // Fee steps signed against the current blockhash
+    let mut pending: Vec<VersionedTransaction> = Vec::new();
+    for attempt in 1..=config.max_attempts.max(1) {
+        // A previous attempt may have landed right as its blockhash expired
+        if let Some((transaction, status)) = check_statuses(rpc, &pending, config.commitment)? {
+            return Ok(landed(rpc, transaction, status, sent, started));
+        }
+        pending.clear();
+
+        // A later attempt only runs after the previous blockhash expired, by which time the
+        // cached one has long been replaced
+        let (blockhash, last_valid_block_height) =
+            blockhash_cache::global().get_with_commitment(rpc, config.commitment)?;
+        loop {
+            let transaction = alt::build_versioned_transaction(
+                &instructions,
+                payer,
+                &config.lookup_tables,
+                blockhash,
+            )?;
+            let price = compute_unit_price(&instructions);
+            info!(attempt, price, signature = %transaction.signatures[0], "Sending transaction");
+            pending.push(transaction);
+            sent += 1;
+
+            let next_price = config
+                .escalation_ladder
+                .iter()
+                .copied()
+                .find(|step| *step > price);
+            match broadcast(
+                rpc,
+                &pending,
+                last_valid_block_height,
+                config,
+                sent,
+                started,
+                next_price.is_some(),
+            )? {
+                Broadcast::Done(result) => return Ok(*result),
+                Broadcast::Expired => break,
+                Broadcast::Escalate => {
+                    let price = next_price.expect("escalates only with a higher step");
+                    warn!(
+                        attempt,
+                        price, "Transaction not landing, raising priority fee"
+                    );
+                    instructions = with_compute_unit_price(&instructions, price);
+                }
+            }
+        }
+    }
+
+    if let Some((transaction, status)) = check_statuses(rpc, &pending, config.commitment)? {
+        return Ok(landed(rpc, transaction, status, sent, started));
+    }
+    let transaction = pending
+        .last()
+        .ok_or_else(|| anyhow!("No transaction attempts were made"))?;
+    Ok(unlanded(
+        transaction.signatures[0],
+        TradeStatus::Expired,
+        sent,
+        started,
+    ))
+}
+
+/// Whether any of `instructions` touches a Jito tip account
+fn pays_tip(instructions: &[Instruction]) -> bool {
+    instructions
+        .iter()
+        .flat_map(|ix| &ix.accounts)
+        .any(|meta| jito::is_tip_account(&meta.pubkey))
+}
+
+/// Send a transaction signed elsewhere (e.g. by an aggregator's API), rebroadcasting
+/// until it confirms or its blockhash expires after `last_valid_block_height`.
+/// Unlike `send_and_confirm_with_retries` it can't be re-signed, so there is one attempt.
+pub fn send_and_confirm_signed(
+    rpc: &RpcClient,
+    transaction: &VersionedTransaction,
+    last_valid_block_height: u64,
+    config: &SendConfig,
+) -> Result<TradeResult> {
+    let started = Instant::now();
+    let signature = transaction.signatures[0];
+    info!(%signature, "Sending signed transaction");
+    let transactions = std::slice::from_ref(transaction);
+    if let Broadcast::Done(result) = broadcast(
+        rpc,
+        transactions,
+        last_valid_block_height,
+        config,
+        1,
+        started,
+        false,
+    )? {
+        return Ok(*result);
+    }
+    Ok(match check_status(rpc, &signature, config.commitment)? {
+        Some(status) => landed(rpc, transaction, status, 1, started),
+        None => unlanded(signature, TradeStatus::Expired, 1, started),
+    })
+}
+
+/// Race `transactions`, variants of one trade signed at different fees, against each other:
+/// all of them are broadcast at once every `rebroadcast_interval` and watched together
+/// until the first lands or `last_valid_block_height` passes. The others stop being
+/// tracked once one lands. Unless they share a durable nonce they may still land after it.
+/// Variants sharing a durable nonce don't expire with a blockhash: they are raced until
+/// the nonce moves on, then watched until the one that moved it confirms.
+pub fn send_and_confirm_raced(
+    rpc: &RpcClient,
+    transactions: &[VersionedTransaction],
+    last_valid_block_height: u64,
+    config: &SendConfig,
+) -> Result<TradeResult> {
+    let started = Instant::now();
+    let first = transactions
+        .first()
+        .ok_or_else(|| anyhow!("No transactions to race"))?;
+    let attempts = transactions.len() as u32;
+    info!(variants = attempts, "Racing transaction variants");
+    let send_config = RpcSendTransactionConfig {
+        skip_preflight: true,
+        max_retries: Some(0),
+        ..Default::default()
+    };
+
+    let nonce =
+        nonce::nonce_account(first).map(|account| (account, *first.message.recent_blockhash()));
+    let mut nonce_advanced = false;
+    let mut last_broadcast: Option<Instant> = None;
+    loop {
+        if !nonce_advanced
+            && last_broadcast.is_none_or(|t| t.elapsed() >= config.rebroadcast_interval)
+        {
+            std::thread::scope(|scope| {
+                for transaction in transactions {
+                    scope.spawn(move || {
+                        let signature = transaction.signatures[0];
+                        let sent = match config.strategy {
+                            SendStrategy::Protected => jito::send_bundle_only(transaction),
+                            _ => rpc
+                                .send_transaction_with_config(transaction, send_config)
+                                .map(|_| ())
+                                .map_err(Into::into),
+                        };
+                        if let Err(error) = sent {
+                            debug!(%signature, %error, "Race variant send failed");
+                        }
+                        // A tipped variant is only auctioned through the block engine
+                        if config.strategy != SendStrategy::Protected && jito::has_tip(transaction)
+                        {
+                            if let Err(error) = jito::send_transaction(transaction) {
+                                debug!(%signature, %error, "Race variant Jito send failed");
+                            }
+                        }
+                    });
+                }
+            });
+            last_broadcast = Some(Instant::now());
+        }
+
+        if let Some((winner, status)) = check_statuses(rpc, transactions, config.commitment)? {
+            info!(signature = %winner.signatures[0], elapsed = ?started.elapsed(), "Race won");
+            return Ok(landed(rpc, winner, status, attempts, started));
+        }
+        let expired = match nonce {
+            Some((account, signed_nonce)) => {
+                nonce_advanced =
+                    nonce_advanced || DurableNonce::fetch(rpc, &account)?.nonce != signed_nonce;
+                // Moved on by a variant still short of the commitment, or by something else
+                nonce_advanced && !any_seen(rpc, transactions)?
+            }
+            None => rpc.get_block_height()? > last_valid_block_height,
+        };
+        if expired {
+            warn!(signature = %first.signatures[0], "Race over before any variant landed");
+            return Ok(unlanded(
+                first.signatures[0],
+                TradeStatus::Expired,
+                attempts,
+                started,
+            ));
+        }
+        std::thread::sleep(config.poll_interval);
+    }
+}
+
+/// Simulate `instructions` against the latest blockhash and return the units consumed.
+/// A failure is returned as `BotError::SimulationFailed`, classified from the program logs.
+pub fn simulate(
+    rpc: &RpcClient,
+    instructions: &[Instruction],
+    payer: &dyn Signer,
+) -> error::Result<Option<u64>> {
+    let transaction = alt::build_versioned_transaction(instructions, payer, &[], Hash::default())?;
+    simulate_transaction(rpc, &transaction)
+}
+
+/// Simulate an already built `transaction`, as `simulate` does
+pub fn simulate_transaction(
+    rpc: &RpcClient,
+    transaction: &VersionedTransaction,
+) -> error::Result<Option<u64>> {
+    let simulation = rpc.simulate_transaction_with_config(
+        transaction,
+        RpcSimulateTransactionConfig {
+            replace_recent_blockhash: true,
+            commitment: Some(CommitmentConfig::confirmed()),
+            ..Default::default()
+        },
+    )?;
+    // A failed simulation's logs are what explain it
+    for log in simulation.value.logs.iter().flatten() {
+        if simulation.value.err.is_some() {
+            warn!("{}", log);
+        } else {
+            debug!("{}", log);
+        }
+    }
+
+    match simulation.value.err {
+        None => Ok(simulation.value.units_consumed),
+        Some(e) => {
+            let error =
+                SimulationError::new(e.to_string(), simulation.value.logs.unwrap_or_default());
+            warn!(failure = %error.failure, error = %error.error, "Simulation failed");
+            Err(error.into())
+        }
+    }
+}
+
+/// Simulate `instructions` and log the result
+pub fn simulate_and_print(
+    rpc: &RpcClient,
+    instructions: &[Instruction],
+    payer: &dyn Signer,
+) -> error::Result<()> {
+    let units_consumed = simulate(rpc, instructions, payer)?;
+    info!(?units_consumed, "Simulation succeeded");
+    Ok(())
+}
+
+/// `instructions` with any compute unit limit replaced by a leading one requesting `limit`
+pub fn with_compute_unit_limit(instructions: &[Instruction], limit: u32) -> Vec<Instruction> {
+    let set_limit = ComputeBudgetInstruction::set_compute_unit_limit(limit);
+    let is_set_limit = |ix: &Instruction| {
+        ix.program_id == set_limit.program_id && ix.data.first() == set_limit.data.first()
+    };
+    let rest: Vec<Instruction> = instructions
+        .iter()
+        .filter(|ix| !is_set_limit(ix))
+        .cloned()
+        .collect();
+    std::iter::once(set_limit).chain(rest).collect()
+}
+
+/// The compute unit price `instructions` request, 0 if they set none
+pub fn compute_unit_price(instructions: &[Instruction]) -> u64 {
+    let set_price = ComputeBudgetInstruction::set_compute_unit_price(0);
+    instructions
+        .iter()
+        .rev()
+        .find(|ix| {
+            ix.program_id == set_price.program_id && ix.data.first() == set_price.data.first()
+        })
+        .and_then(|ix| Some(u64::from_le_bytes(ix.data.get(1..9)?.try_into().ok()?)))
+        .unwrap_or(0)
+}
+
+/// `instructions` with any compute unit price replaced by a leading one of `micro_lamports`
+pub fn with_compute_unit_price(
+    instructions: &[Instruction],
+    micro_lamports: u64,
+) -> Vec<Instruction> {
+    let set_price = ComputeBudgetInstruction::set_compute_unit_price(micro_lamports);
+    let is_set_price = |ix: &Instruction| {
+        ix.program_id == set_price.program_id && ix.data.first() == set_price.data.first()
+    };
+    let rest: Vec<Instruction> = instructions
+        .iter()
+        .filter(|ix| !is_set_price(ix))
+        .cloned()
+        .collect();
+    std::iter::once(set_price).chain(rest).collect()
+}
+
+/// Simulate `instructions` under the maximum limit and return them requesting the
+/// units consumed plus `margin_percent`
+pub fn estimate_compute_units(
+    rpc: &RpcClient,
+    instructions: &[Instruction],
+    payer: &dyn Signer,
+    margin_percent: u32,
+) -> error::Result<Vec<Instruction>> {
+    let probe = with_compute_unit_limit(instructions, MAX_COMPUTE_UNIT_LIMIT);
+    let consumed = simulate(rpc, &probe, payer)?
+        .ok_or_else(|| anyhow!("Simulation did not report units consumed"))?;
+    let limit =
+        (consumed * (100 + margin_percent as u64) / 100).min(MAX_COMPUTE_UNIT_LIMIT as u64) as u32;
+    debug!(consumed, limit, "Sized compute unit limit from simulation");
+    Ok(with_compute_unit_limit(instructions, limit))
+}
+
+/// Simulate `instructions` before a live send so a trade that would fail is never sent.
+/// With `fees.estimate_compute_units` the same simulation sizes the compute unit limit.
+pub fn simulate_for_send(
+    rpc: &RpcClient,
+    instructions: &[Instruction],
+    payer: &dyn Signer,
+) -> error::Result<Vec<Instruction>> {
+    let fees = &config::get().fees;
+    if fees.estimate_compute_units {
+        return estimate_compute_units(rpc, instructions, payer, fees.compute_unit_margin_percent);
+    }
+    simulate(rpc, instructions, payer)?;
+    Ok(instructions.to_vec())
+}
+
+/// Signature recorded for paper fills, which never touch the chain
+pub(crate) fn paper_signature() -> String {
+    format!("paper-{}", Signature::new_unique())
+}
+
+/// What `execute` runs: instructions it signs itself, a transaction signed elsewhere,
+/// or signed variants of one trade raced against each other
+enum Payload<'a> {
+    Instructions(&'a [Instruction]),
+    Raced {
+        transactions: &'a [VersionedTransaction],
+        last_valid_block_height: u64,
+    },
+    Signed {
+        transaction: &'a VersionedTransaction,
+        last_valid_block_height: u64,
+        /// Simulate before a live send
+        simulate: bool,
+    },
+}
+
+/// Execute a built trade according to `trading.mode`: simulate it, record `fill` as a
+/// paper trade, or send it and record the fill once it lands. `fill` holds the quoted
+/// amounts; live trades record what the landed transaction actually filled at instead.
+/// Returns the fill recorded, None when simulated.
+pub fn execute_trade(
+    rpc: &RpcClient,
+    instructions: &[Instruction],
+    payer: &dyn Signer,
+    fill: Fill,
+) -> error::Result<Option<Fill>> {
+    execute(rpc, Payload::Instructions(instructions), payer, fill)
+}
+
+/// `execute_trade` for a transaction already signed by `payer` against a blockhash
+/// valid until `last_valid_block_height`
+pub fn execute_signed_trade(
+    rpc: &RpcClient,
+    transaction: &VersionedTransaction,
+    last_valid_block_height: u64,
+    payer: &dyn Signer,
+    fill: Fill,
+) -> error::Result<Option<Fill>> {
+    let payload = Payload::Signed {
+        transaction,
+        last_valid_block_height,
+        simulate: true,
+    };
+    execute(rpc, payload, payer, fill)
+}
+
+/// `execute_signed_trade` that sends live trades without simulating them first, for
+/// snipes that can't spare the round trip. Simulate mode still simulates.
+pub fn execute_unsimulated_trade(
+    rpc: &RpcClient,
+    transaction: &VersionedTransaction,
+    last_valid_block_height: u64,
+    payer: &dyn Signer,
+    fill: Fill,
+) -> error::Result<Option<Fill>> {
+    let payload = Payload::Signed {
+        transaction,
+        last_valid_block_height,
+        simulate: false,
+    };
+    execute(rpc, payload, payer, fill)
+}
+
+/// `execute_unsimulated_trade` for variants of one buy at different fees, of which the
+/// first to land is recorded. Simulate mode simulates the first variant.
+pub fn execute_raced_trade(
+    rpc: &RpcClient,
+    transactions: &[VersionedTransaction],
+    last_valid_block_height: u64,
+    payer: &dyn Signer,
+    fill: Fill,
+) -> error::Result<Option<Fill>> {
+    let payload = Payload::Raced {
+        transactions,
+        last_valid_block_height,
+    };
+    execute(rpc, payload, payer, fill)
+}
+
+#[instrument(skip_all, fields(mint = %fill.mint, side = ?fill.side))]
+fn execute(
+    rpc: &RpcClient,
+    payload: Payload,
+    payer: &dyn Signer,
+    mut fill: Fill,
+) -> error::Result<Option<Fill>> {
+    let side = match fill.side {
+        Side::Buy => "Buy",
+        Side::Sell => "Sell",
+    };
+
+    match config::get().trading.mode {
+        ExecutionMode::Simulate => {
+            match payload {
+                Payload::Instructions(instructions) => {
+                    simulate_and_print(rpc, instructions, payer)?
+                }
+                Payload::Signed { transaction, .. } => {
+                    simulate_transaction(rpc, transaction)?;
+                }
+                Payload::Raced { transactions, .. } => {
+                    if let Some(transaction) = transactions.first() {
+                        simulate_transaction(rpc, transaction)?;
+                    }
+                }
+            }
+            info!("Simulation successful, ready to send transaction");
+        }
+        ExecutionMode::Paper => {
+            fill.signature = paper_signature();
+            Portfolio::open_default()?
+                .record_fill_with_price(&fill, price_feed::cached_at(fill.timestamp))?;
+            info!(
+                signature = %fill.signature,
+                token_amount = fill.token_amount,
+                sol_amount = fill.sol_amount,
+                fee = fill.fee_lamports,
+                "Paper fill recorded"
+            );
+            telegram::notify(format!(
+                "Paper {}: {} tokens of {} for {} lamports (fee {})",
+                side.to_lowercase(),
+                fill.token_amount,
+                fill.mint,
+                fill.sol_amount,
+                fill.fee_lamports
+            ));
+            return Ok(Some(fill));
+        }
+        ExecutionMode::Live => {
+            // Preflight would repeat the simulation, or spend the time skipping it saved
+            let mut send_config = SendConfig {
+                skip_preflight: true,
+                strategy: config::get()
+                    .trading
+                    .send_strategy_for(fill.side == Side::Buy),
+                ..Default::default()
+            };
+            // A transaction signed elsewhere can't have a tip added to it
+            let tipless = match &payload {
+                Payload::Instructions(_) => false,
+                Payload::Signed { transaction, .. } => !jito::has_tip(transaction),
+                Payload::Raced { transactions, .. } => !transactions.iter().all(jito::has_tip),
+            };
+            if send_config.strategy == SendStrategy::Protected && tipless {
+                warn!("Signed transaction pays no Jito tip, sending it unprotected");
+                send_config.strategy = SendStrategy::Single;
+            }
+            let mut result = match payload {
+                Payload::Instructions(instructions) => {
+                    let instructions = simulate_for_send(rpc, instructions, payer)?;
+                    send_and_confirm_with_retries(rpc, &instructions, payer, &send_config)?
+                }
+                Payload::Signed {
+                    transaction,
+                    last_valid_block_height,
+                    simulate,
+                } => {
+                    if simulate {
+                        simulate_transaction(rpc, transaction)?;
+                    }
+                    send_and_confirm_signed(
+                        rpc,
+                        transaction,
+                        last_valid_block_height,
+                        &send_config,
+                    )?
+                }
+                Payload::Raced {
+                    transactions,
+                    last_valid_block_height,
+                } => send_and_confirm_raced(
+                    rpc,
+                    transactions,
+                    last_valid_block_height,
+                    &send_config,
+                )?,
+            };
+            info!(
+                signature = %result.signature,
+                status = ?result.status,
+                attempts = result.attempts,
+                elapsed = ?result.elapsed,
+                fee = result.cost.total_fee(),
+                priority_fee = result.cost.priority_fee,
+                tip = result.cost.tip_lamports,
+                "Transaction finished"
+            );
+
+            // Fees burned by failed attempts are tracked separately and only landed trades become fills
+            fill.signature = result.signature.to_string();
+            if result.is_confirmed() {
+                let realized = result
+                    .transaction
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("Transaction could not be fetched"))
+                    .and_then(|landed| fills::realized_fill(landed, &payer.pubkey(), &fill));
+                match realized {
+                    Ok(realized) => {
+                        info!(
+                            quoted_price = fill.price(),
+                            realized_price = realized.price(),
+                            token_amount = realized.token_amount,
+                            sol_amount = realized.sol_amount,
+                            fee = realized.fee_lamports,
+                            "Realized fill"
+                        );
+                        fill = realized;
+                    }
+                    Err(e) => {
+                        warn!(error = %e, "Failed to read realized fill, recording the quote")
+                    }
+                }
+                result.fill = Some(fill.clone());
+            }
+            let recorded = Portfolio::open_default().and_then(|p| {
+                p.record_tx_cost(&fill.mint, fill.side, &result.cost)?;
+                if result.is_confirmed() {
+                    p.record_fill_with_price(&fill, price_feed::cached_at(fill.timestamp))?;
+                }
+                Ok(())
+            });
+            if let Err(e) = recorded {
+                warn!(
+                    signature = %fill.signature,
+                    error = %e,
+                    "Failed to record trade in portfolio"
+                );
+            }
+
+            if let Some(e) = BotError::from_trade_status(&fill.mint, &result.status) {
+                telegram::notify(format!("{} of {} failed: {}", side, fill.mint, e));
+                return Err(e);
+            }
+            info!(
+                signature = %result.signature,
+                "{} successful, view on Solscan: https://solscan.io/tx/{}",
+                side,
+                result.signature
+            );
+            telegram::notify(format!(
+                "{}: {} tokens of {} for {} lamports\nhttps://solscan.io/tx/{}",
+                side, fill.token_amount, fill.mint, fill.sol_amount, result.signature
+            ));
+            return Ok(result.fill);
+        }
+    }
+    Ok(None)
+}
+
+/// Tokens of `mint` held in the paper portfolio
+pub fn paper_balance(mint: &Pubkey) -> error::Result<u64> {
+    Ok(Portfolio::open_default()?
+        .get_position(mint)?
+        .map_or(0, |p| p.token_amount))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_already_processed_detection() {
+        assert!(is_already_processed(
+            "Transaction simulation failed: This transaction has already been processed"
+        ));
+        assert!(is_already_processed("TransactionError::AlreadyProcessed"));
+        assert!(!is_already_processed("custom program error: 0x1772"));
+    }
+
+    #[test]
+    fn test_spam_targets_and_stop_flag() {
+        let pool = RpcPool::new(&[
+            "http://127.0.0.1:1".to_string(),
+            "http://127.0.0.1:2".to_string(),
+            "http://127.0.0.1:3".to_string(),
+        ])
+        .unwrap();
+        // Every pool endpoint by default, without Jito
+        let mut trading = TradingConfig::default();
+        let targets = SpamTarget::new(&pool, &trading);
+        assert_eq!(targets.len(), 3);
+        assert!(targets.iter().all(|t| matches!(t, SpamTarget::Rpc(_))));
+
+        trading.spam_endpoints = 2;
+        trading.spam_jito = true;
+        let targets = SpamTarget::new(&pool, &trading);
+        assert_eq!(targets.len(), 3);
+        assert!(matches!(targets[2], SpamTarget::Jito));
+
+        let done = Arc::new(AtomicBool::new(false));
+        drop(StopOnDrop(done.clone()));
+        assert!(done.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn test_with_compute_unit_price_steps_up_the_fee() {
+        let transfer = solana_sdk::system_instruction::transfer(
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            1,
+        );
+        let instructions = vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(200_000),
+            transfer.clone(),
+        ];
+        assert_eq!(compute_unit_price(&instructions), 0);
+
+        let priced = with_compute_unit_price(&instructions, 10_000);
+        assert_eq!(compute_unit_price(&priced), 10_000);
+        let escalated = with_compute_unit_price(&priced, 100_000);
+        assert_eq!(escalated.len(), 3);
+        assert_eq!(compute_unit_price(&escalated), 100_000);
+        assert_eq!(escalated[2], transfer);
+    }
+
+    #[test]
+    fn test_with_compute_unit_limit_replaces_existing_limit() {
+        let transfer = solana_sdk::system_instruction::transfer(
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            1,
+        );
+        let instructions = vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(200_000),
+            ComputeBudgetInstruction::set_compute_unit_price(1_000),
+            transfer.clone(),
+        ];
+
+        let limited = with_compute_unit_limit(&instructions, 55_000);
+        assert_eq!(limited.len(), 3);
+        assert_eq!(
+            limited[0],
+            ComputeBudgetInstruction::set_compute_unit_limit(55_000)
+        );
+        assert_eq!(limited[1], instructions[1]);
+        assert_eq!(limited[2], transfer);
+
+        let added = with_compute_unit_limit(&instructions[2..], 55_000);
+        assert_eq!(added.len(), 2);
+    }
+}
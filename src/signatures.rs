@@ -0,0 +1,60 @@
+use anyhow::Result;
+use solana_client::{
+    rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient},
+    rpc_response::RpcConfirmedTransactionStatusWithSignature,
+};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use std::str::FromStr;
+
+// Constants
+/// Most signatures one getSignaturesForAddress call returns
+const SIGNATURE_PAGE: usize = 1_000;
+
+/// Up to `limit` of `address`'s most recent transaction signatures, failed ones included,
+/// newest first
+pub fn recent(
+    rpc: &RpcClient,
+    address: &Pubkey,
+    limit: usize,
+) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+    recent_until(rpc, address, limit, |_| false)
+}
+
+/// `recent`, paging back no further than the first signature `stop` holds for, which is
+/// left out
+pub fn recent_until(
+    rpc: &RpcClient,
+    address: &Pubkey,
+    limit: usize,
+    mut stop: impl FnMut(&RpcConfirmedTransactionStatusWithSignature) -> bool,
+) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+    let mut signatures = Vec::new();
+    let mut before = None;
+    while signatures.len() < limit {
+        let wanted = (limit - signatures.len()).min(SIGNATURE_PAGE);
+        let page = rpc.get_signatures_for_address_with_config(
+            address,
+            GetConfirmedSignaturesForAddress2Config {
+                before,
+                until: None,
+                limit: Some(wanted),
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        )?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        before = Some(Signature::from_str(&last.signature)?);
+        let exhausted = page.len() < wanted;
+        for status in page {
+            if stop(&status) {
+                return Ok(signatures);
+            }
+            signatures.push(status);
+        }
+        if exhausted {
+            break;
+        }
+    }
+    Ok(signatures)
+}
@@ -0,0 +1,208 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde_json::{json, Value};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::{Signer, SignerError},
+};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::config::{self, SignerBackend};
+use crate::pump_buy;
+
+/// Signs through an external HTTP signing service (a vault or KMS shim) holding the
+/// secret key of `pubkey`. Each message is POSTed to `<url>/sign` as
+/// `{"pubkey": <base58>, "message": <base64>}` and the service answers
+/// `{"signature": <base58>}`, which is checked against `pubkey` before it is used.
+pub struct RemoteSigner {
+    pubkey: Pubkey,
+    url: String,
+    auth_token: String,
+    client: reqwest::blocking::Client,
+}
+
+impl fmt::Debug for RemoteSigner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RemoteSigner")
+            .field("pubkey", &self.pubkey)
+            .field("url", &self.url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RemoteSigner {
+    pub fn new(pubkey: Pubkey, url: &str, auth_token: &str, timeout: Duration) -> Result<Self> {
+        Ok(Self {
+            pubkey,
+            url: format!("{}/sign", url.trim_end_matches('/')),
+            auth_token: auth_token.to_string(),
+            client: reqwest::blocking::Client::builder()
+                .timeout(timeout)
+                .build()?,
+        })
+    }
+
+    /// Signer for `pubkey` at the configured `signer.url`
+    pub fn from_config(pubkey: Pubkey) -> Result<Self> {
+        let config = &config::get().signer;
+        Self::new(
+            pubkey,
+            &config.url,
+            &config.auth_token,
+            Duration::from_millis(config.timeout_ms),
+        )
+    }
+
+    fn request(&self, message: &[u8]) -> Result<Value, SignerError> {
+        let mut request = self.client.post(&self.url).json(&json!({
+            "pubkey": self.pubkey.to_string(),
+            "message": BASE64.encode(message),
+        }));
+        if !self.auth_token.is_empty() {
+            request = request.bearer_auth(&self.auth_token);
+        }
+        request
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.json())
+            .map_err(|e| SignerError::Connection(format!("{}: {}", self.url, e)))
+    }
+}
+
+/// The signature in a signing service response, if it is `pubkey`'s over `message`
+fn parse_signature(
+    response: &Value,
+    pubkey: &Pubkey,
+    message: &[u8],
+) -> Result<Signature, SignerError> {
+    let signature = response["signature"]
+        .as_str()
+        .ok_or_else(|| SignerError::Protocol("No signature in signer response".to_string()))
+        .and_then(|s| {
+            Signature::from_str(s).map_err(|e| SignerError::Protocol(format!("{}: {}", s, e)))
+        })?;
+    if !signature.verify(pubkey.as_ref(), message) {
+        return Err(SignerError::KeypairPubkeyMismatch);
+    }
+    Ok(signature)
+}
+
+impl Signer for RemoteSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.pubkey)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        parse_signature(&self.request(message)?, &self.pubkey, message)
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+/// A trading wallet: a keypair in this process, or a key held by the signing service
+#[derive(Debug)]
+pub enum Wallet {
+    Local(Keypair),
+    Remote(RemoteSigner),
+}
+
+impl From<Keypair> for Wallet {
+    fn from(keypair: Keypair) -> Self {
+        Wallet::Local(keypair)
+    }
+}
+
+impl Signer for Wallet {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        match self {
+            Wallet::Local(keypair) => keypair.try_pubkey(),
+            Wallet::Remote(signer) => signer.try_pubkey(),
+        }
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        match self {
+            Wallet::Local(keypair) => keypair.try_sign_message(message),
+            Wallet::Remote(signer) => signer.try_sign_message(message),
+        }
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+/// The wallet for a configured key: a base58 secret key with the `local` signer backend,
+/// the base58 public key the service signs for with `remote`
+pub fn load_wallet(key: &str) -> Result<Wallet> {
+    match config::get().signer.backend {
+        SignerBackend::Local => pump_buy::load_wallet_from_private_key(key).map(Wallet::Local),
+        SignerBackend::Remote => {
+            let pubkey = Pubkey::from_str(key)
+                .map_err(|e| anyhow!("Remote signer key {:?} is not a pubkey: {}", key, e))?;
+            RemoteSigner::from_config(pubkey).map(Wallet::Remote)
+        }
+    }
+}
+
+/// The wallet for `wallet.private_key`
+pub fn load_default_wallet() -> Result<Wallet> {
+    load_wallet(&config::get().wallet.private_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_signatures_are_verified() {
+        let keypair = Keypair::new();
+        let message = b"message";
+        let signature = keypair.sign_message(message);
+
+        let response = json!({ "signature": signature.to_string() });
+        assert_eq!(
+            parse_signature(&response, &keypair.pubkey(), message),
+            Ok(signature)
+        );
+        // Another key's signature, or one over another message, is rejected
+        assert_eq!(
+            parse_signature(&response, &Pubkey::new_unique(), message),
+            Err(SignerError::KeypairPubkeyMismatch)
+        );
+        assert_eq!(
+            parse_signature(&response, &keypair.pubkey(), b"other"),
+            Err(SignerError::KeypairPubkeyMismatch)
+        );
+        assert!(matches!(
+            parse_signature(&json!({}), &keypair.pubkey(), message),
+            Err(SignerError::Protocol(_))
+        ));
+
+        let wallet = Wallet::from(keypair.insecure_clone());
+        assert_eq!(wallet.pubkey(), keypair.pubkey());
+        assert_eq!(wallet.sign_message(message), signature);
+        // A port just released, so nothing listens on it
+        let closed = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let remote = RemoteSigner::new(
+            keypair.pubkey(),
+            &format!("http://{}/", closed),
+            "",
+            Duration::from_millis(100),
+        )
+        .unwrap();
+        assert_eq!(remote.url, format!("http://{}/sign", closed));
+        assert!(matches!(
+            remote.try_sign_message(message),
+            Err(SignerError::Connection(_))
+        ));
+    }
+}
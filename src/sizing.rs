@@ -0,0 +1,508 @@
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
+use tracing::{info, warn};
+
+use crate::accounts::BondingCurve;
+use crate::config::{self, SizingConfig, SizingMode};
+use crate::error::{self, BotError};
+use crate::events::{self, PumpEvent};
+use crate::portfolio::Portfolio;
+use crate::wallets::WalletPool;
+use crate::{cal, fees, signatures};
+
+// Constants
+/// Bonding curve transactions sampled for the volatility estimate
+const VOLATILITY_SAMPLES: usize = 50;
+
+/// Guard shared by the executor and the sizing engine, using the `[sizing]` limits
+static GUARD: Lazy<Arc<ExposureGuard>> = Lazy::new(|| Arc::new(ExposureGuard::from_config()));
+
+/// Lamports tied up per mint
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Exposure {
+    pub per_mint: HashMap<Pubkey, u64>,
+}
+
+impl Exposure {
+    pub fn mint(&self, mint: &Pubkey) -> u64 {
+        self.per_mint.get(mint).copied().unwrap_or(0)
+    }
+
+    pub fn total(&self) -> u64 {
+        self.per_mint.values().sum()
+    }
+
+    /// Mints with an open position or a queued buy
+    pub fn positions(&self) -> usize {
+        self.per_mint.values().filter(|&&lamports| lamports > 0).count()
+    }
+}
+
+/// Per-mint and global exposure caps in lamports, and a cap on how many mints may be
+/// held at once (0 = no limit)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExposureLimits {
+    pub per_mint: u64,
+    pub total: u64,
+    pub positions: usize,
+}
+
+impl ExposureLimits {
+    pub fn from_config(config: &SizingConfig) -> Self {
+        Self {
+            per_mint: config.max_mint_exposure_lamports,
+            total: config.max_total_exposure_lamports,
+            positions: config.max_open_positions,
+        }
+    }
+
+    pub fn unlimited(&self) -> bool {
+        self.per_mint == 0 && self.total == 0 && self.positions == 0
+    }
+
+    /// Whether a buy on `mint` would open a position past the `positions` cap
+    fn opens_too_many(&self, exposure: &Exposure, mint: &Pubkey) -> bool {
+        self.positions > 0 && exposure.mint(mint) == 0 && exposure.positions() >= self.positions
+    }
+
+    /// Most lamports a new buy on `mint` may add
+    pub fn headroom(&self, exposure: &Exposure, mint: &Pubkey) -> u64 {
+        let room = |limit: u64, current: u64| match limit {
+            0 => u64::MAX,
+            limit => limit.saturating_sub(current),
+        };
+        if self.opens_too_many(exposure, mint) {
+            return 0;
+        }
+        room(self.per_mint, exposure.mint(mint)).min(room(self.total, exposure.total()))
+    }
+
+    /// Refuse a buy of `lamports` on `mint` that would go past any limit
+    pub fn check(&self, exposure: &Exposure, mint: &Pubkey, lamports: u64) -> error::Result<()> {
+        if self.opens_too_many(exposure, mint) {
+            return Err(BotError::PositionLimit {
+                mint: *mint,
+                open: exposure.positions(),
+                limit: self.positions,
+            });
+        }
+        for (scope, limit, current) in [
+            ("per-mint", self.per_mint, exposure.mint(mint)),
+            ("total", self.total, exposure.total()),
+        ] {
+            if limit > 0 && current + lamports > limit {
+                return Err(BotError::ExposureLimit {
+                    mint: *mint,
+                    scope,
+                    requested: lamports,
+                    current,
+                    limit,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+type OpenExposure = Arc<dyn Fn() -> Result<Exposure> + Send + Sync>;
+
+/// Cost basis of the open positions in the portfolio for the current `trading.mode`
+pub fn open_exposure() -> Result<Exposure> {
+    let per_mint = Portfolio::open_default()?
+        .get_open_positions()?
+        .into_iter()
+        .map(|position| (position.mint, position.cost_basis_lamports))
+        .collect();
+    Ok(Exposure { per_mint })
+}
+
+/// Enforces the exposure limits on buys the executor accepts. Exposure is the open
+/// positions plus buys accepted but not finished yet.
+pub struct ExposureGuard {
+    limits: ExposureLimits,
+    open: OpenExposure,
+    pending: Mutex<HashMap<Pubkey, u64>>,
+}
+
+impl ExposureGuard {
+    pub fn new(limits: ExposureLimits, open: OpenExposure) -> Self {
+        Self {
+            limits,
+            open,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_config() -> Self {
+        Self::new(
+            ExposureLimits::from_config(&config::get().sizing),
+            Arc::new(open_exposure),
+        )
+    }
+
+    pub fn limits(&self) -> ExposureLimits {
+        self.limits
+    }
+
+    /// Lamports in buys accepted but not finished
+    pub fn pending_total(&self) -> u64 {
+        self.lock_pending().values().sum()
+    }
+
+    fn lock_pending(&self) -> std::sync::MutexGuard<'_, HashMap<Pubkey, u64>> {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn with_pending(&self, pending: &HashMap<Pubkey, u64>) -> Result<Exposure> {
+        let mut exposure = (self.open)()?;
+        for (mint, lamports) in pending {
+            *exposure.per_mint.entry(*mint).or_insert(0) += lamports;
+        }
+        Ok(exposure)
+    }
+
+    /// Open positions plus pending buys
+    pub fn exposure(&self) -> Result<Exposure> {
+        self.with_pending(&self.lock_pending())
+    }
+
+    /// Count a buy of `lamports` on `mint` as pending, or refuse it if it would go past a
+    /// limit. Every successful reserve must be followed by a `release`.
+    pub fn reserve(&self, mint: &Pubkey, lamports: u64) -> error::Result<()> {
+        // Held across the check so concurrent buys can't both squeeze under a limit
+        let mut pending = self.lock_pending();
+        if !self.limits.unlimited() {
+            let exposure = self.with_pending(&pending)?;
+            self.limits.check(&exposure, mint, lamports)?;
+        }
+        *pending.entry(*mint).or_insert(0) += lamports;
+        Ok(())
+    }
+
+    /// The buy reserved with `reserve` has finished, landed or not
+    pub fn release(&self, mint: &Pubkey, lamports: u64) {
+        let mut pending = self.lock_pending();
+        if let Some(reserved) = pending.get_mut(mint) {
+            *reserved = reserved.saturating_sub(lamports);
+            if *reserved == 0 {
+                pending.remove(mint);
+            }
+        }
+    }
+}
+
+/// The exposure guard shared by the executor
+pub fn guard() -> Arc<ExposureGuard> {
+    GUARD.clone()
+}
+
+/// Standard deviation of the moves between consecutive prices, in percent.
+/// None with fewer than three prices.
+pub fn volatility_pct(prices: &[f64]) -> Option<f64> {
+    let moves: Vec<f64> = prices
+        .windows(2)
+        .filter(|w| w[0] > 0.0 && w[1] > 0.0)
+        .map(|w| (w[1] / w[0]).ln())
+        .collect();
+    if moves.len() < 2 {
+        return None;
+    }
+    let mean = moves.iter().sum::<f64>() / moves.len() as f64;
+    let variance = moves.iter().map(|m| (m - mean).powi(2)).sum::<f64>() / (moves.len() - 1) as f64;
+    Some(variance.sqrt() * 100.0)
+}
+
+/// Volatility of `mint`'s bonding curve over its last trades, oldest to newest
+pub fn recent_volatility_pct(rpc: &RpcClient, mint: &Pubkey) -> Result<Option<f64>> {
+    let (bonding_curve, _) = cal::get_bonding_curve_pda(mint);
+    let history = signatures::recent(rpc, &bonding_curve, VOLATILITY_SAMPLES)?;
+
+    let mut prices = Vec::new();
+    // Newest first, so walk backwards
+    for status in history.iter().rev().filter(|s| s.err.is_none()) {
+        let signature = Signature::from_str(&status.signature)?;
+        let events = match events::fetch_transaction_events(rpc, &signature) {
+            Ok(events) => events,
+            Err(e) => {
+                warn!(%signature, error = %e, "Skipping curve transaction");
+                continue;
+            }
+        };
+        prices.extend(events.into_iter().filter_map(|event| match event {
+            PumpEvent::Trade(trade) if trade.mint == *mint && trade.virtual_token_reserves > 0 => {
+                Some(trade.virtual_sol_reserves as f64 / trade.virtual_token_reserves as f64)
+            }
+            _ => None,
+        }));
+    }
+    Ok(volatility_pct(&prices))
+}
+
+/// Refuse a trade on `mint` that moves the price `impact_bps` when that is past
+/// `trading.max_price_impact_bps` (0 = no limit). A `force`d trade is only warned about.
+pub fn check_price_impact(
+    mint: &Pubkey,
+    is_buy: bool,
+    impact_bps: u64,
+    force: bool,
+) -> error::Result<()> {
+    let limit_bps = config::get().trading.max_price_impact_bps;
+    check_impact_limit(mint, is_buy, impact_bps, limit_bps, force)
+}
+
+/// `check_price_impact` against `limit_bps` rather than the configured limit
+pub fn check_impact_limit(
+    mint: &Pubkey,
+    is_buy: bool,
+    impact_bps: u64,
+    limit_bps: u64,
+    force: bool,
+) -> error::Result<()> {
+    if limit_bps == 0 || impact_bps <= limit_bps {
+        return Ok(());
+    }
+    if force {
+        warn!(%mint, impact_bps, limit_bps, "Price impact past the limit, trading anyway");
+        return Ok(());
+    }
+    Err(BotError::PriceImpactTooHigh {
+        mint: *mint,
+        side: if is_buy { "Buy" } else { "Sell" },
+        impact_bps,
+        limit_bps,
+    })
+}
+
+/// Refuse growing a position to `position_tokens` on `bonding_curve`, the curve as the buy
+/// leaves it, when selling all of it at once would move the price past `limit_bps`
+pub fn check_exit_impact(
+    global: &cal::Global,
+    bonding_curve: &BondingCurve,
+    mint: &Pubkey,
+    position_tokens: u64,
+    limit_bps: u64,
+) -> error::Result<()> {
+    if limit_bps == 0 {
+        return Ok(());
+    }
+    let exit = cal::exit_liquidity(global, bonding_curve, position_tokens, limit_bps);
+    if exit.fully_sellable() {
+        return Ok(());
+    }
+    Err(BotError::ExitLiquidity {
+        mint: *mint,
+        impact_bps: exit.liquidation_impact_bps,
+        limit_bps,
+    })
+}
+
+/// `check_exit_impact` for a buy of `token_amount` more tokens of `mint` under
+/// `sizing.max_exit_impact_bps`. Buys on a completed curve aren't checked.
+pub fn check_exit_liquidity(
+    rpc: &RpcClient,
+    mint: &Pubkey,
+    token_amount: u64,
+) -> error::Result<()> {
+    let limit_bps = config::get().sizing.max_exit_impact_bps;
+    if limit_bps == 0 {
+        return Ok(());
+    }
+    let curve = cal::fetch_bonding_curve(rpc, mint)?;
+    if curve.complete {
+        return Ok(());
+    }
+    let global = fees::current_global(rpc, &curve);
+    let held = Portfolio::open_default()?
+        .get_position(mint)?
+        .map_or(0, |position| position.token_amount);
+    let cost = cal::get_sol_for_tokens(&global, Some(&curve), token_amount);
+    let after = cal::curve_after_buy(&global, &curve, cost);
+    check_exit_impact(&global, &after, mint, held + token_amount, limit_bps)
+}
+
+/// What a buy size is decided from
+#[derive(Debug, Clone, Default)]
+pub struct SizingInput {
+    /// SOL not set aside for fees or already committed to queued buys
+    pub free_balance: u64,
+    /// Recent volatility of the mint in percent, when known
+    pub volatility_pct: Option<f64>,
+    /// Lamports the exposure limits still allow on the mint
+    pub headroom: u64,
+    /// What the `supply_pct` or `target_market_cap` buy costs on the mint's curve
+    pub target_cost: Option<u64>,
+}
+
+/// Buy size in lamports for `input` under `config`. `base` is the fixed buy size.
+pub fn size(config: &SizingConfig, base: u64, input: &SizingInput) -> u64 {
+    let lamports = match config.mode {
+        SizingMode::Fixed => base,
+        SizingMode::PercentOfBalance => {
+            (input.free_balance as f64 * config.balance_pct / 100.0) as u64
+        }
+        SizingMode::Volatility => match input.volatility_pct {
+            Some(volatility) if volatility > config.target_volatility_pct => {
+                (base as f64 * config.target_volatility_pct / volatility) as u64
+            }
+            _ => base,
+        },
+        SizingMode::ExposureCapped => base.min(input.headroom),
+        SizingMode::SupplyPct | SizingMode::TargetMarketCap => input.target_cost.unwrap_or(0),
+    };
+    lamports.min(input.free_balance)
+}
+
+/// Lamports a strategy should spend buying `mint`, using `[sizing]`. 0 means skip the buy.
+/// The exposure limits are enforced again when the executor accepts the trade.
+pub fn buy_amount(rpc: &RpcClient, mint: &Pubkey) -> error::Result<u64> {
+    let config = &config::get().sizing;
+    let guard = guard();
+
+    let balance: u64 = WalletPool::from_config()?
+        .refresh_balances(rpc)?
+        .iter()
+        .map(|b| b.lamports)
+        .sum();
+    let reserve = config::get().balance.reserve_lamports;
+    let free_balance = balance.saturating_sub(reserve + guard.pending_total());
+    let volatility_pct = match config.mode {
+        SizingMode::Volatility => recent_volatility_pct(rpc, mint)
+            .map_err(|e| warn!(%mint, error = %e, "Failed to measure volatility"))
+            .ok()
+            .flatten(),
+        _ => None,
+    };
+    let headroom = match config.mode {
+        SizingMode::ExposureCapped => guard.limits().headroom(&guard.exposure()?, mint),
+        _ => u64::MAX,
+    };
+    let target_cost = match config.mode {
+        SizingMode::SupplyPct | SizingMode::TargetMarketCap => {
+            let curve = cal::fetch_bonding_curve(rpc, mint)?;
+            let global = fees::current_global(rpc, &curve);
+            Some(match config.mode {
+                SizingMode::SupplyPct => {
+                    cal::sol_for_supply_pct(&global, &curve, config.supply_pct)
+                }
+                _ => cal::sol_to_market_cap(&global, &curve, config.target_market_cap_sol),
+            })
+        }
+        _ => None,
+    };
+
+    let input = SizingInput {
+        free_balance,
+        volatility_pct,
+        headroom,
+        target_cost,
+    };
+    let lamports = size(config, config::get().trading.buy_amount_lamports, &input);
+    info!(
+        %mint,
+        mode = ?config.mode,
+        lamports,
+        free_balance,
+        volatility_pct = ?input.volatility_pct,
+        "Sized buy"
+    );
+    Ok(lamports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sizing_modes_and_exposure_limits() {
+        let mut config = SizingConfig::default();
+        let input = SizingInput {
+            free_balance: 2_000_000_000,
+            volatility_pct: Some(40.0),
+            headroom: 30_000_000,
+            target_cost: Some(3_000_000_000),
+        };
+        assert_eq!(size(&config, 100_000_000, &input), 100_000_000);
+        config.mode = SizingMode::PercentOfBalance;
+        assert_eq!(size(&config, 100_000_000, &input), 100_000_000);
+        config.mode = SizingMode::Volatility;
+        assert_eq!(size(&config, 100_000_000, &input), 25_000_000);
+        config.mode = SizingMode::ExposureCapped;
+        assert_eq!(size(&config, 100_000_000, &input), 30_000_000);
+        // Capped at the free balance
+        config.mode = SizingMode::SupplyPct;
+        assert_eq!(size(&config, 100_000_000, &input), 2_000_000_000);
+
+        assert_eq!(volatility_pct(&[1.0, 1.0]), None);
+        assert!(volatility_pct(&[1.0, 1.1, 1.0, 1.1]).unwrap() > 5.0);
+
+        let held = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let limits = ExposureLimits {
+            per_mint: 500,
+            total: 800,
+            positions: 0,
+        };
+        let guard = ExposureGuard::new(
+            limits,
+            Arc::new(move || {
+                Ok(Exposure {
+                    per_mint: HashMap::from([(held, 400)]),
+                })
+            }),
+        );
+        assert!(matches!(
+            guard.reserve(&held, 200),
+            Err(BotError::ExposureLimit {
+                scope: "per-mint",
+                ..
+            })
+        ));
+        guard.reserve(&held, 100).unwrap();
+        guard.reserve(&other, 300).unwrap();
+        assert!(matches!(
+            guard.reserve(&other, 1),
+            Err(BotError::ExposureLimit { scope: "total", .. })
+        ));
+        assert_eq!(limits.headroom(&guard.exposure().unwrap(), &other), 0);
+        guard.release(&other, 300);
+        assert_eq!(guard.pending_total(), 100);
+        assert_eq!(limits.headroom(&guard.exposure().unwrap(), &other), 300);
+
+        // With two mints held, only they can be added to
+        let limits = ExposureLimits {
+            positions: 2,
+            ..Default::default()
+        };
+        let exposure = Exposure {
+            per_mint: HashMap::from([(held, 400), (other, 100)]),
+        };
+        let third = Pubkey::new_unique();
+        assert!(limits.check(&exposure, &held, 1_000).is_ok());
+        assert!(matches!(
+            limits.check(&exposure, &third, 1),
+            Err(BotError::PositionLimit { open: 2, .. })
+        ));
+        assert_eq!(limits.headroom(&exposure, &third), 0);
+    }
+
+    #[test]
+    fn test_exit_impact_limit() {
+        let global = cal::Global::default();
+        let fresh = cal::new_bonding_curve(&global);
+        let mint = Pubkey::new_unique();
+        let tokens = cal::get_tokens_for_sol(&global, Some(&fresh), 1_000_000_000);
+        let after = cal::curve_after_buy(&global, &fresh, 1_000_000_000);
+
+        assert!(check_exit_impact(&global, &after, &mint, tokens, 0).is_ok());
+        assert!(check_exit_impact(&global, &after, &mint, tokens, 1_000).is_ok());
+        assert!(matches!(
+            check_exit_impact(&global, &after, &mint, tokens, 100),
+            Err(BotError::ExitLiquidity { impact_bps, limit_bps: 100, .. }) if impact_bps > 100
+        ));
+    }
+}
@@ -0,0 +1,630 @@
+use anyhow::{anyhow, Result};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig, hash::Hash, instruction::Instruction, pubkey::Pubkey,
+    signer::Signer, transaction::VersionedTransaction,
+};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account_idempotent,
+};
+use spl_token::ID as TOKEN_PROGRAM_ID;
+use spl_token_2022::ID as TOKEN_2022_PROGRAM_ID;
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::sync::{mpsc, Arc, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{info, instrument, warn};
+
+use crate::accounts::{BondingCurve, FeeConfig};
+use crate::blacklist::{self, Token, TokenList};
+use crate::config::MAX_RACE_TIERS;
+use crate::creators::{CreatorList, ListStatus};
+use crate::events::{self, CreateEvent, PumpEvent};
+use crate::executor::TradeIntent;
+use crate::nonce::{self, DurableNonce};
+use crate::portfolio::{Fill, Side};
+use crate::pump_idl::{get_creator_vault_pda, PUMP_PROGRAM_ID};
+use crate::signer::Wallet;
+use crate::strategy::{Context, Strategy};
+use crate::{alt, cal, config, fees, jito, launch_analysis, pump_buy, rpc_pool, sender, sizing};
+
+// Constants
+/// Token account and mint positions in an idempotent ATA creation
+const ATA_ACCOUNT: usize = 1;
+const ATA_MINT: usize = 3;
+/// Positions of the per-launch accounts in a pump.fun buy
+const BUY_FEE_RECIPIENT: usize = 1;
+const BUY_MINT: usize = 2;
+const BUY_BONDING_CURVE: usize = 3;
+const BUY_ASSOCIATED_BONDING_CURVE: usize = 4;
+const BUY_ASSOCIATED_USER: usize = 5;
+const BUY_CREATOR_VAULT: usize = 9;
+/// Bytes of the `amount` and `max_sol_cost` arguments in buy instruction data
+const BUY_AMOUNT: Range<usize> = 8..16;
+const BUY_MAX_SOL_COST: Range<usize> = 16..24;
+
+/// Compute budget, ATA creation and buy instructions built once for one token program,
+/// with placeholder accounts that `patch` swaps for a launch's own
+#[derive(Debug, Clone)]
+pub struct BuyTemplate {
+    user: Pubkey,
+    token_program: Pubkey,
+    instructions: Vec<Instruction>,
+    ata_index: usize,
+    buy_index: usize,
+}
+
+impl BuyTemplate {
+    pub fn new(user: &Pubkey, token_program: &Pubkey) -> Self {
+        let placeholder = Pubkey::default();
+        let mut instructions = config::get().fees.compute_budget_instructions();
+        let ata_index = instructions.len();
+        instructions.push(create_associated_token_account_idempotent(
+            user,
+            user,
+            &placeholder,
+            token_program,
+        ));
+        instructions.push(pump_buy::buy_instruction(
+            user,
+            &placeholder,
+            &placeholder,
+            token_program,
+            0,
+            0,
+        ));
+        Self {
+            user: *user,
+            token_program: *token_program,
+            instructions,
+            ata_index,
+            buy_index: ata_index + 1,
+        }
+    }
+
+    /// Instructions buying `token_amount` tokens of `mint` for at most `max_sol_cost`.
+    /// Only the per-launch accounts, the amounts and the rotated fee recipient differ from
+    /// the template.
+    pub fn patch(
+        &self,
+        mint: &Pubkey,
+        bonding_curve: &Pubkey,
+        creator: &Pubkey,
+        token_amount: u64,
+        max_sol_cost: u64,
+    ) -> Vec<Instruction> {
+        let associated_user =
+            get_associated_token_address_with_program_id(&self.user, mint, &self.token_program);
+        let (creator_vault, _) = get_creator_vault_pda(creator);
+
+        let mut instructions = self.instructions.clone();
+        let ata = &mut instructions[self.ata_index];
+        ata.accounts[ATA_ACCOUNT].pubkey = associated_user;
+        ata.accounts[ATA_MINT].pubkey = *mint;
+
+        let buy = &mut instructions[self.buy_index];
+        buy.accounts[BUY_FEE_RECIPIENT].pubkey = fees::next_fee_recipient();
+        buy.accounts[BUY_MINT].pubkey = *mint;
+        buy.accounts[BUY_BONDING_CURVE].pubkey = *bonding_curve;
+        buy.accounts[BUY_ASSOCIATED_BONDING_CURVE].pubkey =
+            get_associated_token_address_with_program_id(bonding_curve, mint, &self.token_program);
+        buy.accounts[BUY_ASSOCIATED_USER].pubkey = associated_user;
+        buy.accounts[BUY_CREATOR_VAULT].pubkey = creator_vault;
+        buy.data[BUY_AMOUNT].copy_from_slice(&token_amount.to_le_bytes());
+        buy.data[BUY_MAX_SOL_COST].copy_from_slice(&max_sol_cost.to_le_bytes());
+        instructions
+    }
+}
+
+/// The curve a launch opens with, read from its Create event
+pub(crate) fn launch_curve(event: &CreateEvent) -> BondingCurve {
+    BondingCurve {
+        virtual_token_reserves: event.virtual_token_reserves,
+        virtual_sol_reserves: event.virtual_sol_reserves,
+        real_token_reserves: event.real_token_reserves,
+        real_sol_reserves: 0,
+        token_total_supply: event.token_total_supply,
+        complete: false,
+        creator: event.creator,
+    }
+}
+
+/// `snipe.buy_amount_lamports`, or `trading.buy_amount_lamports` when unset
+fn buy_lamports() -> u64 {
+    let config = config::get();
+    match config.snipe.buy_amount_lamports {
+        0 => config.trading.buy_amount_lamports,
+        lamports => lamports,
+    }
+}
+
+/// Fee config fetched once up front; None charges the default fees
+pub(crate) fn fetch_fee_config(rpc: &RpcClient) -> Option<FeeConfig> {
+    fees::fetch_fee_config(rpc)
+        .map_err(|e| warn!(error = %e, "Failed to fetch fee config, sniping with default fees"))
+        .ok()
+}
+
+/// Global with the fees `fee_config` charges at `curve`'s market cap; None charges the
+/// default fees
+pub(crate) fn launch_global(fee_config: Option<&FeeConfig>, curve: &BondingCurve) -> cal::Global {
+    fee_config.map_or_else(cal::Global::default, |config| {
+        fees::global_with_fees(&fees::fees_for_market_cap(
+            config,
+            fees::market_cap_lamports(curve),
+        ))
+    })
+}
+
+/// Tokens `lamports` buys at the launch's opening reserves, and the fee charged
+pub(crate) fn quote_launch(fee_config: Option<&FeeConfig>, curve: &BondingCurve, lamports: u64) -> (u64, u64) {
+    let global = launch_global(fee_config, curve);
+    (
+        cal::get_tokens_for_sol(&global, Some(curve), lamports),
+        cal::get_fee(&global, curve, lamports, false),
+    )
+}
+
+/// Whether the launch's creator is deny-listed. Lookup failures let it through.
+fn denied(creators: &CreatorList, event: &CreateEvent) -> bool {
+    match creators.status(&event.creator) {
+        Ok(Some(ListStatus::Deny)) => {
+            info!(mint = %event.mint, creator = %event.creator, "Skipping deny-listed creator");
+            true
+        }
+        Ok(_) => false,
+        Err(e) => {
+            warn!(creator = %event.creator, error = %e, "Failed to check creator list");
+            false
+        }
+    }
+}
+
+/// Whether the black / allow lists (and `safety.allowlist_only`) rule the launch out,
+/// judged on the Create event's mint, creator and symbol so nothing is fetched. Launches
+/// the lists can't be read for are skipped.
+fn blacklisted(lists: &TokenList, event: &CreateEvent) -> bool {
+    let entries = match lists.entries() {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!(mint = %event.mint, error = %e, "Failed to read blacklist, skipping launch");
+            return true;
+        }
+    };
+    let token = Token {
+        mint: event.mint,
+        creator: Some(event.creator),
+        symbol: Some(&event.symbol),
+    };
+    let allowlist_only = config::get().safety.allowlist_only;
+    match blacklist::refusal(&entries, &token, allowlist_only) {
+        Some(reason) => {
+            info!(mint = %event.mint, %reason, "Skipping blacklisted launch");
+            true
+        }
+        None => false,
+    }
+}
+
+/// Whether `snipe.skip_bundled_launches` rules the launch out. The analysis runs on a
+/// thread of its own and gets `snipe.launch_analysis_timeout_ms`; launches that can't be
+/// analyzed in time are let through.
+fn bundled(event: &CreateEvent) -> bool {
+    let config = &config::get().snipe;
+    if !config.skip_bundled_launches {
+        return false;
+    }
+    let (tx, rx) = mpsc::channel();
+    let (mint, transactions) = (event.mint, config.launch_transactions);
+    std::thread::spawn(move || {
+        let analysis = launch_analysis::analyze(&rpc_pool::get_client(), &mint, transactions);
+        let _ = tx.send(analysis);
+    });
+    match rx.recv_timeout(Duration::from_millis(config.launch_analysis_timeout_ms)) {
+        Ok(Ok(analysis)) if analysis.bundled_launch => {
+            info!(mint = %event.mint, clusters = ?analysis.clusters, "Skipping bundled launch");
+            true
+        }
+        Ok(Ok(_)) => false,
+        Ok(Err(e)) => {
+            warn!(mint = %event.mint, error = %e, "Failed to analyze launch");
+            false
+        }
+        Err(_) => {
+            warn!(mint = %event.mint, "Launch analysis timed out, sniping unchecked");
+            false
+        }
+    }
+}
+
+/// A signed launch buy
+pub struct SnipeBuy {
+    /// The buy, or one variant of it per `snipe.race_tiers` tier
+    pub transactions: Vec<VersionedTransaction>,
+    pub last_valid_block_height: u64,
+    /// The quoted fill at the launch's opening reserves
+    pub fill: Fill,
+    pub max_sol_cost: u64,
+    /// From the Create event to the signed transaction
+    pub build_time: Duration,
+}
+
+/// What snipes are signed against, kept fresh in the background
+struct Recent {
+    /// Newest first, one per race tier so raced variants can each use their own
+    blockhashes: VecDeque<(Hash, u64)>,
+    /// Raced variants are signed against `trading.nonce_account` when it is set, so
+    /// only one of them can land
+    nonce: Option<DurableNonce>,
+}
+
+/// Signs launch buys from a `BuyTemplate` against a blockhash kept fresh in the
+/// background, so nothing is fetched between a Create event and the send
+pub struct HotPath {
+    user: Wallet,
+    template: BuyTemplate,
+    /// Fetched once up front; None charges the default fees
+    fee_config: Option<FeeConfig>,
+    buy_lamports: u64,
+    slippage_bps: u64,
+    race_tiers: Vec<(u64, u64)>,
+    recent: Arc<RwLock<Recent>>,
+}
+
+impl HotPath {
+    /// Build the template for `snipe.token_2022` launches and start refreshing the
+    /// blockhash (and the nonce raced snipes use) every `snipe.blockhash_refresh_ms`
+    pub fn new(rpc: &RpcClient, user: Wallet) -> Result<Self> {
+        let config = config::get();
+        let token_program = if config.snipe.token_2022 {
+            TOKEN_2022_PROGRAM_ID
+        } else {
+            TOKEN_PROGRAM_ID
+        };
+        let fee_config = fetch_fee_config(rpc);
+        fees::refresh_fee_recipients(rpc);
+        let blockhash = rpc
+            .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+            .map_err(|e| anyhow!("Failed to fetch blockhash: {}", e))?;
+        let race_tiers = config.snipe.race_tiers.clone();
+        let nonce = if race_tiers.is_empty() {
+            None
+        } else {
+            DurableNonce::configured(rpc)?
+        };
+        if let Some(nonce) = nonce.as_ref().filter(|n| n.authority != user.pubkey()) {
+            return Err(anyhow!(
+                "Nonce account {} is controlled by {}, not the sniping wallet",
+                nonce.account,
+                nonce.authority
+            ));
+        }
+        let recent = Arc::new(RwLock::new(Recent {
+            blockhashes: VecDeque::from([blockhash]),
+            nonce,
+        }));
+
+        let refreshed = Arc::downgrade(&recent);
+        let interval = Duration::from_millis(config.snipe.blockhash_refresh_ms);
+        std::thread::spawn(move || {
+            let rpc = rpc_pool::get_client();
+            loop {
+                std::thread::sleep(interval);
+                // Stops once the hot path is dropped
+                let Some(recent) = refreshed.upgrade() else {
+                    return;
+                };
+                let latest =
+                    rpc.get_latest_blockhash_with_commitment(CommitmentConfig::confirmed());
+                // A nonce only changes when a raced snipe using it lands
+                let nonce = recent
+                    .read()
+                    .ok()
+                    .and_then(|r| r.nonce.clone())
+                    .map(|nonce| DurableNonce::fetch(&rpc, &nonce.account));
+                let Ok(mut recent) = recent.write() else {
+                    return;
+                };
+                match latest {
+                    Ok(latest) if recent.blockhashes.front() != Some(&latest) => {
+                        recent.blockhashes.push_front(latest);
+                        recent.blockhashes.truncate(MAX_RACE_TIERS);
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!(error = %e, "Failed to refresh snipe blockhash"),
+                }
+                match nonce {
+                    Some(Ok(nonce)) => recent.nonce = Some(nonce),
+                    Some(Err(e)) => warn!(error = %e, "Failed to refresh snipe nonce"),
+                    None => {}
+                }
+            }
+        });
+
+        Ok(Self {
+            template: BuyTemplate::new(&user.pubkey(), &token_program),
+            user,
+            fee_config,
+            buy_lamports: buy_lamports(),
+            slippage_bps: config.snipe.slippage_bps,
+            race_tiers,
+            recent,
+        })
+    }
+
+    pub fn user(&self) -> &Wallet {
+        &self.user
+    }
+
+    /// Most a snipe may spend, slippage included
+    pub fn max_sol_cost(&self) -> u64 {
+        pump_buy::with_slippage(self.buy_lamports, self.slippage_bps)
+    }
+
+    /// Lamports a snipe counts against the exposure limits. Raced variants signed against
+    /// different blockhashes can all land, so each one counts; behind a nonce only one can.
+    pub fn reserved_lamports(&self) -> u64 {
+        let racing_blockhashes = self
+            .recent
+            .read()
+            .is_ok_and(|recent| recent.nonce.is_none());
+        match self.race_tiers.len() {
+            tiers if tiers > 1 && racing_blockhashes => self.max_sol_cost() * tiers as u64,
+            _ => self.max_sol_cost(),
+        }
+    }
+
+    /// One signed variant of `instructions` per race tier, at the tier's compute unit
+    /// price and with its tip. They share the nonce if there is one; otherwise each takes
+    /// the next older blockhash, the oldest being reused when there are fewer than tiers.
+    fn race_variants(
+        &self,
+        instructions: &[Instruction],
+        recent: &Recent,
+    ) -> Result<Vec<VersionedTransaction>> {
+        self.race_tiers
+            .iter()
+            .enumerate()
+            .map(|(tier, (price, tip))| {
+                let mut variant = sender::with_compute_unit_price(instructions, *price);
+                if *tip > 0 {
+                    variant.push(jito::tip_instruction(&self.user.pubkey(), *tip));
+                }
+                match &recent.nonce {
+                    Some(nonce) => nonce::build_nonce_transaction(&variant, &self.user, &[], nonce),
+                    None => {
+                        let newest = recent.blockhashes.len() - 1;
+                        let (blockhash, _) = recent.blockhashes[tier.min(newest)];
+                        alt::build_versioned_transaction(&variant, &self.user, &[], blockhash)
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Patch and sign a buy of the launch in `event`
+    pub fn prepare(&self, event: &CreateEvent) -> Result<SnipeBuy> {
+        let started = Instant::now();
+        let curve = launch_curve(event);
+        let (token_amount, fee) = quote_launch(self.fee_config.as_ref(), &curve, self.buy_lamports);
+        if token_amount == 0 {
+            return Err(anyhow!("{} has no tokens to buy", event.mint));
+        }
+        let max_sol_cost = self.max_sol_cost();
+
+        let instructions = self.template.patch(
+            &event.mint,
+            &event.bonding_curve,
+            &event.creator,
+            token_amount,
+            max_sol_cost,
+        );
+        let recent = self
+            .recent
+            .read()
+            .map_err(|_| anyhow!("Snipe blockhash lock poisoned"))?;
+        let (blockhash, last_valid_block_height) = recent.blockhashes[0];
+        let transactions = if self.race_tiers.is_empty() {
+            vec![alt::build_versioned_transaction(
+                &instructions,
+                &self.user,
+                &[],
+                blockhash,
+            )?]
+        } else {
+            self.race_variants(&instructions, &recent)?
+        };
+        Ok(SnipeBuy {
+            transactions,
+            last_valid_block_height,
+            fill: Fill::new(
+                event.mint,
+                Side::Buy,
+                token_amount,
+                self.buy_lamports,
+                fee,
+                String::new(),
+            ),
+            max_sol_cost,
+            build_time: started.elapsed(),
+        })
+    }
+}
+
+/// Buy every launch the pump.fun program announces, skipping blacklisted launches and
+/// (with `snipe.skip_bundled_launches`) bundled launches, until `snipe.max_snipes` buys
+/// have been sent (0 = until the stream closes)
+#[instrument(name = "snipe", skip_all, fields(user = %user.pubkey()))]
+pub fn run(user: Wallet) -> Result<()> {
+    let config = config::get();
+    let rpc = rpc_pool::get_client();
+    let hot_path = Arc::new(HotPath::new(&rpc, user)?);
+    let lists = TokenList::open_default()?;
+    let guard = sizing::guard();
+    let stream = events::subscribe_events(&config.rpc.ws_url, &PUMP_PROGRAM_ID)?;
+    info!(
+        buy_lamports = hot_path.buy_lamports,
+        max_sol_cost = hot_path.max_sol_cost(),
+        "Watching pump.fun launches"
+    );
+
+    let mut snipes = Vec::new();
+    for event in stream {
+        let PumpEvent::Create(event) = event else {
+            continue;
+        };
+        // The hot path sends without the executor, so it screens launches itself
+        if blacklisted(&lists, &event) || bundled(&event) {
+            continue;
+        }
+
+        let reserved = hot_path.reserved_lamports();
+        if let Err(e) = guard.reserve(&event.mint, reserved) {
+            warn!(mint = %event.mint, error = %e, "Skipping launch");
+            continue;
+        }
+        let snipe = match hot_path.prepare(&event) {
+            Ok(snipe) => snipe,
+            Err(e) => {
+                guard.release(&event.mint, reserved);
+                warn!(mint = %event.mint, error = %e, "Failed to build snipe");
+                continue;
+            }
+        };
+        info!(
+            mint = %event.mint,
+            symbol = %event.symbol,
+            token_amount = snipe.fill.token_amount,
+            variants = snipe.transactions.len(),
+            build_time = ?snipe.build_time,
+            "Sniping launch"
+        );
+
+        let hot_path = hot_path.clone();
+        let guard = guard.clone();
+        snipes.push(std::thread::spawn(move || {
+            let rpc = rpc_pool::get_client();
+            let mint = snipe.fill.mint;
+            let result = match snipe.transactions.as_slice() {
+                [transaction] => sender::execute_unsimulated_trade(
+                    &rpc,
+                    transaction,
+                    snipe.last_valid_block_height,
+                    hot_path.user(),
+                    snipe.fill,
+                ),
+                transactions => sender::execute_raced_trade(
+                    &rpc,
+                    transactions,
+                    snipe.last_valid_block_height,
+                    hot_path.user(),
+                    snipe.fill,
+                ),
+            };
+            if let Err(e) = result {
+                warn!(%mint, error = %e, "Snipe failed");
+            }
+            guard.release(&mint, reserved);
+        }));
+        if config.snipe.max_snipes > 0 && snipes.len() >= config.snipe.max_snipes {
+            break;
+        }
+    }
+
+    for snipe in snipes {
+        let _ = snipe.join();
+    }
+    Ok(())
+}
+
+/// Launch sniping under the strategy runner. Its buys go through the executor like
+/// every other strategy's: slower than `run`'s hot path, but retried on slippage.
+pub struct SnipeStrategy {
+    creators: CreatorList,
+    fee_config: Option<FeeConfig>,
+    buy_lamports: u64,
+    slippage_bps: u64,
+    /// Buys left before it stops sniping, None for no limit
+    remaining: Option<usize>,
+}
+
+impl SnipeStrategy {
+    /// Snipe with the `[snipe]` settings
+    pub fn new(rpc: &RpcClient) -> Result<Self> {
+        let config = config::get();
+        Ok(Self {
+            creators: CreatorList::open_default()?,
+            fee_config: fetch_fee_config(rpc),
+            buy_lamports: buy_lamports(),
+            slippage_bps: config.snipe.slippage_bps,
+            remaining: (config.snipe.max_snipes > 0).then_some(config.snipe.max_snipes),
+        })
+    }
+}
+
+impl Strategy for SnipeStrategy {
+    fn name(&self) -> &str {
+        "snipe"
+    }
+
+    fn subscriptions(&self) -> Vec<Pubkey> {
+        vec![*PUMP_PROGRAM_ID]
+    }
+
+    fn on_new_token(&mut self, _ctx: &Context, event: &CreateEvent) -> Vec<TradeIntent> {
+        if self.remaining == Some(0) || denied(&self.creators, event) || bundled(event) {
+            return Vec::new();
+        }
+        let curve = launch_curve(event);
+        let (token_amount, _) = quote_launch(self.fee_config.as_ref(), &curve, self.buy_lamports);
+        if token_amount == 0 {
+            return Vec::new();
+        }
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= 1;
+        }
+        info!(mint = %event.mint, symbol = %event.symbol, token_amount, "Sniping launch");
+        vec![TradeIntent::Buy {
+            mint: event.mint,
+            token_amount,
+            max_sol_cost: pump_buy::with_slippage(self.buy_lamports, self.slippage_bps),
+        }]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pump_idl::get_bonding_curve_pda;
+
+    #[test]
+    fn test_patched_template_matches_fresh_build() {
+        let user = Pubkey::new_unique();
+        let (mint, creator) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (bonding_curve, _) = get_bonding_curve_pda(&mint);
+
+        for token_program in [TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID] {
+            let template = BuyTemplate::new(&user, &token_program);
+            let mut expected = config::get().fees.compute_budget_instructions();
+            expected.push(create_associated_token_account_idempotent(
+                &user,
+                &user,
+                &mint,
+                &token_program,
+            ));
+            expected.push(pump_buy::buy_instruction(
+                &user,
+                &mint,
+                &creator,
+                &token_program,
+                1_000_000,
+                55_000_000,
+            ));
+            assert_eq!(
+                template.patch(&mint, &bonding_curve, &creator, 1_000_000, 55_000_000),
+                expected
+            );
+        }
+    }
+}
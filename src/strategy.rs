@@ -0,0 +1,548 @@
+use anyhow::{anyhow, Result};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use tracing::{error, info, instrument, warn};
+
+use crate::accounts::BondingCurve;
+use crate::cal::Global;
+use crate::error;
+use crate::events::{self, CompleteEvent, CreateEvent, PumpEvent, TradeEvent};
+use crate::executor::{Executor, ExecutorHandle, TradeIntent, TradeOutcome};
+use crate::indicators::Indicators;
+use crate::portfolio::{now_unix, Fill, Portfolio, Position, Side};
+use crate::{config, curve_cache, fees, rpc_pool, telegram, token_accounts};
+
+lazy_static::lazy_static! {
+    static ref PUMP_PROGRAM_ID: Pubkey = Pubkey::from_str("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P").unwrap();
+}
+
+/// Where strategies read curves, balances and positions: the chain and the portfolio
+/// when trading, replayed history in a backtest
+pub trait Market {
+    /// The RPC client when trading, None in a backtest
+    fn rpc(&self) -> Option<&RpcClient>;
+
+    /// Unix seconds now: the wall clock when trading, the replayed clock in a backtest
+    fn now(&self) -> i64 {
+        now_unix()
+    }
+
+    fn bonding_curve(&self, mint: &Pubkey) -> error::Result<BondingCurve>;
+
+    /// Quote parameters with the fees charged on `curve`
+    fn global(&self, curve: &BondingCurve) -> Global;
+
+    /// Tokens of `mint` that `owner` can sell
+    fn token_balance(&self, owner: &Pubkey, mint: &Pubkey) -> error::Result<u64>;
+
+    /// Our position in `mint`, from the recorded fills
+    fn position(&self, mint: &Pubkey) -> Result<Option<Position>>;
+}
+
+/// The chain and the portfolio for the current `trading.mode`. Curves are read through
+/// the shared curve cache.
+pub struct LiveMarket {
+    rpc: RpcClient,
+}
+
+impl LiveMarket {
+    pub fn new(rpc: RpcClient) -> Self {
+        Self { rpc }
+    }
+}
+
+impl Market for LiveMarket {
+    fn rpc(&self) -> Option<&RpcClient> {
+        Some(&self.rpc)
+    }
+
+    fn bonding_curve(&self, mint: &Pubkey) -> error::Result<BondingCurve> {
+        curve_cache::global().get(&self.rpc, mint)
+    }
+
+    fn global(&self, curve: &BondingCurve) -> Global {
+        fees::current_global(&self.rpc, curve)
+    }
+
+    fn token_balance(&self, owner: &Pubkey, mint: &Pubkey) -> error::Result<u64> {
+        let token_program = self.rpc.get_account(mint)?.owner;
+        let holdings =
+            token_accounts::fetch_token_holdings(&self.rpc, owner, mint, &token_program)?;
+        Ok(holdings.total_spendable())
+    }
+
+    fn position(&self, mint: &Pubkey) -> Result<Option<Position>> {
+        Portfolio::open_default()?.get_position(mint)
+    }
+}
+
+/// What every strategy hook can use
+pub struct Context<'a> {
+    pub market: &'a dyn Market,
+    /// The trading wallet
+    pub user: &'a Pubkey,
+    /// Rolling trade flow of every mint traded in the last five minutes
+    pub indicators: &'a Indicators,
+}
+
+/// A trading strategy driven by `StrategyRunner`. Hooks return the trades the strategy
+/// wants, which the runner submits to the executor, so the exposure limits and the
+/// balance reserve apply to every strategy alike. Hooks run on the runner's thread and
+/// should not block for long.
+pub trait Strategy: Send {
+    fn name(&self) -> &str;
+
+    /// Accounts whose pump.fun events the strategy needs, e.g. copy-trade targets.
+    /// The pump.fun program covers every launch and trade.
+    fn subscriptions(&self) -> Vec<Pubkey>;
+
+    /// How often `on_timer` runs, None for never
+    fn timer_interval(&self) -> Option<Duration> {
+        None
+    }
+
+    /// A token launched
+    fn on_new_token(&mut self, _ctx: &Context, _event: &CreateEvent) -> Vec<TradeIntent> {
+        Vec::new()
+    }
+
+    /// A trade moved a bonding curve; the event carries its reserves after the trade
+    fn on_price_update(&mut self, _ctx: &Context, _trade: &TradeEvent) -> Vec<TradeIntent> {
+        Vec::new()
+    }
+
+    /// A bonding curve completed and its token is migrating off pump.fun
+    fn on_complete(&mut self, _ctx: &Context, _event: &CompleteEvent) -> Vec<TradeIntent> {
+        Vec::new()
+    }
+
+    /// A trade submitted by any strategy landed, so e.g. exits can follow other
+    /// strategies' entries. Only paper and live trades record fills.
+    fn on_fill(&mut self, _ctx: &Context, _fill: &Fill) -> Vec<TradeIntent> {
+        Vec::new()
+    }
+
+    /// A trade this strategy submitted failed, so e.g. an exit it is waiting on can be
+    /// tried again
+    fn on_trade_failed(&mut self, _ctx: &Context, _intent: &TradeIntent) -> Vec<TradeIntent> {
+        Vec::new()
+    }
+
+    /// `timer_interval` elapsed
+    fn on_timer(&mut self, _ctx: &Context) -> Vec<TradeIntent> {
+        Vec::new()
+    }
+}
+
+impl<S: Strategy + ?Sized> Strategy for Box<S> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn subscriptions(&self) -> Vec<Pubkey> {
+        (**self).subscriptions()
+    }
+
+    fn timer_interval(&self) -> Option<Duration> {
+        (**self).timer_interval()
+    }
+
+    fn on_new_token(&mut self, ctx: &Context, event: &CreateEvent) -> Vec<TradeIntent> {
+        (**self).on_new_token(ctx, event)
+    }
+
+    fn on_price_update(&mut self, ctx: &Context, trade: &TradeEvent) -> Vec<TradeIntent> {
+        (**self).on_price_update(ctx, trade)
+    }
+
+    fn on_complete(&mut self, ctx: &Context, event: &CompleteEvent) -> Vec<TradeIntent> {
+        (**self).on_complete(ctx, event)
+    }
+
+    fn on_fill(&mut self, ctx: &Context, fill: &Fill) -> Vec<TradeIntent> {
+        (**self).on_fill(ctx, fill)
+    }
+
+    fn on_trade_failed(&mut self, ctx: &Context, intent: &TradeIntent) -> Vec<TradeIntent> {
+        (**self).on_trade_failed(ctx, intent)
+    }
+
+    fn on_timer(&mut self, ctx: &Context) -> Vec<TradeIntent> {
+        (**self).on_timer(ctx)
+    }
+}
+
+/// What the run loop reacts to
+enum Input {
+    Event(PumpEvent),
+    Outcome(TradeOutcome),
+    /// One of the event streams closed
+    StreamClosed,
+}
+
+/// Fill recorded by a finished trade, if it landed in a mode that records fills
+fn recorded_fill(outcome: &TradeOutcome) -> Option<Fill> {
+    outcome.result.as_ref().ok()?;
+    let side = match outcome.intent {
+        TradeIntent::Buy { .. } | TradeIntent::ProtectedBuy { .. } => Side::Buy,
+        TradeIntent::Sell { .. } | TradeIntent::SellRequest { .. } => Side::Sell,
+    };
+    let fills = Portfolio::open_default()
+        .and_then(|portfolio| portfolio.get_fills(Some(&outcome.intent.mint())))
+        .map_err(|e| warn!(trade_id = outcome.id, error = %e, "Failed to look up fill"))
+        .ok()?;
+    fills.into_iter().rev().find(|fill| fill.side == side)
+}
+
+/// Feeds pump.fun events, trade fills and timers to registered strategies and
+/// submits the trades they return
+#[derive(Default)]
+pub struct StrategyRunner {
+    strategies: Vec<Box<dyn Strategy>>,
+    /// When each strategy's timer is next due
+    timers: Vec<Option<Instant>>,
+    /// Strategy that submitted each trade still in flight
+    owners: HashMap<u64, usize>,
+}
+
+impl StrategyRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, strategy: impl Strategy + 'static) -> &mut Self {
+        let timer = strategy
+            .timer_interval()
+            .map(|interval| Instant::now() + interval);
+        self.strategies.push(Box::new(strategy));
+        self.timers.push(timer);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strategies.is_empty()
+    }
+
+    /// Accounts to stream: the pump.fun program alone when any strategy needs it, since
+    /// its events include every other account's
+    fn subscriptions(&self) -> Vec<Pubkey> {
+        let accounts: BTreeSet<Pubkey> = self
+            .strategies
+            .iter()
+            .flat_map(|strategy| strategy.subscriptions())
+            .collect();
+        if accounts.contains(&PUMP_PROGRAM_ID) {
+            vec![*PUMP_PROGRAM_ID]
+        } else {
+            accounts.into_iter().collect()
+        }
+    }
+
+    fn on_event(&mut self, ctx: &Context, event: &PumpEvent) -> Vec<(usize, TradeIntent)> {
+        let mut intents = Vec::new();
+        for (index, strategy) in self.strategies.iter_mut().enumerate() {
+            let wanted = match event {
+                PumpEvent::Create(event) => strategy.on_new_token(ctx, event),
+                PumpEvent::Trade(event) => strategy.on_price_update(ctx, event),
+                PumpEvent::Complete(event) => strategy.on_complete(ctx, event),
+            };
+            intents.extend(wanted.into_iter().map(|intent| (index, intent)));
+        }
+        intents
+    }
+
+    /// Trade `id` failed; only the strategy that submitted it hears of it
+    fn on_failure(
+        &mut self,
+        ctx: &Context,
+        id: u64,
+        intent: &TradeIntent,
+    ) -> Vec<(usize, TradeIntent)> {
+        let Some(&index) = self.owners.get(&id) else {
+            return Vec::new();
+        };
+        let wanted = self.strategies[index].on_trade_failed(ctx, intent);
+        wanted.into_iter().map(|intent| (index, intent)).collect()
+    }
+
+    /// Trade `id` finished; its fill, if any, goes to every strategy
+    fn on_outcome(
+        &mut self,
+        ctx: &Context,
+        id: u64,
+        fill: Option<&Fill>,
+    ) -> Vec<(usize, TradeIntent)> {
+        self.owners.remove(&id);
+        let Some(fill) = fill else {
+            return Vec::new();
+        };
+        let mut intents = Vec::new();
+        for (index, strategy) in self.strategies.iter_mut().enumerate() {
+            let wanted = strategy.on_fill(ctx, fill);
+            intents.extend(wanted.into_iter().map(|intent| (index, intent)));
+        }
+        intents
+    }
+
+    /// Run the timers due at `now` and schedule their next runs
+    fn on_timers(&mut self, ctx: &Context, now: Instant) -> Vec<(usize, TradeIntent)> {
+        let mut intents = Vec::new();
+        for (index, strategy) in self.strategies.iter_mut().enumerate() {
+            let Some(due) = self.timers[index] else {
+                continue;
+            };
+            if due > now {
+                continue;
+            }
+            self.timers[index] = strategy.timer_interval().map(|interval| now + interval);
+            let wanted = strategy.on_timer(ctx);
+            intents.extend(wanted.into_iter().map(|intent| (index, intent)));
+        }
+        intents
+    }
+
+    fn next_timer(&self) -> Option<Instant> {
+        self.timers.iter().flatten().min().copied()
+    }
+
+    fn submit(&mut self, executor: &ExecutorHandle, intents: Vec<(usize, TradeIntent)>) {
+        for (index, intent) in intents {
+            let strategy = self.strategies[index].name();
+            match executor.submit(intent.clone()) {
+                Ok(id) => {
+                    info!(strategy, trade_id = id, ?intent, "Strategy trade submitted");
+                    self.owners.insert(id, index);
+                }
+                Err(e) => warn!(strategy, ?intent, error = %e, "Strategy trade refused"),
+            }
+        }
+    }
+
+    /// Stream the registered strategies' accounts and drive them until every stream has
+    /// closed and no trade or timer is left. Trades go through an executor of its own,
+    /// sized by `trading.max_in_flight`.
+    #[instrument(name = "strategies", skip_all, fields(%user))]
+    pub fn run(mut self, user: &Pubkey) -> Result<()> {
+        if self.is_empty() {
+            return Err(anyhow!("No strategies registered"));
+        }
+        let config = config::get();
+        let market = LiveMarket::new(rpc_pool::get_client());
+        let (executor, mut outcomes) = Executor::start(config.trading.max_in_flight)?;
+        let (tx, rx) = mpsc::channel();
+
+        let subscriptions = self.subscriptions();
+        for account in subscriptions.clone() {
+            let tx = tx.clone();
+            let ws_url = config.rpc.ws_url.clone();
+            std::thread::spawn(move || {
+                match events::subscribe_events(&ws_url, &account) {
+                    Ok(stream) => {
+                        for event in stream {
+                            if tx.send(Input::Event(event)).is_err() {
+                                return;
+                            }
+                        }
+                        warn!(%account, "Strategy event stream closed");
+                    }
+                    Err(e) => warn!(%account, error = %e, "Failed to subscribe for strategies"),
+                }
+                let _ = tx.send(Input::StreamClosed);
+            });
+        }
+        std::thread::spawn(move || {
+            while let Some(outcome) = outcomes.blocking_recv() {
+                if tx.send(Input::Outcome(outcome)).is_err() {
+                    return;
+                }
+            }
+        });
+
+        info!(
+            strategies = ?self.strategies.iter().map(|s| s.name()).collect::<Vec<_>>(),
+            accounts = subscriptions.len(),
+            "Running strategies"
+        );
+        let mut indicators = Indicators::new();
+        let mut open_streams = subscriptions.len();
+        loop {
+            if open_streams == 0 && self.owners.is_empty() && self.next_timer().is_none() {
+                break;
+            }
+            let input = match self.next_timer() {
+                Some(due) => match rx.recv_timeout(due.saturating_duration_since(Instant::now())) {
+                    Ok(input) => Some(input),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                },
+                None => match rx.recv() {
+                    Ok(input) => Some(input),
+                    Err(_) => break,
+                },
+            };
+
+            if let Some(Input::Event(event)) = &input {
+                curve_cache::global().on_event(event);
+                indicators.on_event(event);
+            }
+            let ctx = Context {
+                market: &market,
+                user,
+                indicators: &indicators,
+            };
+            let intents = match input {
+                Some(Input::Event(event)) => self.on_event(&ctx, &event),
+                Some(Input::Outcome(outcome)) => {
+                    let mut intents = Vec::new();
+                    if let Err(e) = &outcome.result {
+                        let strategy = self
+                            .owners
+                            .get(&outcome.id)
+                            .map(|&i| self.strategies[i].name());
+                        error!(
+                            strategy,
+                            trade_id = outcome.id,
+                            intent = ?outcome.intent,
+                            error = %e,
+                            "Strategy trade failed"
+                        );
+                        telegram::notify(format!(
+                            "Trade #{} ({:?}) failed: {}",
+                            outcome.id, outcome.intent, e
+                        ));
+                        intents = self.on_failure(&ctx, outcome.id, &outcome.intent);
+                    }
+                    let fill = recorded_fill(&outcome);
+                    intents.extend(self.on_outcome(&ctx, outcome.id, fill.as_ref()));
+                    intents
+                }
+                Some(Input::StreamClosed) => {
+                    open_streams -= 1;
+                    Vec::new()
+                }
+                None => self.on_timers(&ctx, Instant::now()),
+            };
+            self.submit(&executor, intents);
+        }
+
+        info!("Strategies stopped");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Buys every launch and sells whatever it fills
+    struct Flipper;
+
+    impl Strategy for Flipper {
+        fn name(&self) -> &str {
+            "flipper"
+        }
+
+        fn subscriptions(&self) -> Vec<Pubkey> {
+            vec![*PUMP_PROGRAM_ID]
+        }
+
+        fn timer_interval(&self) -> Option<Duration> {
+            Some(Duration::from_secs(60))
+        }
+
+        fn on_new_token(&mut self, _ctx: &Context, event: &CreateEvent) -> Vec<TradeIntent> {
+            vec![TradeIntent::Buy {
+                mint: event.mint,
+                token_amount: 1_000,
+                max_sol_cost: 10_000,
+            }]
+        }
+
+        fn on_fill(&mut self, _ctx: &Context, fill: &Fill) -> Vec<TradeIntent> {
+            vec![TradeIntent::Sell {
+                mint: fill.mint,
+                token_amount: fill.token_amount,
+                min_sol_output: 0,
+            }]
+        }
+    }
+
+    /// Only watches one wallet
+    struct Watcher(Pubkey);
+
+    impl Strategy for Watcher {
+        fn name(&self) -> &str {
+            "watcher"
+        }
+
+        fn subscriptions(&self) -> Vec<Pubkey> {
+            vec![self.0]
+        }
+    }
+
+    #[test]
+    fn test_runner_routes_events_fills_and_timers() {
+        let market = LiveMarket::new(RpcClient::new("http://localhost:8899".to_string()));
+        let user = Pubkey::new_unique();
+        let indicators = Indicators::new();
+        let ctx = Context {
+            market: &market,
+            user: &user,
+            indicators: &indicators,
+        };
+        let wallet = Pubkey::new_unique();
+        let mut runner = StrategyRunner::new();
+        runner.register(Watcher(wallet));
+        assert_eq!(runner.subscriptions(), vec![wallet]);
+        runner.register(Flipper);
+        assert_eq!(runner.subscriptions(), vec![*PUMP_PROGRAM_ID]);
+
+        let mint = Pubkey::new_unique();
+        let launch = PumpEvent::Create(CreateEvent {
+            name: "Test".to_string(),
+            symbol: "TST".to_string(),
+            uri: String::new(),
+            mint,
+            bonding_curve: Pubkey::new_unique(),
+            user: wallet,
+            creator: wallet,
+            timestamp: 0,
+            virtual_token_reserves: 0,
+            virtual_sol_reserves: 0,
+            real_token_reserves: 0,
+            token_total_supply: 0,
+        });
+        let intents = runner.on_event(&ctx, &launch);
+        assert_eq!(intents.len(), 1);
+        assert_eq!(intents[0].0, 1);
+        assert_eq!(intents[0].1.mint(), mint);
+
+        // Every strategy hears of every fill, whoever submitted the trade
+        runner.owners.insert(7, 0);
+        let fill = Fill::new(mint, Side::Buy, 1_000, 10_000, 0, String::new());
+        assert!(runner.on_outcome(&ctx, 7, None).is_empty());
+        assert!(runner.owners.is_empty());
+        let sells = runner.on_outcome(&ctx, 8, Some(&fill));
+        assert!(matches!(
+            sells[..],
+            [(
+                1,
+                TradeIntent::Sell {
+                    token_amount: 1_000,
+                    ..
+                }
+            )]
+        ));
+
+        let due = runner.next_timer().unwrap();
+        assert!(runner
+            .on_timers(&ctx, due - Duration::from_secs(1))
+            .is_empty());
+        runner.on_timers(&ctx, due);
+        assert!(runner.next_timer().unwrap() > due);
+    }
+}
@@ -0,0 +1,300 @@
+use once_cell::sync::OnceCell;
+use tokio::sync::mpsc;
+
+#[cfg(feature = "telegram")]
+pub use bot::{run, Command};
+
+/// Messages waiting to be pushed to the configured chat, set while the bot runs
+static OUTBOX: OnceCell<mpsc::UnboundedSender<String>> = OnceCell::new();
+
+/// Push `text` to the Telegram chat. Does nothing unless the bot is running.
+pub fn notify(text: impl Into<String>) {
+    if let Some(outbox) = OUTBOX.get() {
+        let _ = outbox.send(text.into());
+    }
+}
+
+#[cfg(feature = "telegram")]
+mod bot {
+    use anyhow::anyhow;
+    use solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+    use std::str::FromStr;
+    use teloxide::{prelude::*, utils::command::BotCommands};
+    use tokio::sync::mpsc;
+    use tracing::warn;
+
+    use super::{notify, OUTBOX};
+    use crate::amount::SolAmount;
+    use crate::blacklist::{EntryKind, TokenList};
+    use crate::creators::ListStatus;
+    use crate::executor::{self, IdempotencyKey, TradeIntent};
+    use crate::portfolio::Portfolio;
+    use crate::pump_sell::{SellAmount, SellRequest};
+    use crate::{
+        address_book, cal, config, error, fees, pnl, price_feed, pump_buy, rpc_pool, sizing,
+    };
+
+    #[derive(BotCommands, Clone)]
+    #[command(rename_rule = "lowercase", description = "Commands:")]
+    pub enum Command {
+        #[command(description = "show this help")]
+        Help,
+        #[command(description = "buy <mint> <sol>", parse_with = "split")]
+        Buy { mint: String, sol: f64 },
+        #[command(description = "sell <mint> <percent>", parse_with = "split")]
+        Sell { mint: String, pct: f64 },
+        #[command(description = "list open positions")]
+        Positions,
+        #[command(description = "realized and unrealized PnL")]
+        Pnl,
+        #[command(
+            description = "blacklist <mint|creator|symbol> <value>",
+            parse_with = "split"
+        )]
+        Blacklist { kind: String, value: String },
+        #[command(
+            description = "allowlist <mint|creator|symbol> <value>",
+            parse_with = "split"
+        )]
+        Allowlist { kind: String, value: String },
+        #[command(
+            description = "unlist <mint|creator|symbol> <value>",
+            parse_with = "split"
+        )]
+        Unlist { kind: String, value: String },
+        #[command(description = "show the blacklist and allowlist")]
+        Lists,
+    }
+
+    fn sol(lamports: i64) -> String {
+        format!("{:.4} SOL", lamports as f64 / LAMPORTS_PER_SOL as f64)
+    }
+
+    fn parse_mint(mint: &str) -> error::Result<Pubkey> {
+        Ok(Pubkey::from_str(mint).map_err(|e| anyhow!("Invalid mint {}: {}", mint, e))?)
+    }
+
+    /// Put `value` on the blacklist (Deny) or allowlist (Allow)
+    fn list(kind: &str, value: &str, status: ListStatus) -> error::Result<String> {
+        let kind = EntryKind::parse(kind)?;
+        TokenList::open_default()?.set(kind, value, status, None)?;
+        let list = match status {
+            ListStatus::Allow => "allowlist",
+            ListStatus::Deny => "blacklist",
+        };
+        Ok(format!("Added {} {} to the {}", kind.as_str(), value, list))
+    }
+
+    /// Run one command against the trade paths, trading under `key` so a redelivered
+    /// message can't trade twice. Blocking.
+    fn run_command(key: IdempotencyKey, command: Command) -> error::Result<String> {
+        let config = config::get();
+        let rpc = rpc_pool::get_client();
+        match command {
+            Command::Help => Ok(Command::descriptions().to_string()),
+            Command::Buy { mint, sol: amount } => {
+                let mint = parse_mint(&mint)?;
+                let sol_amount = SolAmount::from_sol(amount)?.lamports();
+                if sol_amount == 0 {
+                    return Err(anyhow!("SOL amount must be positive").into());
+                }
+                let curve = cal::fetch_bonding_curve(&rpc, &mint)?;
+                let global = fees::current_global(&rpc, &curve);
+                let token_amount = cal::get_tokens_for_sol(&global, Some(&curve), sol_amount);
+                let impact_bps = cal::price_impact_bps(&global, &curve, sol_amount);
+                sizing::check_price_impact(&mint, true, impact_bps, false)?;
+                let max_sol_cost =
+                    pump_buy::with_slippage(sol_amount, config.trading.buy_slippage_bps);
+                executor::global().execute_keyed(
+                    key,
+                    TradeIntent::Buy {
+                        mint,
+                        token_amount,
+                        max_sol_cost,
+                    },
+                )?;
+                Ok(format!(
+                    "Bought {} tokens of {} for about {}",
+                    token_amount,
+                    address_book::label(&mint),
+                    sol(sol_amount as i64)
+                ))
+            }
+            Command::Sell { mint, pct } => {
+                let mint = parse_mint(&mint)?;
+                executor::global().execute_keyed(
+                    key,
+                    TradeIntent::SellRequest {
+                        request: SellRequest {
+                            mint,
+                            amount: SellAmount::Percent(pct),
+                            force: false,
+                        },
+                        slippage_bps: config.trading.sell_slippage_bps,
+                    },
+                )?;
+                Ok(format!("Sold {}% of {}", pct, address_book::label(&mint)))
+            }
+            Command::Positions => {
+                let positions = Portfolio::open_default()?.get_open_positions()?;
+                if positions.is_empty() {
+                    return Ok("No open positions".to_string());
+                }
+                let holdings: Vec<(Pubkey, u64)> =
+                    positions.iter().map(|p| (p.mint, p.token_amount)).collect();
+                let slippage_bps = config.trading.sell_slippage_bps;
+                let exits = cal::fetch_exit_liquidity(&rpc, &holdings, slippage_bps);
+                Ok(positions
+                    .iter()
+                    .zip(exits)
+                    .map(|(p, exit)| {
+                        let exit = match exit {
+                            Ok(Some(exit)) => format!(
+                                ", exit {} ({} bps), {}% within {} bps",
+                                sol(exit.liquidation_lamports as i64),
+                                exit.liquidation_impact_bps,
+                                exit.sellable_tokens * 100 / exit.position_tokens.max(1),
+                                slippage_bps
+                            ),
+                            _ => String::new(),
+                        };
+                        format!(
+                            "{}: {} tokens, cost {}{}",
+                            address_book::label(&p.mint),
+                            p.token_amount,
+                            sol(p.cost_basis_lamports as i64),
+                            exit
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            }
+            Command::Pnl => {
+                let report = pnl::generate(&rpc, &Portfolio::open_default()?)?;
+                let mut reply = format!(
+                    "Realized: {}\nUnrealized: {}\nFees: {}\nNetwork fees: {}\nTips: {}\nNet: {}",
+                    sol(report.realized_pnl_lamports()),
+                    sol(report.unrealized_pnl_lamports()),
+                    sol(report.fees_paid_lamports() as i64),
+                    sol(report.network_fees_lamports() as i64),
+                    sol(report.tips_lamports() as i64),
+                    sol(report.net_pnl_lamports())
+                );
+                if let Some(win_rate) = report.win_rate() {
+                    reply.push_str(&format!(
+                        "\nWin rate: {:.1}% of {} trades",
+                        win_rate * 100.0,
+                        report.round_trips.len()
+                    ));
+                }
+                if let Ok(price) = price_feed::sol_usd(&rpc) {
+                    let total = report.total_pnl_lamports();
+                    reply.push_str(&format!(
+                        "\nTotal: ${:.2}",
+                        total.signum() as f64 * price.lamports_to_usd(total.unsigned_abs())
+                    ));
+                }
+                Ok(reply)
+            }
+            Command::Blacklist { kind, value } => list(&kind, &value, ListStatus::Deny),
+            Command::Allowlist { kind, value } => list(&kind, &value, ListStatus::Allow),
+            Command::Unlist { kind, value } => {
+                let kind = EntryKind::parse(&kind)?;
+                if TokenList::open_default()?.remove(kind, &value)? {
+                    Ok(format!("Removed {} {}", kind.as_str(), value))
+                } else {
+                    Ok(format!("{} {} is not listed", kind.as_str(), value))
+                }
+            }
+            Command::Lists => {
+                let entries = TokenList::open_default()?.entries()?;
+                if entries.is_empty() {
+                    return Ok("Nothing is listed".to_string());
+                }
+                Ok(entries
+                    .iter()
+                    .map(|e| format!("{:?} {} {}", e.status, e.kind.as_str(), e.value))
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            }
+        }
+    }
+
+    async fn answer(bot: Bot, msg: Message, command: Command) -> ResponseResult<()> {
+        if msg.chat.id != ChatId(config::get().telegram.chat_id) {
+            warn!(
+                chat = msg.chat.id.0,
+                "Ignoring Telegram command from unknown chat"
+            );
+            return Ok(());
+        }
+        let key = IdempotencyKey::new(format!("telegram-{}-{}", msg.chat.id.0, msg.id.0));
+        let reply = match tokio::task::spawn_blocking(move || run_command(key, command)).await {
+            Ok(Ok(reply)) => reply,
+            Ok(Err(e)) => format!("Error: {}", e),
+            Err(e) => format!("Command panicked: {}", e),
+        };
+        bot.send_message(msg.chat.id, reply).await?;
+        Ok(())
+    }
+
+    /// Answer commands from `telegram.chat_id` and push notifications to it until Ctrl-C
+    pub fn run() -> anyhow::Result<()> {
+        let config = &config::get().telegram;
+        if config.bot_token.is_empty() {
+            return Err(anyhow!("Config field `telegram.bot_token` is not set"));
+        }
+        let chat = ChatId(config.chat_id);
+
+        tokio::runtime::Runtime::new()?.block_on(async {
+            let bot = Bot::new(&config.bot_token);
+            let (outbox, mut queued) = mpsc::unbounded_channel::<String>();
+            OUTBOX
+                .set(outbox)
+                .map_err(|_| anyhow!("Telegram bot is already running"))?;
+
+            let notifier = bot.clone();
+            tokio::spawn(async move {
+                while let Some(text) = queued.recv().await {
+                    if let Err(e) = notifier.send_message(chat, text).await {
+                        warn!(error = %e, "Failed to send Telegram notification");
+                    }
+                }
+            });
+
+            notify(format!(
+                "Bot started in {} mode",
+                config::get().trading.mode.as_str()
+            ));
+            Command::repl(bot, answer).await;
+            Ok::<(), anyhow::Error>(())
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_parse_commands() {
+            let mint = "Ar4vi1BZXHVgQFRYD8AF7rBe7gsh3D1nM2hZG153pump";
+            assert!(matches!(
+                Command::parse(&format!("/buy {} 0.5", mint), "pumpbot"),
+                Ok(Command::Buy { sol, .. }) if sol == 0.5
+            ));
+            assert!(matches!(
+                Command::parse(&format!("/sell {} 25", mint), "pumpbot"),
+                Ok(Command::Sell { pct, .. }) if pct == 25.0
+            ));
+            assert!(matches!(
+                Command::parse("/pnl", "pumpbot"),
+                Ok(Command::Pnl)
+            ));
+            assert!(matches!(
+                Command::parse("/blacklist symbol rug$", "pumpbot"),
+                Ok(Command::Blacklist { kind, value }) if kind == "symbol" && value == "rug$"
+            ));
+            assert!(Command::parse(&format!("/buy {}", mint), "pumpbot").is_err());
+        }
+    }
+}
@@ -0,0 +1,434 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use solana_account_decoder::UiAccountData;
+use solana_client::{
+    rpc_client::RpcClient, rpc_request::TokenAccountsFilter, rpc_response::RpcKeyedAccount,
+};
+use solana_sdk::{
+    instruction::Instruction, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signer::Signer,
+};
+use spl_token::ID as TOKEN_PROGRAM_ID;
+use spl_token_2022::ID as TOKEN_2022_PROGRAM_ID;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use tracing::info;
+
+use crate::config::{self, ExecutionMode};
+use crate::error::{self, BotError};
+use crate::sender::{self, SendConfig};
+
+// Constants
+/// Close instructions per cleanup transaction, well inside the transaction size limit
+const CLOSE_BATCH_SIZE: usize = 20;
+
+/// Token accounts known to exist, so repeat buys of a mint skip the lookup and the create
+/// instruction. Building a close instruction forgets the account.
+static KNOWN_ACCOUNTS: Lazy<Mutex<HashSet<Pubkey>>> = Lazy::new(Default::default);
+
+fn known_accounts() -> MutexGuard<'static, HashSet<Pubkey>> {
+    KNOWN_ACCOUNTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Whether `account` exists, asking the RPC only until it has been seen once
+pub fn account_exists(rpc: &RpcClient, account: &Pubkey) -> bool {
+    if known_accounts().contains(account) {
+        return true;
+    }
+    let exists = rpc.get_account(account).is_ok();
+    if exists {
+        known_accounts().insert(*account);
+    }
+    exists
+}
+
+/// Remember that `account` exists, once a transaction creating it has landed
+pub fn mark_account_exists(account: &Pubkey) {
+    known_accounts().insert(*account);
+}
+
+/// Forget `account`, so the next buy looks it up again
+pub fn forget_account(account: &Pubkey) {
+    known_accounts().remove(account);
+}
+
+/// A single token account holding the mint that the wallet can spend from
+#[derive(Debug, Clone)]
+pub struct HeldTokenAccount {
+    pub address: Pubkey,
+    /// Raw balance of the account
+    pub amount: u64,
+    /// Amount the wallet may move (full balance if owned, delegated allowance otherwise)
+    pub spendable: u64,
+    pub is_ata: bool,
+    pub is_delegated: bool,
+}
+
+/// Every spendable balance of one mint across the wallet's token accounts
+#[derive(Debug, Clone)]
+pub struct TokenHoldings {
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub accounts: Vec<HeldTokenAccount>,
+}
+
+impl TokenHoldings {
+    /// Total amount the wallet can sell across all accounts
+    pub fn total_spendable(&self) -> u64 {
+        self.accounts.iter().map(|a| a.spendable).sum()
+    }
+
+    /// Total amount held in accounts the wallet owns (excludes delegations)
+    pub fn total_owned(&self) -> u64 {
+        self.accounts
+            .iter()
+            .filter(|a| !a.is_delegated)
+            .map(|a| a.amount)
+            .sum()
+    }
+
+    /// Split `amount` across accounts: canonical ATA first, then largest balances
+    /// Returns (token_account, amount) pairs, or `InsufficientTokens` if the wallet can't cover it
+    pub fn allocate(&self, amount: u64) -> error::Result<Vec<(Pubkey, u64)>> {
+        let total = self.total_spendable();
+        if amount > total {
+            return Err(BotError::InsufficientTokens {
+                needed: amount,
+                available: total,
+            });
+        }
+
+        let mut ordered: Vec<&HeldTokenAccount> =
+            self.accounts.iter().filter(|a| a.spendable > 0).collect();
+        ordered.sort_by(|a, b| b.is_ata.cmp(&a.is_ata).then(b.spendable.cmp(&a.spendable)));
+
+        let mut remaining = amount;
+        let mut plan = Vec::new();
+        for account in ordered {
+            if remaining == 0 {
+                break;
+            }
+            let take = remaining.min(account.spendable);
+            plan.push((account.address, take));
+            remaining -= take;
+        }
+
+        Ok(plan)
+    }
+
+    /// Instructions closing the owned accounts that `allocations` sells down to zero,
+    /// returning their rent to the owner
+    pub fn close_emptied(
+        &self,
+        allocations: &[(Pubkey, u64)],
+        token_program: &Pubkey,
+    ) -> Result<Vec<Instruction>> {
+        self.accounts
+            .iter()
+            .filter(|a| !a.is_delegated)
+            .filter(|a| allocations.contains(&(a.address, a.amount)))
+            .map(|a| close_account(&a.address, &self.owner, token_program))
+            .collect()
+    }
+}
+
+/// A token account holding nothing but rent (or wrapped SOL) that the owner can close
+#[derive(Debug, Clone)]
+pub struct ClosableAccount {
+    pub address: Pubkey,
+    pub mint: Pubkey,
+    pub token_program: Pubkey,
+    /// Lamports returned to the owner on close (rent plus any wrapped SOL)
+    pub lamports: u64,
+}
+
+/// Close `account`, sending its lamports to `owner`
+pub fn close_account(
+    account: &Pubkey,
+    owner: &Pubkey,
+    token_program: &Pubkey,
+) -> Result<Instruction> {
+    forget_account(account);
+    spl_token_2022::instruction::close_account(token_program, account, owner, owner, &[])
+        .map_err(|e| anyhow!("Failed to build close instruction for {}: {}", account, e))
+}
+
+/// Read a raw token amount string at `info.<field>.amount` from jsonParsed account data
+fn parsed_amount(info: &serde_json::Value, field: &str) -> u64 {
+    info[field]["amount"]
+        .as_str()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Convert an RPC keyed account (jsonParsed) into a held token account
+fn parse_keyed_account(
+    keyed: &RpcKeyedAccount,
+    owner: &Pubkey,
+    ata: &Pubkey,
+) -> Result<HeldTokenAccount> {
+    let address = Pubkey::from_str(&keyed.pubkey)?;
+    let info = match &keyed.account.data {
+        UiAccountData::Json(parsed) => &parsed.parsed["info"],
+        _ => return Err(anyhow!("Token account {} not returned as jsonParsed", address)),
+    };
+
+    let amount = parsed_amount(info, "tokenAmount");
+    let is_delegated = info["owner"].as_str() != Some(owner.to_string().as_str());
+    let spendable = if is_delegated {
+        amount.min(parsed_amount(info, "delegatedAmount"))
+    } else {
+        amount
+    };
+
+    Ok(HeldTokenAccount {
+        address,
+        amount,
+        spendable,
+        is_ata: address == *ata,
+        is_delegated,
+    })
+}
+
+/// An account is closable when it's empty or wrapped SOL and the owner holds the close authority
+fn parse_closable_account(
+    keyed: &RpcKeyedAccount,
+    owner: &Pubkey,
+    token_program: &Pubkey,
+) -> Result<Option<ClosableAccount>> {
+    let address = Pubkey::from_str(&keyed.pubkey)?;
+    let info = match &keyed.account.data {
+        UiAccountData::Json(parsed) => &parsed.parsed["info"],
+        _ => {
+            return Err(anyhow!(
+                "Token account {} not returned as jsonParsed",
+                address
+            ))
+        }
+    };
+
+    let owner = owner.to_string();
+    let close_authority = info["closeAuthority"].as_str().unwrap_or(owner.as_str());
+    let is_native = info["isNative"].as_bool().unwrap_or(false);
+    if close_authority != owner || (!is_native && parsed_amount(info, "tokenAmount") != 0) {
+        return Ok(None);
+    }
+
+    let mint = info["mint"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Token account {} has no mint", address))?;
+    Ok(Some(ClosableAccount {
+        address,
+        mint: Pubkey::from_str(mint)?,
+        token_program: *token_program,
+        lamports: keyed.account.lamports,
+    }))
+}
+
+/// Fetch every empty or wrapped SOL token account of `owner` under both token programs
+pub fn fetch_closable_accounts(rpc: &RpcClient, owner: &Pubkey) -> Result<Vec<ClosableAccount>> {
+    let mut closable = Vec::new();
+    for token_program in [TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID] {
+        let accounts = rpc
+            .get_token_accounts_by_owner(owner, TokenAccountsFilter::ProgramId(token_program))
+            .map_err(|e| anyhow!("Failed to fetch token accounts by owner: {}", e))?;
+        for keyed in &accounts {
+            closable.extend(parse_closable_account(keyed, owner, &token_program)?);
+        }
+    }
+    Ok(closable)
+}
+
+/// Raw balance of every mint across the token accounts `owner` owns, under both token
+/// programs. Empty accounts are left out.
+pub fn fetch_owned_balances(rpc: &RpcClient, owner: &Pubkey) -> Result<HashMap<Pubkey, u64>> {
+    let mut balances = HashMap::new();
+    for token_program in [TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID] {
+        let accounts = rpc
+            .get_token_accounts_by_owner(owner, TokenAccountsFilter::ProgramId(token_program))
+            .map_err(|e| anyhow!("Failed to fetch token accounts by owner: {}", e))?;
+        for keyed in &accounts {
+            let UiAccountData::Json(parsed) = &keyed.account.data else {
+                return Err(anyhow!(
+                    "Token account {} not returned as jsonParsed",
+                    keyed.pubkey
+                ));
+            };
+            let info = &parsed.parsed["info"];
+            let amount = parsed_amount(info, "tokenAmount");
+            let mint = info["mint"]
+                .as_str()
+                .ok_or_else(|| anyhow!("Token account {} has no mint", keyed.pubkey))?;
+            if amount > 0 {
+                *balances.entry(Pubkey::from_str(mint)?).or_insert(0) += amount;
+            }
+        }
+    }
+    Ok(balances)
+}
+
+/// Close every empty or wrapped SOL token account of `owner`, in batches.
+/// Only sends in live mode; otherwise each batch is simulated.
+/// Returns the lamports reclaimed by confirmed batches.
+pub fn cleanup(rpc: &RpcClient, owner: &dyn Signer) -> error::Result<u64> {
+    let closable = fetch_closable_accounts(rpc, &owner.pubkey())?;
+    info!(count = closable.len(), "Found closable token accounts");
+
+    let config = config::get();
+    let mut reclaimed = 0;
+    for batch in closable.chunks(CLOSE_BATCH_SIZE) {
+        let mut instructions = config.fees.compute_budget_instructions();
+        for account in batch {
+            info!(
+                address = %account.address,
+                mint = %account.mint,
+                sol = account.lamports as f64 / LAMPORTS_PER_SOL as f64,
+                "Closing token account"
+            );
+            instructions.push(close_account(
+                &account.address,
+                &owner.pubkey(),
+                &account.token_program,
+            )?);
+        }
+        let lamports: u64 = batch.iter().map(|a| a.lamports).sum();
+
+        if config.trading.mode != ExecutionMode::Live {
+            sender::simulate_and_print(rpc, &instructions, owner)?;
+            continue;
+        }
+        let instructions = sender::simulate_for_send(rpc, &instructions, owner)?;
+        let result = sender::send_and_confirm_with_retries(
+            rpc,
+            &instructions,
+            owner,
+            &SendConfig::default(),
+        )?;
+        info!(signature = %result.signature, status = ?result.status, "Cleanup batch finished");
+        if let Some(e) = BotError::from_trade_status(&batch[0].mint, &result.status) {
+            return Err(e);
+        }
+        reclaimed += lamports;
+    }
+    Ok(reclaimed)
+}
+
+/// Fetch all token accounts for `mint` that `owner` owns or is a delegate of
+pub fn fetch_token_holdings(
+    rpc: &RpcClient,
+    owner: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Result<TokenHoldings> {
+    let ata = spl_associated_token_account::get_associated_token_address_with_program_id(
+        owner,
+        mint,
+        token_program,
+    );
+
+    let owned = rpc
+        .get_token_accounts_by_owner(owner, TokenAccountsFilter::Mint(*mint))
+        .map_err(|e| anyhow!("Failed to fetch token accounts by owner: {}", e))?;
+    let delegated = rpc
+        .get_token_accounts_by_delegate(owner, TokenAccountsFilter::Mint(*mint))
+        .map_err(|e| anyhow!("Failed to fetch token accounts by delegate: {}", e))?;
+
+    let mut accounts: Vec<HeldTokenAccount> = Vec::new();
+    for keyed in owned.iter().chain(delegated.iter()) {
+        let account = parse_keyed_account(keyed, owner, &ata)?;
+        // An owned account that also lists us as delegate shows up twice
+        if !accounts.iter().any(|a| a.address == account.address) {
+            accounts.push(account);
+        }
+    }
+
+    Ok(TokenHoldings {
+        owner: *owner,
+        mint: *mint,
+        accounts,
+    })
+}
+
+/// Raw balance of one token account via `getTokenAccountBalance`. The exact `amount` string
+/// is parsed rather than the UI amount, a float already divided by the mint's decimals.
+pub fn fetch_token_balance(rpc: &RpcClient, account: &Pubkey) -> Result<u64> {
+    let balance = rpc
+        .get_token_account_balance(account)
+        .map_err(|e| anyhow!("Failed to fetch token balance of {}: {}", account, e))?;
+    balance.amount.parse().map_err(|e| {
+        anyhow!(
+            "Bad token amount {:?} for {}: {}",
+            balance.amount,
+            account,
+            e
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(amount: u64, is_ata: bool) -> HeldTokenAccount {
+        HeldTokenAccount {
+            address: Pubkey::new_unique(),
+            amount,
+            spendable: amount,
+            is_ata,
+            is_delegated: false,
+        }
+    }
+
+    #[test]
+    fn test_allocate_prefers_ata_then_largest() {
+        let holdings = TokenHoldings {
+            owner: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            accounts: vec![account(100, false), account(50, true), account(300, false)],
+        };
+
+        let plan = holdings.allocate(400).unwrap();
+        assert_eq!(plan.len(), 3);
+        assert_eq!(plan[0], (holdings.accounts[1].address, 50));
+        assert_eq!(plan[1], (holdings.accounts[2].address, 300));
+        assert_eq!(plan[2], (holdings.accounts[0].address, 50));
+        assert!(holdings.allocate(451).is_err());
+    }
+
+    #[test]
+    fn test_close_emptied_only_closes_fully_sold_owned_accounts() {
+        let mut delegated = account(20, false);
+        delegated.is_delegated = true;
+        let holdings = TokenHoldings {
+            owner: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            accounts: vec![account(50, true), account(300, false), delegated],
+        };
+
+        let plan = vec![
+            (holdings.accounts[0].address, 50),
+            (holdings.accounts[1].address, 100),
+            (holdings.accounts[2].address, 20),
+        ];
+        let closes = holdings.close_emptied(&plan, &TOKEN_PROGRAM_ID).unwrap();
+        assert_eq!(closes.len(), 1);
+        assert_eq!(closes[0].accounts[0].pubkey, holdings.accounts[0].address);
+        assert_eq!(closes[0].accounts[1].pubkey, holdings.owner);
+    }
+
+    #[test]
+    fn test_known_accounts_skip_the_lookup_until_closed() {
+        // Every request to this client fails, so only remembered accounts exist
+        let rpc = RpcClient::new_mock("fails".to_string());
+        let ata = Pubkey::new_unique();
+        assert!(!account_exists(&rpc, &ata));
+
+        mark_account_exists(&ata);
+        assert!(account_exists(&rpc, &ata));
+
+        close_account(&ata, &Pubkey::new_unique(), &TOKEN_PROGRAM_ID).unwrap();
+        assert!(!account_exists(&rpc, &ata));
+    }
+}
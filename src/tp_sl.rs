@@ -0,0 +1,1199 @@
+use anyhow::{anyhow, Result};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{info, instrument, warn};
+
+use crate::accounts::BondingCurve;
+use crate::config::{Config, MigrationPolicy};
+use crate::error;
+use crate::events::{CompleteEvent, TradeEvent};
+use crate::limit_orders::{LimitOrderBook, OrderKind};
+use crate::portfolio::{now_unix, Fill, Portfolio, Position, Side};
+use crate::executor::{self, TradeIntent};
+use crate::strategy::{Context, Market, Strategy};
+use crate::{cal, config, curve_cache, fees, router, telegram};
+
+lazy_static::lazy_static! {
+    static ref PUMP_PROGRAM_ID: Pubkey = Pubkey::from_str("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P").unwrap();
+}
+
+/// What selling `token_amount` of a graduated `mint` on its AMM returns now, as a price
+/// in lamports per raw token unit and in lamports
+fn amm_price(rpc: &RpcClient, mint: &Pubkey, token_amount: u64) -> error::Result<(f64, u64)> {
+    let venue = router::venue(rpc, mint)?;
+    let expected_sol = router::quote_sell(rpc, mint, venue, token_amount)?;
+    Ok((expected_sol as f64 / token_amount.max(1) as f64, expected_sol))
+}
+
+/// Lamports a migration exit of `token_amount` should return, 0 (any price) when the
+/// AMM can't be quoted, e.g. before the pool is created or in a backtest
+fn migration_quote(rpc: Option<&RpcClient>, mint: &Pubkey, token_amount: u64) -> u64 {
+    let Some(rpc) = rpc else {
+        return 0;
+    };
+    match amm_price(rpc, mint, token_amount) {
+        Ok((_, expected_sol)) => expected_sol,
+        Err(e) => {
+            warn!(%mint, error = %e, "No AMM quote, selling at any price");
+            0
+        }
+    }
+}
+
+/// Sell part of the position once price reaches `gain_pct` above entry
+#[derive(Debug, Clone)]
+pub struct TakeProfit {
+    /// Gain over entry price in percent (100.0 = 2x)
+    pub gain_pct: f64,
+    /// Percent of the *remaining* position to sell (100.0 = everything left)
+    pub sell_pct: f64,
+}
+
+/// Take-profit ladder and stop-loss settings for one position
+#[derive(Debug, Clone)]
+pub struct TpSlConfig {
+    /// Take-profit levels, fired in ascending order of `gain_pct`
+    pub take_profits: Vec<TakeProfit>,
+    /// Loss from entry price in percent that triggers a full exit
+    pub stop_loss_pct: Option<f64>,
+    /// Retrace from the high-water mark in percent that triggers a full exit
+    pub trailing_stop_pct: Option<f64>,
+    /// Keep what the last take-profit level leaves as a runner instead of selling
+    /// everything left there
+    pub keep_runner: bool,
+    /// Retrace from the high-water mark in percent that sells the runner, once every
+    /// take-profit level has fired
+    pub runner_trailing_stop_pct: Option<f64>,
+    /// Sell whatever is left once the position has been open this long
+    pub max_hold: Option<Duration>,
+    /// Used when the curve can't be streamed
+    pub poll_interval: Duration,
+    /// Slippage applied to the quoted SOL output of each exit
+    pub slippage_bps: u64,
+    /// What happens to the position once its bonding curve completes
+    pub migration_policy: MigrationPolicy,
+}
+
+impl Default for TpSlConfig {
+    /// The built-in strategy: sell half at 2x, the rest at 5x, bail out at -50%
+    fn default() -> Self {
+        Self::from_config(&Config::default()).expect("the default strategy has no ladder")
+    }
+}
+
+impl TpSlConfig {
+    /// Strategy from the `[strategy]` section and `trading.sell_slippage_bps` of `config`
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let tp_sl = Self {
+            take_profits: config
+                .strategy
+                .take_profits
+                .iter()
+                .map(|&(gain_pct, sell_pct)| TakeProfit { gain_pct, sell_pct })
+                .collect(),
+            stop_loss_pct: config.strategy.stop_loss_pct,
+            trailing_stop_pct: config.strategy.trailing_stop_pct,
+            keep_runner: false,
+            runner_trailing_stop_pct: config.strategy.runner_trailing_stop_pct,
+            max_hold: config.strategy.max_hold_secs.map(Duration::from_secs),
+            poll_interval: Duration::from_secs(config.strategy.poll_interval_secs),
+            slippage_bps: config.trading.sell_slippage_bps,
+            migration_policy: config.strategy.migration_policy,
+        };
+        if config.strategy.ladder.is_empty() {
+            Ok(tp_sl)
+        } else {
+            tp_sl.with_ladder(&config.strategy.ladder)
+        }
+    }
+
+    /// Replace the take-profit levels with a ladder of (gain %, sell % of the initial
+    /// position) tranches. Whatever they leave, e.g. 25% after three tranches of 25%, is
+    /// kept as a runner. Fails if a tranche size is not positive or the sizes add up to
+    /// more than the whole position.
+    pub fn with_ladder(mut self, tranches: &[(f64, f64)]) -> Result<Self> {
+        if let Some(i) = tranches.iter().position(|&(_, size_pct)| size_pct <= 0.0) {
+            return Err(anyhow!(
+                "Config field `strategy.ladder[{}]`: size must be greater than 0",
+                i
+            ));
+        }
+        let ladder_pct: f64 = tranches.iter().map(|(_, size_pct)| size_pct).sum();
+        if ladder_pct > 100.0 + 1e-9 {
+            return Err(anyhow!(
+                "Config field `strategy.ladder`: tranches add up to {}% of the position, more than 100%",
+                ladder_pct
+            ));
+        }
+        let mut tranches = tranches.to_vec();
+        tranches.sort_by(|a, b| a.0.total_cmp(&b.0));
+        // Levels sell a share of what is left, so each tranche is rescaled to it
+        let mut remaining_pct = 100.0;
+        self.take_profits = tranches
+            .iter()
+            .map(|&(gain_pct, size_pct)| {
+                let sell_pct = (size_pct / remaining_pct * 100.0).min(100.0);
+                remaining_pct -= size_pct;
+                TakeProfit { gain_pct, sell_pct }
+            })
+            .collect();
+        self.keep_runner = remaining_pct > 1e-9;
+        Ok(self)
+    }
+}
+
+/// Exit the monitor decided to take
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExitAction {
+    TakeProfit { level: usize, token_amount: u64 },
+    StopLoss { token_amount: u64 },
+    /// Price fell `trailing_stop_pct` from `peak_price`
+    TrailingStop { peak_price: f64, token_amount: u64 },
+    /// The curve completed under the `sell` migration policy
+    Migration { token_amount: u64 },
+    /// The position has been open for `max_hold`
+    MaxHold { held_secs: i64, token_amount: u64 },
+}
+
+impl ExitAction {
+    pub fn token_amount(&self) -> u64 {
+        match self {
+            ExitAction::TakeProfit { token_amount, .. } => *token_amount,
+            ExitAction::StopLoss { token_amount } => *token_amount,
+            ExitAction::TrailingStop { token_amount, .. } => *token_amount,
+            ExitAction::Migration { token_amount } => *token_amount,
+            ExitAction::MaxHold { token_amount, .. } => *token_amount,
+        }
+    }
+}
+
+/// Watches one position and sells it down as TP/SL thresholds are crossed
+pub struct TpSlMonitor {
+    pub mint: Pubkey,
+    /// Entry price in lamports per raw token unit
+    pub entry_price: f64,
+    pub remaining_tokens: u64,
+    /// Highest price seen since entry, the trailing stop's reference
+    pub peak_price: f64,
+    /// The curve completed, so the position is priced off its AMM
+    pub migrated: bool,
+    /// Unix time the position was opened, which `max_hold` counts from
+    pub opened_at: i64,
+    config: TpSlConfig,
+    next_take_profit: usize,
+    /// Where the pending take-profit levels are recorded as ladder orders, if anywhere
+    orders: Option<LimitOrderBook>,
+    /// Ladder order of each pending take-profit level
+    level_orders: HashMap<usize, i64>,
+    /// Exit sent as a trade that has neither filled nor failed yet
+    sent_exit: Option<ExitAction>,
+}
+
+impl TpSlMonitor {
+    pub fn new(mint: Pubkey, entry_price: f64, token_amount: u64, mut config: TpSlConfig) -> Self {
+        config
+            .take_profits
+            .sort_by(|a, b| a.gain_pct.total_cmp(&b.gain_pct));
+        Self {
+            mint,
+            entry_price,
+            remaining_tokens: token_amount,
+            peak_price: entry_price,
+            migrated: false,
+            opened_at: now_unix(),
+            config,
+            next_take_profit: 0,
+            orders: None,
+            level_orders: HashMap::new(),
+            sent_exit: None,
+        }
+    }
+
+    /// Record the pending take-profit levels in `book` as ladder orders, so they are listed
+    /// with the bot's other orders and cancelling one skips its level
+    pub fn with_order_book(mut self, book: LimitOrderBook) -> Self {
+        self.orders = Some(book);
+        self.record_levels();
+        self
+    }
+
+    /// Monitor an open portfolio position using its average entry price
+    pub fn from_position(position: &Position, config: TpSlConfig) -> Self {
+        Self {
+            opened_at: position.opened_at,
+            ..Self::new(
+                position.mint,
+                position.avg_entry_price(),
+                position.token_amount,
+                config,
+            )
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.remaining_tokens == 0
+    }
+
+    /// Every take-profit level fired and what is left is the runner
+    fn is_runner(&self) -> bool {
+        self.config.keep_runner && self.next_take_profit >= self.config.take_profits.len()
+    }
+
+    /// Tokens take-profit `level` sells out of `remaining_tokens`, to the nearest token
+    fn level_tokens(&self, level: usize, remaining_tokens: u64) -> u64 {
+        let sell_pct = self.config.take_profits[level].sell_pct;
+        let is_last = level + 1 == self.config.take_profits.len() && !self.config.keep_runner;
+        if is_last || sell_pct >= 100.0 {
+            remaining_tokens
+        } else {
+            (remaining_tokens as f64 * sell_pct / 100.0).round() as u64
+        }
+    }
+
+    /// Pending take-profit levels as (level, price, tokens), assuming each earlier one fills
+    fn pending_levels(&self) -> Vec<(usize, f64, u64)> {
+        let mut remaining_tokens = self.remaining_tokens;
+        (self.next_take_profit..self.config.take_profits.len())
+            .map(|level| {
+                let gain_pct = self.config.take_profits[level].gain_pct;
+                let tokens = self.level_tokens(level, remaining_tokens);
+                remaining_tokens -= tokens;
+                (level, self.entry_price * (1.0 + gain_pct / 100.0), tokens)
+            })
+            .collect()
+    }
+
+    /// Replace `mint`'s ladder orders in `book` with the pending levels, returning the
+    /// order of each
+    fn place_levels(&self, book: &LimitOrderBook) -> Result<HashMap<usize, i64>> {
+        // Also clears orders left by an earlier entry or an earlier run of the bot
+        for order in book.open_orders(Some(&self.mint))? {
+            if order.kind == OrderKind::Ladder {
+                book.cancel(order.id)?;
+            }
+        }
+        let mut level_orders = HashMap::new();
+        for (level, price, tokens) in self.pending_levels() {
+            if tokens == 0 || self.entry_price <= 0.0 {
+                continue;
+            }
+            let order = book.place_order(
+                self.mint,
+                Side::Sell,
+                OrderKind::Ladder,
+                tokens,
+                price,
+                self.config.slippage_bps,
+            )?;
+            level_orders.insert(level, order.id);
+        }
+        Ok(level_orders)
+    }
+
+    /// Record the pending levels in the order book, if there is one
+    fn record_levels(&mut self) {
+        let Some(book) = &self.orders else {
+            return;
+        };
+        match self.place_levels(book) {
+            Ok(level_orders) => self.level_orders = level_orders,
+            Err(e) => warn!(mint = %self.mint, error = %e, "Failed to record ladder orders"),
+        }
+    }
+
+    /// Cancel the ladder orders of the levels that haven't fired
+    pub fn cancel_levels(&mut self) {
+        let Some(book) = &self.orders else {
+            return;
+        };
+        for (_, id) in self.level_orders.drain() {
+            if let Err(e) = book.cancel(id) {
+                warn!(order_id = id, error = %e, "Failed to cancel ladder order");
+            }
+        }
+    }
+
+    /// Decide whether `price` triggers an exit. A triggered take-profit level is
+    /// consumed so it never fires twice.
+    pub fn evaluate(&mut self, price: f64) -> Option<ExitAction> {
+        if self.is_done() || self.entry_price <= 0.0 {
+            return None;
+        }
+        let change_pct = (price / self.entry_price - 1.0) * 100.0;
+        self.peak_price = self.peak_price.max(price);
+
+        if let Some(stop_loss_pct) = self.config.stop_loss_pct {
+            if change_pct <= -stop_loss_pct {
+                return Some(ExitAction::StopLoss {
+                    token_amount: self.remaining_tokens,
+                });
+            }
+        }
+
+        let runner_stop_pct = self
+            .config
+            .runner_trailing_stop_pct
+            .filter(|_| self.is_runner());
+        for trailing_stop_pct in [self.config.trailing_stop_pct, runner_stop_pct]
+            .into_iter()
+            .flatten()
+        {
+            if price <= self.peak_price * (1.0 - trailing_stop_pct / 100.0) {
+                return Some(ExitAction::TrailingStop {
+                    peak_price: self.peak_price,
+                    token_amount: self.remaining_tokens,
+                });
+            }
+        }
+
+        let take_profit = self.config.take_profits.get(self.next_take_profit)?;
+        if change_pct < take_profit.gain_pct {
+            return None;
+        }
+
+        let level = self.next_take_profit;
+        self.next_take_profit += 1;
+        let token_amount = self.level_tokens(level, self.remaining_tokens);
+
+        Some(ExitAction::TakeProfit { level, token_amount })
+    }
+
+    /// Whether the position has outlived `max_hold` at unix time `now`, in which case
+    /// everything left is sold. Not consumed, so once a failed sell is reported (or
+    /// `execute_exit` returns) the exit is taken again.
+    pub fn expire(&self, now: i64) -> Option<ExitAction> {
+        let max_hold = self.config.max_hold?;
+        let held_secs = now - self.opened_at;
+        (!self.is_done() && held_secs >= max_hold.as_secs() as i64).then_some(ExitAction::MaxHold {
+            held_secs,
+            token_amount: self.remaining_tokens,
+        })
+    }
+
+    /// Note that the curve completed. Returns the full exit under the `sell` migration
+    /// policy; under `follow` the levels stay, priced off the AMM from now on.
+    pub fn on_migration(&mut self) -> Option<ExitAction> {
+        if self.migrated {
+            return None;
+        }
+        self.migrated = true;
+        info!(mint = %self.mint, policy = ?self.config.migration_policy, "Bonding curve complete");
+        (self.config.migration_policy == MigrationPolicy::Sell && !self.is_done()).then_some(
+            ExitAction::Migration {
+                token_amount: self.remaining_tokens,
+            },
+        )
+    }
+
+    /// Catch up with trades on the position made elsewhere. A buy moves the average entry
+    /// and restarts the ladder from it; a sell leaves fewer tokens to exit.
+    pub fn sync(&mut self, position: &Position) {
+        if (position.avg_entry_price() - self.entry_price).abs() > self.entry_price * 1e-6 {
+            info!(mint = %self.mint, entry_price = position.avg_entry_price(), "Entry moved");
+            let (migrated, orders) = (self.migrated, self.orders.take());
+            *self = Self::from_position(position, self.config.clone());
+            self.migrated = migrated;
+            self.orders = orders;
+            self.record_levels();
+        } else if position.token_amount < self.remaining_tokens {
+            self.remaining_tokens = position.token_amount;
+            self.record_levels();
+        }
+    }
+
+    /// Record that an exit of `token_amount` tokens went through
+    pub fn apply_exit(&mut self, token_amount: u64) {
+        self.remaining_tokens = self.remaining_tokens.saturating_sub(token_amount);
+    }
+
+    /// Whether an exit was sent and has neither filled nor failed yet, in which case no
+    /// other exit is taken
+    pub fn is_exiting(&self) -> bool {
+        self.sent_exit.is_some()
+    }
+
+    /// Note that the sell for `action` was sent
+    pub fn send_exit(&mut self, action: &ExitAction) {
+        self.sent_exit = Some(action.clone());
+    }
+
+    /// The sent exit filled, leaving `remaining_tokens` to exit
+    pub fn fill_exit(&mut self, remaining_tokens: u64) {
+        self.sent_exit = None;
+        self.remaining_tokens = remaining_tokens;
+    }
+
+    /// The sent exit failed, so its tokens are still held and a take-profit level or
+    /// migration exit it consumed fires again
+    pub fn fail_exit(&mut self) {
+        match self.sent_exit.take() {
+            Some(ExitAction::TakeProfit { level, .. }) => {
+                self.next_take_profit = self.next_take_profit.min(level);
+            }
+            Some(ExitAction::Migration { .. }) => self.migrated = false,
+            _ => {}
+        }
+    }
+
+    /// Announce `action` taken at `price` and build its sell for `expected_sol`, less the
+    /// slippage
+    fn exit(&self, action: &ExitAction, price: f64, expected_sol: u64) -> TradeIntent {
+        let token_amount = action.token_amount();
+        let min_sol_output = (expected_sol as u128
+            * 10_000u128.saturating_sub(self.config.slippage_bps as u128)
+            / 10_000) as u64;
+
+        info!(mint = %self.mint, ?action, price, token_amount, "Exit triggered");
+        telegram::notify(format!(
+            "{:?} triggered on {} at {:.9} lamports/token - selling {} tokens",
+            action, self.mint, price, token_amount
+        ));
+        TradeIntent::Sell {
+            mint: self.mint,
+            token_amount,
+            min_sol_output,
+        }
+    }
+
+    /// Sell through the trade executor and count the tokens as sold if it went through. A
+    /// take-profit level whose ladder order was cancelled is skipped.
+    fn execute_exit(&mut self, action: &ExitAction, price: f64, expected_sol: u64) {
+        let level_order = match action {
+            ExitAction::TakeProfit { level, .. } => self.level_orders.remove(level),
+            _ => None,
+        };
+        if let (Some(book), Some(id)) = (&self.orders, level_order) {
+            match book.claim(id) {
+                Ok(true) => {}
+                Ok(false) => {
+                    info!(order_id = id, "Ladder order cancelled, skipping level");
+                    return;
+                }
+                Err(e) => warn!(order_id = id, error = %e, "Failed to claim ladder order"),
+            }
+        }
+
+        let result = executor::global().execute(self.exit(action, price, expected_sol));
+        if let (Some(book), Some(id)) = (&self.orders, level_order) {
+            let outcome = result.as_ref().map(|_| ()).map_err(|e| e.to_string());
+            if let Err(e) = book.finish(id, outcome, config::get().trading.mode) {
+                warn!(order_id = id, error = %e, "Failed to record ladder order");
+            }
+        }
+        match result {
+            Ok(()) => self.apply_exit(action.token_amount()),
+            Err(e) => warn!(error = %e, "Exit sell failed"),
+        }
+        if self.is_done() {
+            self.cancel_levels();
+        }
+    }
+
+    /// Act on one curve update, selling through the trade executor if an exit triggers
+    fn on_curve(&mut self, rpc: &RpcClient, curve: &BondingCurve) {
+        if curve.complete {
+            if let Some(action) = self.on_migration() {
+                let expected_sol = migration_quote(Some(rpc), &self.mint, action.token_amount());
+                let price = expected_sol as f64 / action.token_amount() as f64;
+                self.execute_exit(&action, price, expected_sol);
+            }
+            return;
+        }
+        let price = cal::spot_price_lamports_per_token(curve);
+        let Some(action) = self.expire(now_unix()).or_else(|| self.evaluate(price)) else {
+            return;
+        };
+        let global = fees::current_global(rpc, curve);
+        let expected_sol = cal::get_sol_from_tokens(&global, Some(curve), action.token_amount());
+        self.execute_exit(&action, price, expected_sol);
+    }
+
+    /// Price the migrated position off its AMM, selling if an exit triggers
+    fn on_amm_quote(&mut self, rpc: &RpcClient) {
+        let (price, expected_sol) = match amm_price(rpc, &self.mint, self.remaining_tokens) {
+            Ok(quote) => quote,
+            Err(e) => {
+                warn!(error = %e, "Failed to quote the AMM");
+                return;
+            }
+        };
+        let Some(action) = self.expire(now_unix()).or_else(|| self.evaluate(price)) else {
+            return;
+        };
+        let expected_sol = (expected_sol as u128 * action.token_amount() as u128
+            / self.remaining_tokens as u128) as u64;
+        self.execute_exit(&action, price, expected_sol);
+    }
+
+    /// Price the position once, off its curve or its AMM once migrated, and sell through
+    /// the trade executor if an exit triggers
+    pub fn poll(&mut self, rpc: &RpcClient) {
+        if self.migrated {
+            self.on_amm_quote(rpc);
+            return;
+        }
+        match curve_cache::global().get(rpc, &self.mint) {
+            Ok(curve) => self.on_curve(rpc, &curve),
+            Err(e) => warn!(mint = %self.mint, error = %e, "Failed to fetch bonding curve"),
+        }
+    }
+
+    pub fn poll_interval(&self) -> Duration {
+        self.config.poll_interval
+    }
+
+    /// Follow the live curve stream, falling back to polling when it can't be
+    /// opened or drops, and sell through the trade executor until the position is closed.
+    /// Once the curve completes the AMM is polled instead, per the migration policy.
+    #[instrument(name = "tp_sl", skip_all, fields(mint = %self.mint))]
+    pub fn run(&mut self, rpc: &RpcClient) -> Result<()> {
+        info!(
+            tokens = self.remaining_tokens,
+            entry_price = self.entry_price,
+            "Monitoring position"
+        );
+
+        match cal::subscribe_bonding_curve(&self.mint) {
+            Ok(stream) => {
+                for curve in stream {
+                    self.on_curve(rpc, &curve);
+                    if self.is_done() || self.migrated {
+                        break;
+                    }
+                }
+            }
+            Err(e) => warn!(error = %e, "Curve stream unavailable, polling instead"),
+        }
+
+        while !self.is_done() {
+            self.poll(rpc);
+            std::thread::sleep(self.config.poll_interval);
+        }
+
+        info!("Position fully exited");
+        Ok(())
+    }
+}
+
+/// TP/SL as a `Strategy`: monitors every position the strategies open, following
+/// trades on its curve and polling every `poll_interval`
+pub struct TpSlStrategy {
+    config: TpSlConfig,
+    monitors: HashMap<Pubkey, TpSlMonitor>,
+}
+
+impl TpSlStrategy {
+    pub fn new(config: TpSlConfig) -> Self {
+        Self {
+            config,
+            monitors: HashMap::new(),
+        }
+    }
+
+    /// Also monitor the positions already open in the portfolio
+    pub fn with_open_positions(mut self) -> Result<Self> {
+        for position in Portfolio::open_default()?.get_open_positions()? {
+            let monitor = TpSlMonitor::from_position(&position, self.config.clone());
+            self.monitors.insert(position.mint, monitor);
+        }
+        Ok(self)
+    }
+
+    pub fn monitored(&self) -> usize {
+        self.monitors.len()
+    }
+
+    /// Sell `mint` if `price` triggers an exit, quoting off `curve` or a freshly fetched one
+    fn check(
+        &mut self,
+        market: &dyn Market,
+        mint: &Pubkey,
+        price: f64,
+        curve: Option<BondingCurve>,
+    ) -> Vec<TradeIntent> {
+        let Some(monitor) = self.monitors.get_mut(mint).filter(|m| !m.is_exiting()) else {
+            return Vec::new();
+        };
+        let Some(action) = monitor
+            .expire(market.now())
+            .or_else(|| monitor.evaluate(price))
+        else {
+            return Vec::new();
+        };
+        let curve = match curve.map_or_else(|| market.bonding_curve(mint), Ok) {
+            Ok(curve) => curve,
+            Err(e) => {
+                warn!(%mint, error = %e, "Failed to fetch bonding curve");
+                return Vec::new();
+            }
+        };
+        if curve.complete {
+            return self.migrate(market, mint);
+        }
+        let expected_sol =
+            cal::get_sol_from_tokens(&market.global(&curve), Some(&curve), action.token_amount());
+        self.exit(mint, &action, price, expected_sol)
+    }
+
+    /// Build `action`'s sell. Its tokens are counted as sold once the sell fills, and until
+    /// it fills or fails no other exit is taken.
+    fn exit(
+        &mut self,
+        mint: &Pubkey,
+        action: &ExitAction,
+        price: f64,
+        expected_sol: u64,
+    ) -> Vec<TradeIntent> {
+        let Some(monitor) = self.monitors.get_mut(mint) else {
+            return Vec::new();
+        };
+        let intent = monitor.exit(action, price, expected_sol);
+        monitor.send_exit(action);
+        vec![intent]
+    }
+
+    /// `mint`'s curve completed: exit or switch to AMM pricing per the migration policy.
+    /// With an exit in flight the migration is left for a later timer to notice.
+    fn migrate(&mut self, market: &dyn Market, mint: &Pubkey) -> Vec<TradeIntent> {
+        let Some(action) = self
+            .monitors
+            .get_mut(mint)
+            .filter(|m| !m.is_exiting())
+            .and_then(TpSlMonitor::on_migration)
+        else {
+            return Vec::new();
+        };
+        let expected_sol = migration_quote(market.rpc(), mint, action.token_amount());
+        let price = expected_sol as f64 / action.token_amount() as f64;
+        self.exit(mint, &action, price, expected_sol)
+    }
+
+    /// Price a migrated position off its AMM, selling if an exit triggers. Backtests
+    /// can't quote AMMs, so migrated positions are left there.
+    fn check_amm(&mut self, market: &dyn Market, mint: &Pubkey) -> Vec<TradeIntent> {
+        let monitor = self.monitors.get_mut(mint).filter(|m| !m.is_exiting());
+        let (Some(rpc), Some(monitor)) = (market.rpc(), monitor) else {
+            return Vec::new();
+        };
+        let (price, expected_sol) = match amm_price(rpc, mint, monitor.remaining_tokens) {
+            Ok(quote) => quote,
+            Err(e) => {
+                warn!(%mint, error = %e, "Failed to quote the AMM");
+                return Vec::new();
+            }
+        };
+        let remaining_tokens = monitor.remaining_tokens;
+        let Some(action) = monitor
+            .expire(market.now())
+            .or_else(|| monitor.evaluate(price))
+        else {
+            return Vec::new();
+        };
+        let expected_sol = (expected_sol as u128 * action.token_amount() as u128
+            / remaining_tokens as u128) as u64;
+        self.exit(mint, &action, price, expected_sol)
+    }
+}
+
+impl Strategy for TpSlStrategy {
+    fn name(&self) -> &str {
+        "tp_sl"
+    }
+
+    fn subscriptions(&self) -> Vec<Pubkey> {
+        vec![*PUMP_PROGRAM_ID]
+    }
+
+    fn timer_interval(&self) -> Option<Duration> {
+        Some(self.config.poll_interval)
+    }
+
+    fn on_price_update(&mut self, ctx: &Context, trade: &TradeEvent) -> Vec<TradeIntent> {
+        let curve_priced = self.monitors.get(&trade.mint).is_some_and(|m| !m.migrated);
+        if !curve_priced || trade.virtual_token_reserves == 0 {
+            return Vec::new();
+        }
+        let price = trade.virtual_sol_reserves as f64 / trade.virtual_token_reserves as f64;
+        self.check(ctx.market, &trade.mint, price, None)
+    }
+
+    /// Re-read the position after every fill: buys restart the ladder from the new
+    /// average entry, sells shrink what is left to exit
+    fn on_fill(&mut self, ctx: &Context, fill: &Fill) -> Vec<TradeIntent> {
+        let position = match ctx.market.position(&fill.mint) {
+            Ok(position) => position.filter(Position::is_open),
+            Err(e) => {
+                warn!(mint = %fill.mint, error = %e, "Failed to read position");
+                return Vec::new();
+            }
+        };
+        match (position, fill.side) {
+            (Some(position), Side::Buy) => {
+                let monitor = TpSlMonitor::from_position(&position, self.config.clone());
+                info!(mint = %fill.mint, entry_price = monitor.entry_price, "Monitoring position");
+                self.monitors.insert(fill.mint, monitor);
+            }
+            (Some(position), Side::Sell) => {
+                if let Some(monitor) = self.monitors.get_mut(&fill.mint) {
+                    monitor.fill_exit(position.token_amount);
+                }
+            }
+            (None, _) => {
+                self.monitors.remove(&fill.mint);
+            }
+        }
+        Vec::new()
+    }
+
+    /// A failed exit leaves its tokens held, so the position is checked again
+    fn on_trade_failed(&mut self, _ctx: &Context, intent: &TradeIntent) -> Vec<TradeIntent> {
+        if let TradeIntent::Sell { mint, .. } = intent {
+            if let Some(monitor) = self.monitors.get_mut(mint) {
+                warn!(%mint, "Exit sell failed, checking the position again");
+                monitor.fail_exit();
+            }
+        }
+        Vec::new()
+    }
+
+    fn on_complete(&mut self, ctx: &Context, event: &CompleteEvent) -> Vec<TradeIntent> {
+        self.migrate(ctx.market, &event.mint)
+    }
+
+    fn on_timer(&mut self, ctx: &Context) -> Vec<TradeIntent> {
+        let mints: Vec<Pubkey> = self.monitors.keys().copied().collect();
+        let mut intents = Vec::new();
+        for mint in mints {
+            if self.monitors[&mint].migrated {
+                intents.extend(self.check_amm(ctx.market, &mint));
+                continue;
+            }
+            match ctx.market.bonding_curve(&mint) {
+                Ok(curve) if curve.complete => intents.extend(self.migrate(ctx.market, &mint)),
+                Ok(curve) => {
+                    let price = cal::spot_price_lamports_per_token(&curve);
+                    intents.extend(self.check(ctx.market, &mint, price, Some(curve)));
+                }
+                Err(e) => warn!(%mint, error = %e, "Failed to fetch bonding curve"),
+            }
+        }
+        intents
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cal::Global;
+    use crate::indicators::Indicators;
+    use crate::portfolio;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_partial_take_profits_then_rest() {
+        let mut monitor = TpSlMonitor::new(Pubkey::new_unique(), 1.0, 1_000, TpSlConfig::default());
+
+        assert_eq!(monitor.evaluate(1.5), None);
+        let first = monitor.evaluate(2.0).unwrap();
+        assert_eq!(first, ExitAction::TakeProfit { level: 0, token_amount: 500 });
+        monitor.apply_exit(first.token_amount());
+
+        // Level 0 is consumed, level 1 not reached yet
+        assert_eq!(monitor.evaluate(2.5), None);
+        let second = monitor.evaluate(5.0).unwrap();
+        assert_eq!(second, ExitAction::TakeProfit { level: 1, token_amount: 500 });
+        monitor.apply_exit(second.token_amount());
+        assert!(monitor.is_done());
+    }
+
+    #[test]
+    fn test_stop_loss_sells_everything() {
+        let mut monitor = TpSlMonitor::new(Pubkey::new_unique(), 1.0, 1_000, TpSlConfig::default());
+        assert_eq!(
+            monitor.evaluate(0.4),
+            Some(ExitAction::StopLoss { token_amount: 1_000 })
+        );
+    }
+
+    #[test]
+    fn test_trailing_stop_follows_the_peak() {
+        let config = TpSlConfig {
+            take_profits: Vec::new(),
+            stop_loss_pct: Some(50.0),
+            trailing_stop_pct: Some(20.0),
+            keep_runner: false,
+            runner_trailing_stop_pct: None,
+            max_hold: None,
+            poll_interval: Duration::from_secs(1),
+            slippage_bps: 500,
+            migration_policy: MigrationPolicy::Follow,
+        };
+        let mut monitor = TpSlMonitor::new(Pubkey::new_unique(), 1.0, 1_000, config);
+
+        // Below entry the peak stays at the entry price
+        assert_eq!(monitor.evaluate(0.9), None);
+        assert_eq!(monitor.evaluate(3.0), None);
+        assert_eq!(monitor.evaluate(2.5), None);
+        assert_eq!(monitor.peak_price, 3.0);
+        assert_eq!(
+            monitor.evaluate(2.4),
+            Some(ExitAction::TrailingStop {
+                peak_price: 3.0,
+                token_amount: 1_000
+            })
+        );
+    }
+
+    #[test]
+    fn test_ladder_keeps_a_runner_and_records_its_tranches() {
+        let config = TpSlConfig {
+            stop_loss_pct: None,
+            trailing_stop_pct: None,
+            runner_trailing_stop_pct: Some(30.0),
+            ..TpSlConfig::default()
+        }
+        .with_ladder(&[(100.0, 25.0), (50.0, 25.0), (200.0, 25.0)])
+        .unwrap();
+        let book = LimitOrderBook::open_in_memory().unwrap();
+        let mint = Pubkey::new_unique();
+        let mut monitor = TpSlMonitor::new(mint, 1.0, 1_000, config).with_order_book(book);
+
+        let tranches: Vec<(u64, f64)> = monitor
+            .orders
+            .as_ref()
+            .unwrap()
+            .open_orders(Some(&mint))
+            .unwrap()
+            .iter()
+            .map(|order| (order.amount, order.limit_price))
+            .collect();
+        assert_eq!(tranches, vec![(250, 1.5), (250, 2.0), (250, 3.0)]);
+
+        for (price, level) in [(1.5, 0), (2.0, 1), (3.0, 2)] {
+            let action = monitor.evaluate(price).unwrap();
+            assert_eq!(
+                action,
+                ExitAction::TakeProfit {
+                    level,
+                    token_amount: 250
+                }
+            );
+            monitor.apply_exit(action.token_amount());
+        }
+        // The runner rides on until it falls 30% off its peak
+        assert_eq!(monitor.remaining_tokens, 250);
+        assert_eq!(monitor.evaluate(4.0), None);
+        assert_eq!(
+            monitor.evaluate(2.7),
+            Some(ExitAction::TrailingStop {
+                peak_price: 4.0,
+                token_amount: 250
+            })
+        );
+
+        // A sell made elsewhere shrinks the tranches still pending
+        let mut monitor = TpSlMonitor::new(mint, 1.0, 1_000, monitor.config.clone())
+            .with_order_book(monitor.orders.take().unwrap());
+        monitor.sync(&Position {
+            mint,
+            token_amount: 500,
+            cost_basis_lamports: 500,
+            realized_pnl_lamports: 0,
+            fees_paid_lamports: 0,
+            opened_at: 0,
+            last_fill_at: 0,
+            fill_count: 2,
+        });
+        let book = monitor.orders.as_ref().unwrap();
+        let amounts: Vec<u64> = book
+            .open_orders(Some(&mint))
+            .unwrap()
+            .iter()
+            .map(|order| order.amount)
+            .collect();
+        assert_eq!(amounts, vec![125, 125, 125]);
+        monitor.cancel_levels();
+        assert!(monitor
+            .orders
+            .as_ref()
+            .unwrap()
+            .open_orders(None)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_max_hold_sells_whatever_is_left() {
+        let config = TpSlConfig {
+            max_hold: Some(Duration::from_secs(900)),
+            ..TpSlConfig::default()
+        };
+        let mut monitor = TpSlMonitor::new(Pubkey::new_unique(), 1.0, 1_000, config);
+        monitor.opened_at = 10_000;
+        monitor.apply_exit(400);
+
+        assert_eq!(monitor.expire(10_899), None);
+        let expired = Some(ExitAction::MaxHold {
+            held_secs: 900,
+            token_amount: 600,
+        });
+        assert_eq!(monitor.expire(10_900), expired);
+        // Until the sell goes through
+        assert_eq!(monitor.expire(10_900), expired);
+        monitor.apply_exit(600);
+        assert_eq!(monitor.expire(20_000), None);
+
+        let no_limit = TpSlConfig {
+            max_hold: None,
+            ..TpSlConfig::default()
+        };
+        let forever = TpSlMonitor::new(Pubkey::new_unique(), 1.0, 1_000, no_limit);
+        assert_eq!(forever.expire(i64::MAX), None);
+    }
+
+    #[test]
+    fn test_migration_policy() {
+        let sell = TpSlConfig {
+            migration_policy: MigrationPolicy::Sell,
+            ..TpSlConfig::default()
+        };
+        let mut monitor = TpSlMonitor::new(Pubkey::new_unique(), 1.0, 1_000, sell);
+        monitor.apply_exit(400);
+        assert_eq!(
+            monitor.on_migration(),
+            Some(ExitAction::Migration { token_amount: 600 })
+        );
+        // A Complete event and a completed curve both report the same migration
+        assert_eq!(monitor.on_migration(), None);
+        // Unless its sell fails, when it is reported again
+        monitor.send_exit(&ExitAction::Migration { token_amount: 600 });
+        monitor.fail_exit();
+        assert_eq!(
+            monitor.on_migration(),
+            Some(ExitAction::Migration { token_amount: 600 })
+        );
+
+        let follow = TpSlConfig {
+            migration_policy: MigrationPolicy::Follow,
+            ..TpSlConfig::default()
+        };
+        let mut monitor = TpSlMonitor::new(Pubkey::new_unique(), 1.0, 1_000, follow);
+        assert_eq!(monitor.on_migration(), None);
+        assert!(monitor.migrated);
+        // The levels still apply to the AMM price
+        assert_eq!(
+            monitor.evaluate(0.4),
+            Some(ExitAction::StopLoss {
+                token_amount: 1_000
+            })
+        );
+    }
+
+    #[test]
+    fn test_sync_follows_trades_made_elsewhere() {
+        let position = |token_amount, cost_basis_lamports| Position {
+            mint: Pubkey::new_unique(),
+            token_amount,
+            cost_basis_lamports,
+            realized_pnl_lamports: 0,
+            fees_paid_lamports: 0,
+            opened_at: 0,
+            last_fill_at: 0,
+            fill_count: 1,
+        };
+        let mut monitor =
+            TpSlMonitor::from_position(&position(1_000, 1_000), TpSlConfig::default());
+        let first = monitor.evaluate(2.0).unwrap();
+        monitor.apply_exit(first.token_amount());
+
+        // A manual sell of 100 more at the same entry just shrinks what is left
+        monitor.sync(&position(400, 400));
+        assert_eq!(monitor.remaining_tokens, 400);
+        assert_eq!(monitor.evaluate(2.0), None);
+
+        // A buy moves the entry, so the ladder starts over from it
+        monitor.sync(&position(1_400, 2_400));
+        assert_eq!(monitor.remaining_tokens, 1_400);
+        assert!((monitor.entry_price - 2_400.0 / 1_400.0).abs() < 1e-12);
+        assert!(matches!(
+            monitor.evaluate(monitor.entry_price * 2.0),
+            Some(ExitAction::TakeProfit { level: 0, .. })
+        ));
+    }
+
+    /// A market pricing every curve at twice the entry, where we hold `held` tokens
+    struct Doubled {
+        held: Cell<u64>,
+    }
+
+    impl Market for Doubled {
+        fn rpc(&self) -> Option<&RpcClient> {
+            None
+        }
+
+        fn bonding_curve(&self, _mint: &Pubkey) -> error::Result<BondingCurve> {
+            Ok(BondingCurve {
+                virtual_token_reserves: 1_000_000_000,
+                virtual_sol_reserves: 2_000_000_000,
+                real_token_reserves: 1_000_000_000,
+                real_sol_reserves: 0,
+                token_total_supply: 1_000_000_000,
+                complete: false,
+                creator: Pubkey::default(),
+            })
+        }
+
+        fn global(&self, _curve: &BondingCurve) -> Global {
+            Global::default()
+        }
+
+        fn token_balance(&self, _owner: &Pubkey, _mint: &Pubkey) -> error::Result<u64> {
+            Ok(self.held.get())
+        }
+
+        fn position(&self, mint: &Pubkey) -> Result<Option<Position>> {
+            let held = self.held.get();
+            let buy = Fill::new(*mint, Side::Buy, held, held, 0, String::new());
+            Ok(portfolio::build_position(mint, &[buy]))
+        }
+    }
+
+    #[test]
+    fn test_strategy_counts_exits_as_sold_once_they_fill() {
+        let market = Doubled {
+            held: Cell::new(1_000),
+        };
+        let user = Pubkey::new_unique();
+        let indicators = Indicators::new();
+        let ctx = Context {
+            market: &market,
+            user: &user,
+            indicators: &indicators,
+        };
+        let config = TpSlConfig {
+            take_profits: vec![
+                TakeProfit {
+                    gain_pct: 50.0,
+                    sell_pct: 50.0,
+                },
+                TakeProfit {
+                    gain_pct: 400.0,
+                    sell_pct: 100.0,
+                },
+            ],
+            stop_loss_pct: None,
+            trailing_stop_pct: None,
+            keep_runner: false,
+            runner_trailing_stop_pct: None,
+            max_hold: None,
+            poll_interval: Duration::from_secs(1),
+            slippage_bps: 0,
+            migration_policy: MigrationPolicy::Follow,
+        };
+        let mut strategy = TpSlStrategy::new(config);
+        let mint = Pubkey::new_unique();
+        let buy = Fill::new(mint, Side::Buy, 1_000, 1_000, 0, String::new());
+        strategy.on_fill(&ctx, &buy);
+
+        let sells = strategy.on_timer(&ctx);
+        assert!(matches!(
+            sells[..],
+            [TradeIntent::Sell {
+                token_amount: 500,
+                ..
+            }]
+        ));
+        // Nothing else is sold while the exit is in flight
+        assert!(strategy.on_timer(&ctx).is_empty());
+        assert_eq!(strategy.monitors[&mint].remaining_tokens, 1_000);
+
+        // A failed exit still holds its tokens, so the level fires again
+        strategy.on_trade_failed(&ctx, &sells[0]);
+        assert!(matches!(
+            strategy.on_timer(&ctx)[..],
+            [TradeIntent::Sell {
+                token_amount: 500,
+                ..
+            }]
+        ));
+
+        market.held.set(500);
+        let sell = Fill::new(mint, Side::Sell, 500, 1_000, 0, String::new());
+        strategy.on_fill(&ctx, &sell);
+        assert_eq!(strategy.monitors[&mint].remaining_tokens, 500);
+        assert!(strategy.on_timer(&ctx).is_empty());
+    }
+
+    #[test]
+    fn test_strategy_retries_a_failed_max_hold_exit() {
+        let market = Doubled {
+            held: Cell::new(1_000),
+        };
+        let user = Pubkey::new_unique();
+        let indicators = Indicators::new();
+        let ctx = Context {
+            market: &market,
+            user: &user,
+            indicators: &indicators,
+        };
+        let config = TpSlConfig {
+            take_profits: Vec::new(),
+            stop_loss_pct: None,
+            trailing_stop_pct: None,
+            keep_runner: false,
+            runner_trailing_stop_pct: None,
+            max_hold: Some(Duration::ZERO),
+            poll_interval: Duration::from_secs(1),
+            slippage_bps: 0,
+            migration_policy: MigrationPolicy::Follow,
+        };
+        let mut strategy = TpSlStrategy::new(config);
+        let mint = Pubkey::new_unique();
+        let buy = Fill::new(mint, Side::Buy, 1_000, 1_000, 0, String::new());
+        strategy.on_fill(&ctx, &buy);
+
+        let sells = strategy.on_timer(&ctx);
+        assert!(matches!(
+            sells[..],
+            [TradeIntent::Sell {
+                token_amount: 1_000,
+                ..
+            }]
+        ));
+        assert!(strategy.on_timer(&ctx).is_empty());
+        strategy.on_trade_failed(&ctx, &sells[0]);
+        assert_eq!(strategy.on_timer(&ctx).len(), 1);
+
+        market.held.set(0);
+        let sell = Fill::new(mint, Side::Sell, 1_000, 1_000, 0, String::new());
+        strategy.on_fill(&ctx, &sell);
+        assert_eq!(strategy.monitored(), 0);
+    }
+
+    #[test]
+    fn test_config_from_strategy_section() {
+        let defaults = TpSlConfig::default();
+        assert_eq!(defaults.take_profits.len(), 2);
+        assert_eq!(defaults.stop_loss_pct, Some(50.0));
+
+        let mut config = Config::default();
+        config.strategy.ladder = vec![(100.0, 25.0), (200.0, 25.0)];
+        config.trading.sell_slippage_bps = 900;
+        let tp_sl = TpSlConfig::from_config(&config).unwrap();
+        assert_eq!(tp_sl.take_profits.len(), 2);
+        assert!(tp_sl.keep_runner && !defaults.keep_runner);
+        assert_eq!(tp_sl.slippage_bps, 900);
+
+        config.strategy.ladder = vec![(50.0, 60.0), (100.0, 60.0)];
+        let error = TpSlConfig::from_config(&config).unwrap_err().to_string();
+        assert!(error.contains("more than 100%"), "{}", error);
+        for size_pct in [0.0, -10.0] {
+            config.strategy.ladder = vec![(100.0, 25.0), (200.0, size_pct)];
+            let error = TpSlConfig::from_config(&config).unwrap_err().to_string();
+            assert!(error.contains("strategy.ladder[1]"), "{}", error);
+        }
+    }
+}
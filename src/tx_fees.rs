@@ -0,0 +1,193 @@
+use anyhow::{anyhow, Result};
+use solana_client::{rpc_client::RpcClient, rpc_config::RpcTransactionConfig};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, UiMessage, UiTransactionEncoding,
+};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::jito;
+
+// Constants
+/// Base fee charged per transaction signature
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+/// How long to wait for a signature before treating the transaction as expired
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(90);
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Final state of a sent transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TxOutcome {
+    /// Landed and executed successfully
+    Succeeded,
+    /// Landed on-chain but the program returned an error (fees still charged)
+    Failed(String),
+    /// Never landed before its blockhash expired (no fees charged)
+    Expired,
+}
+
+impl TxOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TxOutcome::Succeeded => "success",
+            TxOutcome::Failed(_) => "failed",
+            TxOutcome::Expired => "expired",
+        }
+    }
+}
+
+/// Fees a transaction actually cost, split into base and priority components, plus
+/// any Jito tip it paid
+#[derive(Debug, Clone)]
+pub struct TxCost {
+    pub signature: Signature,
+    pub outcome: TxOutcome,
+    pub base_fee: u64,
+    pub priority_fee: u64,
+    pub tip_lamports: u64,
+}
+
+impl TxCost {
+    /// Cost of a transaction that never landed
+    pub fn unlanded(signature: Signature) -> Self {
+        Self {
+            signature,
+            outcome: TxOutcome::Expired,
+            base_fee: 0,
+            priority_fee: 0,
+            tip_lamports: 0,
+        }
+    }
+
+    /// Network fee, without the tip
+    pub fn total_fee(&self) -> u64 {
+        self.base_fee + self.priority_fee
+    }
+
+    /// Network fee plus tip
+    pub fn total_cost(&self) -> u64 {
+        self.total_fee() + self.tip_lamports
+    }
+}
+
+/// Split a charged fee into base and priority parts
+pub fn split_fee(total_fee: u64, num_signatures: usize) -> (u64, u64) {
+    let base_fee = (LAMPORTS_PER_SIGNATURE * num_signatures as u64).min(total_fee);
+    (base_fee, total_fee - base_fee)
+}
+
+/// Wait for a sent transaction to land (or expire) and read the fee it was charged
+pub fn await_transaction_cost(
+    rpc: &RpcClient,
+    signature: &Signature,
+    num_signatures: usize,
+) -> Result<TxCost> {
+    let started = Instant::now();
+
+    let status = loop {
+        if let Some(status) =
+            rpc.get_signature_status_with_commitment(signature, CommitmentConfig::confirmed())?
+        {
+            break status;
+        }
+        if started.elapsed() >= CONFIRMATION_TIMEOUT {
+            return Ok(TxCost::unlanded(*signature));
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    };
+
+    let outcome = match status {
+        Ok(()) => TxOutcome::Succeeded,
+        Err(e) => TxOutcome::Failed(e.to_string()),
+    };
+
+    fetch_transaction_cost(rpc, signature, num_signatures, outcome)
+}
+
+/// Fetch a landed transaction with its status meta
+pub fn fetch_transaction(
+    rpc: &RpcClient,
+    signature: &Signature,
+) -> Result<EncodedConfirmedTransactionWithStatusMeta> {
+    rpc.get_transaction_with_config(
+        signature,
+        RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Json),
+            commitment: Some(CommitmentConfig::confirmed()),
+            max_supported_transaction_version: Some(0),
+        },
+    )
+    .map_err(|e| anyhow!("Failed to fetch transaction {}: {}", signature, e))
+}
+
+/// Read the fee charged to a transaction that has already landed
+pub fn fetch_transaction_cost(
+    rpc: &RpcClient,
+    signature: &Signature,
+    num_signatures: usize,
+    outcome: TxOutcome,
+) -> Result<TxCost> {
+    let transaction = fetch_transaction(rpc, signature)?;
+    transaction_cost(&transaction, signature, num_signatures, outcome)
+}
+
+/// Static account keys of a fetched transaction, fee payer first
+pub fn account_keys(transaction: &EncodedConfirmedTransactionWithStatusMeta) -> Vec<Pubkey> {
+    match &transaction.transaction.transaction {
+        EncodedTransaction::Json(ui) => match &ui.message {
+            UiMessage::Raw(message) => message
+                .account_keys
+                .iter()
+                .filter_map(|key| Pubkey::from_str(key).ok())
+                .collect(),
+            UiMessage::Parsed(message) => message
+                .account_keys
+                .iter()
+                .filter_map(|key| Pubkey::from_str(&key.pubkey).ok())
+                .collect(),
+        },
+        encoded => encoded
+            .decode()
+            .map(|tx| tx.message.static_account_keys().to_vec())
+            .unwrap_or_default(),
+    }
+}
+
+/// Lamports a fetched transaction paid into Jito tip accounts. Tip accounts are
+/// only looked for among the static keys, which is where `jito::tip_instruction` puts them.
+pub fn tip_paid(transaction: &EncodedConfirmedTransactionWithStatusMeta) -> u64 {
+    let Some(meta) = transaction.transaction.meta.as_ref() else {
+        return 0;
+    };
+    account_keys(transaction)
+        .iter()
+        .zip(meta.pre_balances.iter().zip(&meta.post_balances))
+        .filter(|(key, _)| jito::is_tip_account(key))
+        .map(|(_, (pre, post))| post.saturating_sub(*pre))
+        .sum()
+}
+
+/// Fee charged to an already fetched transaction
+pub fn transaction_cost(
+    transaction: &EncodedConfirmedTransactionWithStatusMeta,
+    signature: &Signature,
+    num_signatures: usize,
+    outcome: TxOutcome,
+) -> Result<TxCost> {
+    let fee = transaction
+        .transaction
+        .meta
+        .as_ref()
+        .map(|meta| meta.fee)
+        .ok_or_else(|| anyhow!("Transaction {} has no status meta", signature))?;
+    let (base_fee, priority_fee) = split_fee(fee, num_signatures);
+
+    Ok(TxCost {
+        signature: *signature,
+        outcome,
+        base_fee,
+        priority_fee,
+        tip_lamports: tip_paid(transaction),
+    })
+}
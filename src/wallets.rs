@@ -0,0 +1,257 @@
+use anyhow::{anyhow, Result};
+use rand::{seq::SliceRandom, Rng};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signer::Signer};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::config::{self, WalletSelection};
+use crate::signer::{self, Wallet};
+use crate::{address_book, blacklist, cal, error, fees, pump_buy, sizing, token_accounts};
+
+/// Last known SOL balance of one wallet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalletBalance {
+    pub pubkey: Pubkey,
+    pub lamports: u64,
+}
+
+/// Several trading wallets with per-trade selection and balance tracking
+pub struct WalletPool {
+    wallets: Vec<Wallet>,
+    selection: WalletSelection,
+    next: AtomicUsize,
+    balances: Mutex<HashMap<Pubkey, u64>>,
+}
+
+impl WalletPool {
+    pub fn new(wallets: Vec<Wallet>, selection: WalletSelection) -> Result<Self> {
+        if wallets.is_empty() {
+            return Err(anyhow!("Wallet pool needs at least one wallet"));
+        }
+        Ok(Self {
+            wallets,
+            selection,
+            next: AtomicUsize::new(0),
+            balances: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Pool of `wallet.private_key` plus `wallet.extra_private_keys`
+    pub fn from_config() -> Result<Self> {
+        let wallet = &config::get().wallet;
+        let wallets = std::iter::once(&wallet.private_key)
+            .chain(&wallet.extra_private_keys)
+            .map(|key| signer::load_wallet(key))
+            .collect::<Result<Vec<_>>>()?;
+        Self::new(wallets, wallet.selection)
+    }
+
+    pub fn len(&self) -> usize {
+        self.wallets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.wallets.is_empty()
+    }
+
+    /// Wallet at `index` in pool order: `wallet.private_key`, then `extra_private_keys`
+    pub fn get(&self, index: usize) -> Option<&Wallet> {
+        self.wallets.get(index)
+    }
+
+    pub fn pubkeys(&self) -> Vec<Pubkey> {
+        self.wallets.iter().map(|w| w.pubkey()).collect()
+    }
+
+    /// Wallet for the next trade
+    pub fn next_wallet(&self) -> &Wallet {
+        let i = match self.selection {
+            WalletSelection::RoundRobin => self.next.fetch_add(1, Ordering::Relaxed),
+            WalletSelection::Random => rand::thread_rng().gen_range(0..self.wallets.len()),
+        };
+        &self.wallets[i % self.wallets.len()]
+    }
+
+    /// Fetch every wallet's SOL balance in one request
+    pub fn refresh_balances(&self, rpc: &RpcClient) -> error::Result<Vec<WalletBalance>> {
+        let pubkeys = self.pubkeys();
+        let accounts = rpc.get_multiple_accounts(&pubkeys)?;
+        let balances: Vec<WalletBalance> = pubkeys
+            .iter()
+            .zip(accounts)
+            .map(|(pubkey, account)| WalletBalance {
+                pubkey: *pubkey,
+                lamports: account.map_or(0, |a| a.lamports),
+            })
+            .collect();
+        if let Ok(mut known) = self.balances.lock() {
+            known.extend(balances.iter().map(|b| (b.pubkey, b.lamports)));
+        }
+        Ok(balances)
+    }
+
+    /// Balances as of the last refresh (0 for wallets never refreshed)
+    pub fn balances(&self) -> Vec<WalletBalance> {
+        let known = self.balances.lock().map(|b| b.clone()).unwrap_or_default();
+        self.wallets
+            .iter()
+            .map(|w| WalletBalance {
+                pubkey: w.pubkey(),
+                lamports: known.get(&w.pubkey()).copied().unwrap_or(0),
+            })
+            .collect()
+    }
+
+    pub fn total_lamports(&self) -> u64 {
+        self.balances().iter().map(|b| b.lamports).sum()
+    }
+
+    /// Tokens of `mint` held across every wallet
+    pub fn total_tokens(&self, rpc: &RpcClient, mint: &Pubkey) -> error::Result<u64> {
+        let token_program = cal::fetch_account(rpc, mint)?.owner;
+        let mut total = 0;
+        for pubkey in self.pubkeys() {
+            total += token_accounts::fetch_token_holdings(rpc, &pubkey, mint, &token_program)?
+                .total_owned();
+        }
+        Ok(total)
+    }
+
+    /// Split `sol_amount` into at most `parts` buys, one per selected wallet. Wallets that
+    /// can't afford their share with `slippage_bps` on top plus the fee reserve are skipped.
+    pub fn split(
+        &self,
+        sol_amount: u64,
+        parts: usize,
+        slippage_bps: u64,
+    ) -> Result<Vec<(&Wallet, u64)>> {
+        let parts = parts.clamp(1, self.wallets.len());
+        let balances: HashMap<Pubkey, u64> = self
+            .balances()
+            .into_iter()
+            .map(|b| (b.pubkey, b.lamports))
+            .collect();
+
+        // Every wallet once, in selection order
+        let len = self.wallets.len();
+        let order: Vec<usize> = match self.selection {
+            WalletSelection::RoundRobin => {
+                let start = self.next.fetch_add(parts, Ordering::Relaxed);
+                (0..len).map(|i| (start + i) % len).collect()
+            }
+            WalletSelection::Random => {
+                let mut order: Vec<usize> = (0..len).collect();
+                order.shuffle(&mut rand::thread_rng());
+                order
+            }
+        };
+
+        let share = sol_amount / parts as u64;
+        // Each wallet may pay its share's max cost and keeps its own reserve for rent and fees
+        let needed = pump_buy::with_slippage(share + sol_amount % parts as u64, slippage_bps)
+            + config::get().balance.reserve_lamports;
+        let mut chosen: Vec<(&Wallet, u64)> = order
+            .into_iter()
+            .map(|i| &self.wallets[i])
+            .filter(|w| balances.get(&w.pubkey()).copied().unwrap_or(0) >= needed)
+            .take(parts)
+            .map(|w| (w, share))
+            .collect();
+        if chosen.len() < parts {
+            return Err(anyhow!(
+                "Only {} of {} wallets can afford a {} SOL share",
+                chosen.len(),
+                parts,
+                share as f64 / LAMPORTS_PER_SOL as f64
+            ));
+        }
+        // The first wallet picks up the rounding remainder
+        chosen[0].1 += sol_amount % parts as u64;
+        Ok(chosen)
+    }
+
+    /// Buy `sol_amount` worth of `mint` split across `parts` wallets, unless the mint is
+    /// blacklisted. The whole amount's price impact is checked against
+    /// `trading.max_price_impact_bps` first, since the shares move the curve together. Returns the outcome of every wallet's buy.
+    pub fn split_buy(
+        &self,
+        rpc: &RpcClient,
+        mint: &Pubkey,
+        sol_amount: u64,
+        parts: usize,
+        slippage_bps: u64,
+    ) -> error::Result<Vec<(Pubkey, error::Result<()>)>> {
+        blacklist::screen(mint)?;
+        self.refresh_balances(rpc)?;
+        let curve = cal::fetch_bonding_curve(rpc, mint)?;
+        let global = fees::current_global(rpc, &curve);
+        if !curve.complete {
+            let impact_bps = cal::price_impact_bps(&global, &curve, sol_amount);
+            sizing::check_price_impact(mint, true, impact_bps, false)?;
+        }
+
+        Ok(self
+            .split(sol_amount, parts, slippage_bps)?
+            .into_iter()
+            .map(|(wallet, share)| {
+                let tokens = cal::get_tokens_for_sol(&global, Some(&curve), share);
+                let max_sol_cost = pump_buy::with_slippage(share, slippage_bps);
+                let result = pump_buy::buy(rpc, wallet, tokens, *mint, max_sol_cost).map(|_| ());
+                (wallet.pubkey(), result)
+            })
+            .collect())
+    }
+
+    /// Print every wallet's balance and the pool total
+    pub fn print_summary(&self) {
+        println!("=== Wallet Pool ({} wallets) ===", self.wallets.len());
+        for balance in self.balances() {
+            println!(
+                "  {:<50} {:>12.4} SOL",
+                address_book::label(&balance.pubkey),
+                balance.lamports as f64 / LAMPORTS_PER_SOL as f64
+            );
+        }
+        println!(
+            "  {:<50} {:>12.4} SOL",
+            "Total",
+            self.total_lamports() as f64 / LAMPORTS_PER_SOL as f64
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::Keypair;
+
+    #[test]
+    fn test_split_skips_wallets_that_cannot_afford_a_share() {
+        let pool = WalletPool::new(
+            (0..3).map(|_| Wallet::from(Keypair::new())).collect(),
+            WalletSelection::RoundRobin,
+        )
+        .unwrap();
+        let pubkeys = pool.pubkeys();
+        pool.balances.lock().unwrap().extend([
+            (pubkeys[0], LAMPORTS_PER_SOL),
+            (pubkeys[1], 1_000),
+            (pubkeys[2], LAMPORTS_PER_SOL),
+        ]);
+
+        let split = pool.split(300_000_001, 2, 0).unwrap();
+        let buyers: Vec<Pubkey> = split.iter().map(|(w, _)| w.pubkey()).collect();
+        assert_eq!(buyers, vec![pubkeys[0], pubkeys[2]]);
+        assert_eq!(split[0].1, 150_000_001);
+        assert_eq!(split[1].1, 150_000_000);
+        assert_eq!(pool.total_lamports(), 2 * LAMPORTS_PER_SOL + 1_000);
+
+        assert!(pool.split(300_000_000, 3, 0).is_err());
+
+        // Each share must also cover its slippage headroom
+        assert!(pool.split(1_600_000_000, 2, 0).is_ok());
+        assert!(pool.split(1_600_000_000, 2, 2_500).is_err());
+    }
+}
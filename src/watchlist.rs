@@ -0,0 +1,490 @@
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, instrument, warn};
+
+use crate::accounts::BondingCurve;
+use crate::holders::{self, HolderDistribution};
+use crate::portfolio::now_unix;
+use crate::{address_book, cal, config, rpc_pool, telegram};
+
+// Constants
+/// How often `run` looks for newly watched mints
+const RESCAN_INTERVAL: Duration = Duration::from_secs(5);
+/// How often a mint with holder alerts has its holders scanned again
+const HOLDER_SCAN_INTERVAL: Duration = Duration::from_secs(60);
+
+/// What alerts are checked against: a curve update and the latest holder scan, if any
+#[derive(Debug, Clone, Copy)]
+pub struct Snapshot<'a> {
+    pub curve: &'a BondingCurve,
+    pub holders: Option<&'a HolderDistribution>,
+}
+
+impl<'a> Snapshot<'a> {
+    pub fn new(curve: &'a BondingCurve, holders: Option<&'a HolderDistribution>) -> Self {
+        Self { curve, holders }
+    }
+}
+
+/// What an alert compares against its threshold
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertKind {
+    /// Spot price in lamports per token at or above the threshold
+    PriceAbove,
+    /// Spot price in lamports per token at or below the threshold
+    PriceBelow,
+    /// Market cap in SOL at or above the threshold
+    MarketCapAbove,
+    /// Market cap in SOL at or below the threshold
+    MarketCapBelow,
+    /// Graduation progress in percent at or above the threshold
+    Graduation,
+    /// Percent of supply held by the 10 largest holders at or above the threshold
+    Top10Above,
+    /// Percent of supply the creator holds at or above the threshold
+    DevHoldingsAbove,
+    /// Percent of supply bundler clusters hold at or above the threshold
+    BundlersAbove,
+}
+
+impl AlertKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertKind::PriceAbove => "price-above",
+            AlertKind::PriceBelow => "price-below",
+            AlertKind::MarketCapAbove => "mcap-above",
+            AlertKind::MarketCapBelow => "mcap-below",
+            AlertKind::Graduation => "progress",
+            AlertKind::Top10Above => "top10-above",
+            AlertKind::DevHoldingsAbove => "dev-above",
+            AlertKind::BundlersAbove => "bundlers-above",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "price-above" => Ok(AlertKind::PriceAbove),
+            "price-below" => Ok(AlertKind::PriceBelow),
+            "mcap-above" => Ok(AlertKind::MarketCapAbove),
+            "mcap-below" => Ok(AlertKind::MarketCapBelow),
+            "progress" => Ok(AlertKind::Graduation),
+            "top10-above" => Ok(AlertKind::Top10Above),
+            "dev-above" => Ok(AlertKind::DevHoldingsAbove),
+            "bundlers-above" => Ok(AlertKind::BundlersAbove),
+            other => Err(anyhow!(
+                "Unknown alert {} (price-above, price-below, mcap-above, mcap-below, progress, \
+                 top10-above, dev-above or bundlers-above)",
+                other
+            )),
+        }
+    }
+
+    /// Whether the alert needs holder scans
+    pub fn is_holder_metric(&self) -> bool {
+        matches!(
+            self,
+            AlertKind::Top10Above | AlertKind::DevHoldingsAbove | AlertKind::BundlersAbove
+        )
+    }
+
+    /// The figure this kind of alert watches, None for holder figures not scanned yet
+    pub fn value(&self, snapshot: &Snapshot) -> Option<f64> {
+        let curve = snapshot.curve;
+        match self {
+            AlertKind::PriceAbove | AlertKind::PriceBelow => {
+                Some(cal::spot_price_lamports_per_token(curve))
+            }
+            AlertKind::MarketCapAbove | AlertKind::MarketCapBelow => {
+                Some(cal::market_cap_sol(curve))
+            }
+            AlertKind::Graduation => Some(cal::graduation_progress(curve)),
+            AlertKind::Top10Above => snapshot.holders.map(HolderDistribution::top10_pct),
+            AlertKind::DevHoldingsAbove => snapshot.holders.and_then(HolderDistribution::dev_pct),
+            AlertKind::BundlersAbove => snapshot.holders.map(HolderDistribution::bundler_pct),
+        }
+    }
+
+    fn unit(&self) -> &'static str {
+        match self {
+            AlertKind::PriceAbove | AlertKind::PriceBelow => "lamports/token",
+            AlertKind::MarketCapAbove | AlertKind::MarketCapBelow => "SOL",
+            AlertKind::Graduation
+            | AlertKind::Top10Above
+            | AlertKind::DevHoldingsAbove
+            | AlertKind::BundlersAbove => "%",
+        }
+    }
+}
+
+/// A threshold on one watched mint. It fires when its condition starts holding and
+/// re-arms once the condition stops holding, so a price hovering at the threshold
+/// alerts again only after leaving it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub id: i64,
+    pub mint: Pubkey,
+    pub kind: AlertKind,
+    pub threshold: f64,
+    /// Whether the condition held at the last update
+    pub active: bool,
+    pub created_at: i64,
+    pub last_fired_at: Option<i64>,
+}
+
+impl Alert {
+    /// Whether the condition holds on `snapshot`, None if its figure is unknown
+    pub fn holds(&self, snapshot: &Snapshot) -> Option<bool> {
+        let value = self.kind.value(snapshot)?;
+        Some(match self.kind {
+            AlertKind::PriceBelow | AlertKind::MarketCapBelow => value <= self.threshold,
+            _ => value >= self.threshold,
+        })
+    }
+
+    /// Notification text for the alert firing on `snapshot`
+    pub fn message(&self, snapshot: &Snapshot) -> String {
+        format!(
+            "Watchlist #{}: {} {} {} {} (now {:.9})",
+            self.id,
+            address_book::label(&self.mint),
+            self.kind.as_str(),
+            self.threshold,
+            self.kind.unit(),
+            self.kind.value(snapshot).unwrap_or_default()
+        )
+    }
+}
+
+/// Watched mints and their alerts, persisted in SQLite so they survive restarts
+pub struct Watchlist {
+    conn: Connection,
+}
+
+impl Watchlist {
+    /// Open (or create) the watchlist table in the database at `path`
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)
+            .map_err(|e| anyhow!("Failed to open watchlist database {}: {}", path, e))?;
+        Self::init(conn)
+    }
+
+    /// Open the watchlist in the portfolio database
+    pub fn open_default() -> Result<Self> {
+        Self::open(&config::get().storage.db_path)
+    }
+
+    /// Open a throwaway in-memory watchlist
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS watchlist (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                mint TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                threshold REAL NOT NULL,
+                active INTEGER NOT NULL DEFAULT 0,
+                created_at INTEGER NOT NULL,
+                last_fired_at INTEGER
+            );
+            CREATE INDEX IF NOT EXISTS idx_watchlist_mint ON watchlist (mint);",
+        )?;
+        Ok(Self { conn })
+    }
+
+    /// Watch `mint` for `kind` crossing `threshold`
+    pub fn add(&self, mint: Pubkey, kind: AlertKind, threshold: f64) -> Result<Alert> {
+        if !(threshold >= 0.0 && threshold.is_finite()) {
+            return Err(anyhow!("Invalid alert threshold {}", threshold));
+        }
+        self.conn.execute(
+            "INSERT INTO watchlist (mint, kind, threshold, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![mint.to_string(), kind.as_str(), threshold, now_unix()],
+        )?;
+        self.get(self.conn.last_insert_rowid())?
+            .ok_or_else(|| anyhow!("Watchlist alert vanished after insert"))
+    }
+
+    pub fn get(&self, id: i64) -> Result<Option<Alert>> {
+        Ok(self
+            .query_alerts("WHERE id = ?1", params![id])?
+            .into_iter()
+            .next())
+    }
+
+    /// Alerts, optionally only those on `mint`, oldest first
+    pub fn alerts(&self, mint: Option<&Pubkey>) -> Result<Vec<Alert>> {
+        self.query_alerts(
+            "WHERE ?1 IS NULL OR mint = ?1",
+            params![mint.map(|m| m.to_string())],
+        )
+    }
+
+    /// Mints with at least one alert
+    pub fn mints(&self) -> Result<HashSet<Pubkey>> {
+        Ok(self.alerts(None)?.iter().map(|a| a.mint).collect())
+    }
+
+    /// Stop watching for an alert. Returns false if there was none with that id.
+    pub fn remove(&self, id: i64) -> Result<bool> {
+        let changed = self
+            .conn
+            .execute("DELETE FROM watchlist WHERE id = ?1", params![id])?;
+        Ok(changed == 1)
+    }
+
+    /// Update `mint`'s alerts for `snapshot`, returning those that just started holding.
+    /// Alerts on figures the snapshot lacks are left as they were.
+    pub fn check(&self, mint: &Pubkey, snapshot: &Snapshot) -> Result<Vec<Alert>> {
+        let mut fired = Vec::new();
+        for mut alert in self.alerts(Some(mint))? {
+            let Some(holds) = alert.holds(snapshot) else {
+                continue;
+            };
+            if holds == alert.active {
+                continue;
+            }
+            alert.active = holds;
+            if holds {
+                alert.last_fired_at = Some(now_unix());
+            }
+            self.conn.execute(
+                "UPDATE watchlist SET active = ?2, last_fired_at = ?3 WHERE id = ?1",
+                params![alert.id, alert.active, alert.last_fired_at],
+            )?;
+            if holds {
+                fired.push(alert);
+            }
+        }
+        Ok(fired)
+    }
+
+    fn query_alerts(&self, filter: &str, params: impl rusqlite::Params) -> Result<Vec<Alert>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, mint, kind, threshold, active, created_at, last_fired_at
+             FROM watchlist {} ORDER BY id",
+            filter
+        ))?;
+        let rows = stmt
+            .query_map(params, |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, f64>(3)?,
+                    row.get::<_, bool>(4)?,
+                    row.get::<_, i64>(5)?,
+                    row.get::<_, Option<i64>>(6)?,
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(
+                |(id, mint, kind, threshold, active, created_at, last_fired_at)| {
+                    Ok(Alert {
+                        id,
+                        mint: Pubkey::from_str(&mint)?,
+                        kind: AlertKind::parse(&kind)?,
+                        threshold,
+                        active,
+                        created_at,
+                        last_fired_at,
+                    })
+                },
+            )
+            .collect()
+    }
+}
+
+/// Check `mint`'s alerts against `snapshot` and push the ones that fire
+fn check_alerts(watchlist: &Watchlist, mint: &Pubkey, snapshot: &Snapshot) -> Result<()> {
+    for alert in watchlist.check(mint, snapshot)? {
+        info!(
+            alert_id = alert.id,
+            kind = alert.kind.as_str(),
+            threshold = alert.threshold,
+            value = alert.kind.value(snapshot),
+            "Watchlist alert fired"
+        );
+        telegram::notify(alert.message(snapshot));
+    }
+    Ok(())
+}
+
+/// `mint`'s holders, scanned again once the last scan is `HOLDER_SCAN_INTERVAL` old
+/// while it has holder alerts
+struct HolderScans {
+    latest: Option<HolderDistribution>,
+    scanned_at: Option<Instant>,
+}
+
+impl HolderScans {
+    fn refresh(&mut self, watchlist: &Watchlist, rpc: &RpcClient, mint: &Pubkey) -> Result<()> {
+        let due = self
+            .scanned_at
+            .is_none_or(|at| at.elapsed() >= HOLDER_SCAN_INTERVAL);
+        if !due
+            || !watchlist
+                .alerts(Some(mint))?
+                .iter()
+                .any(|a| a.kind.is_holder_metric())
+        {
+            return Ok(());
+        }
+        self.scanned_at = Some(Instant::now());
+        match holders::fetch(rpc, mint) {
+            Ok(distribution) => self.latest = Some(distribution),
+            Err(e) => warn!(error = %e, "Failed to scan holders"),
+        }
+        Ok(())
+    }
+}
+
+/// Check `mint`'s alerts on every curve update until none are left or the socket closes
+#[instrument(name = "watchlist", skip_all, fields(%mint))]
+fn watch_mint(mint: Pubkey) -> Result<()> {
+    let watchlist = Watchlist::open_default()?;
+    let rpc = rpc_pool::get_client();
+    let stream = cal::subscribe_bonding_curve(&mint)?;
+    let mut scans = HolderScans {
+        latest: None,
+        scanned_at: None,
+    };
+    // Catch alerts that already hold before the first update
+    let curve = cal::fetch_bonding_curve(&rpc, &mint)?;
+    scans.refresh(&watchlist, &rpc, &mint)?;
+    check_alerts(
+        &watchlist,
+        &mint,
+        &Snapshot::new(&curve, scans.latest.as_ref()),
+    )?;
+
+    for curve in stream {
+        if watchlist.alerts(Some(&mint))?.is_empty() {
+            break;
+        }
+        scans.refresh(&watchlist, &rpc, &mint)?;
+        check_alerts(
+            &watchlist,
+            &mint,
+            &Snapshot::new(&curve, scans.latest.as_ref()),
+        )?;
+    }
+    Ok(())
+}
+
+/// Watch every mint on the watchlist, picking up new alerts as they are added. Only
+/// notifies, never trades. Never returns.
+pub fn run() -> Result<()> {
+    let watchlist = Watchlist::open_default()?;
+    let watching: Arc<Mutex<HashSet<Pubkey>>> = Arc::new(Mutex::new(HashSet::new()));
+    loop {
+        for mint in watchlist.mints()? {
+            let Ok(mut active) = watching.lock() else {
+                continue;
+            };
+            if !active.insert(mint) {
+                continue;
+            }
+            let watching = watching.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = watch_mint(mint) {
+                    warn!(%mint, error = %e, "Watchlist watcher stopped");
+                }
+                if let Ok(mut active) = watching.lock() {
+                    active.remove(&mint);
+                }
+            });
+        }
+        std::thread::sleep(RESCAN_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alerts_fire_once_per_crossing() {
+        let watchlist = Watchlist::open_in_memory().unwrap();
+        let mint = Pubkey::new_unique();
+        let start = cal::new_bonding_curve(&cal::Global::default());
+        let price = cal::spot_price_lamports_per_token(&start);
+        let above = watchlist
+            .add(mint, AlertKind::PriceAbove, price * 2.0)
+            .unwrap();
+        let graduated = watchlist.add(mint, AlertKind::Graduation, 50.0).unwrap();
+        let mcap = watchlist
+            .add(mint, AlertKind::MarketCapBelow, cal::market_cap_sol(&start))
+            .unwrap();
+        assert!(watchlist
+            .add(mint, AlertKind::Graduation, f64::NAN)
+            .is_err());
+        assert_eq!(watchlist.mints().unwrap(), HashSet::from([mint]));
+
+        // The starting curve is already at its market cap floor
+        let at = |curve| Snapshot::new(curve, None);
+        let fired = watchlist.check(&mint, &at(&start)).unwrap();
+        assert_eq!(fired.iter().map(|a| a.id).collect::<Vec<_>>(), [mcap.id]);
+        assert!(watchlist.check(&mint, &at(&start)).unwrap().is_empty());
+
+        // Three quarters of the tokens sold pumps the price well past double
+        let mut pumped = start.clone();
+        let sold = start.real_token_reserves * 3 / 4;
+        pumped.real_token_reserves -= sold;
+        pumped.virtual_token_reserves -= sold;
+        pumped.virtual_sol_reserves = (start.virtual_sol_reserves as u128
+            * start.virtual_token_reserves as u128
+            / pumped.virtual_token_reserves as u128) as u64;
+        let fired = watchlist.check(&mint, &at(&pumped)).unwrap();
+        assert_eq!(
+            fired.iter().map(|a| a.id).collect::<Vec<_>>(),
+            [above.id, graduated.id]
+        );
+        assert!(fired[0].message(&at(&pumped)).contains("price-above"));
+        assert!(watchlist.check(&mint, &at(&pumped)).unwrap().is_empty());
+
+        // Back at the start the price alert re-arms and the market cap one fires again
+        let fired = watchlist.check(&mint, &at(&start)).unwrap();
+        assert_eq!(fired.iter().map(|a| a.id).collect::<Vec<_>>(), [mcap.id]);
+        assert!(!watchlist.get(above.id).unwrap().unwrap().active);
+
+        assert!(watchlist.remove(above.id).unwrap());
+        assert!(!watchlist.remove(above.id).unwrap());
+        assert_eq!(watchlist.alerts(Some(&mint)).unwrap().len(), 2);
+
+        // Holder alerts wait for a holder scan
+        let dev = Pubkey::new_unique();
+        let concentrated = watchlist
+            .add(mint, AlertKind::DevHoldingsAbove, 10.0)
+            .unwrap();
+        assert!(watchlist.check(&mint, &at(&start)).unwrap().is_empty());
+        let scan = HolderDistribution::from_balances(
+            mint,
+            1_000,
+            [(dev, 150)],
+            &Pubkey::new_unique(),
+            Some(dev),
+            true,
+        );
+        let fired = watchlist
+            .check(&mint, &Snapshot::new(&start, Some(&scan)))
+            .unwrap();
+        assert_eq!(
+            fired.iter().map(|a| a.id).collect::<Vec<_>>(),
+            [concentrated.id]
+        );
+        // Updates without a scan leave it holding
+        assert!(watchlist.check(&mint, &at(&start)).unwrap().is_empty());
+        assert!(watchlist.get(concentrated.id).unwrap().unwrap().active);
+    }
+}
@@ -0,0 +1,170 @@
+//! Launches, buys and sells through the instruction builders against a local
+//! `solana-test-validator` running the pump.fun program cloned from mainnet, so an account
+//! order or layout the program rejects fails here instead of on mainnet.
+//!
+//! Needs `solana-test-validator` on the PATH and the fixtures in `network.fixture_dir`:
+//!
+//! ```bash
+//! cargo run -- localnet clone
+//! cargo test --test localnet -- --ignored
+//! ```
+
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig, instruction::Instruction, native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey, signature::Keypair, signer::Signer, transaction::Transaction,
+};
+use spl_associated_token_account::get_associated_token_address;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use trading_bot_rust::{cal, config, localnet, pump_buy, pump_create, pump_sell};
+
+// Constants
+/// Ports clear of a validator already running on the defaults
+const RPC_PORT: u16 = 18899;
+const FAUCET_PORT: u16 = 19900;
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+const SLIPPAGE_BPS: u64 = 500;
+
+/// A `solana-test-validator` loaded with the fixtures, killed when dropped
+struct Validator {
+    child: Child,
+    ledger: PathBuf,
+    rpc: RpcClient,
+}
+
+impl Validator {
+    fn start() -> Self {
+        let fixture_dir = Path::new(&config::get().network.fixture_dir);
+        let fixtures = localnet::test_validator_args(fixture_dir)
+            .unwrap_or_else(|e| panic!("{}; fill it with `cargo run -- localnet clone`", e));
+        let ledger = std::env::temp_dir().join(format!("pump-localnet-{}", std::process::id()));
+        let child = Command::new("solana-test-validator")
+            .arg("--ledger")
+            .arg(&ledger)
+            .args(["--rpc-port", &RPC_PORT.to_string()])
+            .args(["--faucet-port", &FAUCET_PORT.to_string()])
+            .arg("--quiet")
+            .args(fixtures)
+            .stdout(Stdio::null())
+            .spawn()
+            .expect("solana-test-validator must be on the PATH");
+        let rpc = RpcClient::new_with_commitment(
+            format!("http://127.0.0.1:{}", RPC_PORT),
+            CommitmentConfig::confirmed(),
+        );
+
+        let validator = Self { child, ledger, rpc };
+        let started = Instant::now();
+        while validator.rpc.get_health().is_err() {
+            assert!(
+                started.elapsed() < STARTUP_TIMEOUT,
+                "solana-test-validator did not start"
+            );
+            sleep(Duration::from_millis(500));
+        }
+        validator
+    }
+
+    /// A new wallet holding `sol` SOL
+    fn funded_wallet(&self, sol: u64) -> Keypair {
+        let wallet = Keypair::new();
+        self.rpc
+            .request_airdrop(&wallet.pubkey(), sol * LAMPORTS_PER_SOL)
+            .expect("airdrop");
+        let started = Instant::now();
+        while self.rpc.get_balance(&wallet.pubkey()).unwrap_or(0) == 0 {
+            assert!(started.elapsed() < STARTUP_TIMEOUT, "airdrop did not land");
+            sleep(Duration::from_millis(500));
+        }
+        wallet
+    }
+
+    fn send(&self, instructions: &[Instruction], signers: &[&Keypair]) {
+        let blockhash = self.rpc.get_latest_blockhash().expect("blockhash");
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&signers[0].pubkey()),
+            signers,
+            blockhash,
+        );
+        if let Err(e) = self.rpc.send_and_confirm_transaction(&transaction) {
+            let logs = self
+                .rpc
+                .simulate_transaction(&transaction)
+                .ok()
+                .and_then(|simulation| simulation.value.logs)
+                .unwrap_or_default();
+            panic!("Transaction failed: {}\n{}", e, logs.join("\n"));
+        }
+    }
+
+    fn token_balance(&self, owner: &Pubkey, mint: &Pubkey) -> u64 {
+        self.rpc
+            .get_token_account_balance(&get_associated_token_address(owner, mint))
+            .map(|balance| balance.amount.parse().expect("token amount"))
+            .unwrap_or(0)
+    }
+}
+
+impl Drop for Validator {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(&self.ledger);
+    }
+}
+
+#[test]
+#[ignore = "needs solana-test-validator and the localnet fixtures"]
+fn test_launch_buy_sell_on_localnet() {
+    let validator = Validator::start();
+    let user = validator.funded_wallet(10);
+    let mint = Keypair::new();
+
+    // Create with a dev buy
+    let args = pump_create::CreateArgs {
+        name: "Localnet Token".to_string(),
+        symbol: "LOCAL".to_string(),
+        uri: "https://example.com/metadata.json".to_string(),
+        creator: user.pubkey(),
+    };
+    let launch = pump_create::launch_instructions(
+        &user.pubkey(),
+        &mint.pubkey(),
+        &args,
+        None,
+        LAMPORTS_PER_SOL / 10,
+        SLIPPAGE_BPS,
+    );
+    validator.send(&launch.instructions, &[&user, &mint]);
+    let dev_tokens = validator.token_balance(&user.pubkey(), &mint.pubkey());
+    assert_eq!(dev_tokens, launch.fill.token_amount);
+
+    // Buy again into the existing token account
+    let quote =
+        cal::quote_buy(&validator.rpc, &mint.pubkey(), LAMPORTS_PER_SOL / 10).expect("buy quote");
+    let buy = pump_buy::buy_instruction(
+        &user.pubkey(),
+        &mint.pubkey(),
+        &user.pubkey(),
+        &spl_token::ID,
+        quote.output_amount,
+        quote.input_amount * (10_000 + SLIPPAGE_BPS) / 10_000,
+    );
+    validator.send(&[buy], &[&user]);
+    let held = validator.token_balance(&user.pubkey(), &mint.pubkey());
+    assert_eq!(held, dev_tokens + quote.output_amount);
+
+    // Sell everything back into the curve
+    let curve_before = cal::fetch_bonding_curve(&validator.rpc, &mint.pubkey()).unwrap();
+    let sell =
+        pump_sell::build_sell_instruction(&validator.rpc, &user.pubkey(), &mint.pubkey(), held, 0)
+            .expect("sell instruction");
+    validator.send(&[sell], &[&user]);
+    assert_eq!(validator.token_balance(&user.pubkey(), &mint.pubkey()), 0);
+    let curve_after = cal::fetch_bonding_curve(&validator.rpc, &mint.pubkey()).unwrap();
+    assert!(curve_after.virtual_sol_reserves < curve_before.virtual_sol_reserves);
+}